@@ -0,0 +1,217 @@
+//! Accounts shared by several strategies. Each strategy keeps its own `PerformanceTracker`,
+//! which attributes PnL, fees and margin to it; an `Account` pools the members' ledgers into
+//! one balance and one margin requirement, and it is the account's available funds that
+//! decide whether a member may open more.
+//!
+//! With `MarginNetting::LargerSide`, margin is charged the way Chinese futures exchanges do
+//! for opposite positions in one product: only the larger of the long and short sides,
+//! summed over every member, is frozen. A strategy long rb against another short rb then
+//! ties up one side's margin, not both.
+use crate::calendar;
+use crate::types::SymbolType;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// How opposite positions in one product are margined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MarginNetting {
+    /// long and short margin are both charged
+    #[default]
+    Gross,
+    /// only the larger side of each product is charged (单边大保证金)
+    LargerSide,
+}
+
+/// One member's contribution to its account, from its tracker.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Ledger {
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub unrealized_pnl: f64,
+    pub long_margin: f64,
+    pub short_margin: f64,
+}
+
+/// A member's share of the account, as `Account::summary` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Attribution {
+    pub strategy: String,
+    pub symbol: String,
+    #[serde(flatten)]
+    pub ledger: Ledger,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccountSummary {
+    pub account: String,
+    pub init_cash: f64,
+    /// initial cash plus realized PnL, less fees
+    pub balance: f64,
+    pub equity: f64,
+    /// margin after netting
+    pub margin: f64,
+    /// margin as if nothing were netted
+    pub gross_margin: f64,
+    pub available: f64,
+    pub strategies: Vec<Attribution>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Account {
+    name: String,
+    init_cash: f64,
+    netting: MarginNetting,
+    /// (strategy, symbol) → its latest ledger
+    members: BTreeMap<(String, String), Ledger>,
+}
+
+impl Account {
+    pub fn new(name: &str, init_cash: f64, netting: MarginNetting) -> Self {
+        Self {
+            name: name.into(),
+            init_cash,
+            netting,
+            members: BTreeMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Replace the ledger of `strategy` trading `symbol`.
+    pub fn update(&mut self, strategy: &str, symbol: SymbolType, ledger: Ledger) {
+        self.members.insert((strategy.into(), symbol.as_str().to_string()), ledger);
+    }
+
+    pub fn remove(&mut self, strategy: &str, symbol: SymbolType) {
+        self.members.remove(&(strategy.into(), symbol.as_str().to_string()));
+    }
+
+    pub fn balance(&self) -> f64 {
+        self.init_cash + self.members.values().map(|l| l.realized_pnl - l.fees).sum::<f64>()
+    }
+
+    pub fn equity(&self) -> f64 {
+        self.balance() + self.members.values().map(|l| l.unrealized_pnl).sum::<f64>()
+    }
+
+    pub fn gross_margin(&self) -> f64 {
+        self.members.values().map(|l| l.long_margin + l.short_margin).sum()
+    }
+
+    /// Frozen margin after netting.
+    pub fn margin(&self) -> f64 {
+        Self::netted_margin(self.netting, self.members.iter().map(|((_, symbol), ledger)| (symbol, ledger)))
+    }
+
+    /// Funds left to open with: equity less the netted margin.
+    pub fn available(&self) -> f64 {
+        self.equity() - self.margin()
+    }
+
+    /// What `available` would be with `ledger` in place of the current ledger of `strategy`
+    /// trading `symbol`, e.g. after a fill it is about to send.
+    pub fn available_with(&self, strategy: &str, symbol: SymbolType, ledger: Ledger) -> f64 {
+        let mut probe = self.clone();
+        probe.update(strategy, symbol, ledger);
+        probe.available()
+    }
+
+    fn netted_margin<'a>(netting: MarginNetting, ledgers: impl Iterator<Item = (&'a String, &'a Ledger)>) -> f64 {
+        match netting {
+            MarginNetting::Gross => ledgers.map(|(_, l)| l.long_margin + l.short_margin).sum(),
+            MarginNetting::LargerSide => {
+                let mut by_product: HashMap<String, (f64, f64)> = HashMap::new();
+                for (symbol, l) in ledgers {
+                    let product = calendar::product_of(&SymbolType::from(symbol.as_str())).to_string();
+                    let sides = by_product.entry(product).or_default();
+                    sides.0 += l.long_margin;
+                    sides.1 += l.short_margin;
+                }
+                by_product.values().map(|(long, short)| long.max(*short)).sum()
+            }
+        }
+    }
+
+    pub fn summary(&self) -> AccountSummary {
+        AccountSummary {
+            account: self.name.clone(),
+            init_cash: self.init_cash,
+            balance: self.balance(),
+            equity: self.equity(),
+            margin: self.margin(),
+            gross_margin: self.gross_margin(),
+            available: self.available(),
+            strategies: self
+                .members
+                .iter()
+                .map(|((strategy, symbol), ledger)| Attribution {
+                    strategy: strategy.clone(),
+                    symbol: symbol.clone(),
+                    ledger: *ledger,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nets_opposite_sides_of_a_product_across_members() {
+        let long = Ledger {
+            realized_pnl: 300.0,
+            fees: 10.0,
+            unrealized_pnl: 50.0,
+            long_margin: 4_000.0,
+            ..Default::default()
+        };
+        let short = Ledger {
+            fees: 5.0,
+            unrealized_pnl: -20.0,
+            short_margin: 3_000.0,
+            ..Default::default()
+        };
+        let mut account = Account::new("main", 100_000.0, MarginNetting::LargerSide);
+        account.update("trend", SymbolType::from("rb2505"), long);
+        account.update("revert", SymbolType::from("rb2510"), short);
+        account.update(
+            "carry",
+            SymbolType::from("MA505"),
+            Ledger {
+                long_margin: 1_000.0,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(account.balance(), 100_285.0);
+        assert_eq!(account.equity(), 100_315.0);
+        assert_eq!(account.gross_margin(), 8_000.0);
+        // rb: the larger long side only; MA unaffected
+        assert_eq!(account.margin(), 5_000.0);
+        assert_eq!(account.available(), 95_315.0);
+
+        // the short side growing past the long one makes it the side charged
+        let bigger = Ledger {
+            short_margin: 6_000.0,
+            ..short
+        };
+        assert_eq!(account.available_with("revert", SymbolType::from("rb2510"), bigger), 93_315.0);
+        assert_eq!(account.available(), 95_315.0);
+
+        let gross = Account {
+            netting: MarginNetting::Gross,
+            ..account.clone()
+        };
+        assert_eq!(gross.margin(), 8_000.0);
+        let summary = account.summary();
+        assert_eq!(summary.strategies.len(), 3);
+        assert_eq!(
+            (summary.strategies[0].strategy.as_str(), summary.strategies[0].symbol.as_str()),
+            ("carry", "MA505")
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! CPU core pinning for the receive loop and the worker threads. A pinned thread is never
+//! migrated by the scheduler, so its caches stay warm and it does not queue behind unrelated
+//! work; pair it with `isolcpus`/`nohz_full` on the chosen cores for the full effect.
+use std::io;
+
+/// Which core each engine thread runs on; `None` leaves a thread to the scheduler.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuAffinity {
+    /// the thread calling `CtaEngine::start`
+    pub receive_loop: Option<usize>,
+    /// indexed by worker id; workers past the end are not pinned
+    pub workers: Vec<usize>,
+}
+
+impl CpuAffinity {
+    pub fn worker(&self, worker_id: usize) -> Option<usize> {
+        self.workers.get(worker_id).copied()
+    }
+
+    /// Every core this configuration pins something to, receive loop first.
+    pub fn cores(&self) -> impl Iterator<Item = usize> + '_ {
+        self.receive_loop.into_iter().chain(self.workers.iter().copied())
+    }
+}
+
+/// Pin the calling thread to `core`.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(core: usize) -> io::Result<()> {
+    // SAFETY: `set` is a plain bitmask owned by this frame; pid 0 means the calling thread.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Cores the calling thread may run on, e.g. as restricted by a cgroup or `taskset`.
+#[cfg(target_os = "linux")]
+pub fn allowed_cores() -> io::Result<Vec<usize>> {
+    // SAFETY: as above; the kernel fills `set` in.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize).filter(|&core| libc::CPU_ISSET(core, &set)).collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_core: usize) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "CPU pinning is Linux-only"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn allowed_cores() -> io::Result<Vec<usize>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "CPU pinning is Linux-only"))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_a_thread_to_one_core() {
+        let core = *allowed_cores().unwrap().last().unwrap();
+        std::thread::spawn(move || {
+            pin_current_thread(core).unwrap();
+            assert_eq!(allowed_cores().unwrap(), vec![core]);
+        })
+        .join()
+        .unwrap();
+    }
+}
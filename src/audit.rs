@@ -0,0 +1,231 @@
+use crate::types::{BookSnapshot, DirectionType, NameType, OffsetFlagType, Order, OrderType, SymbolType};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use tracing::error;
+
+const HEADER: &str =
+    "timestamp,strategy,symbol,direction,offset,price,lots,book_stamp,last,bid,ask,bid_size,ask_size,spread,order_type,client_id,order_id";
+
+/// An order as submitted, with the book the strategy was looking at.
+#[derive(Debug, Clone, Copy)]
+pub struct OrderRecord {
+    pub order: Order,
+    pub book: BookSnapshot,
+}
+
+impl OrderRecord {
+    /// Slippage of the order price against the arrival mid, in price units; positive means
+    /// the order paid up (bought above / sold below mid).
+    pub fn slippage_vs_mid(&self) -> f64 {
+        match self.order.direction {
+            DirectionType::BUY => self.order.price - self.book.mid(),
+            DirectionType::SELL => self.book.mid() - self.order.price,
+        }
+    }
+
+    fn csv_line(&self) -> String {
+        let (o, b) = (&self.order, &self.book);
+        format!(
+            "{},{},{},{:?},{:?},{},{},{},{},{},{},{},{},{},{:?},{},{}",
+            o.timestamp,
+            o.stg_name.as_str(),
+            o.symbol.as_str(),
+            o.direction,
+            o.offset,
+            o.price,
+            o.lots,
+            b.stamp,
+            b.last,
+            b.bid,
+            b.ask,
+            b.bid_size,
+            b.ask_size,
+            b.spread(),
+            o.order_type,
+            o.client_id,
+            o.order_id
+        )
+    }
+
+    fn from_csv(line: &str) -> Option<Self> {
+        let f: Vec<&str> = line.split(',').collect();
+        // logs written before order types were added have 14 fields: limit orders, no client id;
+        // before engine order ids, 16
+        if !matches!(f.len(), 14 | 16 | 17) {
+            return None;
+        }
+        let order_id = match f.get(16) {
+            Some(id) => id.parse().ok()?,
+            None => 0,
+        };
+        let (order_type, client_id) = match f.get(14..16) {
+            Some([kind, id]) => (
+                match *kind {
+                    "LIMIT" => OrderType::LIMIT,
+                    "MARKET" => OrderType::MARKET,
+                    "FAK" => OrderType::FAK,
+                    "FOK" => OrderType::FOK,
+                    _ => return None,
+                },
+                id.parse().ok()?,
+            ),
+            _ => (OrderType::LIMIT, 0),
+        };
+        let order = Order {
+            stg_name: NameType::from(f[1]),
+            symbol: SymbolType::from(f[2]),
+            timestamp: f[0].parse().ok()?,
+            price: f[5].parse().ok()?,
+            lots: f[6].parse().ok()?,
+            direction: match f[3] {
+                "BUY" => DirectionType::BUY,
+                "SELL" => DirectionType::SELL,
+                _ => return None,
+            },
+            offset: match f[4] {
+                "OPEN" => OffsetFlagType::OPEN,
+                "CLOSE" => OffsetFlagType::CLOSE,
+                _ => return None,
+            },
+            order_type,
+            client_id,
+            order_id,
+        };
+        let book = BookSnapshot {
+            stamp: f[7].parse().ok()?,
+            last: f[8].parse().ok()?,
+            bid: f[9].parse().ok()?,
+            ask: f[10].parse().ok()?,
+            bid_size: f[11].parse().ok()?,
+            ask_size: f[12].parse().ok()?,
+        };
+        Some(OrderRecord { order, book })
+    }
+}
+
+/// Append-only CSV of every submitted order. Lines are written by the log's own thread
+/// through a buffer flushed whenever its queue runs dry, so the send path only queues a
+/// record; dropping the log writes what is queued and joins the thread.
+pub struct AuditLog {
+    tx: Option<mpsc::Sender<OrderRecord>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl AuditLog {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", HEADER)?;
+        }
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name("audit-log".into())
+            .spawn(move || write_records(BufWriter::new(file), rx))?;
+        Ok(Self {
+            tx: Some(tx),
+            handle: Some(handle),
+        })
+    }
+
+    /// A handle for queueing records from another thread.
+    pub fn sender(&self) -> mpsc::Sender<OrderRecord> {
+        self.tx.clone().expect("audit log is open until dropped")
+    }
+
+    pub fn append(&self, record: &OrderRecord) {
+        if let Some(ref tx) = self.tx {
+            let _ = tx.send(*record);
+        }
+    }
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        // the thread exits once every sender, including those handed out, is gone
+        self.tx.take();
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            error!("audit log writer panicked");
+        }
+    }
+}
+
+fn write_records(mut file: BufWriter<File>, rx: mpsc::Receiver<OrderRecord>) {
+    while let Ok(first) = rx.recv() {
+        let written = std::iter::once(first)
+            .chain(rx.try_iter())
+            .try_for_each(|record| writeln!(file, "{}", record.csv_line()))
+            .and_then(|()| file.flush());
+        if let Err(e) = written {
+            error!(error = ?e, "failed to append to audit log");
+        }
+    }
+}
+
+/// Load an audit log written by `AuditLog`, skipping the header and malformed lines.
+pub fn read_records<P: AsRef<Path>>(path: P) -> io::Result<Vec<OrderRecord>> {
+    let mut records = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        if let Some(record) = OrderRecord::from_csv(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_queued_from_other_threads_are_written_before_the_log_closes() {
+        let path = std::env::temp_dir().join(format!("fustg_audit_{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let record = OrderRecord {
+            order: Order {
+                stg_name: NameType::from("a"),
+                symbol: SymbolType::from("rb2505"),
+                timestamp: 1000,
+                price: 3500.0,
+                lots: 1,
+                direction: DirectionType::BUY,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::FAK,
+                client_id: 3,
+                order_id: 9,
+            },
+            book: BookSnapshot {
+                stamp: 1000,
+                last: 3500.0,
+                bid: 3499.0,
+                ask: 3500.0,
+                bid_size: 1,
+                ask_size: 1,
+            },
+        };
+        let log = AuditLog::open(&path).unwrap();
+        let sender = log.sender();
+        thread::spawn(move || sender.send(record).unwrap()).join().unwrap();
+        log.append(&OrderRecord {
+            order: Order {
+                client_id: 4,
+                ..record.order
+            },
+            ..record
+        });
+        drop(log);
+
+        let records = read_records(&path).unwrap();
+        let mut ids: Vec<_> = records.iter().map(|r| (r.order.client_id, r.order.order_id)).collect();
+        ids.sort();
+        assert_eq!(ids, [(3, 9), (4, 9)]);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+//! In-process backtest of one strategy over recorded ticks, without the engine, sockets or
+//! threads: each tick goes to a `SimBroker` first, its fills to the strategy's
+//! `PerformanceTracker`, and then to the strategy, whose order is matched against the same
+//! tick. Fast and deterministic enough to run thousands of times in a parameter search; the
+//! engine-side features (stops, throttles, pending-order expiry) are not simulated, so confirm
+//! a candidate with the replayer before trading it. Fill prices and sizes follow the given
+//! `ExecutionModel`; the default fills at the touch, which flatters strategies that trade often.
+//!
+//! Opening orders the cash cannot fund are refused, and once the floating loss eats through
+//! the free cash (a margin call, see `PerformanceTracker::is_margin_call`) every position is
+//! liquidated at the touch, as a broker would, and the strategy may open again afterwards.
+//! With `ExecutionModel::conduct` set, orders past the exchange conduct limits are refused and
+//! message fees charged, as the engine's risk checks do.
+use crate::benchmark::{Benchmark, RelativeStats};
+use crate::calendar;
+use crate::clock::{Clock, EventClock};
+use crate::conduct::ConductTracker;
+use crate::config::ContractInfo;
+use crate::execution::Report;
+use crate::pending::PendingOrders;
+use crate::perf_stats::DAY_MS;
+use crate::perf_tracker::PerformanceTracker;
+use crate::position::PositionManager;
+use crate::round_trips::RoundTrip;
+use crate::sim::{ExecutionModel, SimBroker};
+use crate::sizing::SizingContext;
+use crate::strategy::{EngineContext, Strategy};
+use crate::timeutil;
+use crate::types::TickData;
+use std::sync::Arc;
+
+/// Trading days per year, for annualizing.
+const TRADING_DAYS: f64 = 252.0;
+
+/// Summary of one backtest run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BacktestStats {
+    pub final_equity: f64,
+    /// final over initial equity, minus one
+    pub total_return: f64,
+    /// annualized, from daily closing equity; 0 with fewer than two days or no variance
+    pub sharpe: f64,
+    /// largest peak-to-trough fall of the equity curve, as a fraction of the peak
+    pub max_drawdown: f64,
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub fills: usize,
+    /// forced liquidations after a margin call
+    pub margin_calls: usize,
+}
+
+/// The equity curve and stats of a backtest.
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    pub stats: BacktestStats,
+    /// closing equity of each local trading day, as `(yyyymmdd, equity)`
+    pub daily_equity: Vec<(u32, f64)>,
+    /// round trips closed, see `PerformanceTracker::trades`
+    pub trades: Vec<RoundTrip>,
+    /// against holding the contract throughout, sampled daily
+    pub benchmark: RelativeStats,
+}
+
+/// Run `strategy` over `ticks`, in stamp order, with `init_cash` on a contract described by
+/// `info`, filling orders as `model` does.
+pub fn run(strategy: &mut dyn Strategy, ticks: &[TickData], init_cash: f64, info: ContractInfo, model: &ExecutionModel) -> BacktestResult {
+    let clock = Arc::new(EventClock::new(ticks.first().map_or(0, |t| t.stamp)));
+    strategy.on_clock(clock.clone());
+    if let Some(first) = ticks.first() {
+        strategy.on_start(&EngineContext {
+            symbol: first.symbol,
+            worker: 0,
+            now: clock.now(),
+            history: None,
+        });
+    }
+    let mut perf = PerformanceTracker::new(init_cash, info).with_benchmark(Benchmark::buy_and_hold(DAY_MS));
+    let mut sim = SimBroker::new().with_model(model.clone());
+    if let Some(first) = ticks.first() {
+        sim = sim.with_fees(first.symbol, perf.fee_model(), info.multiplier);
+    }
+    let mut conduct = model.conduct.map(ConductTracker::new);
+    let mut next_id = 0;
+    // resting orders, so fills report what is left and a target-position strategy is not
+    // sent the same order twice
+    let mut pending = PendingOrders::new();
+    let mut positions = PositionManager::new();
+    let mut fills = 0;
+    let mut margin_calls = 0;
+    let mut daily_equity: Vec<(u32, f64)> = Vec::new();
+    let mut trading_day = None;
+
+    for tick in ticks {
+        clock.on_event(tick.stamp);
+        let day = calendar::trading_day(tick.stamp, calendar::is_weekday);
+        if trading_day != Some(day) {
+            if trading_day.is_some() {
+                strategy.on_day_close();
+            }
+            trading_day = Some(day);
+            strategy.on_day_open();
+        }
+        let mut reports = sim.on_tick(tick);
+        strategy.on_account(&SizingContext {
+            equity: perf.equity(),
+            multiplier: info.multiplier,
+        });
+        let orders = match strategy.target_position(tick) {
+            Some(target) => {
+                let (long, short) = perf.positions();
+                let held = (long.map_or(0, |p| p.lots), short.map_or(0, |p| p.lots));
+                positions.rebalance(strategy.name(), tick, target, held, pending.iter())
+            }
+            None => strategy.update(tick),
+        };
+        for mut order in orders {
+            if let Err(available) = perf.check_margin(&order) {
+                strategy.on_order_rejected(&order, &format!("insufficient funds, would leave {:.2} available", available));
+                continue;
+            }
+            if let Some(ref mut conduct) = conduct {
+                if let Err(breach) = conduct.admit_order(order.timestamp) {
+                    strategy.on_conduct_warning(&breach);
+                    strategy.on_order_rejected(&order, &breach.to_string());
+                    continue;
+                }
+                perf.charge_fee(conduct.on_order(order.timestamp));
+            }
+            next_id += 1;
+            order.client_id = next_id;
+            pending.insert(order);
+            reports.extend(sim.on_order(&order));
+        }
+        for report in &reports {
+            match report {
+                Report::Fill(report) => {
+                    perf.on_fill(&report.fill);
+                    let remaining = pending.on_fill(&report.fill).map_or(0, |rest| rest.lots);
+                    strategy.on_fill(&report.fill, remaining);
+                    if let Some(ref mut conduct) = conduct {
+                        conduct.on_trade(report.fill.timestamp);
+                    }
+                    fills += 1;
+                }
+                Report::Status(status) => match pending.remove(status.order.client_id) {
+                    Some(rest) if status.is_rejection() => strategy.on_order_rejected(&rest, "rejected by the exchange"),
+                    Some(rest) => strategy.on_order_cancelled(&rest),
+                    None => {}
+                },
+            }
+        }
+        if let Some(ref mut conduct) = conduct {
+            for warning in conduct.take_warnings() {
+                strategy.on_conduct_warning(&warning);
+            }
+        }
+        perf.on_tick_end(tick);
+
+        if perf.is_margin_call() {
+            margin_calls += 1;
+            for mut order in perf.closing_orders(strategy.name(), tick) {
+                next_id += 1;
+                order.client_id = next_id;
+                pending.insert(order);
+                for report in sim.on_order(&order) {
+                    match report {
+                        Report::Fill(report) => {
+                            perf.on_fill(&report.fill);
+                            let remaining = pending.on_fill(&report.fill).map_or(0, |rest| rest.lots);
+                            strategy.on_fill(&report.fill, remaining);
+                            fills += 1;
+                        }
+                        Report::Status(status) => {
+                            pending.remove(status.order.client_id);
+                        }
+                    }
+                }
+            }
+            // the curve's last sample shows the account after the liquidation
+            perf.on_tick_end(tick);
+        }
+
+        let date = timeutil::local_date(tick.stamp);
+        let equity = perf.equity();
+        match daily_equity.last_mut() {
+            Some((last, close)) if *last == date => *close = equity,
+            _ => daily_equity.push((date, equity)),
+        }
+    }
+
+    strategy.on_stop();
+
+    let final_equity = perf.equity();
+    BacktestResult {
+        stats: BacktestStats {
+            final_equity,
+            total_return: final_equity / init_cash - 1.0,
+            sharpe: sharpe(init_cash, &daily_equity),
+            max_drawdown: max_drawdown(&perf.market_values().to_vec()),
+            realized_pnl: perf.realized_pnl(),
+            fees: perf.total_fee(),
+            fills,
+            margin_calls,
+        },
+        daily_equity,
+        trades: perf.trades().to_vec(),
+        benchmark: perf.benchmark_stats().unwrap_or_default(),
+    }
+}
+
+/// Annualized Sharpe ratio of daily returns, starting from `init_cash`, at a zero risk-free rate.
+pub(crate) fn sharpe(init_cash: f64, daily_equity: &[(u32, f64)]) -> f64 {
+    let mut prev = init_cash;
+    let returns: Vec<f64> = daily_equity
+        .iter()
+        .map(|&(_, equity)| {
+            let r = equity / prev - 1.0;
+            prev = equity;
+            r
+        })
+        .collect();
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    if var <= 0.0 { 0.0 } else { mean / var.sqrt() * TRADING_DAYS.sqrt() }
+}
+
+/// Largest peak-to-trough fall of `equity`, as a fraction of the peak.
+pub(crate) fn max_drawdown(equity: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut worst: f64 = 0.0;
+    for &e in equity {
+        peak = peak.max(e);
+        if peak > 0.0 {
+            worst = worst.max((peak - e) / peak);
+        }
+    }
+    worst
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conduct::{ConductLimits, ConductWarning};
+    use crate::strategy::{Orders, smallvec};
+    use crate::testing;
+    use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType};
+
+    /// Buys one lot on the first tick and holds it.
+    struct BuyAndHold {
+        bought: bool,
+    }
+
+    impl Strategy for BuyAndHold {
+        fn name(&self) -> NameType {
+            NameType::from("hold")
+        }
+
+        fn update(&mut self, tick: &TickData) -> Orders {
+            if std::mem::replace(&mut self.bought, true) {
+                return Orders::new();
+            }
+            smallvec![Order {
+                stg_name: self.name(),
+                symbol: tick.symbol,
+                timestamp: tick.stamp,
+                price: tick.ap1,
+                lots: 1,
+                direction: DirectionType::BUY,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            }]
+        }
+    }
+
+    #[test]
+    fn tracks_equity_across_days() {
+        let ticks = testing::ticks("rb2505", &[100.0, 110.0, 99.0, 120.0], 0, DAY_MS, 1.0);
+        let info = ContractInfo {
+            multiplier: 10.0,
+            long_margin_rate: 0.1,
+            ..Default::default()
+        };
+        let result = run(&mut BuyAndHold { bought: false }, &ticks, 10_000.0, info, &ExecutionModel::default());
+        assert_eq!(result.daily_equity.len(), 4);
+        assert_eq!(result.stats.fills, 1);
+        // bought at the 100.5 ask, marked at 120
+        assert!((result.stats.final_equity - (10_000.0 + 195.0)).abs() < 1e-6);
+        assert!(result.stats.max_drawdown > 0.0);
+        assert!(result.stats.sharpe > 0.0);
+        // one lot on 10,000 moves about a tenth as much as the contract, and lagged its rise
+        assert!((result.benchmark.beta - 0.1).abs() < 0.02);
+        assert!(result.benchmark.relative_return < 0.0);
+    }
+
+    #[test]
+    fn refuses_unfunded_opens_and_liquidates_on_margin_call() {
+        let ticks = testing::ticks("rb2505", &[100.0, 95.0, 90.0], 0, 1000, 1.0);
+        let info = ContractInfo {
+            multiplier: 10.0,
+            long_margin_rate: 0.1,
+            ..Default::default()
+        };
+        // 100.5 of margin needed
+        let result = run(&mut BuyAndHold { bought: false }, &ticks, 50.0, info, &ExecutionModel::default());
+        assert_eq!((result.stats.fills, result.stats.margin_calls), (0, 0));
+
+        // 49.5 left free, then a 55 floating loss at 95
+        let result = run(&mut BuyAndHold { bought: false }, &ticks, 150.0, info, &ExecutionModel::default());
+        assert_eq!((result.stats.fills, result.stats.margin_calls), (2, 1));
+        // sold at the 94.5 bid: flat, and the fall to 90 costs nothing more
+        assert!((result.stats.realized_pnl + 60.0).abs() < 1e-6);
+        assert_eq!(result.trades.len(), 1);
+        assert!((result.trades[0].pnl + 60.0).abs() < 1e-6 && result.trades[0].mae >= 60.0);
+    }
+
+    /// Logs its lifecycle callbacks.
+    #[derive(Default)]
+    struct Lifecycle {
+        calls: Vec<&'static str>,
+    }
+
+    impl Strategy for Lifecycle {
+        fn name(&self) -> NameType {
+            NameType::from("lifecycle")
+        }
+
+        fn update(&mut self, _tick: &TickData) -> Orders {
+            self.calls.push("tick");
+            Orders::new()
+        }
+
+        fn on_start(&mut self, _ctx: &EngineContext) {
+            self.calls.push("start");
+        }
+
+        fn on_stop(&mut self) {
+            self.calls.push("stop");
+        }
+
+        fn on_day_open(&mut self) {
+            self.calls.push("open");
+        }
+
+        fn on_day_close(&mut self) {
+            self.calls.push("close");
+        }
+    }
+
+    #[test]
+    fn night_sessions_open_the_next_trading_day() {
+        // Thursday 2025-03-13 day session, its night session, Friday's day and night sessions, then Monday
+        let at = |date: u32, hh: u32| timeutil::local_stamp(date, hh * 3600);
+        let stamps = [at(20250313, 10), at(20250313, 21), at(20250314, 10), at(20250314, 22), at(20250317, 10)];
+        let mut ticks = testing::ticks("rb2505", &[100.0; 5], 0, 0, 1.0);
+        for (tick, stamp) in ticks.iter_mut().zip(stamps) {
+            tick.stamp = stamp;
+        }
+        let mut strategy = Lifecycle::default();
+        run(&mut strategy, &ticks, 10_000.0, ContractInfo::default(), &ExecutionModel::default());
+        assert_eq!(
+            strategy.calls,
+            [
+                "start", "open", "tick", "close", "open", "tick", "tick", "close", "open", "tick", "tick", "stop"
+            ]
+        );
+    }
+
+    /// Bids well below the market on every tick, so nothing fills.
+    #[derive(Default)]
+    struct Quoter {
+        rejected: usize,
+        warnings: usize,
+    }
+
+    impl Strategy for Quoter {
+        fn name(&self) -> NameType {
+            NameType::from("quoter")
+        }
+
+        fn update(&mut self, tick: &TickData) -> Orders {
+            smallvec![Order {
+                stg_name: self.name(),
+                symbol: tick.symbol,
+                timestamp: tick.stamp,
+                price: tick.bp1 - 10.0,
+                lots: 1,
+                direction: DirectionType::BUY,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            }]
+        }
+
+        fn on_order_rejected(&mut self, _order: &Order, _reason: &str) {
+            self.rejected += 1;
+        }
+
+        fn on_conduct_warning(&mut self, _warning: &ConductWarning) {
+            self.warnings += 1;
+        }
+    }
+
+    #[test]
+    fn holds_orders_to_the_conduct_limits() {
+        let ticks = testing::ticks("rb2505", &[100.0; 5], 0, 1000, 1.0);
+        let model = ExecutionModel {
+            conduct: Some(ConductLimits {
+                max_order_to_trade: 2.0,
+                ratio_min_orders: 2,
+                free_messages: 1,
+                ..ConductLimits::cffex()
+            }),
+            ..Default::default()
+        };
+        let mut strategy = Quoter::default();
+        let result = run(&mut strategy, &ticks, 10_000.0, ContractInfo::default(), &model);
+        // two orders without a trade reach the ratio: warned once, then the last three refused
+        assert_eq!((strategy.rejected, strategy.warnings), (3, 4));
+        // the second order was past the free message
+        assert_eq!(result.stats.fees, 1.0);
+    }
+}
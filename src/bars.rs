@@ -0,0 +1,182 @@
+//! Local store of OHLCV bars for backtesting, kept as `<root>/<period>/<symbol>.csv`.
+//!
+//! Files are append-only and sorted by time; `BarStore::append` only writes bars newer than
+//! the last one stored, so repeated downloads update the store incrementally.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "stamp,open,high,low,close,volume,oi";
+
+/// One bar; `stamp` is its start, as a `TickData.stamp`. Daily bars start at CST midnight of
+/// the trading day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bar {
+    pub stamp: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub oi: f64,
+}
+
+impl Bar {
+    fn csv_line(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{}",
+            self.stamp, self.open, self.high, self.low, self.close, self.volume, self.oi
+        )
+    }
+
+    fn from_csv(line: &str) -> Option<Self> {
+        let f: Vec<&str> = line.split(',').collect();
+        if f.len() != 7 {
+            return None;
+        }
+        Some(Bar {
+            stamp: f[0].parse().ok()?,
+            open: f[1].parse().ok()?,
+            high: f[2].parse().ok()?,
+            low: f[3].parse().ok()?,
+            close: f[4].parse().ok()?,
+            volume: f[5].parse().ok()?,
+            oi: f[6].parse().ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BarPeriod {
+    Minute,
+    Daily,
+}
+
+impl BarPeriod {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "1m" => Some(BarPeriod::Minute),
+            "1d" => Some(BarPeriod::Daily),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BarPeriod::Minute => "1m",
+            BarPeriod::Daily => "1d",
+        }
+    }
+}
+
+pub struct BarStore {
+    root: PathBuf,
+}
+
+impl BarStore {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self, symbol: &str, period: BarPeriod) -> PathBuf {
+        self.root.join(period.as_str()).join(format!("{}.csv", symbol))
+    }
+
+    /// Every stored bar of `symbol`, oldest first; empty if nothing is stored yet.
+    pub fn load(&self, symbol: &str, period: BarPeriod) -> io::Result<Vec<Bar>> {
+        let file = match File::open(self.path(symbol, period)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut bars = Vec::new();
+        for line in BufReader::new(file).lines() {
+            if let Some(bar) = Bar::from_csv(&line?) {
+                bars.push(bar);
+            }
+        }
+        Ok(bars)
+    }
+
+    pub fn last_stamp(&self, symbol: &str, period: BarPeriod) -> io::Result<Option<i64>> {
+        Ok(self.load(symbol, period)?.last().map(|b| b.stamp))
+    }
+
+    /// Append the bars newer than the last stored one; returns how many were written.
+    /// The last stored bar may still have been forming when it was saved, but it is kept.
+    pub fn append(&self, symbol: &str, period: BarPeriod, bars: &[Bar]) -> io::Result<usize> {
+        let last = self.last_stamp(symbol, period)?;
+        let mut fresh: Vec<&Bar> = bars.iter().filter(|b| last.is_none_or(|l| b.stamp > l)).collect();
+        fresh.sort_by_key(|b| b.stamp);
+        fresh.dedup_by_key(|b| b.stamp);
+        if fresh.is_empty() {
+            return Ok(0);
+        }
+
+        let path = self.path(symbol, period);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", HEADER)?;
+        }
+        for bar in &fresh {
+            writeln!(file, "{}", bar.csv_line())?;
+        }
+        Ok(fresh.len())
+    }
+
+    /// Replace everything stored for `symbol` with `bars`, e.g. a rebuilt continuous series.
+    /// The file is swapped atomically, so readers see either the old or the new series.
+    pub fn save(&self, symbol: &str, period: BarPeriod, bars: &[Bar]) -> io::Result<()> {
+        let path = self.path(symbol, period);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        writeln!(file, "{}", HEADER)?;
+        for bar in bars {
+            writeln!(file, "{}", bar.csv_line())?;
+        }
+        file.sync_all()?;
+        fs::rename(tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeutil;
+
+    #[test]
+    fn append_is_incremental() {
+        let root = std::env::temp_dir().join(format!("fustg_bars_{}", std::process::id()));
+        let store = BarStore::new(&root);
+        let bar = |date, close| Bar {
+            stamp: timeutil::local_stamp(date, 0),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            oi: 2.0,
+        };
+        assert_eq!(timeutil::local_date(bar(20250314, 1.0).stamp), 20250314);
+
+        assert_eq!(
+            store.append("RB0", BarPeriod::Daily, &[bar(20250314, 2.0), bar(20250313, 1.0)]).unwrap(),
+            2
+        );
+        assert_eq!(
+            store.append("RB0", BarPeriod::Daily, &[bar(20250314, 9.0), bar(20250317, 3.0)]).unwrap(),
+            1
+        );
+        let closes: Vec<f64> = store.load("RB0", BarPeriod::Daily).unwrap().iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![1.0, 2.0, 3.0]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+//! Performance relative to a benchmark: the traded contract held throughout (buy and hold)
+//! or any other series, such as an index, fed in as it moves. The strategy's equity and the
+//! benchmark's level are sampled together, the last of each `interval_ms` bucket, and the
+//! returns between samples are regressed as they arrive, so the figures can be read live.
+//! Annualized like `PerfStats`, with the number of samples per calendar year actually seen;
+//! the ratios stay 0 until there are two returns and a day of history.
+use crate::perf_stats::{DAY_MS, YEAR_MS};
+use serde::Serialize;
+
+/// Where the strategy stands against its benchmark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RelativeStats {
+    /// annualized return not explained by the benchmark, at a zero risk-free rate
+    pub alpha: f64,
+    /// sensitivity of the strategy's returns to the benchmark's
+    pub beta: f64,
+    /// annualized mean over deviation of the returns in excess of the benchmark's
+    pub information_ratio: f64,
+    /// annualized deviation of the excess returns
+    pub tracking_error: f64,
+    /// growth of equity over growth of the benchmark, minus one
+    pub relative_return: f64,
+    /// largest fall of that relative growth from a peak, as a fraction of the peak
+    pub max_relative_drawdown: f64,
+}
+
+/// A benchmark attached to a `PerformanceTracker`, see `PerformanceTracker::with_benchmark`.
+#[derive(Debug, Clone, Copy)]
+pub struct Benchmark {
+    interval_ms: i64,
+    /// take the level from each tick's last price
+    follow_contract: bool,
+    level: Option<f64>,
+    /// bucket, equity and level of the sample not yet booked
+    pending: Option<(i64, f64, f64)>,
+    first: Option<(i64, f64, f64)>,
+    last: Option<(i64, f64, f64)>,
+    returns: u64,
+    mean_strategy: f64,
+    mean_benchmark: f64,
+    /// co-moment of the two returns
+    co_moment: f64,
+    /// sum of squared deviations of the benchmark's returns
+    m2_benchmark: f64,
+    mean_excess: f64,
+    m2_excess: f64,
+    peak: f64,
+    max_drawdown: f64,
+}
+
+impl Benchmark {
+    fn new(interval_ms: i64, follow_contract: bool) -> Self {
+        Self {
+            interval_ms: interval_ms.max(0),
+            follow_contract,
+            level: None,
+            pending: None,
+            first: None,
+            last: None,
+            returns: 0,
+            mean_strategy: 0.0,
+            mean_benchmark: 0.0,
+            co_moment: 0.0,
+            m2_benchmark: 0.0,
+            mean_excess: 0.0,
+            m2_excess: 0.0,
+            peak: 0.0,
+            max_drawdown: 0.0,
+        }
+    }
+
+    /// The traded contract bought at the first tick and held, sampled once per `interval_ms`
+    /// (0 for every tick).
+    pub fn buy_and_hold(interval_ms: i64) -> Self {
+        Self::new(interval_ms, true)
+    }
+
+    /// A series whose level is given with `set_level`, sampled once per `interval_ms`.
+    pub fn series(interval_ms: i64) -> Self {
+        Self::new(interval_ms, false)
+    }
+
+    /// The benchmark's latest level, for a `series`.
+    pub fn set_level(&mut self, level: f64) {
+        self.level = Some(level);
+    }
+
+    /// Sample the strategy's `equity` against the benchmark as of `stamp`, when the
+    /// contract's last price was `last`. Nothing is sampled before the series has a level.
+    pub fn update(&mut self, stamp: i64, equity: f64, last: f64) {
+        if self.follow_contract {
+            self.level = Some(last);
+        }
+        let Some(level) = self.level.filter(|l| *l > 0.0) else {
+            return;
+        };
+        let bucket = if self.interval_ms > 0 {
+            stamp - stamp.rem_euclid(self.interval_ms)
+        } else {
+            stamp
+        };
+        if let Some(pending) = self.pending
+            && (self.interval_ms == 0 || pending.0 != bucket)
+        {
+            self.book(pending);
+        }
+        self.pending = Some((bucket, equity, level));
+    }
+
+    fn book(&mut self, (stamp, equity, level): (i64, f64, f64)) {
+        if let Some((_, prev_equity, prev_level)) = self.last
+            && prev_equity > 0.0
+        {
+            let (rs, rb) = (equity / prev_equity - 1.0, level / prev_level - 1.0);
+            self.returns += 1;
+            let n = self.returns as f64;
+            let delta = rb - self.mean_benchmark;
+            self.mean_strategy += (rs - self.mean_strategy) / n;
+            self.mean_benchmark += delta / n;
+            self.co_moment += delta * (rs - self.mean_strategy);
+            self.m2_benchmark += delta * (rb - self.mean_benchmark);
+            let excess = rs - rb;
+            let delta = excess - self.mean_excess;
+            self.mean_excess += delta / n;
+            self.m2_excess += delta * (excess - self.mean_excess);
+        }
+        let (_, first_equity, first_level) = *self.first.get_or_insert((stamp, equity, level));
+        self.last = Some((stamp, equity, level));
+        if first_equity > 0.0 {
+            let relative = (equity / first_equity) / (level / first_level);
+            if relative >= self.peak {
+                self.peak = relative;
+            } else {
+                self.max_drawdown = self.max_drawdown.max((self.peak - relative) / self.peak);
+            }
+        }
+    }
+
+    /// The figures so far, the sample still being taken included.
+    pub fn stats(&self) -> RelativeStats {
+        let mut booked = *self;
+        if let Some(pending) = booked.pending.take() {
+            booked.book(pending);
+        }
+        booked.booked_stats()
+    }
+
+    fn booked_stats(&self) -> RelativeStats {
+        let (Some((start, first_equity, first_level)), Some((end, equity, level))) = (self.first, self.last) else {
+            return RelativeStats::default();
+        };
+        let mut stats = RelativeStats {
+            max_relative_drawdown: self.max_drawdown,
+            ..RelativeStats::default()
+        };
+        if first_equity > 0.0 {
+            stats.relative_return = (equity / first_equity) / (level / first_level) - 1.0;
+        }
+        if self.m2_benchmark > 0.0 {
+            stats.beta = self.co_moment / self.m2_benchmark;
+        }
+        let elapsed = end - start;
+        if self.returns < 2 || elapsed < DAY_MS {
+            return stats;
+        }
+        let per_year = self.returns as f64 / (elapsed as f64 / YEAR_MS);
+        stats.alpha = (self.mean_strategy - stats.beta * self.mean_benchmark) * per_year;
+        let sd = (self.m2_excess / (self.returns - 1) as f64).sqrt();
+        stats.tracking_error = sd * per_year.sqrt();
+        if sd > 0.0 {
+            stats.information_ratio = self.mean_excess / sd * per_year.sqrt();
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regresses_sampled_returns_on_the_benchmark() {
+        let mut benchmark = Benchmark::buy_and_hold(DAY_MS);
+        assert_eq!(benchmark.stats(), RelativeStats::default());
+        // twice the contract's daily moves, plus 1% a day; the intraday ticks are resampled away
+        let levels = [100.0, 102.0, 99.0, 101.0, 104.0];
+        let mut equity = 1000.0;
+        for (day, pair) in levels.windows(2).enumerate() {
+            let stamp = (day as i64 + 1) * DAY_MS;
+            if day == 0 {
+                benchmark.update(0, 5.0, 50.0);
+                benchmark.update(DAY_MS / 2, equity, pair[0]);
+            }
+            equity *= 1.0 + 2.0 * (pair[1] / pair[0] - 1.0) + 0.01;
+            benchmark.update(stamp, equity, pair[1]);
+        }
+        let stats = benchmark.stats();
+        assert!((stats.beta - 2.0).abs() < 1e-9);
+        assert!((stats.alpha - 0.01 * YEAR_MS / DAY_MS as f64).abs() < 1e-6);
+        assert!(stats.relative_return > 0.0 && stats.information_ratio > 0.0 && stats.tracking_error > 0.0);
+        // the strategy lost more than the contract on the third day
+        assert!(stats.max_relative_drawdown > 0.0);
+
+        let mut series = Benchmark::series(0);
+        series.update(0, 1000.0, 3500.0);
+        assert_eq!(series.stats(), RelativeStats::default());
+        series.set_level(10.0);
+        series.update(1, 1000.0, 3500.0);
+        series.set_level(11.0);
+        series.update(2, 1000.0, 3500.0);
+        assert!((series.stats().relative_return + 1.0 / 11.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,146 @@
+//! Download daily and minute bars for the configured products into the local bar store.
+//!
+//! usage: data [config/data.toml]
+//! Bars come from Sina's public futures quote service and are appended incrementally, so the
+//! store grows with every run. Configured continuous series are then rebuilt from the stored
+//! contracts. Build with `--features download`.
+use fustg_rs::bars::{Bar, BarPeriod, BarStore};
+use fustg_rs::continuous::{self, Adjustment, RollRule};
+use fustg_rs::timeutil;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use std::{env, fs, process};
+
+const SINA: &str = "https://stock2.finance.sina.com.cn/futures/api/json.php/InnerFuturesNewService";
+
+#[derive(Deserialize)]
+struct DataConfig {
+    store: String,
+    periods: Vec<String>,
+    symbols: Vec<String>,
+    #[serde(default)]
+    continuous: Vec<ContinuousConfig>,
+}
+
+/// A continuous series rebuilt from downloaded contracts after every run.
+#[derive(Deserialize)]
+struct ContinuousConfig {
+    name: String,
+    contracts: Vec<String>,
+    roll: String,
+    adjust: String,
+}
+
+fn url(symbol: &str, period: BarPeriod) -> String {
+    match period {
+        BarPeriod::Daily => format!("{}.getDailyKLine?symbol={}", SINA, symbol),
+        BarPeriod::Minute => format!("{}.getFewMinLine?symbol={}&type=1", SINA, symbol),
+    }
+}
+
+/// `2025-03-14` or `2025-03-14 09:01:00` → (yyyymmdd, seconds of day).
+fn parse_time(s: &str) -> Option<(u32, u32)> {
+    let (date, time) = s.split_once(' ').unwrap_or((s, "00:00:00"));
+    let date = date.replace('-', "").parse().ok()?;
+    let mut hms = time.split(':').map(|f| f.parse::<u32>().ok());
+    let secs = hms.next()?? * 3600 + hms.next()?? * 60 + hms.next().flatten().unwrap_or(0);
+    Some((date, secs))
+}
+
+/// Sina quotes numbers as strings.
+fn number(row: &Value, key: &str) -> Option<f64> {
+    match row.get(key)? {
+        Value::String(s) => s.parse().ok(),
+        v => v.as_f64(),
+    }
+}
+
+fn parse_bars(body: &str, period: BarPeriod) -> Option<Vec<Bar>> {
+    // tolerate a JSONP wrapper around the array
+    let json = &body[body.find('[')?..=body.rfind(']')?];
+    let rows: Vec<Value> = serde_json::from_str(json).ok()?;
+    let bars = rows.iter().filter_map(|row| {
+        let (date, secs) = parse_time(row.get("d")?.as_str()?)?;
+        // minute bars are stamped with their close; a `Bar` with its start
+        let start = match period {
+            BarPeriod::Minute => timeutil::local_stamp(date, secs) - 60 * timeutil::STAMP_PER_SEC,
+            BarPeriod::Daily => timeutil::local_stamp(date, 0),
+        };
+        Some(Bar {
+            stamp: start,
+            open: number(row, "o")?,
+            high: number(row, "h")?,
+            low: number(row, "l")?,
+            close: number(row, "c")?,
+            volume: number(row, "v")?,
+            oi: number(row, "p").unwrap_or(0.0),
+        })
+    });
+    Some(bars.collect())
+}
+
+fn fetch(symbol: &str, period: BarPeriod) -> Result<Vec<Bar>, String> {
+    let body = ureq::get(&url(symbol, period))
+        .timeout(Duration::from_secs(30))
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    parse_bars(&body, period).ok_or_else(|| format!("unexpected response: {:.80}", body))
+}
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| "config/data.toml".into());
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+    let cfg: DataConfig = toml::from_str(&text).unwrap_or_else(|e| panic!("Failed to parse {}: {}", path, e));
+    let periods: Vec<BarPeriod> = cfg
+        .periods
+        .iter()
+        .map(|p| {
+            BarPeriod::parse(p).unwrap_or_else(|| {
+                eprintln!("invalid period {:?}, expected 1d or 1m", p);
+                process::exit(2);
+            })
+        })
+        .collect();
+
+    let store = BarStore::new(&cfg.store);
+    let mut failed = 0;
+    for symbol in &cfg.symbols {
+        for &period in &periods {
+            match fetch(symbol, period).and_then(|bars| store.append(symbol, period, &bars).map_err(|e| e.to_string())) {
+                Ok(n) => println!("{} {}: {} new bars", symbol, period.as_str(), n),
+                Err(e) => {
+                    eprintln!("{} {}: {}", symbol, period.as_str(), e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    for cont in &cfg.continuous {
+        let (Some(rule), Some(adjustment)) = (RollRule::parse(&cont.roll), Adjustment::parse(&cont.adjust)) else {
+            eprintln!("{}: invalid roll {:?} or adjust {:?}", cont.name, cont.roll, cont.adjust);
+            failed += 1;
+            continue;
+        };
+        for &period in &periods {
+            match continuous::build_into_store(&store, &cont.name, &cont.contracts, period, rule, adjustment) {
+                Ok(series) => println!(
+                    "{} {}: {} bars, {} rolls",
+                    cont.name,
+                    period.as_str(),
+                    series.bars.len(),
+                    series.rolls.len()
+                ),
+                Err(e) => {
+                    eprintln!("{} {}: {}", cont.name, period.as_str(), e);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,98 @@
+//! Operate a running engine over its control and query sockets, so pausing, flattening or
+//! reloading never means killing the process.
+//!
+//! usage: fustg-ctl [--control <uri>] [--query <uri>] <command>...
+//!   commands are the control socket's (see `control::Command`) and the query socket's
+//!   (see `query::Query`), plus `state` for every strategy's positions, PnL and recent fills
+//!   e.g. fustg-ctl pause rb2505
+//!        fustg-ctl pause strategy aberration_20
+//!        fustg-ctl flatten rb2505
+//!        fustg-ctl flatten all
+//!        fustg-ctl pnl
+//!        fustg-ctl reload
+//!        fustg-ctl --control ipc://@control-1 rearm all
+//!
+//! Exits 1 when the engine answers with an error and 2 on bad usage or no answer.
+use fustg_rs::control::Command;
+use fustg_rs::query::Query;
+use std::{env, process};
+
+/// How long to wait for the engine's answer.
+const TIMEOUT_MS: i32 = 5000;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--control <uri>] [--query <uri>] <command>...", program);
+    eprintln!("  control: pause|resume <symbol>, pause|resume strategy <strategy>, flatten <symbol|all>,");
+    eprintln!("           reload, rearm <strategy|all>,");
+    eprintln!("           param|toggle <strategy> <name> <value>, cancel <strategy> <client_id>,");
+    eprintln!("           add <symbol> <contract> <kind> <args>..., remove <strategy>, risk [pct]");
+    eprintln!("  query:   pnl, positions <strategy>, equity <symbol> [points], accounts,");
+    eprintln!("           strategies [points] [fills], state");
+    process::exit(2);
+}
+
+/// Send `line` on a REQ socket connected to `uri` and wait for the reply.
+fn request(uri: &str, line: &str) -> Result<String, String> {
+    let ctx = zmq::Context::new();
+    let socket = ctx.socket(zmq::REQ).map_err(|e| e.to_string())?;
+    socket.set_linger(0).map_err(|e| e.to_string())?;
+    socket.set_rcvtimeo(TIMEOUT_MS).map_err(|e| e.to_string())?;
+    socket.connect(uri).map_err(|e| format!("connecting to {}: {}", uri, e))?;
+    socket.send(line, 0).map_err(|e| format!("sending to {}: {}", uri, e))?;
+    match socket.recv_string(0) {
+        Ok(Ok(reply)) => Ok(reply),
+        Ok(Err(_)) => Err("reply is not valid UTF-8".into()),
+        Err(zmq::Error::EAGAIN) => Err(format!("no answer from {} within {} ms; is the engine running?", uri, TIMEOUT_MS)),
+        Err(e) => Err(format!("receiving from {}: {}", uri, e)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+    let (mut control_uri, mut query_uri) = ("ipc://@control".to_string(), "ipc://@query".to_string());
+    let mut words = Vec::new();
+    let mut rest = args.into_iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--control" => control_uri = rest.next().unwrap_or_else(|| usage(&program)),
+            "--query" => query_uri = rest.next().unwrap_or_else(|| usage(&program)),
+            "-h" | "--help" => usage(&program),
+            _ => words.push(arg),
+        }
+    }
+    if words.is_empty() {
+        usage(&program);
+    }
+    let line = match words.join(" ").as_str() {
+        "state" => "strategies 100 20".to_string(),
+        line => line.to_string(),
+    };
+
+    // check the command here, so a typo is not sent to a trading engine
+    let (uri, is_query) = if Query::parse(&line).is_ok() {
+        (query_uri, true)
+    } else if let Err(e) = Command::parse(&line) {
+        eprintln!("{}", e);
+        usage(&program);
+    } else {
+        (control_uri, false)
+    };
+
+    let reply = request(&uri, &line).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    if is_query {
+        let json: serde_json::Value = serde_json::from_str(&reply).unwrap_or(serde_json::Value::String(reply));
+        println!("{}", serde_json::to_string_pretty(&json).unwrap_or_default());
+        if json.get("error").is_some() {
+            process::exit(1);
+        }
+    } else {
+        println!("{}", reply);
+        if reply.starts_with("error") {
+            process::exit(1);
+        }
+    }
+}
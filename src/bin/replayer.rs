@@ -0,0 +1,91 @@
+//! Republish recorded tick files over a ZMQ PUB socket.
+//!
+//! usage: replayer <pub_uri> <speed> <file_or_dir>...
+//!   speed: `realtime`, `<N>x` (e.g. `10x`) or `max`
+//!   e.g.   replayer ipc://@hq 10x data/ticks/20250310
+use fustg_rs::{TickData, recorder, timeutil};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, fs, mem, process, thread};
+
+#[derive(Debug, Clone, Copy)]
+enum Speed {
+    /// multiple of the recorded pace; 1.0 is realtime
+    Factor(f64),
+    /// no pacing at all
+    Max,
+}
+
+fn parse_speed(s: &str) -> Option<Speed> {
+    match s {
+        "realtime" => Some(Speed::Factor(1.0)),
+        "max" => Some(Speed::Max),
+        _ => s.strip_suffix('x')?.parse::<f64>().ok().filter(|f| *f > 0.0).map(Speed::Factor),
+    }
+}
+
+/// Expand directories into the `.bin` files they contain.
+fn collect_files(path: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), out)?;
+        }
+    } else if path.extension().is_some_and(|ext| ext == "bin") {
+        out.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        eprintln!("usage: {} <pub_uri> <realtime|Nx|max> <file_or_dir>...", args[0]);
+        process::exit(2);
+    }
+    let Some(speed) = parse_speed(&args[2]) else {
+        eprintln!("invalid speed {:?}, expected realtime, Nx or max", args[2]);
+        process::exit(2);
+    };
+
+    let mut files = Vec::new();
+    for arg in &args[3..] {
+        collect_files(Path::new(arg), &mut files).expect("Failed to list tick files");
+    }
+
+    // merge all symbols into one stream ordered by exchange time
+    let mut ticks: Vec<TickData> = Vec::new();
+    for file in &files {
+        ticks.extend(recorder::read_ticks(file).unwrap_or_else(|e| panic!("Failed to read {:?}: {}", file, e)));
+    }
+    ticks.sort_by_key(|t| t.stamp);
+    println!("Loaded {} ticks from {} files", ticks.len(), files.len());
+
+    let ctx = zmq::Context::new();
+    let publisher = ctx.socket(zmq::PUB).expect("Failed to create PUB socket");
+    publisher.set_sndhwm(0).expect("Failed to set SNDHWM");
+    publisher.bind(&args[1]).expect("Failed to bind PUB socket");
+    // give subscribers time to connect before the first tick (ZMQ slow-joiner)
+    thread::sleep(Duration::from_secs(1));
+
+    let Some(first_stamp) = ticks.first().map(|t| t.stamp) else {
+        return;
+    };
+    let started = Instant::now();
+    for tick in &ticks {
+        if let Speed::Factor(factor) = speed {
+            let elapsed_ms = (tick.stamp - first_stamp) as f64 * 1000.0 / timeutil::STAMP_PER_SEC as f64;
+            let due = Duration::from_secs_f64(elapsed_ms / 1000.0 / factor);
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(tick as *const TickData as *const u8, mem::size_of::<TickData>()) };
+        if let Err(e) = publisher.send(bytes, 0) {
+            eprintln!("Error sending on PUB socket: {:?}", e);
+            break;
+        }
+    }
+
+    println!("Replayed {} ticks in {:?}", ticks.len(), started.elapsed());
+}
@@ -0,0 +1,81 @@
+//! Paper-trading counterparty: matches the engine's orders against the tick stream and
+//! publishes its fills, rejections and cancels back as execution reports.
+//!
+//! usage: simbroker <tick_uri> <order_uri> <report_uri>
+//!   e.g. simbroker ipc://@hq ipc://@orders ipc://@reports
+//! with the engine started with `enable_execution_reports("ipc://@reports", ...)`.
+use fustg_rs::codec;
+use fustg_rs::execution::Report;
+use fustg_rs::sim::{Request, SimBroker};
+use std::{env, process};
+
+fn publish(reporter: &zmq::Socket, reports: impl IntoIterator<Item = Report>) {
+    for report in reports {
+        match report {
+            Report::Fill(ref fill) => println!("fill seq={} {:?}", fill.seq, fill.fill),
+            Report::Status(ref status) if status.is_rejection() => println!("rejected seq={} {:?}", status.seq, status.order),
+            Report::Status(ref status) => println!("cancelled seq={} {:?}", status.seq, status.order),
+        }
+        if let Err(e) = reporter.send(report.as_bytes(), 0) {
+            eprintln!("Error sending on PUSH socket: {:?}", e);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        eprintln!("usage: {} <tick_uri> <order_uri> <report_uri>", args[0]);
+        process::exit(2);
+    }
+
+    let ctx = zmq::Context::new();
+    let subscriber = ctx.socket(zmq::SUB).expect("Failed to create SUB socket");
+    subscriber.set_rcvhwm(0).expect("Failed to set rcvhwm");
+    subscriber.connect(&args[1]).expect("Failed to connect SUB socket");
+    subscriber.set_subscribe(b"").expect("Failed to subscribe");
+    // the engine connects its PUSH/PULL sockets, so this side binds
+    let orders = ctx.socket(zmq::PULL).expect("Failed to create PULL socket");
+    orders.bind(&args[2]).expect("Failed to bind PULL socket");
+    let reporter = ctx.socket(zmq::PUSH).expect("Failed to create PUSH socket");
+    reporter.set_sndhwm(0).expect("Failed to set SNDHWM");
+    reporter.bind(&args[3]).expect("Failed to bind PUSH socket");
+
+    let mut sim = SimBroker::new();
+    let mut buf = [0u8; codec::MAX_TICK_FRAME];
+    loop {
+        let mut items = [subscriber.as_poll_item(zmq::POLLIN), orders.as_poll_item(zmq::POLLIN)];
+        if let Err(e) = zmq::poll(&mut items, -1) {
+            eprintln!("Error polling sockets: {:?}", e);
+            break;
+        }
+        if items[1].is_readable() {
+            match orders.recv_bytes(0).map(|frame| Request::decode(&frame)) {
+                Ok(Some(Request::Order(order))) => publish(&reporter, sim.on_order(&order)),
+                Ok(Some(Request::Cancel(cancel))) => match sim.on_cancel(&cancel) {
+                    Some(rest) => publish(&reporter, [Report::Status(rest)]),
+                    None => println!("cancel for order {} not resting", cancel.client_id),
+                },
+                Ok(None) => eprintln!("dropped order frame of unknown size"),
+                Err(e) => {
+                    eprintln!("Error receiving on PULL socket: {:?}", e);
+                    break;
+                }
+            }
+        }
+        if items[0].is_readable() {
+            match subscriber.recv_into(&mut buf, 0) {
+                Ok(n) => {
+                    // frames larger than the buffer were truncated and match no layout
+                    if let Some(tick) = buf.get(..n).and_then(codec::decode_tick) {
+                        publish(&reporter, sim.on_tick(&tick));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error receiving on SUB socket: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
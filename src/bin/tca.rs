@@ -0,0 +1,40 @@
+//! Post-trade transaction cost analysis of the fills in an engine's order store, against the
+//! orders in its audit log.
+//!
+//! usage: tca <orders.csv> <orders.db> <tick_dir> [vwap_window_secs]
+//!   e.g. tca data/orders.csv data/orders.db data/ticks 60
+use fustg_rs::store::OrderStore;
+use fustg_rs::{audit, tca, timeutil};
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        eprintln!("usage: {} <orders.csv> <orders.db> <tick_dir> [vwap_window_secs]", args[0]);
+        process::exit(2);
+    }
+    let window_secs: i64 = match args.get(4) {
+        Some(s) => s.parse().unwrap_or_else(|_| {
+            eprintln!("invalid vwap window {:?}", s);
+            process::exit(2);
+        }),
+        None => 60,
+    };
+
+    let records = audit::read_records(&args[1]).unwrap_or_else(|e| panic!("Failed to read {}: {}", args[1], e));
+    let store = OrderStore::open(&args[2], 0).unwrap_or_else(|e| panic!("Failed to open {}: {}", args[2], e));
+    let fills = store
+        .fills(None, i64::MIN)
+        .unwrap_or_else(|e| panic!("Failed to read fills from {}: {}", args[2], e));
+    let ticks = tca::load_ticks(&args[3], &records).expect("Failed to load recorded ticks");
+    let report = tca::analyze(&records, &fills, &ticks, window_secs * timeutil::STAMP_PER_SEC);
+
+    println!(
+        "{} fills, {} orders with lots unfilled, {} fills of orders not in the audit log",
+        report.fills.len(),
+        report.unfilled.len(),
+        report.unmatched
+    );
+    println!("costs in price units per lot (positive = worse than benchmark)\n");
+    print!("{}", report.render());
+}
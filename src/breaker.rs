@@ -0,0 +1,132 @@
+//! Equity circuit breaker: trips when an equity curve falls too far from its peak or loses
+//! too much within one trading day, and stays tripped until an operator re-arms it. The
+//! engine runs one per strategy and optionally one over the summed equity of all of them;
+//! a tripped breaker has its positions flattened and its opening orders blocked.
+use crate::calendar;
+use std::fmt;
+
+/// Thresholds a breaker trips at; a `None` threshold is not checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BreakerLimits {
+    /// largest fall from peak equity, as a fraction of the peak (e.g. 0.1)
+    pub max_drawdown: Option<f64>,
+    /// largest loss since the first equity of the trading day, night session included, in
+    /// account currency
+    pub max_daily_loss: Option<f64>,
+}
+
+/// Why a breaker tripped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trip {
+    Drawdown { drawdown: f64, limit: f64 },
+    DailyLoss { loss: f64, limit: f64 },
+}
+
+impl fmt::Display for Trip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trip::Drawdown { drawdown, limit } => write!(f, "drawdown {:.2}% over the {:.2}% limit", drawdown * 100.0, limit * 100.0),
+            Trip::DailyLoss { loss, limit } => write!(f, "daily loss {:.2} over the {:.2} limit", loss, limit),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DrawdownBreaker {
+    limits: BreakerLimits,
+    peak: f64,
+    /// trading day and equity at its first sample
+    day: i64,
+    day_start: f64,
+    tripped: Option<Trip>,
+}
+
+impl DrawdownBreaker {
+    pub fn new(limits: BreakerLimits) -> Self {
+        Self {
+            limits,
+            peak: f64::MIN,
+            day: i64::MIN,
+            day_start: 0.0,
+            tripped: None,
+        }
+    }
+
+    /// Feed the equity at `stamp`; returns the trip when this sample trips the breaker, and
+    /// `None` otherwise, including while it is already tripped.
+    pub fn on_equity(&mut self, stamp: i64, equity: f64) -> Option<Trip> {
+        self.peak = self.peak.max(equity);
+        let day = calendar::trading_day(stamp, calendar::is_weekday);
+        if day != self.day {
+            self.day = day;
+            self.day_start = equity;
+        }
+        if self.tripped.is_some() {
+            return None;
+        }
+        let drawdown = if self.peak > 0.0 { (self.peak - equity) / self.peak } else { 0.0 };
+        let loss = self.day_start - equity;
+        self.tripped = match self.limits {
+            BreakerLimits {
+                max_drawdown: Some(limit), ..
+            } if drawdown > limit => Some(Trip::Drawdown { drawdown, limit }),
+            BreakerLimits {
+                max_daily_loss: Some(limit), ..
+            } if loss > limit => Some(Trip::DailyLoss { loss, limit }),
+            _ => None,
+        };
+        self.tripped
+    }
+
+    pub fn tripped(&self) -> Option<Trip> {
+        self.tripped
+    }
+
+    /// Re-arm after a trip, measuring drawdown and the day's loss afresh from `equity`.
+    pub fn reset(&mut self, equity: f64) {
+        self.tripped = None;
+        self.peak = equity;
+        self.day_start = equity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_once_until_reset() {
+        let mut breaker = DrawdownBreaker::new(BreakerLimits {
+            max_drawdown: Some(0.1),
+            max_daily_loss: Some(500.0),
+        });
+        assert_eq!(breaker.on_equity(0, 10_000.0), None);
+        assert_eq!(breaker.on_equity(1, 10_400.0), None);
+        // 550 down on the day, but only 9.1% off the peak
+        let trip = breaker.on_equity(2, 9_450.0).unwrap();
+        assert_eq!(trip, Trip::DailyLoss { loss: 550.0, limit: 500.0 });
+        assert_eq!(breaker.on_equity(3, 9_000.0), None);
+        assert!(breaker.tripped().is_some());
+
+        breaker.reset(9_000.0);
+        assert_eq!(breaker.on_equity(4, 8_900.0), None);
+        // a new day starts the daily loss over; the drawdown from 9000 keeps counting
+        let next_day = 86_400_000;
+        assert_eq!(breaker.on_equity(next_day, 8_500.0), None);
+        assert!(matches!(breaker.on_equity(next_day + 1, 8_000.0), Some(Trip::Drawdown { .. })));
+    }
+
+    #[test]
+    fn the_night_session_counts_toward_the_next_day() {
+        let mut breaker = DrawdownBreaker::new(BreakerLimits {
+            max_drawdown: None,
+            max_daily_loss: Some(500.0),
+        });
+        // stamp 0 is 08:00 CST on a Thursday: 21:00 that evening, then 01:00 and 09:00 on Friday
+        let hour = 3_600_000;
+        assert_eq!(breaker.on_equity(13 * hour, 10_000.0), None);
+        assert_eq!(breaker.on_equity(17 * hour, 9_700.0), None);
+        // midnight does not start the day over: 600 down since the night session opened
+        assert!(matches!(breaker.on_equity(25 * hour, 9_400.0), Some(Trip::DailyLoss { .. })));
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-strategy resource budgets. Every `sample_every`-th `update` is timed on the worker
+//! thread's CPU clock and, when the binary installs `CountingAllocator`, its allocations
+//! counted; a strategy whose rolling average exceeds its budget is downgraded to every
+//! `subsample`-th tick so it cannot starve the strategies sharing its worker.
+use crate::operator::rolling::Mean;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceBudget {
+    /// average CPU time per `update`, in microseconds
+    pub max_cpu_us: f64,
+    /// average bytes allocated per `update`; only enforced with `CountingAllocator` installed
+    pub max_alloc_bytes: f64,
+    /// measure one `update` in this many
+    pub sample_every: u32,
+    /// samples averaged before the budget is enforced
+    pub window: usize,
+    /// a downgraded strategy sees one tick in this many
+    pub subsample: u32,
+}
+
+impl Default for ResourceBudget {
+    fn default() -> Self {
+        Self {
+            max_cpu_us: 200.0,
+            max_alloc_bytes: 64.0 * 1024.0,
+            sample_every: 16,
+            window: 64,
+            subsample: 10,
+        }
+    }
+}
+
+/// A strategy just went over budget and now runs on subsampled ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetViolation {
+    pub cpu_us: f64,
+    pub alloc_bytes: f64,
+    pub subsample: u32,
+}
+
+/// One strategy's measurements against its budget. Downgrades stick until the strategy is
+/// re-added, since a strategy that costs too much per tick costs the same on fewer ticks.
+pub struct BudgetMeter {
+    budget: ResourceBudget,
+    ticks: u64,
+    cpu_us: Mean,
+    alloc_bytes: Mean,
+    samples: usize,
+    downgraded: bool,
+}
+
+impl BudgetMeter {
+    pub fn new(budget: ResourceBudget) -> Self {
+        Self {
+            budget,
+            ticks: 0,
+            cpu_us: Mean::new(budget.window),
+            alloc_bytes: Mean::new(budget.window),
+            samples: 0,
+            downgraded: false,
+        }
+    }
+
+    pub fn is_downgraded(&self) -> bool {
+        self.downgraded
+    }
+
+    /// Count a tick for the strategy; false if a downgrade skips it.
+    pub fn admit(&mut self) -> bool {
+        self.ticks += 1;
+        !self.downgraded || self.ticks.is_multiple_of(self.budget.subsample.max(1) as u64)
+    }
+
+    /// Whether the `update` about to run should be measured.
+    pub fn should_sample(&self) -> bool {
+        self.ticks.is_multiple_of(self.budget.sample_every.max(1) as u64)
+    }
+
+    /// Record one measured `update`.
+    pub fn record(&mut self, cpu_ns: u64, alloc_bytes: u64) -> Option<BudgetViolation> {
+        let cpu_us = self.cpu_us.update(cpu_ns as f64 / 1e3);
+        let alloc_bytes = self.alloc_bytes.update(alloc_bytes as f64);
+        self.samples += 1;
+        if self.downgraded || self.samples < self.budget.window {
+            return None;
+        }
+        if cpu_us > self.budget.max_cpu_us || alloc_bytes > self.budget.max_alloc_bytes {
+            self.downgraded = true;
+            return Some(BudgetViolation {
+                cpu_us,
+                alloc_bytes,
+                subsample: self.budget.subsample,
+            });
+        }
+        None
+    }
+}
+
+/// CPU time the calling thread has used, in nanoseconds.
+#[cfg(target_os = "linux")]
+pub fn thread_cpu_ns() -> u64 {
+    // SAFETY: `ts` is owned by this frame and filled in by the kernel.
+    unsafe {
+        let mut ts: libc::timespec = std::mem::zeroed();
+        libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts);
+        ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+    }
+}
+
+/// Wall time stands in for CPU time where there is no per-thread CPU clock.
+#[cfg(not(target_os = "linux"))]
+pub fn thread_cpu_ns() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+thread_local! {
+    static ALLOCATED: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Bytes the calling thread has allocated so far; always 0 unless the binary declares
+/// `#[global_allocator] static ALLOC: CountingAllocator = CountingAllocator;`.
+pub fn thread_allocated() -> u64 {
+    ALLOCATED.try_with(Cell::get).unwrap_or(0)
+}
+
+/// The system allocator plus a per-thread count of allocated bytes.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // the thread-local may already be gone while a thread tears down
+        let _ = ALLOCATED.try_with(|n| n.set(n.get() + layout.size() as u64));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let grown = new_size.saturating_sub(layout.size()) as u64;
+        let _ = ALLOCATED.try_with(|n| n.set(n.get() + grown));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrades_a_strategy_over_budget() {
+        let mut meter = BudgetMeter::new(ResourceBudget {
+            max_cpu_us: 100.0,
+            max_alloc_bytes: 1024.0,
+            sample_every: 2,
+            window: 4,
+            subsample: 5,
+        });
+        let mut violation = None;
+        for tick in 1..=8 {
+            assert!(meter.admit());
+            if meter.should_sample() {
+                assert_eq!(tick % 2, 0);
+                violation = meter.record(300_000, 0);
+            }
+        }
+        assert_eq!(violation.map(|v| v.cpu_us), Some(300.0));
+        assert!(meter.is_downgraded());
+        assert_eq!((0..20).filter(|_| meter.admit()).count(), 4);
+        assert!(thread_cpu_ns() > 0);
+    }
+}
@@ -0,0 +1,215 @@
+//! Trading sessions per exchange and product, with holidays, loaded from `config/calendar.toml`.
+//!
+//! A night session belongs to the next trading day: it runs on the evening of a trading day
+//! (and past midnight into the next calendar day) unless the following weekday is a holiday,
+//! which is when the exchanges skip it.
+use crate::timeutil;
+use crate::types::SymbolType;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::{fs, path::Path};
+
+/// A continuous trading window in CST seconds of day, `[start, end)`. `end < start` wraps past
+/// midnight (e.g. 21:00–02:30).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Session {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Session {
+    /// Evening sessions start at or after 18:00.
+    pub fn is_night(&self) -> bool {
+        self.start >= 18 * 3600
+    }
+
+    fn parse(range: &[String; 2]) -> Result<Self> {
+        let secs = |hhmm: &str| -> Result<u32> {
+            let (h, m) = hhmm.split_once(':').ok_or_else(|| anyhow!("expected HH:MM, got {:?}", hhmm))?;
+            Ok(h.parse::<u32>()? * 3600 + m.parse::<u32>()? * 60)
+        };
+        Ok(Self {
+            start: secs(&range[0])?,
+            end: secs(&range[1])?,
+        })
+    }
+}
+
+/// What the engine does with ticks stamped outside their symbol's sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionFilter {
+    Dispatch,
+    /// dispatch, but count and log them
+    Flag,
+    Drop,
+}
+
+#[derive(Deserialize)]
+struct CalendarFile {
+    #[serde(default)]
+    holidays: Vec<u32>,
+    exchanges: HashMap<String, ExchangeFile>,
+}
+
+#[derive(Deserialize)]
+struct ExchangeFile {
+    sessions: Vec<[String; 2]>,
+    products: Vec<String>,
+    /// products whose sessions differ from the exchange's, e.g. a longer night session
+    #[serde(default)]
+    product_sessions: HashMap<String, Vec<[String; 2]>>,
+}
+
+pub struct TradingCalendar {
+    /// closed weekdays, `yyyymmdd`
+    holidays: HashSet<u32>,
+    /// product code (symbol prefix, e.g. `rb`) → sessions
+    products: HashMap<String, Vec<Session>>,
+}
+
+/// The trading day, as a `timeutil::local_day`, that a tick at `stamp` belongs to: evening and
+/// after-midnight ticks count for the next day `is_trading_day` accepts, the rest for their own
+/// date. Exchanges skip the night session before a holiday, so weekends are the only gap live
+/// data ever has to bridge; pass `TradingCalendar::is_trading_day` to bridge holidays as well.
+pub fn trading_day(stamp: i64, is_trading_day: impl Fn(i64) -> bool) -> i64 {
+    let (day, secs) = (timeutil::local_day(stamp), timeutil::local_secs_of_day(stamp));
+    let evening = match secs {
+        s if s >= 18 * 3600 => day,
+        s if s < 6 * 3600 => day - 1,
+        _ => return day,
+    };
+    let mut next = evening + 1;
+    // a calendar closed for weeks on end is a broken calendar, not a long holiday
+    while !is_trading_day(next) && next - evening < 31 {
+        next += 1;
+    }
+    next
+}
+
+/// Monday to Friday, for `trading_day` without a calendar.
+pub fn is_weekday(day: i64) -> bool {
+    !matches!(timeutil::weekday(day), 0 | 6)
+}
+
+/// The product code of a symbol: its leading letters, e.g. `rb` for `rb2505`, `SR` for `SR505`.
+pub fn product_of(symbol: &SymbolType) -> &str {
+    let s = symbol.as_str();
+    let end = s.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(s.len());
+    &s[..end]
+}
+
+impl TradingCalendar {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: CalendarFile = toml::from_str(text)?;
+        let parse_all = |ranges: &[[String; 2]]| ranges.iter().map(Session::parse).collect::<Result<Vec<_>>>();
+
+        let mut products = HashMap::new();
+        for (exchange, cfg) in &file.exchanges {
+            let sessions = parse_all(&cfg.sessions).map_err(|e| anyhow!("{}: {}", exchange, e))?;
+            for product in &cfg.products {
+                let own = match cfg.product_sessions.get(product) {
+                    Some(ranges) => parse_all(ranges).map_err(|e| anyhow!("{}.{}: {}", exchange, product, e))?,
+                    None => sessions.clone(),
+                };
+                products.insert(product.clone(), own);
+            }
+        }
+        Ok(Self {
+            holidays: file.holidays.into_iter().collect(),
+            products,
+        })
+    }
+
+    /// Weekday and not a holiday; `day` is a `timeutil::local_day`.
+    pub fn is_trading_day(&self, day: i64) -> bool {
+        is_weekday(day) && !self.holidays.contains(&timeutil::day_to_date(day))
+    }
+
+    /// Whether the night session starting on the evening of `day` runs.
+    fn has_night(&self, day: i64) -> bool {
+        let mut next = day + 1;
+        while matches!(timeutil::weekday(next), 0 | 6) {
+            next += 1;
+        }
+        self.is_trading_day(day) && !self.holidays.contains(&timeutil::day_to_date(next))
+    }
+
+    /// Whether `session` is the last day session of `symbol`, whose end closes the trading day.
+    pub fn closes_day(&self, symbol: &SymbolType, session: &Session) -> bool {
+        !session.is_night()
+            && self
+                .sessions(symbol)
+                .is_some_and(|all| all.iter().filter(|s| !s.is_night()).all(|s| s.end <= session.end))
+    }
+
+    pub fn sessions(&self, symbol: &SymbolType) -> Option<&[Session]> {
+        self.products.get(product_of(symbol)).map(Vec::as_slice)
+    }
+
+    /// The session `symbol` is trading in at `stamp`: `Some(None)` when closed, `None` when the
+    /// symbol's product is not in the calendar.
+    pub fn session_at(&self, symbol: &SymbolType, stamp: i64) -> Option<Option<Session>> {
+        let sessions = self.sessions(symbol)?;
+        let (day, secs) = (timeutil::local_day(stamp), timeutil::local_secs_of_day(stamp));
+        let open = sessions.iter().find(|s| {
+            if s.start < s.end {
+                let runs = if s.is_night() { self.has_night(day) } else { self.is_trading_day(day) };
+                runs && (s.start..s.end).contains(&secs)
+            } else {
+                // wraps midnight: the part after midnight belongs to the previous evening
+                (secs >= s.start && self.has_night(day)) || (secs < s.end && self.has_night(day - 1))
+            }
+        });
+        Some(open.copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(date_day: i64, hh: i64, mm: i64) -> i64 {
+        ((date_day * 86400 + hh * 3600 + mm * 60) - timeutil::CST_OFFSET_SECS) * timeutil::STAMP_PER_SEC
+    }
+
+    #[test]
+    fn shipped_calendar_sessions() {
+        let cal = TradingCalendar::load("config/calendar.toml").expect("calendar should parse");
+        let rb = SymbolType::from("rb2505");
+        let au = SymbolType::from("au2506");
+        let ic = SymbolType::from("IC2503");
+
+        // 2025-03-14 is a Friday
+        let friday = 20161;
+        assert_eq!(timeutil::day_to_date(friday), 20250314);
+        assert!(cal.session_at(&rb, stamp(friday, 9, 5)).unwrap().is_some());
+        assert!(cal.session_at(&rb, stamp(friday, 12, 0)).unwrap().is_none());
+        assert!(cal.session_at(&ic, stamp(friday, 9, 5)).unwrap().is_none());
+        // Friday night runs for Monday, and au trades past midnight into Saturday
+        assert!(cal.session_at(&rb, stamp(friday, 22, 0)).unwrap().is_some());
+        assert!(cal.session_at(&au, stamp(friday + 1, 1, 0)).unwrap().is_some());
+        assert!(cal.session_at(&rb, stamp(friday + 1, 9, 5)).unwrap().is_none());
+        assert!(cal.session_at(&SymbolType::from("xx2505"), stamp(friday, 9, 5)).is_none());
+
+        // no night session before the 2025-04-04 holiday
+        let thursday = friday + 20;
+        assert_eq!(timeutil::day_to_date(thursday), 20250403);
+        assert!(cal.session_at(&rb, stamp(thursday, 22, 0)).unwrap().is_none());
+
+        // Friday night and the small hours of Saturday trade for Monday
+        let monday = friday + 3;
+        assert_eq!(trading_day(stamp(friday, 9, 5), is_weekday), friday);
+        assert_eq!(trading_day(stamp(friday, 22, 0), is_weekday), monday);
+        assert_eq!(trading_day(stamp(friday + 1, 1, 0), is_weekday), monday);
+        // with holidays, Thursday night before the Friday 2025-04-04 holiday counts for Monday
+        assert_eq!(trading_day(stamp(thursday, 21, 5), |d| cal.is_trading_day(d)), thursday + 4);
+        let sessions = cal.sessions(&rb).unwrap();
+        let closes: Vec<bool> = sessions.iter().map(|s| cal.closes_day(&rb, s)).collect();
+        assert_eq!(closes.iter().filter(|&&c| c).count(), 1);
+    }
+}
@@ -0,0 +1,117 @@
+//! Where the engine, its workers and strategies read "now" from, in milliseconds since the
+//! epoch. Live, that is the wall clock; in a backtest or a test an `EventClock` advances
+//! only with the data, so timeouts and other time-based logic replay identically run after
+//! run. Strategies get their worker's clock through `Strategy::on_clock` and should read it
+//! instead of the system time or ad-hoc `tick.stamp` bookkeeping.
+use crate::timeutil;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+pub type SharedClock = Arc<dyn Clock>;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> i64;
+
+    /// Something stamped `stamp` was processed; event clocks move forward to it, wall clocks
+    /// ignore it.
+    fn on_event(&self, _stamp: i64) {}
+
+    /// The clock for one worker thread. Wall clocks are shared; an event clock is copied, so
+    /// each worker's time follows the ticks that worker has processed rather than what the
+    /// receive loop has already read ahead.
+    fn fork(&self) -> SharedClock;
+}
+
+/// The system clock, which is what a host disciplined by `ptp4l` + `phc2sys` serves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        timeutil::now_stamp()
+    }
+
+    fn fork(&self) -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+/// The system clock corrected by an offset someone else measures, e.g. a thread polling the
+/// PTP daemon's `offsetFromMaster` when the system clock itself is not disciplined.
+#[derive(Clone, Default)]
+pub struct OffsetClock {
+    offset_ms: Arc<AtomicI64>,
+}
+
+impl OffsetClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle to update the offset through; the clock reads `system + offset`.
+    pub fn offset(&self) -> Arc<AtomicI64> {
+        Arc::clone(&self.offset_ms)
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> i64 {
+        timeutil::now_stamp() + self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    fn fork(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+/// Time as of the latest event processed. It never runs backwards: an out-of-order stamp
+/// leaves it where it is.
+#[derive(Debug, Default)]
+pub struct EventClock {
+    stamp: AtomicI64,
+}
+
+impl EventClock {
+    pub fn new(start: i64) -> Self {
+        Self {
+            stamp: AtomicI64::new(start),
+        }
+    }
+}
+
+impl Clock for EventClock {
+    fn now(&self) -> i64 {
+        self.stamp.load(Ordering::Relaxed)
+    }
+
+    fn on_event(&self, stamp: i64) {
+        self.stamp.fetch_max(stamp, Ordering::Relaxed);
+    }
+
+    fn fork(&self) -> SharedClock {
+        Arc::new(EventClock::new(self.now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_clock_follows_the_data() {
+        let clock: SharedClock = Arc::new(EventClock::new(1_000));
+        clock.on_event(1_500);
+        clock.on_event(1_200);
+        assert_eq!(clock.now(), 1_500);
+
+        let worker = clock.fork();
+        clock.on_event(9_000);
+        assert_eq!(worker.now(), 1_500, "a worker's clock only moves with its own ticks");
+        worker.on_event(2_000);
+        assert_eq!((clock.now(), worker.now()), (9_000, 2_000));
+
+        let wall = SystemClock.fork();
+        wall.on_event(0);
+        assert!(wall.now() > 1_600_000_000_000);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::types::TickData;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::mem;
+use tracing::warn;
+
+pub mod flatbuffer;
+
+/// One revision of the C `TickData` struct as seen on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickLayout {
+    pub version: u8,
+    pub size: usize,
+}
+
+/// Every tick body layout we know how to read, oldest first.
+///
+/// Revisions only ever append fields, so an older frame is a prefix of `TickData` (missing
+/// fields keep their `Default` value) and a newer frame carries `TickData` as its prefix.
+/// v2 frames keep the v1 body and append an extension block; see `decode_frame`.
+pub const TICK_LAYOUTS: [TickLayout; 2] = [
+    // v0: published before the `adj` factor was appended
+    TickLayout {
+        version: 0,
+        size: mem::offset_of!(TickData, adj),
+    },
+    // v1: current layout
+    TickLayout {
+        version: 1,
+        size: mem::size_of::<TickData>(),
+    },
+];
+
+/// Opens the extension block a v2 frame appends to the current `TickData` body. The body
+/// stays first so SUB sockets keep filtering on the symbol prefix.
+pub const TICK_EXT_MAGIC: [u8; 4] = *b"FTX\0";
+/// Extension format this engine writes; readers accept any version and read the fields they
+/// know, since versions only ever append fields.
+pub const TICK_EXT_VERSION: u16 = 2;
+/// Longest extension block accepted after its header.
+pub const MAX_TICK_EXT_LEN: usize = 256;
+
+/// Header of the extension block: magic, version and the length of the fields that follow.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TickExtHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub len: u16,
+}
+
+/// Feed handler metadata carried by v2 frames, laid out as the extension's fields.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickMeta {
+    /// per-symbol sequence number assigned by the feed handler, counting up from 1
+    pub seq: u64,
+    /// when the feed handler received the tick from the exchange, in ns since the epoch
+    pub recv_ns: i64,
+}
+
+const EXT_HEADER_SIZE: usize = mem::size_of::<TickExtHeader>();
+
+/// Largest body of `TICK_LAYOUTS`.
+const MAX_TICK_BODY: usize = {
+    let mut max = 0;
+    let mut i = 0;
+    while i < TICK_LAYOUTS.len() {
+        if TICK_LAYOUTS[i].size > max {
+            max = TICK_LAYOUTS[i].size;
+        }
+        i += 1;
+    }
+    max
+};
+
+/// Largest frame the receive buffer must hold: a body with the longest extension.
+pub const MAX_TICK_FRAME: usize = MAX_TICK_BODY + EXT_HEADER_SIZE + MAX_TICK_EXT_LEN;
+
+/// Find the layout whose size matches a received frame.
+pub fn layout_for(frame_len: usize) -> Option<TickLayout> {
+    TICK_LAYOUTS.iter().copied().find(|l| l.size == frame_len)
+}
+
+/// Decode a raw frame of any known version into the current `TickData`, dropping a v2
+/// frame's metadata.
+pub fn decode_tick(frame: &[u8]) -> Option<TickData> {
+    decode_frame(frame).map(|(tick, _)| tick)
+}
+
+/// Decode a raw frame, with the feed handler's metadata when it is a v2 frame: the current
+/// body followed by a `TickExtHeader` whose `len` accounts for the rest of the frame.
+pub fn decode_frame(frame: &[u8]) -> Option<(TickData, Option<TickMeta>)> {
+    let body = mem::size_of::<TickData>();
+    if let Some(ext) = frame.get(body..)
+        && ext.len() >= EXT_HEADER_SIZE
+    {
+        // SAFETY: `TickExtHeader` is plain old data and the slice is long enough
+        let header = unsafe { std::ptr::read_unaligned(ext.as_ptr() as *const TickExtHeader) };
+        let fields = &ext[EXT_HEADER_SIZE..];
+        if header.magic != TICK_EXT_MAGIC || header.len as usize != fields.len() {
+            return None;
+        }
+        let mut meta = TickMeta::default();
+        let n = fields.len().min(mem::size_of::<TickMeta>());
+        unsafe {
+            std::ptr::copy_nonoverlapping(fields.as_ptr(), &mut meta as *mut TickMeta as *mut u8, n);
+        }
+        return Some((decode_body(&frame[..body])?, Some(meta)));
+    }
+    Some((decode_body(frame)?, None))
+}
+
+/// A v2 frame: `tick` followed by the extension carrying `meta`.
+pub fn encode_frame(tick: &TickData, meta: &TickMeta) -> Vec<u8> {
+    let header = TickExtHeader {
+        magic: TICK_EXT_MAGIC,
+        version: TICK_EXT_VERSION,
+        len: mem::size_of::<TickMeta>() as u16,
+    };
+    let mut frame = Vec::with_capacity(mem::size_of::<TickData>() + EXT_HEADER_SIZE + header.len as usize);
+    frame.extend_from_slice(pod_bytes(tick));
+    frame.extend_from_slice(pod_bytes(&header));
+    frame.extend_from_slice(pod_bytes(meta));
+    frame
+}
+
+/// The bytes of a `#[repr(C)]` plain-old-data value.
+fn pod_bytes<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn decode_body(frame: &[u8]) -> Option<TickData> {
+    let layout = layout_for(frame.len())?;
+    let mut tick = TickData::default();
+    let n = layout.size.min(mem::size_of::<TickData>());
+    // SAFELY overlay the known prefix; fields beyond `n` keep their defaults
+    unsafe {
+        let dst = &mut tick as *mut TickData as *mut u8;
+        std::ptr::copy_nonoverlapping(frame.as_ptr(), dst, n);
+    }
+    Some(tick)
+}
+
+/// How tick publishers encode `TickData`, e.g. `wire_format = "json"` in a config file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    /// the C struct in any layout of `TICK_LAYOUTS`
+    #[default]
+    Raw,
+    /// an object with `TickData`'s field names and the symbol as a string; missing fields
+    /// keep their `Default` value
+    Json,
+    /// the same object as MessagePack
+    Msgpack,
+    /// a `Tick` table of `schema/wire.fbs`
+    Flatbuffer,
+}
+
+impl WireFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "raw" => Some(WireFormat::Raw),
+            "json" => Some(WireFormat::Json),
+            "msgpack" => Some(WireFormat::Msgpack),
+            "flatbuffer" => Some(WireFormat::Flatbuffer),
+            _ => None,
+        }
+    }
+
+    /// Receive buffer size: raw frames have a known maximum, encoded objects do not.
+    pub fn max_frame(&self) -> usize {
+        match self {
+            WireFormat::Raw => MAX_TICK_FRAME,
+            WireFormat::Json | WireFormat::Msgpack | WireFormat::Flatbuffer => 64 * 1024,
+        }
+    }
+
+    pub fn decode(&self, frame: &[u8]) -> Option<TickData> {
+        self.decode_frame(frame).map(|(tick, _)| tick)
+    }
+
+    /// Decode with the feed handler's metadata, which only raw v2 frames carry.
+    pub fn decode_frame(&self, frame: &[u8]) -> Option<(TickData, Option<TickMeta>)> {
+        let tick = match self {
+            WireFormat::Raw => return decode_frame(frame),
+            WireFormat::Json => serde_json::from_slice(frame).ok(),
+            WireFormat::Msgpack => rmp_serde::from_slice(frame).ok(),
+            WireFormat::Flatbuffer => flatbuffer::decode_tick(frame),
+        };
+        tick.map(|tick| (tick, None))
+    }
+}
+
+/// Holds frames whose size matches no known layout, so they can be inspected instead of
+/// being misread as ticks.
+pub struct Quarantine {
+    counts: HashMap<usize, u64>,
+    samples: Vec<Vec<u8>>,
+    max_samples: usize,
+}
+
+impl Quarantine {
+    pub fn new(max_samples: usize) -> Self {
+        Self {
+            counts: HashMap::new(),
+            samples: Vec::with_capacity(max_samples),
+            max_samples,
+        }
+    }
+
+    /// Record an unknown frame. `n` is the real message size, which may exceed `frame.len()`
+    /// when the receive buffer truncated it.
+    pub fn admit(&mut self, frame: &[u8], n: usize) {
+        let count = self.counts.entry(n).or_insert(0);
+        *count += 1;
+        // log the first frame of every unknown size, then every 10000th
+        if *count == 1 || count.is_multiple_of(10000) {
+            warn!(frame_size = n, count = *count, "quarantined frame of unknown size");
+        }
+        if self.samples.len() < self.max_samples {
+            self.samples.push(frame.to_vec());
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// (frame size, count) pairs seen so far.
+    pub fn counts(&self) -> &HashMap<usize, u64> {
+        &self.counts
+    }
+
+    /// The first few quarantined frames, possibly truncated to the receive buffer.
+    pub fn samples(&self) -> &[Vec<u8>] {
+        &self.samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SymbolType;
+
+    fn as_bytes(tick: &TickData) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(tick as *const TickData as *const u8, mem::size_of::<TickData>()) }
+    }
+
+    #[test]
+    fn decodes_current_layout() {
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            last: 3500.0,
+            adj: 1.25,
+            ..Default::default()
+        };
+
+        let decoded = decode_tick(as_bytes(&tick)).expect("v1 frame should decode");
+        assert_eq!(decoded.symbol, tick.symbol);
+        assert_eq!(decoded.last, 3500.0);
+        assert_eq!(decoded.adj, 1.25);
+    }
+
+    #[test]
+    fn defaults_fields_missing_from_older_layout() {
+        let tick = TickData {
+            symbol: SymbolType::from("MA505"),
+            adj: 1.25,
+            bv5: 7,
+            ..Default::default()
+        };
+
+        let v0 = &as_bytes(&tick)[..TICK_LAYOUTS[0].size];
+        let decoded = decode_tick(v0).expect("v0 frame should decode");
+        assert_eq!(decoded.bv5, 7);
+        assert_eq!(decoded.adj, 1.0);
+    }
+
+    #[test]
+    fn decodes_json_and_msgpack() {
+        let json = br#"{"symbol": "rb2505", "stamp": 1741915800000, "last": 3500.0, "bp1": 3499.0, "bv1": 12}"#;
+        let tick = WireFormat::Json.decode(json).expect("json tick should decode");
+        assert_eq!(
+            (tick.symbol, tick.last, tick.bv1, tick.adj),
+            (SymbolType::from("rb2505"), 3500.0, 12, 1.0)
+        );
+
+        let value: serde_json::Value = serde_json::from_slice(json).unwrap();
+        let packed = rmp_serde::to_vec_named(&value).unwrap();
+        let tick = WireFormat::Msgpack.decode(&packed).expect("msgpack tick should decode");
+        assert_eq!((tick.stamp, tick.bp1), (1741915800000, 3499.0));
+        assert!(WireFormat::Json.decode(b"[1, 2]").is_none());
+    }
+
+    #[test]
+    fn reads_feed_metadata_from_v2_frames() {
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            last: 3500.0,
+            ..Default::default()
+        };
+        let meta = TickMeta {
+            seq: 42,
+            recv_ns: 1_741_915_800_000_000_123,
+        };
+        let frame = encode_frame(&tick, &meta);
+        assert!(frame.starts_with(&tick.symbol.0), "the body stays first for SUB filtering");
+        let (decoded, decoded_meta) = decode_frame(&frame).expect("v2 frame should decode");
+        assert_eq!((decoded.last, decoded_meta), (3500.0, Some(meta)));
+        assert_eq!(decode_tick(as_bytes(&tick)).map(|t| t.last), Some(3500.0));
+        assert_eq!(decode_frame(as_bytes(&tick)).map(|(_, meta)| meta), Some(None));
+
+        // a later version with more fields still yields the ones we know
+        let mut newer = frame.clone();
+        let len_at = mem::size_of::<TickData>() + 6;
+        newer[len_at..len_at + 2].copy_from_slice(&24u16.to_ne_bytes());
+        newer.extend_from_slice(&[0u8; 8]);
+        assert_eq!(decode_frame(&newer).and_then(|(_, meta)| meta), Some(meta));
+        // the length must account for the whole frame
+        assert!(decode_frame(&frame[..frame.len() - 1]).is_none());
+        assert!(frame.len() <= MAX_TICK_FRAME);
+    }
+
+    #[test]
+    fn rejects_unknown_sizes() {
+        assert!(decode_tick(&[0u8; 10]).is_none());
+
+        let mut quarantine = Quarantine::new(1);
+        quarantine.admit(&[0u8; 10], 10);
+        quarantine.admit(&[0u8; 12], 12);
+        assert_eq!(quarantine.total(), 2);
+        assert_eq!(quarantine.samples().len(), 1);
+    }
+}
@@ -0,0 +1,404 @@
+//! FlatBuffers encoding of `TickData` and `Order`, following `schema/wire.fbs`.
+//!
+//! Tables carry a vtable, so producers in any language can append fields without breaking
+//! older readers, and fields a producer leaves out read as their schema default. Frames are
+//! verified before they are read, so a malformed frame is rejected rather than misread.
+//! The field lists below must match the schema's declaration order; the tests check it.
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, SymbolType, TickData};
+use flatbuffers::{FlatBufferBuilder, Follow, ForwardsUOffset, InvalidFlatbuffer, Table, VOffsetT, Verifiable, Verifier};
+
+/// Field types the schema uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    String,
+    UByte,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    Double,
+}
+
+/// `table Tick`, in declaration order.
+const TICK_FIELDS: [(&str, Kind); 38] = [
+    ("symbol", Kind::String),
+    ("stamp", Kind::Long),
+    ("open", Kind::Double),
+    ("high", Kind::Double),
+    ("low", Kind::Double),
+    ("last", Kind::Double),
+    ("limit_down", Kind::Double),
+    ("limit_up", Kind::Double),
+    ("preclose", Kind::Double),
+    ("close", Kind::Double),
+    ("presettle", Kind::Double),
+    ("settle", Kind::Double),
+    ("preoi", Kind::Double),
+    ("oi", Kind::Double),
+    ("volume", Kind::Long),
+    ("amount", Kind::Double),
+    ("avgprice", Kind::Double),
+    ("ap1", Kind::Double),
+    ("ap2", Kind::Double),
+    ("ap3", Kind::Double),
+    ("ap4", Kind::Double),
+    ("ap5", Kind::Double),
+    ("bp1", Kind::Double),
+    ("bp2", Kind::Double),
+    ("bp3", Kind::Double),
+    ("bp4", Kind::Double),
+    ("bp5", Kind::Double),
+    ("av1", Kind::Int),
+    ("av2", Kind::Int),
+    ("av3", Kind::Int),
+    ("av4", Kind::Int),
+    ("av5", Kind::Int),
+    ("bv1", Kind::Int),
+    ("bv2", Kind::Int),
+    ("bv3", Kind::Int),
+    ("bv4", Kind::Int),
+    ("bv5", Kind::Int),
+    ("adj", Kind::Double),
+];
+
+/// `table Order`, in declaration order.
+const ORDER_FIELDS: [(&str, Kind); 10] = [
+    ("stg_name", Kind::String),
+    ("symbol", Kind::String),
+    ("timestamp", Kind::Long),
+    ("price", Kind::Double),
+    ("lots", Kind::UInt),
+    ("direction", Kind::UByte),
+    ("offset", Kind::UByte),
+    ("order_type", Kind::UByte),
+    ("client_id", Kind::ULong),
+    ("order_id", Kind::ULong),
+];
+
+/// vtable offset of the field at `index`.
+const fn slot_at(index: usize) -> VOffsetT {
+    // the vtable starts with its own size and the table's size
+    (4 + 2 * index) as VOffsetT
+}
+
+/// vtable offset of field `name`; used in const context, so a typo fails the build.
+const fn slot(fields: &[(&str, Kind)], name: &str) -> VOffsetT {
+    let mut i = 0;
+    while i < fields.len() {
+        if fields[i].0.len() == name.len() {
+            let (a, b) = (fields[i].0.as_bytes(), name.as_bytes());
+            let mut j = 0;
+            while j < a.len() && a[j] == b[j] {
+                j += 1;
+            }
+            if j == a.len() {
+                return slot_at(i);
+            }
+        }
+        i += 1;
+    }
+    panic!("field is not in the schema");
+}
+
+macro_rules! tick_slot {
+    ($name:literal) => {
+        const { slot(&TICK_FIELDS, $name) }
+    };
+}
+
+macro_rules! order_slot {
+    ($name:literal) => {
+        const { slot(&ORDER_FIELDS, $name) }
+    };
+}
+
+fn verify_fields(v: &mut Verifier, pos: usize, fields: &'static [(&'static str, Kind)]) -> Result<(), InvalidFlatbuffer> {
+    let mut table = v.visit_table(pos)?;
+    for (i, &(name, kind)) in fields.iter().enumerate() {
+        let slot = slot_at(i);
+        table = match kind {
+            Kind::String => table.visit_field::<ForwardsUOffset<&str>>(name, slot, false)?,
+            Kind::UByte => table.visit_field::<u8>(name, slot, false)?,
+            Kind::Int => table.visit_field::<i32>(name, slot, false)?,
+            Kind::UInt => table.visit_field::<u32>(name, slot, false)?,
+            Kind::Long => table.visit_field::<i64>(name, slot, false)?,
+            Kind::ULong => table.visit_field::<u64>(name, slot, false)?,
+            Kind::Double => table.visit_field::<f64>(name, slot, false)?,
+        };
+    }
+    table.finish();
+    Ok(())
+}
+
+/// A verified `Order` table if `ORDER`, else a `Tick` table.
+struct Root<'a, const ORDER: bool>(Table<'a>);
+
+impl<'a, const ORDER: bool> Follow<'a> for Root<'a, ORDER> {
+    type Inner = Self;
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self {
+        Root(unsafe { Table::new(buf, loc) })
+    }
+}
+
+impl<const ORDER: bool> Verifiable for Root<'_, ORDER> {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+        verify_fields(v, pos, if ORDER { &ORDER_FIELDS } else { &TICK_FIELDS })
+    }
+}
+
+impl<'a, const ORDER: bool> Root<'a, ORDER> {
+    fn get<T: Follow<'a, Inner = T> + Copy + 'a>(&self, slot: VOffsetT, default: T) -> T {
+        // SAFETY: the buffer was verified against the field list `slot` comes from
+        unsafe { self.0.get::<T>(slot, Some(default)) }.unwrap_or(default)
+    }
+
+    fn get_str(&self, slot: VOffsetT) -> &'a str {
+        unsafe { self.0.get::<ForwardsUOffset<&str>>(slot, Some("")) }.unwrap_or("")
+    }
+}
+
+/// Encode `tick` as a `Tick` table; fields equal to their default are left out.
+pub fn encode_tick<'b>(fbb: &'b mut FlatBufferBuilder<'static>, tick: &TickData) -> &'b [u8] {
+    fbb.reset();
+    let symbol = fbb.create_string(tick.symbol.as_str());
+    let start = fbb.start_table();
+    fbb.push_slot_always(tick_slot!("symbol"), symbol);
+    fbb.push_slot(tick_slot!("stamp"), tick.stamp, 0);
+    fbb.push_slot(tick_slot!("volume"), tick.volume, 0);
+    let mut copy = *tick;
+    for (slot, value) in tick_doubles(&mut copy) {
+        fbb.push_slot(slot, *value, 0.0);
+    }
+    fbb.push_slot(tick_slot!("adj"), tick.adj, 1.0);
+    for (slot, value) in tick_ints(&mut copy) {
+        fbb.push_slot(slot, *value, 0);
+    }
+    let root = fbb.end_table(start);
+    fbb.finish_minimal(root);
+    fbb.finished_data()
+}
+
+/// Decode a verified `Tick` table; `None` if the frame is not one.
+pub fn decode_tick(frame: &[u8]) -> Option<TickData> {
+    let table = flatbuffers::root::<Root<false>>(frame).ok()?;
+    let mut tick = TickData {
+        symbol: SymbolType::from(table.get_str(tick_slot!("symbol"))),
+        stamp: table.get(tick_slot!("stamp"), 0),
+        volume: table.get(tick_slot!("volume"), 0),
+        adj: table.get(tick_slot!("adj"), 1.0),
+        ..Default::default()
+    };
+    for (slot, value) in tick_doubles(&mut tick) {
+        *value = table.get(slot, 0.0);
+    }
+    for (slot, value) in tick_ints(&mut tick) {
+        *value = table.get(slot, 0);
+    }
+    Some(tick)
+}
+
+/// Every `double` field of `Tick` but `adj`, whose default is not zero.
+fn tick_doubles(t: &mut TickData) -> [(VOffsetT, &mut f64); 24] {
+    [
+        (tick_slot!("open"), &mut t.open),
+        (tick_slot!("high"), &mut t.high),
+        (tick_slot!("low"), &mut t.low),
+        (tick_slot!("last"), &mut t.last),
+        (tick_slot!("limit_down"), &mut t.limit_down),
+        (tick_slot!("limit_up"), &mut t.limit_up),
+        (tick_slot!("preclose"), &mut t.preclose),
+        (tick_slot!("close"), &mut t.close),
+        (tick_slot!("presettle"), &mut t.presettle),
+        (tick_slot!("settle"), &mut t.settle),
+        (tick_slot!("preoi"), &mut t.preoi),
+        (tick_slot!("oi"), &mut t.oi),
+        (tick_slot!("amount"), &mut t.amount),
+        (tick_slot!("avgprice"), &mut t.avgprice),
+        (tick_slot!("ap1"), &mut t.ap1),
+        (tick_slot!("ap2"), &mut t.ap2),
+        (tick_slot!("ap3"), &mut t.ap3),
+        (tick_slot!("ap4"), &mut t.ap4),
+        (tick_slot!("ap5"), &mut t.ap5),
+        (tick_slot!("bp1"), &mut t.bp1),
+        (tick_slot!("bp2"), &mut t.bp2),
+        (tick_slot!("bp3"), &mut t.bp3),
+        (tick_slot!("bp4"), &mut t.bp4),
+        (tick_slot!("bp5"), &mut t.bp5),
+    ]
+}
+
+fn tick_ints(t: &mut TickData) -> [(VOffsetT, &mut i32); 10] {
+    [
+        (tick_slot!("av1"), &mut t.av1),
+        (tick_slot!("av2"), &mut t.av2),
+        (tick_slot!("av3"), &mut t.av3),
+        (tick_slot!("av4"), &mut t.av4),
+        (tick_slot!("av5"), &mut t.av5),
+        (tick_slot!("bv1"), &mut t.bv1),
+        (tick_slot!("bv2"), &mut t.bv2),
+        (tick_slot!("bv3"), &mut t.bv3),
+        (tick_slot!("bv4"), &mut t.bv4),
+        (tick_slot!("bv5"), &mut t.bv5),
+    ]
+}
+
+/// Encode `order` as an `Order` table, for gateways that read FlatBuffers.
+pub fn encode_order<'b>(fbb: &'b mut FlatBufferBuilder<'static>, order: &Order) -> &'b [u8] {
+    fbb.reset();
+    let stg_name = fbb.create_string(order.stg_name.as_str());
+    let symbol = fbb.create_string(order.symbol.as_str());
+    let start = fbb.start_table();
+    fbb.push_slot_always(order_slot!("stg_name"), stg_name);
+    fbb.push_slot_always(order_slot!("symbol"), symbol);
+    fbb.push_slot(order_slot!("timestamp"), order.timestamp, 0);
+    fbb.push_slot(order_slot!("price"), order.price, 0.0);
+    fbb.push_slot(order_slot!("lots"), order.lots, 0);
+    fbb.push_slot(order_slot!("direction"), order.direction as u8, 0);
+    fbb.push_slot(order_slot!("offset"), order.offset as u8, 0);
+    fbb.push_slot(order_slot!("order_type"), order.order_type as u8, 0);
+    fbb.push_slot(order_slot!("client_id"), order.client_id, 0);
+    fbb.push_slot(order_slot!("order_id"), order.order_id, 0);
+    let root = fbb.end_table(start);
+    fbb.finish_minimal(root);
+    fbb.finished_data()
+}
+
+/// Decode a verified `Order` table; `None` if the frame is not one or an enum is out of range.
+pub fn decode_order(frame: &[u8]) -> Option<Order> {
+    let table = flatbuffers::root::<Root<true>>(frame).ok()?;
+    let direction = match table.get(order_slot!("direction"), 0u8) {
+        0 => DirectionType::BUY,
+        1 => DirectionType::SELL,
+        _ => return None,
+    };
+    let offset = match table.get(order_slot!("offset"), 0u8) {
+        0 => OffsetFlagType::OPEN,
+        1 => OffsetFlagType::CLOSE,
+        _ => return None,
+    };
+    let order_type = match table.get(order_slot!("order_type"), 0u8) {
+        0 => OrderType::LIMIT,
+        1 => OrderType::MARKET,
+        2 => OrderType::FAK,
+        3 => OrderType::FOK,
+        _ => return None,
+    };
+    Some(Order {
+        stg_name: NameType::from(table.get_str(order_slot!("stg_name"))),
+        symbol: SymbolType::from(table.get_str(order_slot!("symbol"))),
+        timestamp: table.get(order_slot!("timestamp"), 0),
+        price: table.get(order_slot!("price"), 0.0),
+        lots: table.get(order_slot!("lots"), 0),
+        direction,
+        offset,
+        order_type,
+        client_id: table.get(order_slot!("client_id"), 0),
+        order_id: table.get(order_slot!("order_id"), 0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (field, type) pairs of `table <name>` in the schema file.
+    fn schema_fields(name: &str) -> Vec<(String, String)> {
+        let schema = include_str!("../../schema/wire.fbs");
+        let body = schema.split(&format!("table {} {{", name)).nth(1).unwrap();
+        let body = &body[..body.find('}').unwrap()];
+        body.lines()
+            .filter_map(|line| line.trim().strip_suffix(';'))
+            .map(|field| {
+                let (name, ty) = field.split_once(':').unwrap();
+                let ty = ty.split('=').next().unwrap().trim();
+                (name.trim().to_string(), ty.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn field_lists_match_schema() {
+        for (table, fields) in [("Tick", &TICK_FIELDS[..]), ("Order", &ORDER_FIELDS[..])] {
+            let expected: Vec<(String, String)> = fields
+                .iter()
+                .map(|&(name, kind)| {
+                    let ty = match kind {
+                        Kind::String => "string",
+                        Kind::Int => "int",
+                        Kind::UInt => "uint",
+                        Kind::Long => "long",
+                        Kind::ULong => "ulong",
+                        Kind::Double => "double",
+                        // enums are declared `: ubyte` and referenced by name
+                        Kind::UByte => return (name.to_string(), "ubyte".to_string()),
+                    };
+                    (name.to_string(), ty.to_string())
+                })
+                .collect();
+            let enums = ["Direction", "OffsetFlag", "OrderType"];
+            let actual: Vec<(String, String)> = schema_fields(table)
+                .into_iter()
+                .map(|(name, ty)| {
+                    if enums.contains(&ty.as_str()) {
+                        (name, "ubyte".into())
+                    } else {
+                        (name, ty)
+                    }
+                })
+                .collect();
+            assert_eq!(actual, expected, "table {}", table);
+        }
+    }
+
+    #[test]
+    fn round_trips_and_defaults() {
+        let mut fbb = FlatBufferBuilder::new();
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp: 1741915800000,
+            last: 3500.0,
+            bp5: 3495.0,
+            bv5: 9,
+            volume: 42,
+            adj: 1.25,
+            ..Default::default()
+        };
+        let decoded = decode_tick(encode_tick(&mut fbb, &tick)).expect("tick should decode");
+        assert_eq!(
+            (
+                decoded.symbol,
+                decoded.stamp,
+                decoded.last,
+                decoded.bp5,
+                decoded.bv5,
+                decoded.volume,
+                decoded.adj
+            ),
+            (tick.symbol, tick.stamp, 3500.0, 3495.0, 9, 42, 1.25)
+        );
+        // a producer that leaves `adj` out gets the schema default
+        let plain = decode_tick(encode_tick(&mut fbb, &TickData::default())).unwrap();
+        assert_eq!(plain.adj, 1.0);
+
+        let order = Order {
+            stg_name: NameType::from("aberration"),
+            symbol: SymbolType::from("MA505"),
+            timestamp: 7,
+            price: 2500.0,
+            lots: 3,
+            direction: DirectionType::SELL,
+            offset: OffsetFlagType::CLOSE,
+            order_type: OrderType::FAK,
+            client_id: 11,
+            order_id: 1 << 48 | 5,
+        };
+        let decoded = decode_order(encode_order(&mut fbb, &order)).expect("order should decode");
+        assert_eq!(decoded.stg_name.as_str(), "aberration");
+        assert_eq!(
+            (decoded.direction, decoded.order_type, decoded.client_id, decoded.order_id),
+            (DirectionType::SELL, OrderType::FAK, 11, 1 << 48 | 5)
+        );
+
+        assert!(decode_tick(&[0xff; 12]).is_none());
+    }
+}
@@ -0,0 +1,419 @@
+//! Signal combination: a `CompositeStrategy` runs several child strategies on the same ticks
+//! and trades one net position decided from theirs, instead of each child trading its own.
+//! A child's orders never leave the composite; they only move the position that child would
+//! hold (a child trading by `target_position` holds its target), and the children's
+//! positions are combined into the composite's target:
+//!
+//! - `Combine::Vote`: each child votes long, short or flat with its weight; the side with
+//!   the larger weight is held for `lots` lots, and a tie is flat.
+//! - `Combine::WeightedSum`: the weighted sum of the children's positions, rounded to lots.
+//!
+//! The engine then sees only the composite's orders, netted: children pulling in opposite
+//! directions cost nothing instead of a round trip each. The composite holds what its orders
+//! filled, and orders for the rest of the target once those in flight are filled or gone;
+//! the children hear about the composite's fills, cancels, rejections and limit locks.
+//!
+//! Parameters and toggles addressed as `<child>.<name>` go to the child named `<child>`;
+//! `<child>.weight` sets its weight.
+use crate::calendar::Session;
+use crate::clock::SharedClock;
+use crate::conduct::ConductWarning;
+use crate::sizing::SizingContext;
+use crate::strategy::{EngineContext, Orders, Strategy};
+use crate::types::{DirectionType, LimitLock, NameType, OffsetFlagType, Order, OrderType, SymbolType, TickData};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combine {
+    /// weighted majority of the children's sides, held for this many lots
+    Vote {
+        lots: u32,
+    },
+    WeightedSum,
+}
+
+struct Child {
+    strategy: Box<dyn Strategy>,
+    weight: f64,
+    /// signed lots the child believes it holds
+    position: i64,
+}
+
+pub struct CompositeStrategy {
+    name: NameType,
+    combine: Combine,
+    children: Vec<Child>,
+    /// signed lots the composite's fills add up to
+    held: i64,
+    /// signed lots of its orders not yet filled, cancelled or rejected
+    working: i64,
+}
+
+impl CompositeStrategy {
+    pub fn new(name: &str, combine: Combine) -> Self {
+        Self {
+            name: NameType::from(name),
+            combine,
+            children: Vec::new(),
+            held: 0,
+            working: 0,
+        }
+    }
+
+    /// Add a child with `weight`; children trade the composite's symbol.
+    pub fn with_child(mut self, strategy: Box<dyn Strategy>, weight: f64) -> Self {
+        self.children.push(Child {
+            strategy,
+            weight,
+            position: 0,
+        });
+        self
+    }
+
+    /// The position the children currently call for.
+    pub fn target(&self) -> i64 {
+        match self.combine {
+            Combine::Vote { lots } => {
+                let vote: f64 = self.children.iter().map(|c| c.weight * c.position.signum() as f64).sum();
+                if vote > 0.0 {
+                    lots as i64
+                } else if vote < 0.0 {
+                    -(lots as i64)
+                } else {
+                    0
+                }
+            }
+            Combine::WeightedSum => self.children.iter().map(|c| c.weight * c.position as f64).sum::<f64>().round() as i64,
+        }
+    }
+
+    fn child_mut(&mut self, name: &str) -> Option<&mut Child> {
+        self.children.iter_mut().find(|c| c.strategy.name().as_str() == name)
+    }
+
+    fn order(&self, tick: &TickData, direction: DirectionType, offset: OffsetFlagType, lots: i64) -> Order {
+        Order {
+            stg_name: self.name,
+            symbol: tick.symbol,
+            timestamp: tick.stamp,
+            price: match direction {
+                DirectionType::BUY => tick.ap1,
+                DirectionType::SELL => tick.bp1,
+            },
+            lots: lots as u32,
+            direction,
+            offset,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        }
+    }
+}
+
+/// The signed change `order` makes to a position.
+fn signed_lots(order: &Order) -> i64 {
+    match order.direction {
+        DirectionType::BUY => order.lots as i64,
+        DirectionType::SELL => -(order.lots as i64),
+    }
+}
+
+impl Strategy for CompositeStrategy {
+    fn name(&self) -> NameType {
+        self.name
+    }
+
+    fn update(&mut self, tick: &TickData) -> Orders {
+        for child in &mut self.children {
+            if let Some(target) = child.strategy.target_position(tick) {
+                child.position = target as i64;
+                continue;
+            }
+            for order in child.strategy.update(tick) {
+                child.position += signed_lots(&order);
+            }
+        }
+        // trade from where the orders in flight will leave the composite
+        let (held, target) = (self.held + self.working, self.target());
+        let mut orders = Orders::new();
+        if held == target {
+            return orders;
+        }
+        // close what is held on the wrong side or beyond the target, then open the rest
+        if held > 0 && target < held {
+            orders.push(self.order(tick, DirectionType::SELL, OffsetFlagType::CLOSE, held - target.max(0)));
+        } else if held < 0 && target > held {
+            orders.push(self.order(tick, DirectionType::BUY, OffsetFlagType::CLOSE, target.min(0) - held));
+        }
+        if target > 0 && target > held.max(0) {
+            orders.push(self.order(tick, DirectionType::BUY, OffsetFlagType::OPEN, target - held.max(0)));
+        } else if target < 0 && target < held.min(0) {
+            orders.push(self.order(tick, DirectionType::SELL, OffsetFlagType::OPEN, held.min(0) - target));
+        }
+        self.working += target - held;
+        orders
+    }
+
+    fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+        let Some((child, param)) = name.split_once('.') else {
+            return Err(format!("unknown parameter {:?}, expected <child>.<name>", name));
+        };
+        let Some(child) = self.child_mut(child) else {
+            return Err(format!("no child strategy {:?}", child));
+        };
+        if param == "weight" {
+            // a NaN or infinite weight would turn the whole target flat
+            if !value.is_finite() {
+                return Err(format!("{} must be finite, got {}", name, value));
+            }
+            child.weight = value;
+            return Ok(());
+        }
+        child.strategy.on_param_update(param, value)
+    }
+
+    fn on_toggle(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        let Some((child, toggle)) = name.split_once('.') else {
+            return Err(format!("unknown toggle {:?}, expected <child>.<name>", name));
+        };
+        match self.child_mut(child) {
+            Some(child) => child.strategy.on_toggle(toggle, enabled),
+            None => Err(format!("no child strategy {:?}", child)),
+        }
+    }
+
+    fn on_account(&mut self, ctx: &SizingContext) {
+        for child in &mut self.children {
+            child.strategy.on_account(ctx);
+        }
+    }
+
+    fn on_conduct_warning(&mut self, warning: &ConductWarning) {
+        for child in &mut self.children {
+            child.strategy.on_conduct_warning(warning);
+        }
+    }
+
+    fn references(&self) -> Vec<SymbolType> {
+        let mut symbols: Vec<SymbolType> = Vec::new();
+        for symbol in self.children.iter().flat_map(|c| c.strategy.references()) {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+        symbols
+    }
+
+    fn on_reference_tick(&mut self, tick: &TickData) {
+        for child in &mut self.children {
+            child.strategy.on_reference_tick(tick);
+        }
+    }
+
+    fn on_limit_lock(&mut self, lock: Option<LimitLock>) {
+        for child in &mut self.children {
+            child.strategy.on_limit_lock(lock);
+        }
+    }
+
+    fn on_order_cancelled(&mut self, order: &Order) {
+        // `order` carries the lots that were still open
+        self.working -= signed_lots(order);
+        for child in &mut self.children {
+            child.strategy.on_order_cancelled(order);
+        }
+    }
+
+    fn on_order_rejected(&mut self, order: &Order, reason: &str) {
+        self.working -= signed_lots(order);
+        for child in &mut self.children {
+            child.strategy.on_order_rejected(order, reason);
+        }
+    }
+
+    fn on_fill(&mut self, fill: &Order, remaining: u32) {
+        self.held += signed_lots(fill);
+        self.working -= signed_lots(fill);
+        for child in &mut self.children {
+            child.strategy.on_fill(fill, remaining);
+        }
+    }
+
+    fn on_stop_triggered(&mut self, order: &Order) {
+        // the composite's whole position is gone; children start over from flat
+        self.held = 0;
+        for child in &mut self.children {
+            child.position = 0;
+            child.strategy.on_stop_triggered(order);
+        }
+    }
+
+    fn on_clock(&mut self, clock: SharedClock) {
+        for child in &mut self.children {
+            child.strategy.on_clock(clock.clone());
+        }
+    }
+
+    /// Children start flat; the restored position is netted to their target on the next tick.
+    fn on_resume(&mut self, long_lots: u32, short_lots: u32) {
+        self.held = long_lots as i64 - short_lots as i64;
+        self.working = 0;
+    }
+
+    fn on_start(&mut self, ctx: &EngineContext) {
+        for child in &mut self.children {
+            child.strategy.on_start(ctx);
+        }
+    }
+
+    fn on_stop(&mut self) {
+        for child in &mut self.children {
+            child.strategy.on_stop();
+        }
+    }
+
+    fn on_day_open(&mut self) {
+        for child in &mut self.children {
+            child.strategy.on_day_open();
+        }
+    }
+
+    fn on_day_close(&mut self) {
+        for child in &mut self.children {
+            child.strategy.on_day_close();
+        }
+    }
+
+    fn on_session_start(&mut self, session: &Session) {
+        for child in &mut self.children {
+            child.strategy.on_session_start(session);
+        }
+    }
+
+    fn on_session_end(&mut self, session: &Session) {
+        for child in &mut self.children {
+            child.strategy.on_session_end(session);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::smallvec;
+    use crate::testing::{StrategyHarness, ticks};
+
+    /// Moves its position through `path`, one step per tick.
+    struct Path {
+        name: &'static str,
+        path: Vec<i64>,
+        step: usize,
+        held: i64,
+    }
+
+    impl Path {
+        fn boxed(name: &'static str, path: &[i64]) -> Box<dyn Strategy> {
+            Box::new(Self {
+                name,
+                path: path.to_vec(),
+                step: 0,
+                held: 0,
+            })
+        }
+    }
+
+    impl Strategy for Path {
+        fn name(&self) -> NameType {
+            NameType::from(self.name)
+        }
+
+        fn update(&mut self, tick: &TickData) -> Orders {
+            let target = self.path.get(self.step).copied().unwrap_or(self.held);
+            self.step += 1;
+            let change = target - self.held;
+            self.held = target;
+            if change == 0 {
+                return Orders::new();
+            }
+            // offsets do not matter to the composite, only the direction
+            let direction = if change > 0 { DirectionType::BUY } else { DirectionType::SELL };
+            smallvec![Order {
+                stg_name: self.name(),
+                symbol: tick.symbol,
+                timestamp: tick.stamp,
+                price: tick.last,
+                lots: change.unsigned_abs() as u32,
+                direction,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            }]
+        }
+
+        fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+            match name {
+                "step" => {
+                    self.step = value as usize;
+                    Ok(())
+                }
+                _ => Err(format!("unknown parameter {:?}", name)),
+            }
+        }
+    }
+
+    #[test]
+    fn nets_children_into_one_position() {
+        let prices = [3500.0; 4];
+        let ticks = ticks("rb2505", &prices, 0, 500, 1.0);
+        let children = || [Path::boxed("trend", &[1, 1, 1, 0]), Path::boxed("revert", &[0, -2, -2, -2])];
+
+        let [trend, revert] = children();
+        let mut vote = StrategyHarness::new(
+            CompositeStrategy::new("combo", Combine::Vote { lots: 3 })
+                .with_child(trend, 1.0)
+                .with_child(revert, 1.0),
+        );
+        vote.run(&ticks[..2]);
+        // trend alone says long; trend against revert ties flat
+        vote.assert_positions(&[3, 0]);
+        vote.strategy_mut().on_param_update("revert.weight", 2.0).unwrap();
+        vote.run(&ticks[2..]);
+        vote.assert_positions(&[3, 0, -3]);
+        assert!(vote.strategy_mut().on_param_update("missing.weight", 1.0).is_err());
+        assert!(vote.strategy_mut().on_param_update("trend.step", 0.0).is_ok());
+
+        let [trend, revert] = children();
+        let mut sum = StrategyHarness::new(
+            CompositeStrategy::new("combo", Combine::WeightedSum)
+                .with_child(trend, 2.0)
+                .with_child(revert, 0.5),
+        );
+        let orders = sum.run(&ticks);
+        // 2, then 2 - 1 = 1, then 1, then -1: the reversal is a close and an open on one tick
+        sum.assert_positions(&[2, 1, 0, -1]);
+        assert!(orders.iter().all(|o| o.stg_name.as_str() == "combo"));
+    }
+
+    #[test]
+    fn holds_what_fills_and_orders_again_after_a_cancel() {
+        let ticks = ticks("rb2505", &[3500.0; 3], 0, 500, 1.0);
+        let mut combo = CompositeStrategy::new("combo", Combine::Vote { lots: 2 }).with_child(Path::boxed("trend", &[1]), 1.0);
+        let sent = combo.update(&ticks[0]);
+        assert_eq!(sent.len(), 1);
+        // nothing more while the order is in flight
+        assert!(combo.update(&ticks[1]).is_empty());
+        combo.on_fill(&Order { lots: 1, ..sent[0] }, 1);
+        combo.on_order_cancelled(&Order { lots: 1, ..sent[0] });
+        let again = combo.update(&ticks[2]);
+        assert_eq!(
+            (again[0].direction, again[0].offset, again[0].lots),
+            (DirectionType::BUY, OffsetFlagType::OPEN, 1)
+        );
+        combo.on_order_rejected(&again[0], "send failed");
+        assert_eq!(combo.update(&ticks[2]).len(), 1);
+
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(combo.on_param_update("trend.weight", bad).is_err());
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Exchange conduct rules: per-strategy order-to-trade and cancel ratios, checked against
+//! limits modelled on CFFEX's abnormal-trading thresholds, plus its message (申报) fees.
+//!
+//! Counters are per strategy and trading day, so a night session counts toward the next day,
+//! as the exchange counts it.
+use crate::{calendar, timeutil};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConductLimits {
+    /// cancels per day before the exchange flags the account
+    pub max_cancels: u32,
+    /// a cancel of at least this many lots counts as a large cancel
+    pub large_cancel_lots: u32,
+    pub max_large_cancels: u32,
+    /// orders / trades; only enforced once `ratio_min_orders` orders were sent that day
+    pub max_order_to_trade: f64,
+    pub ratio_min_orders: u32,
+    /// orders + cancels per day that are free of message fees
+    pub free_messages: u32,
+    pub fee_per_message: f64,
+    /// fraction of a limit at which the strategy is warned, e.g. 0.8
+    pub warn_at: f64,
+}
+
+impl ConductLimits {
+    /// CFFEX defaults: 500 cancels and 50 cancels of ≥ 800 lots per day. The order-to-trade
+    /// and message-fee thresholds vary by product and notice; check them before relying on these.
+    pub fn cffex() -> Self {
+        Self {
+            max_cancels: 500,
+            large_cancel_lots: 800,
+            max_large_cancels: 50,
+            max_order_to_trade: 50.0,
+            ratio_min_orders: 100,
+            free_messages: 4000,
+            fee_per_message: 1.0,
+            warn_at: 0.8,
+        }
+    }
+}
+
+/// One trading day's order flow of a strategy.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OrderFlowStats {
+    /// the trading day, yyyymmdd
+    pub date: u32,
+    pub orders: u32,
+    pub cancels: u32,
+    pub large_cancels: u32,
+    pub trades: u32,
+    /// message fees charged so far today
+    pub fees: f64,
+}
+
+impl OrderFlowStats {
+    pub fn messages(&self) -> u32 {
+        self.orders + self.cancels
+    }
+
+    /// Orders per trade; all orders count while nothing has traded.
+    pub fn order_to_trade(&self) -> f64 {
+        self.orders as f64 / self.trades.max(1) as f64
+    }
+
+    pub fn cancel_ratio(&self) -> f64 {
+        if self.orders == 0 {
+            0.0
+        } else {
+            self.cancels as f64 / self.orders as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Cancels,
+    LargeCancels,
+    OrderToTrade,
+}
+
+const RULES: [Rule; 3] = [Rule::Cancels, Rule::LargeCancels, Rule::OrderToTrade];
+
+/// A strategy is approaching (`blocking == false`) or at (`blocking == true`) an exchange limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConductWarning {
+    pub rule: Rule,
+    pub value: f64,
+    pub limit: f64,
+    pub blocking: bool,
+}
+
+impl fmt::Display for ConductWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.blocking { "reached" } else { "approaching" };
+        write!(f, "{:?} {} limit: {} of {}", self.rule, verb, self.value, self.limit)
+    }
+}
+
+pub struct ConductTracker {
+    limits: ConductLimits,
+    stats: OrderFlowStats,
+    /// rules already warned about today, indexed like `RULES`
+    warned: [bool; 3],
+}
+
+impl ConductTracker {
+    pub fn new(limits: ConductLimits) -> Self {
+        Self {
+            limits,
+            stats: OrderFlowStats::default(),
+            warned: [false; 3],
+        }
+    }
+
+    pub fn stats(&self) -> &OrderFlowStats {
+        &self.stats
+    }
+
+    fn roll(&mut self, stamp: i64) {
+        let date = timeutil::day_to_date(calendar::trading_day(stamp, calendar::is_weekday));
+        if date != self.stats.date {
+            self.stats = OrderFlowStats { date, ..Default::default() };
+            self.warned = [false; 3];
+        }
+    }
+
+    fn usage(&self, rule: Rule) -> (f64, f64) {
+        match rule {
+            Rule::Cancels => (self.stats.cancels as f64, self.limits.max_cancels as f64),
+            Rule::LargeCancels => (self.stats.large_cancels as f64, self.limits.max_large_cancels as f64),
+            Rule::OrderToTrade if self.stats.orders < self.limits.ratio_min_orders => (0.0, self.limits.max_order_to_trade),
+            Rule::OrderToTrade => (self.stats.order_to_trade(), self.limits.max_order_to_trade),
+        }
+    }
+
+    fn blocked(&self, rule: Rule) -> Option<ConductWarning> {
+        let (value, limit) = self.usage(rule);
+        (value >= limit).then_some(ConductWarning {
+            rule,
+            value,
+            limit,
+            blocking: true,
+        })
+    }
+
+    /// Whether a new order may be sent at `stamp`.
+    pub fn admit_order(&mut self, stamp: i64) -> Result<(), ConductWarning> {
+        self.roll(stamp);
+        self.blocked(Rule::OrderToTrade).map_or(Ok(()), Err)
+    }
+
+    /// Whether `lots` may be cancelled at `stamp`.
+    pub fn admit_cancel(&mut self, stamp: i64, lots: u32) -> Result<(), ConductWarning> {
+        self.roll(stamp);
+        if let Some(w) = self.blocked(Rule::Cancels) {
+            return Err(w);
+        }
+        if lots >= self.limits.large_cancel_lots {
+            return self.blocked(Rule::LargeCancels).map_or(Ok(()), Err);
+        }
+        Ok(())
+    }
+
+    /// Record a sent order; returns the message fee it incurred.
+    pub fn on_order(&mut self, stamp: i64) -> f64 {
+        self.roll(stamp);
+        self.stats.orders += 1;
+        self.message_fee()
+    }
+
+    /// Record a sent cancel; returns the message fee it incurred.
+    pub fn on_cancel(&mut self, stamp: i64, lots: u32) -> f64 {
+        self.roll(stamp);
+        self.stats.cancels += 1;
+        if lots >= self.limits.large_cancel_lots {
+            self.stats.large_cancels += 1;
+        }
+        self.message_fee()
+    }
+
+    pub fn on_trade(&mut self, stamp: i64) {
+        self.roll(stamp);
+        self.stats.trades += 1;
+    }
+
+    fn message_fee(&mut self) -> f64 {
+        let fee = if self.stats.messages() > self.limits.free_messages {
+            self.limits.fee_per_message
+        } else {
+            0.0
+        };
+        self.stats.fees += fee;
+        fee
+    }
+
+    /// Rules that crossed `warn_at` of their limit since the last call; each is reported
+    /// at most once per day.
+    pub fn take_warnings(&mut self) -> Vec<ConductWarning> {
+        let mut warnings = Vec::new();
+        for (i, &rule) in RULES.iter().enumerate() {
+            let (value, limit) = self.usage(rule);
+            if !self.warned[i] && value >= limit * self.limits.warn_at {
+                self.warned[i] = true;
+                warnings.push(ConductWarning {
+                    rule,
+                    value,
+                    limit,
+                    blocking: value >= limit,
+                });
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_limits_warn_then_block_and_reset_daily() {
+        let limits = ConductLimits {
+            max_cancels: 5,
+            free_messages: 6,
+            ..ConductLimits::cffex()
+        };
+        let mut tracker = ConductTracker::new(limits);
+        let day = 1_700_000_000_000;
+        for _ in 0..4 {
+            tracker.admit_order(day).unwrap();
+            tracker.on_order(day);
+            tracker.admit_cancel(day, 1).unwrap();
+            tracker.on_cancel(day, 1);
+        }
+        // 4 of 5 cancels is past the 80% mark
+        let warnings = tracker.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].rule, Rule::Cancels);
+        assert!(!warnings[0].blocking);
+        assert!(tracker.take_warnings().is_empty());
+
+        tracker.on_cancel(day, 1);
+        assert_eq!(tracker.admit_cancel(day, 1).unwrap_err().rule, Rule::Cancels);
+        // 9 messages against 6 free ones
+        assert_eq!(tracker.stats().fees, 3.0);
+
+        let next_day = day + 86_400_000;
+        assert!(tracker.admit_cancel(next_day, 1).is_ok());
+        assert_eq!(tracker.stats().cancels, 0);
+    }
+
+    #[test]
+    fn the_night_session_counts_toward_the_next_day() {
+        let mut tracker = ConductTracker::new(ConductLimits::cffex());
+        // stamp 0 is Thursday 08:00 CST
+        let hour = 3_600_000;
+        tracker.on_order(5 * hour);
+        // Thursday 21:00 opens Friday's trading day
+        tracker.on_order(13 * hour);
+        assert_eq!(tracker.stats().orders, 1);
+        // and Friday 09:00 stays in it
+        tracker.on_order(25 * hour);
+        assert_eq!(tracker.stats().orders, 2);
+    }
+}
@@ -1,17 +1,46 @@
-use anyhow::Result;
+use crate::codec::WireFormat;
+use crate::currency::Currency;
+use crate::equity_curve::CurveConfig;
+use crate::feed::BusyPoll;
+use crate::fees::{FeeModel, FeeRates};
+use crate::limits::PositionLimits;
+use crate::order_stats::ChurnLimits;
+use crate::reconnect::ReconnectPolicy;
+use crate::rollover::Rollover;
+use crate::shard::Shard;
+use crate::symbols::SymbolMap;
+use anyhow::{Context, Result};
 use serde::Deserialize;
-use std::{collections::HashMap, fs, path::Path};
+use std::collections::{BTreeMap, HashMap};
+use std::{env, fs, path::Path};
 
+/// An entry of an instrument fee file: per lot when `byvolume`, otherwise a rate on value.
 #[derive(Debug, Deserialize, PartialEq)]
-struct InstrumentFee {
-    open: f64,
-    close: f64,
-    closetoday: f64,
-    byvolume: bool,
+pub struct InstrumentFee {
+    pub open: f64,
+    pub close: f64,
+    pub closetoday: f64,
+    pub byvolume: bool,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+impl InstrumentFee {
+    pub fn model(&self) -> FeeModel {
+        let rates = FeeRates {
+            open: self.open,
+            close: self.close,
+            close_today: self.closetoday,
+        };
+        if self.byvolume {
+            FeeModel::ByVolume(rates)
+        } else {
+            FeeModel::ByValue(rates)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
 pub struct ContractInfo {
+    #[serde(alias = "contract_multiplier")]
     pub multiplier: f64,
     pub min_move: f64,
     // open fee
@@ -29,6 +58,38 @@ pub struct ContractInfo {
     // short margin
     pub short_margin_rate: f64,
     pub short_margin_fixed: f64,
+    /// 计价货币, the account's when unset
+    pub currency: Option<Currency>,
+    /// 汇率: units of the account's currency per unit of `currency`, 1 when unset
+    pub fx_rate: Option<f64>,
+}
+
+impl ContractInfo {
+    /// 折算汇率 of the contract's amounts into the account's currency.
+    pub fn fx(&self) -> f64 {
+        self.fx_rate.unwrap_or(1.0)
+    }
+
+    /// 每点价值: what one lot makes on a one-point move, in the account's currency.
+    pub fn point_value(&self) -> f64 {
+        self.multiplier * self.fx()
+    }
+
+    /// Whether the contract is priced in a currency other than `account` without a rate to
+    /// convert it with, so nothing on it can be valued.
+    pub fn lacks_fx(&self, account: Currency) -> bool {
+        self.currency.is_some_and(|c| c != account) && self.fx_rate.is_none()
+    }
+}
+
+/// What the engine does for a strategy whose contract has no entry in the fees table.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum MissingContract {
+    /// register the strategy but block every order on its symbol
+    #[default]
+    Block,
+    /// trade with these values in place of the missing entry
+    Defaults(ContractInfo),
 }
 
 /// Read `path` and parse it in one go.
@@ -39,6 +100,207 @@ pub fn load_fees<P: AsRef<Path>>(path: P) -> Result<HashMap<String, ContractInfo
     Ok(map)
 }
 
+/// Read an instrument fee file into a commission model per contract.
+pub fn load_fee_models<P: AsRef<Path>>(path: P) -> Result<HashMap<String, FeeModel>> {
+    let s = fs::read_to_string(path)?;
+    let fees: HashMap<String, InstrumentFee> = toml::from_str(&s)?;
+    Ok(fees.into_iter().map(|(contract, fee)| (contract, fee.model())).collect())
+}
+
+/// Expected ticks per second by symbol, e.g. `rb2505 = 12.5`, for `TickRateRouter`.
+pub fn load_tick_rates<P: AsRef<Path>>(path: P) -> Result<HashMap<String, f64>> {
+    let s = fs::read_to_string(path)?;
+    Ok(toml::from_str(&s)?)
+}
+
+/// A strategy the engine starts with, built through `strategies::create`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct StrategySpec {
+    pub symbol: String,
+    /// fees table entry, e.g. `SHFE.rb`; found through `symbol_map` when left out
+    #[serde(default)]
+    pub contract: Option<String>,
+    pub kind: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// caps on the position the strategy may open up to, see `limits`
+    #[serde(default)]
+    pub limits: Option<PositionLimits>,
+}
+
+/// When products traded through a dominant alias roll, see `rollover`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RolloverSpec {
+    /// contract → trading day (`yyyymmdd`) it is traded from, e.g. `rb2510 = 20250317`
+    pub schedule: BTreeMap<String, u32>,
+    /// roll once a watched contract's open interest is this many times the current one's
+    pub oi_lead: Option<f64>,
+    pub watch: Vec<String>,
+}
+
+impl RolloverSpec {
+    pub fn build(&self) -> Rollover {
+        let mut rollover = self
+            .schedule
+            .iter()
+            .fold(Rollover::new(), |r, (contract, &date)| r.with_roll(date, contract));
+        if let Some(lead) = self.oi_lead {
+            rollover = rollover.roll_on_open_interest(lead);
+        }
+        self.watch.iter().fold(rollover, |r, contract| r.watch(contract))
+    }
+}
+
+/// Everything the `fustg_rs` binary is started with, from `config/engine.toml`. Optional
+/// outputs and sockets are off when left out.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    /// tick feeds, highest priority first
+    pub tick_uris: Vec<String>,
+    pub order_uri: String,
+    pub num_workers: usize,
+    pub wire_format: WireFormat,
+    /// trade only this shard's symbols, for several processes splitting them, see `shard`
+    pub shard: Option<Shard>,
+    /// busy-poll the sockets instead of sleeping until one is readable, see `feed::BusyPoll`
+    pub busy_poll: Option<BusyPoll>,
+    /// backoff for rebuilding the tick and order sockets after an error, see `reconnect`
+    pub reconnect: ReconnectPolicy,
+    /// `EnvFilter` directive, see `logging::LogConfig`
+    pub log_level: String,
+    pub fees_path: String,
+    /// per-lot or by-value commissions overriding the fee columns of `fees_path`
+    pub instrument_fees_path: Option<String>,
+    pub init_cash: f64,
+    /// what `init_cash` is in; contracts priced otherwise need an `fx_rate` in the fees table
+    pub currency: Currency,
+    pub calendar_path: Option<String>,
+    pub tick_rates_path: Option<String>,
+    pub control_uri: Option<String>,
+    pub query_uri: Option<String>,
+    pub metrics_addr: Option<String>,
+    /// PUB socket supervisors watch for heartbeats, see `heartbeat`
+    pub heartbeat_uri: Option<String>,
+    /// names this engine in heartbeats, to tell shards and hosts apart
+    pub engine_id: String,
+    pub dashboard_addr: Option<String>,
+    pub record_dir: Option<String>,
+    pub audit_log: Option<String>,
+    pub handoff_path: Option<String>,
+    /// close every position with `flatten_all` on Ctrl-C instead of carrying it into the
+    /// handoff file
+    pub flatten_on_exit: bool,
+    /// write-ahead journal a crashed run is recovered from, see `journal`
+    pub journal_path: Option<String>,
+    /// daily bars for the risk report, also offered to strategies to warm up from
+    pub bar_dir: Option<String>,
+    /// ticks each strategy is warmed up on before trading, from `record_dir`, else from the
+    /// minute bars in `bar_dir`; 0 for none
+    pub warmup_ticks: usize,
+    pub report_dir: Option<String>,
+    /// resampling of the strategies' equity curves; every tick's equity is kept without it
+    pub equity_curve: Option<CurveConfig>,
+    /// throttling of strategies that churn orders, see `order_stats`
+    pub churn_limits: Option<ChurnLimits>,
+    /// end-of-day orders, trades and positions for reconciliation, see `statement`
+    pub statement_dir: Option<String>,
+    /// how strategies' symbols find their fees entry, see `symbols::SymbolMap`
+    pub symbol_map: SymbolMap,
+    pub rollover: Option<RolloverSpec>,
+    pub strategies: Vec<StrategySpec>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            tick_uris: vec!["ipc://@hq".into()],
+            order_uri: "ipc://@orders".into(),
+            num_workers: 4,
+            wire_format: WireFormat::default(),
+            shard: None,
+            busy_poll: None,
+            reconnect: ReconnectPolicy::default(),
+            log_level: "info".into(),
+            fees_path: "config/fees.1st.toml".into(),
+            instrument_fees_path: None,
+            init_cash: 1e6,
+            currency: Currency::default(),
+            calendar_path: None,
+            tick_rates_path: None,
+            control_uri: None,
+            query_uri: None,
+            metrics_addr: None,
+            heartbeat_uri: None,
+            engine_id: "fustg".into(),
+            dashboard_addr: None,
+            record_dir: None,
+            audit_log: None,
+            handoff_path: None,
+            flatten_on_exit: false,
+            journal_path: None,
+            bar_dir: None,
+            warmup_ticks: 0,
+            report_dir: None,
+            equity_curve: None,
+            churn_limits: None,
+            statement_dir: None,
+            symbol_map: SymbolMap::default(),
+            rollover: None,
+            strategies: Vec::new(),
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Read `path`, then apply the environment overrides (see `apply_overrides`).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let mut config: Self = toml::from_str(&s).with_context(|| format!("parsing {}", path.display()))?;
+        config.apply_overrides(|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Override fields from variables looked up through `var`: `FUSTG_TICK_URIS`
+    /// (comma-separated), `FUSTG_ORDER_URI`, `FUSTG_NUM_WORKERS`, `FUSTG_WIRE_FORMAT`,
+    /// `FUSTG_LOG_LEVEL`, `FUSTG_FEES`, `FUSTG_INIT_CASH` and `FUSTG_SHARD` (`<index>/<count>`).
+    pub fn apply_overrides(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(uris) = var("FUSTG_TICK_URIS") {
+            self.tick_uris = uris.split(',').map(|uri| uri.trim().to_string()).filter(|uri| !uri.is_empty()).collect();
+        }
+        if let Some(uri) = var("FUSTG_ORDER_URI") {
+            self.order_uri = uri;
+        }
+        if let Some(n) = var("FUSTG_NUM_WORKERS") {
+            self.num_workers = n.parse().with_context(|| format!("FUSTG_NUM_WORKERS={:?}", n))?;
+        }
+        if let Some(format) = var("FUSTG_WIRE_FORMAT") {
+            self.wire_format = WireFormat::deserialize(toml::Value::String(format.to_ascii_lowercase()))
+                .with_context(|| format!("FUSTG_WIRE_FORMAT={:?}", format))?;
+        }
+        if let Some(level) = var("FUSTG_LOG_LEVEL") {
+            self.log_level = level;
+        }
+        if let Some(path) = var("FUSTG_FEES") {
+            self.fees_path = path;
+        }
+        if let Some(cash) = var("FUSTG_INIT_CASH") {
+            self.init_cash = cash.parse().with_context(|| format!("FUSTG_INIT_CASH={:?}", cash))?;
+        }
+        if let Some(shard) = var("FUSTG_SHARD") {
+            self.shard = Some(Shard::parse(&shard).map_err(|e| anyhow::anyhow!("FUSTG_SHARD: {}", e))?);
+        }
+        if let Some(shard) = self.shard {
+            Shard::new(shard.index, shard.count).map_err(|e| anyhow::anyhow!(e))?;
+        }
+        anyhow::ensure!(!self.tick_uris.is_empty(), "no tick_uris configured");
+        anyhow::ensure!(self.num_workers > 0, "num_workers must be at least 1");
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,6 +337,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fee_model_by_volume_and_by_value() {
+        let map: HashMap<String, InstrumentFee> = toml::from_str(TEST_TOML).expect("parsing should succeed");
+        assert!(matches!(map["CFFEX.IF"].model(), FeeModel::ByVolume(r) if r.close == 102.5));
+        assert!(matches!(map["CZSE.IC"].model(), FeeModel::ByValue(r) if r.close_today == 199.0));
+    }
+
     #[test]
     fn parse_file() {
         // test use crate root as working directory
@@ -83,4 +352,25 @@ mod tests {
         assert_eq!(map.len(), 83);
         // println!("{:?}", map);
     }
+
+    #[test]
+    fn engine_config_with_env_overrides() {
+        let mut config = EngineConfig::load("config/engine.toml").expect("engine.toml parses");
+        assert!(!config.strategies.is_empty());
+        let env: HashMap<&str, &str> = [
+            ("FUSTG_TICK_URIS", "tcp://a:1, tcp://b:2"),
+            ("FUSTG_WIRE_FORMAT", "JSON"),
+            ("FUSTG_NUM_WORKERS", "2"),
+        ]
+        .into();
+        config.apply_overrides(|name| env.get(name).map(|v| v.to_string())).unwrap();
+        assert_eq!(config.tick_uris, vec!["tcp://a:1", "tcp://b:2"]);
+        assert_eq!((config.wire_format, config.num_workers), (WireFormat::Json, 2));
+        assert!(
+            config
+                .apply_overrides(|name| (name == "FUSTG_NUM_WORKERS").then(|| "many".into()))
+                .is_err()
+        );
+        assert!(toml::from_str::<EngineConfig>("order_url = 'x'").is_err());
+    }
 }
@@ -0,0 +1,264 @@
+//! Continuous ("main") contract series stitched from single-contract bars in the `BarStore`.
+//!
+//! The series follows one contract at a time and only ever rolls forward, to a later delivery
+//! month. Roll decisions for a day use the previous day's data, so the series has no lookahead.
+//! Prices before each roll are back-adjusted so the latest bars keep their real prices.
+use crate::bars::{Bar, BarPeriod, BarStore};
+use crate::timeutil;
+use crate::types::SymbolType;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollRule {
+    /// roll once a later contract closes a day with more open interest
+    OpenInterest,
+    /// roll once a later contract trades more volume in a day
+    Volume,
+    /// roll to the next contract this many calendar days before the current one's delivery month
+    DaysBeforeExpiry(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adjustment {
+    None,
+    /// shift earlier prices by the close-to-close gap at each roll
+    Difference,
+    /// scale earlier prices by the close-to-close ratio at each roll
+    Ratio,
+}
+
+impl RollRule {
+    /// `oi`, `volume` or `expiry:<days>`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.split_once(':') {
+            Some(("expiry", days)) => days.parse().ok().map(RollRule::DaysBeforeExpiry),
+            None if s == "oi" => Some(RollRule::OpenInterest),
+            None if s == "volume" => Some(RollRule::Volume),
+            _ => None,
+        }
+    }
+}
+
+impl Adjustment {
+    /// `none`, `difference` or `ratio`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(Adjustment::None),
+            "difference" => Some(Adjustment::Difference),
+            "ratio" => Some(Adjustment::Ratio),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll {
+    /// first bar taken from `to`
+    pub stamp: i64,
+    pub from: String,
+    pub to: String,
+    /// closes of both contracts on the day before the roll
+    pub from_close: f64,
+    pub to_close: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContinuousSeries {
+    pub bars: Vec<Bar>,
+    pub rolls: Vec<Roll>,
+}
+
+/// First day (`yyyymmdd`) of a contract's delivery month, e.g. 20250501 for `rb2505`. CZCE
+/// codes carry one year digit (`MA505`); the decade is the one placing the month on or after
+/// `ref_date`, the date of the contract's first bar, within ten years.
+pub fn delivery_month(symbol: &str, ref_date: u32) -> Option<u32> {
+    let digits = &symbol[crate::calendar::product_of(&SymbolType::from(symbol)).len()..];
+    let code: u32 = digits.parse().ok()?;
+    let (year, month) = match digits.len() {
+        4 => (2000 + code / 100, code % 100),
+        3 => {
+            let ref_year = ref_date / 10000;
+            let year = ref_year - ref_year % 10 + code / 100;
+            let year = if year * 100 + code % 100 < ref_date / 100 { year + 10 } else { year };
+            (year, code % 100)
+        }
+        _ => return None,
+    };
+    (1..=12).contains(&month).then_some(year * 10000 + month * 100 + 1)
+}
+
+/// Per contract and day: last close, last open interest and total volume.
+type DayStats = HashMap<(usize, i64), (f64, f64, f64)>;
+
+/// Stitch `contracts` (symbol, bars oldest first) into one series.
+pub fn stitch(contracts: &[(String, Vec<Bar>)], rule: RollRule, adjustment: Adjustment) -> ContinuousSeries {
+    // order contracts by delivery month, unknown codes last by name
+    let mut order: Vec<(Option<u32>, usize)> = contracts
+        .iter()
+        .enumerate()
+        .map(|(i, (symbol, bars))| (bars.first().and_then(|b| delivery_month(symbol, timeutil::local_date(b.stamp))), i))
+        .collect();
+    order.sort_by(|a, b| (a.0.is_none(), a.0, &contracts[a.1].0).cmp(&(b.0.is_none(), b.0, &contracts[b.1].0)));
+
+    let mut stats: DayStats = HashMap::new();
+    let mut by_stamp: BTreeMap<i64, Vec<(usize, Bar)>> = BTreeMap::new();
+    for (rank, &(_, i)) in order.iter().enumerate() {
+        for bar in &contracts[i].1 {
+            let day = timeutil::local_day(bar.stamp);
+            let entry = stats.entry((rank, day)).or_insert((0.0, 0.0, 0.0));
+            *entry = (bar.close, bar.oi, entry.2 + bar.volume);
+            by_stamp.entry(bar.stamp).or_default().push((rank, *bar));
+        }
+    }
+
+    let mut series = ContinuousSeries::default();
+    let mut active: Option<usize> = None;
+    let mut prev_day: Option<i64> = None;
+    // (index into series.bars where the roll happened, gap to apply before it)
+    let mut adjust_at: Vec<(usize, f64)> = Vec::new();
+    for (&stamp, bars) in &by_stamp {
+        let day = timeutil::local_day(stamp);
+        if prev_day != Some(day) {
+            if let Some(prev) = prev_day {
+                let next = pick(active, prev, day, &stats, &order, rule);
+                if let (Some(from), Some(to)) = (active, next)
+                    && from != to
+                {
+                    // candidates traded on `prev`; if the old contract did not, there is no gap
+                    let to_close = stats[&(to, prev)].0;
+                    let from_close = stats.get(&(from, prev)).map_or(to_close, |s| s.0);
+                    let gap = match adjustment {
+                        Adjustment::None => 0.0,
+                        Adjustment::Difference => to_close - from_close,
+                        Adjustment::Ratio => to_close / from_close,
+                    };
+                    adjust_at.push((series.bars.len(), gap));
+                    series.rolls.push(Roll {
+                        stamp,
+                        from: contracts[order[from].1].0.clone(),
+                        to: contracts[order[to].1].0.clone(),
+                        from_close,
+                        to_close,
+                    });
+                }
+                active = next.or(active);
+            }
+            prev_day = Some(day);
+        }
+        let active_rank = *active.get_or_insert_with(|| bars.iter().map(|(rank, _)| *rank).min().unwrap_or(0));
+        if let Some((_, bar)) = bars.iter().find(|(rank, _)| *rank == active_rank) {
+            series.bars.push(*bar);
+        }
+    }
+
+    // back-adjust: every roll shifts everything before it
+    for (end, gap) in adjust_at.into_iter().rev() {
+        for bar in &mut series.bars[..end] {
+            match adjustment {
+                Adjustment::None => {}
+                Adjustment::Difference => {
+                    bar.open += gap;
+                    bar.high += gap;
+                    bar.low += gap;
+                    bar.close += gap;
+                }
+                Adjustment::Ratio => {
+                    bar.open *= gap;
+                    bar.high *= gap;
+                    bar.low *= gap;
+                    bar.close *= gap;
+                }
+            }
+        }
+    }
+    series
+}
+
+/// The contract to hold on `day`, judged on `prev`. Only later contracts that traded on
+/// `prev` are candidates, so the roll has a close to adjust by.
+fn pick(active: Option<usize>, prev: i64, day: i64, stats: &DayStats, order: &[(Option<u32>, usize)], rule: RollRule) -> Option<usize> {
+    let current = active?;
+    let later = (current + 1..order.len()).filter(|rank| stats.contains_key(&(*rank, prev)));
+    match rule {
+        RollRule::DaysBeforeExpiry(days) => {
+            let expiry = order[current].0?;
+            let roll_day = timeutil::date_to_day(expiry) - days as i64;
+            if day >= roll_day { later.min() } else { Some(current) }
+        }
+        RollRule::OpenInterest | RollRule::Volume => {
+            let metric = |rank: usize| {
+                stats.get(&(rank, prev)).map_or(0.0, |&(_, oi, volume)| match rule {
+                    RollRule::OpenInterest => oi,
+                    _ => volume,
+                })
+            };
+            let best = later.max_by(|a, b| metric(*a).total_cmp(&metric(*b)));
+            match best {
+                Some(rank) if metric(rank) > metric(current) => Some(rank),
+                _ => Some(current),
+            }
+        }
+    }
+}
+
+/// Stitch `symbols` from `store` and save the result under `name` (e.g. `rb.main`), replacing
+/// any previous build.
+pub fn build_into_store(
+    store: &BarStore,
+    name: &str,
+    symbols: &[String],
+    period: BarPeriod,
+    rule: RollRule,
+    adjustment: Adjustment,
+) -> io::Result<ContinuousSeries> {
+    let mut contracts = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        contracts.push((symbol.clone(), store.load(symbol, period)?));
+    }
+    let series = stitch(&contracts, rule, adjustment);
+    store.save(name, period, &series.bars)?;
+    Ok(series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily(date: u32, close: f64, oi: f64) -> Bar {
+        Bar {
+            stamp: timeutil::local_stamp(date, 0),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: oi,
+            oi,
+        }
+    }
+
+    #[test]
+    fn delivery_months() {
+        assert_eq!(delivery_month("rb2505", 20240516), Some(20250501));
+        assert_eq!(delivery_month("MA505", 20240516), Some(20250501));
+        assert_eq!(delivery_month("MA001", 20190102), Some(20200101));
+        assert_eq!(delivery_month("RB0", 20250101), None);
+    }
+
+    #[test]
+    fn rolls_on_open_interest_with_ratio_adjustment() {
+        let near = vec![daily(20250310, 100.0, 50.0), daily(20250311, 100.0, 40.0), daily(20250312, 101.0, 30.0)];
+        let far = vec![daily(20250310, 110.0, 45.0), daily(20250311, 120.0, 60.0), daily(20250312, 121.0, 70.0)];
+        let series = stitch(
+            &[("rb2510".into(), far), ("rb2505".into(), near)],
+            RollRule::OpenInterest,
+            Adjustment::Ratio,
+        );
+
+        // far overtook near on the 11th, so the 12th is the first far bar
+        assert_eq!(series.rolls.len(), 1);
+        assert_eq!((series.rolls[0].from.as_str(), series.rolls[0].to.as_str()), ("rb2505", "rb2510"));
+        let closes: Vec<f64> = series.bars.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![120.0, 120.0, 121.0]);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::types::{DirectionType, OffsetFlagType, SymbolType};
+
+/// Operator commands accepted on the engine's control socket.
+///
+/// Each request is one line of whitespace-separated text, e.g. `pause rb2505` or
+/// `add rb2505 SHFE.rb Aberration 100`.
+/// The reply is `ok` or `error: <reason>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Stop dispatching the symbol's ticks and block its orders.
+    PauseSymbol(SymbolType),
+    ResumeSymbol(SymbolType),
+    /// Keep running the strategies with this name on their ticks but drop their orders, or
+    /// let their orders out again.
+    PauseStrategy {
+        strategy: String,
+        paused: bool,
+    },
+    /// Push a new parameter value to every strategy with this name.
+    UpdateParam {
+        strategy: String,
+        name: String,
+        value: f64,
+    },
+    /// Switch a named toggle on or off for every strategy with this name.
+    SetToggle {
+        strategy: String,
+        name: String,
+        enabled: bool,
+    },
+    /// Run a hypothetical order for this strategy through the risk checks and margin
+    /// calculation and report the outcome, without sending it.
+    PreviewOrder {
+        strategy: String,
+        direction: DirectionType,
+        offset: OffsetFlagType,
+        price: f64,
+        lots: u32,
+    },
+    /// Cancel a resting order of this strategy by client order id.
+    CancelOrder {
+        strategy: String,
+        client_id: u64,
+    },
+    /// Build a strategy of `kind` from `args` and start it on `symbol`, tracked with the fees of
+    /// `contract`; `auto` finds the entry through the engine's symbol map.
+    AddStrategy {
+        symbol: SymbolType,
+        contract: String,
+        kind: String,
+        args: Vec<String>,
+    },
+    /// Remove every strategy with this name; its symbol is unsubscribed if nothing else trades it.
+    RemoveStrategy(String),
+    /// Load a strategy plugin; its kind becomes available to `add`.
+    LoadPlugin(String),
+    /// Re-arm the circuit breakers of the strategies with this name, or every breaker when
+    /// `None` (`rearm all`); they may open positions again.
+    Rearm(Option<String>),
+    /// Cancel the resting orders and close the positions of every strategy on the symbol,
+    /// halting them until re-armed.
+    Flatten(SymbolType),
+    /// `flatten all`: the same for every strategy, at once at the last quotes.
+    FlattenAll,
+    /// Re-read the fees and margins table without waiting for the file to change.
+    Reload,
+    /// VaR and stress scenarios over current positions, moving each product by `±shock`
+    /// (3% unless given, in percent); the reply is the report as JSON.
+    RiskReport {
+        shock: f64,
+    },
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["pause", "strategy", strategy] => Ok(Command::PauseStrategy {
+                strategy: strategy.to_string(),
+                paused: true,
+            }),
+            ["resume", "strategy", strategy] => Ok(Command::PauseStrategy {
+                strategy: strategy.to_string(),
+                paused: false,
+            }),
+            ["pause", symbol] => Ok(Command::PauseSymbol(SymbolType::from(*symbol))),
+            ["resume", symbol] => Ok(Command::ResumeSymbol(SymbolType::from(*symbol))),
+            ["param", strategy, name, value] => Ok(Command::UpdateParam {
+                strategy: strategy.to_string(),
+                name: name.to_string(),
+                value: value.parse().map_err(|_| format!("invalid value {:?}", value))?,
+            }),
+            ["toggle", strategy, name, state] => Ok(Command::SetToggle {
+                strategy: strategy.to_string(),
+                name: name.to_string(),
+                enabled: match *state {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(format!("toggle state must be on or off, got {:?}", state)),
+                },
+            }),
+            ["preview", strategy, direction, offset, price, lots] => Ok(Command::PreviewOrder {
+                strategy: strategy.to_string(),
+                direction: match direction.to_ascii_lowercase().as_str() {
+                    "buy" => DirectionType::BUY,
+                    "sell" => DirectionType::SELL,
+                    _ => return Err(format!("direction must be buy or sell, got {:?}", direction)),
+                },
+                offset: match offset.to_ascii_lowercase().as_str() {
+                    "open" => OffsetFlagType::OPEN,
+                    "close" => OffsetFlagType::CLOSE,
+                    _ => return Err(format!("offset must be open or close, got {:?}", offset)),
+                },
+                price: price.parse().map_err(|_| format!("invalid price {:?}", price))?,
+                lots: lots.parse().map_err(|_| format!("invalid lots {:?}", lots))?,
+            }),
+            ["cancel", strategy, client_id] => Ok(Command::CancelOrder {
+                strategy: strategy.to_string(),
+                client_id: client_id.parse().map_err(|_| format!("invalid order id {:?}", client_id))?,
+            }),
+            ["add", symbol, contract, kind, args @ ..] => Ok(Command::AddStrategy {
+                symbol: SymbolType::from(*symbol),
+                contract: contract.to_string(),
+                kind: kind.to_string(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+            }),
+            ["remove", strategy] => Ok(Command::RemoveStrategy(strategy.to_string())),
+            ["plugin", path] => Ok(Command::LoadPlugin(path.to_string())),
+            ["rearm", "all"] => Ok(Command::Rearm(None)),
+            ["rearm", strategy] => Ok(Command::Rearm(Some(strategy.to_string()))),
+            ["flatten", "all"] => Ok(Command::FlattenAll),
+            ["flatten", symbol] => Ok(Command::Flatten(SymbolType::from(*symbol))),
+            ["reload"] => Ok(Command::Reload),
+            ["risk"] => Ok(Command::RiskReport { shock: 0.03 }),
+            ["risk", pct] => Ok(Command::RiskReport {
+                shock: pct.parse::<f64>().map_err(|_| format!("invalid shock {:?}", pct))? / 100.0,
+            }),
+            [] => Err("empty command".into()),
+            _ => Err(format!("unknown command {:?}", line)),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Currencies accounts and contracts are denominated in. Every amount the engine keeps is in
+//! the account's currency; a contract priced in another one carries an `fx_rate` in its
+//! `ContractInfo`, and its PnL, margins and fees are converted with it as they are booked.
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// An ISO 4217 code, e.g. `CNY` or `USD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const CNY: Currency = Currency(*b"CNY");
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("a currency code is ASCII")
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::CNY
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[a, b, c] if [a, b, c].iter().all(u8::is_ascii_alphabetic) => Ok(Currency([a, b, c].map(|x| x.to_ascii_uppercase()))),
+            _ => Err(format!("{:?} is not a three-letter currency code", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_three_letter_codes() {
+        assert_eq!("usd".parse::<Currency>().unwrap().to_string(), "USD");
+        assert_eq!("CNY".parse::<Currency>(), Ok(Currency::CNY));
+        assert!("US".parse::<Currency>().is_err() && "U$D".parse::<Currency>().is_err());
+    }
+}
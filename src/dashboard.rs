@@ -0,0 +1,167 @@
+//! Embedded live dashboard, built with the `dashboard` feature. A plain HTTP server like the
+//! metrics endpoint: `/` is a single page, `/state` one JSON update, and `/events` a stream of
+//! them as server-sent events, one per interval. Strategy state comes from the engine's query
+//! socket (see `query`), so serving the dashboard never touches the tick path; tick rates come
+//! from `EngineMetrics`.
+use crate::metrics::EngineMetrics;
+use crate::types::SymbolType;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Equity samples per strategy in each update.
+const CURVE_POINTS: usize = 300;
+/// Latest fills per strategy in each update.
+const RECENT_FILLS: usize = 10;
+
+const PAGE: &str = include_str!("dashboard.html");
+
+/// Serve the dashboard on `addr` from background threads for the life of the process.
+/// `query_uri` is the engine's query socket; a `*` host is reached on localhost.
+pub fn serve(addr: &str, ctx: zmq::Context, query_uri: &str, metrics: Arc<EngineMetrics>, interval: Duration) -> io::Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    let query_uri = query_uri.replace("://*:", "://127.0.0.1:");
+    info!(addr, query_uri, "serving dashboard");
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!(error = ?e, "failed to accept dashboard connection");
+                    continue;
+                }
+            };
+            // event streams stay open, so every connection gets its own thread and query socket
+            let source = StateSource::new(ctx.clone(), query_uri.clone(), Arc::clone(&metrics));
+            thread::spawn(move || {
+                if let Err(e) = respond(stream, source, interval) {
+                    warn!(error = ?e, "dashboard connection ended");
+                }
+            });
+        }
+    }))
+}
+
+fn respond(mut stream: TcpStream, mut source: StateSource, interval: Duration) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let (status, content_type, body) = match request_line.split_whitespace().nth(1) {
+        Some("/") => ("200 OK", "text/html; charset=utf-8", PAGE.to_string()),
+        Some("/state") => ("200 OK", "application/json", source.update()),
+        Some("/events") => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+            )?;
+            // ends when the browser goes away and the write fails
+            loop {
+                write!(stream, "data: {}\n\n", source.update())?;
+                stream.flush()?;
+                thread::sleep(interval);
+            }
+        }
+        _ => ("404 Not Found", "text/plain", String::from("not found\n")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Builds dashboard updates: strategy snapshots from the query socket plus tick rates since
+/// the previous update.
+struct StateSource {
+    ctx: zmq::Context,
+    query_uri: String,
+    socket: Option<zmq::Socket>,
+    metrics: Arc<EngineMetrics>,
+    counts: HashMap<SymbolType, u64>,
+    counted_at: Instant,
+}
+
+impl StateSource {
+    fn new(ctx: zmq::Context, query_uri: String, metrics: Arc<EngineMetrics>) -> Self {
+        let counts = metrics.symbol_tick_counts().into_iter().collect();
+        Self {
+            ctx,
+            query_uri,
+            socket: None,
+            metrics,
+            counts,
+            counted_at: Instant::now(),
+        }
+    }
+
+    /// One update as JSON: `{"strategies": [...], "tick_rates": {...}}`, with an `error` in
+    /// place of the strategies when the engine did not answer.
+    fn update(&mut self) -> String {
+        let strategies = match self.query(&format!("strategies {} {}", CURVE_POINTS, RECENT_FILLS)) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() })),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        let counts: HashMap<SymbolType, u64> = self.metrics.symbol_tick_counts().into_iter().collect();
+        let rates = tick_rates(&self.counts, &counts, self.counted_at.elapsed());
+        self.counts = counts;
+        self.counted_at = Instant::now();
+        serde_json::json!({ "strategies": strategies, "tick_rates": rates }).to_string()
+    }
+
+    fn query(&mut self, request: &str) -> Result<String, String> {
+        if self.socket.is_none() {
+            let socket = self.ctx.socket(zmq::REQ).map_err(|e| e.to_string())?;
+            socket.set_linger(0).map_err(|e| e.to_string())?;
+            socket.set_rcvtimeo(2000).map_err(|e| e.to_string())?;
+            socket.connect(&self.query_uri).map_err(|e| e.to_string())?;
+            self.socket = Some(socket);
+        }
+        let socket = self.socket.as_ref().unwrap();
+        let reply = socket.send(request, 0).and_then(|()| socket.recv_string(0));
+        match reply {
+            Ok(Ok(json)) => Ok(json),
+            Ok(Err(_)) => Err("query reply is not valid UTF-8".into()),
+            Err(e) => {
+                // a REQ socket that missed its reply cannot send again; start over next time
+                self.socket = None;
+                Err(format!("engine did not answer: {}", e))
+            }
+        }
+    }
+}
+
+/// Ticks per second of each symbol between two readings of the per-symbol counters.
+fn tick_rates(before: &HashMap<SymbolType, u64>, after: &HashMap<SymbolType, u64>, elapsed: Duration) -> BTreeMap<String, f64> {
+    let seconds = elapsed.as_secs_f64().max(1e-3);
+    after
+        .iter()
+        .map(|(symbol, &count)| {
+            let delta = count - before.get(symbol).copied().unwrap_or(0).min(count);
+            (symbol.as_str().to_string(), delta as f64 / seconds)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_ticks_between_readings() {
+        let rb = SymbolType::from("rb2505");
+        let ma = SymbolType::from("MA505");
+        let before = HashMap::from([(rb, 100)]);
+        let after = HashMap::from([(rb, 150), (ma, 10)]);
+        let rates = tick_rates(&before, &after, Duration::from_secs(2));
+        assert_eq!(rates["rb2505"], 25.0);
+        assert_eq!(rates["MA505"], 5.0);
+    }
+}
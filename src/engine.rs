@@ -1,74 +1,2973 @@
+use crate::account::{Account, AccountSummary, MarginNetting};
+use crate::affinity::{self, CpuAffinity};
+use crate::audit::{self, AuditLog, OrderRecord};
+use crate::bars::BarStore;
+use crate::breaker::{BreakerLimits, DrawdownBreaker};
+use crate::budget::{self, BudgetMeter, ResourceBudget};
+use crate::calendar::{self, Session, SessionFilter, TradingCalendar};
+use crate::clock::{SharedClock, SystemClock};
+use crate::codec::{Quarantine, TickMeta, WireFormat};
+use crate::conduct::{ConductLimits, ConductTracker, ConductWarning};
+use crate::config::{self, ContractInfo, MissingContract};
+use crate::control::Command;
+use crate::currency::Currency;
+use crate::equity_curve::CurveConfig;
+use crate::error::EngineError;
+use crate::events::{Event, EventLog, EventReader};
+use crate::execution::{ExecutionReport, Report, SeqStore, StatusReport};
+use crate::feed::{FeedSelector, ReceiveMode, SequenceTracker, TickFeeds};
+use crate::fees::FeeModel;
+use crate::handoff::{Handoff, LastTick, OpenOrder, PositionState, StrategyState};
+use crate::heartbeat::HeartbeatPublisher;
+use crate::ids::IdGenerator;
+use crate::journal::{self, Entry, Journal};
+use crate::limits::{LimitBreach, PositionLimits};
+use crate::metrics::{self, EngineMetrics};
+use crate::order_queue::{Admission, OrderQueue, Purpose, QueueConfig, Queued};
+use crate::order_stats::{Churn, ChurnLimits, OrderStatsTracker};
+use crate::pause::{PausedSymbols, PausedView};
+use crate::pending::{PartialFillPolicy, PendingOrders};
 use crate::perf_tracker::PerformanceTracker;
-use crate::strategy::Strategy;
-use crate::types::{Order, SymbolType, TickData};
+use crate::plugin::PluginRegistry;
+use crate::position::PositionManager;
+use crate::pricing::PriceOffset;
+use crate::query::{self, FillDetail, Query, SnapshotFilter, StrategySnapshot};
+use crate::queue;
+use crate::reconnect::{self, Backoff, OrderPusher, ReconnectPolicy};
+use crate::recorder::TickRecorder;
+use crate::reload::{self, FeesWatcher};
+use crate::report::{self, DailyReport, DailyReportCallback, Marks, ReportConfig};
+use crate::risk::{self, RiskModel, RiskReport};
+use crate::rollover::{Roll, Rollover};
+use crate::router::{HashRouter, Router};
+use crate::shard::Shard;
+use crate::sizing::SizingContext;
+use crate::skew::{SkewConfig, SkewMonitor, SkewStats};
+use crate::split;
+use crate::statement::{self, Statement};
+use crate::stops::{StopLevels, StopManager};
+use crate::store::{OrderStore, StoreRecord};
+use crate::strategies;
+use crate::strategy::{EngineContext, Orders, Strategy};
+use crate::symbols::SymbolMap;
+use crate::timeutil;
+use crate::tracking::{TrackingAlert, TrackingMonitor};
+use crate::types::{
+    BookSnapshot, DirectionType, LimitLock, NameType, OffsetFlagType, Order, OrderCancel, OrderType, PriceLimits, SymbolType, TickData,
+};
+use crate::validate::{self, ValidationReport};
+use crate::warmup::WarmupSource;
+use crate::watchdog::{DataAlert, DataAlertCallback, Watchdog, WatchdogConfig};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::sync::mpsc;
-use std::{mem, thread};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, mpsc};
+use std::time::{Duration, Instant};
+use std::{fs, mem, thread};
+use tracing::{debug, error, info, info_span, warn};
 use zmq;
 
+/// How long a worker's order socket keeps trying to deliver after `flatten_all`.
+const FLATTEN_LINGER_MS: i32 = 2000;
+
 struct StratPerf {
     stg: Box<dyn Strategy>,
+    /// embedded in the engine order ids of this strategy's orders
+    strategy_id: u16,
     perf: PerformanceTracker,
+    conduct: Option<ConductTracker>,
+    /// order, cancel and fill counts, and the churn throttle when enabled
+    order_stats: OrderStatsTracker,
+    stops: StopManager,
+    pending: PendingOrders,
+    partial_fills: PartialFillPolicy,
+    /// turns targets into orders when the strategy trades by target position
+    positions: PositionManager,
+    tracking: Option<TrackingMonitor>,
+    budget: Option<BudgetMeter>,
+    price_offset: PriceOffset,
+    /// auxiliary symbols whose ticks go to `on_reference_tick`
+    references: Vec<SymbolType>,
+    /// orders the risk checks refused
+    rejected: u64,
+    breaker: Option<DrawdownBreaker>,
+    /// caps on the lots and notional opening orders may build up to
+    limits: Option<PositionLimits>,
+    /// flattened after a breaker trip or margin call; opening orders are blocked until re-armed
+    halted: bool,
+    /// paused by the operator: the strategy still sees every tick, its orders are dropped
+    paused: bool,
+    /// shared account whose funds gate this strategy's opening orders
+    account: Option<Arc<Mutex<Account>>>,
+    /// trading day of the last tick seen, and whether `on_day_close` is still due for it
+    trading_day: i64,
+    day_open: bool,
+    /// the contract a rollover moves this strategy to once it is flat here
+    rolling_to: Option<SymbolType>,
+    /// long and short lots to open again on the contract rolled to
+    reopen: Option<(u32, u32)>,
+    /// flatten on the next tick, by operator command
+    flatten_due: bool,
+    /// the symbol's daily price limits as of its last tick, and the one its book is locked at
+    price_limits: PriceLimits,
+    limit_lock: Option<LimitLock>,
+}
+
+/// A worker's route to the exchange: every order, from a strategy or a stop, passes the same
+/// checks and bookkeeping.
+struct OrderSink {
+    pusher: RefCell<OrderPusher>,
+    paused: PausedView,
+    blocked_symbols: Arc<RwLock<HashSet<SymbolType>>>,
+    /// set when the engine-wide breaker trips
+    halted: Arc<AtomicBool>,
+    /// set by `CtaEngine::replay_events`: the log's orders went out when it was written
+    replaying: Arc<AtomicBool>,
+    ids: Arc<IdGenerator>,
+    metrics: Arc<EngineMetrics>,
+    audit_log: Option<mpsc::Sender<OrderRecord>>,
+    /// sent orders and booked fills for the order store's thread
+    store: Option<mpsc::Sender<StoreRecord>>,
+    journal: Option<Arc<Mutex<Journal>>>,
+    /// without execution reports, orders are booked as filled when sent
+    book_on_send: bool,
+    /// when set, throttled orders and orders for paused symbols wait in `queues`
+    queue_config: Option<QueueConfig>,
+    queues: HashMap<SymbolType, OrderQueue>,
+    /// the worker this sink sends for, and when it started on the current tick
+    worker_id: usize,
+    tick_started_ns: i64,
+}
+
+/// Subscribe the tick feeds to `symbol`; a failure leaves the engine running, deaf to it.
+fn subscribe(feeds: &TickFeeds, symbol: &SymbolType, what: &str) {
+    if let Err(e) = feeds.set_subscribe(&symbol.0) {
+        error!(?symbol, error = ?e, "failed to subscribe {}, its ticks will not arrive", what);
+    }
+}
+
+/// Empty `items`, releasing the sockets they borrow, and keep the allocation for the next poll.
+fn recycle<'a>(mut items: Vec<zmq::PollItem<'_>>) -> Vec<zmq::PollItem<'a>> {
+    items.clear();
+    // an empty vec collected in place: same layout, no reallocation
+    items.into_iter().map(|_| unreachable!()).collect()
+}
+
+/// Each worker in `readers` once, in first-seen order, without allocating on the tick path.
+fn distinct_workers(readers: &[(String, usize)]) -> impl Iterator<Item = usize> + '_ {
+    readers
+        .iter()
+        .enumerate()
+        .filter(|&(i, &(_, worker_id))| !readers[..i].iter().any(|&(_, w)| w == worker_id))
+        .map(|(_, &(_, worker_id))| worker_id)
+}
+
+/// Join an engine thread; if it panicked, log it and keep the first such error in `panicked`.
+fn join<T>(handle: thread::JoinHandle<T>, panicked: &mut Option<EngineError>) -> Option<T> {
+    let name = handle.thread().name().unwrap_or("engine").to_string();
+    handle
+        .join()
+        .inspect_err(|_| error!(thread = %name, "thread panicked"))
+        .map_err(|_| panicked.get_or_insert(EngineError::Panicked(name)))
+        .ok()
+}
+
+/// Pass the strategy's latest ledger on to its shared account, if it has one.
+fn sync_account(strat_perf: &StratPerf, symbol: SymbolType) {
+    if let Some(ref account) = strat_perf.account {
+        account
+            .lock()
+            .unwrap()
+            .update(strat_perf.stg.name().as_str(), symbol, strat_perf.perf.ledger());
+    }
+}
+
+/// Log that `strat_perf` was just throttled for `churn`; its opening orders are refused, and
+/// it hears why from `on_order_rejected` as they are.
+fn warn_churn(strat_perf: &StratPerf, churn: Churn) {
+    warn!(strategy = %strat_perf.stg.name().as_str(), %churn, "strategy churning orders, throttling its opening orders");
+}
+
+/// Apply a fill to the strategy's tracker, stops and conduct counters.
+fn book_fill(strat_perf: &mut StratPerf, fill: &Order) {
+    strat_perf.perf.on_fill(fill);
+    sync_account(strat_perf, fill.symbol);
+    let levels = match fill.offset {
+        OffsetFlagType::OPEN => strat_perf.stg.stop_levels(fill),
+        OffsetFlagType::CLOSE => StopLevels::default(),
+    };
+    strat_perf.stops.on_order(fill, levels);
+    let remaining = strat_perf.pending.on_fill(fill).map_or(0, |rest| rest.lots);
+    if let Some(ref mut conduct) = strat_perf.conduct {
+        conduct.on_trade(fill.timestamp);
+    }
+    strat_perf.order_stats.on_fill(fill.timestamp);
+    if remaining > 0 {
+        debug!(strategy = %fill.stg_name.as_str(), ?fill, remaining, "partial fill");
+    }
+    strat_perf.stg.on_fill(fill, remaining);
+}
+
+/// The strategy a report on `order` belongs to: the one the order is pending with, or by
+/// name for reports on orders the engine has no record of.
+fn report_owner<'a>(strat_map: &'a mut HashMap<SymbolType, Vec<StratPerf>>, order: &Order) -> Option<&'a mut StratPerf> {
+    let strat_perfs = strat_map.get_mut(&order.symbol)?;
+    let by_order = strat_perfs.iter().position(|sp| sp.pending.by_order_id(order.order_id).is_some());
+    let index = by_order.or_else(|| strat_perfs.iter().position(|sp| sp.stg.name().as_str() == order.stg_name.as_str()))?;
+    strat_perfs.get_mut(index)
+}
+
+/// Stop tracking what the gateway reports ended unfilled of a pending order and tell the
+/// strategy, unless it is the cancelled rest of an order to send again; the order as it was
+/// still open and whether to send its rest again, or `None` if it no longer was pending,
+/// e.g. already cancelled by the engine.
+fn end_order(strat_perf: &mut StratPerf, report: &StatusReport) -> Option<(Order, bool)> {
+    let client_id = strat_perf.pending.matching(&report.order)?.client_id;
+    let resend = !report.is_rejection() && strat_perf.pending.is_resending(client_id);
+    let rest = strat_perf.pending.remove(client_id)?;
+    debug!(strategy = %rest.stg_name.as_str(), ?rest, rejected = report.is_rejection(), resend, "order ended unfilled");
+    if report.is_rejection() {
+        strat_perf.stg.on_order_rejected(&rest, "rejected by the exchange");
+    } else if !resend {
+        strat_perf.stg.on_order_cancelled(&rest);
+    }
+    Some((rest, resend))
+}
+
+fn report_tracking(strat_perf: &StratPerf, alert: TrackingAlert, metrics: &EngineMetrics) {
+    let strategy = strat_perf.stg.name();
+    let benchmark = strat_perf.tracking.as_ref().map(|t| t.benchmark().symbol);
+    match alert {
+        TrackingAlert::Drifting(stats) => {
+            warn!(strategy = %strategy.as_str(), ?benchmark, %stats, "strategy drifted from its backtest profile");
+            metrics.tracking_alerts.fetch_add(1, Ordering::Relaxed);
+        }
+        TrackingAlert::Recovered(stats) => info!(strategy = %strategy.as_str(), ?benchmark, %stats, "strategy back within its backtest profile"),
+    }
+}
+
+/// The fill `order` gets when no execution reports are coming: marketable orders trade at the
+/// touch, anything else is assumed to rest (or be killed) unfilled.
+fn assumed_fill(order: &Order, tick: &TickData) -> Option<Order> {
+    if !order.is_marketable(tick) {
+        return None;
+    }
+    let price = match order.direction {
+        DirectionType::BUY => tick.ap1,
+        DirectionType::SELL => tick.bp1,
+    };
+    Some(Order { price, ..*order })
+}
+
+/// Take the daily price limits from `tick` and tell the strategy when its book locks at one
+/// or comes off it.
+fn track_price_limits(strat_perf: &mut StratPerf, tick: &TickData) {
+    strat_perf.price_limits = PriceLimits::of(tick);
+    let lock = tick.limit_lock();
+    if lock == strat_perf.limit_lock {
+        return;
+    }
+    match lock {
+        Some(lock) => warn!(strategy = %strat_perf.stg.name().as_str(), symbol = ?tick.symbol, ?lock, price = tick.last, "locked at price limit"),
+        None => info!(strategy = %strat_perf.stg.name().as_str(), symbol = ?tick.symbol, "off the price limit"),
+    }
+    strat_perf.limit_lock = lock;
+    strat_perf.stg.on_limit_lock(lock);
+}
+
+/// Lots `strat_perf` holds long and short plus those its resting opening orders would add.
+fn opening_exposure(strat_perf: &StratPerf) -> (u32, u32) {
+    let (long, short) = held_lots(strat_perf);
+    strat_perf
+        .pending
+        .iter()
+        .filter(|o| o.offset == OffsetFlagType::OPEN)
+        .fold((long, short), |(long, short), o| match o.direction {
+            DirectionType::BUY => (long + o.lots, short),
+            DirectionType::SELL => (long, short + o.lots),
+        })
+}
+
+/// Why the risk checks refused an order.
+enum Rejection {
+    Paused,
+    NoContract,
+    /// an opening order after a circuit breaker trip
+    Halted,
+    /// an opening order the strategy's cash, or the shared account, could not fund; what it
+    /// would leave available
+    InsufficientFunds(f64),
+    Conduct(ConductWarning),
+    Limit(LimitBreach),
+    /// priced beyond the symbol's daily limits, which the exchange would refuse
+    OutsidePriceLimits(PriceLimits),
+    /// an opening order of a strategy throttled for churning orders, until the stamp given
+    Churning(i64),
+}
+
+impl Rejection {
+    /// Why `order` was refused, for logs, previews and `Strategy::on_order_rejected`.
+    fn describe(&self, order: &Order) -> String {
+        match self {
+            Rejection::Paused => format!("{} is paused", order.symbol.as_str()),
+            Rejection::NoContract => format!("{} has no contract info", order.symbol.as_str()),
+            Rejection::Halted => "circuit breaker tripped, opening orders blocked until re-armed".into(),
+            Rejection::InsufficientFunds(available) => format!("insufficient funds, would leave {:.2} available", available),
+            Rejection::Conduct(breach) => breach.to_string(),
+            Rejection::Limit(breach) => format!("position limit: {}", breach),
+            Rejection::OutsidePriceLimits(limits) => {
+                format!("price {} outside the daily limits {}..{}", order.price, limits.down, limits.up)
+            }
+            Rejection::Churning(until) => format!("throttled for churning orders, opening orders blocked until {}", until),
+        }
+    }
+}
+
+impl OrderSink {
+    /// The risk checks every order passes before it is sent.
+    fn risk_check(&self, strat_perf: &mut StratPerf, order: &Order) -> Result<(), Rejection> {
+        if self.paused.contains(&order.symbol) {
+            return Err(Rejection::Paused);
+        }
+        if self.blocked_symbols.read().unwrap().contains(&order.symbol) {
+            return Err(Rejection::NoContract);
+        }
+        if order.order_type != OrderType::MARKET && !strat_perf.price_limits.contains(order.price) {
+            return Err(Rejection::OutsidePriceLimits(strat_perf.price_limits));
+        }
+        if order.offset == OffsetFlagType::OPEN && (strat_perf.halted || self.halted.load(Ordering::Relaxed)) {
+            return Err(Rejection::Halted);
+        }
+        if order.offset == OffsetFlagType::OPEN {
+            strat_perf.order_stats.admit(order.timestamp).map_err(Rejection::Churning)?;
+        }
+        if order.offset == OffsetFlagType::OPEN {
+            match strat_perf.account {
+                Some(ref account) => {
+                    let after = strat_perf.perf.preview_ledger(order);
+                    let available = account
+                        .lock()
+                        .unwrap()
+                        .available_with(strat_perf.stg.name().as_str(), order.symbol, after);
+                    if available < 0.0 {
+                        return Err(Rejection::InsufficientFunds(available));
+                    }
+                }
+                None => strat_perf.perf.check_margin(order).map_err(Rejection::InsufficientFunds)?,
+            }
+        }
+        if let Some(limits) = strat_perf.limits {
+            let (long, short) = opening_exposure(strat_perf);
+            limits
+                .check(long, short, order, strat_perf.perf.info().point_value())
+                .map_err(Rejection::Limit)?;
+        }
+        if let Some(ref mut conduct) = strat_perf.conduct {
+            conduct.admit_order(order.timestamp).map_err(Rejection::Conduct)?;
+        }
+        Ok(())
+    }
+
+    /// Push a wire struct, including any padding, to the order socket.
+    fn send_raw<T: Copy>(&self, value: &T) -> zmq::Result<()> {
+        if self.replaying.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+        self.pusher.borrow_mut().send(bytes)
+    }
+
+    fn drain_conduct_warnings(strat_perf: &mut StratPerf) {
+        if let Some(ref mut conduct) = strat_perf.conduct {
+            for warning in conduct.take_warnings() {
+                warn!(strategy = %strat_perf.stg.name().as_str(), %warning, "conduct limit warning");
+                strat_perf.stg.on_conduct_warning(&warning);
+            }
+        }
+    }
+
+    /// Check, send and book `order` for `strat_perf`, or hold it in the symbol's order queue
+    /// while the symbol is throttled or paused; `false` if it was blocked or did not fit.
+    fn submit(&mut self, strat_perf: &mut StratPerf, order: Order, tick: &TickData, purpose: Purpose) -> bool {
+        let order = strat_perf.price_offset.apply(order, tick, strat_perf.perf.info().min_move);
+        let Some(config) = self.queue_config else {
+            return self.send_order(strat_perf, order, tick);
+        };
+        let paused = self.paused.contains(&order.symbol);
+        let queue = self.queues.entry(order.symbol).or_insert_with(|| OrderQueue::new(config));
+        // anything already waiting goes first, or an entry could overtake a held exit
+        if !paused && queue.is_empty() && queue.has_room(tick.stamp) {
+            let sent = self.send_order(strat_perf, order, tick);
+            if sent {
+                self.queues.get_mut(&order.symbol).unwrap().on_sent(tick.stamp);
+            }
+            return sent;
+        }
+        let entry = Queued {
+            strategy: strat_perf.stg.name(),
+            order,
+            purpose,
+            queued_at: tick.stamp,
+        };
+        match queue.push(entry) {
+            Admission::Rejected(entry) => {
+                self.drop_queued(&entry, "order queue full");
+                return false;
+            }
+            Admission::Displaced(displaced) => self.drop_queued(&displaced, "displaced from a full order queue"),
+            Admission::Held => {}
+        }
+        debug!(strategy = %order.stg_name.as_str(), ?order, ?purpose, paused, "queued order");
+        self.metrics.orders_queued.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Send what the symbol's order queue holds, most urgent first, as far as its throttle
+    /// allows; entries that waited too long are dropped.
+    fn release(&mut self, strategies: &mut [StratPerf], tick: &TickData) {
+        // taken out so sending can borrow `self`
+        let Some(mut queue) = self.queues.remove(&tick.symbol) else {
+            return;
+        };
+        for stale in queue.expire(tick.stamp) {
+            self.drop_queued(&stale, "queued too long");
+        }
+        while !self.paused.contains(&tick.symbol)
+            && queue.has_room(tick.stamp)
+            && let Some(entry) = queue.pop()
+        {
+            let Some(strat_perf) = strategies.iter_mut().find(|sp| sp.stg.name().as_str() == entry.strategy.as_str()) else {
+                self.drop_queued(&entry, "strategy was removed");
+                continue;
+            };
+            let order = Order {
+                timestamp: tick.stamp,
+                ..entry.order
+            };
+            if self.send_order(strat_perf, order, tick) {
+                queue.on_sent(tick.stamp);
+            }
+        }
+        self.queues.insert(tick.symbol, queue);
+    }
+
+    /// Orders of `strategy` held in `symbol`'s order queue.
+    fn queued(&self, symbol: SymbolType, strategy: NameType) -> impl Iterator<Item = &Order> {
+        self.queues
+            .get(&symbol)
+            .into_iter()
+            .flat_map(OrderQueue::iter)
+            .filter(move |entry| entry.strategy.as_str() == strategy.as_str())
+            .map(|entry| &entry.order)
+    }
+
+    fn drop_queued(&self, entry: &Queued, reason: &str) {
+        warn!(strategy = %entry.strategy.as_str(), order = ?entry.order, purpose = ?entry.purpose, reason, "dropped queued order");
+        self.metrics.queued_orders_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Risk-check, send and book `order` right away; `false` if it was blocked or could not be
+    /// sent, either way reported to the strategy.
+    fn send_order(&self, strat_perf: &mut StratPerf, mut order: Order, tick: &TickData) -> bool {
+        if let Err(rejection) = self.risk_check(strat_perf, &order) {
+            strat_perf.rejected += 1;
+            match rejection {
+                Rejection::OutsidePriceLimits(limits) => {
+                    warn!(strategy = %order.stg_name.as_str(), ?order, ?limits, "blocked order priced beyond the daily limits")
+                }
+                Rejection::Limit(breach) => {
+                    warn!(strategy = %order.stg_name.as_str(), ?order, %breach, "blocked order at position limit");
+                    self.metrics.limit_breaches.fetch_add(1, Ordering::Relaxed);
+                }
+                Rejection::Paused => warn!(strategy = %order.stg_name.as_str(), ?order, "blocked order for paused symbol"),
+                Rejection::NoContract => warn!(strategy = %order.stg_name.as_str(), ?order, "blocked order for symbol without contract info"),
+                Rejection::Halted => warn!(strategy = %order.stg_name.as_str(), ?order, "blocked opening order after circuit breaker trip"),
+                Rejection::InsufficientFunds(available) => {
+                    warn!(strategy = %order.stg_name.as_str(), ?order, available, "blocked opening order without the funds for its margin")
+                }
+                Rejection::Conduct(breach) => {
+                    warn!(strategy = %order.stg_name.as_str(), %breach, "blocked order at conduct limit");
+                    strat_perf.stg.on_conduct_warning(&breach);
+                }
+                Rejection::Churning(until) => {
+                    debug!(strategy = %order.stg_name.as_str(), ?order, until, "blocked opening order of churning strategy")
+                }
+            }
+            strat_perf.stg.on_order_rejected(&order, &rejection.describe(&order));
+            return false;
+        }
+        strat_perf.pending.assign_id(&mut order);
+        order.order_id = self.ids.next(strat_perf.strategy_id);
+        // the book as the strategy saw it, for realized-slippage analysis
+        let book = BookSnapshot::from_tick(tick);
+        debug!(strategy = %order.stg_name.as_str(), ?order, ?book, "send order");
+        // ahead of the send: an order the journal lost could be resting unknown after a crash
+        self.journal(Entry::Order(order));
+
+        if let Err(e) = self.send_raw(&order) {
+            error!(strategy = %order.stg_name.as_str(), error = ?e, "failed to send on PUSH socket");
+            // never reached the gateway: recovery must not take it for resting
+            self.journal(Entry::Cancel(order));
+            strat_perf.stg.on_order_rejected(&order, "send failed");
+            return false;
+        }
+        self.metrics.orders_sent.fetch_add(1, Ordering::Relaxed);
+        if self.tick_started_ns > 0 {
+            let latency = timeutil::now_nanos() - self.tick_started_ns;
+            self.metrics.workers[self.worker_id].strategy_send.record(latency);
+        }
+        if let Some(ref log) = self.audit_log {
+            let _ = log.send(OrderRecord { order, book });
+        }
+        self.store(StoreRecord::Order(order));
+
+        if let Some(ref mut conduct) = strat_perf.conduct {
+            let fee = conduct.on_order(order.timestamp);
+            strat_perf.perf.charge_fee(fee);
+        }
+        if let Some(churn) = strat_perf.order_stats.on_order(order.timestamp) {
+            warn_churn(strat_perf, churn);
+        }
+        if !self.book_on_send {
+            // execution reports fill it
+            strat_perf.pending.insert(order);
+        } else if let Some(fill) = assumed_fill(&order, tick) {
+            book_fill(strat_perf, &fill);
+            self.store(StoreRecord::Fill(fill));
+            self.journal(Entry::Fill(fill));
+        } else if order.order_type == OrderType::LIMIT {
+            debug!(strategy = %order.stg_name.as_str(), ?order, "order not marketable, resting");
+            strat_perf.pending.insert(order);
+        }
+        Self::drain_conduct_warnings(strat_perf);
+        true
+    }
+
+    fn store(&self, record: StoreRecord) {
+        if let Some(ref store) = self.store {
+            // the store only fails after its thread died; keep trading regardless
+            let _ = store.send(record);
+        }
+    }
+
+    fn journal(&self, entry: Entry) {
+        if let Some(ref journal) = self.journal
+            && let Err(e) = journal.lock().unwrap().append(&entry)
+        {
+            error!(?entry, error = ?e, "failed to append to journal");
+        }
+    }
+
+    /// Send a cancel for the resting order `client_id` of `strat_perf`; `false` if it is not
+    /// pending or the conduct limits refuse the cancel.
+    fn cancel(&self, strat_perf: &mut StratPerf, client_id: u64, stamp: i64) -> bool {
+        let Some(order) = self.withdraw(strat_perf, client_id, stamp) else {
+            return false;
+        };
+        strat_perf.stg.on_order_cancelled(&order);
+        true
+    }
+
+    /// Cancel what is left of a partly filled order to send it again at the touch. It stays
+    /// pending until the gateway confirms the cancel, and what the confirmation says was
+    /// left is sent again then (see `resend`).
+    fn cancel_to_resend(&mut self, strat_perf: &mut StratPerf, client_id: u64, stamp: i64) -> bool {
+        let Some(order) = strat_perf.pending.get(client_id).copied() else {
+            return false;
+        };
+        if !self.send_cancel(strat_perf, &order, stamp) {
+            return false;
+        }
+        strat_perf.pending.resend_on_cancel(client_id);
+        true
+    }
+
+    /// Send `rest`, the cancelled remainder of a partly filled order, again at the touch of
+    /// `tick`; the strategy only hears of the cancel if the new order cannot go out.
+    fn resend(&mut self, strat_perf: &mut StratPerf, rest: Order, tick: &TickData) -> bool {
+        let price = match rest.direction {
+            DirectionType::BUY => tick.ap1,
+            DirectionType::SELL => tick.bp1,
+        };
+        let order = Order {
+            timestamp: tick.stamp,
+            price,
+            client_id: 0,
+            order_id: 0,
+            ..rest
+        };
+        debug!(strategy = %order.stg_name.as_str(), ?order, "resending remainder of partly filled order");
+        let sent = self.submit(strat_perf, order, tick, Purpose::of(&order));
+        if !sent {
+            strat_perf.stg.on_order_cancelled(&rest);
+        }
+        sent
+    }
+
+    /// Send a cancel for pending order `client_id` and stop tracking it, without telling the
+    /// strategy; the order with the lots that were still open.
+    fn withdraw(&self, strat_perf: &mut StratPerf, client_id: u64, stamp: i64) -> Option<Order> {
+        let Some(order) = strat_perf.pending.get(client_id).copied() else {
+            warn!(strategy = %strat_perf.stg.name().as_str(), client_id, "cancel for unknown order");
+            return None;
+        };
+        if !self.send_cancel(strat_perf, &order, stamp) {
+            return None;
+        }
+        strat_perf.pending.remove(client_id);
+        self.journal(Entry::Cancel(order));
+        Some(order)
+    }
+
+    /// Send a cancel for `order`, counting it against the conduct and churn limits; `false` if
+    /// the conduct limits refuse it or it could not be sent.
+    fn send_cancel(&self, strat_perf: &mut StratPerf, order: &Order, stamp: i64) -> bool {
+        if let Some(ref mut conduct) = strat_perf.conduct
+            && let Err(breach) = conduct.admit_cancel(stamp, order.lots)
+        {
+            warn!(strategy = %order.stg_name.as_str(), %breach, "blocked cancel at conduct limit");
+            strat_perf.stg.on_conduct_warning(&breach);
+            return false;
+        }
+        let cancel = OrderCancel {
+            stg_name: order.stg_name,
+            symbol: order.symbol,
+            timestamp: stamp,
+            client_id: order.client_id,
+        };
+        if let Err(e) = self.send_raw(&cancel) {
+            error!(strategy = %order.stg_name.as_str(), client_id = order.client_id, error = ?e, "failed to send cancel on PUSH socket");
+            return false;
+        }
+        debug!(strategy = %order.stg_name.as_str(), ?order, "cancel order");
+        self.metrics.cancels_sent.fetch_add(1, Ordering::Relaxed);
+        if let Some(ref mut conduct) = strat_perf.conduct {
+            let fee = conduct.on_cancel(stamp, order.lots);
+            strat_perf.perf.charge_fee(fee);
+        }
+        if let Some(churn) = strat_perf.order_stats.on_cancel(stamp) {
+            warn_churn(strat_perf, churn);
+        }
+        Self::drain_conduct_warnings(strat_perf);
+        true
+    }
+
+    /// Cancel every resting order of `strat_perf` and close its positions at the touch.
+    fn flatten(&mut self, strat_perf: &mut StratPerf, tick: &TickData) {
+        let resting: Vec<u64> = strat_perf.pending.iter().map(|o| o.client_id).collect();
+        for client_id in resting {
+            self.cancel(strat_perf, client_id, tick.stamp);
+        }
+        for order in strat_perf.perf.closing_orders(strat_perf.stg.name(), tick) {
+            if self.submit(strat_perf, order, tick, Purpose::Flatten) {
+                strat_perf.stops.disarm(&order);
+            }
+        }
+    }
+
+    /// Open `long` and `short` lots again at the touch, for a strategy rolled onto `tick`'s contract.
+    fn reopen(&mut self, strat_perf: &mut StratPerf, tick: &TickData, long: u32, short: u32) {
+        for (lots, direction, price) in [(long, DirectionType::BUY, tick.ap1), (short, DirectionType::SELL, tick.bp1)] {
+            if lots == 0 {
+                continue;
+            }
+            let order = Order {
+                stg_name: strat_perf.stg.name(),
+                symbol: tick.symbol,
+                timestamp: tick.stamp,
+                price,
+                lots,
+                direction,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            };
+            self.submit(strat_perf, order, tick, Purpose::Open);
+        }
+    }
+
+    /// Outcome of `order` through `risk_check` and the tracker's margin calculation, as text.
+    fn preview(&self, strat_perf: &mut StratPerf, order: &Order) -> String {
+        if let Err(rejection) = self.risk_check(strat_perf, order) {
+            return format!("rejected: {}", rejection.describe(order));
+        }
+        let impact = strat_perf.perf.preview(order);
+        let mut reply = format!(
+            "accepted fee={:.2} realized_pnl={:.2} margin_change={:.2} cash_change={:.2} available_cash={:.2}",
+            impact.fee, impact.realized_pnl, impact.margin_change, impact.cash_change, impact.available_cash
+        );
+        if let Some(ref account) = strat_perf.account {
+            let after = strat_perf.perf.preview_ledger(order);
+            let available = account
+                .lock()
+                .unwrap()
+                .available_with(strat_perf.stg.name().as_str(), order.symbol, after);
+            reply.push_str(&format!(" account_available={:.2}", available));
+        } else if impact.available_cash < 0.0 {
+            reply.push_str(" (warning: available cash would go negative)");
+        }
+        reply
+    }
+}
+
+/// The strategy's orders for `tick`: those reaching its target position, counting what it
+/// holds, what is pending and what waits in the order queue, or else what `update` sends.
+fn strategy_orders(strat_perf: &mut StratPerf, tick: &TickData, sink: &OrderSink) -> Orders {
+    let Some(target) = strat_perf.stg.target_position(tick) else {
+        return strat_perf.stg.update(tick);
+    };
+    let name = strat_perf.stg.name();
+    let held = held_lots(strat_perf);
+    let in_flight = strat_perf.pending.iter().chain(sink.queued(tick.symbol, name));
+    strat_perf.positions.rebalance(name, tick, target, held, in_flight)
+}
+
+/// Run the strategy (see `strategy_orders`) unless its resource budget has it skip this tick,
+/// measuring the call when a sample is due.
+fn budgeted_update(strat_perf: &mut StratPerf, tick: &TickData, sink: &OrderSink, metrics: &EngineMetrics) -> Orders {
+    let Some(meter) = strat_perf.budget.as_mut() else {
+        return strategy_orders(strat_perf, tick, sink);
+    };
+    if !meter.admit() {
+        return Orders::new();
+    }
+    if !meter.should_sample() {
+        return strategy_orders(strat_perf, tick, sink);
+    }
+    let (cpu_start, alloc_start) = (budget::thread_cpu_ns(), budget::thread_allocated());
+    let orders = strategy_orders(strat_perf, tick, sink);
+    let cpu_ns = budget::thread_cpu_ns().saturating_sub(cpu_start);
+    let alloc_bytes = budget::thread_allocated().saturating_sub(alloc_start);
+    // the meter is borrowed anew: the strategy needed all of `strat_perf` in between
+    if let Some(violation) = strat_perf.budget.as_mut().and_then(|meter| meter.record(cpu_ns, alloc_bytes)) {
+        metrics.budget_downgrades.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            strategy = %strat_perf.stg.name().as_str(),
+            cpu_us = violation.cpu_us,
+            alloc_bytes = violation.alloc_bytes,
+            subsample = violation.subsample,
+            "strategy over its resource budget, downgraded to subsampled ticks"
+        );
+    }
+    orders
+}
+
+/// Run `ticks` through the strategy with its orders dropped, then have it adopt the position
+/// it actually holds, as positions it thinks it took on the way were never sent.
+fn warm_up(strat_perf: &mut StratPerf, ticks: &[TickData]) {
+    for tick in ticks {
+        if strat_perf.stg.target_position(tick).is_none() {
+            let _ = strat_perf.stg.update(tick);
+        }
+    }
+    let (long, short) = held_lots(strat_perf);
+    strat_perf.stg.on_resume(long, short);
+}
+
+/// Long and short lots the strategy holds.
+fn held_lots(strat_perf: &StratPerf) -> (u32, u32) {
+    let (long, short) = strat_perf.perf.positions();
+    (long.map_or(0, |p| p.lots), short.map_or(0, |p| p.lots))
+}
+
+/// Snapshot of a strategy's positions, cash and resting orders for the handoff file.
+fn export_state(symbol: SymbolType, strat_perf: &StratPerf) -> StrategyState {
+    let (long, short) = strat_perf.perf.positions();
+    StrategyState {
+        strategy: strat_perf.stg.name().as_str().to_string(),
+        symbol: symbol.as_str().to_string(),
+        available_cash: strat_perf.perf.available_cash(),
+        long,
+        short,
+        open_orders: strat_perf.pending.iter().map(OpenOrder::from_order).collect(),
+    }
+}
+
+/// Take over what a previous run handed off: positions and cash, stops re-armed at the
+/// average entry price, and resting orders so their fills and cancels are recognized.
+fn restore_state(symbol: SymbolType, strat_perf: &mut StratPerf, state: &StrategyState) {
+    strat_perf.perf.restore(state);
+    let name = strat_perf.stg.name();
+    for (side, direction) in [(state.long, DirectionType::BUY), (state.short, DirectionType::SELL)] {
+        let Some(position) = side else {
+            continue;
+        };
+        let entry = Order {
+            stg_name: name,
+            symbol,
+            timestamp: 0,
+            price: position.avg_price,
+            lots: position.lots,
+            direction,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+        let levels = strat_perf.stg.stop_levels(&entry);
+        strat_perf.stops.on_order(&entry, levels);
+    }
+    for open in &state.open_orders {
+        let mut order = open.to_order(name, symbol);
+        // keeps ids the engine assigns later clear of the resumed ones
+        strat_perf.pending.assign_id(&mut order);
+        strat_perf.pending.insert(order);
+    }
+    let lots = |side: Option<PositionState>| side.map_or(0, |p| p.lots);
+    strat_perf.stg.on_resume(lots(state.long), lots(state.short));
+}
+
+/// Book what the journal recorded for this strategy as it was booked live: orders rest until
+/// their fills or cancels, fills move positions, cash and stops.
+fn replay_journal<'a>(strat_perf: &mut StratPerf, entries: impl IntoIterator<Item = &'a Entry>) {
+    for entry in entries {
+        match *entry {
+            Entry::Tick { .. } => {}
+            Entry::Order(mut order) => {
+                strat_perf.pending.assign_id(&mut order);
+                if let Some(ref mut conduct) = strat_perf.conduct {
+                    let fee = conduct.on_order(order.timestamp);
+                    strat_perf.perf.charge_fee(fee);
+                }
+                strat_perf.order_stats.on_order(order.timestamp);
+                // orders booked as filled on send are taken off again by their fill
+                strat_perf.pending.insert(order);
+            }
+            Entry::Fill(fill) => book_fill(strat_perf, &fill),
+            Entry::Cancel(order) => {
+                strat_perf.pending.remove(order.client_id);
+                strat_perf.order_stats.on_cancel(order.timestamp);
+            }
+        }
+    }
+    let (long, short) = held_lots(strat_perf);
+    strat_perf.stg.on_resume(long, short);
+}
+
+/// A strategy's positions and PnL as its worker sees them, for the query socket.
+fn snapshot(worker_id: usize, symbol: SymbolType, last_price: f64, strat_perf: &StratPerf, filter: &SnapshotFilter) -> StrategySnapshot {
+    let (long, short) = strat_perf.perf.positions();
+    let curve = strat_perf.perf.market_values();
+    let fills = strat_perf.perf.recent_fills();
+    StrategySnapshot {
+        strategy: strat_perf.stg.name().as_str().to_string(),
+        symbol: symbol.as_str().to_string(),
+        worker: worker_id,
+        equity: strat_perf.perf.equity(),
+        available_cash: strat_perf.perf.available_cash(),
+        last_price,
+        multiplier: strat_perf.perf.info().point_value(),
+        realized_pnl: strat_perf.perf.realized_pnl(),
+        total_fee: strat_perf.perf.total_fee(),
+        long,
+        short,
+        open_orders: strat_perf.pending.len(),
+        fills: strat_perf.perf.fill_count(),
+        rejected_orders: strat_perf.rejected,
+        halted: strat_perf.halted,
+        paused: strat_perf.paused,
+        account: strat_perf.account.as_ref().map(|a| a.lock().unwrap().name().to_string()),
+        equity_curve: curve.tail(filter.curve_points),
+        curve_samples: curve.samples(),
+        performance: curve.stats(),
+        recent_fills: fills
+            .iter()
+            .skip(fills.len().saturating_sub(filter.recent_fills))
+            .map(|(fill, _)| OpenOrder::from_order(fill))
+            .collect(),
+        booked_fills: match filter.fills_since {
+            Some(since) => fills
+                .iter()
+                .filter(|(fill, _)| fill.timestamp >= since)
+                .map(|(fill, cost)| FillDetail {
+                    fill: OpenOrder::from_order(fill),
+                    fee: cost.fee,
+                    realized_pnl: cost.realized_pnl,
+                })
+                .collect(),
+            None => Vec::new(),
+        },
+    }
+}
+
+/// What a worker hands back when it exits: the state of its strategies and the last tick it
+/// processed per symbol.
+type WorkerHandoff = (Vec<StrategyState>, HashMap<SymbolType, LastTick>);
+
+/// Everything a worker thread can be asked to do, in arrival order.
+// Ticks dominate the traffic; boxing them would cost an allocation per tick.
+#[allow(clippy::large_enum_variant)]
+enum WorkerMsg {
+    /// a tick, with the `timeutil::now_nanos` it was dispatched at
+    Tick(TickData, i64),
+    Fill(ExecutionReport),
+    Status(StatusReport),
+    /// latest price of a symbol some strategies are benchmarked against
+    Benchmark {
+        symbol: SymbolType,
+        price: f64,
+    },
+    /// tick of an auxiliary symbol some strategies read as an input
+    Reference(TickData),
+    Session {
+        symbol: SymbolType,
+        session: Session,
+        started: bool,
+        /// the session that ended was the trading day's last
+        closes_day: bool,
+    },
+    UpdateParam {
+        strategy: String,
+        name: String,
+        value: f64,
+    },
+    SetToggle {
+        strategy: String,
+        name: String,
+        enabled: bool,
+    },
+    SetPaused {
+        strategy: String,
+        paused: bool,
+    },
+    Cancel {
+        strategy: String,
+        client_id: u64,
+    },
+    Preview {
+        strategy: String,
+        order: Order,
+        reply: mpsc::Sender<String>,
+    },
+    Snapshot {
+        filter: SnapshotFilter,
+        reply: mpsc::Sender<Vec<StrategySnapshot>>,
+    },
+    AddStrategy {
+        symbol: SymbolType,
+        strat_perf: StratPerf,
+    },
+    RemoveStrategy {
+        strategy: String,
+    },
+    /// flatten the symbol's strategies on its next tick and halt them until re-armed
+    Flatten {
+        symbol: SymbolType,
+    },
+    /// re-arm the breakers of the strategies named `strategy`, or of all when `None`
+    Rearm {
+        strategy: Option<String>,
+    },
+    /// flatten every strategy now, at its symbol's last quote, and halt them until re-armed
+    FlattenAll,
+    /// fees and margins of `symbol` changed in the reloaded table
+    SetContract {
+        symbol: SymbolType,
+        info: ContractInfo,
+    },
+    /// move the strategies on `from` to `to`, see `rollover`
+    Roll(Roll),
 }
 
 pub struct CtaEngine {
     num_workers: usize,
-    senders: Vec<mpsc::Sender<TickData>>,
-    handles: Vec<thread::JoinHandle<()>>,
+    senders: Vec<queue::Sender<WorkerMsg>>,
+    /// size of each worker's queue, and how long its worker spins on an empty one before parking
+    queue_capacity: usize,
+    queue_spin: u32,
+    affinity: CpuAffinity,
+    receive_mode: ReceiveMode,
+    /// set to leave the receive loop, e.g. from a Ctrl-C handler
+    shutdown: Arc<AtomicBool>,
+    handles: Vec<thread::JoinHandle<WorkerHandoff>>,
 
     ctx: zmq::Context,
-    /// We store the subscriber as an `Option` so that `stop()` can `.take()` and drop it,
-    /// which causes the blocking `recv_into` to return an error.
-    tick_subscriber: Option<zmq::Socket>,
+    /// We store the subscribers as an `Option` so that `stop()` can `.take()` and drop them,
+    /// which causes the blocking `recv_into` to return an error. `start()` also takes them
+    /// while running, so control commands can (un)subscribe through a local borrow.
+    tick_subscriber: Option<TickFeeds>,
+    /// picks the feed whose ticks are dispatched and drops repeated ticks
+    feed_selector: FeedSelector,
+    /// feed handler sequence per symbol, from v2 frames
+    sequences: SequenceTracker,
 
     stg_map: HashMap<SymbolType, Vec<StratPerf>>,
     symbol_batches: Vec<HashSet<SymbolType>>,
+    /// Which worker owns each symbol, decided once by `router` in `add_strategy`.
+    router: Box<dyn Router>,
+    /// the part of the symbol universe this process trades, when several split it
+    shard: Option<Shard>,
+    symbol_workers: HashMap<SymbolType, usize>,
+    /// Names of the strategies registered on each symbol (the strategies themselves live in workers).
+    symbol_strategies: HashMap<SymbolType, Vec<String>>,
+    /// Strategies tracked against each benchmark symbol, with their worker.
+    benchmarks: HashMap<SymbolType, Vec<(String, usize)>>,
+    /// Strategies reading each auxiliary reference symbol, with their worker.
+    references: HashMap<SymbolType, Vec<(String, usize)>>,
+    tick_uris: Vec<String>,
     order_uri: String,
+    /// backoff for rebuilding the SUB and PUSH sockets after an error
+    reconnect: ReconnectPolicy,
+
+    /// Fee/margin table and starting cash used for strategies added through the control socket.
+    contracts: HashMap<String, ContractInfo>,
+    /// finds the `contracts` entry of a symbol added without one
+    symbol_map: SymbolMap,
+    /// moves strategies added on a dominant alias on to the next contract
+    rollover: Option<Rollover>,
+    /// contracts workers have moved every strategy off after a roll, for the receive loop to
+    /// stop receiving
+    rolled_off: Arc<Mutex<Vec<SymbolType>>>,
+    init_cash: f64,
+    /// what `init_cash` and every tracker's amounts are denominated in
+    account_currency: Currency,
+    missing_contract: MissingContract,
+    /// Contract each symbol's fees were resolved from, for symbols added by contract.
+    symbol_contracts: HashMap<SymbolType, String>,
+    /// Symbols registered without contract info under `MissingContract::Block`; shared with workers.
+    blocked_symbols: Arc<RwLock<HashSet<SymbolType>>>,
+    /// commission models by contract, in place of the fee columns of `contracts`
+    fee_models: HashMap<String, FeeModel>,
+    /// when set, `contracts` follows the fees file it watches
+    fees_watcher: Option<FeesWatcher>,
+
+    /// When set, every received tick is also archived under this directory.
+    record_dir: Option<PathBuf>,
+    recorder_sender: Option<mpsc::Sender<TickData>>,
+    recorder_handle: Option<thread::JoinHandle<()>>,
+    /// When set, every input is appended to this event log before it is applied.
+    event_log_path: Option<PathBuf>,
+    event_sender: Option<mpsc::Sender<(i64, Event)>>,
+    event_log_handle: Option<thread::JoinHandle<()>>,
+
+    /// Optional REP socket serving `control::Command`s from operators.
+    control_socket: Option<zmq::Socket>,
+    /// Optional REP socket answering `query::Query`s with JSON snapshots.
+    query_socket: Option<zmq::Socket>,
+    query_uri: Option<String>,
+    /// Address of the embedded dashboard, which reads from the query socket.
+    #[cfg(feature = "dashboard")]
+    dashboard_addr: Option<String>,
+    /// PULL socket for execution reports; when set, fills come from here instead of on send.
+    report_socket: Option<zmq::Socket>,
+    report_uri: Option<String>,
+    seq_store: Option<Arc<Mutex<SeqStore>>>,
+    /// Symbols whose ticks are not dispatched and whose orders are blocked; shared with workers.
+    paused_symbols: Arc<PausedSymbols>,
+    /// the receive loop's reader of `paused_symbols`
+    paused: PausedView,
+
+    /// Every submitted order with the book it was decided on; shared by all workers.
+    audit_log: Option<AuditLog>,
+    audit_log_path: Option<PathBuf>,
+    /// settlement statements are exported here, see `statement`
+    statement_dir: Option<PathBuf>,
+    /// stamp the last statement was exported at; the next one covers what came after
+    statement_since: i64,
+    /// When set, sent orders and fills are written to this SQLite database by its own thread.
+    order_store_path: Option<PathBuf>,
+    order_store_sender: Option<mpsc::Sender<StoreRecord>>,
+    order_store_handle: Option<thread::JoinHandle<()>>,
+
+    metrics: Arc<EngineMetrics>,
+    /// `timeutil::now_nanos` when the tick being dispatched was read off the SUB socket
+    tick_recv_ns: i64,
+    /// stamp of the last tick dispatched, for heartbeats
+    last_tick_stamp: Option<i64>,
+    heartbeat: Option<HeartbeatPublisher>,
+    /// When set, `init()` serves `metrics` over HTTP at this address.
+    metrics_addr: Option<String>,
+    /// strategy kinds from `cdylib` plugins; these shadow built-in kinds of the same name
+    plugins: PluginRegistry,
+    conduct_limits: Option<ConductLimits>,
+    churn_limits: Option<ChurnLimits>,
+    /// resource budget of strategies without one of their own in `strategy_budgets`
+    resource_budget: Option<ResourceBudget>,
+    strategy_budgets: HashMap<String, ResourceBudget>,
+    /// how far through the book each strategy's limit orders are priced
+    price_offsets: HashMap<String, PriceOffset>,
+    /// what becomes of partly filled orders of strategies without a policy of their own
+    partial_fill_policy: PartialFillPolicy,
+    strategy_partial_fills: HashMap<String, PartialFillPolicy>,
+    /// reference symbols configured per strategy, on top of the ones it declares
+    strategy_references: HashMap<String, Vec<SymbolType>>,
+    /// per-symbol order throttle and priority queue, when enabled
+    order_queue: Option<QueueConfig>,
+    /// circuit breaker of strategies without one of their own in `strategy_breakers`
+    breaker_limits: Option<BreakerLimits>,
+    strategy_breakers: HashMap<String, BreakerLimits>,
+    strategy_limits: HashMap<String, PositionLimits>,
+    /// breaker over the summed equity of all strategies, checked once a second
+    global_breaker: Option<DrawdownBreaker>,
+    /// set while the global breaker is tripped; shared with workers
+    halted: Arc<AtomicBool>,
+    /// set once `replay_events` starts; the workers then send nothing to the gateway
+    replaying: Arc<AtomicBool>,
+    /// engine order ids, shared by the workers
+    ids: Arc<IdGenerator>,
+    /// strategy name → the id its order ids carry
+    strategy_ids: HashMap<String, u16>,
+    /// accounts by name, shared by the strategies assigned to them in `strategy_accounts`
+    accounts: HashMap<String, Arc<Mutex<Account>>>,
+    strategy_accounts: HashMap<String, String>,
+    /// daily bars the risk report estimates covariances from, and how many days it looks back
+    risk_history: Option<(BarStore, usize)>,
+    /// bars strategies may warm up from in `on_start`
+    history: Option<Arc<BarStore>>,
+    /// where strategies' warm-up ticks come from, and how many each is run through
+    warmup: Option<(Arc<dyn WarmupSource>, usize)>,
+    daily_report: Option<ReportConfig>,
+    daily_report_callback: Option<DailyReportCallback>,
+    /// where each strategy stood at the previous daily report
+    report_marks: Marks,
+    /// local day of the last daily report
+    reported_day: i64,
+    watchdog: Option<Watchdog>,
+    /// receive-time clock for the watchdog, skew monitor and feed failover
+    clock: SharedClock,
+    skew: Option<SkewMonitor>,
+    calendar: Option<(TradingCalendar, SessionFilter)>,
+    /// session each subscribed symbol was in at the last sweep
+    symbol_sessions: HashMap<SymbolType, Session>,
+    /// tick-time minute of the last sweep; session boundaries fall on whole minutes
+    session_minute: i64,
+    data_alert_callback: Option<DataAlertCallback>,
+    wire_format: WireFormat,
+    /// When set, `stop()` writes a handoff file here once the workers have drained.
+    handoff_path: Option<PathBuf>,
+    /// handoff of a previous run, applied to the registered strategies by `init()`
+    resume: Option<Handoff>,
+    /// the write-ahead journal, shared with the workers
+    journal: Option<Arc<Mutex<Journal>>>,
+    /// entries of a crashed run's journal, replayed into the registered strategies by `init()`
+    recovered: Option<Vec<Entry>>,
+    /// how strategies added from now on keep their equity curves; every tick's equity when `None`
+    curve_config: Option<CurveConfig>,
 }
 
 impl CtaEngine {
-    pub fn new(tick_uri: &str, order_uri: &str, num_workers: usize) -> Self {
+    /// Subscribe to ticks from `tick_uris`, highest priority first: the first is the primary
+    /// feed, the rest are backups the engine fails over to when the primary stops publishing.
+    pub fn new(tick_uris: &[&str], order_uri: &str, num_workers: usize) -> Result<Self, EngineError> {
         let ctx = zmq::Context::new();
-        let subscriber = ctx.socket(zmq::SUB).expect("Failed to create SUB socket");
-        // unlimited RCVHWM, subscriber.recv_into won't block
-        subscriber.set_rcvhwm(0).expect("Failed to set rcvhwm");
-        // subscriber.set_rcvtimeo(10000).expect("Failed to set rcvtimo");
-        subscriber.connect(tick_uri).expect("Failed to connect SUB socket to tick_uri");
+        let feeds = TickFeeds::connect(&ctx, tick_uris)?;
+        let paused_symbols = Arc::new(PausedSymbols::default());
 
-        CtaEngine {
+        Ok(CtaEngine {
             num_workers,
             senders: Vec::with_capacity(num_workers),
+            queue_capacity: 16 * 1024,
+            queue_spin: 20_000,
+            affinity: CpuAffinity::default(),
+            shard: None,
+            receive_mode: ReceiveMode::default(),
+            shutdown: Arc::new(AtomicBool::new(false)),
             handles: Vec::with_capacity(num_workers),
             ctx,
-            tick_subscriber: Some(subscriber),
+            feed_selector: FeedSelector::new(feeds.len(), 3000),
+            sequences: SequenceTracker::new(),
+            tick_subscriber: Some(feeds),
             stg_map: HashMap::new(),
             symbol_batches: vec![HashSet::new(); num_workers],
+            router: Box::new(HashRouter),
+            symbol_workers: HashMap::new(),
+            symbol_strategies: HashMap::new(),
+            benchmarks: HashMap::new(),
+            references: HashMap::new(),
+            tick_uris: tick_uris.iter().map(|uri| uri.to_string()).collect(),
             order_uri: order_uri.into(),
+            reconnect: ReconnectPolicy::default(),
+            contracts: HashMap::new(),
+            symbol_map: SymbolMap::new(),
+            rollover: None,
+            rolled_off: Arc::default(),
+            init_cash: 1e6,
+            account_currency: Currency::default(),
+            missing_contract: MissingContract::default(),
+            symbol_contracts: HashMap::new(),
+            blocked_symbols: Arc::new(RwLock::new(HashSet::new())),
+            fee_models: HashMap::new(),
+            fees_watcher: None,
+            record_dir: None,
+            recorder_sender: None,
+            recorder_handle: None,
+            event_log_path: None,
+            event_sender: None,
+            event_log_handle: None,
+            control_socket: None,
+            query_socket: None,
+            query_uri: None,
+            #[cfg(feature = "dashboard")]
+            dashboard_addr: None,
+            report_socket: None,
+            report_uri: None,
+            seq_store: None,
+            paused: paused_symbols.view(),
+            paused_symbols,
+            audit_log: None,
+            audit_log_path: None,
+            statement_dir: None,
+            statement_since: i64::MIN,
+            order_store_path: None,
+            order_store_sender: None,
+            order_store_handle: None,
+            metrics: Arc::new(EngineMetrics::new(num_workers)),
+            tick_recv_ns: 0,
+            last_tick_stamp: None,
+            heartbeat: None,
+            metrics_addr: None,
+            plugins: PluginRegistry::default(),
+            conduct_limits: None,
+            churn_limits: None,
+            resource_budget: None,
+            strategy_budgets: HashMap::new(),
+            price_offsets: HashMap::new(),
+            partial_fill_policy: PartialFillPolicy::default(),
+            strategy_partial_fills: HashMap::new(),
+            strategy_references: HashMap::new(),
+            order_queue: None,
+            breaker_limits: None,
+            strategy_breakers: HashMap::new(),
+            strategy_limits: HashMap::new(),
+            accounts: HashMap::new(),
+            strategy_accounts: HashMap::new(),
+            global_breaker: None,
+            halted: Arc::new(AtomicBool::new(false)),
+            replaying: Arc::new(AtomicBool::new(false)),
+            ids: Arc::new(IdGenerator::new(timeutil::now_stamp())),
+            strategy_ids: HashMap::new(),
+            risk_history: None,
+            history: None,
+            warmup: None,
+            daily_report: None,
+            daily_report_callback: None,
+            report_marks: Marks::new(),
+            reported_day: i64::MIN,
+            watchdog: None,
+            clock: Arc::new(SystemClock),
+            skew: None,
+            calendar: None,
+            symbol_sessions: HashMap::new(),
+            session_minute: i64::MIN,
+            data_alert_callback: None,
+            wire_format: WireFormat::default(),
+            handoff_path: None,
+            resume: None,
+            journal: None,
+            recovered: None,
+            curve_config: None,
+        })
+    }
+
+    /// Append every submitted order, with a top-of-book snapshot, to the CSV at `path`.
+    pub fn enable_audit_log<P: AsRef<Path>>(&mut self, path: P) -> Result<(), EngineError> {
+        let log = AuditLog::open(&path).map_err(EngineError::file("audit log", &path))?;
+        self.audit_log = Some(log);
+        self.audit_log_path = Some(path.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    /// Persist every sent order and booked fill to the SQLite database at `path` (see
+    /// `store`), tagged with this run's start stamp as its session. Must be called before
+    /// `init()`.
+    pub fn enable_order_store<P: AsRef<Path>>(&mut self, path: P) {
+        self.order_store_path = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Track each strategy's order flow against exchange conduct limits: strategies are warned
+    /// as they approach a limit, orders that would breach one are dropped, and message fees
+    /// are charged to the strategy's tracker. Applies to strategies added after this call.
+    pub fn enable_conduct_limits(&mut self, limits: ConductLimits) {
+        self.conduct_limits = Some(limits);
+    }
+
+    /// Throttle strategies that churn orders: one whose order flow passes `limits` has its
+    /// opening orders refused for the cooldown. Order counts are kept and published to the
+    /// metrics either way. Applies to strategies added after this call.
+    pub fn enable_churn_limits(&mut self, limits: ChurnLimits) {
+        self.churn_limits = Some(limits);
+    }
+
+    /// Hold every strategy to `budget`: one that averages more CPU time or allocation per
+    /// `update` is downgraded to subsampled ticks with an alert. Applies to strategies added
+    /// after this call.
+    pub fn enable_resource_budget(&mut self, budget: ResourceBudget) {
+        self.resource_budget = Some(budget);
+    }
+
+    /// Hold orders back per symbol instead of sending or dropping them while the symbol is at
+    /// `config`'s order-rate throttle or paused, releasing exits ahead of entries as room
+    /// returns. Must be called before `init()`.
+    pub fn enable_order_queue(&mut self, config: QueueConfig) {
+        self.order_queue = Some(config);
+    }
+
+    /// Reprice every limit order of the strategy named `strategy` with `offset` before it is
+    /// sent. Applies to strategies added after this call.
+    pub fn set_price_offset(&mut self, strategy: &str, offset: PriceOffset) {
+        self.price_offsets.insert(strategy.into(), offset);
+    }
+
+    /// Cancel, or cancel and resend at the touch, the unfilled rest of partly filled orders
+    /// once they have rested as long as `policy` allows, instead of leaving them resting.
+    /// Applies to strategies added after this call.
+    pub fn set_partial_fill_policy(&mut self, policy: PartialFillPolicy) {
+        self.partial_fill_policy = policy;
+    }
+
+    /// Partial-fill policy for the strategy named `strategy`, in place of the engine-wide one.
+    pub fn set_strategy_partial_fill_policy(&mut self, strategy: &str, policy: PartialFillPolicy) {
+        self.strategy_partial_fills.insert(strategy.into(), policy);
+    }
+
+    /// Feed the strategy named `strategy` the ticks of `symbols` as references, in addition to
+    /// those it declares (see `Strategy::references`). Applies to strategies added after this
+    /// call.
+    pub fn add_references(&mut self, strategy: &str, symbols: &[SymbolType]) {
+        self.strategy_references.entry(strategy.into()).or_default().extend_from_slice(symbols);
+    }
+
+    /// Budget for the strategy named `strategy`, in place of the engine-wide one.
+    pub fn set_strategy_budget(&mut self, strategy: &str, budget: ResourceBudget) {
+        self.strategy_budgets.insert(strategy.into(), budget);
+    }
+
+    /// Trip a strategy's circuit breaker when its equity breaches `limits`: its positions are
+    /// flattened and its opening orders blocked until `rearm`. Applies to strategies added
+    /// after this call.
+    pub fn enable_breaker(&mut self, limits: BreakerLimits) {
+        self.breaker_limits = Some(limits);
+    }
+
+    /// Breaker limits for the strategy named `strategy`, in place of the engine-wide ones.
+    pub fn set_strategy_breaker(&mut self, strategy: &str, limits: BreakerLimits) {
+        self.strategy_breakers.insert(strategy.into(), limits);
+    }
+
+    /// Cap the position the strategy named `strategy` may open up to; opening orders past
+    /// it are refused and reported through `Strategy::on_order_rejected`. Applies to
+    /// strategies added after this call.
+    pub fn set_strategy_limits(&mut self, strategy: &str, limits: PositionLimits) {
+        self.strategy_limits.insert(strategy.into(), limits);
+    }
+
+    /// Trip every strategy at once when the summed equity of all of them breaches `limits`,
+    /// checked once a second. Must be called before `init()`.
+    pub fn enable_global_breaker(&mut self, limits: BreakerLimits) {
+        self.global_breaker = Some(DrawdownBreaker::new(limits));
+    }
+
+    /// Open a shared account named `name` with `init_cash`; strategies assigned to it with
+    /// `set_strategy_account` draw on one cash and margin pool, netted as `netting` says.
+    pub fn add_account(&mut self, name: &str, init_cash: f64, netting: MarginNetting) {
+        self.accounts
+            .insert(name.into(), Arc::new(Mutex::new(Account::new(name, init_cash, netting))));
+    }
+
+    /// Trade the strategy named `strategy` out of the shared account `account`: its opening
+    /// orders are refused once the account cannot fund them, while its own tracker keeps
+    /// attributing PnL, fees and margin to it. Applies to strategies added after this call.
+    pub fn set_strategy_account(&mut self, strategy: &str, account: &str) {
+        self.strategy_accounts.insert(strategy.into(), account.into());
+    }
+
+    /// Every shared account with each member's share, ordered by name.
+    pub fn accounts(&self) -> Vec<AccountSummary> {
+        let mut summaries: Vec<AccountSummary> = self.accounts.values().map(|a| a.lock().unwrap().summary()).collect();
+        summaries.sort_by(|a, b| a.account.cmp(&b.account));
+        summaries
+    }
+
+    /// Summed equity of every strategy.
+    fn total_equity(&self) -> Result<f64, String> {
+        let snapshots = self.snapshot(SnapshotFilter::default())?;
+        Ok(snapshots.iter().map(|s| s.equity).sum())
+    }
+
+    fn check_global_breaker(&mut self, now: i64) {
+        if self.global_breaker.as_ref().is_none_or(|b| b.tripped().is_some()) {
+            return;
+        }
+        let equity = match self.total_equity() {
+            Ok(equity) => equity,
+            Err(e) => {
+                warn!(error = %e, "could not check the global circuit breaker");
+                return;
+            }
+        };
+        if let Some(trip) = self.global_breaker.as_mut().and_then(|b| b.on_equity(now, equity)) {
+            error!(%trip, equity, "global circuit breaker tripped, flattening every strategy");
+            self.metrics.breaker_trips.fetch_add(1, Ordering::Relaxed);
+            self.halted.store(true, Ordering::Relaxed);
         }
     }
 
-    /// Register a strategy for a given symbol.  We store it in stg_map as a
-    /// Box<dyn Strategy>.  It will not be shared—only one worker thread gets it.
-    pub fn add_strategy(&mut self, symbol: SymbolType, strategy: Box<dyn Strategy>, performance_tracker: PerformanceTracker) {
-        // If it's the first time seeing `symbol`, subscribe
-        if self.stg_map.get(&symbol).is_none() {
-            if let Some(ref sock) = self.tick_subscriber {
-                sock.set_subscribe(&symbol.0).expect(&format!("Failed to subscribe {:?}", symbol));
+    /// Cancel the resting orders and close the positions of every strategy on `symbol` at its
+    /// next tick. They are halted like after a breaker trip: `rearm` lets them open again.
+    pub fn flatten_symbol(&self, symbol: SymbolType) -> Result<String, String> {
+        let Some(&worker_id) = self.symbol_workers.get(&symbol) else {
+            return Err(format!("unknown symbol {:?}", symbol));
+        };
+        self.senders[worker_id]
+            .send(WorkerMsg::Flatten { symbol })
+            .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        warn!(?symbol, "flattening symbol");
+        Ok("ok".into())
+    }
+
+    /// Cancel every resting order and close every position of every strategy right away, at
+    /// the last quote each worker saw for the symbol rather than waiting for the next tick,
+    /// so it also works once the feed is gone; strategies with no quote yet close on their
+    /// first tick. They are halted until `rearm`. Meant for emergencies and for shutting
+    /// down flat: the workers keep their order sockets open long enough to deliver the closes.
+    pub fn flatten_all(&self) -> Result<String, String> {
+        if self.senders.is_empty() {
+            return Err("engine not initialized".into());
+        }
+        for (worker_id, sender) in self.senders.iter().enumerate() {
+            sender
+                .send(WorkerMsg::FlattenAll)
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        warn!("flattening every strategy");
+        Ok("ok".into())
+    }
+
+    /// Re-arm the breakers of the strategies named `strategy`, or every breaker including the
+    /// global one when `None`, letting them open positions again.
+    pub fn rearm(&mut self, strategy: Option<&str>) -> Result<String, String> {
+        let workers = match strategy {
+            Some(strategy) => self.strategy_workers(strategy),
+            None => (0..self.senders.len()).collect(),
+        };
+        if workers.is_empty() {
+            return Err(format!("unknown strategy {:?}", strategy.unwrap_or_default()));
+        }
+        if strategy.is_none() && self.global_breaker.is_some() {
+            let equity = self.total_equity()?;
+            if let Some(ref mut breaker) = self.global_breaker {
+                breaker.reset(equity);
+            }
+            self.halted.store(false, Ordering::Relaxed);
+        }
+        for worker_id in workers {
+            let msg = WorkerMsg::Rearm {
+                strategy: strategy.map(String::from),
+            };
+            self.senders[worker_id]
+                .send(msg)
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        info!(strategy = strategy.unwrap_or("all"), "re-armed circuit breakers");
+        Ok("ok".into())
+    }
+
+    /// Alert when a subscribed symbol goes silent during trading hours and flag ticks that
+    /// arrive stale. Alerts are logged, counted in metrics and passed to `on_data_alert`.
+    pub fn enable_watchdog(&mut self, cfg: WatchdogConfig) {
+        let mut watchdog = Watchdog::new(cfg);
+        let now = self.clock.now();
+        for &symbol in self.symbol_workers.keys() {
+            watchdog.watch(symbol, now);
+        }
+        self.watchdog = Some(watchdog);
+    }
+
+    /// Check ticks against `calendar`, handling out-of-session ticks per `filter`, and call
+    /// strategies' `on_session_start`/`on_session_end` as their symbols' sessions change.
+    pub fn enable_calendar(&mut self, calendar: TradingCalendar, filter: SessionFilter) {
+        self.calendar = Some((calendar, filter));
+    }
+
+    /// Notify workers of every symbol whose session changed as of tick time `stamp`.
+    fn sweep_sessions(&mut self, stamp: i64) {
+        let Some((ref calendar, _)) = self.calendar else {
+            return;
+        };
+        for (&symbol, &worker_id) in &self.symbol_workers {
+            let now = calendar.session_at(&symbol, stamp).flatten();
+            let before = self.symbol_sessions.get(&symbol).copied();
+            if now == before {
+                continue;
+            }
+            let events = before.map(|s| (s, false)).into_iter().chain(now.map(|s| (s, true)));
+            for (session, started) in events {
+                debug!(?symbol, ?session, started, "session change");
+                let closes_day = !started && calendar.closes_day(&symbol, &session);
+                let msg = WorkerMsg::Session {
+                    symbol,
+                    session,
+                    started,
+                    closes_day,
+                };
+                if let Err(e) = self.senders[worker_id].send(msg) {
+                    error!(worker_id, error = ?e, "failed to send session change to worker");
+                }
+            }
+            match now {
+                Some(session) => self.symbol_sessions.insert(symbol, session),
+                None => self.symbol_sessions.remove(&symbol),
+            };
+        }
+    }
+
+    /// Read time from `clock` instead of the system clock: receive times, order timeouts and
+    /// what strategies see through `Strategy::on_clock`. With an `EventClock` time advances
+    /// with the ticks, for deterministic backtests. Must be called before `init()`.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    /// Track each symbol's skew between tick stamps and receive time, exported as
+    /// `fustg_clock_skew_ms` and alerted on through `on_data_alert`.
+    pub fn enable_skew_monitor(&mut self, cfg: SkewConfig) {
+        self.skew = Some(SkewMonitor::new(cfg));
+    }
+
+    /// Skew per symbol seen so far, when the skew monitor is enabled.
+    pub fn clock_skew(&self) -> Vec<(SymbolType, SkewStats)> {
+        self.skew
+            .iter()
+            .flat_map(|skew| skew.iter().map(|(&symbol, &stats)| (symbol, stats)))
+            .collect()
+    }
+
+    /// Called from the receive loop for every watchdog alert.
+    pub fn on_data_alert<F: FnMut(&DataAlert) + Send + 'static>(&mut self, callback: F) {
+        self.data_alert_callback = Some(Box::new(callback));
+    }
+
+    fn raise_data_alert(&mut self, alert: DataAlert) {
+        match alert {
+            DataAlert::Silent { symbol, silent_ms } => {
+                warn!(?symbol, silent_ms, "symbol went silent");
+                self.metrics.data_gaps.fetch_add(1, Ordering::Relaxed);
+            }
+            DataAlert::Resumed { symbol, silent_ms } => info!(?symbol, silent_ms, "symbol resumed"),
+            DataAlert::Stale { symbol, lag_ms } => {
+                debug!(?symbol, lag_ms, "stale tick");
+                self.metrics.stale_ticks.fetch_add(1, Ordering::Relaxed);
+            }
+            DataAlert::ClockSkew { symbol, skew_ms } => {
+                warn!(?symbol, skew_ms, "tick clock skew past threshold");
+                self.metrics.clock_skew_alerts.fetch_add(1, Ordering::Relaxed);
+            }
+            DataAlert::SkewRecovered { symbol, skew_ms } => info!(?symbol, skew_ms, "tick clock skew recovered"),
+            DataAlert::SequenceGap { symbol, missed } => {
+                warn!(?symbol, missed, "ticks missing from the feed handler's sequence");
+                self.metrics.sequence_gaps.fetch_add(1, Ordering::Relaxed);
+                self.metrics.ticks_missed.fetch_add(missed, Ordering::Relaxed);
+            }
+        }
+        if let Some(ref watchdog) = self.watchdog {
+            self.metrics.silent_symbols.store(watchdog.silent_count() as i64, Ordering::Relaxed);
+        }
+        if let Some(ref mut callback) = self.data_alert_callback {
+            callback(&alert);
+        }
+    }
+
+    /// Measure feed latency and check the sequence of a tick from a v2 frame.
+    fn on_tick_meta(&mut self, tick: &TickData, meta: TickMeta) {
+        if meta.recv_ns > 0 {
+            let latency = timeutil::now_nanos() - meta.recv_ns;
+            self.metrics.observe_feed_latency(latency.max(0) as u64);
+        }
+        if meta.seq > 0 {
+            let missed = self.sequences.on_seq(tick.symbol, meta.seq);
+            if missed > 0 {
+                self.raise_data_alert(DataAlert::SequenceGap { symbol: tick.symbol, missed });
+            }
+        }
+    }
+
+    /// Serve Prometheus metrics at `http://<addr>/metrics`. Must be called before `init()`.
+    pub fn enable_metrics(&mut self, addr: &str) {
+        self.metrics_addr = Some(addr.into());
+    }
+
+    pub fn metrics(&self) -> Arc<EngineMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Book fills from execution reports received at `report_uri` instead of assuming every
+    /// sent order fills. The last applied sequence per strategy is persisted in `seq_path`,
+    /// so reports replayed after a restart are applied exactly once. Must be called before `init()`.
+    pub fn enable_execution_reports<P: AsRef<Path>>(&mut self, report_uri: &str, seq_path: P) -> Result<(), EngineError> {
+        let socket = self.ctx.socket(zmq::PULL).map_err(EngineError::socket("create PULL socket"))?;
+        socket.set_rcvhwm(0).map_err(EngineError::socket("set rcvhwm"))?;
+        socket
+            .connect(report_uri)
+            .map_err(EngineError::socket(format!("connect PULL socket to {}", report_uri)))?;
+        let store = SeqStore::open(&seq_path).map_err(EngineError::file("sequence store", &seq_path))?;
+        self.report_socket = Some(socket);
+        self.report_uri = Some(report_uri.into());
+        self.seq_store = Some(Arc::new(Mutex::new(store)));
+        Ok(())
+    }
+
+    /// Hand an execution report on `symbol` to the worker owning it.
+    fn dispatch_report(&self, symbol: SymbolType, seq: u64, msg: WorkerMsg) {
+        let Some(&worker_id) = self.symbol_workers.get(&symbol) else {
+            warn!(seq, ?symbol, "execution report for unknown symbol");
+            return;
+        };
+        if let Err(e) = self.senders[worker_id].send(msg) {
+            error!(worker_id, error = ?e, "failed to send execution report to worker");
+        }
+    }
+
+    /// Bind a REP socket at `control_uri` that accepts operator commands while `start()` runs.
+    pub fn enable_control(&mut self, control_uri: &str) -> Result<(), EngineError> {
+        self.control_socket = Some(self.bind(zmq::REP, control_uri)?);
+        Ok(())
+    }
+
+    /// A socket of `kind` bound to `uri`, without linger.
+    fn bind(&self, kind: zmq::SocketType, uri: &str) -> Result<zmq::Socket, EngineError> {
+        let socket = self.ctx.socket(kind).map_err(EngineError::socket(format!("create {:?} socket", kind)))?;
+        socket.set_linger(0).map_err(EngineError::socket("set linger"))?;
+        socket
+            .bind(uri)
+            .map_err(EngineError::socket(format!("bind {:?} socket to {}", kind, uri)))?;
+        Ok(socket)
+    }
+
+    /// Bind a REP socket at `query_uri` that answers position and PnL queries (see `query`)
+    /// with JSON while `start()` runs.
+    pub fn enable_query(&mut self, query_uri: &str) -> Result<(), EngineError> {
+        self.query_socket = Some(self.bind(zmq::REP, query_uri)?);
+        self.query_uri = Some(query_uri.into());
+        Ok(())
+    }
+
+    /// Publish a heartbeat every `interval` on a PUB socket bound to `uri`, naming this engine
+    /// `engine_id` (see `heartbeat`). Sent from the receive loop, so heartbeats stop when it hangs.
+    pub fn enable_heartbeat(&mut self, uri: &str, engine_id: &str, interval: Duration) -> Result<(), EngineError> {
+        let socket = self.bind(zmq::PUB, uri)?;
+        self.heartbeat = Some(HeartbeatPublisher::new(socket, engine_id, interval.as_millis() as i64));
+        Ok(())
+    }
+
+    fn publish_heartbeat(&mut self) {
+        let Some(ref mut heartbeat) = self.heartbeat else {
+            return;
+        };
+        let now = timeutil::now_stamp();
+        if !heartbeat.due(now) {
+            return;
+        }
+        let received_ms = self.tick_recv_ns / 1_000_000;
+        let last_tick = self.last_tick_stamp.map(|stamp| (stamp, received_ms));
+        let depths = self.senders.iter().map(|sender| sender.len()).collect();
+        if let Err(e) = heartbeat.publish(now, last_tick, depths) {
+            warn!(error = ?e, "failed to publish heartbeat");
+        }
+    }
+
+    /// Estimate the risk report's covariances from the last `lookback_days` of each product's
+    /// continuous daily bars under `bar_root` (see `risk`). Without it the report still has
+    /// exposures and isolated stress moves, but no VaR.
+    pub fn enable_risk_history<P: AsRef<Path>>(&mut self, bar_root: P, lookback_days: usize) {
+        self.risk_history = Some((BarStore::new(bar_root), lookback_days));
+    }
+
+    /// Offer strategies the bars under `bar_root` to warm up from, through the
+    /// `EngineContext` their `on_start` receives. Must be called before `init()`.
+    pub fn set_history<P: AsRef<Path>>(&mut self, bar_root: P) {
+        self.history = Some(Arc::new(BarStore::new(bar_root)));
+    }
+
+    /// Run every strategy through up to `count` of its symbol's latest ticks from `source`
+    /// when it starts, its orders dropped (see `warmup`). Must be called before `init()`;
+    /// strategies hot-added later are warmed up as well.
+    pub fn enable_warmup(&mut self, source: Arc<dyn WarmupSource>, count: usize) {
+        self.warmup = Some((source, count));
+    }
+
+    /// VaR and stress scenarios, moving products by `±shock`, over the current positions of
+    /// every strategy.
+    pub fn risk_report(&self, shock: f64) -> Result<RiskReport, String> {
+        let exposures = risk::exposures(&self.snapshot(SnapshotFilter::default())?);
+        let model = match &self.risk_history {
+            Some((store, lookback)) => {
+                let products: Vec<String> = exposures.iter().map(|e| e.product.clone()).collect();
+                RiskModel::load(store, &products, *lookback)
+            }
+            None => RiskModel::default(),
+        };
+        Ok(risk::report(exposures, &model, shock))
+    }
+
+    /// Build a `DailyReport` once a day after `config.settle_secs` and pass it to the
+    /// `on_daily_report` callback. A report covers everything since the previous one, or since
+    /// the engine started; an engine started after the settlement time first reports the next day.
+    pub fn enable_daily_report(&mut self, config: ReportConfig) {
+        self.daily_report = Some(config);
+    }
+
+    /// Called from the receive loop with every daily report; hand slow delivery (mail,
+    /// webhooks) to another thread.
+    pub fn on_daily_report<F: FnMut(&DailyReport) + Send + 'static>(&mut self, callback: F) {
+        self.daily_report_callback = Some(Box::new(callback));
+    }
+
+    /// Report on every strategy since the previous report and move the marks forward.
+    pub fn daily_report(&mut self) -> Result<DailyReport, String> {
+        let config = self.daily_report.unwrap_or_default();
+        let snapshots = self.snapshot(SnapshotFilter {
+            curve_points: usize::MAX,
+            ..Default::default()
+        })?;
+        let risk = self
+            .risk_report(config.shock)
+            .map_err(|e| warn!(error = %e, "no risk section in the daily report"))
+            .ok();
+        let date = timeutil::local_date(self.clock.now());
+        Ok(report::build(date, &snapshots, &mut self.report_marks, risk, &config))
+    }
+
+    /// Export a settlement statement (see `statement`) to `<dir>/<yyyymmdd>/` once a day at
+    /// the daily report's settlement time, for reconciliation against the broker's. Orders
+    /// are read back from the audit log, so `enable_audit_log` should be called too.
+    pub fn enable_statement_export<P: AsRef<Path>>(&mut self, dir: P) {
+        self.statement_dir = Some(dir.as_ref().to_path_buf());
+    }
+
+    /// The orders, fills and positions of every strategy since `since`.
+    pub fn statement(&self, since: i64) -> Result<Statement, String> {
+        let snapshots = self.snapshot(SnapshotFilter {
+            fills_since: Some(since),
+            ..Default::default()
+        })?;
+        let orders = match self.audit_log_path {
+            Some(ref path) => audit::read_records(path).map_err(|e| format!("failed to read audit log: {}", e))?,
+            None => Vec::new(),
+        };
+        let orders = orders
+            .into_iter()
+            .map(|record| record.order)
+            .filter(|order| order.timestamp >= since)
+            .collect();
+        let mut statement = Statement::new(timeutil::local_date(self.clock.now()), snapshots, orders);
+        for symbol in self.symbol_workers.keys() {
+            if let Some((exchange, _)) = self.symbol_map.contract(symbol.as_str()).and_then(|c| c.split_once('.')) {
+                statement = statement.with_exchange(symbol.as_str(), exchange);
+            }
+        }
+        Ok(statement)
+    }
+
+    /// Send the day's report and export its statement once `now` is past the settlement time.
+    fn check_settlement(&mut self, now: i64) {
+        if self.daily_report.is_none() && self.statement_dir.is_none() {
+            return;
+        }
+        let config = self.daily_report.unwrap_or_default();
+        let day = timeutil::local_day(now);
+        if day == self.reported_day || timeutil::local_secs_of_day(now) < config.settle_secs {
+            return;
+        }
+        self.reported_day = day;
+        if self.daily_report.is_some() {
+            match self.daily_report() {
+                Ok(report) => {
+                    info!(date = report.date, pnl = report.pnl(), breaches = report.breaches.len(), "daily report");
+                    if let Some(ref mut callback) = self.daily_report_callback {
+                        callback(&report);
+                    }
+                }
+                Err(e) => error!(error = %e, "failed to build the daily report"),
+            }
+        }
+        if let Some(ref dir) = self.statement_dir {
+            let exported = self
+                .statement(self.statement_since)
+                .and_then(|s| statement::save(dir, &s).map_err(|e| e.to_string()));
+            match exported {
+                Ok(path) => info!(path = %path.display(), "exported settlement statement"),
+                Err(e) => error!(error = %e, "failed to export the settlement statement"),
+            }
+            self.statement_since = now;
+        }
+    }
+
+    /// Serve the live dashboard (see `dashboard`) over HTTP on `addr` once `init()` runs.
+    /// It reads strategy state from the query socket, so `enable_query` must be called too.
+    #[cfg(feature = "dashboard")]
+    pub fn enable_dashboard(&mut self, addr: &str) {
+        self.dashboard_addr = Some(addr.into());
+    }
+
+    /// Stop dispatching `symbol`'s ticks and block its orders; other symbols keep trading.
+    pub fn pause_symbol(&self, symbol: SymbolType) -> Result<String, String> {
+        if !self.symbol_workers.contains_key(&symbol) {
+            return Err(format!("unknown symbol {:?}", symbol));
+        }
+        self.paused_symbols.insert(symbol);
+        info!(?symbol, "paused symbol");
+        Ok("ok".into())
+    }
+
+    pub fn resume_symbol(&self, symbol: SymbolType) -> Result<String, String> {
+        if !self.paused_symbols.remove(&symbol) {
+            return Err(format!("symbol {:?} is not paused", symbol));
+        }
+        info!(?symbol, "resumed symbol");
+        Ok("ok".into())
+    }
+
+    /// Contracts available to `add` control commands, keyed like the fees toml (e.g. `SHFE.rb`).
+    pub fn set_contracts(&mut self, contracts: HashMap<String, ContractInfo>, init_cash: f64) {
+        self.symbol_map.index_contracts(contracts.keys());
+        self.contracts = contracts;
+        self.init_cash = init_cash;
+    }
+
+    /// 账户币种: the currency the account is kept in, CNY unless set. Orders on contracts
+    /// priced in another currency without an `fx_rate` are blocked.
+    pub fn set_account_currency(&mut self, currency: Currency) {
+        self.account_currency = currency;
+    }
+
+    /// Rules and dominant contracts for `add_symbol_strategy`; product roots are found in the
+    /// `set_contracts` table either way.
+    pub fn set_symbol_map(&mut self, mut map: SymbolMap) {
+        map.index_contracts(self.contracts.keys());
+        self.symbol_map = map;
+    }
+
+    /// Charge commissions per contract with these models, e.g. from `config::load_fee_models`,
+    /// instead of the contracts table's fee columns. Applies to strategies added after this call.
+    pub fn set_fee_models(&mut self, models: HashMap<String, FeeModel>) {
+        self.fee_models = models;
+    }
+
+    /// A tracker for a strategy trading `contract` with `cash`.
+    fn contract_tracker(&self, contract: &str, cash: f64, info: ContractInfo) -> PerformanceTracker {
+        let tracker = PerformanceTracker::new(cash, info);
+        match self.fee_models.get(contract) {
+            Some(&fees) => tracker.with_fee_model(fees),
+            None => tracker,
+        }
+    }
+
+    /// Reload the contracts table whenever the fees file at `path` is rewritten: strategies
+    /// added by contract switch to the new fees and margins between two ticks, open positions
+    /// re-margined, and symbols blocked for a missing contract are released once it appears.
+    pub fn enable_fees_reload<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let watcher = FeesWatcher::new(&path).map_err(|e| format!("watching {}: {}", path.as_ref().display(), e))?;
+        self.fees_watcher = Some(watcher);
+        Ok(())
+    }
+
+    fn check_fees_reload(&mut self) {
+        let Some(ref watcher) = self.fees_watcher else {
+            return;
+        };
+        match watcher.poll() {
+            None => {}
+            Some(Ok(contracts)) => self.apply_contracts(contracts),
+            Some(Err(e)) => warn!(path = %watcher.path().display(), error = %e, "failed to reload fees, keeping the current table"),
+        }
+    }
+
+    /// Re-read the watched fees file now rather than on its next change.
+    pub fn reload_fees(&mut self) -> Result<String, String> {
+        let Some(ref watcher) = self.fees_watcher else {
+            return Err("fees reload is not enabled".into());
+        };
+        let contracts = config::load_fees(watcher.path()).map_err(|e| format!("{:#}", e))?;
+        info!(path = %watcher.path().display(), contracts = contracts.len(), "reloading fees by command");
+        self.apply_contracts(contracts);
+        Ok("ok".into())
+    }
+
+    /// Switch to a new contracts table, pushing what changed to the workers.
+    fn apply_contracts(&mut self, contracts: HashMap<String, ContractInfo>) {
+        for (contract, info) in reload::changed_contracts(&self.contracts, &contracts) {
+            info!(contract, ?info, "contract fees or margins changed");
+            let symbols = self
+                .symbol_contracts
+                .iter()
+                .filter(|(_, c)| c.as_str() == contract)
+                .map(|(symbol, _)| *symbol);
+            for symbol in symbols {
+                if info.lacks_fx(self.account_currency) {
+                    error!(?symbol, contract, currency = ?info.currency, "NO FX RATE: all orders on this symbol are blocked");
+                    self.blocked_symbols.write().unwrap().insert(symbol);
+                } else if self.blocked_symbols.write().unwrap().remove(&symbol) {
+                    info!(?symbol, contract, "contract info now available, orders unblocked");
+                }
+                let Some(&worker_id) = self.symbol_workers.get(&symbol) else {
+                    continue;
+                };
+                if let Some(sender) = self.senders.get(worker_id)
+                    && let Err(e) = sender.send(WorkerMsg::SetContract { symbol, info })
+                {
+                    error!(worker_id, error = ?e, "failed to send contract update to worker");
+                }
+            }
+        }
+        self.contracts = contracts;
+    }
+
+    /// What to do for strategies whose contract is missing from `set_contracts`' table.
+    pub fn set_missing_contract(&mut self, policy: MissingContract) {
+        self.missing_contract = policy;
+    }
+
+    /// The fee/margin info for `contract`, falling back to the `MissingContract` policy.
+    fn contract_info(&mut self, symbol: SymbolType, contract: &str) -> ContractInfo {
+        self.symbol_contracts.insert(symbol, contract.to_string());
+        if let Some(info) = self.contracts.get(contract) {
+            if info.lacks_fx(self.account_currency) {
+                error!(?symbol, contract, currency = ?info.currency, "NO FX RATE: all orders on this symbol are blocked");
+                self.blocked_symbols.write().unwrap().insert(symbol);
+            }
+            return *info;
+        }
+        match self.missing_contract {
+            MissingContract::Defaults(info) => {
+                warn!(?symbol, contract, "NO CONTRACT INFO: trading with default fees and margins");
+                info
+            }
+            MissingContract::Block => {
+                error!(?symbol, contract, "NO CONTRACT INFO: all orders on this symbol are blocked");
+                self.blocked_symbols.write().unwrap().insert(symbol);
+                ContractInfo::default()
+            }
+        }
+    }
+
+    /// Add `strategy` on `symbol`, tracked with `contract`'s entry from `set_contracts`.
+    pub fn add_contract_strategy(&mut self, symbol: SymbolType, contract: &str, strategy: Box<dyn Strategy>) {
+        let info = self.contract_info(symbol, contract);
+        let tracker = self.contract_tracker(contract, self.init_cash, info);
+        self.add_strategy(symbol, strategy, tracker);
+    }
+
+    /// Add `strategy` on `symbol`, a contract or a dominant alias (`rb888`), tracked with the
+    /// `contracts` entry the symbol map finds for it. Returns the contract traded; with
+    /// rollover enabled, an alias's strategies move on as the product rolls.
+    pub fn add_symbol_strategy(&mut self, symbol: &str, strategy: Box<dyn Strategy>) -> Result<SymbolType, String> {
+        let traded = self.symbol_map.resolve(symbol)?;
+        let contract = self
+            .symbol_map
+            .contract(traded.as_str())
+            .ok_or_else(|| format!("no fees entry matches {:?}", symbol))?
+            .to_string();
+        if self.symbol_map.is_alias(symbol)
+            && let Some(ref mut rollover) = self.rollover
+        {
+            rollover.follow(traded);
+        }
+        self.add_contract_strategy(traded, &contract, strategy);
+        Ok(traded)
+    }
+
+    /// Roll the products of strategies added on a dominant alias by `rollover`'s rules, and
+    /// subscribe the contracts it watches. Must be called before `add_symbol_strategy`.
+    pub fn enable_rollover(&mut self, rollover: Rollover) {
+        if let Some(ref sock) = self.tick_subscriber {
+            for contract in rollover.watched() {
+                subscribe(sock, contract, "rollover contract");
+            }
+        }
+        self.rollover = Some(rollover);
+    }
+
+    /// Subscribe `roll.to` on the worker trading `roll.from`, and have that worker move its
+    /// strategies over. `roll.from` stays subscribed until they have all moved, as the worker
+    /// flattens on its quotes (see `drop_rolled_off`).
+    fn roll(&mut self, roll: Roll, subscriber: &TickFeeds) {
+        let Some(&worker_id) = self.symbol_workers.get(&roll.from) else {
+            return;
+        };
+        match self.symbol_workers.get(&roll.to) {
+            Some(&other) if other != worker_id => {
+                error!(from = ?roll.from, to = ?roll.to, worker_id, other, "cannot roll onto a contract another worker trades");
+                return;
+            }
+            Some(_) => {}
+            None => {
+                if let Err(e) = subscriber.set_subscribe(&roll.to.0) {
+                    error!(to = ?roll.to, error = ?e, "failed to subscribe the contract rolled to");
+                    return;
+                }
+                self.symbol_workers.insert(roll.to, worker_id);
+                self.symbol_batches[worker_id].insert(roll.to);
+                if let Some(ref mut watchdog) = self.watchdog {
+                    watchdog.watch(roll.to, self.clock.now());
+                }
+            }
+        }
+        if let Some(names) = self.symbol_strategies.remove(&roll.from) {
+            self.symbol_strategies.entry(roll.to).or_default().extend(names);
+        }
+        if let Some(contract) = self.symbol_contracts.get(&roll.from).cloned() {
+            self.symbol_contracts.insert(roll.to, contract);
+        }
+        self.symbol_map.set_dominant(roll.to.as_str());
+        info!(from = ?roll.from, to = ?roll.to, worker_id, "rolling over");
+        if let Err(e) = self.senders[worker_id].send(WorkerMsg::Roll(roll)) {
+            error!(worker_id, error = ?e, "failed to send roll to worker");
+        }
+    }
+
+    /// Stop receiving and routing the contracts workers have moved every strategy off after a
+    /// roll, unless a strategy was added on one since.
+    fn drop_rolled_off(&mut self, subscriber: &TickFeeds) {
+        let rolled_off = mem::take(&mut *self.rolled_off.lock().unwrap());
+        for symbol in rolled_off {
+            if self.symbol_strategies.contains_key(&symbol) {
+                continue;
+            }
+            if let Some(worker_id) = self.symbol_workers.remove(&symbol) {
+                self.symbol_batches[worker_id].remove(&symbol);
+                self.router.release(&symbol, worker_id);
+            }
+            self.symbol_contracts.remove(&symbol);
+            if let Some(ref mut watchdog) = self.watchdog {
+                watchdog.unwatch(&symbol);
+            }
+            // drops only the trading subscription; one as a benchmark, reference or rollover candidate stays
+            if let Err(e) = subscriber.set_unsubscribe(&symbol.0) {
+                error!(?symbol, error = ?e, "failed to unsubscribe the contract rolled off");
+                continue;
+            }
+            info!(?symbol, "unsubscribed the contract rolled off");
+        }
+    }
+
+    /// Roll out version `b` of a strategy next to version `a` on `symbol`: `fraction_a` of the
+    /// size and of the starting cash go to `a`, the rest to `b`, each tracked as its own
+    /// strategy (see `split`).
+    pub fn add_ab_split(&mut self, symbol: SymbolType, contract: &str, a: Box<dyn Strategy>, b: Box<dyn Strategy>, fraction_a: f64) {
+        let info = self.contract_info(symbol, contract);
+        let (a, b) = split::ab_split(a, b, fraction_a);
+        for arm in [a, b] {
+            let cash = self.init_cash * arm.fraction();
+            let tracker = self.contract_tracker(contract, cash, info);
+            self.add_strategy(symbol, Box::new(arm), tracker);
+        }
+    }
+
+    /// Workers hosting at least one strategy named `strategy`.
+    fn strategy_workers(&self, strategy: &str) -> HashSet<usize> {
+        self.symbol_strategies
+            .iter()
+            .filter(|(_, names)| names.iter().any(|n| n == strategy))
+            .filter_map(|(symbol, _)| self.symbol_workers.get(symbol).copied())
+            .collect()
+    }
+
+    /// Forward a parameter change to the workers owning `strategy`; each applies it between ticks.
+    pub fn update_param(&self, strategy: &str, name: &str, value: f64) -> Result<String, String> {
+        let workers = self.strategy_workers(strategy);
+        if workers.is_empty() {
+            return Err(format!("unknown strategy {:?}", strategy));
+        }
+        for worker_id in workers {
+            let msg = WorkerMsg::UpdateParam {
+                strategy: strategy.into(),
+                name: name.into(),
+                value,
+            };
+            self.senders[worker_id]
+                .send(msg)
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        Ok("ok".into())
+    }
+
+    /// Pause or resume every strategy named `strategy`. A paused strategy keeps running on
+    /// its ticks, so indicators and windows stay current, but the orders it emits are dropped;
+    /// stops and resting orders are still looked after. On resume it is told the position it
+    /// actually holds (`on_resume`), as the orders it thinks it sent in between never went out.
+    pub fn pause_strategy(&self, strategy: &str, paused: bool) -> Result<String, String> {
+        let workers = self.strategy_workers(strategy);
+        if workers.is_empty() {
+            return Err(format!("unknown strategy {:?}", strategy));
+        }
+        for worker_id in workers {
+            let msg = WorkerMsg::SetPaused {
+                strategy: strategy.into(),
+                paused,
+            };
+            self.senders[worker_id]
+                .send(msg)
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        info!(strategy, paused, "strategy pause set");
+        Ok("ok".into())
+    }
+
+    /// Flip toggle `name` on every strategy named `strategy`.
+    pub fn set_toggle(&self, strategy: &str, name: &str, enabled: bool) -> Result<String, String> {
+        let workers = self.strategy_workers(strategy);
+        if workers.is_empty() {
+            return Err(format!("unknown strategy {:?}", strategy));
+        }
+        for worker_id in workers {
+            let msg = WorkerMsg::SetToggle {
+                strategy: strategy.into(),
+                name: name.into(),
+                enabled,
+            };
+            self.senders[worker_id]
+                .send(msg)
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        Ok("ok".into())
+    }
+
+    /// Cancel the resting order `client_id` of every strategy named `strategy`.
+    pub fn cancel_order(&self, strategy: &str, client_id: u64) -> Result<String, String> {
+        let workers = self.strategy_workers(strategy);
+        if workers.is_empty() {
+            return Err(format!("unknown strategy {:?}", strategy));
+        }
+        for worker_id in workers {
+            let msg = WorkerMsg::Cancel {
+                strategy: strategy.into(),
+                client_id,
+            };
+            self.senders[worker_id]
+                .send(msg)
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        Ok("ok".into())
+    }
+
+    /// Dry-run an order for `strategy` on its symbol: risk checks and margin impact, nothing sent.
+    pub fn preview_order(&self, strategy: &str, direction: DirectionType, offset: OffsetFlagType, price: f64, lots: u32) -> Result<String, String> {
+        let symbol = self
+            .symbol_strategies
+            .iter()
+            .find(|(_, names)| names.iter().any(|n| n == strategy))
+            .map(|(symbol, _)| *symbol)
+            .ok_or_else(|| format!("unknown strategy {:?}", strategy))?;
+        if self.senders.is_empty() {
+            return Err("engine not initialized".into());
+        }
+        let order = Order {
+            stg_name: NameType::from(strategy),
+            symbol,
+            timestamp: self.clock.now(),
+            price,
+            lots,
+            direction,
+            offset,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+        let (reply, outcome) = mpsc::channel();
+        let worker_id = self.symbol_workers[&symbol];
+        self.senders[worker_id]
+            .send(WorkerMsg::Preview {
+                strategy: strategy.into(),
+                order,
+                reply,
+            })
+            .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        outcome
+            .recv_timeout(Duration::from_secs(1))
+            .map_err(|_| format!("worker {} did not answer", worker_id))
+    }
+
+    /// Snapshots of the strategies `filter` selects, collected from the workers that run them.
+    pub fn snapshot(&self, filter: SnapshotFilter) -> Result<Vec<StrategySnapshot>, String> {
+        if self.senders.is_empty() {
+            return Err("engine not initialized".into());
+        }
+        let workers: Vec<usize> = match filter.symbol {
+            Some(symbol) => self.symbol_workers.get(&symbol).copied().into_iter().collect(),
+            None => (0..self.senders.len()).collect(),
+        };
+        let (reply, answers) = mpsc::channel();
+        for &worker_id in &workers {
+            self.senders[worker_id]
+                .send(WorkerMsg::Snapshot {
+                    filter: filter.clone(),
+                    reply: reply.clone(),
+                })
+                .map_err(|e| format!("worker {} is gone: {:?}", worker_id, e))?;
+        }
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut snapshots = Vec::new();
+        for _ in &workers {
+            let answer = answers
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map_err(|_| "a worker did not answer".to_string())?;
+            snapshots.extend(answer);
+        }
+        Ok(snapshots)
+    }
+
+    /// Answer one pending request on the query socket.
+    fn serve_query(&self, socket: &zmq::Socket) {
+        let result = match socket.recv_string(0) {
+            Ok(Ok(line)) => Query::parse(&line).and_then(|q| match q {
+                Query::Accounts => serde_json::to_string(&self.accounts()).map_err(|e| e.to_string()),
+                q => self.snapshot(query::filter(&q)).and_then(|snapshots| query::answer(&q, snapshots)),
+            }),
+            Ok(Err(_)) => Err("query is not valid UTF-8".into()),
+            Err(e) => {
+                error!(error = ?e, "query socket error");
+                return;
+            }
+        };
+        let reply = result.unwrap_or_else(|e| serde_json::json!({ "error": e }).to_string());
+        if let Err(e) = socket.send(reply.as_str(), 0) {
+            error!(error = ?e, "failed to reply on query socket");
+        }
+    }
+
+    fn execute(&mut self, command: Command, subscriber: &TickFeeds) -> Result<String, String> {
+        match command {
+            Command::PauseSymbol(symbol) => self.pause_symbol(symbol),
+            Command::ResumeSymbol(symbol) => self.resume_symbol(symbol),
+            Command::UpdateParam { strategy, name, value } => self.update_param(&strategy, &name, value),
+            Command::SetToggle { strategy, name, enabled } => self.set_toggle(&strategy, &name, enabled),
+            Command::PauseStrategy { strategy, paused } => self.pause_strategy(&strategy, paused),
+            Command::CancelOrder { strategy, client_id } => self.cancel_order(&strategy, client_id),
+            Command::PreviewOrder {
+                strategy,
+                direction,
+                offset,
+                price,
+                lots,
+            } => self.preview_order(&strategy, direction, offset, price, lots),
+            Command::AddStrategy {
+                symbol,
+                contract,
+                kind,
+                args,
+            } => {
+                let symbol = self.symbol_map.resolve(symbol.as_str())?;
+                if let Some(shard) = self.shard
+                    && !shard.owns(&symbol)
+                {
+                    return Err(format!("{:?} belongs to shard {} of {}", symbol, shard.owner(&symbol), shard.count));
+                }
+                let strategy = match self.plugins.create(&kind, &args) {
+                    Some(strategy) => strategy?,
+                    None => strategies::create(&kind, &args)?,
+                };
+                let name = strategy.name().as_str().to_string();
+                // `auto`: the entry the symbol map finds
+                let contract = match contract.as_str() {
+                    "auto" => self
+                        .symbol_map
+                        .contract(symbol.as_str())
+                        .ok_or_else(|| format!("no fees entry matches {:?}", symbol))?
+                        .to_string(),
+                    _ => contract,
+                };
+                let info = self.contract_info(symbol, &contract);
+                let tracker = self.contract_tracker(&contract, self.init_cash, info);
+                self.register(symbol, strategy, tracker, Some(subscriber));
+                Ok(format!("added {} on {:?}", name, symbol))
+            }
+            Command::RemoveStrategy(strategy) => self.unregister(&strategy, Some(subscriber)),
+            Command::LoadPlugin(path) => self.load_plugin(&path).map(|kind| format!("loaded strategy kind {}", kind)),
+            Command::Rearm(strategy) => self.rearm(strategy.as_deref()),
+            Command::Flatten(symbol) => self.flatten_symbol(symbol),
+            Command::FlattenAll => self.flatten_all(),
+            Command::Reload => self.reload_fees(),
+            Command::RiskReport { shock } => self
+                .risk_report(shock)
+                .and_then(|report| serde_json::to_string(&report).map_err(|e| e.to_string())),
+        }
+    }
+
+    /// Answer one pending request on the control socket.
+    fn serve_control(&mut self, control: &zmq::Socket, subscriber: &TickFeeds) {
+        let result = match control.recv_string(0) {
+            Ok(Ok(line)) => self.on_event(Event::Command(line), subscriber),
+            Ok(Err(_)) => Err("command is not valid UTF-8".into()),
+            Err(e) => {
+                error!(error = ?e, "control socket error");
+                return;
+            }
+        };
+        let reply = result.unwrap_or_else(|e| format!("error: {}", e));
+        if let Err(e) = control.send(reply.as_str(), 0) {
+            error!(error = ?e, "failed to reply on control socket");
+        }
+    }
+
+    /// Load a strategy plugin (see `plugin`) and return the strategy kind it provides.
+    pub fn load_plugin<P: AsRef<Path>>(&mut self, path: P) -> Result<String, String> {
+        let kind = self.plugins.load(path.as_ref())?;
+        info!(kind, path = %path.as_ref().display(), "loaded strategy plugin");
+        Ok(kind)
+    }
+
+    /// Size the worker queues: `capacity` messages each (the receive loop waits for room when
+    /// one is full), with the worker busy-spinning `spin` times on an empty queue before it
+    /// parks. More spinning cuts wake-up latency at the cost of a busier core. Must be called
+    /// before `init()`.
+    pub fn set_worker_queues(&mut self, capacity: usize, spin: u32) {
+        self.queue_capacity = capacity;
+        self.queue_spin = spin;
+    }
+
+    /// Pin the receive loop and the workers to dedicated cores. Must be called before `init()`.
+    pub fn set_cpu_affinity(&mut self, affinity: CpuAffinity) {
+        self.affinity = affinity;
+    }
+
+    /// How the receive loop waits for ticks. `ReceiveMode::BusyPoll` trades a busy core for
+    /// lower wake-up latency, and keeps serving the control socket while no tick arrives.
+    pub fn set_receive_mode(&mut self, mode: ReceiveMode) {
+        self.receive_mode = mode;
+    }
+
+    /// How the tick feeds' and workers' sockets back off while reconnecting after an error.
+    /// Call before `init()` for the workers' sockets to follow it.
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect = policy;
+        if let Some(ref mut feeds) = self.tick_subscriber
+            && let Err(e) = feeds.set_reconnect_policy(policy)
+        {
+            warn!(error = ?e, "failed to set the reconnect interval on the SUB sockets");
+        }
+    }
+
+    /// `start()` returns once `flag` is set. A blocking receive loop is also interrupted by
+    /// the signal itself; a busy-polling one may spin straight past it and needs the flag.
+    pub fn set_shutdown_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.shutdown = flag;
+    }
+
+    /// Trade only the symbols `shard` owns: strategies on other symbols are skipped by
+    /// `add_strategy` and refused by the control socket, and their ticks never subscribed, so
+    /// the other shards' processes can run them. Must be called before any `add_strategy`.
+    pub fn set_shard(&mut self, shard: Shard) {
+        info!(index = shard.index, count = shard.count, "trading one shard of the symbols");
+        self.shard = Some(shard);
+    }
+
+    /// Whether this process trades `symbol`; always, unless sharded.
+    pub fn owns_symbol(&self, symbol: &SymbolType) -> bool {
+        self.shard.is_none_or(|shard| shard.owns(symbol))
+    }
+
+    /// Replace the default `HashRouter`. Must be called before any `add_strategy`.
+    pub fn with_router(mut self, router: Box<dyn Router>) -> Self {
+        self.router = router;
+        self
+    }
+
+    /// Replace the router after strategies were added, e.g. with a `TickRateRouter` built from
+    /// `measure_tick_rates`. Only routers that `rebalance` take effect for those strategies.
+    pub fn set_router(&mut self, router: Box<dyn Router>) {
+        self.router = router;
+    }
+
+    /// How the tick publisher encodes ticks. Encoded (non-raw) frames carry no fixed symbol
+    /// prefix, so the engine then subscribes to everything and filters by symbol itself.
+    pub fn set_wire_format(&mut self, format: WireFormat) {
+        self.wire_format = format;
+        if format != WireFormat::Raw
+            && let Some(ref sock) = self.tick_subscriber
+            && let Err(e) = sock.set_subscribe(b"")
+        {
+            error!(error = ?e, "failed to subscribe to all ticks, none will arrive");
+        }
+    }
+
+    /// Fail over to the next feed once the active one has been silent this long while another
+    /// keeps publishing. 3 seconds by default.
+    pub fn set_feed_failover(&mut self, after: Duration) {
+        self.feed_selector.set_failover_ms(after.as_millis() as i64);
+    }
+
+    /// Archive all received ticks to per-symbol daily files under `dir` (see `recorder`).
+    /// Must be called before `init()`.
+    pub fn enable_recorder<P: AsRef<Path>>(&mut self, dir: P) {
+        self.record_dir = Some(dir.as_ref().to_path_buf());
+    }
+
+    /// Append every tick, execution report and control command to the event log at `path`
+    /// (see `events`) as it is applied, for `replay_events`. Must be called before `init()`.
+    pub fn enable_event_log<P: AsRef<Path>>(&mut self, path: P) {
+        self.event_log_path = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Write a handoff file to `path` on `stop()`, for the next run's `resume_from`.
+    pub fn enable_handoff<P: AsRef<Path>>(&mut self, path: P) {
+        self.handoff_path = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Resume from the handoff file a previous run wrote: strategies registered by `init()`
+    /// take over the positions, cash and resting orders recorded under their name and
+    /// symbol, and ticks up to the last one processed are not dispatched again. Returns
+    /// the number of strategies in the file.
+    pub fn resume_from<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let handoff = Handoff::load(path)?;
+        for (symbol, last) in &handoff.last_ticks {
+            self.feed_selector.resume(SymbolType::from(symbol.as_str()), last.stamp, last.volume);
+        }
+        let count = handoff.strategies.len();
+        self.resume = Some(handoff);
+        Ok(count)
+    }
+
+    /// Journal ticks, orders, fills and cancels to `path` as they happen (see `journal`),
+    /// appending to what is already there. Must be called before `init()`.
+    pub fn enable_journal<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.journal = Some(Arc::new(Mutex::new(Journal::open(path)?)));
+        Ok(())
+    }
+
+    /// Resample the equity curves of strategies added after this call as `config` says,
+    /// instead of keeping every tick's equity for as long as the engine runs; with
+    /// `config.dir`, each curve's finished points are also appended to a file there.
+    pub fn enable_equity_curve(&mut self, config: CurveConfig) -> io::Result<()> {
+        if let Some(ref dir) = config.dir {
+            fs::create_dir_all(dir)?;
+        }
+        self.curve_config = Some(config);
+        Ok(())
+    }
+
+    /// Recover from the journal a previous run left at `path`: strategies registered by
+    /// `init()` get the orders, fills and cancels journaled under their name and symbol
+    /// booked again, and ticks up to the last one journaled are not dispatched again. The
+    /// journal must go back to when the strategies were flat. Returns the number of entries.
+    pub fn recover_from_journal<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let entries = journal::read(path)?;
+        for entry in &entries {
+            if let Entry::Tick { symbol, stamp, volume } = *entry {
+                self.feed_selector.resume(symbol, stamp, volume);
+            }
+        }
+        let count = entries.len();
+        self.recovered = Some(entries);
+        Ok(count)
+    }
+
+    /// Register a strategy for a given symbol.  We store it in stg_map as a
+    /// Box<dyn Strategy>.  It will not be shared—only one worker thread gets it.
+    pub fn add_strategy(&mut self, symbol: SymbolType, strategy: Box<dyn Strategy>, performance_tracker: PerformanceTracker) {
+        if !self.owns_symbol(&symbol) {
+            debug!(strategy = %strategy.name().as_str(), ?symbol, "symbol belongs to another shard, skipped");
+            return;
+        }
+        let subscriber = self.tick_subscriber.take();
+        self.register(symbol, strategy, performance_tracker, subscriber.as_ref());
+        self.tick_subscriber = subscriber;
+    }
+
+    /// Before `init()` the strategy is parked in `stg_map`; afterwards it is sent straight to
+    /// the owning worker, so this also serves hot-adds from the control socket.
+    fn register(&mut self, symbol: SymbolType, strategy: Box<dyn Strategy>, perf: PerformanceTracker, subscriber: Option<&TickFeeds>) {
+        // Figure out which worker “owns” this symbol (and all its strategies):
+        let worker_id = match self.symbol_workers.get(&symbol) {
+            Some(&worker_id) => worker_id,
+            None => {
+                // first time seeing `symbol`, subscribe
+                if let Some(sock) = subscriber {
+                    subscribe(sock, &symbol, "symbol");
+                }
+                let worker_id = self.router.route(&symbol, self.num_workers) % self.num_workers;
+                self.symbol_workers.insert(symbol, worker_id);
+                if let Some(ref mut watchdog) = self.watchdog {
+                    watchdog.watch(symbol, self.clock.now());
+                }
+                worker_id
+            }
+        };
+        self.symbol_batches[worker_id].insert(symbol);
+        self.symbol_strategies
+            .entry(symbol)
+            .or_default()
+            .push(strategy.name().as_str().to_string());
+
+        let tracking = strategy.benchmark().map(TrackingMonitor::new);
+        if let Some(ref tracking) = tracking {
+            let bench = tracking.benchmark().symbol;
+            let tracked = self.benchmarks.entry(bench).or_default();
+            // a SUB socket counts subscriptions, so this one is independent of trading `bench`
+            if tracked.is_empty()
+                && let Some(sock) = subscriber
+            {
+                subscribe(sock, &bench, "benchmark");
+            }
+            tracked.push((strategy.name().as_str().to_string(), worker_id));
+        }
+
+        let mut references = strategy.references();
+        references.extend(self.strategy_references.get(strategy.name().as_str()).into_iter().flatten());
+        references.sort_unstable_by_key(|s| s.0);
+        references.dedup();
+        for &reference in &references {
+            let readers = self.references.entry(reference).or_default();
+            // like benchmarks, subscribed independently of trading `reference`
+            if readers.is_empty()
+                && let Some(sock) = subscriber
+            {
+                subscribe(sock, &reference, "reference");
+            }
+            readers.push((strategy.name().as_str().to_string(), worker_id));
+        }
+
+        let strategy_name = strategy.name();
+        let mut perf = perf;
+        if let Some(ref config) = self.curve_config {
+            let path = config
+                .dir
+                .as_ref()
+                .map(|dir| Path::new(dir).join(format!("{}.{}.csv", strategy_name.as_str(), symbol.as_str())));
+            if let Err(e) = perf.resample_curve(config, path.as_deref()) {
+                warn!(strategy = %strategy_name.as_str(), error = ?e, "cannot write the equity curve file, keeping it in memory only");
+                let _ = perf.resample_curve(config, None);
+            }
+        }
+        let account = self.strategy_accounts.get(strategy_name.as_str()).and_then(|name| {
+            let account = self.accounts.get(name);
+            if account.is_none() {
+                warn!(strategy = %strategy_name.as_str(), account = %name, "unknown account, trading on the strategy's own cash");
+            }
+            account.cloned()
+        });
+        let next_id = self.strategy_ids.len() as u16 + 1;
+        let strategy_id = *self.strategy_ids.entry(strategy_name.as_str().to_string()).or_insert(next_id);
+        debug!(strategy = %strategy_name.as_str(), strategy_id, "strategy id");
+        let strat_perf = StratPerf {
+            stg: strategy,
+            strategy_id,
+            perf,
+            conduct: self.conduct_limits.map(ConductTracker::new),
+            order_stats: OrderStatsTracker::new(self.churn_limits),
+            stops: StopManager::new(),
+            pending: PendingOrders::new(),
+            partial_fills: self
+                .strategy_partial_fills
+                .get(strategy_name.as_str())
+                .copied()
+                .unwrap_or(self.partial_fill_policy),
+            positions: PositionManager::new(),
+            tracking,
+            budget: self
+                .strategy_budgets
+                .get(strategy_name.as_str())
+                .or(self.resource_budget.as_ref())
+                .map(|&budget| BudgetMeter::new(budget)),
+            price_offset: self.price_offsets.get(strategy_name.as_str()).copied().unwrap_or_default(),
+            references,
+            rejected: 0,
+            breaker: self
+                .strategy_breakers
+                .get(strategy_name.as_str())
+                .or(self.breaker_limits.as_ref())
+                .map(|&limits| DrawdownBreaker::new(limits)),
+            limits: self.strategy_limits.get(strategy_name.as_str()).copied(),
+            halted: false,
+            paused: false,
+            account,
+            trading_day: i64::MIN,
+            day_open: false,
+            rolling_to: None,
+            flatten_due: false,
+            price_limits: PriceLimits::default(),
+            limit_lock: None,
+            reopen: None,
+        };
+        sync_account(&strat_perf, symbol);
+        if self.senders.is_empty() {
+            // Push into stg_map (we’ll later drain each Vec into a worker).
+            self.stg_map.entry(symbol).or_default().push(strat_perf);
+        } else {
+            info!(strategy = %strat_perf.stg.name().as_str(), ?symbol, worker_id, "hot-adding strategy");
+            if let Err(e) = self.senders[worker_id].send(WorkerMsg::AddStrategy { symbol, strat_perf }) {
+                error!(worker_id, error = ?e, "failed to hand strategy to worker");
+            }
+        }
+    }
+
+    /// Remove every strategy named `strategy`. Symbols left without strategies are unsubscribed
+    /// and released by the router, so their worker can take new load.
+    pub fn remove_strategy(&mut self, strategy: &str) -> Result<String, String> {
+        let subscriber = self.tick_subscriber.take();
+        let result = self.unregister(strategy, subscriber.as_ref());
+        self.tick_subscriber = subscriber;
+        result
+    }
+
+    fn unregister(&mut self, strategy: &str, subscriber: Option<&TickFeeds>) -> Result<String, String> {
+        let workers = self.strategy_workers(strategy);
+        if workers.is_empty() {
+            return Err(format!("unknown strategy {:?}", strategy));
+        }
+
+        let mut emptied = Vec::new();
+        for (symbol, names) in self.symbol_strategies.iter_mut() {
+            names.retain(|n| n != strategy);
+            if names.is_empty() {
+                emptied.push(*symbol);
+            }
+        }
+        for symbol in emptied {
+            self.symbol_strategies.remove(&symbol);
+            if let Some(worker_id) = self.symbol_workers.remove(&symbol) {
+                self.symbol_batches[worker_id].remove(&symbol);
+                self.router.release(&symbol, worker_id);
+            }
+            self.paused_symbols.remove(&symbol);
+            self.blocked_symbols.write().unwrap().remove(&symbol);
+            self.symbol_contracts.remove(&symbol);
+            if let Some(ref mut watchdog) = self.watchdog {
+                watchdog.unwatch(&symbol);
+            }
+            if let Some(sock) = subscriber
+                && let Err(e) = sock.set_unsubscribe(&symbol.0)
+            {
+                error!(?symbol, error = ?e, "failed to unsubscribe");
+            }
+        }
+
+        let mut untracked = Vec::new();
+        for (bench, tracked) in self.benchmarks.iter_mut() {
+            tracked.retain(|(n, _)| n != strategy);
+            if tracked.is_empty() {
+                untracked.push(*bench);
+            }
+        }
+        for bench in untracked {
+            self.benchmarks.remove(&bench);
+            if let Some(sock) = subscriber
+                && let Err(e) = sock.set_unsubscribe(&bench.0)
+            {
+                error!(?bench, error = ?e, "failed to unsubscribe benchmark");
+            }
+        }
+
+        let mut unread = Vec::new();
+        for (reference, readers) in self.references.iter_mut() {
+            readers.retain(|(n, _)| n != strategy);
+            if readers.is_empty() {
+                unread.push(*reference);
+            }
+        }
+        for reference in unread {
+            self.references.remove(&reference);
+            if let Some(sock) = subscriber
+                && let Err(e) = sock.set_unsubscribe(&reference.0)
+            {
+                error!(?reference, error = ?e, "failed to unsubscribe reference");
+            }
+        }
+
+        if self.senders.is_empty() {
+            for strat_perfs in self.stg_map.values_mut() {
+                strat_perfs.retain(|sp| sp.stg.name().as_str() != strategy);
+            }
+            self.stg_map.retain(|_, strat_perfs| !strat_perfs.is_empty());
+        } else {
+            for worker_id in workers {
+                let msg = WorkerMsg::RemoveStrategy { strategy: strategy.into() };
+                if let Err(e) = self.senders[worker_id].send(msg) {
+                    error!(worker_id, error = ?e, "failed to remove strategy from worker");
+                }
+            }
+        }
+        info!(strategy, "removed strategy");
+        Ok("ok".into())
+    }
+
+    /// Check the whole configuration without processing a tick: registered strategies, contract
+    /// and fee resolution, calendar coverage, endpoint reachability and output paths. Call it
+    /// instead of `init()`; nothing is started.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let probe_timeout = Duration::from_secs(2);
+
+        let mut symbols: Vec<SymbolType> = self.stg_map.keys().copied().collect();
+        symbols.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        if symbols.is_empty() {
+            report.fail("strategy", "no strategies registered");
+        }
+        for symbol in &symbols {
+            let mut names = HashSet::new();
+            for strat_perf in &self.stg_map[symbol] {
+                let name = strat_perf.stg.name();
+                // fills and control commands find their strategy by name
+                if !names.insert(name.as_str().to_string()) {
+                    report.fail("strategy", format!("{} is registered twice on {:?}", name.as_str(), symbol));
+                    continue;
+                }
+                let mut detail = format!("{} on {:?}, worker {}", name.as_str(), symbol, self.symbol_workers[symbol]);
+                if let Some(benchmark) = strat_perf.stg.benchmark() {
+                    detail += &format!(", benchmarked against {:?}", benchmark.symbol);
+                }
+                report.ok("strategy", detail);
+            }
+
+            let blocked = self.blocked_symbols.read().unwrap().contains(symbol);
+            match self.symbol_contracts.get(symbol) {
+                None => report.ok("contract", format!("{:?}: fees supplied with its tracker", symbol)),
+                Some(contract) if blocked && !self.contracts.contains_key(contract) => {
+                    report.fail("contract", format!("{:?}: no entry for {}, orders would be blocked", symbol, contract))
+                }
+                Some(contract) => match self.contracts.get(contract) {
+                    Some(info) if info.lacks_fx(self.account_currency) => report.fail(
+                        "contract",
+                        format!(
+                            "{:?}: {} is priced in {} with no fx_rate into {}, orders would be blocked",
+                            symbol,
+                            contract,
+                            info.currency.unwrap_or_default(),
+                            self.account_currency
+                        ),
+                    ),
+                    None => report.warn("contract", format!("{:?}: no entry for {}, trading with default fees", symbol, contract)),
+                    Some(info) if info.multiplier <= 0.0 || info.min_move <= 0.0 => report.fail(
+                        "contract",
+                        format!(
+                            "{:?}: {} has multiplier {} and min_move {}",
+                            symbol, contract, info.multiplier, info.min_move
+                        ),
+                    ),
+                    Some(info) => report.ok(
+                        "contract",
+                        format!("{:?}: {} (multiplier {}, min_move {})", symbol, contract, info.multiplier, info.min_move),
+                    ),
+                },
+            }
+
+            if let Some((ref calendar, _)) = self.calendar {
+                match calendar.sessions(symbol) {
+                    Some(sessions) => report.ok("calendar", format!("{:?}: {} sessions", symbol, sessions.len())),
+                    None => report.warn(
+                        "calendar",
+                        format!("{:?}: product not in the calendar, every tick counts as in session", symbol),
+                    ),
+                }
+            }
+        }
+        if let Some((ref calendar, _)) = self.calendar
+            && !calendar.is_trading_day(timeutil::local_day(self.clock.now()))
+        {
+            report.warn("calendar", "today is not a trading day");
+        }
+
+        // an unreachable feed is only fatal if no other feed is up
+        let feeds: Vec<_> = self
+            .tick_uris
+            .iter()
+            .map(|uri| (uri, validate::probe_endpoint(uri, probe_timeout)))
+            .collect();
+        let any_feed = feeds.iter().any(|(_, probe)| probe.is_ok());
+        for (uri, probe) in feeds {
+            match probe {
+                Ok(true) => report.ok("endpoint", format!("tick feed {} is reachable", uri)),
+                Ok(false) => report.warn("endpoint", format!("tick feed {} cannot be probed", uri)),
+                Err(e) if any_feed => report.warn("endpoint", format!("tick feed {}: {}", uri, e)),
+                Err(e) => report.fail("endpoint", format!("tick feed {}: {}", uri, e)),
+            }
+        }
+        for (what, uri) in [("orders", Some(&self.order_uri)), ("reports", self.report_uri.as_ref())] {
+            let Some(uri) = uri else {
+                continue;
+            };
+            match validate::probe_endpoint(uri, probe_timeout) {
+                Ok(true) => report.ok("endpoint", format!("{} {} is reachable", what, uri)),
+                Ok(false) => report.warn("endpoint", format!("{} {} cannot be probed", what, uri)),
+                Err(e) => report.fail("endpoint", format!("{} {}: {}", what, uri, e)),
+            }
+        }
+        if let Some(ref addr) = self.metrics_addr {
+            match validate::probe_bind(addr) {
+                Ok(()) => report.ok("endpoint", format!("metrics {} can be bound", addr)),
+                Err(e) => report.fail("endpoint", format!("metrics {}: {}", addr, e)),
+            }
+        }
+        if self.control_socket.is_some() {
+            report.ok("endpoint", "control socket is bound");
+        }
+        if self.query_socket.is_some() {
+            report.ok("endpoint", "query socket is bound");
+        }
+
+        if let Some(ref dir) = self.record_dir {
+            let probe = dir.join(".validate");
+            match std::fs::create_dir_all(dir)
+                .and_then(|()| std::fs::write(&probe, b""))
+                .and_then(|()| std::fs::remove_file(&probe))
+            {
+                Ok(()) => report.ok("output", format!("tick recorder {} is writable", dir.display())),
+                Err(e) => report.fail("output", format!("tick recorder {}: {}", dir.display(), e)),
+            }
+        }
+        if self.audit_log.is_some() {
+            report.ok("output", "audit log is open");
+        }
+        if let Some(ref path) = self.order_store_path {
+            match OrderStore::open(path, 0) {
+                Ok(_) => report.ok("output", format!("order store {} is writable", path.display())),
+                Err(e) => report.fail("output", format!("order store {}: {}", path.display(), e)),
+            }
+        }
+        if self.seq_store.is_some() {
+            report.ok("output", "fill sequence store is open");
+        }
+        for kind in self.plugins.kinds() {
+            report.ok("plugin", format!("strategy kind {}", kind));
+        }
+        if self.affinity != CpuAffinity::default() {
+            let allowed = affinity::allowed_cores();
+            let mut seen = HashSet::new();
+            for core in self.affinity.cores() {
+                match allowed {
+                    Err(ref e) => report.warn("cpu", format!("core {} cannot be pinned: {}", core, e)),
+                    Ok(ref allowed) if !allowed.contains(&core) => report.warn("cpu", format!("core {} is not available to this process", core)),
+                    Ok(_) if !seen.insert(core) => report.warn("cpu", format!("core {} is pinned more than once", core)),
+                    Ok(_) => report.ok("cpu", format!("core {} is available", core)),
+                }
+            }
+        }
+        report
+    }
+
+    /// Let the router reassign every symbol registered so far now that it sees all of them.
+    fn rebalance(&mut self) {
+        let symbols: Vec<SymbolType> = self.symbol_workers.keys().copied().collect();
+        let Some(assignment) = self.router.rebalance(&symbols, self.num_workers) else {
+            return;
+        };
+        for batch in self.symbol_batches.iter_mut() {
+            batch.clear();
+        }
+        for (symbol, worker_id) in assignment {
+            let worker_id = worker_id % self.num_workers;
+            self.symbol_workers.insert(symbol, worker_id);
+            self.symbol_batches[worker_id].insert(symbol);
+        }
+        // benchmark prices and reference ticks go to whichever worker now runs each strategy
+        for tracked in self.benchmarks.values_mut().chain(self.references.values_mut()) {
+            for (strategy, worker_id) in tracked.iter_mut() {
+                let owner = self
+                    .symbol_strategies
+                    .iter()
+                    .find(|(_, names)| names.contains(strategy))
+                    .and_then(|(symbol, _)| self.symbol_workers.get(symbol));
+                if let Some(&owner) = owner {
+                    *worker_id = owner;
+                }
+            }
+        }
+        for (worker_id, batch) in self.symbol_batches.iter().enumerate() {
+            info!(worker_id, symbols = ?batch, "rebalanced symbols");
+        }
+    }
+
+    /// Count ticks per subscribed symbol on the primary feed for `window` and return ticks per
+    /// second, e.g. for a `TickRateRouter`. Call it after adding strategies, so their symbols
+    /// are subscribed, and before `init()`; the ticks it reads are not dispatched.
+    pub fn measure_tick_rates(&mut self, window: Duration) -> Result<HashMap<SymbolType, f64>, EngineError> {
+        let feeds = self
+            .tick_subscriber
+            .as_ref()
+            .ok_or(EngineError::State("no SUB socket: measure_tick_rates() after stop()"))?;
+        let socket = feeds.socket(0);
+        let mut tick_buf = vec![0u8; self.wire_format.max_frame()];
+        let mut counts: HashMap<SymbolType, u64> = HashMap::new();
+        let deadline = Instant::now() + window;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            let mut items = [socket.as_poll_item(zmq::POLLIN)];
+            if let Err(e) = zmq::poll(&mut items, left.as_millis().max(1) as i64) {
+                warn!(error = ?e, "poll error or interrupted while measuring tick rates");
+                break;
+            }
+            if !items[0].is_readable() {
+                continue;
+            }
+            match socket.recv_into(&mut tick_buf, 0) {
+                Ok(n) => {
+                    if let Some(tick) = self.wire_format.decode(&tick_buf[..n.min(tick_buf.len())]) {
+                        *counts.entry(tick.symbol).or_default() += 1;
+                    }
+                }
+                Err(e) => {
+                    warn!(error = ?e, "SUB socket error while measuring tick rates");
+                    break;
+                }
+            }
+        }
+        let secs = window.as_secs_f64().max(f64::EPSILON);
+        Ok(counts.into_iter().map(|(symbol, count)| (symbol, count as f64 / secs)).collect())
+    }
+
+    /// Split `stg_map` into each worker’s “partial_map” and spawn the threads.
+    pub fn init(&mut self) -> Result<(), EngineError> {
+        self.rebalance();
+        if let Some(handoff) = self.resume.take() {
+            for (&symbol, strat_perfs) in self.stg_map.iter_mut() {
+                for strat_perf in strat_perfs.iter_mut() {
+                    let name = strat_perf.stg.name();
+                    match handoff.strategy(name.as_str(), symbol.as_str()) {
+                        Some(state) => {
+                            restore_state(symbol, strat_perf, state);
+                            sync_account(strat_perf, symbol);
+                            info!(strategy = %name.as_str(), ?symbol, long = ?state.long, short = ?state.short, "resumed from handoff");
+                        }
+                        None => warn!(strategy = %name.as_str(), ?symbol, "not in handoff file, starting flat"),
+                    }
+                }
+            }
+        }
+
+        if let Some(entries) = self.recovered.take() {
+            let mut by_strategy: HashMap<(SymbolType, String), Vec<&Entry>> = HashMap::new();
+            for entry in &entries {
+                if let Entry::Order(order) | Entry::Fill(order) | Entry::Cancel(order) = entry {
+                    by_strategy
+                        .entry((order.symbol, order.stg_name.as_str().to_string()))
+                        .or_default()
+                        .push(entry);
+                }
+            }
+            for (&symbol, strat_perfs) in self.stg_map.iter_mut() {
+                for strat_perf in strat_perfs.iter_mut() {
+                    let name = strat_perf.stg.name();
+                    let Some(journaled) = by_strategy.remove(&(symbol, name.as_str().to_string())) else {
+                        continue;
+                    };
+                    replay_journal(strat_perf, journaled.iter().copied());
+                    sync_account(strat_perf, symbol);
+                    let (long, short) = held_lots(strat_perf);
+                    info!(strategy = %name.as_str(), ?symbol, entries = journaled.len(), long, short, "recovered from journal");
+                }
+            }
+            for (symbol, name) in by_strategy.into_keys() {
+                warn!(strategy = %name, ?symbol, "journaled strategy not registered, its entries skipped");
             }
         }
-        // Push into stg_map (we’ll later drain each Vec into a worker).
-        self.stg_map.entry(symbol).or_insert_with(Vec::new).push(StratPerf {
-            stg: strategy,
-            perf: performance_tracker,
-        });
 
-        // Figure out which worker “owns” this symbol (and all its strategies):
-        let worker_id = (symbol.hash_future_symbol() as usize) % self.num_workers;
-        self.symbol_batches[worker_id].insert(symbol);
-    }
+        if let Some(ref path) = self.order_store_path {
+            let store = OrderStore::open(path, self.clock.now()).map_err(|source| EngineError::Store { path: path.clone(), source })?;
+            let (tx, rx) = mpsc::channel::<StoreRecord>();
+            self.order_store_sender = Some(tx);
+            let spawned = thread::Builder::new().name("order-store".into()).spawn(move || {
+                info!(session = store.session(), "recording orders and fills");
+                for record in rx {
+                    if let Err(e) = store.record(&record, timeutil::now_stamp()) {
+                        error!(?record, error = ?e, "failed to write to order store");
+                    }
+                }
+                info!("exiting order store thread");
+            });
+            self.order_store_handle = Some(spawned.map_err(EngineError::spawn("order store"))?);
+        }
 
-    /// Split `stg_map` into each worker’s “partial_map” and spawn the threads.
-    pub fn init(&mut self) {
         for worker_id in 0..self.num_workers {
             // Build this worker’s partial_map from `symbol_batches[worker_id]`.
             let mut partial_stg_map: HashMap<_, _> = self.symbol_batches[worker_id]
@@ -77,84 +2976,877 @@ impl CtaEngine {
                 .filter_map(|sym| self.stg_map.remove(&sym).map(|v| (sym, v)))
                 .collect();
 
-            let (tx, rx) = mpsc::channel::<TickData>();
+            // strategies read time through their worker's clock, which follows that worker's ticks
+            let clock = self.clock.fork();
+            for strat_perf in partial_stg_map.values_mut().flatten() {
+                strat_perf.stg.on_clock(Arc::clone(&clock));
+            }
+
+            let (tx, rx) = queue::channel::<WorkerMsg>(self.queue_capacity, self.queue_spin);
             self.senders.push(tx);
 
-            // Each worker gets its own ZMQ context for pushing orders:
-            let ctx_clone = self.ctx.clone();
-            let order_uri = self.order_uri.clone();
+            // Each worker gets its own socket for pushing orders
+            let order_pusher = OrderPusher::connect(&self.ctx, &self.order_uri, self.reconnect)
+                .map_err(EngineError::socket(format!("connect PUSH socket to {}", self.order_uri)))?;
+            let paused_symbols = Arc::clone(&self.paused_symbols);
+            let blocked_symbols = Arc::clone(&self.blocked_symbols);
+            let rolled_off = Arc::clone(&self.rolled_off);
+            let halted = Arc::clone(&self.halted);
+            let replaying = Arc::clone(&self.replaying);
+            let ids = Arc::clone(&self.ids);
+            let metrics = Arc::clone(&self.metrics);
+            let audit_log = self.audit_log.as_ref().map(AuditLog::sender);
+            let store = self.order_store_sender.clone();
+            let journal = self.journal.clone();
+            let book_on_send = self.report_socket.is_none();
+            let queue_config = self.order_queue;
+            let seq_store = self.seq_store.clone();
+            let core = self.affinity.worker(worker_id);
+            let history = self.history.clone();
+            let warmup = self.warmup.clone();
 
-            let handle = thread::spawn(move || {
-                let order_pusher = ctx_clone.socket(zmq::PUSH).expect("Failed to create PUSH socket");
-                // unlimited SNDHWM, order_pusher.send won't block
-                order_pusher.set_sndhwm(0).expect("Failed to set SNDHWM");
-                order_pusher.set_linger(0).expect("Failed to set linger");
-                order_pusher.connect(&order_uri).expect("Failed to connect PUSH to order_uri");
+            let spawned = thread::Builder::new().name(format!("worker-{}", worker_id)).spawn(move || {
+                // every event logged by this thread carries the worker id
+                let _span = info_span!("worker", id = worker_id).entered();
+                if let Some(core) = core {
+                    match affinity::pin_current_thread(core) {
+                        Ok(()) => info!(core, "pinned worker thread"),
+                        Err(e) => warn!(core, error = ?e, "failed to pin worker thread"),
+                    }
+                }
 
-                for tick in rx {
+                let mut sink = OrderSink {
+                    pusher: RefCell::new(order_pusher),
+                    paused: paused_symbols.view(),
+                    blocked_symbols,
+                    halted: Arc::clone(&halted),
+                    replaying,
+                    ids,
+                    metrics: Arc::clone(&metrics),
+                    audit_log,
+                    store,
+                    journal,
+                    book_on_send,
+                    queue_config,
+                    queues: HashMap::new(),
+                    worker_id,
+                    tick_started_ns: 0,
+                };
+
+                let start = |symbol: SymbolType, strat_perf: &mut StratPerf| {
+                    let ctx = EngineContext {
+                        symbol,
+                        worker: worker_id,
+                        now: clock.now(),
+                        history: history.as_deref(),
+                    };
+                    strat_perf.stg.on_start(&ctx);
+                    if let Some((ref source, count)) = warmup {
+                        match source.ticks(&symbol, clock.now(), count) {
+                            Ok(ticks) => {
+                                warm_up(strat_perf, &ticks);
+                                info!(strategy = %strat_perf.stg.name().as_str(), ?symbol, ticks = ticks.len(), "warmed up");
+                            }
+                            Err(e) => warn!(strategy = %strat_perf.stg.name().as_str(), ?symbol, error = ?e, "no warm-up history"),
+                        }
+                    }
+                };
+                for (&symbol, strat_perfs) in partial_stg_map.iter_mut() {
+                    for strat_perf in strat_perfs.iter_mut() {
+                        start(symbol, strat_perf);
+                    }
+                }
+
+                let worker_metrics = &metrics.workers[worker_id];
+                let mut last_ticks = HashMap::new();
+                // the full last tick per symbol, to flatten at when no tick is coming
+                let mut last_quotes: HashMap<SymbolType, TickData> = HashMap::new();
+                for msg in rx {
+                    let tick = match msg {
+                        WorkerMsg::Tick(tick, dispatched_ns) => {
+                            sink.tick_started_ns = timeutil::now_nanos();
+                            worker_metrics.dispatch_strategy.record(sink.tick_started_ns - dispatched_ns);
+                            tick
+                        }
+                        WorkerMsg::Fill(report) => {
+                            let fill = report.fill;
+                            let strategy = fill.stg_name.as_str();
+                            if let Some(ref store) = seq_store
+                                && !store.lock().unwrap().is_new(strategy, report.seq)
+                            {
+                                debug!(seq = report.seq, strategy, "skipping already applied fill");
+                                continue;
+                            }
+                            let Some(strat_perf) = report_owner(&mut partial_stg_map, &fill) else {
+                                warn!(seq = report.seq, strategy, "fill for unknown strategy");
+                                continue;
+                            };
+                            book_fill(strat_perf, &fill);
+                            sink.store(StoreRecord::Fill(fill));
+                            sink.journal(Entry::Fill(fill));
+                            if let Some(ref store) = seq_store {
+                                store.lock().unwrap().commit(strategy, report.seq);
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Status(report) => {
+                            let strategy = report.order.stg_name.as_str();
+                            if let Some(ref store) = seq_store
+                                && !store.lock().unwrap().is_new(strategy, report.seq)
+                            {
+                                debug!(seq = report.seq, strategy, "skipping already applied status report");
+                                continue;
+                            }
+                            if let Some(strat_perf) = report_owner(&mut partial_stg_map, &report.order) {
+                                match end_order(strat_perf, &report) {
+                                    Some((rest, resend)) => {
+                                        sink.journal(Entry::Cancel(rest));
+                                        // what the confirmation says was left, not what the engine last knew of
+                                        let rest = Order {
+                                            lots: report.order.lots,
+                                            ..rest
+                                        };
+                                        match last_quotes.get(&rest.symbol) {
+                                            Some(tick) if resend && rest.lots > 0 => {
+                                                sink.resend(strat_perf, rest, tick);
+                                            }
+                                            _ if resend => strat_perf.stg.on_order_cancelled(&rest),
+                                            _ => {}
+                                        }
+                                    }
+                                    None => debug!(seq = report.seq, order = ?report.order, "status report for an order no longer pending"),
+                                }
+                            }
+                            if let Some(ref store) = seq_store {
+                                store.lock().unwrap().commit(strategy, report.seq);
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Benchmark { symbol, price } => {
+                            let trackers = partial_stg_map.values_mut().flatten().filter_map(|sp| sp.tracking.as_mut());
+                            for tracking in trackers.filter(|t| t.benchmark().symbol == symbol) {
+                                tracking.on_benchmark(price);
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Reference(tick) => {
+                            let readers = partial_stg_map.values_mut().flatten().filter(|sp| sp.references.contains(&tick.symbol));
+                            for strat_perf in readers {
+                                strat_perf.stg.on_reference_tick(&tick);
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Session {
+                            symbol,
+                            session,
+                            started,
+                            closes_day,
+                        } => {
+                            for strat_perf in partial_stg_map.get_mut(&symbol).into_iter().flatten() {
+                                if started {
+                                    strat_perf.stg.on_session_start(&session);
+                                } else {
+                                    strat_perf.stg.on_session_end(&session);
+                                }
+                                if closes_day && strat_perf.day_open {
+                                    strat_perf.day_open = false;
+                                    strat_perf.stg.on_day_close();
+                                }
+                            }
+                            continue;
+                        }
+                        WorkerMsg::UpdateParam { strategy, name, value } => {
+                            let matching = partial_stg_map.values_mut().flatten().filter(|sp| sp.stg.name().as_str() == strategy);
+                            for strat_perf in matching {
+                                match strat_perf.stg.on_param_update(&name, value) {
+                                    Ok(()) => info!(%strategy, param = %name, value, "parameter updated"),
+                                    Err(e) => warn!(%strategy, param = %name, value, reason = %e, "parameter rejected"),
+                                }
+                            }
+                            continue;
+                        }
+                        WorkerMsg::SetPaused { strategy, paused } => {
+                            let matching = partial_stg_map.values_mut().flatten().filter(|sp| sp.stg.name().as_str() == strategy);
+                            for strat_perf in matching.filter(|sp| sp.paused != paused) {
+                                strat_perf.paused = paused;
+                                if !paused {
+                                    let (long, short) = held_lots(strat_perf);
+                                    strat_perf.stg.on_resume(long, short);
+                                }
+                            }
+                            continue;
+                        }
+                        WorkerMsg::SetToggle { strategy, name, enabled } => {
+                            let matching = partial_stg_map.values_mut().flatten().filter(|sp| sp.stg.name().as_str() == strategy);
+                            for strat_perf in matching {
+                                match strat_perf.stg.on_toggle(&name, enabled) {
+                                    Ok(()) => info!(%strategy, toggle = %name, enabled, "toggle set"),
+                                    Err(e) => warn!(%strategy, toggle = %name, enabled, reason = %e, "toggle rejected"),
+                                }
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Cancel { strategy, client_id } => {
+                            let matching = partial_stg_map.values_mut().flatten().filter(|sp| sp.stg.name().as_str() == strategy);
+                            for strat_perf in matching {
+                                sink.cancel(strat_perf, client_id, clock.now());
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Preview { strategy, order, reply } => {
+                            let strat_perf = partial_stg_map.values_mut().flatten().find(|sp| sp.stg.name().as_str() == strategy);
+                            let outcome = match strat_perf {
+                                Some(strat_perf) => sink.preview(strat_perf, &order),
+                                None => format!("rejected: {} is not on this worker", strategy),
+                            };
+                            // the requester may have timed out already
+                            let _ = reply.send(outcome);
+                            continue;
+                        }
+                        WorkerMsg::Snapshot { filter, reply } => {
+                            let snapshots = partial_stg_map
+                                .iter()
+                                .flat_map(|(symbol, strat_perfs)| strat_perfs.iter().map(move |sp| (*symbol, sp)))
+                                .filter(|(symbol, sp)| filter.matches(sp.stg.name().as_str(), symbol))
+                                .map(|(symbol, sp)| snapshot(worker_id, symbol, last_quotes.get(&symbol).map_or(0.0, |t| t.last), sp, &filter))
+                                .collect();
+                            let _ = reply.send(snapshots);
+                            continue;
+                        }
+                        WorkerMsg::AddStrategy { symbol, mut strat_perf } => {
+                            strat_perf.stg.on_clock(Arc::clone(&clock));
+                            start(symbol, &mut strat_perf);
+                            partial_stg_map.entry(symbol).or_default().push(strat_perf);
+                            continue;
+                        }
+                        WorkerMsg::RemoveStrategy { strategy } => {
+                            for (&symbol, strat_perfs) in partial_stg_map.iter_mut() {
+                                for sp in strat_perfs.iter_mut().filter(|sp| sp.stg.name().as_str() == strategy) {
+                                    sp.stg.on_stop();
+                                    if let Some(ref account) = sp.account {
+                                        account.lock().unwrap().remove(&strategy, symbol);
+                                    }
+                                }
+                                strat_perfs.retain(|sp| sp.stg.name().as_str() != strategy);
+                            }
+                            partial_stg_map.retain(|_, strat_perfs| !strat_perfs.is_empty());
+                            continue;
+                        }
+                        WorkerMsg::SetContract { symbol, info } => {
+                            for strat_perf in partial_stg_map.get_mut(&symbol).into_iter().flatten() {
+                                let margin_change = strat_perf.perf.set_info(info);
+                                sync_account(strat_perf, symbol);
+                                info!(strategy = %strat_perf.stg.name().as_str(), ?symbol, margin_change, "switched to reloaded contract info");
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Roll(roll) => {
+                            for strat_perf in partial_stg_map.get_mut(&roll.from).into_iter().flatten() {
+                                strat_perf.rolling_to = Some(roll.to);
+                                strat_perf.reopen = Some(held_lots(strat_perf));
+                            }
+                            continue;
+                        }
+                        WorkerMsg::FlattenAll => {
+                            for (symbol, strat_perfs) in partial_stg_map.iter_mut() {
+                                let quote = last_quotes.get(symbol);
+                                for strat_perf in strat_perfs.iter_mut() {
+                                    strat_perf.halted = true;
+                                    match quote {
+                                        Some(quote) => sink.flatten(strat_perf, quote),
+                                        None => strat_perf.flatten_due = true,
+                                    }
+                                }
+                                let held = sink.queues.get(symbol).map_or(0, OrderQueue::len);
+                                if held > 0 {
+                                    warn!(?symbol, held, "closes held in the order queue until the next tick");
+                                }
+                            }
+                            // the engine may be exiting right after: give the closes time to go out
+                            if let Err(e) = sink.pusher.get_mut().set_linger(FLATTEN_LINGER_MS) {
+                                warn!(error = ?e, "failed to set linger on the order socket");
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Flatten { symbol } => {
+                            for strat_perf in partial_stg_map.get_mut(&symbol).into_iter().flatten() {
+                                strat_perf.halted = true;
+                                strat_perf.flatten_due = true;
+                            }
+                            continue;
+                        }
+                        WorkerMsg::Rearm { strategy } => {
+                            let matching = partial_stg_map
+                                .values_mut()
+                                .flatten()
+                                .filter(|sp| strategy.as_deref().is_none_or(|s| s == sp.stg.name().as_str()));
+                            for strat_perf in matching {
+                                strat_perf.halted = false;
+                                let equity = strat_perf.perf.equity();
+                                if let Some(ref mut breaker) = strat_perf.breaker {
+                                    breaker.reset(equity);
+                                }
+                            }
+                            continue;
+                        }
+                    };
+                    let loop_start = Instant::now();
+                    clock.on_event(tick.stamp);
+
+                    // ticks already queued when their symbol was paused are dropped as well
+                    if sink.paused.contains(&tick.symbol) {
+                        continue;
+                    }
+                    let last = LastTick {
+                        stamp: tick.stamp,
+                        volume: tick.volume,
+                    };
+                    last_ticks.insert(tick.symbol, last);
+                    last_quotes.insert(tick.symbol, tick);
                     if let Some(strategies) = partial_stg_map.get_mut(&tick.symbol) {
+                        // ahead of releasing queued orders, which are checked against the limits
                         for strat_perf in strategies.iter_mut() {
-                            if let Some(order) = strat_perf.stg.update(&tick) {
-                                println!("[Worker {}] send: {:?}", worker_id, &order);
-
-                                // Serialize the entire `Order` including any padding.
-                                let bytes: &[u8] = unsafe {
-                                    let ptr = &order as *const Order as *const u8;
-                                    std::slice::from_raw_parts(ptr, mem::size_of::<Order>())
-                                };
-                                if let Err(e) = order_pusher.send(bytes, 0) {
-                                    eprintln!("Error sending on PUSH socket: {:?}", e);
+                            track_price_limits(strat_perf, &tick);
+                        }
+                        sink.release(strategies, &tick);
+                        let trading_day = calendar::trading_day(tick.stamp, calendar::is_weekday);
+                        let mut rolled = false;
+                        for strat_perf in strategies.iter_mut() {
+                            if strat_perf.rolling_to.is_some() {
+                                // flatten here, then wait until the closes are done
+                                let name = strat_perf.stg.name();
+                                let in_flight: Vec<OffsetFlagType> = strat_perf
+                                    .pending
+                                    .iter()
+                                    .chain(sink.queued(tick.symbol, name))
+                                    .map(|o| o.offset)
+                                    .collect();
+                                if in_flight.is_empty() && held_lots(strat_perf) == (0, 0) {
+                                    rolled = true;
+                                } else if !in_flight.contains(&OffsetFlagType::CLOSE) {
+                                    sink.flatten(strat_perf, &tick);
+                                }
+                                continue;
+                            }
+                            if mem::take(&mut strat_perf.flatten_due) {
+                                warn!(strategy = %strat_perf.stg.name().as_str(), symbol = ?tick.symbol, "flattening by command");
+                                sink.flatten(strat_perf, &tick);
+                                continue;
+                            }
+                            if let Some((long, short)) = strat_perf.reopen.take() {
+                                sink.reopen(strat_perf, &tick, long, short);
+                            }
+                            if strat_perf.trading_day != trading_day {
+                                if strat_perf.day_open {
+                                    strat_perf.stg.on_day_close();
                                 }
+                                strat_perf.trading_day = trading_day;
+                                strat_perf.day_open = true;
+                                strat_perf.stg.on_day_open();
+                            }
+                            if let Some(order) = strat_perf.stops.check(strat_perf.stg.name(), &tick)
+                                && sink.submit(strat_perf, order, &tick, Purpose::Flatten)
+                            {
+                                strat_perf.stops.disarm(&order);
+                                info!(strategy = %order.stg_name.as_str(), ?order, "stop triggered");
+                                strat_perf.stg.on_stop_triggered(&order);
+                            }
 
-                                strat_perf.perf.on_fill(&order);
+                            let account = SizingContext::new(strat_perf.perf.equity(), strat_perf.perf.info());
+                            strat_perf.stg.on_account(&account);
+                            for order in budgeted_update(strat_perf, &tick, &sink, &metrics) {
+                                if strat_perf.paused {
+                                    debug!(strategy = %order.stg_name.as_str(), ?order, "dropped order of paused strategy");
+                                    continue;
+                                }
+                                // the rest of the batch counted on this one going out
+                                if !sink.submit(strat_perf, order, &tick, Purpose::of(&order)) {
+                                    break;
+                                }
+                            }
+                            let mut cancels = strat_perf.stg.take_cancels();
+                            if let Some(timeout) = strat_perf.stg.order_timeout_ms() {
+                                cancels.extend(strat_perf.pending.expired(clock.now(), timeout));
+                            }
+                            for client_id in cancels {
+                                sink.cancel(strat_perf, client_id, tick.stamp);
+                            }
+                            if let Some(after_ms) = strat_perf.partial_fills.after_ms() {
+                                for client_id in strat_perf.pending.stale_partials(clock.now(), after_ms) {
+                                    if let PartialFillPolicy::Resend { .. } = strat_perf.partial_fills {
+                                        sink.cancel_to_resend(strat_perf, client_id, tick.stamp);
+                                    } else {
+                                        sink.cancel(strat_perf, client_id, tick.stamp);
+                                    }
+                                }
                             }
                             strat_perf.perf.on_tick_end(&tick);
+                            sync_account(strat_perf, tick.symbol);
+                            if let Some(churn) = strat_perf.order_stats.on_tick_end(tick.stamp) {
+                                warn_churn(strat_perf, churn);
+                            }
+                            if let Some(stats) = strat_perf.order_stats.take_changed() {
+                                metrics.set_order_stats(strat_perf.stg.name().as_str(), stats);
+                            }
+                            let equity = strat_perf.perf.equity();
+                            if let Some(alert) = strat_perf.tracking.as_mut().and_then(|t| t.on_equity(tick.stamp, equity)) {
+                                report_tracking(strat_perf, alert, &metrics);
+                            }
+                            let trip = strat_perf.breaker.as_mut().and_then(|b| b.on_equity(tick.stamp, equity));
+                            if let Some(trip) = trip {
+                                error!(strategy = %strat_perf.stg.name().as_str(), %trip, equity, "circuit breaker tripped, flattening");
+                                metrics.breaker_trips.fetch_add(1, Ordering::Relaxed);
+                            }
+                            // a shared account is funded across strategies, so its margin is the account's to call
+                            let margin_call = strat_perf.account.is_none() && strat_perf.perf.is_margin_call();
+                            if margin_call && !strat_perf.halted {
+                                error!(
+                                    strategy = %strat_perf.stg.name().as_str(),
+                                    equity,
+                                    margin = strat_perf.perf.margin(),
+                                    risk_degree = strat_perf.perf.risk_degree(),
+                                    "margin call, liquidating"
+                                );
+                                metrics.margin_calls.fetch_add(1, Ordering::Relaxed);
+                            }
+                            if !strat_perf.halted && (trip.is_some() || margin_call || halted.load(Ordering::Relaxed)) {
+                                strat_perf.halted = true;
+                                sink.flatten(strat_perf, &tick);
+                            }
+                        }
+                        if rolled {
+                            let flat = |sp: &StratPerf| sp.rolling_to.is_some() && sp.pending.is_empty() && held_lots(sp) == (0, 0);
+                            let (moving, staying): (Vec<_>, Vec<_>) = mem::take(strategies).into_iter().partition(flat);
+                            *strategies = staying;
+                            for mut strat_perf in moving {
+                                let to = strat_perf.rolling_to.take().expect("only rolling strategies move");
+                                let name = strat_perf.stg.name();
+                                if let Some(ref account) = strat_perf.account {
+                                    account.lock().unwrap().remove(name.as_str(), tick.symbol);
+                                }
+                                info!(strategy = %name.as_str(), from = ?tick.symbol, ?to, reopen = ?strat_perf.reopen, "rolled over");
+                                partial_stg_map.entry(to).or_default().push(strat_perf);
+                            }
+                            partial_stg_map.retain(|_, strat_perfs| !strat_perfs.is_empty());
+                            if !partial_stg_map.contains_key(&tick.symbol) {
+                                rolled_off.lock().unwrap().push(tick.symbol);
+                            }
                         }
                     }
+                    worker_metrics.observe_loop(loop_start.elapsed());
+                }
+
+                info!("exiting worker thread");
+                for strat_perf in partial_stg_map.values_mut().flatten() {
+                    strat_perf.stg.on_stop();
+                }
+                let states = partial_stg_map
+                    .iter()
+                    .flat_map(|(&symbol, strat_perfs)| strat_perfs.iter().map(move |sp| export_state(symbol, sp)))
+                    .collect();
+                (states, last_ticks)
+            });
+
+            self.handles.push(spawned.map_err(EngineError::spawn(format!("worker-{}", worker_id)))?);
+        }
+
+        if let Some(ref addr) = self.metrics_addr {
+            // the server thread lives as long as the process; it is never joined
+            if let Err(e) = metrics::serve(addr, Arc::clone(&self.metrics)) {
+                error!(addr, error = ?e, "failed to serve metrics");
+            }
+        }
+        #[cfg(feature = "dashboard")]
+        if let Some(ref addr) = self.dashboard_addr {
+            match self.query_uri {
+                Some(ref query_uri) => {
+                    let metrics = Arc::clone(&self.metrics);
+                    if let Err(e) = crate::dashboard::serve(addr, self.ctx.clone(), query_uri, metrics, Duration::from_secs(1)) {
+                        error!(addr, error = ?e, "failed to serve dashboard");
+                    }
+                }
+                None => error!(addr, "the dashboard needs the query socket; call enable_query"),
+            }
+        }
+
+        // Disk writes happen on their own thread so they never stall tick dispatch.
+        if let Some(dir) = self.record_dir.clone() {
+            let (tx, rx) = mpsc::channel::<TickData>();
+            self.recorder_sender = Some(tx);
+            let spawned = thread::Builder::new().name("recorder".into()).spawn(move || {
+                let mut recorder = TickRecorder::new(&dir);
+                for tick in rx {
+                    if let Err(e) = recorder.record(&tick) {
+                        error!(symbol = ?tick.symbol, error = ?e, "failed to record tick");
+                    }
                 }
+                info!("exiting recorder thread");
+            });
+            self.recorder_handle = Some(spawned.map_err(EngineError::spawn("recorder"))?);
+        }
 
-                println!("[Worker {}] Exiting thread.", worker_id);
+        if let Some(ref path) = self.event_log_path {
+            let mut log = EventLog::open(path).map_err(EngineError::file("event log", path))?;
+            let (tx, rx) = mpsc::channel::<(i64, Event)>();
+            self.event_sender = Some(tx);
+            let spawned = thread::Builder::new().name("event-log".into()).spawn(move || {
+                for (stamp, event) in rx {
+                    if let Err(e) = log.append(stamp, &event) {
+                        error!(error = ?e, "failed to append to event log");
+                    }
+                }
+                if let Err(e) = log.flush() {
+                    error!(error = ?e, "failed to flush event log");
+                }
+                info!("exiting event log thread");
             });
+            self.event_log_handle = Some(spawned.map_err(EngineError::spawn("event log"))?);
+        }
+        Ok(())
+    }
+
+    /// Log `event`, then apply it. Everything that changes strategy state enters here.
+    fn on_event(&mut self, event: Event, subscriber: &TickFeeds) -> Result<String, String> {
+        if let Some(ref log) = self.event_sender {
+            // the log only fails after its thread died; keep trading regardless
+            let _ = log.send((self.clock.now(), event.clone()));
+        }
+        self.apply(event, subscriber)
+    }
+
+    fn apply(&mut self, event: Event, subscriber: &TickFeeds) -> Result<String, String> {
+        match event {
+            Event::Tick(tick) => self.dispatch_tick(tick, subscriber),
+            Event::Report(report) => self.dispatch_report(report.fill.symbol, report.seq, WorkerMsg::Fill(report)),
+            Event::Status(report) => self.dispatch_report(report.order.symbol, report.seq, WorkerMsg::Status(report)),
+            Event::Command(line) => return Command::parse(&line).and_then(|command| self.execute(command, subscriber)),
+        }
+        Ok(String::new())
+    }
+
+    /// Apply the events logged at `path` in order, without logging them again, and return how
+    /// many there were. With an event clock set (see `set_clock`), the strategies end up in
+    /// the state the logging run left them in. Call after `init()` instead of `start()`.
+    ///
+    /// Nothing is sent to the gateway from then on. Set up like the logging run, an engine
+    /// taking execution reports books the fills the log's reports carry, and one booking on
+    /// send assumes them at the logged ticks again.
+    pub fn replay_events<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let subscriber = self
+            .tick_subscriber
+            .take()
+            .ok_or_else(|| io::Error::other("no SUB socket: replay_events() after stop()"))?;
+        self.replaying.store(true, Ordering::Relaxed);
+        let mut count = 0;
+        let result = EventReader::open(path).and_then(|events| {
+            for logged in events {
+                let logged = logged?;
+                self.clock.on_event(logged.stamp);
+                if let Err(e) = self.apply(logged.event, &subscriber) {
+                    warn!(seq = logged.seq, error = %e, "replayed command failed");
+                }
+                count += 1;
+            }
+            Ok(count)
+        });
+        self.tick_subscriber = Some(subscriber);
+        result
+    }
 
-            self.handles.push(handle);
+    /// Run one accepted tick through recording, the watchdog, the calendar and the pause
+    /// list, then hand it to its worker.
+    fn dispatch_tick(&mut self, tick: TickData, subscriber: &TickFeeds) {
+        self.metrics.on_tick(&tick.symbol);
+        if let Some(ref recorder) = self.recorder_sender {
+            // recorder only fails after its thread died; keep trading regardless
+            let _ = recorder.send(tick);
+        }
+        self.clock.on_event(tick.stamp);
+        self.last_tick_stamp = Some(tick.stamp);
+        let received = self.clock.now();
+        if let Some(ref mut skew) = self.skew {
+            let alert = skew.on_tick(tick.symbol, tick.stamp, received);
+            if let Some(stats) = skew.stats(&tick.symbol) {
+                self.metrics.set_skew(&tick.symbol, stats.mean_ms.round() as i64);
+            }
+            if let Some(alert) = alert {
+                self.raise_data_alert(alert);
+            }
+        }
+        if let Some(ref mut watchdog) = self.watchdog {
+            let drop_stale = watchdog.config().drop_stale;
+            let (resumed, stale) = watchdog.on_tick(&tick, received);
+            let is_stale = stale.is_some();
+            for alert in resumed.into_iter().chain(stale) {
+                self.raise_data_alert(alert);
+            }
+            if is_stale && drop_stale {
+                return;
+            }
+        }
+        if let Some((ref calendar, filter)) = self.calendar {
+            let in_session = calendar.session_at(&tick.symbol, tick.stamp) != Some(None);
+            let minute = tick.stamp.div_euclid(60 * timeutil::STAMP_PER_SEC);
+            if minute != self.session_minute {
+                self.session_minute = minute;
+                self.sweep_sessions(tick.stamp);
+            }
+            if !in_session && filter != SessionFilter::Dispatch {
+                self.metrics.out_of_session_ticks.fetch_add(1, Ordering::Relaxed);
+                debug!(symbol = ?tick.symbol, stamp = tick.stamp, "tick outside trading session");
+                if filter == SessionFilter::Drop {
+                    return;
+                }
+            }
+        }
+        if let Some(roll) = self.rollover.as_mut().and_then(|r| r.on_tick(&tick)) {
+            self.roll(roll, subscriber);
+        }
+        if let Some(tracked) = self.benchmarks.get(&tick.symbol) {
+            for worker_id in distinct_workers(tracked) {
+                let msg = WorkerMsg::Benchmark {
+                    symbol: tick.symbol,
+                    price: tick.last,
+                };
+                if let Err(e) = self.senders[worker_id].send(msg) {
+                    error!(worker_id, error = ?e, "failed to send benchmark price to worker");
+                }
+            }
+        }
+        if let Some(readers) = self.references.get(&tick.symbol) {
+            for worker_id in distinct_workers(readers) {
+                if let Err(e) = self.senders[worker_id].send(WorkerMsg::Reference(tick)) {
+                    error!(worker_id, error = ?e, "failed to send reference tick to worker");
+                }
+            }
+        }
+        if self.paused.contains(&tick.symbol) {
+            return;
+        }
+        let Some(&worker_id) = self.symbol_workers.get(&tick.symbol) else {
+            return;
+        };
+        let sender = &self.senders[worker_id];
+        let worker_metrics = &self.metrics.workers[worker_id];
+        if sender.is_full() {
+            worker_metrics.queue_stalls.fetch_add(1, Ordering::Relaxed);
+        }
+        let dispatched_ns = timeutil::now_nanos();
+        if self.tick_recv_ns > 0 {
+            self.metrics.recv_dispatch.record(dispatched_ns - self.tick_recv_ns);
         }
+        if let Some(ref journal) = self.journal {
+            let entry = Entry::Tick {
+                symbol: tick.symbol,
+                stamp: tick.stamp,
+                volume: tick.volume,
+            };
+            if let Err(e) = journal.lock().unwrap().append(&entry) {
+                error!(error = ?e, "failed to append tick to journal");
+            }
+        }
+        if let Err(e) = sender.send(WorkerMsg::Tick(tick, dispatched_ns)) {
+            error!(worker_id, error = ?e, "failed to send tick to worker");
+        }
+        worker_metrics.queue_depth.store(sender.len() as i64, Ordering::Relaxed);
     }
 
     /// Main loop: recv raw TickData bytes from `tick_subscriber`, deserialize, then hand off to workers.
-    pub fn start(&self) {
-        // We expect `tick_subscriber` to be `Some(_)` unless `stop()` has been called already.
-        let subscriber = self.tick_subscriber.as_ref().expect("Subscriber socket missing in start()");
-
-        let mut tick_buf = [0u8; std::mem::size_of::<TickData>()];
-        loop {
-            // recv_into listen on Ctrl-C, so it no need to add atomic running
-            match subscriber.recv_into(&mut tick_buf, 0) {
-                Ok(n) if n == tick_buf.len() => {
-                    // SAFELY turn bytes into a TickData
-                    let tick: TickData = unsafe {
-                        let ptr = tick_buf.as_ptr() as *const TickData;
-                        std::ptr::read_unaligned(ptr)
-                    };
-                    let worker_id = (tick.symbol.hash_future_symbol() as usize) % self.num_workers;
-                    if let Err(e) = self.senders[worker_id].send(tick) {
-                        eprintln!("Error sending tick to worker {}: {:?}", worker_id, e);
+    pub fn start(&mut self) -> Result<(), EngineError> {
+        if let Some(core) = self.affinity.receive_loop {
+            match affinity::pin_current_thread(core) {
+                Ok(()) => info!(core, "pinned receive loop"),
+                Err(e) => warn!(core, error = ?e, "failed to pin receive loop"),
+            }
+        }
+        // `tick_subscriber` is `Some(_)` unless `stop()` has been called already
+        let mut subscriber = self
+            .tick_subscriber
+            .take()
+            .ok_or(EngineError::State("no SUB socket: start() after stop()"))?;
+        // same for the control, query and report sockets, so handlers can borrow `self` mutably
+        let control_socket = self.control_socket.take();
+        let query_socket = self.query_socket.take();
+        let report_socket = self.report_socket.take();
+        let mut report_buf = [0u8; 256];
+
+        let mut tick_buf = vec![0u8; self.wire_format.max_frame()];
+        let mut quarantine = Quarantine::new(16);
+        // the watchdog, the daily report, the global breaker and fees reload need to wake up even when no tick arrives
+        let timed = self.watchdog.is_some()
+            || self.daily_report.is_some()
+            || self.statement_dir.is_some()
+            || self.global_breaker.is_some()
+            || self.fees_watcher.is_some()
+            || self.heartbeat.is_some();
+        let poll_timeout = if timed { 1000 } else { -1 };
+        let mut last_check = self.clock.now();
+        // the first statement covers this run; the audit log may go back further
+        if self.statement_since == i64::MIN {
+            self.statement_since = last_check;
+        }
+        if (self.daily_report.is_some() || self.statement_dir.is_some())
+            && timeutil::local_secs_of_day(last_check) >= self.daily_report.unwrap_or_default().settle_secs
+        {
+            self.reported_day = timeutil::local_day(last_check);
+        }
+        let feed_count = subscriber.len();
+        let busy_poll = match self.receive_mode {
+            ReceiveMode::BusyPoll(busy) => Some(busy),
+            ReceiveMode::Blocking => None,
+        };
+        let mut poll_backoff = Backoff::new(self.reconnect);
+        // reused on every pass, so waiting for a frame allocates nothing
+        let mut poll_buf = Vec::with_capacity(feed_count + 3);
+        let mut ready = Vec::with_capacity(feed_count + 3);
+        let mut ready_feeds = Vec::with_capacity(feed_count);
+        'recv: loop {
+            if self.shutdown.load(Ordering::Relaxed) {
+                info!("shutdown requested, leaving the receive loop");
+                break;
+            }
+            // Busy-polling, or with several feeds, other sockets or a watchdog, we poll; otherwise block straight in recv_into.
+            ready_feeds.clear();
+            if busy_poll.is_some() || feed_count > 1 || control_socket.is_some() || query_socket.is_some() || report_socket.is_some() || timed {
+                let mut items = recycle(mem::take(&mut poll_buf));
+                items.extend(subscriber.poll_items());
+                let sockets = control_socket.iter().chain(query_socket.iter()).chain(report_socket.iter());
+                items.extend(sockets.map(|s| s.as_poll_item(zmq::POLLIN)));
+                let polled = match busy_poll {
+                    Some(busy) => busy.poll(&mut items),
+                    None => zmq::poll(&mut items, poll_timeout),
+                };
+                match polled {
+                    // poll is interrupted by Ctrl-C just like recv_into
+                    Err(e) if reconnect::is_shutdown(e) => {
+                        warn!(error = ?e, "poll interrupted");
+                        break;
                     }
+                    Err(e) => {
+                        warn!(error = ?e, attempt = poll_backoff.attempts() + 1, "poll error, retrying");
+                        poll_buf = recycle(items);
+                        poll_backoff.wait(&self.shutdown);
+                        continue;
+                    }
+                    Ok(_) => poll_backoff.reset(),
                 }
-                Ok(n) => {
-                    eprintln!("Warning: received {} bytes (expected {}); ignoring", n, tick_buf.len());
+                ready.clear();
+                ready.extend(items.iter().map(|item| item.is_readable()));
+                poll_buf = recycle(items);
+                ready_feeds.extend((0..feed_count).filter(|&feed| ready[feed]));
+                let mut others = ready[feed_count..].iter();
+                if let Some(ref control) = control_socket
+                    && *others.next().unwrap()
+                {
+                    self.serve_control(control, &subscriber);
                 }
-                Err(e) => {
-                    // Likely the socket was dropped in stop(), so break
-                    eprintln!("SUB socket error or closed: {:?}", e);
-                    break;
+                if let Some(ref socket) = query_socket
+                    && *others.next().unwrap()
+                {
+                    self.serve_query(socket);
+                }
+                if let Some(ref reports) = report_socket
+                    && *others.next().unwrap()
+                {
+                    match reports.recv_into(&mut report_buf, 0) {
+                        Ok(n) => match Report::decode(&report_buf[..n.min(report_buf.len())]) {
+                            Some(Report::Fill(report)) => {
+                                let _ = self.on_event(Event::Report(report), &subscriber);
+                            }
+                            Some(Report::Status(report)) => {
+                                let _ = self.on_event(Event::Status(report), &subscriber);
+                            }
+                            None => warn!(size = n, "dropped malformed execution report"),
+                        },
+                        Err(e) => error!(error = ?e, "execution report socket error"),
+                    }
+                }
+
+                let now = self.clock.now();
+                if now - last_check >= 1000 {
+                    last_check = now;
+                    let alerts = self.watchdog.as_mut().map(|w| w.check(now)).unwrap_or_default();
+                    for alert in alerts {
+                        self.raise_data_alert(alert);
+                    }
+                    self.check_settlement(now);
+                    self.check_global_breaker(now);
+                    self.check_fees_reload();
+                    self.drop_rolled_off(&subscriber);
+                }
+                self.publish_heartbeat();
+            } else {
+                ready_feeds.push(0);
+            }
+
+            for &feed in &ready_feeds {
+                // recv_into listen on Ctrl-C, so it no need to add atomic running
+                let n = match subscriber.socket(feed).recv_into(&mut tick_buf, 0) {
+                    Ok(n) => {
+                        self.tick_recv_ns = timeutil::now_nanos();
+                        n
+                    }
+                    Err(e) if reconnect::is_shutdown(e) => {
+                        // Ctrl-C, or the context is going away
+                        warn!(error = ?e, feed = subscriber.uri(feed), "SUB socket interrupted or closed");
+                        break 'recv;
+                    }
+                    Err(e) => {
+                        warn!(error = ?e, feed = subscriber.uri(feed), "SUB socket error, reconnecting");
+                        let what = format!("tick feed {}", subscriber.uri(feed));
+                        let mut backoff = Backoff::new(self.reconnect);
+                        if backoff.retry(&what, &self.shutdown, || subscriber.reconnect(feed)).is_none() {
+                            break 'recv;
+                        }
+                        self.metrics.feed_reconnects.fetch_add(1, Ordering::Relaxed);
+                        // the other ready feeds are read on the next round
+                        break;
+                    }
+                };
+                if let Some(failover) = self.feed_selector.on_frame(feed, self.clock.now()) {
+                    warn!(
+                        from = subscriber.uri(failover.from),
+                        to = subscriber.uri(failover.to),
+                        silent_ms = failover.silent_ms,
+                        "switched tick feed"
+                    );
+                    self.metrics.feed_failovers.fetch_add(1, Ordering::Relaxed);
+                }
+                // raw frames of any known (older or newer) layout become the current TickData
+                let frame = &tick_buf[..n.min(tick_buf.len())];
+                let Some((tick, meta)) = self.wire_format.decode_frame(frame) else {
+                    quarantine.admit(frame, n);
+                    self.metrics.frames_quarantined.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                };
+                // standby feeds and ticks the active feed repeats are dropped here
+                if self.feed_selector.accept(feed, &tick) {
+                    if let Some(meta) = meta {
+                        self.on_tick_meta(&tick, meta);
+                    }
+                    let _ = self.on_event(Event::Tick(tick), &subscriber);
                 }
             }
         }
+
+        self.tick_subscriber = Some(subscriber);
+        self.control_socket = control_socket;
+        self.query_socket = query_socket;
+        self.report_socket = report_socket;
+
+        if quarantine.total() > 0 {
+            warn!(total = quarantine.total(), sizes = ?quarantine.counts(), "quarantined frames of unknown size");
+        }
+        Ok(())
     }
 
-    /// Gracefully stop: drop the SUB socket (unblocks recv), clear senders (unblocks worker rx loops), then join threads.
-    pub fn stop(&mut self) {
-        println!("stoping engine...");
+    /// Gracefully stop in two phases. First drop the SUB socket (unblocks recv) and clear
+    /// senders (unblocks worker rx loops), so every queued tick and fill is processed before
+    /// the workers exit and hand back their state. Then, with nothing left trading, write
+    /// the handoff file if one is enabled. A thread that panicked is reported once the others
+    /// are joined; a panicked worker's strategies are missing from the handoff.
+    pub fn stop(&mut self) -> Result<(), EngineError> {
+        info!("stopping engine");
         // 1) close subscriber
         if let Some(sub) = self.tick_subscriber.take() {
             drop(sub);
@@ -164,10 +3856,374 @@ impl CtaEngine {
         self.senders.clear();
 
         // 3) Join all worker threads
+        let mut handoff = Handoff::new(self.clock.now());
+        let mut panicked = None;
         for handle in self.handles.drain(..) {
-            handle.join().expect("Worker thread panicked");
+            let Some((states, last_ticks)) = join(handle, &mut panicked) else {
+                continue;
+            };
+            handoff.strategies.extend(states);
+            handoff
+                .last_ticks
+                .extend(last_ticks.into_iter().map(|(symbol, last)| (symbol.as_str().to_string(), last)));
+        }
+
+        // 4) Let the recorder drain its queue and flush files
+        self.recorder_sender.take();
+        if let Some(handle) = self.recorder_handle.take() {
+            join(handle, &mut panicked);
+        }
+        self.order_store_sender.take();
+        if let Some(handle) = self.order_store_handle.take() {
+            join(handle, &mut panicked);
+        }
+        // writes what the workers queued
+        self.audit_log.take();
+        self.event_sender.take();
+        if let Some(handle) = self.event_log_handle.take() {
+            join(handle, &mut panicked);
+        }
+        if let Some(ref store) = self.seq_store {
+            store.lock().unwrap().close();
+        }
+
+        info!("all worker threads have exited");
+
+        // 5) Hand off to the next run
+        if let Some(ref journal) = self.journal
+            && let Err(e) = journal.lock().unwrap().sync()
+        {
+            error!(error = ?e, "failed to sync journal");
+        }
+        if let Some(ref path) = self.handoff_path {
+            handoff.strategies.sort_by(|a, b| (&a.symbol, &a.strategy).cmp(&(&b.symbol, &b.strategy)));
+            match handoff.save(path) {
+                Ok(()) => info!(path = %path.display(), strategies = handoff.strategies.len(), "wrote handoff file"),
+                Err(e) => error!(path = %path.display(), error = ?e, "failed to write handoff file"),
+            }
+        }
+        panicked.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::{STATUS_CANCELLED, STATUS_REJECTED};
+    use crate::strategies::Aberration;
+
+    fn tracker() -> PerformanceTracker {
+        PerformanceTracker::new(1e6, ContractInfo::default())
+    }
+
+    /// An initialized one-worker engine trading `Aberration10` on rb2505, and its SUB sockets
+    /// to feed ticks through `on_event` with.
+    fn running_engine(name: &str, configure: impl FnOnce(&mut CtaEngine)) -> (CtaEngine, TickFeeds) {
+        let ticks = format!("inproc://{}-ticks", name);
+        let orders = format!("inproc://{}-orders", name);
+        let mut engine = CtaEngine::new(&[ticks.as_str()], &orders, 1).unwrap();
+        configure(&mut engine);
+        engine.add_strategy(SymbolType::from("rb2505"), Box::new(Aberration::new(10)), tracker());
+        engine.init().unwrap();
+        let feeds = engine.tick_subscriber.take().unwrap();
+        (engine, feeds)
+    }
+
+    fn tick(stamp: i64, last: f64) -> TickData {
+        TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp,
+            last,
+            ..Default::default()
+        }
+    }
+
+    /// The last price the strategy's worker has seen, once it has handled what was sent before.
+    fn worker_last_price(engine: &CtaEngine) -> f64 {
+        engine.snapshot(SnapshotFilter::default()).unwrap()[0].last_price
+    }
+
+    /// What a strategy heard back about its orders.
+    #[derive(Default)]
+    struct Heard {
+        rejected: Vec<String>,
+        fills: u32,
+        /// lots of each order cancelled
+        cancelled: Vec<u32>,
+    }
+
+    struct Listener(Arc<Mutex<Heard>>);
+
+    impl Strategy for Listener {
+        fn name(&self) -> NameType {
+            NameType::from("listener")
+        }
+
+        fn on_order_rejected(&mut self, _order: &Order, reason: &str) {
+            self.0.lock().unwrap().rejected.push(reason.into());
+        }
+
+        fn on_fill(&mut self, _fill: &Order, _remaining: u32) {
+            self.0.lock().unwrap().fills += 1;
         }
 
-        println!("All worker threads have exited.");
+        fn on_order_cancelled(&mut self, order: &Order) {
+            self.0.lock().unwrap().cancelled.push(order.lots);
+        }
+    }
+
+    fn strat_perf(stg: Box<dyn Strategy>) -> StratPerf {
+        StratPerf {
+            stg,
+            strategy_id: 1,
+            perf: tracker(),
+            conduct: None,
+            order_stats: OrderStatsTracker::new(None),
+            stops: StopManager::new(),
+            pending: PendingOrders::new(),
+            partial_fills: PartialFillPolicy::default(),
+            positions: PositionManager::new(),
+            tracking: None,
+            budget: None,
+            price_offset: PriceOffset::default(),
+            references: Vec::new(),
+            rejected: 0,
+            breaker: None,
+            limits: None,
+            halted: false,
+            paused: false,
+            account: None,
+            trading_day: i64::MIN,
+            day_open: false,
+            rolling_to: None,
+            reopen: None,
+            flatten_due: false,
+            price_limits: PriceLimits::default(),
+            limit_lock: None,
+        }
+    }
+
+    /// The listener's marketable order to open one lot long.
+    fn buy(tick: &TickData) -> Order {
+        Order {
+            stg_name: NameType::from("listener"),
+            symbol: tick.symbol,
+            timestamp: tick.stamp,
+            price: tick.ap1,
+            lots: 1,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        }
+    }
+
+    /// A sink booking on send through `pusher`, with nothing paused or blocked.
+    fn sink(pusher: OrderPusher) -> OrderSink {
+        OrderSink {
+            pusher: RefCell::new(pusher),
+            paused: Arc::new(PausedSymbols::default()).view(),
+            blocked_symbols: Arc::default(),
+            halted: Arc::default(),
+            replaying: Arc::default(),
+            ids: Arc::new(IdGenerator::new(0)),
+            metrics: Arc::new(EngineMetrics::new(1)),
+            audit_log: None,
+            store: None,
+            journal: None,
+            book_on_send: true,
+            queue_config: None,
+            queues: HashMap::new(),
+            worker_id: 0,
+            tick_started_ns: 0,
+        }
+    }
+
+    #[test]
+    fn an_order_that_cannot_be_sent_is_rejected_and_not_booked() {
+        let heard = Arc::new(Mutex::new(Heard::default()));
+        let mut strat_perf = strat_perf(Box::new(Listener(Arc::clone(&heard))));
+        let sink = sink(OrderPusher::down(&zmq::Context::new()));
+        let tick = TickData {
+            ap1: 3501.0,
+            bp1: 3500.0,
+            ..tick(1_000, 3500.0)
+        };
+        assert!(!sink.send_order(&mut strat_perf, buy(&tick), &tick));
+        assert_eq!(heard.lock().unwrap().rejected, ["send failed"]);
+        assert_eq!(heard.lock().unwrap().fills, 0);
+        assert_eq!(held_lots(&strat_perf), (0, 0));
+        assert!(strat_perf.pending.is_empty());
+        assert_eq!(strat_perf.order_stats.stats().orders, 0);
+        assert_eq!(sink.metrics.orders_sent.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn orders_the_gateway_ends_unfilled_stop_pending() {
+        let heard = Arc::new(Mutex::new(Heard::default()));
+        let mut strat_perf = strat_perf(Box::new(Listener(Arc::clone(&heard))));
+        let tick = tick(1_000, 3500.0);
+        let order = |client_id, order_id| Order {
+            lots: 3,
+            client_id,
+            order_id,
+            ..buy(&tick)
+        };
+        strat_perf.pending.insert(order(1, 11));
+        strat_perf.pending.insert(order(2, 12));
+        strat_perf.pending.insert(order(3, 13));
+        strat_perf.pending.resend_on_cancel(3);
+        let ended = |order_id, status| StatusReport {
+            seq: 1,
+            order: Order {
+                lots: 3,
+                ..order(0, order_id)
+            },
+            status,
+        };
+
+        assert_eq!(
+            end_order(&mut strat_perf, &ended(11, STATUS_REJECTED)).map(|(o, resend)| (o.client_id, resend)),
+            Some((1, false))
+        );
+        assert_eq!(
+            end_order(&mut strat_perf, &ended(12, STATUS_CANCELLED)).map(|(o, resend)| (o.client_id, resend)),
+            Some((2, false))
+        );
+        // the rest of a partial fill waiting on its cancel is sent again, not reported cancelled
+        assert_eq!(
+            end_order(&mut strat_perf, &ended(13, STATUS_CANCELLED)).map(|(o, resend)| (o.client_id, resend)),
+            Some((3, true))
+        );
+        // reported again, or already withdrawn by the engine
+        assert!(end_order(&mut strat_perf, &ended(12, STATUS_CANCELLED)).is_none());
+        assert!(strat_perf.pending.is_empty());
+        let heard = heard.lock().unwrap();
+        assert_eq!(
+            (heard.rejected.as_slice(), heard.cancelled.as_slice()),
+            (&["rejected by the exchange".to_string()][..], &[3][..])
+        );
+    }
+
+    #[test]
+    fn a_replaying_sink_books_without_sending() {
+        let heard = Arc::new(Mutex::new(Heard::default()));
+        let mut strat_perf = strat_perf(Box::new(Listener(Arc::clone(&heard))));
+        let sink = sink(OrderPusher::down(&zmq::Context::new()));
+        sink.replaying.store(true, Ordering::Relaxed);
+        let tick = TickData {
+            ap1: 3501.0,
+            bp1: 3500.0,
+            ..tick(1_000, 3500.0)
+        };
+        // the pusher is down, so only an order that is not sent gets through
+        assert!(sink.send_order(&mut strat_perf, buy(&tick), &tick));
+        assert!(heard.lock().unwrap().rejected.is_empty());
+        assert_eq!(heard.lock().unwrap().fills, 1);
+        assert_eq!(held_lots(&strat_perf), (1, 0));
+    }
+
+    #[test]
+    fn hot_removal_leaves_the_worker_and_unsubscribes_emptied_symbols() {
+        let mut engine = CtaEngine::new(&["inproc://hot-remove-ticks"], "inproc://hot-remove-orders", 1).unwrap();
+        let (rb, ma) = (SymbolType::from("rb2505"), SymbolType::from("MA505"));
+        engine.add_strategy(rb, Box::new(Aberration::new(10)), tracker());
+        engine.add_strategy(rb, Box::new(Aberration::new(20)), tracker());
+        engine.init().unwrap();
+        engine.add_strategy(ma, Box::new(Aberration::new(10)), tracker());
+        let names = |engine: &CtaEngine| {
+            let mut names: Vec<_> = engine
+                .snapshot(SnapshotFilter::default())
+                .unwrap()
+                .into_iter()
+                .map(|s| (s.strategy, s.symbol))
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&engine).len(), 3);
+
+        engine.remove_strategy("Aberration10").unwrap();
+        // the worker handles the removal before the snapshot that follows it
+        assert_eq!(names(&engine), [("Aberration20".to_string(), "rb2505".to_string())]);
+        let feeds = engine.tick_subscriber.as_ref().unwrap();
+        assert!(feeds.is_subscribed(&rb.0) && !feeds.is_subscribed(&ma.0));
+        assert!(!engine.symbol_workers.contains_key(&ma));
+
+        engine.remove_strategy("Aberration20").unwrap();
+        assert!(names(&engine).is_empty());
+        assert!(!engine.tick_subscriber.as_ref().unwrap().is_subscribed(&rb.0));
+        assert!(engine.remove_strategy("Aberration20").is_err());
+        engine.stop().unwrap();
+    }
+
+    #[test]
+    fn drops_stale_ticks_when_configured() {
+        let (mut engine, feeds) = running_engine("drop-stale", |engine| {
+            engine.enable_watchdog(WatchdogConfig {
+                drop_stale: true,
+                ..Default::default()
+            })
+        });
+        let now = timeutil::now_stamp();
+        engine.on_event(Event::Tick(tick(now, 3500.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3500.0);
+        // a minute behind the wall clock
+        engine.on_event(Event::Tick(tick(now - 60_000, 3400.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3500.0);
+        engine.on_event(Event::Tick(tick(now + 500, 3501.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3501.0);
+        engine.tick_subscriber = Some(feeds);
+        engine.stop().unwrap();
+
+        let (mut engine, feeds) = running_engine("keep-stale", |engine| engine.enable_watchdog(WatchdogConfig::default()));
+        engine.on_event(Event::Tick(tick(now - 60_000, 3400.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3400.0);
+        engine.tick_subscriber = Some(feeds);
+        engine.stop().unwrap();
+    }
+
+    #[test]
+    fn benchmark_and_reference_ticks_go_to_each_worker_once() {
+        let readers = [("a".to_string(), 1), ("b".to_string(), 0), ("c".to_string(), 1), ("d".to_string(), 2)];
+        assert_eq!(distinct_workers(&readers).collect::<Vec<_>>(), [1, 0, 2]);
+        assert_eq!(distinct_workers(&[]).count(), 0);
+    }
+
+    #[test]
+    fn contracts_are_unsubscribed_once_every_strategy_rolled_off() {
+        let (mut engine, feeds) = running_engine("roll-off", |_| {});
+        let (rb2505, rb2510) = (SymbolType::from("rb2505"), SymbolType::from("rb2510"));
+        engine.roll(Roll { from: rb2505, to: rb2510 }, &feeds);
+        engine.drop_rolled_off(&feeds);
+        // still flattening on its quotes
+        assert!(feeds.is_subscribed(&rb2505.0) && feeds.is_subscribed(&rb2510.0));
+        engine.on_event(Event::Tick(tick(1_000, 3500.0)), &feeds).unwrap();
+        // the worker moves the flat strategy over before the snapshot that follows
+        assert_eq!(engine.snapshot(SnapshotFilter::default()).unwrap()[0].symbol, "rb2510");
+        engine.drop_rolled_off(&feeds);
+        assert!(!feeds.is_subscribed(&rb2505.0) && feeds.is_subscribed(&rb2510.0));
+        assert!(!engine.symbol_workers.contains_key(&rb2505) && engine.symbol_workers.contains_key(&rb2510));
+        engine.tick_subscriber = Some(feeds);
+        engine.stop().unwrap();
+    }
+
+    #[test]
+    fn paused_symbols_ticks_are_not_dispatched_until_resumed() {
+        let (mut engine, feeds) = running_engine("pause", |_| {});
+        let rb = SymbolType::from("rb2505");
+        engine.on_event(Event::Tick(tick(1_000, 3500.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3500.0);
+        engine.pause_symbol(rb).unwrap();
+        assert!(engine.pause_symbol(SymbolType::from("MA505")).is_err());
+        engine.on_event(Event::Tick(tick(2_000, 3400.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3500.0);
+        engine.resume_symbol(rb).unwrap();
+        assert!(engine.resume_symbol(rb).is_err());
+        engine.on_event(Event::Tick(tick(3_000, 3501.0)), &feeds).unwrap();
+        assert_eq!(worker_last_price(&engine), 3501.0);
+        engine.tick_subscriber = Some(feeds);
+        engine.stop().unwrap();
     }
 }
@@ -0,0 +1,195 @@
+//! A strategy's equity curve. By default every sample is kept, one per tick, which is what a
+//! backtest wants; a live engine running for weeks resamples instead, keeping the last
+//! equity of each `interval_ms` bucket, holds only the most recent `points` of them in a ring
+//! and optionally appends every finished point to a CSV file (`stamp,equity`) for the full
+//! history. Each finished point also goes into the curve's running `PerfStats`.
+use crate::perf_stats::{PerfStats, RunningStats};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use tracing::warn;
+
+/// How a live strategy's equity curve is sampled and kept, see `CtaEngine::enable_equity_curve`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CurveConfig {
+    /// one point per this many ms, the last equity within it; 0 for one per tick
+    pub interval_ms: i64,
+    /// most points held in memory, oldest dropped first; 0 for all
+    pub points: usize,
+    /// directory each strategy's finished points are appended to, as `<strategy>.<symbol>.csv`
+    pub dir: Option<String>,
+}
+
+pub struct EquityCurve {
+    interval_ms: i64,
+    capacity: usize,
+    points: VecDeque<f64>,
+    /// bucket (or stamp, sampling every tick) of the last point; `None` for a mark
+    last_stamp: Option<i64>,
+    /// points ever appended, including those dropped from the ring
+    samples: u64,
+    file: Option<BufWriter<File>>,
+    /// over the finished points
+    stats: RunningStats,
+}
+
+impl EquityCurve {
+    /// A curve starting at `equity` that keeps every sample.
+    pub fn new(equity: f64) -> Self {
+        Self {
+            interval_ms: 0,
+            capacity: 0,
+            points: VecDeque::from([equity]),
+            last_stamp: None,
+            samples: 1,
+            file: None,
+            stats: RunningStats::default(),
+        }
+    }
+
+    /// A curve starting at `equity`, sampled and bounded as `config` says; `config.dir` is
+    /// left to `with_file`.
+    pub fn resampled(equity: f64, config: &CurveConfig) -> Self {
+        let mut curve = Self::new(equity);
+        curve.interval_ms = config.interval_ms.max(0);
+        curve.capacity = config.points;
+        curve
+    }
+
+    /// Also append each finished point to `path`, after what it already holds.
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.file = Some(BufWriter::new(file));
+        Ok(self)
+    }
+
+    /// Record `equity` as of `stamp`; within the last point's bucket it replaces that point.
+    pub fn push(&mut self, stamp: i64, equity: f64) {
+        let bucket = if self.interval_ms > 0 {
+            stamp - stamp.rem_euclid(self.interval_ms)
+        } else {
+            stamp
+        };
+        if self.interval_ms > 0
+            && self.last_stamp == Some(bucket)
+            && let Some(last) = self.points.back_mut()
+        {
+            *last = equity;
+            return;
+        }
+        self.finish_last();
+        self.append(equity);
+        self.last_stamp = Some(bucket);
+    }
+
+    /// Record `equity` as a point of its own, outside any bucket, e.g. after restoring a
+    /// handed-off position.
+    pub fn mark(&mut self, equity: f64) {
+        self.finish_last();
+        self.append(equity);
+        self.last_stamp = None;
+    }
+
+    fn append(&mut self, equity: f64) {
+        self.points.push_back(equity);
+        self.samples += 1;
+        if self.capacity > 0 && self.points.len() > self.capacity {
+            self.points.pop_front();
+        }
+    }
+
+    /// Book the last point into the stats and the file now that no later sample can replace it.
+    fn finish_last(&mut self) {
+        let (Some(stamp), Some(&equity)) = (self.last_stamp, self.points.back()) else {
+            return;
+        };
+        self.stats.update(stamp, equity);
+        if let Some(ref mut file) = self.file
+            && let Err(e) = writeln!(file, "{},{}", stamp, equity)
+        {
+            warn!(error = ?e, "failed to append to the equity curve file, no longer writing it");
+            self.file = None;
+        }
+    }
+
+    /// The latest equity.
+    pub fn last(&self) -> f64 {
+        *self.points.back().expect("a curve starts with a point")
+    }
+
+    /// Points held in memory.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Points ever recorded, counting those dropped from memory; the difference between two
+    /// readings is how many points were added in between.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// The last `n` points held, oldest first.
+    pub fn tail(&self, n: usize) -> Vec<f64> {
+        self.points.iter().skip(self.points.len().saturating_sub(n)).copied().collect()
+    }
+
+    /// Ratios and drawdowns over every point so far, the one still being sampled included.
+    pub fn stats(&self) -> PerfStats {
+        let mut running = self.stats;
+        if let (Some(stamp), Some(&equity)) = (self.last_stamp, self.points.back()) {
+            running.update(stamp, equity);
+        }
+        running.stats()
+    }
+
+    /// Every point held, oldest first.
+    pub fn to_vec(&self) -> Vec<f64> {
+        self.points.iter().copied().collect()
+    }
+}
+
+impl Drop for EquityCurve {
+    fn drop(&mut self) {
+        self.finish_last();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resamples_into_a_bounded_ring_and_appends_finished_points() {
+        let path = std::env::temp_dir().join(format!("curve-{}.csv", std::process::id()));
+        let config = CurveConfig {
+            interval_ms: 1000,
+            points: 3,
+            dir: None,
+        };
+        {
+            let mut curve = EquityCurve::resampled(100.0, &config).with_file(&path).unwrap();
+            for (stamp, equity) in [(1000, 101.0), (1500, 102.0), (2100, 99.0), (3000, 98.0), (3999, 97.0), (4000, 103.0)] {
+                curve.push(stamp, equity);
+            }
+            // the 100 start and the 1000 bucket fell out of the ring
+            assert_eq!(curve.to_vec(), [99.0, 97.0, 103.0]);
+            assert_eq!((curve.samples(), curve.last()), (5, 103.0));
+            assert_eq!(curve.tail(2), [97.0, 103.0]);
+        }
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, "1000,102\n2000,99\n3000,97\n4000,103\n");
+
+        let mut every = EquityCurve::new(100.0);
+        every.push(1000, 101.0);
+        every.push(1000, 102.0);
+        assert_eq!(every.to_vec(), [100.0, 101.0, 102.0]);
+    }
+}
@@ -0,0 +1,80 @@
+//! What `CtaEngine`'s setup and lifecycle calls return instead of panicking: a socket that
+//! cannot be bound, a file that cannot be opened, a thread that cannot be spawned or that
+//! died. The program embedding the engine decides which of these end the process.
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EngineError {
+    /// a ZMQ socket could not be created, set up, bound or connected
+    #[error("failed to {what}: {source}")]
+    Socket {
+        what: String,
+        #[source]
+        source: zmq::Error,
+    },
+    /// a file the engine keeps could not be opened
+    #[error("failed to open {what} {}: {source}", path.display())]
+    File {
+        what: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to open order store {}: {source}", path.display())]
+    Store {
+        path: PathBuf,
+        #[source]
+        source: rusqlite::Error,
+    },
+    #[error("failed to spawn {name} thread: {source}")]
+    Spawn {
+        name: String,
+        #[source]
+        source: io::Error,
+    },
+    /// a thread of the engine panicked; what it held is lost
+    #[error("{0} thread panicked")]
+    Panicked(String),
+    /// a call out of order, e.g. `start()` after `stop()`
+    #[error("{0}")]
+    State(&'static str),
+}
+
+impl EngineError {
+    /// For `map_err` on a ZMQ call: `socket.bind(uri).map_err(EngineError::socket("bind ..."))?`.
+    pub(crate) fn socket(what: impl Into<String>) -> impl FnOnce(zmq::Error) -> Self {
+        let what = what.into();
+        move |source| EngineError::Socket { what, source }
+    }
+
+    pub(crate) fn file(what: &'static str, path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |source| EngineError::File { what, path, source }
+    }
+
+    pub(crate) fn spawn(name: impl Into<String>) -> impl FnOnce(io::Error) -> Self {
+        let name = name.into();
+        move |source| EngineError::Spawn { name, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn names_what_failed_and_keeps_the_cause() {
+        let e = EngineError::socket("bind REP socket to tcp://*:5555")(zmq::Error::EINVAL);
+        assert_eq!(
+            e.to_string(),
+            format!("failed to bind REP socket to tcp://*:5555: {}", zmq::Error::EINVAL)
+        );
+        assert!(e.source().is_some());
+        let e = EngineError::file("audit log", "/no/such/audit.log")(io::ErrorKind::NotFound.into());
+        assert!(e.to_string().starts_with("failed to open audit log /no/such/audit.log: "));
+        assert_eq!(EngineError::Panicked("worker-2".into()).to_string(), "worker-2 thread panicked");
+    }
+}
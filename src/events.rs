@@ -0,0 +1,208 @@
+//! Append-only log of the engine's inputs. Every tick the receive loop dispatches, every
+//! execution report and every control command becomes an `Event` that is appended here and
+//! then applied; the strategies' state is the fold of the workers over the events in log
+//! order, so feeding a log back through `CtaEngine::replay_events` rebuilds it.
+//!
+//! Each record is a kind byte, the sequence number, the engine clock's stamp when the event
+//! was applied and the payload length, followed by the payload: the `TickData`,
+//! `ExecutionReport` or `StatusReport` bytes, or the command line as UTF-8. A record cut short by a crash ends
+//! the log.
+use crate::codec;
+use crate::execution::{ExecutionReport, StatusReport};
+use crate::types::TickData;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::Path;
+
+const KIND_TICK: u8 = 1;
+const KIND_REPORT: u8 = 2;
+const KIND_COMMAND: u8 = 3;
+const KIND_STATUS: u8 = 4;
+/// kind, seq, stamp, payload length
+const RECORD_HEADER: usize = 1 + 8 + 8 + 4;
+
+/// One input to the engine.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Tick(TickData),
+    Report(ExecutionReport),
+    /// an order, or its rest, ended unfilled
+    Status(StatusReport),
+    /// a control command line, as `control::Command::parse` reads it
+    Command(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggedEvent {
+    pub seq: u64,
+    pub stamp: i64,
+    pub event: Event,
+}
+
+fn pod_bytes<T: Copy>(value: &T) -> &[u8] {
+    // SAFETY: only used on the repr(C) wire structs
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+pub struct EventLog {
+    writer: BufWriter<File>,
+    seq: u64,
+}
+
+impl EventLog {
+    /// Open `path` for appending, numbering on from the events already in it. A truncated
+    /// record at the end is cut off first.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let (seq, valid) = match EventReader::open(path) {
+            Ok(mut reader) => {
+                let mut seq = 0;
+                while let Some(logged) = reader.read()? {
+                    seq = logged.seq;
+                }
+                (seq, reader.valid)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (0, 0),
+            Err(e) => return Err(e),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() > valid {
+            file.set_len(valid)?;
+        }
+        Ok(Self {
+            writer: BufWriter::new(file),
+            seq,
+        })
+    }
+
+    /// Append `event`, applied at `stamp`, and return its sequence number.
+    pub fn append(&mut self, stamp: i64, event: &Event) -> io::Result<u64> {
+        let (kind, payload) = match event {
+            Event::Tick(tick) => (KIND_TICK, pod_bytes(tick)),
+            Event::Report(report) => (KIND_REPORT, pod_bytes(report)),
+            Event::Status(report) => (KIND_STATUS, pod_bytes(report)),
+            Event::Command(line) => (KIND_COMMAND, line.as_bytes()),
+        };
+        self.seq += 1;
+        let mut header = [0u8; RECORD_HEADER];
+        header[0] = kind;
+        header[1..9].copy_from_slice(&self.seq.to_le_bytes());
+        header[9..17].copy_from_slice(&stamp.to_le_bytes());
+        header[17..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(payload)?;
+        Ok(self.seq)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads a log back in order.
+pub struct EventReader {
+    reader: BufReader<File>,
+    /// bytes of complete records read so far
+    valid: u64,
+}
+
+impl EventReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+            valid: 0,
+        })
+    }
+
+    /// The next record; `None` at the end of the log or at a truncated record.
+    fn read(&mut self) -> io::Result<Option<LoggedEvent>> {
+        let mut header = [0u8; RECORD_HEADER];
+        let mut payload = Vec::new();
+        let complete = read_full(&mut self.reader, &mut header)? && {
+            payload.resize(u32::from_le_bytes(header[17..].try_into().unwrap()) as usize, 0);
+            read_full(&mut self.reader, &mut payload)?
+        };
+        if !complete {
+            return Ok(None);
+        }
+        self.valid += (RECORD_HEADER + payload.len()) as u64;
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("malformed {} event", what));
+        let event = match header[0] {
+            KIND_TICK => Event::Tick(codec::decode_tick(&payload).ok_or_else(|| invalid("tick"))?),
+            KIND_REPORT => Event::Report(ExecutionReport::decode(&payload).ok_or_else(|| invalid("report"))?),
+            KIND_STATUS => Event::Status(StatusReport::decode(&payload).ok_or_else(|| invalid("status"))?),
+            KIND_COMMAND => Event::Command(String::from_utf8(payload).map_err(|_| invalid("command"))?),
+            kind => return Err(invalid(&format!("kind {}", kind))),
+        };
+        Ok(Some(LoggedEvent {
+            seq: u64::from_le_bytes(header[1..9].try_into().unwrap()),
+            stamp: i64::from_le_bytes(header[9..17].try_into().unwrap()),
+            event,
+        }))
+    }
+}
+
+/// Fill `buf`; `false` if the input ended first.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+impl Iterator for EventReader {
+    type Item = io::Result<LoggedEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType};
+
+    #[test]
+    fn appends_and_reads_back_in_order() {
+        let path = std::env::temp_dir().join(format!("events-{}.log", std::process::id()));
+        let tick = testing::ticks("rb2505", &[3500.0], 1000, 0, 1.0)[0];
+        let report = ExecutionReport {
+            seq: 7,
+            fill: Order {
+                stg_name: NameType::from("s"),
+                symbol: tick.symbol,
+                timestamp: 1001,
+                price: 3500.0,
+                lots: 1,
+                direction: DirectionType::BUY,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::LIMIT,
+                client_id: 1,
+                order_id: 0,
+            },
+        };
+        {
+            let mut log = EventLog::open(&path).unwrap();
+            assert_eq!(log.append(1000, &Event::Tick(tick)).unwrap(), 1);
+            assert_eq!(log.append(1001, &Event::Report(report)).unwrap(), 2);
+            log.flush().unwrap();
+        }
+        // a torn tail is cut off on reopening, which numbers on
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&[KIND_TICK, 4]).unwrap();
+        let mut log = EventLog::open(&path).unwrap();
+        assert_eq!(log.append(1002, &Event::Command("pause rb2505".into())).unwrap(), 3);
+        log.flush().unwrap();
+
+        let events: Vec<LoggedEvent> = EventReader::open(&path).unwrap().collect::<io::Result<_>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(matches!(events[0].event, Event::Tick(t) if t.last == 3500.0 && t.symbol == tick.symbol));
+        assert!(matches!(events[1].event, Event::Report(r) if r.seq == 7));
+        assert!(matches!(&events[2].event, Event::Command(line) if line == "pause rb2505"));
+        assert_eq!(events[2].stamp, 1002);
+    }
+}
@@ -0,0 +1,234 @@
+//! Execution reports from the order gateway, applied to trackers exactly once.
+//!
+//! The gateway numbers its reports with a sequence that only grows. The engine persists the
+//! last sequence applied per strategy, so reports replayed after a restart are skipped
+//! instead of being booked twice. Orders the gateway refuses or takes off the book unfilled
+//! come back as `StatusReport`s in the same sequence.
+use crate::types::Order;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use tracing::error;
+
+/// A fill as reported by the gateway: the executed order (price and lots as filled) plus
+/// its sequence number. Matches the gateway's C struct byte for byte.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ExecutionReport {
+    pub seq: u64,
+    pub fill: Order,
+}
+
+impl ExecutionReport {
+    pub fn decode(frame: &[u8]) -> Option<Self> {
+        if frame.len() != mem::size_of::<Self>() {
+            return None;
+        }
+        // SAFETY: the length matches and every field is plain-old-data
+        Some(unsafe { std::ptr::read_unaligned(frame.as_ptr() as *const Self) })
+    }
+}
+
+/// `StatusReport::status` of an order the gateway or exchange refused.
+pub const STATUS_REJECTED: u8 = 1;
+/// `StatusReport::status` of an order, or its rest, taken off the book unfilled: cancelled on
+/// request, or what an FAK or FOK order could not fill at once.
+pub const STATUS_CANCELLED: u8 = 2;
+
+/// The gateway ending an order, or what is left of it, without a fill; `order.lots` is what
+/// was taken off the book. Sent on the report socket alongside fills, numbered in the same
+/// sequence, and told apart from an `ExecutionReport` by frame size.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct StatusReport {
+    pub seq: u64,
+    pub order: Order,
+    /// `STATUS_REJECTED` or `STATUS_CANCELLED`
+    pub status: u8,
+}
+
+const _: () = assert!(mem::size_of::<StatusReport>() != mem::size_of::<ExecutionReport>());
+
+impl StatusReport {
+    pub fn decode(frame: &[u8]) -> Option<Self> {
+        if frame.len() != mem::size_of::<Self>() {
+            return None;
+        }
+        // SAFETY: the length matches and every field is plain-old-data
+        let report = unsafe { std::ptr::read_unaligned(frame.as_ptr() as *const Self) };
+        matches!(report.status, STATUS_REJECTED | STATUS_CANCELLED).then_some(report)
+    }
+
+    pub fn is_rejection(&self) -> bool {
+        self.status == STATUS_REJECTED
+    }
+}
+
+/// A frame on the report socket.
+#[derive(Debug, Copy, Clone)]
+pub enum Report {
+    Fill(ExecutionReport),
+    Status(StatusReport),
+}
+
+impl Report {
+    pub fn decode(frame: &[u8]) -> Option<Self> {
+        ExecutionReport::decode(frame)
+            .map(Report::Fill)
+            .or_else(|| StatusReport::decode(frame).map(Report::Status))
+    }
+
+    pub fn seq(&self) -> u64 {
+        match self {
+            Report::Fill(report) => report.seq,
+            Report::Status(report) => report.seq,
+        }
+    }
+
+    /// The frame as sent.
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: both are repr(C) plain-old-data
+        unsafe {
+            match self {
+                Report::Fill(report) => std::slice::from_raw_parts(report as *const ExecutionReport as *const u8, mem::size_of::<ExecutionReport>()),
+                Report::Status(report) => std::slice::from_raw_parts(report as *const StatusReport as *const u8, mem::size_of::<StatusReport>()),
+            }
+        }
+    }
+}
+
+/// Last applied sequence per strategy. Commits take effect in memory at once and are appended
+/// to the file as `<strategy> <seq>` lines by a writer thread, which syncs once per batch of
+/// commits it finds queued, so a worker booking a fill never waits on the disk. A crash loses
+/// at most the batch being written, whose reports are then applied again.
+pub struct SeqStore {
+    applied: HashMap<String, u64>,
+    writer: Option<Writer>,
+}
+
+/// The writer thread and the queue feeding it.
+struct Writer {
+    tx: mpsc::Sender<(String, u64)>,
+    handle: JoinHandle<()>,
+}
+
+impl SeqStore {
+    /// Load `path` if it exists, the last line per strategy winning; a missing file means
+    /// nothing was applied yet. The file is compacted to one line per strategy first.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut applied = HashMap::new();
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                for line in text.lines() {
+                    let mut fields = line.split_whitespace();
+                    if let (Some(strategy), Some(seq)) = (fields.next(), fields.next().and_then(|s| s.parse().ok())) {
+                        applied.insert(strategy.to_string(), seq);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // replaced atomically, so a crash leaves either the old or the compacted file
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        for (strategy, seq) in &applied {
+            writeln!(file, "{} {}", strategy, seq)?;
+        }
+        file.sync_all()?;
+        fs::rename(tmp, path)?;
+
+        let file = BufWriter::new(fs::OpenOptions::new().append(true).open(path)?);
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::Builder::new().name("fill-seq".into()).spawn(move || write_batches(file, rx))?;
+        Ok(Self {
+            applied,
+            writer: Some(Writer { tx, handle }),
+        })
+    }
+
+    pub fn last_applied(&self, strategy: &str) -> Option<u64> {
+        self.applied.get(strategy).copied()
+    }
+
+    /// Whether `seq` is new for `strategy`, i.e. has not been applied before.
+    pub fn is_new(&self, strategy: &str, seq: u64) -> bool {
+        self.last_applied(strategy).is_none_or(|last| seq > last)
+    }
+
+    /// Record `seq` as applied and queue it for the writer.
+    pub fn commit(&mut self, strategy: &str, seq: u64) {
+        self.applied.insert(strategy.to_string(), seq);
+        if let Some(ref writer) = self.writer
+            && writer.tx.send((strategy.to_string(), seq)).is_err()
+        {
+            error!(strategy, seq, "fill sequence writer has exited");
+        }
+    }
+
+    /// Let the writer persist what is queued and exit; later commits are kept in memory only.
+    pub fn close(&mut self) {
+        if let Some(Writer { tx, handle }) = self.writer.take() {
+            drop(tx);
+            if handle.join().is_err() {
+                error!("fill sequence writer panicked");
+            }
+        }
+    }
+}
+
+impl Drop for SeqStore {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Append every queued commit, then sync once, until the store closes.
+fn write_batches(mut file: BufWriter<fs::File>, rx: mpsc::Receiver<(String, u64)>) {
+    while let Ok(first) = rx.recv() {
+        let written = std::iter::once(first)
+            .chain(rx.try_iter())
+            .try_for_each(|(strategy, seq)| writeln!(file, "{} {}", strategy, seq))
+            .and_then(|()| file.flush())
+            .and_then(|()| file.get_ref().sync_data());
+        if let Err(e) = written {
+            error!(error = ?e, "failed to persist fill sequences");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replayed_sequences_are_skipped_after_reopen() {
+        let path = std::env::temp_dir().join(format!("fustg_seq_{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut store = SeqStore::open(&path).unwrap();
+        assert!(store.is_new("a", 1));
+        store.commit("a", 1);
+        store.commit("a", 2);
+        store.commit("b", 7);
+        store.close();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        // compacted to the last sequence per strategy
+        let store = SeqStore::open(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        assert!(!store.is_new("a", 2));
+        assert!(store.is_new("a", 3));
+        assert!(!store.is_new("b", 7) && store.is_new("b", 8));
+        drop(store);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,347 @@
+//! Redundant tick feeds: one SUB socket per publisher, of which one, the active feed, is
+//! dispatched. The others stay subscribed so the engine can fail over the moment the active
+//! feed stops, without missing or repeating ticks.
+use crate::error::EngineError;
+use crate::reconnect::ReconnectPolicy;
+use crate::types::{SymbolType, TickData};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hint;
+use std::time::{Duration, Instant};
+
+/// Per-symbol sequence numbers from the feed handler (see `codec::TickMeta`), to notice ticks
+/// lost between the feed handler and the engine.
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    last: HashMap<SymbolType, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ticks missing between the previous sequence of `symbol` and `seq`; 0 when in order.
+    /// A sequence at or below the previous one means the feed handler restarted or the
+    /// feed failed over, and counting starts again from it.
+    pub fn on_seq(&mut self, symbol: SymbolType, seq: u64) -> u64 {
+        match self.last.insert(symbol, seq) {
+            Some(prev) if seq > prev => seq - prev - 1,
+            _ => 0,
+        }
+    }
+}
+
+/// A change of active feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Failover {
+    pub from: usize,
+    pub to: usize,
+    /// how long `from` had been silent, by wall clock
+    pub silent_ms: i64,
+}
+
+/// Which feed's ticks are dispatched, and which ticks are new.
+///
+/// Feeds are ranked by the order they were given in: the active feed is the highest-ranked
+/// one that delivered a frame within `failover_ms`, so the engine fails over when the active
+/// feed goes quiet while another keeps publishing, and fails back once a higher-ranked feed
+/// publishes again. A tick is dispatched only if its (stamp, volume) is past the last one
+/// dispatched for its symbol, which drops the copies a new feed resends around a switch.
+pub struct FeedSelector {
+    failover_ms: i64,
+    /// wall-clock stamp of each feed's last frame
+    last_frame: Vec<Option<i64>>,
+    active: usize,
+    last_key: HashMap<SymbolType, (i64, i64)>,
+}
+
+impl FeedSelector {
+    pub fn new(feeds: usize, failover_ms: i64) -> Self {
+        Self {
+            failover_ms,
+            last_frame: vec![None; feeds],
+            active: 0,
+            last_key: HashMap::new(),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn set_failover_ms(&mut self, failover_ms: i64) {
+        self.failover_ms = failover_ms;
+    }
+
+    /// Treat ticks of `symbol` up to `(stamp, volume)` as dispatched already, e.g. by the
+    /// run a handoff file came from.
+    pub fn resume(&mut self, symbol: SymbolType, stamp: i64, volume: i64) {
+        self.last_key.insert(symbol, (stamp, volume));
+    }
+
+    /// Note a frame from `feed` at wall-clock `now`, switching the active feed if that is due.
+    pub fn on_frame(&mut self, feed: usize, now: i64) -> Option<Failover> {
+        self.last_frame[feed] = Some(now);
+        let live = |last: &Option<i64>| last.is_some_and(|t| now - t <= self.failover_ms);
+        let best = self.last_frame.iter().position(live)?;
+        if best == self.active {
+            return None;
+        }
+        let from = self.active;
+        self.active = best;
+        Some(Failover {
+            from,
+            to: best,
+            silent_ms: self.last_frame[from].map_or(0, |t| now - t),
+        })
+    }
+
+    /// Whether `tick`, received from `feed`, should be dispatched.
+    pub fn accept(&mut self, feed: usize, tick: &TickData) -> bool {
+        if feed != self.active {
+            return false;
+        }
+        let key = (tick.stamp, tick.volume);
+        match self.last_key.get_mut(&tick.symbol) {
+            Some(last) if key <= *last => false,
+            Some(last) => {
+                *last = key;
+                true
+            }
+            None => {
+                self.last_key.insert(tick.symbol, key);
+                true
+            }
+        }
+    }
+}
+
+/// The SUB sockets of every feed, subscribed alike. The subscriptions are remembered, so a
+/// feed whose socket is rebuilt with `reconnect` gets them all back.
+pub struct TickFeeds {
+    ctx: zmq::Context,
+    policy: ReconnectPolicy,
+    uris: Vec<String>,
+    sockets: Vec<zmq::Socket>,
+    /// every prefix subscribed, once per `set_subscribe` not undone, as ZMQ counts them
+    topics: RefCell<Vec<Vec<u8>>>,
+}
+
+impl TickFeeds {
+    pub fn connect(ctx: &zmq::Context, uris: &[&str]) -> Result<Self, EngineError> {
+        if uris.is_empty() {
+            return Err(EngineError::State("at least one tick feed is required"));
+        }
+        let policy = ReconnectPolicy::default();
+        let sockets = uris
+            .iter()
+            .map(|uri| Self::open(ctx, policy, uri).map_err(EngineError::socket(format!("connect SUB socket to {}", uri))))
+            .collect::<Result<_, EngineError>>()?;
+        Ok(Self {
+            ctx: ctx.clone(),
+            policy,
+            uris: uris.iter().map(|uri| uri.to_string()).collect(),
+            sockets,
+            topics: RefCell::new(Vec::new()),
+        })
+    }
+
+    fn open(ctx: &zmq::Context, policy: ReconnectPolicy, uri: &str) -> zmq::Result<zmq::Socket> {
+        let subscriber = ctx.socket(zmq::SUB)?;
+        // unlimited RCVHWM, subscriber.recv_into won't block
+        subscriber.set_rcvhwm(0)?;
+        policy.apply(&subscriber)?;
+        subscriber.connect(uri)?;
+        Ok(subscriber)
+    }
+
+    /// Back off as `policy` says when reconnecting, from now on.
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) -> zmq::Result<()> {
+        self.policy = policy;
+        self.sockets.iter().try_for_each(|s| policy.apply(s))
+    }
+
+    /// Replace the socket of `feed` with a new one, connected and subscribed as it was.
+    pub fn reconnect(&mut self, feed: usize) -> zmq::Result<()> {
+        let socket = Self::open(&self.ctx, self.policy, &self.uris[feed])?;
+        for topic in self.topics.borrow().iter() {
+            socket.set_subscribe(topic)?;
+        }
+        self.sockets[feed] = socket;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sockets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sockets.is_empty()
+    }
+
+    pub fn uri(&self, feed: usize) -> &str {
+        &self.uris[feed]
+    }
+
+    pub fn socket(&self, feed: usize) -> &zmq::Socket {
+        &self.sockets[feed]
+    }
+
+    pub fn set_subscribe(&self, prefix: &[u8]) -> zmq::Result<()> {
+        self.sockets.iter().try_for_each(|s| s.set_subscribe(prefix))?;
+        self.topics.borrow_mut().push(prefix.to_vec());
+        Ok(())
+    }
+
+    pub fn set_unsubscribe(&self, prefix: &[u8]) -> zmq::Result<()> {
+        self.sockets.iter().try_for_each(|s| s.set_unsubscribe(prefix))?;
+        let mut topics = self.topics.borrow_mut();
+        if let Some(i) = topics.iter().position(|t| t == prefix) {
+            topics.swap_remove(i);
+        }
+        Ok(())
+    }
+
+    /// Whether `prefix` is subscribed, by `set_subscribe` not yet undone.
+    pub fn is_subscribed(&self, prefix: &[u8]) -> bool {
+        self.topics.borrow().iter().any(|t| t == prefix)
+    }
+
+    pub fn poll_items(&self) -> impl Iterator<Item = zmq::PollItem<'_>> {
+        self.sockets.iter().map(|s| s.as_poll_item(zmq::POLLIN))
+    }
+}
+
+/// How the receive loop waits on the tick feeds and the other sockets it serves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReceiveMode {
+    /// sleep in `zmq::poll` (or straight in `recv_into`, with one feed and nothing else to
+    /// serve) until a socket is readable
+    #[default]
+    Blocking,
+    BusyPoll(BusyPoll),
+}
+
+/// Poll every socket, spinning on a zero-timeout poll for `spin_us` before waiting in one
+/// with a short timeout. A tick is picked up without a wake-up while spinning, and the loop
+/// never sleeps longer than `timeout_ms`, so it keeps serving the control socket and checks
+/// for shutdown even when no tick arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BusyPoll {
+    pub timeout_ms: i64,
+    pub spin_us: u64,
+}
+
+impl Default for BusyPoll {
+    fn default() -> Self {
+        Self { timeout_ms: 1, spin_us: 50 }
+    }
+}
+
+impl BusyPoll {
+    /// Like `zmq::poll`: the number of `items` ready, 0 when none became ready in time.
+    pub fn poll(&self, items: &mut [zmq::PollItem]) -> zmq::Result<i32> {
+        self.wait(|timeout_ms| zmq::poll(items, timeout_ms))
+    }
+
+    /// Spin on `poll` with a zero timeout, then call it once with `timeout_ms`.
+    fn wait(&self, mut poll: impl FnMut(i64) -> zmq::Result<i32>) -> zmq::Result<i32> {
+        let spin_until = Instant::now() + Duration::from_micros(self.spin_us);
+        loop {
+            let ready = poll(0)?;
+            if ready > 0 {
+                return Ok(ready);
+            }
+            if Instant::now() >= spin_until {
+                break;
+            }
+            hint::spin_loop();
+        }
+        poll(self.timeout_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(stamp: i64, volume: i64) -> TickData {
+        TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_ticks_missing_from_the_sequence() {
+        let rb = SymbolType::from("rb2505");
+        let ma = SymbolType::from("MA505");
+        let mut seqs = SequenceTracker::new();
+        assert_eq!(seqs.on_seq(rb, 1), 0);
+        assert_eq!(seqs.on_seq(ma, 7), 0);
+        assert_eq!(seqs.on_seq(rb, 2), 0);
+        assert_eq!(seqs.on_seq(rb, 5), 2);
+        assert_eq!(seqs.on_seq(rb, 1), 0, "a restart is not a gap");
+        assert_eq!(seqs.on_seq(rb, 2), 0);
+    }
+
+    #[test]
+    fn fails_over_without_repeating_ticks() {
+        let mut feeds = FeedSelector::new(2, 1000);
+        assert_eq!(feeds.on_frame(0, 0), None);
+        assert!(feeds.accept(0, &tick(500, 10)));
+        assert_eq!(feeds.on_frame(1, 10), None);
+        assert!(!feeds.accept(1, &tick(500, 10)), "standby feed is not dispatched");
+
+        // the primary stops; the backup keeps going
+        assert_eq!(feeds.on_frame(1, 900), None);
+        let failover = feeds.on_frame(1, 1500);
+        assert_eq!(
+            failover,
+            Some(Failover {
+                from: 0,
+                to: 1,
+                silent_ms: 1500
+            })
+        );
+        assert!(!feeds.accept(1, &tick(500, 10)), "already dispatched from the primary");
+        assert!(feeds.accept(1, &tick(1000, 12)));
+
+        // the primary comes back
+        assert_eq!(feeds.on_frame(0, 1600).map(|f| f.to), Some(0));
+        assert!(!feeds.accept(0, &tick(1000, 12)));
+        assert!(feeds.accept(0, &tick(1500, 13)));
+    }
+
+    #[test]
+    fn busy_poll_spins_before_waiting() {
+        let busy = BusyPoll { timeout_ms: 5, spin_us: 200 };
+        let mut timeouts = Vec::new();
+        // nothing ready: spins for the whole spin phase, then waits once
+        let started = Instant::now();
+        let ready = busy.wait(|timeout| {
+            timeouts.push(timeout);
+            Ok(0)
+        });
+        assert_eq!(ready.unwrap(), 0);
+        assert!(started.elapsed() >= Duration::from_micros(200));
+        assert!(timeouts.len() > 1);
+        assert_eq!(timeouts.iter().filter(|&&t| t == 5).count(), 1);
+        assert_eq!(timeouts.last(), Some(&5));
+
+        // ready while spinning: returns without waiting
+        let busy = BusyPoll { spin_us: 1_000_000, ..busy };
+        let mut calls = 0;
+        let ready = busy.wait(|timeout| {
+            calls += 1;
+            assert_eq!(timeout, 0);
+            Ok(if calls == 3 { 1 } else { 0 })
+        });
+        assert_eq!((ready.unwrap(), calls), (1, 3));
+    }
+}
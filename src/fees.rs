@@ -0,0 +1,110 @@
+//! Commission models. Exchanges charge some products a fixed amount per lot and others a
+//! rate on the traded value; the contracts table carries both components for every product,
+//! while the instrument fee files (`open`/`close`/`closetoday` with a `byvolume` flag) carry
+//! one of them. `FeeModel` computes a fill's commission either way, for the trackers and the
+//! simulated broker alike.
+use crate::config::ContractInfo;
+use crate::types::{OffsetFlagType, Order};
+
+/// One amount per offset: an amount per lot or a rate, depending on the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FeeRates {
+    pub open: f64,
+    pub close: f64,
+    /// closing a position opened the same day; fills do not say which position they close,
+    /// so commissions are charged at `close`
+    pub close_today: f64,
+}
+
+impl FeeRates {
+    fn of(&self, offset: OffsetFlagType) -> f64 {
+        match offset {
+            OffsetFlagType::OPEN => self.open,
+            OffsetFlagType::CLOSE => self.close,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeModel {
+    /// a fixed amount per lot
+    ByVolume(FeeRates),
+    /// a fraction of the traded value, price × multiplier × lots
+    ByValue(FeeRates),
+    /// a rate on the traded value plus an amount per lot, as the contracts table lists them
+    Mixed { rate: FeeRates, fixed: FeeRates },
+}
+
+impl FeeModel {
+    /// The fees of a contracts table entry.
+    pub fn of_contract(info: &ContractInfo) -> Self {
+        FeeModel::Mixed {
+            rate: FeeRates {
+                open: info.open_fee_rate,
+                close: info.close_fee_rate,
+                close_today: info.close_today_fee_rate,
+            },
+            fixed: FeeRates {
+                open: info.open_fee_fixed,
+                close: info.close_fee_fixed,
+                close_today: info.close_today_fee_fixed,
+            },
+        }
+    }
+
+    /// Commission for filling `order` on a contract of `multiplier`.
+    pub fn fee(&self, order: &Order, multiplier: f64) -> f64 {
+        let lots = order.lots as f64;
+        let value = order.price * multiplier * lots;
+        match self {
+            FeeModel::ByVolume(per_lot) => per_lot.of(order.offset) * lots,
+            FeeModel::ByValue(rate) => rate.of(order.offset) * value,
+            FeeModel::Mixed { rate, fixed } => rate.of(order.offset) * value + fixed.of(order.offset) * lots,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DirectionType, NameType, OrderType, SymbolType};
+
+    #[test]
+    fn charges_per_lot_or_on_value() {
+        let close = Order {
+            stg_name: NameType::from("s"),
+            symbol: SymbolType::from("IF2506"),
+            timestamp: 0,
+            price: 4000.0,
+            lots: 2,
+            direction: DirectionType::SELL,
+            offset: OffsetFlagType::CLOSE,
+            order_type: OrderType::LIMIT,
+            client_id: 1,
+            order_id: 0,
+        };
+        let rates = FeeRates {
+            open: 3.0,
+            close: 5.0,
+            close_today: 9.0,
+        };
+        assert_eq!(FeeModel::ByVolume(rates).fee(&close, 300.0), 10.0);
+        let value = FeeRates {
+            open: 2.3e-5,
+            close: 1e-4,
+            close_today: 2.3e-4,
+        };
+        assert!((FeeModel::ByValue(value).fee(&close, 300.0) - 240.0).abs() < 1e-9);
+        let info = ContractInfo {
+            multiplier: 300.0,
+            open_fee_rate: 1e-4,
+            open_fee_fixed: 1.0,
+            ..ContractInfo::default()
+        };
+        let open = Order {
+            offset: OffsetFlagType::OPEN,
+            ..close
+        };
+        assert!((FeeModel::of_contract(&info).fee(&open, 300.0) - 242.0).abs() < 1e-9);
+    }
+}
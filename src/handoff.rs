@@ -0,0 +1,175 @@
+//! Position handoff between engine runs. On shutdown the engine writes what every strategy
+//! holds and has resting, and the last tick it processed per symbol; an engine started with
+//! `--resume-from` reads it back so an intraday upgrade needs no manual position re-entry.
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, SymbolType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Bumped whenever a field changes meaning; older files are refused rather than misread.
+pub const HANDOFF_VERSION: u32 = 1;
+
+/// One side of a strategy's position.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PositionState {
+    pub lots: u32,
+    pub avg_price: f64,
+}
+
+/// A resting order with its unfilled lots.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OpenOrder {
+    pub client_id: u64,
+    /// absent from handoff files written before engine order ids
+    #[serde(default)]
+    pub order_id: u64,
+    pub direction: DirectionType,
+    pub offset: OffsetFlagType,
+    pub order_type: OrderType,
+    pub price: f64,
+    pub lots: u32,
+    pub timestamp: i64,
+}
+
+impl OpenOrder {
+    pub fn from_order(order: &Order) -> Self {
+        Self {
+            client_id: order.client_id,
+            order_id: order.order_id,
+            direction: order.direction,
+            offset: order.offset,
+            order_type: order.order_type,
+            price: order.price,
+            lots: order.lots,
+            timestamp: order.timestamp,
+        }
+    }
+
+    pub fn to_order(&self, stg_name: NameType, symbol: SymbolType) -> Order {
+        Order {
+            stg_name,
+            symbol,
+            timestamp: self.timestamp,
+            price: self.price,
+            lots: self.lots,
+            direction: self.direction,
+            offset: self.offset,
+            order_type: self.order_type,
+            client_id: self.client_id,
+            order_id: self.order_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyState {
+    pub strategy: String,
+    pub symbol: String,
+    pub available_cash: f64,
+    #[serde(default)]
+    pub long: Option<PositionState>,
+    #[serde(default)]
+    pub short: Option<PositionState>,
+    #[serde(default)]
+    pub open_orders: Vec<OpenOrder>,
+}
+
+/// Dedup key of the last tick a worker processed, as `FeedSelector` compares them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastTick {
+    pub stamp: i64,
+    pub volume: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    pub version: u32,
+    /// wall-clock stamp the file was written at
+    pub written_at: i64,
+    pub strategies: Vec<StrategyState>,
+    /// symbol → last processed tick
+    pub last_ticks: BTreeMap<String, LastTick>,
+}
+
+impl Handoff {
+    pub fn new(written_at: i64) -> Self {
+        Self {
+            version: HANDOFF_VERSION,
+            written_at,
+            strategies: Vec::new(),
+            last_ticks: BTreeMap::new(),
+        }
+    }
+
+    pub fn strategy(&self, strategy: &str, symbol: &str) -> Option<&StrategyState> {
+        self.strategies.iter().find(|s| s.strategy == strategy && s.symbol == symbol)
+    }
+
+    /// Write to a sibling temporary file first, so a crash mid-write never leaves a truncated
+    /// handoff where the previous one was.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let handoff: Handoff = serde_json::from_slice(&fs::read(path)?).map_err(io::Error::other)?;
+        if handoff.version != HANDOFF_VERSION {
+            return Err(io::Error::other(format!(
+                "handoff version {}, engine expects {}",
+                handoff.version, HANDOFF_VERSION
+            )));
+        }
+        Ok(handoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let mut handoff = Handoff::new(1_700_000_000_000);
+        handoff.strategies.push(StrategyState {
+            strategy: "Aberration100".into(),
+            symbol: "rb2505".into(),
+            available_cash: 990_000.0,
+            long: Some(PositionState { lots: 2, avg_price: 3500.0 }),
+            short: None,
+            open_orders: vec![OpenOrder {
+                client_id: 7,
+                order_id: 0,
+                direction: DirectionType::SELL,
+                offset: OffsetFlagType::CLOSE,
+                order_type: OrderType::LIMIT,
+                price: 3550.0,
+                lots: 1,
+                timestamp: 1_699_999_990_000,
+            }],
+        });
+        handoff.last_ticks.insert(
+            "rb2505".into(),
+            LastTick {
+                stamp: 1_699_999_999_500,
+                volume: 4242,
+            },
+        );
+
+        let path = std::env::temp_dir().join(format!("fustg_handoff_{}.json", std::process::id()));
+        handoff.save(&path).unwrap();
+        let loaded = Handoff::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, handoff);
+        assert!(loaded.strategy("Aberration100", "rb2505").is_some());
+        assert!(loaded.strategy("Aberration100", "MA505").is_none());
+    }
+}
@@ -0,0 +1,100 @@
+//! Liveness for external supervisors. The engine publishes a `Heartbeat` on a PUB socket at a
+//! fixed interval from its receive loop, so a supervisor subscribed to `TOPIC` that stops
+//! hearing from an engine can restart it or alert: a hung receive loop stops the heartbeats,
+//! and a stuck worker shows as a queue depth that only grows. Each message is one frame, the
+//! topic, a space and the heartbeat as JSON.
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+pub const TOPIC: &str = "heartbeat";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    /// names the engine among several, e.g. one per shard
+    pub engine: String,
+    /// counts up from 0 per run; a reset means the engine restarted
+    pub seq: u64,
+    /// wall clock when sent
+    pub stamp: i64,
+    pub uptime_secs: u64,
+    /// stamp of the last tick received, and how long ago by wall clock
+    pub last_tick_stamp: Option<i64>,
+    pub last_tick_age_ms: Option<i64>,
+    /// messages waiting in each worker's queue
+    pub queue_depths: Vec<usize>,
+}
+
+impl Heartbeat {
+    pub fn frame(&self) -> String {
+        format!("{} {}", TOPIC, serde_json::to_string(self).expect("heartbeat serializes"))
+    }
+
+    pub fn parse(frame: &str) -> Option<Self> {
+        serde_json::from_str(frame.strip_prefix(TOPIC)?.trim_start()).ok()
+    }
+}
+
+/// Publishes heartbeats every `interval_ms` on `socket`.
+pub struct HeartbeatPublisher {
+    socket: zmq::Socket,
+    engine: String,
+    interval_ms: i64,
+    started: Instant,
+    seq: u64,
+    last_sent: i64,
+}
+
+impl HeartbeatPublisher {
+    pub fn new(socket: zmq::Socket, engine: &str, interval_ms: i64) -> Self {
+        Self {
+            socket,
+            engine: engine.into(),
+            interval_ms,
+            started: Instant::now(),
+            seq: 0,
+            last_sent: i64::MIN,
+        }
+    }
+
+    pub fn due(&self, now: i64) -> bool {
+        now.saturating_sub(self.last_sent) >= self.interval_ms
+    }
+
+    /// Send a heartbeat stamped `now` with the engine's latest state.
+    pub fn publish(&mut self, now: i64, last_tick: Option<(i64, i64)>, queue_depths: Vec<usize>) -> zmq::Result<()> {
+        let heartbeat = Heartbeat {
+            engine: self.engine.clone(),
+            seq: self.seq,
+            stamp: now,
+            uptime_secs: self.started.elapsed().as_secs(),
+            last_tick_stamp: last_tick.map(|(stamp, _)| stamp),
+            last_tick_age_ms: last_tick.map(|(_, received)| now - received),
+            queue_depths,
+        };
+        self.seq += 1;
+        self.last_sent = now;
+        self.socket.send(heartbeat.frame().as_str(), zmq::DONTWAIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_and_parses_heartbeats() {
+        let heartbeat = Heartbeat {
+            engine: "shard-0".into(),
+            seq: 7,
+            stamp: 1_741_834_800_000,
+            uptime_secs: 3600,
+            last_tick_stamp: Some(1_741_834_799_500),
+            last_tick_age_ms: Some(480),
+            queue_depths: vec![0, 3],
+        };
+        let frame = heartbeat.frame();
+        assert!(frame.starts_with("heartbeat {"));
+        assert_eq!(Heartbeat::parse(&frame), Some(heartbeat));
+        assert_eq!(Heartbeat::parse("fill {}"), None);
+    }
+}
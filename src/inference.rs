@@ -0,0 +1,157 @@
+//! Side channel to an external model service: a strategy sends a feature vector and gets a
+//! score back, so ML-scored signals do not need the model inside the engine.
+//!
+//! The service answers on a REP socket. A request is the features as little-endian `f64`s and
+//! the reply is one little-endian `f64`. A single thread owns the REQ socket, so strategies
+//! never block on the network unless they ask to; a request not answered within the timeout
+//! resolves to the fallback score.
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+use zmq;
+
+struct Request {
+    features: Vec<f64>,
+    deadline: Instant,
+    reply: mpsc::Sender<f64>,
+}
+
+/// Handle to the model service; cheap to clone, one per strategy is fine.
+#[derive(Clone)]
+pub struct ModelClient {
+    requests: mpsc::Sender<Request>,
+    timeout: Duration,
+    fallback: f64,
+}
+
+/// A score that is being computed. Resolves to the fallback once the timeout passes.
+pub struct PendingScore {
+    reply: mpsc::Receiver<f64>,
+    deadline: Instant,
+    fallback: f64,
+}
+
+impl PendingScore {
+    /// The score if it arrived, the fallback if the deadline passed, `None` while still waiting.
+    pub fn try_get(&self) -> Option<f64> {
+        match self.reply.try_recv() {
+            Ok(score) => Some(score),
+            Err(mpsc::TryRecvError::Disconnected) => Some(self.fallback),
+            Err(mpsc::TryRecvError::Empty) => (Instant::now() >= self.deadline).then_some(self.fallback),
+        }
+    }
+
+    /// Block until the score arrives or the deadline passes.
+    pub fn wait(self) -> f64 {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        self.reply.recv_timeout(left).unwrap_or(self.fallback)
+    }
+}
+
+fn encode_features(features: &[f64]) -> Vec<u8> {
+    features.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+fn decode_score(frame: &[u8]) -> Option<f64> {
+    Some(f64::from_le_bytes(frame.try_into().ok()?))
+}
+
+impl ModelClient {
+    /// Connect to the model service at `uri`. Scores not back within `timeout` are `fallback`.
+    pub fn connect(uri: &str, timeout: Duration, fallback: f64) -> Self {
+        let (requests, rx) = mpsc::channel::<Request>();
+        let uri = uri.to_string();
+        thread::Builder::new()
+            .name("model-client".into())
+            .spawn(move || serve(&uri, rx))
+            .expect("Failed to spawn model client thread");
+        Self { requests, timeout, fallback }
+    }
+
+    /// Queue a request for `features`; poll the result with `PendingScore::try_get`.
+    pub fn score(&self, features: &[f64]) -> PendingScore {
+        let (reply, rx) = mpsc::channel();
+        let deadline = Instant::now() + self.timeout;
+        // if the service thread is gone the receiver disconnects and resolves to the fallback
+        let _ = self.requests.send(Request {
+            features: features.to_vec(),
+            deadline,
+            reply,
+        });
+        PendingScore {
+            reply: rx,
+            deadline,
+            fallback: self.fallback,
+        }
+    }
+
+    /// Score `features` and wait for it, at most the timeout.
+    pub fn score_blocking(&self, features: &[f64]) -> f64 {
+        self.score(features).wait()
+    }
+}
+
+fn req_socket(ctx: &zmq::Context, uri: &str) -> zmq::Result<zmq::Socket> {
+    let socket = ctx.socket(zmq::REQ)?;
+    socket.set_linger(0)?;
+    socket.connect(uri)?;
+    Ok(socket)
+}
+
+/// Answer requests one at a time. A REQ socket cannot send again until it has received, so
+/// after a timeout it is replaced, which also drops the late reply.
+fn serve(uri: &str, requests: mpsc::Receiver<Request>) {
+    let ctx = zmq::Context::new();
+    let mut socket = req_socket(&ctx, uri).expect("Failed to connect REQ socket to model service");
+    for request in requests {
+        let left = request.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            // already resolved to the fallback on the strategy side
+            continue;
+        }
+        if let Err(e) = socket.send(encode_features(&request.features), 0) {
+            error!(uri, error = ?e, "failed to send model request");
+            continue;
+        }
+        let answered = matches!(socket.poll(zmq::POLLIN, left.as_millis() as i64), Ok(n) if n > 0);
+        if !answered {
+            warn!(uri, timeout_ms = left.as_millis() as u64, "model service timed out");
+            match req_socket(&ctx, uri) {
+                Ok(fresh) => socket = fresh,
+                Err(e) => error!(uri, error = ?e, "failed to reconnect to model service"),
+            }
+            continue;
+        }
+        match socket.recv_bytes(0).map(|frame| decode_score(&frame)) {
+            Ok(Some(score)) => {
+                let _ = request.reply.send(score);
+            }
+            Ok(None) => warn!(uri, "malformed model reply"),
+            Err(e) => error!(uri, error = ?e, "failed to receive model reply"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wire_format_and_fallback() {
+        let frame = encode_features(&[1.5, -2.0]);
+        assert_eq!(frame.len(), 16);
+        assert_eq!(decode_score(&frame[8..]), Some(-2.0));
+        assert_eq!(decode_score(&frame), None);
+
+        let (reply, rx) = mpsc::channel();
+        let pending = PendingScore {
+            reply: rx,
+            deadline: Instant::now() + Duration::from_secs(60),
+            fallback: 0.5,
+        };
+        assert_eq!(pending.try_get(), None);
+        drop(reply);
+        assert_eq!(pending.try_get(), Some(0.5));
+    }
+}
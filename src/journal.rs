@@ -0,0 +1,315 @@
+//! Crash-safe write-ahead journal of what the engine did, as opposed to what it was fed (see
+//! `events`): the id of every tick dispatched, every order before it goes out, every fill
+//! booked and every cancel. An engine restarted with `CtaEngine::recover_from_journal`
+//! replays the orders, fills and cancels into the strategies' trackers, so positions, cash
+//! and resting orders come back exactly as booked, and ticks up to the last journaled one
+//! are not dispatched again. Orders are journaled before they are sent, so an order the
+//! crash cut off is recovered as resting rather than lost: recovery is at-least-once.
+//!
+//! The file is memory-mapped and grown by doubling. A write lands in the page cache as soon
+//! as it is copied in, so it survives the process crashing; `sync` flushes it to disk for
+//! surviving the machine going down as well. Each record is a kind byte, the payload length
+//! and an FNV-1a checksum of both plus the payload, followed by the payload; the unwritten
+//! tail is zeros, and the first zero kind or bad checksum ends the journal.
+use crate::types::{Order, SymbolType};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+
+const KIND_TICK: u8 = 1;
+const KIND_ORDER: u8 = 2;
+const KIND_FILL: u8 = 3;
+const KIND_CANCEL: u8 = 4;
+/// kind, payload length, checksum
+const RECORD_HEADER: usize = 1 + 4 + 4;
+/// symbol, stamp, volume
+const TICK_PAYLOAD: usize = mem::size_of::<SymbolType>() + 8 + 8;
+/// Size a new journal is mapped at.
+const INITIAL_CAPACITY: usize = 4 << 20;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Entry {
+    /// a tick dispatched to the workers, by the key `FeedSelector` deduplicates on
+    Tick { symbol: SymbolType, stamp: i64, volume: i64 },
+    /// an order about to be sent, with its client and engine ids assigned
+    Order(Order),
+    /// an order as filled: the fill's price, lots and stamp
+    Fill(Order),
+    /// a resting order withdrawn
+    Cancel(Order),
+}
+
+fn pod_bytes<T: Copy>(value: &T) -> &[u8] {
+    // SAFETY: only used on the repr(C) wire structs
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+fn checksum(kind: u8, payload: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &byte in [kind].iter().chain(&(payload.len() as u32).to_le_bytes()).chain(payload) {
+        hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+/// Write `entry`'s payload over `payload`; its kind.
+fn encode(entry: &Entry, payload: &mut Vec<u8>) -> u8 {
+    payload.clear();
+    match entry {
+        Entry::Tick { symbol, stamp, volume } => {
+            payload.extend_from_slice(pod_bytes(symbol));
+            payload.extend_from_slice(&stamp.to_le_bytes());
+            payload.extend_from_slice(&volume.to_le_bytes());
+            KIND_TICK
+        }
+        Entry::Order(order) => {
+            payload.extend_from_slice(pod_bytes(order));
+            KIND_ORDER
+        }
+        Entry::Fill(order) => {
+            payload.extend_from_slice(pod_bytes(order));
+            KIND_FILL
+        }
+        Entry::Cancel(order) => {
+            payload.extend_from_slice(pod_bytes(order));
+            KIND_CANCEL
+        }
+    }
+}
+
+fn decode(kind: u8, payload: &[u8]) -> Option<Entry> {
+    let order = || {
+        // SAFETY: the length matches and the checksum vouches for the bytes being an `Order`
+        (payload.len() == mem::size_of::<Order>()).then(|| unsafe { ptr::read_unaligned(payload.as_ptr() as *const Order) })
+    };
+    match kind {
+        KIND_TICK if payload.len() == TICK_PAYLOAD => {
+            let (symbol, rest) = payload.split_at(mem::size_of::<SymbolType>());
+            // SAFETY: as above; a symbol is plain bytes
+            let symbol = unsafe { ptr::read_unaligned(symbol.as_ptr() as *const SymbolType) };
+            Some(Entry::Tick {
+                symbol,
+                stamp: i64::from_le_bytes(rest[..8].try_into().unwrap()),
+                volume: i64::from_le_bytes(rest[8..].try_into().unwrap()),
+            })
+        }
+        KIND_ORDER => order().map(Entry::Order),
+        KIND_FILL => order().map(Entry::Fill),
+        KIND_CANCEL => order().map(Entry::Cancel),
+        _ => None,
+    }
+}
+
+/// The entries in `bytes` up to the first empty or damaged record, and where that is.
+fn scan(bytes: &[u8]) -> (Vec<Entry>, usize) {
+    let mut entries = Vec::new();
+    let mut at = 0;
+    while let Some(header) = bytes.get(at..at + RECORD_HEADER) {
+        let kind = header[0];
+        let len = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+        let sum = u32::from_le_bytes(header[5..9].try_into().unwrap());
+        let Some(payload) = bytes.get(at + RECORD_HEADER..at + RECORD_HEADER + len) else {
+            break;
+        };
+        if kind == 0 || checksum(kind, payload) != sum {
+            break;
+        }
+        let Some(entry) = decode(kind, payload) else {
+            break;
+        };
+        entries.push(entry);
+        at += RECORD_HEADER + len;
+    }
+    (entries, at)
+}
+
+/// Every entry journaled at `path`, in order.
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<Entry>> {
+    Ok(scan(&std::fs::read(path)?).0)
+}
+
+pub struct Journal {
+    file: File,
+    map: *mut u8,
+    capacity: usize,
+    /// bytes of complete records
+    len: usize,
+    /// the payload being appended, kept to reuse its allocation
+    payload: Vec<u8>,
+}
+
+// SAFETY: the mapping is owned by the journal and only touched through `&mut self`
+unsafe impl Send for Journal {}
+
+impl Journal {
+    /// Open `path` for appending after the entries already in it, creating it if need be.
+    /// A record a crash cut short is wiped first.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).read(true).write(true).truncate(false).open(path)?;
+        let size = file.metadata()?.len() as usize;
+        let capacity = size.max(INITIAL_CAPACITY).next_power_of_two();
+        file.set_len(capacity as u64)?;
+        let mut journal = Self {
+            map: map(&file, capacity)?,
+            file,
+            capacity,
+            len: 0,
+            payload: Vec::with_capacity(mem::size_of::<Order>().max(TICK_PAYLOAD)),
+        };
+        let (_, valid) = scan(journal.bytes());
+        journal.len = valid;
+        // SAFETY: `valid..capacity` lies inside the mapping
+        unsafe { ptr::write_bytes(journal.map.add(valid), 0, capacity - valid) };
+        Ok(journal)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: the mapping is `capacity` bytes for as long as `self` holds it
+        unsafe { std::slice::from_raw_parts(self.map, self.capacity) }
+    }
+
+    /// Bytes of entries journaled so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn append(&mut self, entry: &Entry) -> io::Result<()> {
+        let kind = encode(entry, &mut self.payload);
+        let size = RECORD_HEADER + self.payload.len();
+        if self.len + size > self.capacity {
+            self.grow(self.len + size)?;
+        }
+        let payload = &self.payload;
+        let mut header = [0u8; RECORD_HEADER];
+        header[1..5].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        header[5..9].copy_from_slice(&checksum(kind, payload).to_le_bytes());
+        // SAFETY: `len + size` fits the mapping, checked above
+        unsafe {
+            let at = self.map.add(self.len);
+            ptr::copy_nonoverlapping(header.as_ptr(), at, RECORD_HEADER);
+            ptr::copy_nonoverlapping(payload.as_ptr(), at.add(RECORD_HEADER), payload.len());
+            // the kind goes in last: until it does, the record reads as the end
+            ptr::write_volatile(at, kind);
+        }
+        self.len += size;
+        Ok(())
+    }
+
+    /// Remap the file at `needed` bytes or more. The old mapping stays in place until the new
+    /// one is made, so a failure leaves the journal as it was.
+    fn grow(&mut self, needed: usize) -> io::Result<()> {
+        let capacity = needed.next_power_of_two();
+        self.sync()?;
+        self.file.set_len(capacity as u64)?;
+        let grown = map(&self.file, capacity)?;
+        // SAFETY: the journal's own mapping, replaced by `grown`
+        unsafe { libc::munmap(self.map as *mut libc::c_void, self.capacity) };
+        self.map = grown;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    /// Flush what is journaled so far to disk.
+    pub fn sync(&self) -> io::Result<()> {
+        // SAFETY: the journal's own mapping
+        if unsafe { libc::msync(self.map as *mut libc::c_void, self.capacity, libc::MS_SYNC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        // SAFETY: the journal's own mapping, not used again
+        unsafe { libc::munmap(self.map as *mut libc::c_void, self.capacity) };
+        // the zeroed tail only holds space
+        let _ = self.file.set_len(self.len as u64);
+    }
+}
+
+fn map(file: &File, capacity: usize) -> io::Result<*mut u8> {
+    use std::os::fd::AsRawFd;
+    // SAFETY: a fresh shared mapping of a file at least `capacity` bytes long
+    let map = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            capacity,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    if map == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(map as *mut u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DirectionType, NameType, OffsetFlagType, OrderType};
+
+    #[test]
+    fn appends_grows_and_recovers_up_to_a_torn_record() {
+        let path = std::env::temp_dir().join(format!("journal-{}.wal", std::process::id()));
+        let symbol = SymbolType::from("rb2505");
+        let order = Order {
+            stg_name: NameType::from("s"),
+            symbol,
+            timestamp: 1000,
+            price: 3500.0,
+            lots: 2,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id: 1,
+            order_id: 7,
+        };
+        let ticks = INITIAL_CAPACITY / (RECORD_HEADER + TICK_PAYLOAD) + 1;
+        {
+            let mut journal = Journal::open(&path).unwrap();
+            for stamp in 0..ticks as i64 {
+                journal
+                    .append(&Entry::Tick {
+                        symbol,
+                        stamp,
+                        volume: stamp,
+                    })
+                    .unwrap();
+            }
+            journal.append(&Entry::Order(order)).unwrap();
+            journal.append(&Entry::Fill(Order { lots: 1, ..order })).unwrap();
+            assert!(journal.len() > INITIAL_CAPACITY);
+        }
+        // a crash mid-append: a header whose payload never made it
+        {
+            let journal = Journal::open(&path).unwrap();
+            let at = journal.len();
+            drop(journal);
+            let mut bytes = std::fs::read(&path).unwrap();
+            bytes.truncate(at);
+            bytes.extend_from_slice(&[KIND_FILL, 80, 0, 0, 0, 1, 2, 3, 4, 9]);
+            std::fs::write(&path, bytes).unwrap();
+        }
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(&Entry::Cancel(Order { lots: 1, ..order })).unwrap();
+        drop(journal);
+
+        let entries = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), ticks + 3);
+        assert!(matches!(entries[ticks - 1], Entry::Tick { stamp, .. } if stamp == ticks as i64 - 1));
+        assert!(matches!(entries[ticks], Entry::Order(o) if o.order_id == 7 && o.lots == 2));
+        assert!(matches!(entries[ticks + 1], Entry::Fill(o) if o.lots == 1));
+        assert!(matches!(entries[ticks + 2], Entry::Cancel(o) if o.symbol == symbol));
+    }
+}
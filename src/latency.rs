@@ -0,0 +1,84 @@
+//! Hot-path latency, stage by stage. A tick is stamped when the receive loop reads it and
+//! when it is handed to its worker; the worker notes when it starts running strategies on it,
+//! and every order the tick leads to is timed when it is pushed out:
+//!
+//! - `recv_dispatch`: receive to dispatch, the receive loop's decoding and checks
+//! - `dispatch_strategy`: dispatch to strategy, time waiting in the worker's queue
+//! - `strategy_send`: strategy to send, the strategies' own time plus the order checks
+//!
+//! Each stage is an HDR histogram, so p99 and p999 are exact to three significant digits
+//! however long the tail, and `/metrics` reports p50, p99 and p999 per stage.
+use hdrhistogram::Histogram;
+use std::sync::Mutex;
+
+/// Latencies beyond a minute are recorded as a minute.
+const MAX_NANOS: u64 = 60_000_000_000;
+
+/// Quantiles `/metrics` reports.
+pub const QUANTILES: [f64; 3] = [0.5, 0.99, 0.999];
+
+pub struct LatencyHistogram {
+    nanos: Mutex<Histogram<u64>>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self { nanos: Mutex::new(empty()) }
+    }
+}
+
+fn empty() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_NANOS, 3).expect("valid histogram bounds")
+}
+
+impl LatencyHistogram {
+    /// Record one latency; negative ones (clocks stepped back) are dropped.
+    pub fn record(&self, nanos: i64) {
+        if nanos >= 0 {
+            self.nanos.lock().unwrap().saturating_record(nanos as u64);
+        }
+    }
+
+    /// A copy of what was recorded so far.
+    pub fn snapshot(&self) -> Histogram<u64> {
+        self.nanos.lock().unwrap().clone()
+    }
+}
+
+/// The recordings of several histograms, e.g. one stage across all workers, together.
+pub fn merged<'a>(histograms: impl IntoIterator<Item = &'a LatencyHistogram>) -> Histogram<u64> {
+    let mut all = empty();
+    for histogram in histograms {
+        // same bounds throughout, so adding cannot fail
+        let _ = all.add(histogram.snapshot());
+    }
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_and_reads_quantiles() {
+        let (a, b) = (LatencyHistogram::default(), LatencyHistogram::default());
+        for nanos in 1..=990 {
+            a.record(nanos * 1_000);
+        }
+        for nanos in 991..=1000 {
+            b.record(nanos * 1_000);
+        }
+        b.record(-5);
+        b.record(i64::MAX);
+        let all = merged([&a, &b]);
+        assert_eq!(all.len(), 1001);
+        let near = |q: f64, expected: u64| {
+            let v = all.value_at_quantile(q);
+            assert!(v.abs_diff(expected) <= expected / 500, "q{} = {}, expected {}", q, v, expected);
+        };
+        near(0.5, 501_000);
+        near(0.99, 991_000);
+        // the clamped outlier is the tail
+        near(1.0, MAX_NANOS);
+    }
+}
@@ -0,0 +1,90 @@
+//! CTA strategy engine for Chinese futures: receives `TickData` over ZMQ, runs strategies on
+//! worker threads and pushes their `Order`s downstream.
+//!
+//! Embed it by building a `CtaEngine`, registering `Strategy` implementations with a
+//! `PerformanceTracker` each, then calling `init`/`start`/`stop`.
+
+pub mod account;
+pub mod affinity;
+pub mod audit;
+pub mod backtest;
+pub mod bars;
+pub mod benchmark;
+pub mod breaker;
+pub mod budget;
+pub mod calendar;
+pub mod clock;
+pub mod codec;
+pub mod composite;
+pub mod conduct;
+pub mod config;
+pub mod continuous;
+pub mod control;
+pub mod currency;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod engine;
+pub mod equity_curve;
+pub mod error;
+pub mod events;
+pub mod execution;
+pub mod feed;
+pub mod fees;
+pub mod handoff;
+pub mod heartbeat;
+pub mod ids;
+pub mod inference;
+pub mod journal;
+pub mod latency;
+pub mod limits;
+pub mod logging;
+pub mod metrics;
+pub mod operator;
+pub mod optimizer;
+pub mod order_queue;
+pub mod order_stats;
+pub mod pause;
+pub mod pending;
+pub mod perf_stats;
+pub mod perf_tracker;
+pub mod plugin;
+pub mod position;
+pub mod pricing;
+pub mod query;
+pub mod queue;
+pub mod reconnect;
+pub mod recorder;
+pub mod reload;
+pub mod report;
+pub mod risk;
+pub mod rollover;
+pub mod round_trips;
+pub mod router;
+pub mod shard;
+pub mod sim;
+pub mod sizing;
+pub mod skew;
+pub mod split;
+pub mod statement;
+pub mod stops;
+pub mod store;
+pub mod strategies;
+pub mod strategy;
+pub mod symbols;
+pub mod tca;
+pub mod testing;
+pub mod time_filter;
+pub mod timeutil;
+pub mod tracking;
+pub mod types;
+pub mod validate;
+pub mod walkforward;
+pub mod warmup;
+pub mod watchdog;
+
+pub use config::ContractInfo;
+pub use engine::CtaEngine;
+pub use error::EngineError;
+pub use perf_tracker::PerformanceTracker;
+pub use strategy::Strategy;
+pub use types::{BookSnapshot, DirectionType, NameType, OffsetFlagType, Order, SymbolType, TickData};
@@ -0,0 +1,108 @@
+//! Per-strategy position and exposure limits, checked on every opening order before it is
+//! sent. A strategy's position counts the lots it holds plus those its resting opening orders
+//! would add, so a burst of orders cannot slip past the limit before the first one fills.
+//! Closing orders only ever shrink a position and are never refused.
+use crate::types::{DirectionType, OffsetFlagType, Order};
+use serde::Deserialize;
+use std::fmt;
+
+/// Caps on one strategy's position; a `None` cap is not checked.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PositionLimits {
+    /// most lots long
+    pub max_long: Option<u32>,
+    /// most lots short
+    pub max_short: Option<u32>,
+    /// largest gross notional, long plus short lots × price × multiplier, in account currency
+    pub max_notional: Option<f64>,
+}
+
+/// Which limit an order would have breached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitBreach {
+    Long { lots: u32, limit: u32 },
+    Short { lots: u32, limit: u32 },
+    Notional { notional: f64, limit: f64 },
+}
+
+impl fmt::Display for LimitBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitBreach::Long { lots, limit } => write!(f, "{} lots long over the {} lot limit", lots, limit),
+            LimitBreach::Short { lots, limit } => write!(f, "{} lots short over the {} lot limit", lots, limit),
+            LimitBreach::Notional { notional, limit } => write!(f, "notional {:.2} over the {:.2} limit", notional, limit),
+        }
+    }
+}
+
+impl PositionLimits {
+    /// Whether `order` fits, given `long` and `short` lots held or resting to open and the
+    /// contract's `multiplier`. The notional is valued at the order's price.
+    pub fn check(&self, long: u32, short: u32, order: &Order, multiplier: f64) -> Result<(), LimitBreach> {
+        if order.offset != OffsetFlagType::OPEN {
+            return Ok(());
+        }
+        let (long, short) = match order.direction {
+            DirectionType::BUY => (long + order.lots, short),
+            DirectionType::SELL => (long, short + order.lots),
+        };
+        if let Some(limit) = self.max_long
+            && long > limit
+        {
+            return Err(LimitBreach::Long { lots: long, limit });
+        }
+        if let Some(limit) = self.max_short
+            && short > limit
+        {
+            return Err(LimitBreach::Short { lots: short, limit });
+        }
+        let notional = (long + short) as f64 * order.price * multiplier;
+        if let Some(limit) = self.max_notional
+            && notional > limit
+        {
+            return Err(LimitBreach::Notional { notional, limit });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{NameType, OrderType, SymbolType};
+
+    #[test]
+    fn refuses_opening_past_a_limit_but_never_closing() {
+        let limits = PositionLimits {
+            max_long: Some(3),
+            max_short: None,
+            max_notional: Some(200_000.0),
+        };
+        let order = |direction, offset, lots| Order {
+            stg_name: NameType::from("s"),
+            symbol: SymbolType::from("rb2505"),
+            timestamp: 0,
+            price: 3500.0,
+            lots,
+            direction,
+            offset,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+        assert_eq!(limits.check(2, 0, &order(DirectionType::BUY, OffsetFlagType::OPEN, 1), 10.0), Ok(()));
+        assert_eq!(
+            limits.check(2, 0, &order(DirectionType::BUY, OffsetFlagType::OPEN, 2), 10.0),
+            Err(LimitBreach::Long { lots: 4, limit: 3 })
+        );
+        assert_eq!(
+            limits.check(3, 2, &order(DirectionType::SELL, OffsetFlagType::OPEN, 1), 10.0),
+            Err(LimitBreach::Notional {
+                notional: 210_000.0,
+                limit: 200_000.0
+            })
+        );
+        assert_eq!(limits.check(9, 9, &order(DirectionType::SELL, OffsetFlagType::CLOSE, 9), 10.0), Ok(()));
+    }
+}
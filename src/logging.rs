@@ -0,0 +1,280 @@
+//! Logging setup. Lines are formatted on the thread that logs them and pushed onto a
+//! lock-free ring; a dedicated `logger` thread drains the ring into stdout or the log file,
+//! so a worker never waits on I/O. When the ring is full a line is dropped rather than
+//! blocking, and the logger reports how many were lost. Routine DEBUG and TRACE events can
+//! be sampled, keeping one in `sample_debug`. Line buffers go back to a free list once
+//! written, so a warmed-up ring logs without allocating.
+use anyhow::Result;
+use crossbeam_queue::ArrayQueue;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::{self, MakeWriter, writer::BoxMakeWriter};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// How long the logger sleeps when the ring is empty.
+const IDLE: Duration = Duration::from_millis(1);
+/// Buffers grown past this by a long line are freed instead of recycled.
+const MAX_RECYCLED: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    /// one JSON object per line, with span fields (worker id, strategy) attached
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// `EnvFilter` directive such as `info` or `fustg_rs=debug`; `RUST_LOG` takes precedence.
+    pub level: String,
+    pub format: LogFormat,
+    /// Append to this file instead of writing to stdout.
+    pub file: Option<PathBuf>,
+    /// Lines the ring holds before dropping; 0 writes synchronously from the logging thread.
+    pub ring_capacity: usize,
+    /// Keep one in this many DEBUG and TRACE events; 1 keeps them all.
+    pub sample_debug: u32,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".into(),
+            format: LogFormat::Text,
+            file: None,
+            ring_capacity: 65536,
+            sample_debug: 1,
+        }
+    }
+}
+
+impl LogConfig {
+    /// Defaults overridden by `FUSTG_LOG_LEVEL`, `FUSTG_LOG_FORMAT` (`text`|`json`), `FUSTG_LOG_FILE`,
+    /// `FUSTG_LOG_RING` (capacity) and `FUSTG_LOG_SAMPLE_DEBUG`.
+    pub fn from_env() -> Self {
+        let mut cfg = Self::default();
+        if let Ok(level) = std::env::var("FUSTG_LOG_LEVEL") {
+            cfg.level = level;
+        }
+        if let Ok(format) = std::env::var("FUSTG_LOG_FORMAT") {
+            cfg.format = if format.eq_ignore_ascii_case("json") {
+                LogFormat::Json
+            } else {
+                LogFormat::Text
+            };
+        }
+        if let Ok(file) = std::env::var("FUSTG_LOG_FILE") {
+            cfg.file = Some(file.into());
+        }
+        if let Some(capacity) = std::env::var("FUSTG_LOG_RING").ok().and_then(|v| v.parse().ok()) {
+            cfg.ring_capacity = capacity;
+        }
+        if let Some(every) = std::env::var("FUSTG_LOG_SAMPLE_DEBUG").ok().and_then(|v| v.parse().ok()) {
+            cfg.sample_debug = every;
+        }
+        cfg
+    }
+}
+
+/// Formatted lines waiting for the logger thread.
+#[derive(Clone)]
+pub struct LogRing {
+    lines: Arc<ArrayQueue<Vec<u8>>>,
+    /// written buffers, emptied, for the next lines
+    free: Arc<ArrayQueue<Vec<u8>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LogRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(ArrayQueue::new(capacity)),
+            free: Arc::new(ArrayQueue::new(capacity)),
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Lines dropped on a full ring and not yet reported.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Start the logger thread draining the ring into `sink`.
+    pub fn spawn(&self, mut sink: Box<dyn Write + Send>) -> io::Result<LogGuard> {
+        let ring = self.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopping = stop.clone();
+        let handle = thread::Builder::new().name("logger".into()).spawn(move || {
+            loop {
+                // read the flag first so lines pushed before the stop are still drained
+                let stopped = stopping.load(Ordering::Acquire);
+                while let Some(line) = ring.lines.pop() {
+                    let _ = sink.write_all(&line);
+                    ring.recycle(line);
+                }
+                let dropped = ring.dropped.swap(0, Ordering::Relaxed);
+                if dropped > 0 {
+                    let _ = writeln!(sink, "logging: {} lines dropped, ring full", dropped);
+                }
+                let _ = sink.flush();
+                if stopped {
+                    break;
+                }
+                thread::park_timeout(IDLE);
+            }
+        })?;
+        Ok(LogGuard { stop, handle: Some(handle) })
+    }
+
+    fn recycle(&self, mut line: Vec<u8>) {
+        if line.capacity() <= MAX_RECYCLED {
+            line.clear();
+            let _ = self.free.push(line);
+        }
+    }
+}
+
+impl Write for LogRing {
+    /// `fmt` hands over a whole formatted event in one write.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut line = self.free.pop().unwrap_or_default();
+        line.extend_from_slice(buf);
+        if let Err(line) = self.lines.push(line) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            self.recycle(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogRing {
+    type Writer = LogRing;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Keeps the logger thread running; dropping it drains what is left and stops the thread.
+/// A synchronous setup has no thread and the guard does nothing.
+#[must_use = "dropping the guard stops the logger thread"]
+pub struct LogGuard {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Lets through one in `every` DEBUG and TRACE events; INFO and above always pass.
+struct Sample {
+    every: u64,
+    seen: AtomicU64,
+}
+
+impl<S: Subscriber> Layer<S> for Sample {
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        *event.metadata().level() < Level::DEBUG || self.seen.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.every)
+    }
+}
+
+/// Install the global `tracing` subscriber. Call once, before the engine is built, and keep
+/// the guard until the process exits.
+pub fn init(cfg: &LogConfig) -> Result<LogGuard> {
+    let filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&cfg.level))?;
+    let sink: Box<dyn Write + Send> = match &cfg.file {
+        Some(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let (writer, guard) = if cfg.ring_capacity > 0 {
+        let ring = LogRing::new(cfg.ring_capacity);
+        let guard = ring.spawn(sink)?;
+        (BoxMakeWriter::new(ring), guard)
+    } else {
+        let guard = LogGuard {
+            stop: Arc::new(AtomicBool::new(true)),
+            handle: None,
+        };
+        (BoxMakeWriter::new(Mutex::new(sink)), guard)
+    };
+    let layer = fmt::layer().with_thread_names(true).with_ansi(cfg.file.is_none()).with_writer(writer);
+    let layer = match cfg.format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    };
+    let sample = (cfg.sample_debug > 1).then(|| Sample {
+        every: cfg.sample_debug as u64,
+        seen: AtomicU64::new(0),
+    });
+    tracing_subscriber::registry().with(filter).with(sample).with(layer).try_init()?;
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects what the logger thread writes.
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Collect {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn drops_on_a_full_ring_and_drains_on_drop() {
+        let ring = LogRing::new(2);
+        let mut writer = ring.make_writer();
+        for line in ["one\n", "two\n", "three\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        assert_eq!(ring.dropped(), 1);
+
+        let out = Collect::default();
+        drop(ring.spawn(Box::new(out.clone())).unwrap());
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(text, "one\ntwo\nlogging: 1 lines dropped, ring full\n");
+        assert_eq!(ring.dropped(), 0);
+    }
+
+    #[test]
+    fn reuses_written_line_buffers() {
+        let ring = LogRing::new(2);
+        let mut writer = ring.make_writer();
+        writer.write_all(b"one\n").unwrap();
+        let out = Collect::default();
+        drop(ring.spawn(Box::new(out.clone())).unwrap());
+        assert_eq!(ring.free.len(), 1);
+
+        writer.write_all(b"two\n").unwrap();
+        assert_eq!((ring.free.len(), ring.lines.len()), (0, 1));
+        assert_eq!(ring.lines.pop().unwrap(), b"two\n");
+    }
+}
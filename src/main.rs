@@ -1,63 +1,305 @@
 use ctrlc;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::{env, process, thread};
+use tracing::{error, info, warn};
 
-mod config;
-mod engine;
-mod operator;
-mod perf_tracker;
-mod strategies;
-mod strategy;
-mod types;
+use fustg_rs::bars::{BarPeriod, BarStore};
+use fustg_rs::budget::{CountingAllocator, ResourceBudget};
+use fustg_rs::calendar::{SessionFilter, TradingCalendar};
+use fustg_rs::config::{EngineConfig, MissingContract, load_fee_models, load_fees, load_tick_rates};
+use fustg_rs::error::EngineError;
+use fustg_rs::feed::ReceiveMode;
+use fustg_rs::handoff::Handoff;
+use fustg_rs::journal;
+use fustg_rs::logging;
+use fustg_rs::order_queue::QueueConfig;
+use fustg_rs::report::{self, ReportConfig};
+use fustg_rs::router::TickRateRouter;
+use fustg_rs::skew::SkewConfig;
+use fustg_rs::strategies;
+use fustg_rs::validate::ValidationReport;
+use fustg_rs::warmup::{BarTicks, RecordedTicks, WarmupSource};
+use fustg_rs::watchdog::WatchdogConfig;
+use fustg_rs::{CtaEngine, SymbolType};
 
-use engine::CtaEngine;
-use strategies::Aberration;
-use types::SymbolType;
-
-use config::load_fees;
-use perf_tracker::PerformanceTracker;
+// counts allocations per thread for the strategies' resource budgets
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
 
 fn main() {
-    // Register a Ctrl-C handler that just flips `running` to false.
+    // `--validate`: check the configuration, print a report and exit without trading
+    let validate_only = env::args().skip(1).any(|arg| arg == "--validate");
+    // `--resume-from <handoff.json>`: take over the positions a previous run handed off
+    let resume_from = env::args().skip_while(|arg| arg != "--resume-from").nth(1);
+    let config_path = env::var("FUSTG_CONFIG").unwrap_or_else(|_| "config/engine.toml".into());
+    let config = EngineConfig::load(&config_path).unwrap_or_else(|e| {
+        eprintln!("Failed to load {}: {:#}", config_path, e);
+        process::exit(2);
+    });
+    let log = logging::LogConfig {
+        level: config.log_level.clone(),
+        ..logging::LogConfig::from_env()
+    };
+    let log_guard = logging::init(&log).expect("init logging");
+    // under `--validate`, setup problems are collected here and reported with the engine's
+    // checks, and nothing that binds, opens or recovers state is set up
+    let mut setup = validate_only.then(ValidationReport::default);
+
+    // Register a Ctrl-C handler that just sets the engine's shutdown flag.
+    let shutdown = Arc::new(AtomicBool::new(false));
     {
+        let stopped = Arc::clone(&shutdown);
         ctrlc::set_handler(move || {
-            println!("trigger Ctrl-C");
+            info!("trigger Ctrl-C");
+            stopped.store(true, Ordering::Relaxed);
         })
         .expect("Error setting Ctrl-C handler");
     }
 
     // Build the engine, passing in the shared flag
-    let mut engine = CtaEngine::new("ipc://@hq", "ipc://@orders", 4);
+    let tick_uris: Vec<&str> = config.tick_uris.iter().map(String::as_str).collect();
+    let mut engine = CtaEngine::new(&tick_uris, &config.order_uri, config.num_workers).unwrap_or_else(|e| fatal(e));
+    engine.set_wire_format(config.wire_format);
+    engine.set_shutdown_flag(Arc::clone(&shutdown));
+    engine.set_reconnect_policy(config.reconnect);
+    if let Some(shard) = config.shard {
+        engine.set_shard(shard);
+    }
+    if let Some(busy) = config.busy_poll {
+        engine.set_receive_mode(ReceiveMode::BusyPoll(busy));
+    }
+    if let Some(ref dir) = config.record_dir {
+        engine.enable_recorder(dir);
+    }
+    if let Some(ref addr) = config.metrics_addr {
+        engine.enable_metrics(addr);
+    }
+    #[cfg(feature = "dashboard")]
+    if let Some(ref addr) = config.dashboard_addr {
+        engine.enable_dashboard(addr);
+    }
+    if let Some(ref mut report) = setup {
+        for (what, uri) in [
+            ("control", &config.control_uri),
+            ("query", &config.query_uri),
+            ("heartbeat", &config.heartbeat_uri),
+        ] {
+            if let Some(uri) = uri {
+                report.ok("endpoint", format!("{} {} is not bound by --validate", what, uri));
+            }
+        }
+    } else {
+        if let Some(ref uri) = config.control_uri {
+            engine.enable_control(uri).unwrap_or_else(|e| fatal(e));
+        }
+        if let Some(ref uri) = config.query_uri {
+            engine.enable_query(uri).unwrap_or_else(|e| fatal(e));
+        }
+        if let Some(ref uri) = config.heartbeat_uri {
+            engine
+                .enable_heartbeat(uri, &config.engine_id, Duration::from_secs(1))
+                .unwrap_or_else(|e| fatal(e));
+        }
+        if let Some(ref path) = config.audit_log {
+            engine.enable_audit_log(path).unwrap_or_else(|e| fatal(e));
+        }
+        if let Some(ref curve) = config.equity_curve
+            && let Err(e) = engine.enable_equity_curve(curve.clone())
+        {
+            warn!(error = %e, "equity curve files unavailable");
+        }
+    }
+    if let Some(limits) = config.churn_limits {
+        engine.enable_churn_limits(limits);
+    }
+    if let Some(ref path) = config.handoff_path {
+        engine.enable_handoff(path);
+    }
+    engine.enable_watchdog(WatchdogConfig::default());
+    engine.enable_skew_monitor(SkewConfig::default());
+    if let Some(ref path) = config.calendar_path
+        && let Some(calendar) = check(setup.as_mut(), "calendar", path, TradingCalendar::load(path))
+    {
+        engine.enable_calendar(calendar, SessionFilter::Flag);
+    }
+
+    let contracts = check(setup.as_mut(), "contract", &config.fees_path, load_fees(&config.fees_path)).unwrap_or_default();
+    // strategies look up their fees here; a missing contract blocks the symbol's orders
+    engine.set_contracts(contracts, config.init_cash);
+    engine.set_account_currency(config.currency);
+    engine.set_symbol_map(config.symbol_map.clone());
+    if let Some(ref rollover) = config.rollover {
+        engine.enable_rollover(rollover.build());
+    }
+    // exchanges change margins intraday; follow edits to the table
+    if setup.is_none()
+        && let Err(e) = engine.enable_fees_reload(&config.fees_path)
+    {
+        warn!(error = %e, "fees hot reload unavailable");
+    }
+    if let Some(ref path) = config.instrument_fees_path
+        && let Some(models) = check(setup.as_mut(), "contract", path, load_fee_models(path))
+    {
+        engine.set_fee_models(models);
+    }
+    engine.set_missing_contract(MissingContract::Block);
+    engine.enable_resource_budget(ResourceBudget::default());
+    engine.enable_order_queue(QueueConfig::default());
+    // a year of continuous daily bars for the risk report's covariances and strategies' warm-up
+    if let Some(ref dir) = config.bar_dir {
+        engine.enable_risk_history(dir, 250);
+        engine.set_history(dir);
+    }
+    // run strategies through recent history first, so long windows are full on the first live tick
+    if config.warmup_ticks > 0 {
+        let source: Option<Arc<dyn WarmupSource>> = match (&config.record_dir, &config.bar_dir) {
+            (Some(dir), _) => Some(Arc::new(RecordedTicks::new(dir))),
+            (None, Some(dir)) => Some(Arc::new(BarTicks::new(BarStore::new(dir), BarPeriod::Minute))),
+            (None, None) => None,
+        };
+        match source {
+            Some(source) => engine.enable_warmup(source, config.warmup_ticks),
+            None => warn!("warmup_ticks set without record_dir or bar_dir to warm up from"),
+        }
+    }
 
-    let mut contracts = load_fees("config/fees.1st.toml").expect("load fees toml success");
+    // settlement report: always saved, and sent on when a webhook or mail address is configured
+    engine.enable_daily_report(ReportConfig::default());
+    let webhook = env::var("FUSTG_REPORT_WEBHOOK").ok();
+    let mail_to = env::var("FUSTG_REPORT_MAIL").ok();
+    let report_dir = config.report_dir.clone();
+    engine.on_daily_report(move |daily| {
+        if let Some(ref dir) = report_dir
+            && let Err(e) = report::save(dir, daily)
+        {
+            warn!(error = ?e, "failed to save the daily report");
+        }
+        let (daily, webhook, mail_to) = (daily.clone(), webhook.clone(), mail_to.clone());
+        thread::spawn(move || {
+            if let Some(url) = webhook
+                && let Err(e) = report::post_webhook(&url, "text/markdown; charset=utf-8", &daily.markdown())
+            {
+                warn!(error = ?e, "failed to post the daily report");
+            }
+            if let Some(to) = mail_to
+                && let Err(e) = report::send_mail(&to, &daily)
+            {
+                warn!(error = ?e, "failed to mail the daily report");
+            }
+        });
+    });
+    if let Some(ref dir) = config.statement_dir {
+        engine.enable_statement_export(dir);
+    }
 
-    // Add some strategies
-    if let Some(contract) = contracts.remove("SHFE.rb") {
-        engine.add_strategy(
-            SymbolType::from("rb2505"),
-            Box::new(Aberration::new(100)),
-            PerformanceTracker::new(1e6, contract),
-        );
+    for spec in &config.strategies {
+        let what = format!("{} on {}", spec.kind, spec.symbol);
+        let Some(strategy) = check(setup.as_mut(), "strategy", &what, strategies::create(&spec.kind, &spec.args)) else {
+            continue;
+        };
+        if let Some(limits) = spec.limits {
+            engine.set_strategy_limits(strategy.name().as_str(), limits);
+        }
+        match spec.contract {
+            Some(ref contract) => engine.add_contract_strategy(SymbolType::from(spec.symbol.as_str()), contract, strategy),
+            None => {
+                let added = engine.add_symbol_strategy(&spec.symbol, strategy);
+                check(setup.as_mut(), "strategy", &what, added);
+            }
+        }
     }
-    if let Some(contract) = contracts.remove("CZCE.MA") {
-        engine.add_strategy(
-            SymbolType::from("MA505"),
-            Box::new(Aberration::new(200)),
-            PerformanceTracker::new(1e6, contract),
-        );
+
+    // spread the busiest symbols across workers by their usual tick rate
+    if let Some(ref path) = config.tick_rates_path
+        && let Ok(rates) = load_tick_rates(path)
+    {
+        let rates = rates.iter().map(|(symbol, &rate)| (SymbolType::from(symbol.as_str()), rate)).collect();
+        engine.set_router(Box::new(TickRateRouter::new(rates, 1.0)));
+    }
+
+    if let Some(mut report) = setup {
+        // read what a start would recover from, without taking it over
+        if let Some(ref path) = resume_from
+            && let Some(handoff) = check(Some(&mut report), "recovery", path, Handoff::load(path))
+        {
+            report.ok("recovery", format!("handoff {} holds {} strategies", path, handoff.strategies.len()));
+        }
+        if let Some(ref path) = config.journal_path
+            && resume_from.is_none()
+            && Path::new(path).exists()
+            && let Some(entries) = check(Some(&mut report), "recovery", path, journal::read(path))
+        {
+            report.ok("recovery", format!("journal {} holds {} entries", path, entries.len()));
+        }
+        report.checks.extend(engine.validate().checks);
+        println!("{}", report);
+        // exit skips destructors: drain the log ring first
+        drop(log_guard);
+        process::exit(if report.passed() { 0 } else { 1 });
+    }
+
+    if let Some(ref path) = resume_from {
+        let count = check(setup.as_mut(), "recovery", path, engine.resume_from(path)).unwrap_or_default();
+        info!(path, strategies = count, "resuming from handoff");
     }
-    if let Some(contract) = contracts.remove("CZCE.MA") {
-        engine.add_strategy(
-            SymbolType::from("MA505"),
-            Box::new(Aberration::new(300)),
-            PerformanceTracker::new(1e6, contract),
-        );
+    if let Some(ref path) = config.journal_path {
+        // a handoff already holds what the journal would rebuild
+        if resume_from.is_none() && Path::new(path).exists() {
+            let count = check(setup.as_mut(), "recovery", path, engine.recover_from_journal(path)).unwrap_or_default();
+            info!(path, entries = count, "recovering from journal");
+        }
+        check(setup.as_mut(), "output", path, engine.enable_journal(path));
     }
 
     // Initialize worker threads, then enter the receive loop.
-    engine.init();
-    engine.start();
+    engine.init().unwrap_or_else(|e| fatal(e));
+    engine.start().unwrap_or_else(|e| fatal(e));
+
+    // Ctrl-C: send the closes before stop() drains the workers
+    if config.flatten_on_exit
+        && shutdown.load(Ordering::Relaxed)
+        && let Err(e) = engine.flatten_all()
+    {
+        warn!(error = %e, "failed to flatten on exit");
+    }
 
     // Once start() returns (because running was set to false), call stop()
-    engine.stop();
+    if let Err(e) = engine.stop() {
+        error!(error = %e, "engine stopped uncleanly");
+        drop(log_guard);
+        process::exit(1);
+    }
+
+    info!("engine has shut down, exiting main()");
+}
+
+/// `result`'s value. An error is fatal, except under `--validate` (`setup` is `Some`), where
+/// it is noted as a failure of `area` and reported with the rest.
+fn check<T, E: fmt::Display>(setup: Option<&mut ValidationReport>, area: &'static str, what: impl fmt::Display, result: Result<T, E>) -> Option<T> {
+    let e = match result {
+        Ok(value) => return Some(value),
+        Err(e) => e,
+    };
+    match setup {
+        Some(report) => {
+            report.fail(area, format!("{}: {:#}", what, e));
+            None
+        }
+        None => {
+            error!(error = %e, %what, "engine setup failed");
+            eprintln!("fustg_rs: {}: {:#}", what, e);
+            process::exit(1);
+        }
+    }
+}
 
-    println!("Engine has shut down. Exiting main().");
+/// The engine could not be set up or run: say why and exit.
+fn fatal(e: EngineError) -> ! {
+    error!(error = %e, "engine failed");
+    eprintln!("fustg_rs: {}", e);
+    process::exit(1);
 }
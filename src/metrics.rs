@@ -0,0 +1,521 @@
+use crate::latency::{self, LatencyHistogram};
+use crate::order_stats::OrderStats;
+use crate::types::SymbolType;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Counters owned by one worker thread.
+#[derive(Default)]
+pub struct WorkerMetrics {
+    /// messages waiting in the worker's queue, as of the last tick sent to it
+    pub queue_depth: AtomicI64,
+    /// ticks that found the worker's queue full and waited for room
+    pub queue_stalls: AtomicU64,
+    pub ticks_processed: AtomicU64,
+    /// time spent running strategies + trackers for one tick
+    pub loop_nanos_total: AtomicU64,
+    pub loop_nanos_max: AtomicU64,
+    /// tick dispatch to the worker starting on it
+    pub dispatch_strategy: LatencyHistogram,
+    /// the worker starting on a tick to each order it leads to being sent
+    pub strategy_send: LatencyHistogram,
+}
+
+impl WorkerMetrics {
+    pub fn observe_loop(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos() as u64;
+        self.ticks_processed.fetch_add(1, Ordering::Relaxed);
+        self.loop_nanos_total.fetch_add(nanos, Ordering::Relaxed);
+        self.loop_nanos_max.fetch_max(nanos, Ordering::Relaxed);
+    }
+}
+
+/// Engine-wide counters, shared by the receive loop, the workers and the `/metrics` server.
+pub struct EngineMetrics {
+    pub ticks_received: AtomicU64,
+    pub frames_quarantined: AtomicU64,
+    /// switches of the active tick feed
+    pub feed_failovers: AtomicU64,
+    /// tick feed sockets rebuilt after an error
+    pub feed_reconnects: AtomicU64,
+    pub orders_sent: AtomicU64,
+    pub cancels_sent: AtomicU64,
+    /// ticks that lagged the wall clock past the watchdog threshold
+    pub stale_ticks: AtomicU64,
+    /// times a symbol went silent in session
+    pub data_gaps: AtomicU64,
+    pub silent_symbols: AtomicI64,
+    /// ticks stamped outside their symbol's trading sessions
+    pub out_of_session_ticks: AtomicU64,
+    /// times a strategy drifted from its backtest tracking profile
+    pub tracking_alerts: AtomicU64,
+    /// times a symbol's clock skew passed the threshold
+    pub clock_skew_alerts: AtomicU64,
+    /// strategies downgraded to subsampled ticks for exceeding their resource budget
+    pub budget_downgrades: AtomicU64,
+    /// circuit breaker trips, per strategy or engine-wide
+    pub breaker_trips: AtomicU64,
+    /// opening orders refused at a strategy's position limits
+    pub limit_breaches: AtomicU64,
+    /// strategies liquidated after their equity no longer covered their margin
+    pub margin_calls: AtomicU64,
+    /// orders held back by the order queue's throttle or a pause
+    pub orders_queued: AtomicU64,
+    /// queued orders dropped as stale, displaced or without room
+    pub queued_orders_dropped: AtomicU64,
+    /// breaks in the feed handler's per-symbol sequence, and the ticks they skipped
+    pub sequence_gaps: AtomicU64,
+    pub ticks_missed: AtomicU64,
+    /// feed handler receive to engine receive, over ticks that carry a receive time
+    pub feed_latency_nanos_total: AtomicU64,
+    pub feed_latency_samples: AtomicU64,
+    pub feed_latency_nanos_max: AtomicU64,
+    /// tick receive to dispatch to its worker
+    pub recv_dispatch: LatencyHistogram,
+    symbol_ticks: RwLock<HashMap<SymbolType, AtomicU64>>,
+    /// smoothed receive-time minus stamp skew per symbol, in ms
+    symbol_skew: RwLock<HashMap<SymbolType, AtomicI64>>,
+    /// each strategy's order flow, as its worker last published it
+    strategy_orders: RwLock<HashMap<String, Mutex<OrderStats>>>,
+    pub workers: Vec<WorkerMetrics>,
+}
+
+impl EngineMetrics {
+    pub fn new(num_workers: usize) -> Self {
+        Self {
+            ticks_received: AtomicU64::new(0),
+            frames_quarantined: AtomicU64::new(0),
+            feed_failovers: AtomicU64::new(0),
+            feed_reconnects: AtomicU64::new(0),
+            orders_sent: AtomicU64::new(0),
+            cancels_sent: AtomicU64::new(0),
+            stale_ticks: AtomicU64::new(0),
+            data_gaps: AtomicU64::new(0),
+            silent_symbols: AtomicI64::new(0),
+            out_of_session_ticks: AtomicU64::new(0),
+            tracking_alerts: AtomicU64::new(0),
+            clock_skew_alerts: AtomicU64::new(0),
+            budget_downgrades: AtomicU64::new(0),
+            breaker_trips: AtomicU64::new(0),
+            limit_breaches: AtomicU64::new(0),
+            margin_calls: AtomicU64::new(0),
+            orders_queued: AtomicU64::new(0),
+            queued_orders_dropped: AtomicU64::new(0),
+            sequence_gaps: AtomicU64::new(0),
+            ticks_missed: AtomicU64::new(0),
+            feed_latency_nanos_total: AtomicU64::new(0),
+            feed_latency_samples: AtomicU64::new(0),
+            feed_latency_nanos_max: AtomicU64::new(0),
+            recv_dispatch: LatencyHistogram::default(),
+            symbol_ticks: RwLock::new(HashMap::new()),
+            symbol_skew: RwLock::new(HashMap::new()),
+            strategy_orders: RwLock::new(HashMap::new()),
+            workers: (0..num_workers).map(|_| WorkerMetrics::default()).collect(),
+        }
+    }
+
+    pub fn on_tick(&self, symbol: &SymbolType) {
+        self.ticks_received.fetch_add(1, Ordering::Relaxed);
+        if let Some(count) = self.symbol_ticks.read().unwrap().get(symbol) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        // first tick of this symbol
+        self.symbol_ticks
+            .write()
+            .unwrap()
+            .entry(*symbol)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_feed_latency(&self, nanos: u64) {
+        self.feed_latency_samples.fetch_add(1, Ordering::Relaxed);
+        self.feed_latency_nanos_total.fetch_add(nanos, Ordering::Relaxed);
+        self.feed_latency_nanos_max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    pub fn set_skew(&self, symbol: &SymbolType, skew_ms: i64) {
+        if let Some(skew) = self.symbol_skew.read().unwrap().get(symbol) {
+            skew.store(skew_ms, Ordering::Relaxed);
+            return;
+        }
+        self.symbol_skew
+            .write()
+            .unwrap()
+            .entry(*symbol)
+            .or_default()
+            .store(skew_ms, Ordering::Relaxed);
+    }
+
+    pub fn set_order_stats(&self, strategy: &str, stats: OrderStats) {
+        if let Some(slot) = self.strategy_orders.read().unwrap().get(strategy) {
+            *slot.lock().unwrap() = stats;
+            return;
+        }
+        self.strategy_orders.write().unwrap().insert(strategy.to_string(), Mutex::new(stats));
+    }
+
+    /// Ticks received so far per symbol.
+    pub fn symbol_tick_counts(&self) -> Vec<(SymbolType, u64)> {
+        let counts = self.symbol_ticks.read().unwrap();
+        counts.iter().map(|(symbol, count)| (*symbol, count.load(Ordering::Relaxed))).collect()
+    }
+
+    /// Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let name = header(&mut out, "fustg_ticks_received_total", "counter", "Ticks decoded from the SUB socket.");
+        let _ = writeln!(out, "{} {}", name, self.ticks_received.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_frames_quarantined_total",
+            "counter",
+            "Frames of unknown size that were not decoded.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.frames_quarantined.load(Ordering::Relaxed));
+
+        let name = header(&mut out, "fustg_feed_failovers_total", "counter", "Switches of the active tick feed.");
+        let _ = writeln!(out, "{} {}", name, self.feed_failovers.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_feed_reconnects_total",
+            "counter",
+            "Tick feed sockets rebuilt after an error.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.feed_reconnects.load(Ordering::Relaxed));
+
+        let name = header(&mut out, "fustg_orders_sent_total", "counter", "Orders pushed to the order socket.");
+        let _ = writeln!(out, "{} {}", name, self.orders_sent.load(Ordering::Relaxed));
+
+        let name = header(&mut out, "fustg_cancels_sent_total", "counter", "Cancels pushed to the order socket.");
+        let _ = writeln!(out, "{} {}", name, self.cancels_sent.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_stale_ticks_total",
+            "counter",
+            "Ticks that arrived later than the staleness threshold.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.stale_ticks.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_data_gaps_total",
+            "counter",
+            "Times a symbol went silent during trading hours.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.data_gaps.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_silent_symbols",
+            "gauge",
+            "Subscribed symbols currently silent in session.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.silent_symbols.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_out_of_session_ticks_total",
+            "counter",
+            "Ticks stamped outside their symbol's trading sessions.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.out_of_session_ticks.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_tracking_alerts_total",
+            "counter",
+            "Times a strategy drifted from its backtest tracking profile.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.tracking_alerts.load(Ordering::Relaxed));
+
+        let name = header(&mut out, "fustg_symbol_ticks_total", "counter", "Ticks received per symbol.");
+        for (symbol, count) in self.symbol_ticks.read().unwrap().iter() {
+            let _ = writeln!(out, "{}{{symbol=\"{}\"}} {}", name, symbol.as_str(), count.load(Ordering::Relaxed));
+        }
+
+        let name = header(
+            &mut out,
+            "fustg_clock_skew_alerts_total",
+            "counter",
+            "Times a symbol's clock skew passed the threshold.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.clock_skew_alerts.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_budget_downgrades_total",
+            "counter",
+            "Strategies downgraded to subsampled ticks for exceeding their resource budget.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.budget_downgrades.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_breaker_trips_total",
+            "counter",
+            "Circuit breaker trips, per strategy or engine-wide.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.breaker_trips.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_limit_breaches_total",
+            "counter",
+            "Opening orders refused at a strategy's position limits.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.limit_breaches.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_margin_calls_total",
+            "counter",
+            "Strategies liquidated after their equity no longer covered their margin.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.margin_calls.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_orders_queued_total",
+            "counter",
+            "Orders held back by the order queue's throttle or a pause.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.orders_queued.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_queued_orders_dropped_total",
+            "counter",
+            "Queued orders dropped as stale, displaced or without room.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.queued_orders_dropped.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_sequence_gaps_total",
+            "counter",
+            "Breaks in the feed handler's per-symbol tick sequence.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.sequence_gaps.load(Ordering::Relaxed));
+
+        let name = header(&mut out, "fustg_ticks_missed_total", "counter", "Ticks skipped by sequence gaps.");
+        let _ = writeln!(out, "{} {}", name, self.ticks_missed.load(Ordering::Relaxed));
+
+        let name = header(
+            &mut out,
+            "fustg_feed_latency_seconds",
+            "summary",
+            "Time from the feed handler receiving a tick to the engine receiving it.",
+        );
+        let total = self.feed_latency_nanos_total.load(Ordering::Relaxed) as f64 / 1e9;
+        let _ = writeln!(out, "{}_sum {}", name, total);
+        let _ = writeln!(out, "{}_count {}", name, self.feed_latency_samples.load(Ordering::Relaxed));
+        let name = header(
+            &mut out,
+            "fustg_feed_latency_seconds_max",
+            "gauge",
+            "Longest feed handler to engine latency seen.",
+        );
+        let _ = writeln!(out, "{} {}", name, self.feed_latency_nanos_max.load(Ordering::Relaxed) as f64 / 1e9);
+
+        let name = header(
+            &mut out,
+            "fustg_latency_seconds",
+            "summary",
+            "Hot-path latency per stage: receive to dispatch, dispatch to strategy, strategy to send.",
+        );
+        let stages = [
+            ("recv_dispatch", latency::merged([&self.recv_dispatch])),
+            ("dispatch_strategy", latency::merged(self.workers.iter().map(|w| &w.dispatch_strategy))),
+            ("strategy_send", latency::merged(self.workers.iter().map(|w| &w.strategy_send))),
+        ];
+        for (stage, histogram) in &stages {
+            for q in latency::QUANTILES {
+                let seconds = histogram.value_at_quantile(q) as f64 / 1e9;
+                let _ = writeln!(out, "{}{{stage=\"{}\",quantile=\"{}\"}} {}", name, stage, q, seconds);
+            }
+            let sum = histogram.mean() * histogram.len() as f64 / 1e9;
+            let _ = writeln!(out, "{}_sum{{stage=\"{}\"}} {}", name, stage, sum);
+            let _ = writeln!(out, "{}_count{{stage=\"{}\"}} {}", name, stage, histogram.len());
+        }
+
+        let name = header(
+            &mut out,
+            "fustg_clock_skew_ms",
+            "gauge",
+            "Smoothed receive time minus tick stamp per symbol.",
+        );
+        for (symbol, skew) in self.symbol_skew.read().unwrap().iter() {
+            let _ = writeln!(out, "{}{{symbol=\"{}\"}} {}", name, symbol.as_str(), skew.load(Ordering::Relaxed));
+        }
+
+        let strategy_orders = self.strategy_orders.read().unwrap();
+        let order_stats: Vec<(&String, OrderStats)> = strategy_orders.iter().map(|(s, stats)| (s, *stats.lock().unwrap())).collect();
+        // name, type, help and the value of one strategy's stats
+        type Family = (&'static str, &'static str, &'static str, fn(&OrderStats) -> f64);
+        let families: [Family; 5] = [
+            ("fustg_strategy_orders_total", "counter", "Orders sent per strategy.", |s| s.orders as f64),
+            ("fustg_strategy_cancels_total", "counter", "Cancels sent per strategy.", |s| {
+                s.cancels as f64
+            }),
+            ("fustg_strategy_fills_total", "counter", "Fills booked per strategy.", |s| s.fills as f64),
+            (
+                "fustg_strategy_order_to_fill",
+                "gauge",
+                "Orders per fill per strategy.",
+                OrderStats::order_to_fill,
+            ),
+            (
+                "fustg_strategy_churn_throttles_total",
+                "counter",
+                "Times a strategy's opening orders were throttled for churning.",
+                |s| s.throttles as f64,
+            ),
+        ];
+        for (family, kind, help, value) in families {
+            let name = header(&mut out, family, kind, help);
+            for (strategy, stats) in &order_stats {
+                let _ = writeln!(out, "{}{{strategy=\"{}\"}} {}", name, strategy, value(stats));
+            }
+        }
+
+        let name = header(&mut out, "fustg_worker_queue_depth", "gauge", "Messages waiting in each worker's queue.");
+        for (id, w) in self.workers.iter().enumerate() {
+            let _ = writeln!(out, "{}{{worker=\"{}\"}} {}", name, id, w.queue_depth.load(Ordering::Relaxed));
+        }
+
+        let name = header(
+            &mut out,
+            "fustg_worker_queue_stalls_total",
+            "counter",
+            "Ticks that waited for room in a full worker queue.",
+        );
+        for (id, w) in self.workers.iter().enumerate() {
+            let _ = writeln!(out, "{}{{worker=\"{}\"}} {}", name, id, w.queue_stalls.load(Ordering::Relaxed));
+        }
+
+        let name = header(
+            &mut out,
+            "fustg_worker_loop_seconds",
+            "summary",
+            "Time spent processing one tick in a worker.",
+        );
+        for (id, w) in self.workers.iter().enumerate() {
+            let total = w.loop_nanos_total.load(Ordering::Relaxed) as f64 / 1e9;
+            let count = w.ticks_processed.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_sum{{worker=\"{}\"}} {}", name, id, total);
+            let _ = writeln!(out, "{}_count{{worker=\"{}\"}} {}", name, id, count);
+        }
+
+        let name = header(
+            &mut out,
+            "fustg_worker_loop_seconds_max",
+            "gauge",
+            "Slowest single tick processed by each worker.",
+        );
+        for (id, w) in self.workers.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}{{worker=\"{}\"}} {}",
+                name,
+                id,
+                w.loop_nanos_max.load(Ordering::Relaxed) as f64 / 1e9
+            );
+        }
+
+        out
+    }
+}
+
+/// Write the HELP/TYPE preamble of a metric family and return its name.
+fn header<'a>(out: &mut String, name: &'a str, kind: &str, help: &str) -> &'a str {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    name
+}
+
+/// Serve `GET /metrics` on `addr` from a background thread for the life of the process.
+pub fn serve(addr: &str, metrics: Arc<EngineMetrics>) -> io::Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    info!(addr, "serving metrics on /metrics");
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = respond(stream, &metrics) {
+                        warn!(error = ?e, "failed to answer metrics request");
+                    }
+                }
+                Err(e) => warn!(error = ?e, "failed to accept metrics connection"),
+            }
+        }
+    }))
+}
+
+fn respond(mut stream: TcpStream, metrics: &EngineMetrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let (status, body) = match request_line.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", metrics.render()),
+        _ => ("404 Not Found", String::from("not found\n")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_per_symbol_and_per_strategy() {
+        let metrics = EngineMetrics::new(1);
+        let (rb, ma) = (SymbolType::from("rb2505"), SymbolType::from("MA505"));
+        for symbol in [rb, rb, ma] {
+            metrics.on_tick(&symbol);
+        }
+        metrics.orders_sent.fetch_add(4, Ordering::Relaxed);
+        metrics.budget_downgrades.fetch_add(1, Ordering::Relaxed);
+        let stats = OrderStats {
+            orders: 4,
+            fills: 2,
+            ..Default::default()
+        };
+        metrics.set_order_stats("Aberration10", stats);
+
+        let text = metrics.render();
+        let lines: Vec<&str> = text.lines().collect();
+        for expected in [
+            "fustg_ticks_received_total 3",
+            "fustg_symbol_ticks_total{symbol=\"rb2505\"} 2",
+            "fustg_symbol_ticks_total{symbol=\"MA505\"} 1",
+            "fustg_orders_sent_total 4",
+            "fustg_cancels_sent_total 0",
+            "fustg_budget_downgrades_total 1",
+            "fustg_strategy_orders_total{strategy=\"Aberration10\"} 4",
+            "fustg_strategy_fills_total{strategy=\"Aberration10\"} 2",
+            "fustg_strategy_order_to_fill{strategy=\"Aberration10\"} 2",
+            "# TYPE fustg_orders_sent_total counter",
+        ] {
+            assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, text);
+        }
+    }
+}
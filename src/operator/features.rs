@@ -0,0 +1,159 @@
+//! Order-flow features from the depth and cumulative fields of `TickData`: per-tick traded
+//! volume and amount, a rolling VWAP over them, bid-ask imbalance over the top levels, and
+//! the microprice.
+use super::rolling::{Mean, NanPolicy, Sum};
+use crate::types::TickData;
+
+/// A tick with what traded since the previous tick of its symbol.
+#[derive(Debug, Clone, Copy)]
+pub struct EnrichedTick {
+    pub tick: TickData,
+    /// lots traded since the previous tick
+    pub volume: i64,
+    /// turnover since the previous tick
+    pub amount: f64,
+}
+
+/// Per-tick volume and amount from the cumulative `TickData.volume` and `amount`, which
+/// count from the start of the trading day (the night session). A cumulative volume going
+/// backwards means a new day began, and the tick's whole volume traded since; so did the
+/// first tick seen, which has nothing earlier to compare with.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VolumeDelta {
+    /// cumulative volume and amount of the previous tick
+    prev: Option<(i64, f64)>,
+}
+
+impl VolumeDelta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Volume and amount traded since the previous tick.
+    pub fn update(&mut self, tick: &TickData) -> (i64, f64) {
+        match self.prev.replace((tick.volume, tick.amount)) {
+            Some((volume, amount)) if tick.volume >= volume => (tick.volume - volume, tick.amount - amount),
+            // first tick seen, or the day rolled over: what traded so far today
+            _ => (tick.volume, tick.amount),
+        }
+    }
+
+    pub fn enrich(&mut self, tick: &TickData) -> EnrichedTick {
+        let (volume, amount) = self.update(tick);
+        EnrichedTick { tick: *tick, volume, amount }
+    }
+}
+
+/// Volume-weighted average price of the trades in the last `n` ticks, from `VolumeDelta`.
+pub struct Vwap {
+    volume: Sum,
+    amount: Sum,
+    /// amount is price × lots × multiplier
+    multiplier: f64,
+    delta: VolumeDelta,
+}
+
+impl Vwap {
+    pub fn new(n: usize, multiplier: f64) -> Self {
+        Self {
+            volume: Sum::new(n).with_nan_policy(NanPolicy::Skip),
+            amount: Sum::new(n).with_nan_policy(NanPolicy::Skip),
+            multiplier,
+            delta: VolumeDelta::new(),
+        }
+    }
+
+    /// NAN while nothing traded within the window.
+    pub fn update(&mut self, tick: &TickData) -> f64 {
+        let (volume, amount) = self.delta.update(tick);
+        let volume = self.volume.update(volume as f64);
+        let amount = self.amount.update(amount);
+        if volume > 0.0 { amount / (volume * self.multiplier) } else { f64::NAN }
+    }
+}
+
+/// Resting bid size against ask size over the top `levels` (1 to 5), from −1 (all asks) to
+/// 1 (all bids); 0 for an empty book.
+pub fn book_imbalance(tick: &TickData, levels: usize) -> f64 {
+    let bids = [tick.bv1, tick.bv2, tick.bv3, tick.bv4, tick.bv5];
+    let asks = [tick.av1, tick.av2, tick.av3, tick.av4, tick.av5];
+    let levels = levels.clamp(1, 5);
+    let bid: f64 = bids[..levels].iter().map(|&v| v.max(0) as f64).sum();
+    let ask: f64 = asks[..levels].iter().map(|&v| v.max(0) as f64).sum();
+    if bid + ask > 0.0 { (bid - ask) / (bid + ask) } else { 0.0 }
+}
+
+/// Mid price weighted towards the side more likely to be traded through: the bid price
+/// weighted by the ask size and vice versa. Falls back to `last` when a side is empty.
+pub fn microprice(tick: &TickData) -> f64 {
+    let (bid, ask) = (tick.bv1.max(0) as f64, tick.av1.max(0) as f64);
+    if bid == 0.0 || ask == 0.0 {
+        return tick.last;
+    }
+    (tick.bp1 * ask + tick.ap1 * bid) / (bid + ask)
+}
+
+/// `book_imbalance` averaged over the last `n` ticks.
+pub struct Imbalance {
+    levels: usize,
+    mean: Mean,
+}
+
+impl Imbalance {
+    pub fn new(levels: usize, n: usize) -> Self {
+        Self { levels, mean: Mean::new(n) }
+    }
+
+    pub fn update(&mut self, tick: &TickData) -> f64 {
+        self.mean.update(book_imbalance(tick, self.levels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(volume: i64, amount: f64, bv: [i32; 2], av: [i32; 2]) -> TickData {
+        TickData {
+            last: 100.0,
+            volume,
+            amount,
+            bp1: 99.0,
+            ap1: 101.0,
+            bv1: bv[0],
+            bv2: bv[1],
+            av1: av[0],
+            av2: av[1],
+            ..TickData::default()
+        }
+    }
+
+    #[test]
+    fn vwap_imbalance_and_microprice() {
+        let mut delta = VolumeDelta::new();
+        assert_eq!(delta.update(&tick(10, 10_000.0, [1, 1], [1, 1])), (10, 10_000.0));
+        let enriched = delta.enrich(&tick(13, 13_060.0, [1, 1], [1, 1]));
+        assert_eq!((enriched.volume, enriched.amount, enriched.tick.volume), (3, 3_060.0, 13));
+        assert_eq!(delta.update(&tick(4, 4_000.0, [1, 1], [1, 1])), (4, 4_000.0));
+
+        let mut vwap = Vwap::new(2, 10.0);
+        assert_eq!(vwap.update(&tick(10, 10_000.0, [1, 1], [1, 1])), 100.0);
+        // 5 lots at 102
+        assert_eq!(vwap.update(&tick(15, 15_100.0, [1, 1], [1, 1])), 15_100.0 / 150.0);
+        // 5 more at 104; the first tick has left the window
+        assert_eq!(vwap.update(&tick(20, 20_300.0, [1, 1], [1, 1])), 103.0);
+        // a new day: 2 lots at 98 so far
+        assert_eq!(vwap.update(&tick(2, 1_960.0, [1, 1], [1, 1])), (5_200.0 + 1_960.0) / 70.0);
+
+        let book = tick(0, 0.0, [3, 5], [1, 1]);
+        assert_eq!(book_imbalance(&book, 1), 0.5);
+        assert_eq!(book_imbalance(&book, 2), 0.6);
+        // bids outweigh asks three to one: a quarter of the way from the ask
+        assert_eq!(microprice(&book), 100.5);
+        assert_eq!(microprice(&tick(0, 0.0, [0, 0], [2, 0])), 100.0);
+
+        let mut imbalance = Imbalance::new(1, 2);
+        imbalance.update(&book);
+        assert_eq!(imbalance.update(&tick(0, 0.0, [1, 0], [3, 0])), 0.0);
+    }
+}
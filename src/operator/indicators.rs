@@ -0,0 +1,271 @@
+//! Streaming technical indicators on top of the rolling primitives: feed each new price (or
+//! bar) to `update` and read the latest values back, instead of every strategy re-deriving
+//! MACD, RSI or KDJ from its own buffers. Conventions follow the Chinese charting packages
+//! (通达信/文华), so the values match what traders see on screen.
+use super::rolling::Container;
+
+/// Exponential moving average with smoothing factor `alpha`, seeded with the first value.
+#[derive(Debug, Clone, Copy)]
+pub struct Ema {
+    alpha: f64,
+    value: f64,
+}
+
+impl Ema {
+    /// The usual `n`-period average, alpha = 2 / (n + 1).
+    pub fn new(n: usize) -> Self {
+        Self::with_alpha(2.0 / (n as f64 + 1.0))
+    }
+
+    pub fn with_alpha(alpha: f64) -> Self {
+        Self { alpha, value: f64::NAN }
+    }
+
+    pub fn update(&mut self, new_val: f64) -> f64 {
+        self.value = if self.value.is_nan() {
+            new_val
+        } else {
+            self.value + self.alpha * (new_val - self.value)
+        };
+        self.value
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdConfig {
+    pub fast: usize,
+    pub slow: usize,
+    pub signal: usize,
+}
+
+impl Default for MacdConfig {
+    fn default() -> Self {
+        Self {
+            fast: 12,
+            slow: 26,
+            signal: 9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdValue {
+    /// fast EMA minus slow EMA
+    pub dif: f64,
+    /// signal line, the EMA of `dif`
+    pub dea: f64,
+    /// 2 × (dif − dea), the bars of the MACD chart
+    pub histogram: f64,
+}
+
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    latest: MacdValue,
+}
+
+impl Macd {
+    pub fn new(config: MacdConfig) -> Self {
+        Self {
+            fast: Ema::new(config.fast),
+            slow: Ema::new(config.slow),
+            signal: Ema::new(config.signal),
+            latest: MacdValue {
+                dif: f64::NAN,
+                dea: f64::NAN,
+                histogram: f64::NAN,
+            },
+        }
+    }
+
+    pub fn update(&mut self, price: f64) -> MacdValue {
+        let dif = self.fast.update(price) - self.slow.update(price);
+        let dea = self.signal.update(dif);
+        self.latest = MacdValue {
+            dif,
+            dea,
+            histogram: 2.0 * (dif - dea),
+        };
+        self.latest
+    }
+
+    pub fn latest(&self) -> MacdValue {
+        self.latest
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RsiConfig {
+    pub period: usize,
+}
+
+impl Default for RsiConfig {
+    fn default() -> Self {
+        Self { period: 14 }
+    }
+}
+
+/// Relative strength index, 0–100, with Wilder's smoothing of gains and losses
+/// (SMA(x, n, 1) in charting terms). NAN until `period` changes have been seen.
+pub struct Rsi {
+    gains: Ema,
+    losses: Ema,
+    period: usize,
+    changes: usize,
+    prev: f64,
+    latest: f64,
+}
+
+impl Rsi {
+    pub fn new(config: RsiConfig) -> Self {
+        let alpha = 1.0 / config.period as f64;
+        Self {
+            gains: Ema::with_alpha(alpha),
+            losses: Ema::with_alpha(alpha),
+            period: config.period,
+            changes: 0,
+            prev: f64::NAN,
+            latest: f64::NAN,
+        }
+    }
+
+    pub fn update(&mut self, price: f64) -> f64 {
+        let prev = std::mem::replace(&mut self.prev, price);
+        if prev.is_nan() {
+            return self.latest;
+        }
+        let change = price - prev;
+        let gain = self.gains.update(change.max(0.0));
+        let loss = self.losses.update((-change).max(0.0));
+        self.changes += 1;
+        if self.changes >= self.period {
+            self.latest = if gain + loss == 0.0 { 50.0 } else { 100.0 * gain / (gain + loss) };
+        }
+        self.latest
+    }
+
+    pub fn latest(&self) -> f64 {
+        self.latest
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdjConfig {
+    /// bars the high-low range is taken over
+    pub n: usize,
+    /// smoothing of K and of D
+    pub m1: usize,
+    pub m2: usize,
+}
+
+impl Default for KdjConfig {
+    fn default() -> Self {
+        Self { n: 9, m1: 3, m2: 3 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdjValue {
+    pub k: f64,
+    pub d: f64,
+    /// 3K − 2D
+    pub j: f64,
+}
+
+/// Stochastic KDJ: RSV places the close within the range of the last `n` bars, K smooths RSV
+/// and D smooths K, both starting from 50. Before `n` bars the range covers what has arrived.
+pub struct Kdj {
+    /// (high, low) of the last `n` bars
+    range: Container<(f64, f64)>,
+    k: Ema,
+    d: Ema,
+    latest: KdjValue,
+}
+
+impl Kdj {
+    pub fn new(config: KdjConfig) -> Self {
+        let mut k = Ema::with_alpha(1.0 / config.m1 as f64);
+        let mut d = Ema::with_alpha(1.0 / config.m2 as f64);
+        k.update(50.0);
+        d.update(50.0);
+        Self {
+            range: Container::with_fill(config.n, (f64::NAN, f64::NAN)),
+            k,
+            d,
+            latest: KdjValue { k: 50.0, d: 50.0, j: 50.0 },
+        }
+    }
+
+    pub fn update(&mut self, high: f64, low: f64, close: f64) -> KdjValue {
+        self.range.update((high, low));
+        // NAN slots of the warm-up drop out of max/min
+        let highest = self.range.iter().map(|r| r.0).fold(f64::NAN, f64::max);
+        let lowest = self.range.iter().map(|r| r.1).fold(f64::NAN, f64::min);
+        let rsv = if highest > lowest {
+            100.0 * (close - lowest) / (highest - lowest)
+        } else {
+            50.0
+        };
+        let k = self.k.update(rsv);
+        let d = self.d.update(k);
+        self.latest = KdjValue { k, d, j: 3.0 * k - 2.0 * d };
+        self.latest
+    }
+
+    /// Tick-driven use: each price is a bar of its own.
+    pub fn update_price(&mut self, price: f64) -> KdjValue {
+        self.update(price, price, price)
+    }
+
+    pub fn latest(&self) -> KdjValue {
+        self.latest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indicators_follow_the_charting_formulas() {
+        let prices = [10.0, 10.5, 10.2, 10.8, 11.0, 10.7, 11.3];
+
+        let mut macd = Macd::new(MacdConfig { fast: 2, slow: 4, signal: 3 });
+        let (mut fast, mut slow, mut dea) = (10.0, 10.0, 0.0);
+        for &p in &prices {
+            let value = macd.update(p);
+            fast += (p - fast) * 2.0 / 3.0;
+            slow += (p - slow) * 2.0 / 5.0;
+            dea += (fast - slow - dea) * 2.0 / 4.0;
+            assert!((value.dif - (fast - slow)).abs() < 1e-12);
+            assert!((value.histogram - 2.0 * (fast - slow - dea)).abs() < 1e-12);
+        }
+        assert!(macd.latest().dif > 0.0);
+
+        let mut rsi = Rsi::new(RsiConfig { period: 3 });
+        assert!(rsi.update(10.0).is_nan());
+        assert!(rsi.update(11.0).is_nan());
+        assert!(rsi.update(10.0).is_nan());
+        // gains 1, 0, 2 and losses 0, 1, 0, each smoothed with alpha 1/3 from its first
+        let gain = ((1.0 * 2.0 / 3.0) * 2.0 / 3.0) + 2.0 / 3.0;
+        let loss = (1.0 / 3.0) * 2.0 / 3.0;
+        assert!((rsi.update(12.0) - 100.0 * gain / (gain + loss)).abs() < 1e-12);
+
+        let mut kdj = Kdj::new(KdjConfig { n: 3, m1: 3, m2: 3 });
+        kdj.update(10.0, 9.0, 9.5);
+        kdj.update(11.0, 9.5, 10.5);
+        // range 9..11, close at 11 is the top: RSV 100
+        let value = kdj.update(11.0, 10.0, 11.0);
+        let k0 = 50.0 + (50.0 - 50.0) / 3.0;
+        let k1 = k0 + (75.0 - k0) / 3.0;
+        let k2 = k1 + (100.0 - k1) / 3.0;
+        assert!((value.k - k2).abs() < 1e-12);
+        assert!((value.j - (3.0 * value.k - 2.0 * value.d)).abs() < 1e-12);
+        assert!(value.k > value.d);
+    }
+}
@@ -1 +1,3 @@
+pub mod features;
+pub mod indicators;
 pub mod rolling;
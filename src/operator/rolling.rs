@@ -1,11 +1,11 @@
-pub struct ContainerIter<'a> {
-    buf: &'a [f64],
+pub struct ContainerIter<'a, T> {
+    buf: &'a [T],
     idx: usize,
     remaining: usize,
 }
 
-impl<'a> Iterator for ContainerIter<'a> {
-    type Item = &'a f64;
+impl<'a, T> Iterator for ContainerIter<'a, T> {
+    type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.remaining == 0 {
@@ -19,31 +19,61 @@ impl<'a> Iterator for ContainerIter<'a> {
     }
 }
 
-pub struct Container {
-    buf: Vec<f64>,
+/// Longest window a runtime parameter update may resize to.
+pub const MAX_WINDOW: usize = 100_000;
+
+/// The window length parameter `name` asks for with `value`: a whole number from `min` to
+/// `MAX_WINDOW`, checked before the cast so an update cannot ask for an unallocatable buffer.
+pub fn window_len(name: &str, value: f64, min: usize) -> Result<usize, String> {
+    if value.is_finite() && value.fract() == 0.0 && (min as f64..=MAX_WINDOW as f64).contains(&value) {
+        Ok(value as usize)
+    } else {
+        Err(format!("{} must be a whole number from {} to {}, got {}", name, min, MAX_WINDOW, value))
+    }
+}
+
+/// Fixed-length window over any `Copy` element, e.g. whole ticks or bars, so an operator
+/// needing several fields of each sample keeps one buffer instead of one per field. Slots
+/// not written yet hold the fill value, NAN for `f64`; `is_full` tells when the window has
+/// warmed up.
+pub struct Container<T: Copy = f64> {
+    buf: Vec<T>,
     head_idx: usize,
     tail_idx: usize,
+    fill: T,
+    /// values written by `update`, up to the window length
+    written: usize,
 }
 
-impl Container {
+impl Container<f64> {
     pub fn new(n: usize) -> Self {
+        Self::with_fill(n, f64::NAN)
+    }
+}
+
+impl<T: Copy> Container<T> {
+    /// A window of `n` slots holding `fill` until written.
+    pub fn with_fill(n: usize, fill: T) -> Self {
         Self {
-            buf: vec![f64::NAN; n],
+            buf: vec![fill; n],
             head_idx: 0,
             tail_idx: 0,
+            fill,
+            written: 0,
         }
     }
 
-    pub fn update(&mut self, new_val: f64) -> (f64, f64) {
+    pub fn update(&mut self, new_val: T) -> (T, T) {
         self.tail_idx = self.head_idx;
         self.buf[self.tail_idx] = new_val;
         self.head_idx = (self.head_idx + 1) % self.buf.len();
+        self.written = (self.written + 1).min(self.buf.len());
 
         // current_old, current_new after updated
         (self.buf[self.head_idx], self.buf[self.tail_idx])
     }
 
-    pub fn step(&mut self) -> (f64, f64) {
+    pub fn step(&mut self) -> (T, T) {
         self.tail_idx = self.head_idx;
         self.head_idx = (self.head_idx + 1) % self.buf.len();
 
@@ -51,16 +81,16 @@ impl Container {
         (self.buf[self.head_idx], self.buf[self.tail_idx])
     }
 
-    pub fn get(&self, idx: usize) -> f64 {
+    pub fn get(&self, idx: usize) -> T {
         // idx=0 is head; idx=n-1 is tail
         self.buf[(self.head_idx + idx) % self.buf.len()]
     }
 
-    pub fn head(&self) -> f64 {
+    pub fn head(&self) -> T {
         self.buf[self.head_idx]
     }
 
-    pub fn tail(&self) -> f64 {
+    pub fn tail(&self) -> T {
         self.buf[self.tail_idx]
     }
 
@@ -68,19 +98,120 @@ impl Container {
         self.buf.len()
     }
 
-    pub fn iter(&self) -> ContainerIter<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Values written so far, up to the window length; `step` writes none.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Every slot has been written since the window was created or last grown.
+    pub fn is_full(&self) -> bool {
+        self.written == self.buf.len()
+    }
+
+    pub fn iter(&self) -> ContainerIter<'_, T> {
         ContainerIter {
             buf: &self.buf,
             idx: self.head_idx,
             remaining: self.buf.len(),
         }
     }
+
+    /// Change the window length, keeping the most recent min(n, len) values.
+    /// Growing pads the oldest slots with the fill value.
+    pub fn resize(&mut self, n: usize) {
+        assert!(n > 0, "window length must be positive");
+        let kept = n.min(self.buf.len());
+        let mut buf = vec![self.fill; n];
+        // iter() runs oldest -> newest, so the last `kept` items are the most recent
+        for (dst, &val) in buf[n - kept..].iter_mut().zip(self.iter().skip(self.buf.len() - kept)) {
+            *dst = val;
+        }
+        self.buf = buf;
+        self.written = self.written.min(n);
+        // next write replaces the oldest slot; the newest value sits at the end
+        self.head_idx = 0;
+        self.tail_idx = n - 1;
+    }
+}
+
+/// How an operator treats non-finite values (NAN, ±inf) inside its window.
+///
+/// The warm-up slots of a fresh window are NAN too, so `Propagate` yields NAN until the
+/// window is full while `Skip` reports over whatever has arrived so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// any non-finite value in the window makes the result NAN
+    Propagate,
+    /// compute over the finite values only; NAN when there are none
+    Skip,
+    /// like `Propagate`, but a non-finite *input* is rejected by `try_update` (and makes `update` panic)
+    Error,
+}
+
+/// A non-finite input offered to an operator under `NanPolicy::Error`. The window is left untouched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NanInput(pub f64);
+
+impl std::fmt::Display for NanInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "non-finite input {} rejected by NanPolicy::Error", self.0)
+    }
+}
+
+impl std::error::Error for NanInput {}
+
+impl NanPolicy {
+    fn check(self, new_val: f64) -> Result<(), NanInput> {
+        if self == NanPolicy::Error && !new_val.is_finite() {
+            return Err(NanInput(new_val));
+        }
+        Ok(())
+    }
+}
+
+/// Running sum with Neumaier compensation: the rounding error of every addition is carried
+/// in `compensation`, so a window sum updated millions of times (adding the new value,
+/// subtracting the old) stays as accurate as one recomputed from the window.
+#[derive(Debug, Clone, Copy, Default)]
+struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    fn of<'a>(values: impl Iterator<Item = &'a f64>) -> Self {
+        let mut sum = Self::default();
+        for &v in values {
+            sum.add(v);
+        }
+        sum
+    }
+
+    fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        // whichever operand is smaller lost its low bits in `t`
+        self.compensation += if self.sum.abs() >= x.abs() {
+            (self.sum - t) + x
+        } else {
+            (x - t) + self.sum
+        };
+        self.sum = t;
+    }
+
+    fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
 }
 
 pub struct Sum {
     pub container: Container,
     nan_count: usize,
-    sum: f64,
+    sum: CompensatedSum,
+    policy: NanPolicy,
 }
 
 impl Sum {
@@ -88,33 +219,65 @@ impl Sum {
         Self {
             container: Container::new(n),
             nan_count: n,
-            sum: 0.0,
+            sum: CompensatedSum::default(),
+            policy: NanPolicy::Propagate,
         }
     }
 
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::Sum: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.policy.check(new_val)?;
         let old_val = self.container.head();
         self.container.update(new_val);
 
         if old_val.is_finite() {
-            self.sum -= old_val;
+            self.sum.add(-old_val);
         } else {
             self.nan_count -= 1;
         }
 
         if new_val.is_finite() {
-            self.sum += new_val;
+            self.sum.add(new_val);
         } else {
             self.nan_count += 1;
         }
 
-        if self.nan_count > 0 { f64::NAN } else { self.sum }
+        Ok(self.value())
+    }
+
+    fn value(&self) -> f64 {
+        match self.policy {
+            NanPolicy::Skip if self.valid_count() > 0 => self.sum.value(),
+            _ if self.nan_count > 0 => f64::NAN,
+            _ => self.sum.value(),
+        }
+    }
+
+    /// Number of finite values currently in the window.
+    pub fn valid_count(&self) -> usize {
+        self.container.len() - self.nan_count
+    }
+
+    /// Resize the window and rebuild the running sum from the kept values.
+    pub fn resize(&mut self, n: usize) {
+        self.container.resize(n);
+        self.nan_count = self.container.iter().filter(|v| !v.is_finite()).count();
+        self.sum = CompensatedSum::of(self.container.iter().filter(|v| v.is_finite()));
     }
 }
 
 pub struct WeightedSum {
     container: Container,
     weights: Vec<f64>,
+    policy: NanPolicy,
 }
 
 impl WeightedSum {
@@ -123,25 +286,46 @@ impl WeightedSum {
         Self {
             container: Container::new(n),
             weights,
+            policy: NanPolicy::Propagate,
         }
     }
 
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::WeightedSum: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.policy.check(new_val)?;
         self.container.update(new_val);
 
-        self.weights
-            .iter()
-            .zip(self.container.iter())
-            .map(|(&weight, &value)| weight * value)
-            .sum()
+        let terms = self.weights.iter().zip(self.container.iter());
+        if self.policy == NanPolicy::Skip {
+            let (sum, valid) = terms
+                .filter(|&(_, value)| value.is_finite())
+                .fold((0.0, 0usize), |(sum, valid), (&weight, &value)| (sum + weight * value, valid + 1));
+            return Ok(if valid > 0 { sum } else { f64::NAN });
+        }
+        Ok(terms.map(|(&weight, &value)| weight * value).sum())
+    }
+
+    /// Swap in new weights, resizing the window to match their length.
+    pub fn set_weights(&mut self, weights: Vec<f64>) {
+        self.container.resize(weights.len());
+        self.weights = weights;
     }
 }
 
-// no NAN rolling average
+// no NAN rolling average by default (NanPolicy::Skip)
 pub struct Mean {
     container: Container,
     nan_count: usize,
-    sum: f64,
+    sum: CompensatedSum,
+    policy: NanPolicy,
 }
 
 impl Mean {
@@ -149,38 +333,60 @@ impl Mean {
         Self {
             container: Container::new(n),
             nan_count: n,
-            sum: 0.0,
+            sum: CompensatedSum::default(),
+            policy: NanPolicy::Skip,
         }
     }
 
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::Mean: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.policy.check(new_val)?;
         let old_val = self.container.head();
         self.container.update(new_val);
 
         if old_val.is_finite() {
-            self.sum -= old_val;
+            self.sum.add(-old_val);
         } else {
             self.nan_count -= 1;
         }
 
         if new_val.is_finite() {
-            self.sum += new_val;
+            self.sum.add(new_val);
         } else {
             self.nan_count += 1;
         }
 
         if self.nan_count > 0 {
-            self.sum / (self.container.len() - self.nan_count) as f64
+            if self.policy != NanPolicy::Skip {
+                return Ok(f64::NAN);
+            }
+            Ok(self.sum.value() / (self.container.len() - self.nan_count) as f64)
         } else {
-            self.sum / self.container.len() as f64
+            Ok(self.sum.value() / self.container.len() as f64)
         }
     }
+
+    /// Resize the window and rebuild the running sum from the kept values.
+    pub fn resize(&mut self, n: usize) {
+        self.container.resize(n);
+        self.nan_count = self.container.iter().filter(|v| !v.is_finite()).count();
+        self.sum = CompensatedSum::of(self.container.iter().filter(|v| v.is_finite()));
+    }
 }
 
 pub struct StDev {
     sumer: Sum,
     sq_sumer: Sum,
     n: usize,
+    policy: NanPolicy,
 }
 
 impl StDev {
@@ -189,14 +395,698 @@ impl StDev {
             sumer: Sum::new(n),
             sq_sumer: Sum::new(n),
             n,
+            policy: NanPolicy::Propagate,
         }
     }
 
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.sumer = self.sumer.with_nan_policy(policy);
+        self.sq_sumer = self.sq_sumer.with_nan_policy(policy);
+        self.policy = policy;
+        self
+    }
+
     pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::StDev: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.policy.check(new_val)?;
         let sum = self.sumer.update(new_val);
         let sq_sum = self.sq_sumer.update(new_val * new_val);
 
-        let variance = (sq_sum - sum * sum / self.n as f64) / (self.n as f64 - 1.0);
-        variance.sqrt()
+        // under Skip only the finite values count towards the sample size
+        let n = match self.policy {
+            NanPolicy::Skip => self.sumer.valid_count(),
+            _ => self.n,
+        } as f64;
+        let variance = (sq_sum - sum * sum / n) / (n - 1.0);
+        Ok(variance.sqrt())
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.sumer.resize(n);
+        self.sq_sumer.resize(n);
+        self.n = n;
+    }
+}
+
+/// Where the newest value sits within the window, from 0 (the lowest) to 1 (the highest);
+/// ties count half, so a window of equal values ranks 0.5. A percentile-based breakout filter
+/// compares this against e.g. 0.95 instead of sorting its own copy of the window.
+pub struct Rank {
+    container: Container,
+    policy: NanPolicy,
+}
+
+impl Rank {
+    pub fn new(n: usize) -> Self {
+        Self {
+            container: Container::new(n),
+            policy: NanPolicy::Propagate,
+        }
+    }
+
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::Rank: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.policy.check(new_val)?;
+        self.container.update(new_val);
+        if !new_val.is_finite() {
+            return Ok(f64::NAN);
+        }
+
+        let (mut below, mut equal, mut others) = (0usize, 0usize, 0usize);
+        for &value in self.container.iter() {
+            if !value.is_finite() {
+                if self.policy != NanPolicy::Skip {
+                    return Ok(f64::NAN);
+                }
+                continue;
+            }
+            others += 1;
+            if value < new_val {
+                below += 1;
+            } else if value == new_val {
+                equal += 1;
+            }
+        }
+        // the newest value itself is one of the equal ones
+        let (equal, others) = (equal - 1, others - 1);
+        if others == 0 {
+            return Ok(0.5);
+        }
+        Ok((below as f64 + 0.5 * equal as f64) / others as f64)
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.container.resize(n);
+    }
+}
+
+/// Running sums of a least-squares fit of the window against its index, 0 for the oldest
+/// slot up to `n - 1` for the newest, shared by `Slope` and `RSquared`.
+struct LinearFit {
+    container: Container,
+    nan_count: usize,
+    sum_y: CompensatedSum,
+    sum_xy: CompensatedSum,
+    sum_yy: CompensatedSum,
+    policy: NanPolicy,
+}
+
+/// Sums over the points a fit is taken from.
+struct FitSums {
+    n: f64,
+    x: f64,
+    xx: f64,
+    y: f64,
+    xy: f64,
+    yy: f64,
+}
+
+impl FitSums {
+    /// n·Σxy − Σx·Σy, n·Σxx − (Σx)², n·Σyy − (Σy)²
+    fn moments(&self) -> (f64, f64, f64) {
+        (
+            self.n * self.xy - self.x * self.y,
+            self.n * self.xx - self.x * self.x,
+            self.n * self.yy - self.y * self.y,
+        )
+    }
+}
+
+impl LinearFit {
+    fn new(n: usize) -> Self {
+        Self {
+            container: Container::new(n),
+            nan_count: n,
+            sum_y: CompensatedSum::default(),
+            sum_xy: CompensatedSum::default(),
+            sum_yy: CompensatedSum::default(),
+            policy: NanPolicy::Propagate,
+        }
+    }
+
+    fn push(&mut self, new_val: f64) -> Result<(), NanInput> {
+        self.policy.check(new_val)?;
+        let finite = |v: f64| if v.is_finite() { v } else { 0.0 };
+        let old_val = self.container.head();
+        self.container.update(new_val);
+        if !old_val.is_finite() {
+            self.nan_count -= 1;
+        }
+        if !new_val.is_finite() {
+            self.nan_count += 1;
+        }
+
+        // every remaining value moves one index down, the new one enters at n - 1
+        let (old, new) = (finite(old_val), finite(new_val));
+        self.sum_xy.add(-self.sum_y.value());
+        self.sum_xy.add(old);
+        self.sum_xy.add((self.container.len() - 1) as f64 * new);
+        self.sum_y.add(-old);
+        self.sum_y.add(new);
+        self.sum_yy.add(-old * old);
+        self.sum_yy.add(new * new);
+        Ok(())
+    }
+
+    /// The sums to fit; `None` when the policy makes the result NAN.
+    fn sums(&self) -> Option<FitSums> {
+        if self.nan_count == 0 {
+            let n = self.container.len() as f64;
+            return Some(FitSums {
+                n,
+                x: n * (n - 1.0) / 2.0,
+                xx: (n - 1.0) * n * (2.0 * n - 1.0) / 6.0,
+                y: self.sum_y.value(),
+                xy: self.sum_xy.value(),
+                yy: self.sum_yy.value(),
+            });
+        }
+        if self.policy != NanPolicy::Skip {
+            return None;
+        }
+        // gaps leave the index sums irregular; fit the finite points directly
+        let mut sums = FitSums {
+            n: 0.0,
+            x: 0.0,
+            xx: 0.0,
+            y: 0.0,
+            xy: 0.0,
+            yy: 0.0,
+        };
+        for (i, &y) in self.container.iter().enumerate().filter(|(_, y)| y.is_finite()) {
+            let x = i as f64;
+            sums.n += 1.0;
+            sums.x += x;
+            sums.xx += x * x;
+            sums.y += y;
+            sums.xy += x * y;
+            sums.yy += y * y;
+        }
+        Some(sums)
+    }
+
+    fn resize(&mut self, n: usize) {
+        self.container.resize(n);
+        self.nan_count = 0;
+        self.sum_y = CompensatedSum::default();
+        self.sum_xy = CompensatedSum::default();
+        self.sum_yy = CompensatedSum::default();
+        for (i, &y) in self.container.iter().enumerate() {
+            if y.is_finite() {
+                self.sum_y.add(y);
+                self.sum_xy.add(i as f64 * y);
+                self.sum_yy.add(y * y);
+            } else {
+                self.nan_count += 1;
+            }
+        }
+    }
+}
+
+/// Least-squares slope of the window against its index, in value per update: positive while
+/// the window trends up. NAN with fewer than two points.
+pub struct Slope {
+    fit: LinearFit,
+}
+
+impl Slope {
+    pub fn new(n: usize) -> Self {
+        Self { fit: LinearFit::new(n) }
+    }
+
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.fit.policy = policy;
+        self
+    }
+
+    pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::Slope: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.fit.push(new_val)?;
+        let Some(sums) = self.fit.sums() else {
+            return Ok(f64::NAN);
+        };
+        let (sxy, sxx, _) = sums.moments();
+        Ok(if sums.n >= 2.0 { sxy / sxx } else { f64::NAN })
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.fit.resize(n);
+    }
+}
+
+/// Coefficient of determination of the fit `Slope` takes: 1 for a window on a straight
+/// line, near 0 for one without a trend. NAN with fewer than two points or a flat window.
+pub struct RSquared {
+    fit: LinearFit,
+}
+
+impl RSquared {
+    pub fn new(n: usize) -> Self {
+        Self { fit: LinearFit::new(n) }
+    }
+
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.fit.policy = policy;
+        self
+    }
+
+    pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::RSquared: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        self.fit.push(new_val)?;
+        let Some(sums) = self.fit.sums() else {
+            return Ok(f64::NAN);
+        };
+        let (sxy, sxx, syy) = sums.moments();
+        if sums.n < 2.0 || syy <= 0.0 {
+            return Ok(f64::NAN);
+        }
+        Ok((sxy * sxy / (sxx * syy)).min(1.0))
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.fit.resize(n);
+    }
+}
+
+/// How far the newest value sits from the window's mean, in sample standard deviations.
+/// NAN until the window is full under `Propagate`, and for a flat window.
+pub struct ZScore {
+    mean: Mean,
+    stdev: StDev,
+}
+
+impl ZScore {
+    pub fn new(n: usize) -> Self {
+        Self {
+            mean: Mean::new(n).with_nan_policy(NanPolicy::Propagate),
+            stdev: StDev::new(n),
+        }
+    }
+
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.mean = self.mean.with_nan_policy(policy);
+        self.stdev = self.stdev.with_nan_policy(policy);
+        self
+    }
+
+    pub fn update(&mut self, new_val: f64) -> f64 {
+        self.try_update(new_val).unwrap_or_else(|e| panic!("rolling::ZScore: {}", e))
+    }
+
+    pub fn try_update(&mut self, new_val: f64) -> Result<f64, NanInput> {
+        let mean = self.mean.try_update(new_val)?;
+        let stdev = self.stdev.update(new_val);
+        Ok(if stdev > 0.0 { (new_val - mean) / stdev } else { f64::NAN })
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.mean.resize(n);
+        self.stdev.resize(n);
+    }
+}
+
+/// Running sums over a window of `(x, y)` pairs, shared by `Corr` and `Beta`. A pair with
+/// either side non-finite counts as missing.
+struct PairFit {
+    container: Container<(f64, f64)>,
+    nan_count: usize,
+    sum_x: CompensatedSum,
+    sum_y: CompensatedSum,
+    sum_xx: CompensatedSum,
+    sum_xy: CompensatedSum,
+    sum_yy: CompensatedSum,
+    policy: NanPolicy,
+}
+
+impl PairFit {
+    fn new(n: usize) -> Self {
+        Self {
+            container: Container::with_fill(n, (f64::NAN, f64::NAN)),
+            nan_count: n,
+            sum_x: CompensatedSum::default(),
+            sum_y: CompensatedSum::default(),
+            sum_xx: CompensatedSum::default(),
+            sum_xy: CompensatedSum::default(),
+            sum_yy: CompensatedSum::default(),
+            policy: NanPolicy::Propagate,
+        }
+    }
+
+    fn add(&mut self, (x, y): (f64, f64), sign: f64) {
+        self.sum_x.add(sign * x);
+        self.sum_y.add(sign * y);
+        self.sum_xx.add(sign * x * x);
+        self.sum_xy.add(sign * x * y);
+        self.sum_yy.add(sign * y * y);
+    }
+
+    fn push(&mut self, x: f64, y: f64) -> Result<(), NanInput> {
+        self.policy.check(x)?;
+        self.policy.check(y)?;
+        let finite = |(x, y): (f64, f64)| x.is_finite() && y.is_finite();
+        let old = self.container.head();
+        self.container.update((x, y));
+        if finite(old) {
+            self.add(old, -1.0);
+        } else {
+            self.nan_count -= 1;
+        }
+        if finite((x, y)) {
+            self.add((x, y), 1.0);
+        } else {
+            self.nan_count += 1;
+        }
+        Ok(())
+    }
+
+    /// The sums to fit; `None` when the policy makes the result NAN.
+    fn sums(&self) -> Option<FitSums> {
+        if self.nan_count > 0 && self.policy != NanPolicy::Skip {
+            return None;
+        }
+        Some(FitSums {
+            n: (self.container.len() - self.nan_count) as f64,
+            x: self.sum_x.value(),
+            xx: self.sum_xx.value(),
+            y: self.sum_y.value(),
+            xy: self.sum_xy.value(),
+            yy: self.sum_yy.value(),
+        })
+    }
+
+    fn resize(&mut self, n: usize) {
+        self.container.resize(n);
+        self.nan_count = 0;
+        (self.sum_x, self.sum_y, self.sum_xx, self.sum_xy, self.sum_yy) = Default::default();
+        let pairs: Vec<(f64, f64)> = self.container.iter().copied().collect();
+        for (x, y) in pairs {
+            if x.is_finite() && y.is_finite() {
+                self.add((x, y), 1.0);
+            } else {
+                self.nan_count += 1;
+            }
+        }
+    }
+}
+
+/// Pearson correlation of the `(x, y)` pairs in the window. NAN with fewer than two pairs
+/// or when either side is flat.
+pub struct Corr {
+    fit: PairFit,
+}
+
+impl Corr {
+    pub fn new(n: usize) -> Self {
+        Self { fit: PairFit::new(n) }
+    }
+
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.fit.policy = policy;
+        self
+    }
+
+    pub fn update(&mut self, x: f64, y: f64) -> f64 {
+        self.try_update(x, y).unwrap_or_else(|e| panic!("rolling::Corr: {}", e))
+    }
+
+    pub fn try_update(&mut self, x: f64, y: f64) -> Result<f64, NanInput> {
+        self.fit.push(x, y)?;
+        let Some(sums) = self.fit.sums() else {
+            return Ok(f64::NAN);
+        };
+        let (sxy, sxx, syy) = sums.moments();
+        if sums.n < 2.0 || sxx <= 0.0 || syy <= 0.0 {
+            return Ok(f64::NAN);
+        }
+        Ok((sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0))
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.fit.resize(n);
+    }
+}
+
+/// Least-squares slope of y on x over the window's pairs: the hedge ratio that makes
+/// `y - beta * x` the flattest. NAN with fewer than two pairs or a flat x.
+pub struct Beta {
+    fit: PairFit,
+}
+
+impl Beta {
+    pub fn new(n: usize) -> Self {
+        Self { fit: PairFit::new(n) }
+    }
+
+    pub fn with_nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.fit.policy = policy;
+        self
+    }
+
+    pub fn update(&mut self, x: f64, y: f64) -> f64 {
+        self.try_update(x, y).unwrap_or_else(|e| panic!("rolling::Beta: {}", e))
+    }
+
+    pub fn try_update(&mut self, x: f64, y: f64) -> Result<f64, NanInput> {
+        self.fit.push(x, y)?;
+        let Some(sums) = self.fit.sums() else {
+            return Ok(f64::NAN);
+        };
+        let (sxy, sxx, _) = sums.moments();
+        Ok(if sums.n >= 2.0 && sxx > 0.0 { sxy / sxx } else { f64::NAN })
+    }
+
+    pub fn resize(&mut self, n: usize) {
+        self.fit.resize(n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_keeps_most_recent_values() {
+        let mut mean = Mean::new(4);
+        for v in [1.0, 2.0, 3.0, 4.0, 5.0] {
+            mean.update(v);
+        }
+
+        // shrink: window holds [4, 5]
+        mean.resize(2);
+        assert_eq!(mean.update(6.0), 5.5);
+
+        // grow: window holds [NAN, NAN, 5, 6] -> [NAN, 5, 6, 7]
+        mean.resize(4);
+        assert_eq!(mean.update(7.0), 6.0);
+        assert_eq!(mean.update(8.0), 6.5);
+    }
+
+    #[test]
+    fn nan_policies() {
+        let mut propagate = Sum::new(3);
+        let mut skip = Sum::new(3).with_nan_policy(NanPolicy::Skip);
+        let mut error = Mean::new(3).with_nan_policy(NanPolicy::Error);
+        for v in [1.0, f64::NAN, 2.0] {
+            propagate.update(v);
+            skip.update(v);
+        }
+        assert!(propagate.update(3.0).is_nan());
+        assert_eq!(skip.update(3.0), 5.0);
+
+        // warm-up slots still propagate; only non-finite inputs are rejected
+        assert!(error.try_update(4.0).unwrap().is_nan());
+        error.update(5.0);
+        assert_eq!(error.update(6.0), 5.0);
+        assert!(error.try_update(f64::NAN).is_err());
+        assert_eq!(error.update(7.0), 6.0);
+    }
+
+    #[test]
+    fn rank_of_newest_value() {
+        let mut rank = Rank::new(5);
+        for v in [3.0, 1.0, 4.0, 1.0] {
+            assert!(rank.update(v).is_nan());
+        }
+        // highest of [3, 1, 4, 1, 5]
+        assert_eq!(rank.update(5.0), 1.0);
+        // [1, 4, 1, 5, 1]: lowest, tied with two others
+        assert_eq!(rank.update(1.0), 0.25);
+        // [4, 1, 5, 1, 4]: above two, tied with one
+        assert_eq!(rank.update(4.0), 0.625);
+
+        let mut skip = Rank::new(4).with_nan_policy(NanPolicy::Skip);
+        assert_eq!(skip.update(2.0), 0.5);
+        assert_eq!(skip.update(1.0), 0.0);
+        assert!(skip.update(f64::NAN).is_nan());
+        assert_eq!(skip.update(1.5), 0.5);
+    }
+
+    #[test]
+    fn slope_and_r_squared_match_a_direct_fit() {
+        let mut slope = Slope::new(4);
+        let mut r2 = RSquared::new(4);
+        let values = [1.0, 3.0, 5.0, 7.0, 6.0, 2.0, 4.0];
+        for (i, &v) in values.iter().enumerate() {
+            let (s, r) = (slope.update(v), r2.update(v));
+            if i < 3 {
+                assert!(s.is_nan() && r.is_nan());
+                continue;
+            }
+            // direct least squares over the last four values against 0..4
+            let window = &values[i - 3..=i];
+            let mean = window.iter().sum::<f64>() / 4.0;
+            let sxy: f64 = window.iter().enumerate().map(|(x, y)| (x as f64 - 1.5) * (y - mean)).sum();
+            let syy: f64 = window.iter().map(|y| (y - mean) * (y - mean)).sum();
+            assert!((s - sxy / 5.0).abs() < 1e-12, "slope at {}", i);
+            assert!((r - sxy * sxy / (5.0 * syy)).abs() < 1e-12, "r² at {}", i);
+        }
+        // [1, 3, 5, 7] lies on a line of slope 2
+        let mut line = Slope::new(4);
+        let mut fit = RSquared::new(4);
+        for v in [1.0, 3.0, 5.0, 7.0] {
+            line.update(v);
+            fit.update(v);
+        }
+        assert_eq!(line.update(9.0), 2.0);
+        assert_eq!(fit.update(9.0), 1.0);
+
+        let mut skip = Slope::new(4).with_nan_policy(NanPolicy::Skip);
+        for v in [1.0, f64::NAN, 5.0] {
+            skip.update(v);
+        }
+        // the window [1, NAN, 5, NAN] is fitted through (0, 1) and (2, 5)
+        assert_eq!(skip.update(f64::NAN), 2.0);
+    }
+
+    #[test]
+    fn no_drift_after_ten_million_updates() {
+        const N: usize = 100;
+        let mut sum = Sum::new(N);
+        let mut mean = Mean::new(N);
+        let mut stdev = StDev::new(N);
+        let mut slope = Slope::new(N);
+        // prices around 1e5 with a bad print now and then; each spike passing through the
+        // window would leave its rounding error behind in a naive running sum
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut window = std::collections::VecDeque::with_capacity(N);
+        let (mut s, mut m, mut sd, mut k) = (0.0, 0.0, 0.0, 0.0);
+        for i in 0..10_000_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let v = if i % 1000 == 500 { 1e9 } else { 1e5 + (state % 10_000) as f64 * 0.01 };
+            if window.len() == N {
+                window.pop_front();
+            }
+            window.push_back(v);
+            (s, m, sd, k) = (sum.update(v), mean.update(v), stdev.update(v), slope.update(v));
+        }
+
+        let naive_sum: f64 = window.iter().sum();
+        let naive_mean = naive_sum / N as f64;
+        let naive_var = window.iter().map(|v| (v - naive_mean).powi(2)).sum::<f64>() / (N - 1) as f64;
+        let x_mean = (N - 1) as f64 / 2.0;
+        let sxy: f64 = window.iter().enumerate().map(|(x, v)| (x as f64 - x_mean) * (v - naive_mean)).sum();
+        let sxx: f64 = (0..N).map(|x| (x as f64 - x_mean).powi(2)).sum();
+        assert!((s - naive_sum).abs() < 1e-6, "sum {} vs {}", s, naive_sum);
+        assert!((m - naive_mean).abs() < 1e-8, "mean {} vs {}", m, naive_mean);
+        assert!(
+            (sd - naive_var.sqrt()).abs() / naive_var.sqrt() < 1e-6,
+            "stdev {} vs {}",
+            sd,
+            naive_var.sqrt()
+        );
+        assert!((k - sxy / sxx).abs() < 1e-6, "slope {} vs {}", k, sxy / sxx);
+    }
+
+    #[test]
+    fn container_over_bars_tracks_warm_up() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Bar {
+            high: f64,
+            low: f64,
+            close: f64,
+        }
+        let empty = Bar {
+            high: f64::NAN,
+            low: f64::NAN,
+            close: f64::NAN,
+        };
+        let mut bars = Container::with_fill(3, empty);
+        let true_range = |bars: &Container<Bar>| {
+            let (prev, bar) = (bars.get(1), bars.get(2));
+            bar.high.max(prev.close) - bar.low.min(prev.close)
+        };
+        bars.update(Bar {
+            high: 11.0,
+            low: 9.0,
+            close: 10.0,
+        });
+        bars.update(Bar {
+            high: 12.0,
+            low: 10.0,
+            close: 11.5,
+        });
+        assert!(!bars.is_full());
+        assert!(bars.head().close.is_nan());
+        bars.update(Bar {
+            high: 11.0,
+            low: 8.0,
+            close: 9.0,
+        });
+        assert!(bars.is_full());
+        // gapped below the previous close: the range reaches up to it
+        assert_eq!(true_range(&bars), 3.5);
+
+        bars.resize(5);
+        assert_eq!((bars.written(), bars.is_full()), (3, false));
+        assert_eq!(bars.iter().filter(|b| b.close.is_finite()).count(), 3);
+    }
+
+    #[test]
+    fn pair_statistics() {
+        let mut corr = Corr::new(4);
+        let mut beta = Beta::new(4);
+        let mut zscore = ZScore::new(4);
+        // y = 2x + 1 exactly
+        for x in [1.0, 2.0, 3.0] {
+            assert!(corr.update(x, 2.0 * x + 1.0).is_nan());
+            assert!(beta.update(x, 2.0 * x + 1.0).is_nan());
+            assert!(zscore.update(x).is_nan());
+        }
+        assert!((corr.update(4.0, 9.0) - 1.0).abs() < 1e-12);
+        assert!((beta.update(4.0, 9.0) - 2.0).abs() < 1e-12);
+        // window [1, 2, 3, 4]: mean 2.5, sample stdev sqrt(5/3)
+        assert!((zscore.update(4.0) - 1.5 / (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+
+        // moving against each other
+        for (x, y) in [(5.0, 4.0), (6.0, 3.0), (7.0, 2.0), (8.0, 1.0)] {
+            corr.update(x, y);
+            beta.update(x, y);
+        }
+        assert!((corr.update(9.0, 0.0) + 1.0).abs() < 1e-12);
+        assert!((beta.update(10.0, -1.0) + 1.0).abs() < 1e-12);
+
+        let mut skip = Corr::new(3).with_nan_policy(NanPolicy::Skip);
+        skip.update(1.0, 1.0);
+        skip.update(f64::NAN, 5.0);
+        assert!((skip.update(2.0, 2.0) - 1.0).abs() < 1e-12);
     }
 }
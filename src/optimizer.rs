@@ -0,0 +1,277 @@
+//! Parameter search over in-process backtests (see `backtest`). Candidates come from a
+//! `ParamGrid`, either every combination or a random sample of them; each one builds a fresh
+//! strategy, applies the parameters through `Strategy::on_param_update` and runs it over the
+//! same ticks. Runs are spread over worker threads and ranked by an `Objective`, best first,
+//! and `write_csv` saves the table for a closer look.
+//!
+//! Only grid and random search are offered: with a few thousand ticks per run a grid is cheap,
+//! and random sampling covers wide spaces about as well as a model-based search would.
+use crate::backtest::{self, BacktestStats};
+use crate::config::ContractInfo;
+use crate::sim::ExecutionModel;
+use crate::strategies;
+use crate::strategy::Strategy;
+use crate::testing::Rng;
+use crate::types::TickData;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// One candidate: parameter names and values, in grid axis order.
+pub type Params = Vec<(String, f64)>;
+
+/// The values to try for each parameter.
+#[derive(Debug, Clone, Default)]
+pub struct ParamGrid {
+    axes: Vec<(String, Vec<f64>)>,
+}
+
+impl ParamGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn axis(mut self, name: &str, values: Vec<f64>) -> Self {
+        self.axes.push((name.to_string(), values));
+        self
+    }
+
+    /// `start`, `start + step`, … up to and including `end`.
+    pub fn range(self, name: &str, start: f64, end: f64, step: f64) -> Self {
+        let steps = ((end - start) / step + 1e-9).floor().max(0.0) as usize;
+        self.axis(name, (0..=steps).map(|i| start + i as f64 * step).collect())
+    }
+
+    /// Number of combinations.
+    pub fn size(&self) -> usize {
+        self.axes.iter().map(|(_, values)| values.len()).product()
+    }
+
+    /// Every combination, the last axis varying fastest.
+    pub fn points(&self) -> Vec<Params> {
+        (0..self.size()).map(|i| self.point(i)).collect()
+    }
+
+    /// `n` combinations drawn at random, without repeats; the whole grid if it is smaller.
+    pub fn sample(&self, rng: &mut Rng, n: usize) -> Vec<Params> {
+        let size = self.size();
+        if n >= size {
+            return self.points();
+        }
+        // partial Fisher-Yates over the combination indices
+        let mut indices: Vec<usize> = (0..size).collect();
+        for i in 0..n {
+            let j = i + rng.below((size - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        indices[..n].iter().map(|&i| self.point(i)).collect()
+    }
+
+    fn point(&self, mut index: usize) -> Params {
+        let mut params: Params = Vec::with_capacity(self.axes.len());
+        for (name, values) in self.axes.iter().rev() {
+            params.push((name.clone(), values[index % values.len()]));
+            index /= values.len();
+        }
+        params.reverse();
+        params
+    }
+}
+
+/// What to rank runs by; higher is better.
+#[derive(Clone)]
+pub enum Objective {
+    Sharpe,
+    TotalReturn,
+    /// return over max drawdown
+    ReturnOverDrawdown,
+    Custom(Arc<dyn Fn(&BacktestStats) -> f64 + Send + Sync>),
+}
+
+impl Objective {
+    pub fn score(&self, stats: &BacktestStats) -> f64 {
+        match self {
+            Objective::Sharpe => stats.sharpe,
+            Objective::TotalReturn => stats.total_return,
+            Objective::ReturnOverDrawdown => stats.total_return / stats.max_drawdown.max(1e-9),
+            Objective::Custom(score) => score(stats),
+        }
+    }
+}
+
+/// How a search runs.
+#[derive(Clone)]
+pub struct SearchConfig {
+    pub init_cash: f64,
+    pub info: ContractInfo,
+    pub objective: Objective,
+    /// how backtest orders fill; see `sim`
+    pub execution: ExecutionModel,
+    /// worker threads; 0 uses one per available core
+    pub threads: usize,
+}
+
+/// The outcome of one candidate.
+#[derive(Debug, Clone)]
+pub struct Trial {
+    pub params: Params,
+    pub score: f64,
+    /// why the candidate did not run, e.g. a rejected parameter value
+    pub error: Option<String>,
+    pub stats: BacktestStats,
+}
+
+/// Builds strategies by kind name through `strategies::create`, e.g. `kind_factory("Aberration",
+/// &["20"])`, for candidates that only change parameters the strategy accepts at runtime.
+pub fn kind_factory(kind: &str, args: &[&str]) -> impl Fn() -> Result<Box<dyn Strategy>, String> + Sync + use<> {
+    let kind = kind.to_string();
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    move || strategies::create(&kind, &args)
+}
+
+/// Backtest every candidate over `ticks` and return the trials best first; candidates that
+/// failed to set up sort last.
+pub fn search<F>(factory: F, candidates: Vec<Params>, ticks: &[TickData], config: &SearchConfig) -> Vec<Trial>
+where
+    F: Fn() -> Result<Box<dyn Strategy>, String> + Sync,
+{
+    let threads = match config.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(candidates.len().max(1));
+    let next = AtomicUsize::new(0);
+    let mut trials: Vec<Trial> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(params) = candidates.get(i) else { break };
+                        done.push(trial(&factory, params, ticks, config));
+                    }
+                    done
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|w| w.join().expect("optimizer worker panicked")).collect()
+    });
+    trials.sort_by(|a, b| b.score.total_cmp(&a.score));
+    trials
+}
+
+/// A fresh strategy from `factory` with `params` applied.
+pub fn build<F>(factory: &F, params: &Params) -> Result<Box<dyn Strategy>, String>
+where
+    F: Fn() -> Result<Box<dyn Strategy>, String>,
+{
+    let mut strategy = factory()?;
+    for (name, value) in params {
+        strategy.on_param_update(name, *value).map_err(|e| format!("{}: {}", name, e))?;
+    }
+    Ok(strategy)
+}
+
+fn trial<F>(factory: &F, params: &Params, ticks: &[TickData], config: &SearchConfig) -> Trial
+where
+    F: Fn() -> Result<Box<dyn Strategy>, String>,
+{
+    match build(factory, params) {
+        Ok(mut strategy) => {
+            let stats = backtest::run(strategy.as_mut(), ticks, config.init_cash, config.info, &config.execution).stats;
+            Trial {
+                params: params.clone(),
+                score: config.objective.score(&stats),
+                error: None,
+                stats,
+            }
+        }
+        Err(e) => Trial {
+            params: params.clone(),
+            score: f64::NEG_INFINITY,
+            error: Some(e),
+            stats: BacktestStats::default(),
+        },
+    }
+}
+
+/// Write the trials as CSV, one column per parameter followed by the score and stats.
+pub fn write_csv(path: impl AsRef<Path>, trials: &[Trial]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let names: Vec<&str> = trials
+        .first()
+        .map(|t| t.params.iter().map(|(n, _)| n.as_str()).collect())
+        .unwrap_or_default();
+    let mut header = names.join(",");
+    if !header.is_empty() {
+        header.push(',');
+    }
+    writeln!(
+        out,
+        "{}score,total_return,sharpe,max_drawdown,realized_pnl,fees,fills,final_equity,error",
+        header
+    )?;
+    for t in trials {
+        let values: String = t.params.iter().map(|(_, v)| format!("{},", v)).collect();
+        let s = &t.stats;
+        writeln!(
+            out,
+            "{}{},{},{},{},{},{},{},{},{}",
+            values,
+            t.score,
+            s.total_return,
+            s.sharpe,
+            s.max_drawdown,
+            s.realized_pnl,
+            s.fees,
+            s.fills,
+            s.final_equity,
+            t.error.as_deref().unwrap_or("").replace(',', ";")
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn ranks_a_grid_of_aberration_runs() {
+        let grid = ParamGrid::new().range("ma_len", 10.0, 25.0, 5.0).axis("stop_loss", vec![0.0, -1.0]);
+        assert_eq!(grid.size(), 8);
+        assert_eq!(grid.points()[1], vec![("ma_len".to_string(), 10.0), ("stop_loss".to_string(), -1.0)]);
+        assert_eq!(grid.sample(&mut Rng::new(1), 3).len(), 3);
+
+        let prices = testing::random_walk(&mut Rng::new(7), 2000, 3500.0, 1.0, 3);
+        let ticks = testing::ticks("rb2505", &prices, 0, 60_000, 1.0);
+        let config = SearchConfig {
+            init_cash: 1_000_000.0,
+            info: ContractInfo {
+                multiplier: 10.0,
+                ..Default::default()
+            },
+            objective: Objective::TotalReturn,
+            execution: ExecutionModel::default(),
+            threads: 3,
+        };
+        let trials = search(kind_factory("Aberration", &["20"]), grid.points(), &ticks, &config);
+        assert_eq!(trials.len(), 8);
+        assert!(trials.windows(2).all(|w| w[0].score >= w[1].score));
+        // a negative stop loss is rejected, so those candidates rank last
+        assert!(trials[4..].iter().all(|t| t.error.is_some()));
+        assert!(trials[..4].iter().all(|t| t.error.is_none() && t.stats.fills > 0));
+
+        let path = std::env::temp_dir().join(format!("optimizer-{}.csv", std::process::id()));
+        write_csv(&path, &trials).unwrap();
+        let table = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(table.starts_with("ma_len,stop_loss,score,"));
+        assert_eq!(table.lines().count(), 9);
+    }
+}
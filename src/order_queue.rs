@@ -0,0 +1,203 @@
+//! Per-symbol order queue for when orders cannot go out right away: the symbol's order rate
+//! is at its throttle, or the symbol is paused. Held orders are released highest purpose
+//! first (flatten, then close, then open) and in arrival order within a purpose, so a
+//! protective exit never waits behind a backlog of entries. Entries that waited too long are
+//! dropped, since the signal behind them has gone stale; exits are kept until they go out.
+use crate::types::{NameType, OffsetFlagType, Order};
+use std::collections::VecDeque;
+
+/// Why an order is sent, from least to most urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Purpose {
+    Open,
+    Close,
+    /// the engine closing a position on risk grounds, e.g. a stop level
+    Flatten,
+}
+
+impl Purpose {
+    /// The purpose of an order a strategy sent itself.
+    pub fn of(order: &Order) -> Self {
+        match order.offset {
+            OffsetFlagType::OPEN => Purpose::Open,
+            OffsetFlagType::CLOSE => Purpose::Close,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueueConfig {
+    /// orders per symbol allowed within `window_ms`
+    pub max_orders: u32,
+    pub window_ms: i64,
+    /// queued entries older than this are dropped
+    pub max_wait_ms: i64,
+    /// orders held per symbol; when full, a more urgent order displaces the newest of the
+    /// least urgent ones
+    pub capacity: usize,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_orders: 50,
+            window_ms: 1000,
+            max_wait_ms: 3000,
+            capacity: 256,
+        }
+    }
+}
+
+/// An order waiting to go out.
+#[derive(Debug, Clone, Copy)]
+pub struct Queued {
+    pub strategy: NameType,
+    pub order: Order,
+    pub purpose: Purpose,
+    pub queued_at: i64,
+}
+
+/// What `OrderQueue::push` did with an entry.
+#[derive(Debug)]
+pub enum Admission {
+    Held,
+    /// held in place of this less urgent entry
+    Displaced(Queued),
+    /// the queue is full of entries at least as urgent; the entry is handed back
+    Rejected(Queued),
+}
+
+/// One symbol's throttle and held orders.
+pub struct OrderQueue {
+    config: QueueConfig,
+    /// send times within the current window, oldest first
+    sent: VecDeque<i64>,
+    /// indexed by `Purpose as usize`
+    entries: [VecDeque<Queued>; 3],
+}
+
+impl OrderQueue {
+    pub fn new(config: QueueConfig) -> Self {
+        Self {
+            config,
+            sent: VecDeque::new(),
+            entries: Default::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(VecDeque::is_empty)
+    }
+
+    /// Whether the throttle lets one more order out at `now`.
+    pub fn has_room(&mut self, now: i64) -> bool {
+        while self.sent.front().is_some_and(|&t| now - t >= self.config.window_ms) {
+            self.sent.pop_front();
+        }
+        (self.sent.len() as u32) < self.config.max_orders
+    }
+
+    /// Count an order sent at `now` against the throttle.
+    pub fn on_sent(&mut self, now: i64) {
+        self.sent.push_back(now);
+    }
+
+    /// Hold `entry`, displacing the newest of the least urgent entries if the queue is full.
+    pub fn push(&mut self, entry: Queued) -> Admission {
+        let mut admission = Admission::Held;
+        if self.len() >= self.config.capacity {
+            let lowest = self.entries[..entry.purpose as usize].iter_mut().find_map(VecDeque::pop_back);
+            match lowest {
+                Some(displaced) => admission = Admission::Displaced(displaced),
+                None => return Admission::Rejected(entry),
+            }
+        }
+        self.entries[entry.purpose as usize].push_back(entry);
+        admission
+    }
+
+    /// Every held entry, most urgent first.
+    pub fn iter(&self) -> impl Iterator<Item = &Queued> {
+        self.entries.iter().rev().flatten()
+    }
+
+    /// The most urgent held order, without taking it.
+    pub fn peek(&self) -> Option<&Queued> {
+        self.entries.iter().rev().find_map(VecDeque::front)
+    }
+
+    /// Take the most urgent held order.
+    pub fn pop(&mut self) -> Option<Queued> {
+        self.entries.iter_mut().rev().find_map(VecDeque::pop_front)
+    }
+
+    /// Remove the entries that have waited longer than `max_wait_ms` at `now`; only opens
+    /// expire.
+    pub fn expire(&mut self, now: i64) -> Vec<Queued> {
+        let opens = &mut self.entries[Purpose::Open as usize];
+        let stale = opens.iter().take_while(|e| now - e.queued_at > self.config.max_wait_ms).count();
+        opens.drain(..stale).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DirectionType, OrderType, SymbolType};
+
+    fn queued(purpose: Purpose, client_id: u64, queued_at: i64) -> Queued {
+        let offset = match purpose {
+            Purpose::Open => OffsetFlagType::OPEN,
+            Purpose::Close | Purpose::Flatten => OffsetFlagType::CLOSE,
+        };
+        Queued {
+            strategy: NameType::from("s"),
+            order: Order {
+                stg_name: NameType::from("s"),
+                symbol: SymbolType::from("rb2505"),
+                timestamp: queued_at,
+                price: 3500.0,
+                lots: 1,
+                direction: DirectionType::BUY,
+                offset,
+                order_type: OrderType::LIMIT,
+                client_id,
+                order_id: 0,
+            },
+            purpose,
+            queued_at,
+        }
+    }
+
+    #[test]
+    fn releases_exits_before_entries() {
+        let mut queue = OrderQueue::new(QueueConfig {
+            max_orders: 2,
+            window_ms: 1000,
+            max_wait_ms: 500,
+            capacity: 3,
+        });
+        assert!(queue.has_room(0));
+        queue.on_sent(0);
+        queue.on_sent(100);
+        assert!(!queue.has_room(900));
+        assert!(queue.has_room(1000));
+
+        for (purpose, client_id) in [(Purpose::Open, 1), (Purpose::Open, 2), (Purpose::Close, 3)] {
+            assert!(matches!(queue.push(queued(purpose, client_id, 10 * client_id as i64)), Admission::Held));
+        }
+        // full: a flatten displaces the newest open, another open does not fit
+        assert!(matches!(queue.push(queued(Purpose::Flatten, 4, 30)), Admission::Displaced(e) if e.order.client_id == 2));
+        assert!(matches!(queue.push(queued(Purpose::Open, 5, 40)), Admission::Rejected(_)));
+
+        assert_eq!(queue.peek().map(|e| e.purpose), Some(Purpose::Flatten));
+        assert_eq!(queue.pop().map(|e| e.order.client_id), Some(4));
+        assert_eq!(queue.expire(600).len(), 1);
+        assert_eq!(queue.pop().map(|e| e.order.client_id), Some(3));
+        assert!(queue.is_empty());
+    }
+}
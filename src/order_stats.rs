@@ -0,0 +1,257 @@
+//! Per-strategy order rate and burst statistics, and the throttle that reins in a strategy
+//! churning orders: sending or cancelling on almost every tick, bursting more messages than
+//! a strategy should, or sending order after order that never fills. Unlike the exchange
+//! conduct limits (`conduct`), which count a whole trading day, these look at a short rolling
+//! window, so a runaway strategy is stopped within seconds and let go once it calms down.
+//!
+//! A throttled strategy has its opening orders refused for `cooldown_ms`; closes and cancels
+//! still go out, so it can always get flat.
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// When a strategy's order flow counts as churning; a 0 limit is not checked.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChurnLimits {
+    /// the rolling window messages and fills are counted over, in ms
+    pub window_ms: i64,
+    /// most orders plus cancels within the window
+    pub max_messages: u32,
+    /// most orders per fill within the window, once it holds `min_orders` orders
+    pub max_order_to_fill: f64,
+    pub min_orders: u32,
+    /// most ticks in a row on which the strategy sent an order or a cancel
+    pub max_busy_ticks: u32,
+    /// how long opening orders are refused once throttled, in ms
+    pub cooldown_ms: i64,
+}
+
+impl Default for ChurnLimits {
+    fn default() -> Self {
+        Self {
+            window_ms: 10_000,
+            max_messages: 0,
+            max_order_to_fill: 0.0,
+            min_orders: 20,
+            max_busy_ticks: 0,
+            cooldown_ms: 60_000,
+        }
+    }
+}
+
+/// Why a strategy was throttled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Churn {
+    Burst { messages: u32, limit: u32 },
+    OrderToFill { ratio: f64, limit: f64 },
+    BusyTicks { ticks: u32, limit: u32 },
+}
+
+impl fmt::Display for Churn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Churn::Burst { messages, limit } => write!(f, "{} orders and cancels in the window, over {}", messages, limit),
+            Churn::OrderToFill { ratio, limit } => write!(f, "{:.1} orders per fill in the window, over {}", ratio, limit),
+            Churn::BusyTicks { ticks, limit } => write!(f, "orders or cancels on {} ticks in a row, over {}", ticks, limit),
+        }
+    }
+}
+
+/// A strategy's order flow so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct OrderStats {
+    pub orders: u64,
+    pub cancels: u64,
+    pub fills: u64,
+    /// most orders plus cancels seen within one window
+    pub max_burst: u32,
+    /// ticks in a row, up to the last, on which orders or cancels were sent
+    pub busy_ticks: u32,
+    /// times the strategy was throttled
+    pub throttles: u64,
+}
+
+impl OrderStats {
+    /// Orders per fill; all orders count while nothing has filled.
+    pub fn order_to_fill(&self) -> f64 {
+        self.orders as f64 / self.fills.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    Order,
+    Cancel,
+    Fill,
+}
+
+/// Counts a strategy's order flow and, given limits, throttles it.
+#[derive(Debug, Clone)]
+pub struct OrderStatsTracker {
+    limits: Option<ChurnLimits>,
+    stats: OrderStats,
+    /// what happened within the window, oldest first; kept only with limits
+    window: VecDeque<(i64, Event)>,
+    /// whether an order or cancel went out since the last tick ended
+    busy: bool,
+    throttled_until: Option<i64>,
+    /// `stats` changed since `take_changed`
+    changed: bool,
+}
+
+impl OrderStatsTracker {
+    /// Count only, or also throttle at `limits`.
+    pub fn new(limits: Option<ChurnLimits>) -> Self {
+        Self {
+            limits,
+            stats: OrderStats::default(),
+            window: VecDeque::new(),
+            busy: false,
+            throttled_until: None,
+            changed: false,
+        }
+    }
+
+    pub fn stats(&self) -> &OrderStats {
+        &self.stats
+    }
+
+    /// The stats, if they changed since last taken, for publishing without redoing it every tick.
+    pub fn take_changed(&mut self) -> Option<OrderStats> {
+        std::mem::take(&mut self.changed).then_some(self.stats)
+    }
+
+    /// Whether the strategy may open at `stamp`; until when it is throttled otherwise.
+    pub fn admit(&self, stamp: i64) -> Result<(), i64> {
+        match self.throttled_until {
+            Some(until) if stamp < until => Err(until),
+            _ => Ok(()),
+        }
+    }
+
+    /// An order went out at `stamp`; the reason, if that throttles the strategy.
+    pub fn on_order(&mut self, stamp: i64) -> Option<Churn> {
+        self.stats.orders += 1;
+        self.busy = true;
+        self.changed = true;
+        self.record(stamp, Event::Order)
+    }
+
+    /// A cancel went out at `stamp`; the reason, if that throttles the strategy.
+    pub fn on_cancel(&mut self, stamp: i64) -> Option<Churn> {
+        self.stats.cancels += 1;
+        self.busy = true;
+        self.changed = true;
+        self.record(stamp, Event::Cancel)
+    }
+
+    pub fn on_fill(&mut self, stamp: i64) {
+        self.stats.fills += 1;
+        self.changed = true;
+        self.record(stamp, Event::Fill);
+    }
+
+    /// The tick at `stamp` is done; the reason, if the run of busy ticks throttles the strategy.
+    pub fn on_tick_end(&mut self, stamp: i64) -> Option<Churn> {
+        let busy_ticks = if std::mem::take(&mut self.busy) { self.stats.busy_ticks + 1 } else { 0 };
+        self.changed |= busy_ticks != self.stats.busy_ticks;
+        self.stats.busy_ticks = busy_ticks;
+        let limits = self.limits?;
+        let ticks = self.stats.busy_ticks;
+        if limits.max_busy_ticks > 0 && ticks > limits.max_busy_ticks {
+            return self.throttle(
+                stamp,
+                Churn::BusyTicks {
+                    ticks,
+                    limit: limits.max_busy_ticks,
+                },
+            );
+        }
+        None
+    }
+
+    fn record(&mut self, stamp: i64, event: Event) -> Option<Churn> {
+        let limits = self.limits?;
+        self.window.push_back((stamp, event));
+        while self.window.front().is_some_and(|&(at, _)| at <= stamp - limits.window_ms) {
+            self.window.pop_front();
+        }
+        let count = |kind| self.window.iter().filter(|(_, e)| *e == kind).count() as u32;
+        let (orders, cancels, fills) = (count(Event::Order), count(Event::Cancel), count(Event::Fill));
+        let messages = orders + cancels;
+        self.stats.max_burst = self.stats.max_burst.max(messages);
+        if event == Event::Fill {
+            return None;
+        }
+        if limits.max_messages > 0 && messages > limits.max_messages {
+            return self.throttle(
+                stamp,
+                Churn::Burst {
+                    messages,
+                    limit: limits.max_messages,
+                },
+            );
+        }
+        let ratio = orders as f64 / fills.max(1) as f64;
+        if limits.max_order_to_fill > 0.0 && orders >= limits.min_orders && ratio > limits.max_order_to_fill {
+            return self.throttle(
+                stamp,
+                Churn::OrderToFill {
+                    ratio,
+                    limit: limits.max_order_to_fill,
+                },
+            );
+        }
+        None
+    }
+
+    /// Refuse opening orders for the cooldown; only the first reason of a throttle is reported.
+    fn throttle(&mut self, stamp: i64, churn: Churn) -> Option<Churn> {
+        let cooldown = self.limits.map_or(0, |l| l.cooldown_ms);
+        let fresh = self.admit(stamp).is_ok();
+        self.throttled_until = Some(stamp + cooldown);
+        if fresh {
+            self.stats.throttles += 1;
+            Some(churn)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttles_bursts_and_busy_ticks_until_the_cooldown_passes() {
+        let limits = ChurnLimits {
+            window_ms: 1000,
+            max_messages: 3,
+            max_busy_ticks: 4,
+            cooldown_ms: 5000,
+            ..Default::default()
+        };
+        let mut tracker = OrderStatsTracker::new(Some(limits));
+        for stamp in [0, 100, 200] {
+            assert_eq!(tracker.on_order(stamp), None);
+        }
+        assert_eq!(tracker.on_cancel(300), Some(Churn::Burst { messages: 4, limit: 3 }));
+        assert!(tracker.admit(5299).is_err() && tracker.admit(5300).is_ok());
+
+        // one order a tick, each on a fresh window
+        let mut tracker = OrderStatsTracker::new(Some(limits));
+        for tick in 0..4 {
+            tracker.on_order(tick * 1000);
+            tracker.on_fill(tick * 1000);
+            assert_eq!(tracker.on_tick_end(tick * 1000), None);
+        }
+        tracker.on_order(4000);
+        assert_eq!(tracker.on_tick_end(4000), Some(Churn::BusyTicks { ticks: 5, limit: 4 }));
+        assert_eq!(tracker.on_tick_end(5000), None);
+        let stats = tracker.stats();
+        assert_eq!((stats.orders, stats.fills, stats.busy_ticks, stats.throttles), (5, 4, 0, 1));
+        assert_eq!(stats.order_to_fill(), 1.25);
+    }
+}
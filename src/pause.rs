@@ -0,0 +1,81 @@
+//! Symbols paused by the operator, shared by the receive loop and every worker. Pausing is
+//! rare and checked on every tick and order, so each thread reads through its own
+//! `PausedView`: a copy of the set refreshed only when the generation moves, which leaves a
+//! single atomic load on the hot path.
+use crate::types::SymbolType;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Default)]
+pub struct PausedSymbols {
+    /// bumped after every change to `symbols`
+    generation: AtomicU64,
+    symbols: RwLock<Arc<HashSet<SymbolType>>>,
+}
+
+impl PausedSymbols {
+    /// Pause `symbol`; whether it was running.
+    pub fn insert(&self, symbol: SymbolType) -> bool {
+        self.update(|symbols| symbols.insert(symbol))
+    }
+
+    /// Resume `symbol`; whether it was paused.
+    pub fn remove(&self, symbol: &SymbolType) -> bool {
+        self.update(|symbols| symbols.remove(symbol))
+    }
+
+    fn update(&self, change: impl FnOnce(&mut HashSet<SymbolType>) -> bool) -> bool {
+        let mut symbols = self.symbols.write().unwrap();
+        let changed = change(Arc::make_mut(&mut symbols));
+        if changed {
+            self.generation.fetch_add(1, Ordering::Release);
+        }
+        changed
+    }
+
+    /// A reader for one thread.
+    pub fn view(self: &Arc<Self>) -> PausedView {
+        PausedView {
+            shared: Arc::clone(self),
+            cached: RefCell::new((u64::MAX, Arc::default())),
+        }
+    }
+}
+
+/// One thread's copy of the paused symbols.
+#[derive(Debug)]
+pub struct PausedView {
+    shared: Arc<PausedSymbols>,
+    /// the generation copied and the set as of it
+    cached: RefCell<(u64, Arc<HashSet<SymbolType>>)>,
+}
+
+impl PausedView {
+    pub fn contains(&self, symbol: &SymbolType) -> bool {
+        let generation = self.shared.generation.load(Ordering::Acquire);
+        let mut cached = self.cached.borrow_mut();
+        if cached.0 != generation {
+            *cached = (generation, Arc::clone(&self.shared.symbols.read().unwrap()));
+        }
+        !cached.1.is_empty() && cached.1.contains(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn views_follow_pauses_made_after_they_were_taken() {
+        let paused = Arc::new(PausedSymbols::default());
+        let (rb, ma) = (SymbolType::from("rb2505"), SymbolType::from("MA505"));
+        let view = paused.view();
+        assert!(!view.contains(&rb));
+        assert!(paused.insert(rb) && !paused.insert(rb));
+        assert!(view.contains(&rb) && !view.contains(&ma));
+        assert!(paused.remove(&rb) && !paused.remove(&rb));
+        assert!(!view.contains(&rb));
+    }
+}
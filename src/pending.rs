@@ -0,0 +1,218 @@
+//! Orders a strategy has sent that are not fully filled yet, keyed by client order id, so
+//! they can be cancelled by id or once they have rested too long.
+//!
+//! Fills are taken off an order as they come, so an order filled in parts stays pending
+//! with the lots still open; `PartialFillPolicy` says what the engine does with such a
+//! remainder when it has rested too long.
+use crate::types::Order;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What becomes of the unfilled rest of a partly filled order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartialFillPolicy {
+    /// leave it resting until it fills or the strategy cancels it
+    #[default]
+    Rest,
+    /// cancel it once the order is `after_ms` old
+    Cancel { after_ms: i64 },
+    /// cancel it once the order is `after_ms` old and send it again at the touch
+    Resend { after_ms: i64 },
+}
+
+impl PartialFillPolicy {
+    /// How old a partly filled order may get before the policy acts on it, if it ever does.
+    pub fn after_ms(&self) -> Option<i64> {
+        match *self {
+            Self::Rest => None,
+            Self::Cancel { after_ms } | Self::Resend { after_ms } => Some(after_ms),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PendingOrders {
+    /// client id → the order with its unfilled lots
+    orders: BTreeMap<u64, Order>,
+    /// client id → lots filled so far, for orders filled in part
+    filled: BTreeMap<u64, u32>,
+    /// client ids cancelled to be sent again, still open until the gateway confirms the cancel
+    resending: BTreeSet<u64>,
+    last_id: u64,
+}
+
+impl PendingOrders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give `order` the next client id unless the strategy chose one; ids stay unique per
+    /// strategy either way.
+    pub fn assign_id(&mut self, order: &mut Order) {
+        if order.client_id == 0 {
+            self.last_id += 1;
+            while self.orders.contains_key(&self.last_id) {
+                self.last_id += 1;
+            }
+            order.client_id = self.last_id;
+        } else {
+            self.last_id = self.last_id.max(order.client_id);
+        }
+    }
+
+    /// Track a sent order until it is filled or cancelled.
+    pub fn insert(&mut self, order: Order) {
+        self.orders.insert(order.client_id, order);
+    }
+
+    /// The pending order `fill` reports on: the one with its engine order id, or, failing
+    /// that (e.g. replaying fills of an earlier run), the one with its client id.
+    pub fn matching(&self, fill: &Order) -> Option<&Order> {
+        self.by_order_id(fill.order_id).or_else(|| self.orders.get(&fill.client_id))
+    }
+
+    /// The pending order with engine order id `order_id`; never one without an id.
+    pub fn by_order_id(&self, order_id: u64) -> Option<&Order> {
+        self.orders.values().find(|o| order_id != 0 && o.order_id == order_id)
+    }
+
+    /// Take `fill.lots` off the order it fills (see `matching`); returns the order with the
+    /// lots still open, or `None` once it is complete (or was not pending).
+    pub fn on_fill(&mut self, fill: &Order) -> Option<Order> {
+        let client_id = self.matching(fill)?.client_id;
+        let pending = self.orders.get_mut(&client_id)?;
+        let lots = fill.lots.min(pending.lots);
+        pending.lots -= lots;
+        if pending.lots == 0 {
+            self.remove(client_id);
+            return None;
+        }
+        *self.filled.entry(client_id).or_default() += lots;
+        Some(*pending)
+    }
+
+    /// Lots of pending order `client_id` filled so far.
+    pub fn filled_lots(&self, client_id: u64) -> u32 {
+        self.filled.get(&client_id).copied().unwrap_or(0)
+    }
+
+    /// Ids of partly filled orders sent at least `after_ms` before `now`, and not already
+    /// being resent.
+    pub fn stale_partials(&self, now: i64, after_ms: i64) -> Vec<u64> {
+        self.filled
+            .keys()
+            .filter(|id| !self.resending.contains(id))
+            .filter(|id| self.orders.get(id).is_some_and(|o| now - o.timestamp >= after_ms))
+            .copied()
+            .collect()
+    }
+
+    pub fn get(&self, client_id: u64) -> Option<&Order> {
+        self.orders.get(&client_id)
+    }
+
+    pub fn remove(&mut self, client_id: u64) -> Option<Order> {
+        self.filled.remove(&client_id);
+        self.resending.remove(&client_id);
+        self.orders.remove(&client_id)
+    }
+
+    /// Note that pending order `client_id` was cancelled so its rest can be sent again once
+    /// the cancel is confirmed; it stays pending, and fills, until then.
+    pub fn resend_on_cancel(&mut self, client_id: u64) {
+        if self.orders.contains_key(&client_id) {
+            self.resending.insert(client_id);
+        }
+    }
+
+    /// Whether pending order `client_id` was cancelled to be sent again.
+    pub fn is_resending(&self, client_id: u64) -> bool {
+        self.resending.contains(&client_id)
+    }
+
+    /// Ids of orders sent at least `timeout_ms` before `now`, other than those already
+    /// cancelled to be sent again.
+    pub fn expired(&self, now: i64, timeout_ms: i64) -> Vec<u64> {
+        self.orders
+            .values()
+            .filter(|o| now - o.timestamp >= timeout_ms && !self.resending.contains(&o.client_id))
+            .map(|o| o.client_id)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Order> {
+        self.orders.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DirectionType, NameType, OffsetFlagType, OrderType, SymbolType};
+
+    fn order(client_id: u64, timestamp: i64, lots: u32) -> Order {
+        Order {
+            stg_name: NameType::from("s"),
+            symbol: SymbolType::from("rb2505"),
+            timestamp,
+            price: 100.0,
+            lots,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id,
+            order_id: 0,
+        }
+    }
+
+    #[test]
+    fn partial_fills_and_timeouts() {
+        let mut pending = PendingOrders::new();
+        let mut first = order(0, 1_000, 3);
+        pending.assign_id(&mut first);
+        let mut chosen = order(7, 2_000, 1);
+        pending.assign_id(&mut chosen);
+        let mut next = Order {
+            order_id: 42,
+            ..order(0, 3_000, 1)
+        };
+        pending.assign_id(&mut next);
+        assert_eq!((first.client_id, chosen.client_id, next.client_id), (1, 7, 8));
+
+        for o in [first, chosen, next] {
+            pending.insert(o);
+        }
+        assert_eq!(pending.on_fill(&order(1, 0, 2)).map(|o| o.lots), Some(1));
+        assert!(pending.on_fill(&order(7, 0, 1)).is_none());
+        assert_eq!(pending.expired(3_500, 2_000), vec![1]);
+        assert_eq!((pending.filled_lots(1), pending.filled_lots(8)), (2, 0));
+        // only the partly filled order is a candidate for the partial-fill policy
+        assert_eq!(pending.stale_partials(3_500, 0), vec![1]);
+        assert!(pending.stale_partials(1_500, 1_000).is_empty());
+        assert_eq!(pending.len(), 2);
+        // a report carrying the engine id finds its order whatever client id it echoes
+        assert!(
+            pending
+                .on_fill(&Order {
+                    order_id: 42,
+                    ..order(0, 0, 1)
+                })
+                .is_none()
+        );
+        assert_eq!(pending.iter().map(|o| o.client_id).collect::<Vec<_>>(), vec![1]);
+
+        // waiting on the cancel confirmation: still pending, but not cancelled again
+        pending.resend_on_cancel(1);
+        assert!(pending.is_resending(1) && pending.get(1).is_some());
+        assert!(pending.stale_partials(3_500, 0).is_empty() && pending.expired(3_500, 0).is_empty());
+        pending.remove(1);
+        assert!(!pending.is_resending(1));
+    }
+}
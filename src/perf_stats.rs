@@ -0,0 +1,126 @@
+//! Risk-adjusted performance of an equity curve, updated point by point as the curve grows
+//! so a live strategy's ratios can be queried at any time without replaying its history.
+//!
+//! Returns are taken between consecutive points of the (possibly resampled) curve. Points need
+//! not be evenly spaced: the ratios are annualized with the number of points per calendar
+//! year the curve has actually had, so a curve sampled every minute and one sampled every
+//! tick both scale to a year. Ratios stay 0 until there are two returns and a day of history.
+use serde::Serialize;
+
+pub(crate) const YEAR_MS: f64 = 365.25 * 86_400_000.0;
+pub(crate) const DAY_MS: i64 = 86_400_000;
+
+/// Annualized ratios and drawdowns of a curve so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PerfStats {
+    /// mean return over its standard deviation, at a zero risk-free rate
+    pub sharpe: f64,
+    /// mean return over the deviation of the losing returns
+    pub sortino: f64,
+    /// annual return over the maximum drawdown
+    pub calmar: f64,
+    /// largest fall from a peak, as a fraction of the peak
+    pub max_drawdown: f64,
+    /// longest time below a previous peak, in ms
+    pub max_drawdown_ms: i64,
+    /// time since the last peak, in ms; 0 at a new high
+    pub drawdown_ms: i64,
+}
+
+/// Running sums behind `PerfStats`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunningStats {
+    first: Option<(i64, f64)>,
+    last: Option<(i64, f64)>,
+    returns: u64,
+    mean: f64,
+    /// sum of squared deviations from the mean (Welford)
+    m2: f64,
+    /// sum of squared losing returns
+    downside: f64,
+    peak: f64,
+    peak_stamp: i64,
+    max_drawdown: f64,
+    max_drawdown_ms: i64,
+}
+
+impl RunningStats {
+    /// Take the curve's next point.
+    pub fn update(&mut self, stamp: i64, equity: f64) {
+        let is_first = self.first.is_none();
+        if let Some((_, prev)) = self.last
+            && prev > 0.0
+        {
+            let r = equity / prev - 1.0;
+            self.returns += 1;
+            let delta = r - self.mean;
+            self.mean += delta / self.returns as f64;
+            self.m2 += delta * (r - self.mean);
+            self.downside += r.min(0.0).powi(2);
+        }
+        self.first.get_or_insert((stamp, equity));
+        self.last = Some((stamp, equity));
+        if is_first || equity >= self.peak {
+            self.peak = equity;
+            self.peak_stamp = stamp;
+        } else if self.peak > 0.0 {
+            self.max_drawdown = self.max_drawdown.max((self.peak - equity) / self.peak);
+        }
+        self.max_drawdown_ms = self.max_drawdown_ms.max(stamp - self.peak_stamp);
+    }
+
+    pub fn stats(&self) -> PerfStats {
+        let (Some((start, first)), Some((end, last))) = (self.first, self.last) else {
+            return PerfStats::default();
+        };
+        let mut stats = PerfStats {
+            max_drawdown: self.max_drawdown,
+            max_drawdown_ms: self.max_drawdown_ms,
+            drawdown_ms: end - self.peak_stamp,
+            ..PerfStats::default()
+        };
+        let elapsed = end - start;
+        if self.returns < 2 || elapsed < DAY_MS || first <= 0.0 {
+            return stats;
+        }
+        let years = elapsed as f64 / YEAR_MS;
+        let scale = (self.returns as f64 / years).sqrt();
+        let sd = (self.m2 / (self.returns - 1) as f64).sqrt();
+        let downside = (self.downside / self.returns as f64).sqrt();
+        if sd > 0.0 {
+            stats.sharpe = self.mean / sd * scale;
+        }
+        if downside > 0.0 {
+            stats.sortino = self.mean / downside * scale;
+        }
+        if self.max_drawdown > 0.0 {
+            stats.calmar = (last / first - 1.0) / years / self.max_drawdown;
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratios_and_drawdowns_follow_the_curve() {
+        let mut running = RunningStats::default();
+        assert_eq!(running.stats(), PerfStats::default());
+        // one point a day: up, down 10% from the peak for two days, then a new high
+        for (day, equity) in [100.0, 110.0, 99.0, 104.5, 121.0].into_iter().enumerate() {
+            running.update(day as i64 * DAY_MS, equity);
+        }
+        let stats = running.stats();
+        assert!((stats.max_drawdown - 0.1).abs() < 1e-12);
+        assert_eq!((stats.max_drawdown_ms, stats.drawdown_ms), (2 * DAY_MS, 0));
+        assert!(stats.sharpe > 0.0 && stats.sortino > stats.sharpe);
+        // 21% over four days, annualized, over the 10% drawdown
+        let annual = 0.21 / (4.0 * DAY_MS as f64 / YEAR_MS);
+        assert!((stats.calmar - annual / 0.1).abs() < 1e-6);
+
+        running.update(5 * DAY_MS, 120.0);
+        assert_eq!(running.stats().drawdown_ms, DAY_MS);
+    }
+}
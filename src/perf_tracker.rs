@@ -1,7 +1,21 @@
 use crate::{
+    account::Ledger,
+    benchmark::{Benchmark, RelativeStats},
     config::ContractInfo,
-    types::{DirectionType, OffsetFlagType, Order, TickData},
+    equity_curve::{CurveConfig, EquityCurve},
+    fees::FeeModel,
+    handoff::{PositionState, StrategyState},
+    perf_stats::PerfStats,
+    round_trips::{RoundTrip, RoundTrips, TradeSummary},
+    types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, TickData},
 };
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+
+/// Fills a tracker keeps for snapshots and statements; older ones are only in the audit log
+/// and the order store.
+pub const RECENT_FILLS: usize = 10_000;
 
 /// 单向持仓
 #[derive(Debug, Clone, Copy)]
@@ -68,92 +82,407 @@ impl Position {
     }
 }
 
+/// 成交对资金的影响, as computed by `PerformanceTracker::preview`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillImpact {
+    pub fee: f64,
+    pub realized_pnl: f64,
+    /// 保证金变化 (positive = frozen)
+    pub margin_change: f64,
+    pub cash_change: f64,
+    /// 成交后可用资金
+    pub available_cash: f64,
+}
+
+/// 成交明细: what one fill cost and, closing, realized
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FillCost {
+    /// 手续费
+    pub fee: f64,
+    /// 平仓盈亏, 0 for an open
+    pub realized_pnl: f64,
+}
+
+/// One strategy's cash, positions and results on one contract. Every amount is in the
+/// account's currency: a contract priced in another one is converted at its `fx_rate`.
 pub struct PerformanceTracker {
     info: ContractInfo,
+    /// 手续费模型, the contract's own fees unless replaced
+    fees: FeeModel,
     available_cash: f64,
     long_position: Option<Position>,
     short_position: Option<Position>,
-    market_values: Vec<f64>,
+    /// 市值曲线
+    market_values: EquityCurve,
     total_fee: f64,
     total_realized_pnl: f64,
-    orders: Vec<Order>,
+    /// the latest `RECENT_FILLS` fills with their costs, a cache for snapshots and statements
+    recent_fills: VecDeque<(Order, FillCost)>,
+    /// fills booked since the start, including those dropped from `recent_fills`
+    fill_count: usize,
+    /// 最新价, as of the last `on_tick_end`
+    last_price: Option<f64>,
+    /// 开平配对
+    round_trips: RoundTrips,
+    /// 业绩基准
+    benchmark: Option<Benchmark>,
 }
 
 impl PerformanceTracker {
     pub fn new(init_cash: f64, info: ContractInfo) -> Self {
         Self {
             info,
+            fees: FeeModel::of_contract(&info),
             available_cash: init_cash,
             long_position: None,
             short_position: None,
-            market_values: vec![init_cash],
+            market_values: EquityCurve::new(init_cash),
             total_fee: 0.0,
             total_realized_pnl: 0.0,
-            orders: Vec::with_capacity(1024),
+            recent_fills: VecDeque::with_capacity(1024),
+            fill_count: 0,
+            last_price: None,
+            round_trips: RoundTrips::default(),
+            benchmark: None,
+        }
+    }
+
+    /// 业绩基准: measure the equity against `benchmark` from now on.
+    pub fn with_benchmark(mut self, benchmark: Benchmark) -> Self {
+        self.benchmark = Some(benchmark);
+        self
+    }
+
+    /// The latest level of a `Benchmark::series`, taken with the next `on_tick_end`.
+    pub fn set_benchmark_level(&mut self, level: f64) {
+        if let Some(ref mut benchmark) = self.benchmark {
+            benchmark.set_level(level);
+        }
+    }
+
+    /// Alpha, beta, information ratio and relative drawdown against the benchmark, if any.
+    pub fn benchmark_stats(&self) -> Option<RelativeStats> {
+        self.benchmark.as_ref().map(Benchmark::stats)
+    }
+
+    /// 按手/按金额: charge commissions with `fees` instead of the contract's fee columns.
+    pub fn with_fee_model(mut self, fees: FeeModel) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    /// 绩效指标: annualized Sharpe, Sortino and Calmar ratios and drawdowns of the equity
+    /// curve so far, one return per curve point.
+    pub fn stats(&self) -> PerfStats {
+        self.market_values.stats()
+    }
+
+    /// 重采样: keep the equity curve as `config` says from now on, starting over from the
+    /// current equity, and append its finished points to `path` when given.
+    pub fn resample_curve(&mut self, config: &CurveConfig, path: Option<&Path>) -> io::Result<()> {
+        let mut curve = EquityCurve::resampled(self.equity(), config);
+        if let Some(path) = path {
+            curve = curve.with_file(path)?;
+        }
+        self.market_values = curve;
+        Ok(())
+    }
+
+    pub fn info(&self) -> &ContractInfo {
+        &self.info
+    }
+
+    pub fn fee_model(&self) -> FeeModel {
+        self.fees
+    }
+
+    /// 最新市值 (as of the last `on_tick_end`)
+    pub fn equity(&self) -> f64 {
+        self.market_values.last()
+    }
+
+    /// 额外费用 (e.g. exchange message fees), booked like commissions
+    pub fn charge_fee(&mut self, fee: f64) {
+        self.total_fee += fee;
+        self.available_cash -= fee;
+    }
+
+    /// 已占用保证金
+    pub fn margin(&self) -> f64 {
+        self.long_position.map_or(0.0, |p| p.margin) + self.short_position.map_or(0.0, |p| p.margin)
+    }
+
+    /// 持仓快照 (long, short), for the shutdown handoff
+    pub fn positions(&self) -> (Option<PositionState>, Option<PositionState>) {
+        let state = |p: &Position| PositionState {
+            lots: p.lots,
+            avg_price: p.avg_price,
+        };
+        (self.long_position.as_ref().map(state), self.short_position.as_ref().map(state))
+    }
+
+    pub fn available_cash(&self) -> f64 {
+        self.available_cash
+    }
+
+    /// 资金检查: whether the cash left after filling the opening `order` covers its margin and
+    /// fee; otherwise the (negative) cash it would leave.
+    pub fn check_margin(&self, order: &Order) -> Result<(), f64> {
+        if order.offset != OffsetFlagType::OPEN {
+            return Ok(());
+        }
+        let available = self.preview(order).available_cash;
+        if available < 0.0 { Err(available) } else { Ok(()) }
+    }
+
+    /// 风险度: frozen margin over equity as of the last `on_tick_end`; above 1 is a margin call.
+    pub fn risk_degree(&self) -> f64 {
+        let margin = self.margin();
+        if margin <= 0.0 {
+            0.0
+        } else {
+            margin / self.equity().max(f64::MIN_POSITIVE)
+        }
+    }
+
+    /// 追保: the floating loss has eaten through the free cash, so equity no longer covers
+    /// the margin. A broker would liquidate (强平) unless funds are added.
+    pub fn is_margin_call(&self) -> bool {
+        self.risk_degree() > 1.0
+    }
+
+    /// 强平: orders closing every position at `tick`'s touch, or at the price limit when the
+    /// side to trade against is empty, for the strategy `name`.
+    pub fn closing_orders(&self, name: NameType, tick: &TickData) -> Vec<Order> {
+        let sides = [
+            (self.long_position, DirectionType::SELL, tick.bp1, tick.limit_down),
+            (self.short_position, DirectionType::BUY, tick.ap1, tick.limit_up),
+        ];
+        sides
+            .into_iter()
+            .filter_map(|(position, direction, touch, limit)| {
+                let position = position.filter(|p| p.lots > 0)?;
+                Some(Order {
+                    stg_name: name,
+                    symbol: tick.symbol,
+                    timestamp: tick.stamp,
+                    price: if touch > 0.0 { touch } else { limit },
+                    lots: position.lots,
+                    direction,
+                    offset: OffsetFlagType::CLOSE,
+                    order_type: OrderType::LIMIT,
+                    client_id: 0,
+                    order_id: 0,
+                })
+            })
+            .collect()
+    }
+
+    /// 累计已实现盈亏
+    pub fn realized_pnl(&self) -> f64 {
+        self.total_realized_pnl
+    }
+
+    /// 累计手续费
+    pub fn total_fee(&self) -> f64 {
+        self.total_fee
+    }
+
+    /// 成交记录: the latest fills, at most `RECENT_FILLS`, with the 手续费 and 平仓盈亏 of
+    /// each, oldest first
+    pub fn recent_fills(&self) -> &VecDeque<(Order, FillCost)> {
+        &self.recent_fills
+    }
+
+    /// 成交笔数 since the start
+    pub fn fill_count(&self) -> usize {
+        self.fill_count
+    }
+
+    /// 交易明细: round trips closed so far, opens paired with closes first in first out
+    pub fn trades(&self) -> &[RoundTrip] {
+        self.round_trips.closed()
+    }
+
+    /// 交易统计 of `trades`
+    pub fn trade_summary(&self) -> TradeSummary {
+        TradeSummary::of(self.trades())
+    }
+
+    /// 市值曲线, one sample per `on_tick_end` unless `resample_curve` said otherwise
+    pub fn market_values(&self) -> &EquityCurve {
+        &self.market_values
+    }
+
+    /// 恢复 the cash and positions a previous run handed off; margin is re-frozen at the
+    /// average price with this run's rates, and equity starts without unrealized PnL.
+    pub fn restore(&mut self, state: &StrategyState) {
+        let info = self.info;
+        let position = |p: &PositionState, rate: f64, fixed: f64| Position::new(p.lots, p.avg_price, rate, fixed * info.fx(), info.point_value());
+        self.long_position = state.long.as_ref().map(|p| position(p, info.long_margin_rate, info.long_margin_fixed));
+        self.short_position = state.short.as_ref().map(|p| position(p, info.short_margin_rate, info.short_margin_fixed));
+        self.available_cash = state.available_cash;
+        self.round_trips = RoundTrips::default();
+        for (position, side) in [(&self.long_position, DirectionType::BUY), (&self.short_position, DirectionType::SELL)] {
+            if let Some(p) = position {
+                self.round_trips.open(side, None, p.avg_price, p.lots, 0.0);
+            }
+        }
+        self.market_values.mark(self.available_cash + self.margin());
+    }
+
+    /// 换费率: trade on with `info` from now on, e.g. after the exchange changed margin rates
+    /// or to follow the exchange rate. Open positions are re-margined at their average price
+    /// with the new rates; returns the change in frozen margin.
+    pub fn set_info(&mut self, info: ContractInfo) -> f64 {
+        let before = self.margin();
+        if self.fees == FeeModel::of_contract(&self.info) {
+            self.fees = FeeModel::of_contract(&info);
+        }
+        self.info = info;
+        if let Some(ref mut p) = self.long_position {
+            p.margin = Position::new(
+                p.lots,
+                p.avg_price,
+                info.long_margin_rate,
+                info.long_margin_fixed * info.fx(),
+                info.point_value(),
+            )
+            .margin;
+        }
+        if let Some(ref mut p) = self.short_position {
+            p.margin = Position::new(
+                p.lots,
+                p.avg_price,
+                info.short_margin_rate,
+                info.short_margin_fixed * info.fx(),
+                info.point_value(),
+            )
+            .margin;
+        }
+        let change = self.margin() - before;
+        self.available_cash -= change;
+        change
+    }
+
+    /// 账户分摊: this strategy's share of a shared account, unrealized PnL at the last price.
+    pub fn ledger(&self) -> Ledger {
+        let unrealized = |p: &Option<Position>, direction| match (p, self.last_price) {
+            (Some(p), Some(price)) => p.unrealized_pnl(price, self.info.point_value(), direction),
+            _ => 0.0,
+        };
+        Ledger {
+            realized_pnl: self.total_realized_pnl,
+            fees: self.total_fee,
+            unrealized_pnl: unrealized(&self.long_position, DirectionType::BUY) + unrealized(&self.short_position, DirectionType::SELL),
+            long_margin: self.long_position.map_or(0.0, |p| p.margin),
+            short_margin: self.short_position.map_or(0.0, |p| p.margin),
+        }
+    }
+
+    /// A copy of the cash and positions, without the history, to book hypothetical fills on.
+    fn probe(&self) -> PerformanceTracker {
+        PerformanceTracker {
+            info: self.info,
+            fees: self.fees,
+            available_cash: self.available_cash,
+            long_position: self.long_position,
+            short_position: self.short_position,
+            market_values: EquityCurve::new(0.0),
+            total_fee: self.total_fee,
+            total_realized_pnl: self.total_realized_pnl,
+            recent_fills: VecDeque::new(),
+            fill_count: 0,
+            last_price: self.last_price,
+            round_trips: RoundTrips::default(),
+            benchmark: None,
+        }
+    }
+
+    /// The `ledger` after filling `order`, without booking it.
+    pub fn preview_ledger(&self, order: &Order) -> Ledger {
+        let mut probe = self.probe();
+        probe.on_fill(order);
+        probe.ledger()
+    }
+
+    /// 试算: what filling `order` now would do to cash and margin, without booking it.
+    pub fn preview(&self, order: &Order) -> FillImpact {
+        let mut probe = self.probe();
+        probe.on_fill(order);
+        FillImpact {
+            fee: probe.total_fee - self.total_fee,
+            realized_pnl: probe.total_realized_pnl - self.total_realized_pnl,
+            margin_change: probe.margin() - self.margin(),
+            cash_change: probe.available_cash - self.available_cash,
+            available_cash: probe.available_cash,
         }
     }
 
     pub fn on_fill(&mut self, order: &Order) {
-        let (margin_rate, margin_fixed, pos_opt_slot) = match order.direction {
+        // the side the fill trades on: a sell closes the long position, a buy the short one
+        let side = match (order.offset, order.direction) {
+            (OffsetFlagType::OPEN, direction) => direction,
+            (OffsetFlagType::CLOSE, DirectionType::SELL) => DirectionType::BUY,
+            (OffsetFlagType::CLOSE, DirectionType::BUY) => DirectionType::SELL,
+        };
+        // amounts in the account's currency
+        let (fx, point_value) = (self.info.fx(), self.info.point_value());
+        let (margin_rate, margin_fixed, pos_opt_slot) = match side {
             DirectionType::BUY => (
-                self.info.long_margin_rate,  // 多头开仓保证金(按金额)
-                self.info.long_margin_fixed, // 多头开仓保证金(按手数)
-                &mut self.long_position,     // 多头持仓
+                self.info.long_margin_rate,       // 多头开仓保证金(按金额)
+                self.info.long_margin_fixed * fx, // 多头开仓保证金(按手数)
+                &mut self.long_position,          // 多头持仓
             ),
             DirectionType::SELL => (
-                self.info.short_margin_rate,  // 空头开仓保证金(按金额)
-                self.info.short_margin_fixed, // 空头开仓保证金(按手数)
-                &mut self.short_position,     // 空头持仓
+                self.info.short_margin_rate,       // 空头开仓保证金(按金额)
+                self.info.short_margin_fixed * fx, // 空头开仓保证金(按手数)
+                &mut self.short_position,          // 空头持仓
             ),
         };
 
-        let (fee_rate, fee_fixed) = match order.offset {
-            OffsetFlagType::OPEN => (
-                self.info.open_fee_rate,  // 多空开仓手续费(按金额)
-                self.info.open_fee_fixed, // 多空开仓手续费(按手数)
-            ),
-            // 为了计算方便，使用更高的平昨费率
-            OffsetFlagType::CLOSE => (
-                self.info.close_fee_rate,  // 多空平仓手续费(按金额)
-                self.info.close_fee_fixed, // 多空平仓手续费(按手数)
-            ),
-        };
-
-        // 1) 计算手续费
-        let value_per_lot = order.price * self.info.multiplier;
-        let fee = (fee_rate * value_per_lot + fee_fixed) * (order.lots as f64);
+        // 1) 计算手续费, 平仓一律按平昨
+        let fee = self.fees.fee(order, self.info.multiplier) * fx;
         self.total_fee += fee;
         self.available_cash -= fee;
 
         // 2) 更新持仓和保证金
+        let mut realized = 0.0;
         match order.offset {
             OffsetFlagType::OPEN => {
                 // 新增/累加持仓
-                let pos = pos_opt_slot.get_or_insert_with(|| Position::new(0, order.price, margin_rate, margin_fixed, self.info.multiplier));
+                let pos = pos_opt_slot.get_or_insert_with(|| Position::new(0, order.price, margin_rate, margin_fixed, point_value));
                 // 如果已有仓位，重新计算加权均价和保证金
-                let prev_margin = pos.increase(order.lots, order.price, margin_rate, margin_fixed, self.info.multiplier);
+                let prev_margin = pos.increase(order.lots, order.price, margin_rate, margin_fixed, point_value);
                 // 冻结保证金
                 self.available_cash -= pos.margin - prev_margin; // 增量冻结
+                self.round_trips.open(side, Some(order.timestamp), order.price, order.lots, fee);
             }
             OffsetFlagType::CLOSE => {
                 if let Some(pos) = pos_opt_slot {
                     // 已经实现的pnl
                     let closed_lots = order.lots.min(pos.lots);
-                    let realized_pnl = pos.realized_pnl(closed_lots, order.price, self.info.multiplier, order.direction);
+                    let realized_pnl = pos.realized_pnl(closed_lots, order.price, point_value, side);
                     self.available_cash += realized_pnl;
                     self.total_realized_pnl += realized_pnl;
+                    realized = realized_pnl;
                     // 释放对应保证金
-                    let released_margin = pos.decrease(closed_lots, order.price, margin_rate, margin_fixed, self.info.multiplier);
+                    let released_margin = pos.decrease(closed_lots, order.price, margin_rate, margin_fixed, point_value);
                     self.available_cash += released_margin;
                     // 清理仓位
                     if pos.lots == 0 {
                         pos_opt_slot.take();
                     }
+                    self.round_trips.close(side, order.timestamp, order.price, closed_lots, fee, point_value);
                 }
             }
         }
 
-        self.orders.push(order.clone());
+        if self.recent_fills.len() == RECENT_FILLS {
+            self.recent_fills.pop_front();
+        }
+        self.recent_fills.push_back((*order, FillCost { fee, realized_pnl: realized }));
+        self.fill_count += 1;
     }
 
     /// 每个 tick 结束后，重新计算浮动盈亏、市值和已冻保证金
@@ -162,14 +491,20 @@ impl PerformanceTracker {
         let mut total_margin = 0.0;
 
         if let Some(pos) = &self.long_position {
-            total_unreal += pos.unrealized_pnl(tick.last, self.info.multiplier, DirectionType::BUY);
+            total_unreal += pos.unrealized_pnl(tick.last, self.info.point_value(), DirectionType::BUY);
             total_margin += pos.margin;
         }
         if let Some(pos) = &self.short_position {
-            total_unreal += pos.unrealized_pnl(tick.last, self.info.multiplier, DirectionType::SELL);
+            total_unreal += pos.unrealized_pnl(tick.last, self.info.point_value(), DirectionType::SELL);
             total_margin += pos.margin;
         }
 
-        self.market_values.push(self.available_cash + total_unreal + total_margin);
+        let equity = self.available_cash + total_unreal + total_margin;
+        self.market_values.push(tick.stamp, equity);
+        if let Some(ref mut benchmark) = self.benchmark {
+            benchmark.update(tick.stamp, equity, tick.last);
+        }
+        self.last_price = Some(tick.last);
+        self.round_trips.mark(tick.stamp, tick.last);
     }
 }
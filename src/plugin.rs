@@ -0,0 +1,291 @@
+//! Strategies loaded at runtime from `cdylib` plugins.
+//!
+//! A plugin is a crate built with `crate-type = ["cdylib"]` against the same `fustg_rs` version
+//! and rustc as the engine (trait objects cross the boundary, so the Rust ABI must match).
+//! It exports its factory with `declare_strategy_plugin!`:
+//!
+//! ```ignore
+//! fn build(args: &[String]) -> Result<Box<dyn Strategy>, String> { ... }
+//! fustg_rs::declare_strategy_plugin!("MyStrategy", build);
+//! ```
+//!
+//! and is loaded with `CtaEngine::load_plugin` or the `plugin <path>` control command, after
+//! which `add <symbol> <contract> MyStrategy args..` works like a built-in kind.
+use crate::calendar::Session;
+use crate::clock::SharedClock;
+use crate::conduct::ConductWarning;
+use crate::sizing::SizingContext;
+use crate::stops::StopLevels;
+use crate::strategy::{EngineContext, Orders, Strategy};
+use crate::tracking::Benchmark;
+use crate::types::{LimitLock, NameType, Order, SymbolType, TickData};
+use libloading::Library;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Bumped whenever the exported symbols or the `Strategy` trait change shape.
+pub const PLUGIN_ABI_VERSION: u32 = 18;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type KindFn = unsafe extern "C" fn() -> *const c_char;
+/// Takes the NUL-separated, double-NUL-terminated argument list and returns a
+/// `Box<Box<dyn Strategy>>` as an opaque pointer, or null if the arguments were rejected.
+type CreateFn = unsafe extern "C" fn(args: *const c_char) -> *mut c_void;
+
+/// Export the C-ABI entry points the engine looks for. `$build` is a
+/// `fn(&[String]) -> Result<Box<dyn Strategy>, String>`.
+#[macro_export]
+macro_rules! declare_strategy_plugin {
+    ($kind:literal, $build:path) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn fustg_plugin_abi_version() -> u32 {
+            $crate::plugin::PLUGIN_ABI_VERSION
+        }
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn fustg_plugin_kind() -> *const ::std::ffi::c_char {
+            concat!($kind, "\0").as_ptr().cast()
+        }
+
+        /// # Safety
+        /// `args` must be a list encoded by the engine's plugin loader.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn fustg_create_strategy(args: *const ::std::ffi::c_char) -> *mut ::std::ffi::c_void {
+            let args = unsafe { $crate::plugin::decode_args(args) };
+            match $build(&args) {
+                Ok(strategy) => Box::into_raw(Box::new(strategy)).cast(),
+                Err(e) => {
+                    // the plugin has its own copy of `tracing` with no subscriber installed
+                    eprintln!("{}: {}", $kind, e);
+                    ::std::ptr::null_mut()
+                }
+            }
+        }
+    };
+}
+
+fn encode_args(args: &[String]) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    for arg in args {
+        if arg.is_empty() {
+            return Err("empty argument".into());
+        }
+        buf.extend_from_slice(
+            CString::new(arg.as_str())
+                .map_err(|_| format!("argument {:?} contains NUL", arg))?
+                .as_bytes_with_nul(),
+        );
+    }
+    buf.push(0);
+    Ok(buf)
+}
+
+/// Inverse of the loader's argument encoding; used by `declare_strategy_plugin!`.
+///
+/// # Safety
+/// `args` must point to NUL-separated strings terminated by an empty string.
+#[doc(hidden)]
+pub unsafe fn decode_args(mut args: *const c_char) -> Vec<String> {
+    let mut out = Vec::new();
+    loop {
+        let arg = unsafe { CStr::from_ptr(args) };
+        if arg.is_empty() {
+            return out;
+        }
+        out.push(arg.to_string_lossy().into_owned());
+        args = unsafe { args.add(arg.count_bytes() + 1) };
+    }
+}
+
+struct Plugin {
+    create: CreateFn,
+    // keeps `create` (and the code of every strategy it built) mapped
+    lib: Arc<Library>,
+}
+
+/// Strategy kinds provided by loaded plugins.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: HashMap<String, Plugin>,
+}
+
+impl PluginRegistry {
+    /// Load the plugin at `path` and return the strategy kind it provides.
+    /// Loading a kind that is already registered replaces it for future strategies.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<String, String> {
+        let path = path.as_ref();
+        // SAFETY: running the library's initialisers is inherent to loading a plugin; the
+        // symbol signatures are pinned by `declare_strategy_plugin!` and the ABI version.
+        unsafe {
+            let lib = Library::new(path).map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+            let version = lib
+                .get::<AbiVersionFn>(b"fustg_plugin_abi_version\0")
+                .map_err(|e| format!("{} is not a strategy plugin: {}", path.display(), e))?();
+            if version != PLUGIN_ABI_VERSION {
+                return Err(format!(
+                    "{} has plugin ABI {}, engine expects {}",
+                    path.display(),
+                    version,
+                    PLUGIN_ABI_VERSION
+                ));
+            }
+            let kind = lib.get::<KindFn>(b"fustg_plugin_kind\0").map_err(|e| e.to_string())?();
+            let kind = CStr::from_ptr(kind).to_string_lossy().into_owned();
+            let create = *lib.get::<CreateFn>(b"fustg_create_strategy\0").map_err(|e| e.to_string())?;
+            self.plugins.insert(kind.clone(), Plugin { create, lib: Arc::new(lib) });
+            Ok(kind)
+        }
+    }
+
+    /// Build a strategy of `kind`, or `None` if no loaded plugin provides it.
+    pub fn create(&self, kind: &str, args: &[String]) -> Option<Result<Box<dyn Strategy>, String>> {
+        let plugin = self.plugins.get(kind)?;
+        Some(encode_args(args).and_then(|buf| {
+            // SAFETY: `buf` is encoded as `decode_args` expects; a non-null result is the
+            // `Box<Box<dyn Strategy>>` leaked by `fustg_create_strategy`.
+            let raw = unsafe { (plugin.create)(buf.as_ptr().cast()) };
+            if raw.is_null() {
+                return Err(format!("plugin {} rejected arguments {:?}", kind, args));
+            }
+            let inner = unsafe { *Box::from_raw(raw.cast::<Box<dyn Strategy>>()) };
+            Ok(Box::new(PluginStrategy {
+                inner,
+                _lib: Arc::clone(&plugin.lib),
+            }) as Box<dyn Strategy>)
+        }))
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = &str> {
+        self.plugins.keys().map(String::as_str)
+    }
+}
+
+/// A plugin-built strategy that keeps its library loaded for as long as it lives.
+struct PluginStrategy {
+    // declared first so it is dropped before the library is unloaded
+    inner: Box<dyn Strategy>,
+    _lib: Arc<Library>,
+}
+
+impl Strategy for PluginStrategy {
+    fn name(&self) -> NameType {
+        self.inner.name()
+    }
+
+    fn update(&mut self, tick: &TickData) -> Orders {
+        self.inner.update(tick)
+    }
+
+    fn target_position(&mut self, tick: &TickData) -> Option<i32> {
+        self.inner.target_position(tick)
+    }
+
+    fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+        self.inner.on_param_update(name, value)
+    }
+
+    fn on_account(&mut self, ctx: &SizingContext) {
+        self.inner.on_account(ctx)
+    }
+
+    fn on_conduct_warning(&mut self, warning: &ConductWarning) {
+        self.inner.on_conduct_warning(warning)
+    }
+
+    fn stop_levels(&self, entry: &Order) -> StopLevels {
+        self.inner.stop_levels(entry)
+    }
+
+    fn benchmark(&self) -> Option<Benchmark> {
+        self.inner.benchmark()
+    }
+
+    fn references(&self) -> Vec<SymbolType> {
+        self.inner.references()
+    }
+
+    fn on_reference_tick(&mut self, tick: &TickData) {
+        self.inner.on_reference_tick(tick)
+    }
+
+    fn on_limit_lock(&mut self, lock: Option<LimitLock>) {
+        self.inner.on_limit_lock(lock)
+    }
+
+    fn order_timeout_ms(&self) -> Option<i64> {
+        self.inner.order_timeout_ms()
+    }
+
+    fn take_cancels(&mut self) -> Vec<u64> {
+        self.inner.take_cancels()
+    }
+
+    fn on_order_cancelled(&mut self, order: &Order) {
+        self.inner.on_order_cancelled(order)
+    }
+
+    fn on_order_rejected(&mut self, order: &Order, reason: &str) {
+        self.inner.on_order_rejected(order, reason)
+    }
+
+    fn on_fill(&mut self, fill: &Order, remaining: u32) {
+        self.inner.on_fill(fill, remaining)
+    }
+
+    fn on_stop_triggered(&mut self, order: &Order) {
+        self.inner.on_stop_triggered(order)
+    }
+
+    fn on_clock(&mut self, clock: SharedClock) {
+        self.inner.on_clock(clock)
+    }
+
+    fn on_resume(&mut self, long_lots: u32, short_lots: u32) {
+        self.inner.on_resume(long_lots, short_lots)
+    }
+
+    fn on_start(&mut self, ctx: &EngineContext) {
+        self.inner.on_start(ctx)
+    }
+
+    fn on_stop(&mut self) {
+        self.inner.on_stop()
+    }
+
+    fn on_day_open(&mut self) {
+        self.inner.on_day_open()
+    }
+
+    fn on_day_close(&mut self) {
+        self.inner.on_day_close()
+    }
+
+    fn on_session_start(&mut self, session: &Session) {
+        self.inner.on_session_start(session)
+    }
+
+    fn on_session_end(&mut self, session: &Session) {
+        self.inner.on_session_end(session)
+    }
+
+    fn on_toggle(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        self.inner.on_toggle(name, enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args_round_trip() {
+        let args = vec!["20".to_string(), "x y".to_string()];
+        let buf = encode_args(&args).unwrap();
+        assert_eq!(unsafe { decode_args(buf.as_ptr().cast()) }, args);
+        assert!(unsafe { decode_args(encode_args(&[]).unwrap().as_ptr().cast()) }.is_empty());
+        // an empty argument would terminate the list early
+        assert!(encode_args(&["".to_string()]).is_err());
+    }
+}
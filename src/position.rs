@@ -0,0 +1,134 @@
+//! Target-position mode. A strategy implementing `Strategy::target_position` says how many
+//! lots it wants to hold (signed, > 0 long) and leaves the orders to a `PositionManager`,
+//! which diffs the target against the position actually held plus the orders still in
+//! flight, and sends the closes and opens that make up the difference. Strategies then never
+//! track offsets themselves: a reversal closes before it opens, a position held on both
+//! sides is closed down on the side the target does not want, and an order that has not
+//! filled yet is not sent again on the next tick.
+use crate::strategy::Orders;
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, TickData};
+
+/// Long and short lots held once `in_flight` fills, starting from `held`.
+pub fn projected<'a>(held: (u32, u32), in_flight: impl IntoIterator<Item = &'a Order>) -> (i64, i64) {
+    let (mut long, mut short) = (held.0 as i64, held.1 as i64);
+    for order in in_flight {
+        let lots = order.lots as i64;
+        match (order.offset, order.direction) {
+            (OffsetFlagType::OPEN, DirectionType::BUY) => long += lots,
+            (OffsetFlagType::OPEN, DirectionType::SELL) => short += lots,
+            (OffsetFlagType::CLOSE, DirectionType::SELL) => long -= lots,
+            (OffsetFlagType::CLOSE, DirectionType::BUY) => short -= lots,
+        }
+    }
+    (long.max(0), short.max(0))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PositionManager {
+    /// the latest target, once the strategy has given one
+    target: Option<i32>,
+}
+
+impl PositionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn target(&self) -> Option<i32> {
+        self.target
+    }
+
+    /// Orders for strategy `name` that take the position from `held` (long, short lots),
+    /// with `in_flight` still to fill, to `target`: closes first, priced at the touch.
+    pub fn rebalance<'a>(
+        &mut self,
+        name: NameType,
+        tick: &TickData,
+        target: i32,
+        held: (u32, u32),
+        in_flight: impl IntoIterator<Item = &'a Order>,
+    ) -> Orders {
+        self.target = Some(target);
+        let (long, short) = projected(held, in_flight);
+        let (want_long, want_short) = (target.max(0) as i64, (-(target as i64)).max(0));
+        let order = |direction, offset, lots: i64| Order {
+            stg_name: name,
+            symbol: tick.symbol,
+            timestamp: tick.stamp,
+            price: match direction {
+                DirectionType::BUY => tick.ap1,
+                DirectionType::SELL => tick.bp1,
+            },
+            lots: lots as u32,
+            direction,
+            offset,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+        let mut orders = Orders::new();
+        if long > want_long {
+            orders.push(order(DirectionType::SELL, OffsetFlagType::CLOSE, long - want_long));
+        }
+        if short > want_short {
+            orders.push(order(DirectionType::BUY, OffsetFlagType::CLOSE, short - want_short));
+        }
+        if want_long > long {
+            orders.push(order(DirectionType::BUY, OffsetFlagType::OPEN, want_long - long));
+        }
+        if want_short > short {
+            orders.push(order(DirectionType::SELL, OffsetFlagType::OPEN, want_short - short));
+        }
+        orders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SymbolType;
+
+    #[test]
+    fn diffs_the_target_against_held_and_in_flight() {
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp: 1000,
+            last: 3500.0,
+            bp1: 3499.0,
+            ap1: 3501.0,
+            ..Default::default()
+        };
+        let name = NameType::from("target");
+        let mut manager = PositionManager::new();
+        let summary = |orders: &[Order]| orders.iter().map(|o| (o.direction, o.offset, o.lots)).collect::<Vec<_>>();
+
+        // long 2 → short 3: close the long, then open the short
+        let orders = manager.rebalance(name, &tick, -3, (2, 0), []);
+        assert_eq!(
+            summary(&orders),
+            [
+                (DirectionType::SELL, OffsetFlagType::CLOSE, 2),
+                (DirectionType::SELL, OffsetFlagType::OPEN, 3)
+            ]
+        );
+        assert_eq!((orders[0].price, orders[1].price), (3499.0, 3499.0));
+        assert_eq!(manager.target(), Some(-3));
+
+        // the same orders still in flight: nothing more to send
+        assert!(manager.rebalance(name, &tick, -3, (2, 0), &orders).is_empty());
+        // part of the open filled, the rest in flight, and the target eased to -1
+        let rest = Order { lots: 1, ..orders[1] };
+        assert_eq!(
+            summary(&manager.rebalance(name, &tick, -1, (0, 2), [&rest])),
+            [(DirectionType::BUY, OffsetFlagType::CLOSE, 2)]
+        );
+        // held on both sides: flat closes each
+        assert_eq!(
+            summary(&manager.rebalance(name, &tick, 0, (1, 1), [])),
+            [
+                (DirectionType::SELL, OffsetFlagType::CLOSE, 1),
+                (DirectionType::BUY, OffsetFlagType::CLOSE, 1)
+            ]
+        );
+    }
+}
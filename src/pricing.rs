@@ -0,0 +1,119 @@
+//! Order price aggressiveness, applied by the engine to every limit order a strategy sends,
+//! so how far through the book to price is set per strategy in config instead of each
+//! strategy hard-coding `ap1`/`bp1`.
+use crate::types::{DirectionType, Order, OrderType, TickData};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceOffset {
+    /// keep the price the strategy chose
+    #[default]
+    AsIs,
+    /// rest on the own side's touch: buy on the bid, sell on the ask
+    Join,
+    /// take the opposite touch and go `n` ticks through it: `Cross(0)` buys on the ask,
+    /// `Cross(2)` two ticks above it
+    Cross(u32),
+}
+
+impl PriceOffset {
+    /// Reprice `order` against `tick`'s top of book; `min_move` is the contract's tick size.
+    /// Market orders and sides without a quote keep their price.
+    pub fn apply(self, mut order: Order, tick: &TickData, min_move: f64) -> Order {
+        if order.order_type == OrderType::MARKET {
+            return order;
+        }
+        let (own, opposite, sign) = match order.direction {
+            DirectionType::BUY => (tick.bp1, tick.ap1, 1.0),
+            DirectionType::SELL => (tick.ap1, tick.bp1, -1.0),
+        };
+        let price = match self {
+            PriceOffset::AsIs => return order,
+            PriceOffset::Join => own,
+            PriceOffset::Cross(n) => opposite + sign * n as f64 * min_move,
+        };
+        if price.is_finite() && price > 0.0 {
+            order.price = price;
+        }
+        order
+    }
+}
+
+impl fmt::Display for PriceOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceOffset::AsIs => f.write_str("as-is"),
+            PriceOffset::Join => f.write_str("join"),
+            PriceOffset::Cross(0) => f.write_str("cross"),
+            PriceOffset::Cross(n) => write!(f, "cross:{}", n),
+        }
+    }
+}
+
+/// `as-is`, `join`, `cross` or `cross:<ticks>`.
+impl FromStr for PriceOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "as-is" => Ok(PriceOffset::AsIs),
+            "join" => Ok(PriceOffset::Join),
+            "cross" => Ok(PriceOffset::Cross(0)),
+            other => other
+                .strip_prefix("cross:")
+                .and_then(|n| n.parse().ok())
+                .map(PriceOffset::Cross)
+                .ok_or_else(|| format!("unknown price offset {:?}", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PriceOffset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{NameType, OffsetFlagType, SymbolType};
+
+    #[test]
+    fn prices_through_the_touch() {
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            bp1: 3500.0,
+            ap1: 3501.0,
+            ..Default::default()
+        };
+        let buy = Order {
+            stg_name: NameType::from("test"),
+            symbol: tick.symbol,
+            timestamp: 0,
+            price: 3499.0,
+            lots: 1,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+        let sell = Order {
+            direction: DirectionType::SELL,
+            ..buy
+        };
+        let price = |offset: &str, order: Order| offset.parse::<PriceOffset>().unwrap().apply(order, &tick, 1.0).price;
+        assert_eq!(price("as-is", buy), 3499.0);
+        assert_eq!(price("join", buy), 3500.0);
+        assert_eq!(price("cross", buy), 3501.0);
+        assert_eq!(price("cross:2", buy), 3503.0);
+        assert_eq!(price("join", sell), 3501.0);
+        assert_eq!(price("cross:2", sell), 3498.0);
+        assert_eq!(price("cross", buy.with_type(OrderType::MARKET)), 3499.0);
+        assert!("cross:x".parse::<PriceOffset>().is_err());
+        assert_eq!(PriceOffset::Cross(2).to_string(), "cross:2");
+    }
+}
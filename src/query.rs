@@ -0,0 +1,252 @@
+//! Read-only snapshot queries on the engine's query socket, answered as JSON for dashboards
+//! and ops tooling.
+//!
+//! Each request is one line of text:
+//! - `positions <strategy>`: positions, cash and PnL of every strategy with that name
+//! - `equity <symbol> [points]`: equity curve of each strategy on the symbol, the last
+//!   `points` samples (500 by default)
+//! - `pnl`: realized PnL and fees, in total and per strategy
+//! - `strategies [points [fills]]`: a snapshot of every strategy, with the last `points`
+//!   equity samples and `fills` fills of each (none by default)
+//! - `accounts`: every shared account's balance, netted margin and available funds, with
+//!   each member strategy's share
+//!
+//! Failures are answered with `{"error": "<reason>"}`.
+use crate::handoff::{OpenOrder, PositionState};
+use crate::perf_stats::PerfStats;
+use crate::types::SymbolType;
+use serde::Serialize;
+
+const DEFAULT_CURVE_POINTS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Positions(String),
+    Equity {
+        symbol: SymbolType,
+        points: usize,
+    },
+    Pnl,
+    Strategies {
+        points: usize,
+        fills: usize,
+    },
+    /// answered by the engine from its accounts rather than from strategy snapshots
+    Accounts,
+}
+
+impl Query {
+    pub fn parse(line: &str) -> Result<Query, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["positions", strategy] => Ok(Query::Positions(strategy.to_string())),
+            ["equity", symbol] => Ok(Query::Equity {
+                symbol: SymbolType::from(*symbol),
+                points: DEFAULT_CURVE_POINTS,
+            }),
+            ["equity", symbol, points] => Ok(Query::Equity {
+                symbol: SymbolType::from(*symbol),
+                points: points.parse().map_err(|_| format!("invalid point count {:?}", points))?,
+            }),
+            ["pnl"] => Ok(Query::Pnl),
+            ["accounts"] => Ok(Query::Accounts),
+            ["strategies", rest @ ..] if rest.len() <= 2 => {
+                let count = |i: usize| {
+                    rest.get(i)
+                        .map_or(Ok(0), |n: &&str| n.parse().map_err(|_| format!("invalid count {:?}", n)))
+                };
+                Ok(Query::Strategies {
+                    points: count(0)?,
+                    fills: count(1)?,
+                })
+            }
+            [] => Err("empty query".into()),
+            _ => Err(format!("unknown query {:?}", line)),
+        }
+    }
+}
+
+/// What selects the strategies a worker reports on.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotFilter {
+    pub strategy: Option<String>,
+    pub symbol: Option<SymbolType>,
+    /// trailing equity samples to include; none when 0
+    pub curve_points: usize,
+    /// latest fills to include; none when 0
+    pub recent_fills: usize,
+    /// include every fill booked at or after this stamp, with its costs
+    pub fills_since: Option<i64>,
+}
+
+impl SnapshotFilter {
+    pub fn matches(&self, strategy: &str, symbol: &SymbolType) -> bool {
+        self.strategy.as_deref().is_none_or(|s| s == strategy) && self.symbol.is_none_or(|s| s == *symbol)
+    }
+}
+
+/// One strategy as its worker sees it between two ticks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StrategySnapshot {
+    pub strategy: String,
+    pub symbol: String,
+    pub worker: usize,
+    pub equity: f64,
+    pub available_cash: f64,
+    /// last traded price of the symbol the worker has seen; 0 before its first tick
+    pub last_price: f64,
+    /// value of a one-point move per lot in the account's currency, see `ContractInfo::point_value`
+    pub multiplier: f64,
+    pub realized_pnl: f64,
+    pub total_fee: f64,
+    pub long: Option<PositionState>,
+    pub short: Option<PositionState>,
+    pub open_orders: usize,
+    pub fills: usize,
+    /// orders the risk checks refused
+    pub rejected_orders: u64,
+    /// flattened by a circuit breaker and not re-armed yet
+    pub halted: bool,
+    /// running without sending orders, see `CtaEngine::pause_strategy`
+    pub paused: bool,
+    /// shared account the strategy trades out of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub equity_curve: Vec<f64>,
+    /// points the curve ever had, see `EquityCurve::samples`
+    pub curve_samples: u64,
+    /// ratios and drawdowns of the equity curve so far
+    pub performance: PerfStats,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recent_fills: Vec<OpenOrder>,
+    /// fills selected by `SnapshotFilter::fills_since`, oldest first, among the last
+    /// `perf_tracker::RECENT_FILLS`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub booked_fills: Vec<FillDetail>,
+}
+
+/// A booked fill with its commission and the PnL it realized.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FillDetail {
+    #[serde(flatten)]
+    pub fill: OpenOrder,
+    pub fee: f64,
+    pub realized_pnl: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct StrategyPnl<'a> {
+    strategy: &'a str,
+    symbol: &'a str,
+    realized_pnl: f64,
+    total_fee: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct PnlSummary<'a> {
+    realized_pnl: f64,
+    total_fee: f64,
+    strategies: Vec<StrategyPnl<'a>>,
+}
+
+/// The filter that collects what `query` needs.
+pub fn filter(query: &Query) -> SnapshotFilter {
+    match query {
+        Query::Positions(strategy) => SnapshotFilter {
+            strategy: Some(strategy.clone()),
+            ..Default::default()
+        },
+        Query::Equity { symbol, points } => SnapshotFilter {
+            symbol: Some(*symbol),
+            curve_points: *points,
+            ..Default::default()
+        },
+        Query::Strategies { points, fills } => SnapshotFilter {
+            curve_points: *points,
+            recent_fills: *fills,
+            ..Default::default()
+        },
+        Query::Pnl | Query::Accounts => SnapshotFilter::default(),
+    }
+}
+
+/// JSON answer to `query` from the snapshots its filter collected.
+pub fn answer(query: &Query, mut snapshots: Vec<StrategySnapshot>) -> Result<String, String> {
+    snapshots.sort_by(|a, b| (&a.symbol, &a.strategy).cmp(&(&b.symbol, &b.strategy)));
+    let json = match query {
+        Query::Positions(strategy) if snapshots.is_empty() => return Err(format!("unknown strategy {:?}", strategy)),
+        Query::Equity { symbol, .. } if snapshots.is_empty() => return Err(format!("no strategy on {:?}", symbol.as_str())),
+        Query::Accounts => return Err("accounts are not answered from strategy snapshots".into()),
+        Query::Pnl => serde_json::to_string(&PnlSummary {
+            realized_pnl: snapshots.iter().map(|s| s.realized_pnl).sum(),
+            total_fee: snapshots.iter().map(|s| s.total_fee).sum(),
+            strategies: snapshots
+                .iter()
+                .map(|s| StrategyPnl {
+                    strategy: &s.strategy,
+                    symbol: &s.symbol,
+                    realized_pnl: s.realized_pnl,
+                    total_fee: s.total_fee,
+                })
+                .collect(),
+        }),
+        Query::Positions(_) | Query::Equity { .. } | Query::Strategies { .. } => serde_json::to_string(&snapshots),
+    };
+    json.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(strategy: &str, realized_pnl: f64) -> StrategySnapshot {
+        StrategySnapshot {
+            strategy: strategy.into(),
+            symbol: "rb2505".into(),
+            worker: 0,
+            equity: 1e6 + realized_pnl,
+            available_cash: 1e6 + realized_pnl,
+            last_price: 3500.0,
+            multiplier: 10.0,
+            realized_pnl,
+            total_fee: 5.0,
+            long: None,
+            short: None,
+            open_orders: 0,
+            fills: 0,
+            rejected_orders: 0,
+            halted: false,
+            paused: false,
+            account: None,
+            equity_curve: Vec::new(),
+            curve_samples: 0,
+            performance: PerfStats::default(),
+            recent_fills: Vec::new(),
+            booked_fills: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn answers_queries_as_json() {
+        assert_eq!(
+            Query::parse("equity rb2505 10"),
+            Ok(Query::Equity {
+                symbol: SymbolType::from("rb2505"),
+                points: 10
+            })
+        );
+        assert!(Query::parse("positions").is_err());
+        assert_eq!(Query::parse("strategies 300"), Ok(Query::Strategies { points: 300, fills: 0 }));
+
+        let pnl = answer(&Query::Pnl, vec![snapshot("B", 200.0), snapshot("A", -50.0)]).unwrap();
+        let pnl: serde_json::Value = serde_json::from_str(&pnl).unwrap();
+        assert_eq!(pnl["realized_pnl"], 150.0);
+        assert_eq!(pnl["total_fee"], 10.0);
+        assert_eq!(pnl["strategies"][0]["strategy"], "A");
+
+        let query = Query::parse("positions C").unwrap();
+        assert!(filter(&query).matches("C", &SymbolType::from("MA505")));
+        assert_eq!(answer(&query, Vec::new()), Err("unknown strategy \"C\"".into()));
+    }
+}
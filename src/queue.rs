@@ -0,0 +1,177 @@
+//! Bounded lock-free queue from the receive loop to one worker. The consumer busy-spins for
+//! a while after the queue runs dry, so a tick arriving mid-burst is picked up without a
+//! wake-up, and only then parks; the producer unparks it on the next push.
+use crossbeam_queue::ArrayQueue;
+use std::cell::Cell;
+use std::fmt;
+use std::hint;
+use std::marker::PhantomData;
+use std::sync::atomic::{self, AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::{self, Thread};
+
+struct Shared<T> {
+    queue: ArrayQueue<T>,
+    /// the consumer thread, recorded the first time it parks
+    consumer: OnceLock<Thread>,
+    parked: AtomicBool,
+    sender_gone: AtomicBool,
+    receiver_gone: AtomicBool,
+}
+
+impl<T> Shared<T> {
+    fn wake(&self) {
+        // pairs with the fence in `Receiver::recv`: either it sees our push, or we see `parked`
+        atomic::fence(Ordering::SeqCst);
+        if self.parked.load(Ordering::Relaxed)
+            && let Some(consumer) = self.consumer.get()
+        {
+            consumer.unpark();
+        }
+    }
+}
+
+/// The receiving worker has exited; the message is handed back.
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+/// The producing half. It is neither `Clone` nor `Sync`, so there is one producer thread.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    spin: u32,
+}
+
+/// A queue holding up to `capacity` messages whose consumer spins `spin` times on an empty
+/// queue before parking.
+pub fn channel<T>(capacity: usize, spin: u32) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: ArrayQueue::new(capacity),
+        consumer: OnceLock::new(),
+        parked: AtomicBool::new(false),
+        sender_gone: AtomicBool::new(false),
+        receiver_gone: AtomicBool::new(false),
+    });
+    let sender = Sender {
+        shared: Arc::clone(&shared),
+        _not_sync: PhantomData,
+    };
+    (sender, Receiver { shared, spin })
+}
+
+impl<T> Sender<T> {
+    /// Queue `msg`, waiting for room while the queue is full.
+    pub fn send(&self, mut msg: T) -> Result<(), SendError<T>> {
+        loop {
+            if self.shared.receiver_gone.load(Ordering::Acquire) {
+                return Err(SendError(msg));
+            }
+            match self.shared.queue.push(msg) {
+                Ok(()) => break,
+                Err(back) => {
+                    msg = back;
+                    // the consumer may be parked on a wake-up it has not seen yet
+                    self.shared.wake();
+                    thread::yield_now();
+                }
+            }
+        }
+        self.shared.wake();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.queue.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.shared.queue.is_full()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.queue.capacity()
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.sender_gone.store(true, Ordering::Release);
+        self.shared.wake();
+    }
+}
+
+impl<T> Receiver<T> {
+    /// The next message, or `None` once the sender is gone and the queue is drained.
+    pub fn recv(&self) -> Option<T> {
+        let mut spins = 0;
+        loop {
+            if let Some(msg) = self.shared.queue.pop() {
+                return Some(msg);
+            }
+            if self.shared.sender_gone.load(Ordering::Acquire) {
+                // anything pushed before the drop is visible now
+                return self.shared.queue.pop();
+            }
+            if spins < self.spin {
+                spins += 1;
+                hint::spin_loop();
+                continue;
+            }
+            self.shared.consumer.get_or_init(thread::current);
+            self.shared.parked.store(true, Ordering::Relaxed);
+            atomic::fence(Ordering::SeqCst);
+            if self.shared.queue.is_empty() && !self.shared.sender_gone.load(Ordering::Acquire) {
+                thread::park();
+            }
+            self.shared.parked.store(false, Ordering::Relaxed);
+            spins = 0;
+        }
+    }
+}
+
+impl<T> Iterator for Receiver<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.recv()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_gone.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delivers_in_order_through_a_full_queue() {
+        let (tx, rx) = channel::<u64>(4, 100);
+        let consumer = thread::spawn(move || rx.collect::<Vec<_>>());
+        for i in 0..10_000 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        assert_eq!(consumer.join().unwrap(), (0..10_000).collect::<Vec<_>>());
+
+        let (tx, rx) = channel::<u64>(4, 0);
+        drop(rx);
+        assert!(tx.send(1).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Getting ZMQ sockets back after an error instead of giving up on them. ZMQ reconnects a
+//! socket's transport by itself when the peer goes away, e.g. a publisher restarting, and
+//! `ReconnectPolicy::apply` has it back off as set here; a socket that errors outright is
+//! dropped and rebuilt, its subscriptions applied again, with the same exponential backoff
+//! between attempts that fail.
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// The delay before each reconnect attempt doubles from `initial_ms` up to `max_ms`.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectPolicy {
+    pub initial_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_ms: 100,
+            max_ms: 10_000,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Have ZMQ back off alike when reconnecting `socket`'s transport.
+    pub fn apply(&self, socket: &zmq::Socket) -> zmq::Result<()> {
+        socket.set_reconnect_ivl(self.initial_ms.min(i32::MAX as u64) as i32)?;
+        socket.set_reconnect_ivl_max(self.max_ms.min(i32::MAX as u64) as i32)
+    }
+}
+
+/// Whether a socket error is the engine shutting down, a signal or the context being
+/// terminated, rather than something to reconnect from.
+pub fn is_shutdown(e: zmq::Error) -> bool {
+    matches!(e, zmq::Error::EINTR | zmq::Error::ETERM)
+}
+
+/// The delays between attempts of one reconnect, see `ReconnectPolicy`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: ReconnectPolicy,
+    attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        Self { policy, attempts: 0 }
+    }
+
+    /// Failed attempts since the last success.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Count a failed attempt; how long to wait before the next one.
+    pub fn next_delay(&mut self) -> Duration {
+        let ms = self.policy.initial_ms.saturating_mul(1 << self.attempts.min(32)).min(self.policy.max_ms);
+        self.attempts += 1;
+        Duration::from_millis(ms)
+    }
+
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// Count a failed attempt and sleep until the next one is due, or `shutdown` is set.
+    pub fn wait(&mut self, shutdown: &AtomicBool) -> Duration {
+        let delay = self.next_delay();
+        let until = Instant::now() + delay;
+        while !shutdown.load(Ordering::Relaxed) && Instant::now() < until {
+            thread::sleep((until - Instant::now()).min(Duration::from_millis(100)));
+        }
+        delay
+    }
+
+    /// Call `attempt` until it succeeds, backing off between failures; `None` once `shutdown`
+    /// is set, which is checked while waiting.
+    pub fn retry<T>(&mut self, what: &str, shutdown: &AtomicBool, mut attempt: impl FnMut() -> zmq::Result<T>) -> Option<T> {
+        loop {
+            if shutdown.load(Ordering::Relaxed) {
+                return None;
+            }
+            match attempt() {
+                Ok(value) => {
+                    if self.attempts > 0 {
+                        info!(what, attempts = self.attempts + 1, "reconnected");
+                    }
+                    self.reset();
+                    return Some(value);
+                }
+                Err(e) => {
+                    warn!(what, error = ?e, attempt = self.attempts + 1, "failed to reconnect");
+                    self.wait(shutdown);
+                }
+            }
+        }
+    }
+}
+
+/// A worker's PUSH socket to the order gateway, rebuilt when a send fails. A worker cannot
+/// stall its strategies to wait out a backoff: while the socket is down, sends fail at once
+/// and each one past the backoff delay tries to rebuild it.
+pub struct OrderPusher {
+    ctx: zmq::Context,
+    uri: String,
+    policy: ReconnectPolicy,
+    linger_ms: i32,
+    socket: Option<zmq::Socket>,
+    backoff: Backoff,
+    retry_at: Option<Instant>,
+}
+
+impl OrderPusher {
+    pub fn connect(ctx: &zmq::Context, uri: &str, policy: ReconnectPolicy) -> zmq::Result<Self> {
+        let mut pusher = Self {
+            ctx: ctx.clone(),
+            uri: uri.into(),
+            policy,
+            linger_ms: 0,
+            socket: None,
+            backoff: Backoff::new(policy),
+            retry_at: None,
+        };
+        pusher.socket = Some(pusher.open()?);
+        Ok(pusher)
+    }
+
+    fn open(&self) -> zmq::Result<zmq::Socket> {
+        let socket = self.ctx.socket(zmq::PUSH)?;
+        // unlimited SNDHWM, send won't block
+        socket.set_sndhwm(0)?;
+        socket.set_linger(self.linger_ms)?;
+        self.policy.apply(&socket)?;
+        socket.connect(&self.uri)?;
+        Ok(socket)
+    }
+
+    /// How long unsent orders are kept once the socket is closed, for this and later sockets.
+    pub fn set_linger(&mut self, linger_ms: i32) -> zmq::Result<()> {
+        self.linger_ms = linger_ms;
+        self.socket.as_ref().map_or(Ok(()), |s| s.set_linger(linger_ms))
+    }
+
+    /// Send `bytes`; a socket that fails is dropped and, when the backoff allows, rebuilt and
+    /// sent on once more.
+    pub fn send(&mut self, bytes: &[u8]) -> zmq::Result<()> {
+        let failed = match self.socket {
+            Some(ref socket) => match socket.send(bytes, 0) {
+                Ok(()) => return Ok(()),
+                Err(e) if is_shutdown(e) => return Err(e),
+                Err(e) => e,
+            },
+            None => zmq::Error::ENOTSOCK,
+        };
+        if self.socket.take().is_some() {
+            warn!(uri = %self.uri, error = ?failed, "order socket failed, reconnecting");
+        }
+        if self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Err(failed);
+        }
+        match self.open() {
+            Ok(socket) => {
+                info!(uri = %self.uri, attempts = self.backoff.attempts() + 1, "order socket reconnected");
+                self.backoff.reset();
+                self.retry_at = None;
+                let sent = socket.send(bytes, 0);
+                self.socket = Some(socket);
+                sent
+            }
+            Err(e) => {
+                let delay = self.backoff.next_delay();
+                warn!(uri = %self.uri, error = ?e, retry_in_ms = delay.as_millis() as u64, "failed to reconnect the order socket");
+                self.retry_at = Some(Instant::now() + delay);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl OrderPusher {
+    /// A pusher whose socket is down and not due to be rebuilt for an hour, every send failing.
+    pub(crate) fn down(ctx: &zmq::Context) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            uri: "nowhere".into(),
+            policy: ReconnectPolicy::default(),
+            linger_ms: 0,
+            socket: None,
+            backoff: Backoff::new(ReconnectPolicy::default()),
+            retry_at: Some(Instant::now() + Duration::from_secs(3600)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_the_delay_up_to_the_cap() {
+        let mut backoff = Backoff::new(ReconnectPolicy {
+            initial_ms: 100,
+            max_ms: 1000,
+        });
+        let delays: Vec<u128> = (0..6).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+
+        let mut failures = 2;
+        let shutdown = AtomicBool::new(false);
+        let mut backoff = Backoff::new(ReconnectPolicy { initial_ms: 1, max_ms: 2 });
+        let attempt = || {
+            if failures > 0 {
+                Err(zmq::Error::EINVAL).inspect_err(|_| failures -= 1)
+            } else {
+                Ok(7)
+            }
+        };
+        assert_eq!(backoff.retry("test", &shutdown, attempt), Some(7));
+        assert_eq!(backoff.attempts(), 0);
+        shutdown.store(true, Ordering::Relaxed);
+        assert_eq!(backoff.retry("test", &shutdown, || Ok(1)), None);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::timeutil;
+use crate::types::{SymbolType, TickData};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+/// Size of one record in a tick file: the current `TickData` layout, no header.
+pub const TICK_RECORD_SIZE: usize = mem::size_of::<TickData>();
+
+struct DailyFile {
+    date: u32,
+    writer: BufWriter<File>,
+}
+
+/// Archives ticks to `<root>/<yyyymmdd>/<symbol>.bin`, one append-only file per symbol per
+/// local (CST) calendar day. Restarting within a day appends to the same file.
+pub struct TickRecorder {
+    root: PathBuf,
+    files: HashMap<SymbolType, DailyFile>,
+}
+
+impl TickRecorder {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Path of the file holding `symbol` on `date` (yyyymmdd).
+    pub fn tick_path(root: &Path, date: u32, symbol: &SymbolType) -> PathBuf {
+        root.join(date.to_string()).join(format!("{}.bin", symbol.as_str()))
+    }
+
+    pub fn record(&mut self, tick: &TickData) -> io::Result<()> {
+        let date = timeutil::local_date(tick.stamp);
+
+        // rotate when the first tick of a new day arrives
+        let rotate = self.files.get(&tick.symbol).is_none_or(|f| f.date != date);
+        if rotate {
+            if let Some(mut old) = self.files.remove(&tick.symbol) {
+                old.writer.flush()?;
+            }
+            let path = Self::tick_path(&self.root, date, &tick.symbol);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            self.files.insert(
+                tick.symbol,
+                DailyFile {
+                    date,
+                    writer: BufWriter::new(file),
+                },
+            );
+        }
+
+        let bytes: &[u8] = unsafe { std::slice::from_raw_parts(tick as *const TickData as *const u8, TICK_RECORD_SIZE) };
+        let file = self.files.get_mut(&tick.symbol).expect("file opened above");
+        file.writer.write_all(bytes)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        for file in self.files.values_mut() {
+            file.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TickRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!(error = ?e, "failed to flush tick files");
+        }
+    }
+}
+
+/// Read every tick from a recorded file. A trailing partial record (e.g. after a crash) is ignored.
+pub fn read_ticks<P: AsRef<Path>>(path: P) -> io::Result<Vec<TickData>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let ticks = bytes
+        .chunks_exact(TICK_RECORD_SIZE)
+        .map(|chunk| unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const TickData) })
+        .collect();
+    Ok(ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_ticks_per_symbol_and_local_date_and_reads_them_back() {
+        let root = std::env::temp_dir().join(format!("fustg_recorder_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (rb, ma) = (SymbolType::from("rb2505"), SymbolType::from("MA505"));
+        let tick = |symbol, stamp, last| TickData {
+            symbol,
+            stamp,
+            last,
+            ..Default::default()
+        };
+        // the night session runs past local midnight into the next calendar date's file
+        let ticks = [
+            tick(rb, timeutil::local_stamp(20250506, 14 * 3600 + 59 * 60), 3500.0),
+            tick(ma, timeutil::local_stamp(20250506, 21 * 3600), 2400.0),
+            tick(rb, timeutil::local_stamp(20250506, 23 * 3600 + 59 * 60), 3501.0),
+            tick(rb, timeutil::local_stamp(20250507, 30 * 60), 3502.0),
+        ];
+        let mut recorder = TickRecorder::new(&root);
+        for t in &ticks {
+            recorder.record(t).unwrap();
+        }
+        drop(recorder);
+
+        let read = |date, symbol| read_ticks(TickRecorder::tick_path(&root, date, &symbol)).unwrap();
+        let lasts = |ticks: Vec<TickData>| ticks.iter().map(|t| t.last).collect::<Vec<_>>();
+        assert_eq!(root.join("20250506").join("rb2505.bin"), TickRecorder::tick_path(&root, 20250506, &rb));
+        assert_eq!(lasts(read(20250506, rb)), [3500.0, 3501.0]);
+        assert_eq!(lasts(read(20250506, ma)), [2400.0]);
+        let after_midnight = read(20250507, rb);
+        assert_eq!((after_midnight[0].stamp, after_midnight[0].symbol), (ticks[3].stamp, rb));
+
+        // a restart appends, and a torn last record is ignored
+        TickRecorder::new(&root).record(&tick(rb, ticks[3].stamp + 500, 3503.0)).unwrap();
+        let path = TickRecorder::tick_path(&root, 20250507, &rb);
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&[0; 7]).unwrap();
+        assert_eq!(lasts(read_ticks(&path).unwrap()), [3502.0, 3503.0]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
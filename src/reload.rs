@@ -0,0 +1,135 @@
+//! Hot reload of the fees and margin table. Exchanges raise margin rates intraday when markets
+//! turn volatile; `FeesWatcher` notices the table file being rewritten and hands back the new
+//! table, which the engine pushes to the workers so each tracker swaps its `ContractInfo`
+//! between two ticks.
+//!
+//! The file's directory is watched rather than the file, so editors that save by writing a
+//! new file and renaming it over the old one are still seen. Events are only collected as
+//! they arrive and the file is read on `poll`, so a burst of writes becomes one reload.
+use crate::config::{self, ContractInfo};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+pub struct FeesWatcher {
+    path: PathBuf,
+    /// kept alive for as long as events are wanted
+    _watcher: RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+impl FeesWatcher {
+    pub fn new<P: AsRef<Path>>(path: P) -> notify::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file_name = path.file_name().map(|f| f.to_os_string());
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // only events touching the table file; other files in the directory are ignored
+            let relevant = event
+                .as_ref()
+                .map_or(true, |e| e.paths.iter().any(|p| p.file_name().map(|f| f.to_os_string()) == file_name));
+            if relevant {
+                let _ = tx.send(event);
+            }
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            path,
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The reloaded table if the file changed since the last call, or why it could not be
+    /// read; `None` if nothing changed.
+    pub fn poll(&self) -> Option<Result<HashMap<String, ContractInfo>, String>> {
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if event.kind.is_create() || event.kind.is_modify() => changed = true,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.to_string())),
+            }
+        }
+        changed.then(|| config::load_fees(&self.path).map_err(|e| format!("{:#}", e)))
+    }
+}
+
+/// Contracts whose entry in `new` differs from `old`, including ones `old` lacked.
+pub fn changed_contracts<'a>(old: &HashMap<String, ContractInfo>, new: &'a HashMap<String, ContractInfo>) -> Vec<(&'a str, ContractInfo)> {
+    let mut changed: Vec<(&str, ContractInfo)> = new
+        .iter()
+        .filter(|(contract, info)| old.get(contract.as_str()) != Some(info))
+        .map(|(contract, info)| (contract.as_str(), *info))
+        .collect();
+    changed.sort_by_key(|(contract, _)| *contract);
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn reloads_the_table_when_the_file_is_rewritten() {
+        let dir = std::env::temp_dir().join(format!("fees-reload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fees.toml");
+        let table = |rate: f64| {
+            let fields = [
+                "min_move = 1.0",
+                "open_fee_rate = 1e-4",
+                "open_fee_fixed = 0.0",
+                "close_fee_rate = 1e-4",
+                "close_fee_fixed = 0.0",
+            ];
+            let more = [
+                "close_today_fee_rate = 1e-4",
+                "close_today_fee_fixed = 0.0",
+                "long_margin_fixed = 0.0",
+                "short_margin_fixed = 0.0",
+            ];
+            format!(
+                "['SHFE.rb']\nmultiplier = 10.0\n{}\n{}\nlong_margin_rate = {}\nshort_margin_rate = {}\n",
+                fields.join("\n"),
+                more.join("\n"),
+                rate,
+                rate
+            )
+        };
+        fs::write(&path, table(0.1)).unwrap();
+        let old = config::load_fees(&path).unwrap();
+
+        let watcher = FeesWatcher::new(&path).unwrap();
+        assert!(watcher.poll().is_none());
+        fs::write(dir.join("other.toml"), "x = 1").unwrap();
+        fs::write(&path, table(0.15)).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let new = loop {
+            // a write caught halfway fails to parse; the next event brings the whole file
+            if let Some(Ok(reloaded)) = watcher.poll() {
+                break reloaded;
+            }
+            assert!(Instant::now() < deadline, "no reload after rewriting the table");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        fs::remove_dir_all(&dir).unwrap();
+
+        let changed = changed_contracts(&old, &new);
+        assert_eq!(changed.len(), 1);
+        assert_eq!((changed[0].0, changed[0].1.long_margin_rate), ("SHFE.rb", 0.15));
+        assert!(changed_contracts(&new, &new).is_empty());
+    }
+}
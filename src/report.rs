@@ -0,0 +1,379 @@
+//! Daily settlement report: what each strategy and the portfolio did since the previous
+//! report (PnL, fees, trades, intraday drawdown, orders the risk checks refused) plus the
+//! risk report over the positions carried into the next session, rendered as Markdown or
+//! HTML. The engine builds one after the settlement time each day (see
+//! `CtaEngine::enable_daily_report`) and hands it to `on_daily_report`; `save`,
+//! `post_webhook` and `send_mail` deliver it from there.
+use crate::query::StrategySnapshot;
+use crate::risk::RiskReport;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const COLUMNS: [&str; 8] = ["strategy", "symbol", "pnl", "realized", "fees", "trades", "drawdown", "equity"];
+
+/// Receives each daily report, on the engine's receive thread.
+pub type DailyReportCallback = Box<dyn FnMut(&DailyReport) + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReportConfig {
+    /// CST seconds of day after which the day's report is built, once settlement prices are in
+    pub settle_secs: u32,
+    /// stress move applied to each product in the risk section
+    pub shock: f64,
+    /// VaR99 above this is listed as a breach
+    pub var_limit: Option<f64>,
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            settle_secs: 15 * 3600 + 30 * 60,
+            shock: 0.03,
+            var_limit: None,
+        }
+    }
+}
+
+/// One strategy's day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyDay {
+    pub strategy: String,
+    pub symbol: String,
+    /// change in equity, realized and floating, net of fees
+    pub pnl: f64,
+    pub realized_pnl: f64,
+    pub fees: f64,
+    pub trades: usize,
+    /// largest fall from a high of the day's equity curve
+    pub max_drawdown: f64,
+    pub equity: f64,
+    pub rejected_orders: u64,
+}
+
+/// Where a strategy stood at the previous report.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mark {
+    equity: f64,
+    realized_pnl: f64,
+    fees: f64,
+    fills: usize,
+    rejected_orders: u64,
+    curve_samples: u64,
+}
+
+/// Marks per `(strategy, symbol)`; a strategy without one is measured from its start.
+pub type Marks = HashMap<(String, String), Mark>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyReport {
+    /// trading day, `yyyymmdd`
+    pub date: u32,
+    pub strategies: Vec<StrategyDay>,
+    pub risk: Option<RiskReport>,
+    /// limits crossed during the day, one line each
+    pub breaches: Vec<String>,
+}
+
+/// Build the report for `date` from full snapshots (every equity sample and fill), measuring
+/// each strategy against its mark and moving the marks forward.
+pub fn build(date: u32, snapshots: &[StrategySnapshot], marks: &mut Marks, risk: Option<RiskReport>, config: &ReportConfig) -> DailyReport {
+    let mut strategies = Vec::with_capacity(snapshots.len());
+    let mut breaches = Vec::new();
+    for s in snapshots {
+        let key = (s.strategy.clone(), s.symbol.clone());
+        let now = Mark {
+            equity: s.equity,
+            realized_pnl: s.realized_pnl,
+            fees: s.total_fee,
+            fills: s.fills,
+            rejected_orders: s.rejected_orders,
+            curve_samples: s.curve_samples,
+        };
+        let start = marks.insert(key, now).unwrap_or(Mark {
+            equity: s.equity_curve.first().copied().unwrap_or(s.equity),
+            ..Default::default()
+        });
+        // the curve may be resampled into a ring: count back the points added since the mark
+        let since_mark = s.curve_samples.saturating_sub(start.curve_samples) as usize;
+        let day = StrategyDay {
+            strategy: s.strategy.clone(),
+            symbol: s.symbol.clone(),
+            pnl: s.equity - start.equity,
+            realized_pnl: s.realized_pnl - start.realized_pnl,
+            fees: s.total_fee - start.fees,
+            trades: s.fills.saturating_sub(start.fills),
+            max_drawdown: max_drawdown(&s.equity_curve[s.equity_curve.len().saturating_sub(since_mark)..]),
+            equity: s.equity,
+            rejected_orders: s.rejected_orders.saturating_sub(start.rejected_orders),
+        };
+        if day.rejected_orders > 0 {
+            breaches.push(format!(
+                "{} on {}: {} orders blocked by risk checks",
+                day.strategy, day.symbol, day.rejected_orders
+            ));
+        }
+        strategies.push(day);
+    }
+    if let (Some(risk), Some(limit)) = (&risk, config.var_limit)
+        && risk.var_99 > limit
+    {
+        breaches.push(format!("VaR99 {:.0} over the {:.0} limit", risk.var_99, limit));
+    }
+    strategies.sort_by(|a, b| (&a.strategy, &a.symbol).cmp(&(&b.strategy, &b.symbol)));
+    DailyReport {
+        date,
+        strategies,
+        risk,
+        breaches,
+    }
+}
+
+fn max_drawdown(curve: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut worst: f64 = 0.0;
+    for &e in curve {
+        peak = peak.max(e);
+        worst = worst.max(peak - e);
+    }
+    worst
+}
+
+impl DailyReport {
+    pub fn pnl(&self) -> f64 {
+        self.strategies.iter().map(|s| s.pnl).sum()
+    }
+
+    pub fn fees(&self) -> f64 {
+        self.strategies.iter().map(|s| s.fees).sum()
+    }
+
+    pub fn trades(&self) -> usize {
+        self.strategies.iter().map(|s| s.trades).sum()
+    }
+
+    /// Subject line for mail and chat.
+    pub fn title(&self) -> String {
+        format!(
+            "fustg daily report {}: PnL {:.0}, {} breaches",
+            self.date,
+            self.pnl(),
+            self.breaches.len()
+        )
+    }
+
+    fn rows(&self) -> Vec<[String; 8]> {
+        self.strategies
+            .iter()
+            .map(|s| {
+                [
+                    s.strategy.clone(),
+                    s.symbol.clone(),
+                    format!("{:.2}", s.pnl),
+                    format!("{:.2}", s.realized_pnl),
+                    format!("{:.2}", s.fees),
+                    s.trades.to_string(),
+                    format!("{:.2}", s.max_drawdown),
+                    format!("{:.2}", s.equity),
+                ]
+            })
+            .collect()
+    }
+
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Daily report {}\n", self.date);
+        let _ = writeln!(
+            out,
+            "Portfolio PnL **{:.2}**, fees {:.2}, {} trades.\n",
+            self.pnl(),
+            self.fees(),
+            self.trades()
+        );
+        let _ = writeln!(out, "| {} |", COLUMNS.join(" | "));
+        let _ = writeln!(out, "|{}", "---|".repeat(COLUMNS.len()));
+        for row in self.rows() {
+            let _ = writeln!(out, "| {} |", row.join(" | "));
+        }
+        let _ = writeln!(out, "\n## Breaches\n");
+        if self.breaches.is_empty() {
+            let _ = writeln!(out, "None.");
+        }
+        for breach in &self.breaches {
+            let _ = writeln!(out, "- {}", breach);
+        }
+        if let Some(ref risk) = self.risk {
+            let _ = writeln!(out, "\n## Risk\n\n```\n{}```", risk.render());
+        }
+        out
+    }
+
+    pub fn html(&self) -> String {
+        let cell = |tag: &str, text: &str| format!("<{tag}>{}</{tag}>", escape(text));
+        let mut out = String::new();
+        let _ = write!(out, "<html><body><h1>Daily report {}</h1>", self.date);
+        let _ = write!(
+            out,
+            "<p>Portfolio PnL <b>{:.2}</b>, fees {:.2}, {} trades.</p><table border=\"1\" cellspacing=\"0\" cellpadding=\"4\"><tr>",
+            self.pnl(),
+            self.fees(),
+            self.trades()
+        );
+        for h in COLUMNS {
+            out.push_str(&cell("th", h));
+        }
+        out.push_str("</tr>");
+        for row in self.rows() {
+            out.push_str("<tr>");
+            for value in &row {
+                out.push_str(&cell("td", value));
+            }
+            out.push_str("</tr>");
+        }
+        out.push_str("</table><h2>Breaches</h2><ul>");
+        if self.breaches.is_empty() {
+            out.push_str("<li>None.</li>");
+        }
+        for breach in &self.breaches {
+            out.push_str(&cell("li", breach));
+        }
+        out.push_str("</ul>");
+        if let Some(ref risk) = self.risk {
+            let _ = write!(out, "<h2>Risk</h2><pre>{}</pre>", escape(&risk.render()));
+        }
+        out.push_str("</body></html>");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Write `<dir>/<date>.md` and `<dir>/<date>.html`; returns the Markdown path.
+pub fn save<P: AsRef<Path>>(dir: P, report: &DailyReport) -> io::Result<PathBuf> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.md", report.date));
+    fs::write(&path, report.markdown())?;
+    fs::write(dir.join(format!("{}.html", report.date)), report.html())?;
+    Ok(path)
+}
+
+/// POST `body` to a plain `http://host[:port]/path` webhook, e.g. a chat bot relay; fails
+/// unless the reply is a 2xx.
+pub fn post_webhook(url: &str, content_type: &str, body: &str) -> io::Result<()> {
+    let invalid = |why: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {:?}", why, url));
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid("only http:// webhooks are supported"))?;
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = if path.is_empty() { "/" } else { path };
+    let host = authority.split(':').next().unwrap_or(authority);
+    let addr = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        content_type,
+        body.len(),
+        body
+    )?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    let status = reply.split_whitespace().nth(1).unwrap_or("");
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("webhook answered {:?}", reply.lines().next().unwrap_or(""))))
+    }
+}
+
+/// Mail the report as HTML through the local `sendmail`.
+pub fn send_mail(to: &str, report: &DailyReport) -> io::Result<()> {
+    let mut child = Command::new("sendmail").arg("-t").stdin(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take().expect("sendmail stdin is piped");
+    write!(
+        stdin,
+        "To: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\n\r\n{}",
+        to,
+        report.title(),
+        report.html()
+    )?;
+    // sendmail reads the message until end of input
+    drop(stdin);
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("sendmail exited with {}", status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(equity_curve: Vec<f64>, realized_pnl: f64, fills: usize, rejected_orders: u64) -> StrategySnapshot {
+        StrategySnapshot {
+            strategy: "aberration_20".into(),
+            symbol: "rb2505".into(),
+            worker: 0,
+            equity: *equity_curve.last().unwrap(),
+            available_cash: 0.0,
+            last_price: 3500.0,
+            multiplier: 10.0,
+            realized_pnl,
+            total_fee: fills as f64 * 2.0,
+            long: None,
+            short: None,
+            open_orders: 0,
+            fills,
+            rejected_orders,
+            halted: false,
+            paused: false,
+            account: None,
+            curve_samples: equity_curve.len() as u64,
+            performance: Default::default(),
+            equity_curve,
+            recent_fills: Vec::new(),
+            booked_fills: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn measures_each_day_from_the_previous_report() {
+        let config = ReportConfig {
+            var_limit: Some(1.0),
+            ..Default::default()
+        };
+        let mut marks = Marks::new();
+        let first = build(20250102, &[snapshot(vec![1000.0, 1100.0, 1050.0], 40.0, 2, 0)], &mut marks, None, &config);
+        assert_eq!(first.strategies[0].pnl, 50.0);
+        assert_eq!(first.strategies[0].max_drawdown, 50.0);
+        assert!(first.breaches.is_empty());
+
+        let curve = vec![1000.0, 1100.0, 1050.0, 1200.0, 1150.0, 1180.0];
+        let second = build(20250103, &[snapshot(curve, 100.0, 5, 3)], &mut marks, None, &config);
+        let day = &second.strategies[0];
+        assert_eq!((day.pnl, day.realized_pnl, day.fees, day.trades), (130.0, 60.0, 6.0, 3));
+        // only today's samples count towards today's drawdown
+        assert_eq!(day.max_drawdown, 50.0);
+        assert_eq!(second.breaches.len(), 1);
+        assert!(second.markdown().contains("| aberration_20 | rb2505 | 130.00 |"));
+        assert!(second.html().contains("<td>130.00</td>"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Portfolio risk over current positions: one-day parametric VaR from the covariance of daily
+//! returns, and stress scenarios moving one product at a time, alone or with the others
+//! following through their historical betas to it.
+//!
+//! Positions are netted per product (the symbol's leading letters, see
+//! `calendar::product_of`) into a signed notional, lots × last price × multiplier. History
+//! comes from each product's continuous daily series in the `BarStore` (`<product>.main`, as
+//! `continuous::build_into_store` names them); products without enough of it still appear in
+//! the isolated scenarios but are left out of VaR and listed in `missing_history`.
+use crate::bars::{BarPeriod, BarStore};
+use crate::calendar;
+use crate::query::StrategySnapshot;
+use crate::timeutil;
+use crate::types::SymbolType;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+/// One-sided normal quantiles.
+const Z_95: f64 = 1.644_854;
+const Z_99: f64 = 2.326_348;
+/// Fewest overlapping daily returns a variance or covariance is estimated from.
+const MIN_RETURNS: usize = 20;
+
+/// Net exposure to one product.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Exposure {
+    pub product: String,
+    /// signed: long positive, short negative
+    pub notional: f64,
+}
+
+/// Net every strategy's position into one notional per product, largest first.
+pub fn exposures(snapshots: &[StrategySnapshot]) -> Vec<Exposure> {
+    let mut by_product: BTreeMap<String, f64> = BTreeMap::new();
+    for s in snapshots {
+        let lots = s.long.map_or(0, |p| p.lots) as f64 - s.short.map_or(0, |p| p.lots) as f64;
+        if lots != 0.0 {
+            let product = calendar::product_of(&SymbolType::from(s.symbol.as_str())).to_string();
+            *by_product.entry(product).or_default() += lots * s.last_price * s.multiplier;
+        }
+    }
+    let mut exposures: Vec<Exposure> = by_product
+        .into_iter()
+        .filter(|(_, notional)| *notional != 0.0)
+        .map(|(product, notional)| Exposure { product, notional })
+        .collect();
+    exposures.sort_by(|a, b| b.notional.abs().total_cmp(&a.notional.abs()));
+    exposures
+}
+
+/// Daily return covariances between products.
+#[derive(Debug, Clone, Default)]
+pub struct RiskModel {
+    /// product → local day → close-to-close return
+    returns: HashMap<String, BTreeMap<i64, f64>>,
+}
+
+impl RiskModel {
+    /// From each product's daily closes, `(stamp, close)` oldest first, keeping the last
+    /// `lookback` returns.
+    pub fn from_closes(closes: &HashMap<String, Vec<(i64, f64)>>, lookback: usize) -> Self {
+        let returns = closes
+            .iter()
+            .map(|(product, closes)| {
+                let returns: Vec<(i64, f64)> = closes
+                    .windows(2)
+                    .filter(|w| w[0].1 > 0.0)
+                    .map(|w| (timeutil::local_day(w[1].0), w[1].1 / w[0].1 - 1.0))
+                    .collect();
+                let recent = returns[returns.len().saturating_sub(lookback)..].iter().copied().collect();
+                (product.clone(), recent)
+            })
+            .collect();
+        Self { returns }
+    }
+
+    /// Load the continuous daily series of `products` from `store`; products without a
+    /// series are left out.
+    pub fn load(store: &BarStore, products: &[String], lookback: usize) -> Self {
+        let closes = products
+            .iter()
+            .filter_map(|product| {
+                let bars = store.load(&format!("{}.main", product), BarPeriod::Daily).ok()?;
+                Some((product.clone(), bars.iter().map(|b| (b.stamp, b.close)).collect()))
+            })
+            .collect();
+        Self::from_closes(&closes, lookback)
+    }
+
+    /// Sample covariance of daily returns over the days both products have; `None` with too
+    /// little overlap.
+    pub fn covariance(&self, a: &str, b: &str) -> Option<f64> {
+        let (ra, rb) = (self.returns.get(a)?, self.returns.get(b)?);
+        let pairs: Vec<(f64, f64)> = ra.iter().filter_map(|(day, x)| rb.get(day).map(|y| (*x, *y))).collect();
+        if pairs.len() < MIN_RETURNS {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+        Some(pairs.iter().map(|(x, y)| (x - mean_a) * (y - mean_b)).sum::<f64>() / (n - 1.0))
+    }
+
+    /// How far `product` tends to move per unit move of `driver`.
+    fn beta(&self, product: &str, driver: &str) -> Option<f64> {
+        if product == driver {
+            return Some(1.0);
+        }
+        let var = self.covariance(driver, driver).filter(|v| *v > 0.0)?;
+        Some(self.covariance(product, driver)? / var)
+    }
+}
+
+/// Portfolio PnL under one scenario.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Scenario {
+    pub name: String,
+    pub pnl: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskReport {
+    pub exposures: Vec<Exposure>,
+    /// sum of absolute notionals
+    pub gross: f64,
+    pub net: f64,
+    /// one-day losses not exceeded with 95% / 99% confidence, as positive amounts
+    pub var_95: f64,
+    pub var_99: f64,
+    pub scenarios: Vec<Scenario>,
+    /// products with positions but too little history for VaR or correlated moves
+    pub missing_history: Vec<String>,
+}
+
+/// VaR and stress scenarios for `exposures`, moving each product by `±shock` (e.g. 0.03).
+pub fn report(exposures: Vec<Exposure>, model: &RiskModel, shock: f64) -> RiskReport {
+    let (modelled, missing): (Vec<&Exposure>, Vec<&Exposure>) = exposures.iter().partition(|e| model.covariance(&e.product, &e.product).is_some());
+
+    let mut variance = 0.0;
+    for a in &modelled {
+        for b in &modelled {
+            variance += a.notional * b.notional * model.covariance(&a.product, &b.product).unwrap_or(0.0);
+        }
+    }
+    let sigma = variance.max(0.0).sqrt();
+
+    let mut scenarios = Vec::new();
+    for sign in [1.0, -1.0] {
+        let pct = sign * shock * 100.0;
+        scenarios.push(Scenario {
+            name: format!("all {:+.0}%", pct),
+            pnl: exposures.iter().map(|e| e.notional * sign * shock).sum(),
+        });
+        for driver in &exposures {
+            scenarios.push(Scenario {
+                name: format!("{} {:+.0}%", driver.product, pct),
+                pnl: driver.notional * sign * shock,
+            });
+            scenarios.push(Scenario {
+                name: format!("{} {:+.0}% correlated", driver.product, pct),
+                pnl: exposures
+                    .iter()
+                    .map(|e| e.notional * sign * shock * model.beta(&e.product, &driver.product).unwrap_or(0.0))
+                    .sum(),
+            });
+        }
+    }
+
+    RiskReport {
+        gross: exposures.iter().map(|e| e.notional.abs()).sum(),
+        net: exposures.iter().map(|e| e.notional).sum(),
+        var_95: Z_95 * sigma,
+        var_99: Z_99 * sigma,
+        scenarios,
+        missing_history: missing.iter().map(|e| e.product.clone()).collect(),
+        exposures,
+    }
+}
+
+impl RiskReport {
+    /// Plain-text rendering for reports and logs.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "gross {:.0}  net {:.0}  VaR95 {:.0}  VaR99 {:.0}",
+            self.gross, self.net, self.var_95, self.var_99
+        );
+        for e in &self.exposures {
+            let _ = writeln!(out, "  {:<8} {:>14.0}", e.product, e.notional);
+        }
+        if !self.missing_history.is_empty() {
+            let _ = writeln!(out, "  no history for VaR: {}", self.missing_history.join(", "));
+        }
+        let _ = writeln!(out, "stress:");
+        for s in &self.scenarios {
+            let _ = writeln!(out, "  {:<28} {:>14.0}", s.name, s.pnl);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hedged_book_has_less_var_than_its_legs() {
+        // hc moves with rb at half its size; i is unrelated noise
+        let day = 86_400_000;
+        let mut closes: HashMap<String, Vec<(i64, f64)>> = HashMap::new();
+        let (mut rb, mut hc) = (100.0, 100.0);
+        for d in 0..60 {
+            let r = if d % 2 == 0 { 0.02 } else { -0.01 };
+            rb *= 1.0 + r;
+            hc *= 1.0 + r / 2.0;
+            closes.entry("rb".into()).or_default().push((d * day, rb));
+            closes.entry("hc".into()).or_default().push((d * day, hc));
+        }
+        let model = RiskModel::from_closes(&closes, 250);
+        assert!((model.beta("hc", "rb").unwrap() - 0.5).abs() < 1e-9);
+
+        let exposures = vec![
+            Exposure {
+                product: "rb".into(),
+                notional: 1_000_000.0,
+            },
+            Exposure {
+                product: "hc".into(),
+                notional: -2_000_000.0,
+            },
+            Exposure {
+                product: "i".into(),
+                notional: 500_000.0,
+            },
+        ];
+        let hedged = report(exposures.clone(), &model, 0.03);
+        let outright = report(exposures[..1].to_vec(), &model, 0.03);
+        assert!(hedged.var_95 < 1e-6 && outright.var_95 > 0.0);
+        assert!(outright.var_99 > outright.var_95);
+        assert_eq!(hedged.missing_history, vec!["i".to_string()]);
+
+        let pnl = |name: &str| hedged.scenarios.iter().find(|s| s.name == name).unwrap().pnl;
+        assert_eq!(pnl("rb +3%"), 30_000.0);
+        // hc follows at half the move and offsets rb; i does not follow
+        assert!(pnl("rb +3% correlated").abs() < 1e-6);
+        assert_eq!(pnl("all -3%"), 15_000.0);
+        assert!(hedged.render().contains("VaR95"));
+    }
+}
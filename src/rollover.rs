@@ -0,0 +1,146 @@
+//! Dominant-contract rollover. Strategies added on a dominant alias (`rb888`, see `symbols`)
+//! trade one concrete contract at a time; a `Rollover` decides when a product moves on to a
+//! later contract, either on the trading days of a schedule or once a later contract's open
+//! interest overtakes the current one's by a margin. Rolls only ever go forward, to a later
+//! delivery month.
+//!
+//! On a roll the engine subscribes the new contract; the strategies' worker flattens them on
+//! the old contract, moves them over once flat, and opens the same position again on the new
+//! one, so the strategies themselves never notice.
+use crate::calendar;
+use crate::symbols;
+use crate::timeutil;
+use crate::types::{SymbolType, TickData};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Roll {
+    pub from: SymbolType,
+    pub to: SymbolType,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rollover {
+    /// (trading day `yyyymmdd`, contract) to trade from that day on
+    schedule: Vec<(u32, SymbolType)>,
+    /// roll once a later contract's open interest exceeds the current one's this many times
+    oi_lead: Option<f64>,
+    /// later contracts whose open interest is compared, by the ticks the engine subscribes
+    candidates: Vec<SymbolType>,
+    /// product root → contract traded
+    current: HashMap<String, SymbolType>,
+    /// latest open interest by contract
+    oi: HashMap<SymbolType, f64>,
+}
+
+/// Whether `a` delivers later than `b`, both of one product: the longer, then the greater
+/// month code (`rb2510` after `rb2505`, `MA601` after `MA512`).
+fn later(a: &SymbolType, b: &SymbolType) -> bool {
+    (a.as_str().len(), a.as_str()) > (b.as_str().len(), b.as_str())
+}
+
+impl Rollover {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trade `contract` from trading day `date` (`yyyymmdd`) on.
+    pub fn with_roll(mut self, date: u32, contract: &str) -> Self {
+        self.schedule.push((date, SymbolType::from(contract)));
+        self.schedule.sort_by_key(|&(date, _)| date);
+        self
+    }
+
+    /// Roll once one of the `watch`ed contracts' open interest is `lead` times the current one's.
+    pub fn roll_on_open_interest(mut self, lead: f64) -> Self {
+        self.oi_lead = Some(lead);
+        self
+    }
+
+    /// Compare `contract`'s open interest against the one traded of its product.
+    pub fn watch(mut self, contract: &str) -> Self {
+        self.candidates.push(SymbolType::from(contract));
+        self
+    }
+
+    /// Contracts whose ticks the open interest rule needs.
+    pub fn watched(&self) -> &[SymbolType] {
+        &self.candidates
+    }
+
+    /// Roll `contract`'s product, starting from `contract`.
+    pub fn follow(&mut self, contract: SymbolType) {
+        self.current.insert(symbols::root(contract.as_str()).to_string(), contract);
+    }
+
+    /// The contract traded of `root`'s product.
+    pub fn current(&self, root: &str) -> Option<SymbolType> {
+        self.current.get(root).copied()
+    }
+
+    /// The roll `tick` calls for, if any, taken as done.
+    pub fn on_tick(&mut self, tick: &TickData) -> Option<Roll> {
+        let root = symbols::root(tick.symbol.as_str());
+        let &from = self.current.get(root)?;
+        if tick.oi > 0.0 {
+            self.oi.insert(tick.symbol, tick.oi);
+        }
+        let same_product = |c: &SymbolType| symbols::root(c.as_str()) == root && later(c, &from);
+        let today = timeutil::day_to_date(calendar::trading_day(tick.stamp, calendar::is_weekday));
+        let scheduled = self
+            .schedule
+            .iter()
+            .filter(|&&(date, c)| date <= today && same_product(&c))
+            .map(|&(_, c)| c)
+            .next_back();
+        let overtaken = self.oi_lead.and_then(|lead| {
+            let held = self.oi.get(&from).copied().unwrap_or(0.0);
+            self.candidates
+                .iter()
+                .filter(|c| same_product(c))
+                .filter_map(|c| self.oi.get(c).map(|&oi| (*c, oi)))
+                .filter(|&(_, oi)| oi > held * lead)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(c, _)| c)
+        });
+        let to = scheduled.or(overtaken)?;
+        self.current.insert(root.to_string(), to);
+        Some(Roll { from, to })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(symbol: &str, date: u32, oi: f64) -> TickData {
+        TickData {
+            symbol: SymbolType::from(symbol),
+            stamp: timeutil::local_stamp(date, 10 * 3600),
+            oi,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rolls_forward_on_schedule_or_open_interest() {
+        let (rb2505, rb2510, ma505) = (SymbolType::from("rb2505"), SymbolType::from("rb2510"), SymbolType::from("MA505"));
+        let mut rollover = Rollover::new().with_roll(20250317, "rb2510").with_roll(20250301, "rb2501");
+        rollover.follow(rb2505);
+        assert_eq!(rollover.on_tick(&tick("rb2505", 20250314, 0.0)), None);
+        // an earlier contract is never rolled back to, and other products are left alone
+        assert_eq!(rollover.on_tick(&tick("MA505", 20250317, 0.0)), None);
+        assert_eq!(rollover.on_tick(&tick("rb2505", 20250317, 0.0)), Some(Roll { from: rb2505, to: rb2510 }));
+        assert_eq!(rollover.on_tick(&tick("rb2505", 20250318, 0.0)), None);
+        assert_eq!(rollover.current("rb"), Some(rb2510));
+
+        let mut rollover = Rollover::new().roll_on_open_interest(1.1).watch("MA509").watch("MA501");
+        rollover.follow(ma505);
+        assert_eq!(rollover.on_tick(&tick("MA505", 20250314, 1000.0)), None);
+        assert_eq!(rollover.on_tick(&tick("MA501", 20250314, 5000.0)), None);
+        // ahead, but not by the margin
+        assert_eq!(rollover.on_tick(&tick("MA509", 20250314, 1050.0)), None);
+        let roll = rollover.on_tick(&tick("MA509", 20250314, 1200.0));
+        assert_eq!(roll.map(|r| r.to.as_str().to_string()), Some("MA509".into()));
+    }
+}
@@ -0,0 +1,216 @@
+//! Round-trip trades: each opening fill's lots paired with the closing fills that take them
+//! off, first in first out, so a strategy's record reads as a list of trades rather than of
+//! fills. An open filled in one go and closed in two parts makes two round trips, as does an
+//! open in two parts closed in one. While lots are held, the best and worst price since their
+//! entry are followed tick by tick for the trade's excursions.
+use crate::types::DirectionType;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// One trade, from the fill that opened its lots to the one that closed them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RoundTrip {
+    /// BUY for a long trade, SELL for a short one
+    pub direction: DirectionType,
+    pub lots: u32,
+    pub entry_stamp: i64,
+    pub entry_price: f64,
+    pub exit_stamp: i64,
+    pub exit_price: f64,
+    /// 持仓时长, in ms
+    pub holding_ms: i64,
+    /// 毛盈亏, before fees
+    pub pnl: f64,
+    /// the opening and closing fees of these lots
+    pub fee: f64,
+    /// 最大不利偏移: the largest floating loss while held, ≥ 0
+    pub mae: f64,
+    /// 最大有利偏移: the largest floating profit while held, ≥ 0
+    pub mfe: f64,
+}
+
+impl RoundTrip {
+    /// 净盈亏, after fees
+    pub fn net_pnl(&self) -> f64 {
+        self.pnl - self.fee
+    }
+}
+
+/// 交易统计 over a list of round trips, by net PnL; a trade at exactly 0 is neither a win
+/// nor a loss.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TradeSummary {
+    pub trades: usize,
+    pub wins: usize,
+    pub losses: usize,
+    /// wins over trades
+    pub win_rate: f64,
+    pub avg_win: f64,
+    /// as a negative number
+    pub avg_loss: f64,
+    /// 期望: average net PnL per trade
+    pub expectancy: f64,
+    /// gross wins over gross losses; 0 without losses
+    pub profit_factor: f64,
+    pub avg_holding_ms: f64,
+}
+
+impl TradeSummary {
+    pub fn of(trades: &[RoundTrip]) -> Self {
+        if trades.is_empty() {
+            return Self::default();
+        }
+        let (mut won, mut lost, mut wins, mut losses, mut held) = (0.0, 0.0, 0, 0, 0);
+        for trade in trades {
+            let pnl = trade.net_pnl();
+            if pnl > 0.0 {
+                won += pnl;
+                wins += 1;
+            } else if pnl < 0.0 {
+                lost += pnl;
+                losses += 1;
+            }
+            held += trade.holding_ms;
+        }
+        let n = trades.len() as f64;
+        Self {
+            trades: trades.len(),
+            wins,
+            losses,
+            win_rate: wins as f64 / n,
+            avg_win: if wins > 0 { won / wins as f64 } else { 0.0 },
+            avg_loss: if losses > 0 { lost / losses as f64 } else { 0.0 },
+            expectancy: (won + lost) / n,
+            profit_factor: if lost < 0.0 { won / -lost } else { 0.0 },
+            avg_holding_ms: held as f64 / n,
+        }
+    }
+}
+
+/// Lots of one opening fill still held.
+#[derive(Debug, Clone, Copy)]
+struct OpenLots {
+    /// `None` for lots restored from a handoff until the first tick after
+    stamp: Option<i64>,
+    price: f64,
+    lots: u32,
+    fee_per_lot: f64,
+    low: f64,
+    high: f64,
+}
+
+/// The open lots of each side and the round trips closed so far.
+#[derive(Debug, Clone, Default)]
+pub struct RoundTrips {
+    long: VecDeque<OpenLots>,
+    short: VecDeque<OpenLots>,
+    closed: Vec<RoundTrip>,
+}
+
+impl RoundTrips {
+    fn side(&mut self, side: DirectionType) -> &mut VecDeque<OpenLots> {
+        match side {
+            DirectionType::BUY => &mut self.long,
+            DirectionType::SELL => &mut self.short,
+        }
+    }
+
+    /// Book `lots` opened on `side` at `price`; `stamp` is `None` for restored lots.
+    pub fn open(&mut self, side: DirectionType, stamp: Option<i64>, price: f64, lots: u32, fee: f64) {
+        if lots == 0 {
+            return;
+        }
+        self.side(side).push_back(OpenLots {
+            stamp,
+            price,
+            lots,
+            fee_per_lot: fee / lots as f64,
+            low: price,
+            high: price,
+        });
+    }
+
+    /// Close `lots` of `side` at `price`, oldest first, into round trips. Lots beyond those
+    /// held are ignored, as the tracker ignores them.
+    pub fn close(&mut self, side: DirectionType, stamp: i64, price: f64, lots: u32, fee: f64, multiplier: f64) {
+        let close_fee_per_lot = if lots > 0 { fee / lots as f64 } else { 0.0 };
+        let mut left = lots;
+        while left > 0 {
+            let Some(open) = self.side(side).front_mut() else {
+                break;
+            };
+            let lots = left.min(open.lots);
+            let (low, high) = (open.low.min(price), open.high.max(price));
+            let (diff, worst, best) = match side {
+                DirectionType::BUY => (price - open.price, open.price - low, high - open.price),
+                DirectionType::SELL => (open.price - price, high - open.price, open.price - low),
+            };
+            let value = multiplier * lots as f64;
+            let entry_stamp = open.stamp.unwrap_or(stamp);
+            let trip = RoundTrip {
+                direction: side,
+                lots,
+                entry_stamp,
+                entry_price: open.price,
+                exit_stamp: stamp,
+                exit_price: price,
+                holding_ms: stamp - entry_stamp,
+                pnl: diff * value,
+                fee: (open.fee_per_lot + close_fee_per_lot) * lots as f64,
+                mae: worst * value,
+                mfe: best * value,
+            };
+            open.lots -= lots;
+            if open.lots == 0 {
+                self.side(side).pop_front();
+            }
+            self.closed.push(trip);
+            left -= lots;
+        }
+    }
+
+    /// Follow the excursions of every open lot to `price`, and date restored lots to `stamp`.
+    pub fn mark(&mut self, stamp: i64, price: f64) {
+        for open in self.long.iter_mut().chain(self.short.iter_mut()) {
+            open.stamp.get_or_insert(stamp);
+            open.low = open.low.min(price);
+            open.high = open.high.max(price);
+        }
+    }
+
+    /// Round trips closed so far, in the order they closed.
+    pub fn closed(&self) -> &[RoundTrip] {
+        &self.closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_opens_with_closes_first_in_first_out() {
+        let mut book = RoundTrips::default();
+        book.open(DirectionType::BUY, Some(1000), 100.0, 2, 2.0);
+        book.mark(2000, 97.0);
+        book.open(DirectionType::BUY, Some(2000), 97.0, 1, 1.0);
+        book.mark(3000, 104.0);
+        // three lots out in one fill: two round trips
+        book.close(DirectionType::BUY, 4000, 103.0, 3, 3.0, 10.0);
+        book.open(DirectionType::SELL, Some(5000), 103.0, 1, 1.0);
+        book.close(DirectionType::SELL, 6000, 105.0, 1, 1.0, 10.0);
+
+        let trips = book.closed();
+        assert_eq!(trips.len(), 3);
+        assert_eq!((trips[0].lots, trips[0].holding_ms, trips[0].pnl, trips[0].fee), (2, 3000, 60.0, 4.0));
+        assert_eq!((trips[0].mae, trips[0].mfe), (60.0, 80.0));
+        assert_eq!((trips[1].entry_price, trips[1].pnl, trips[1].mae, trips[1].mfe), (97.0, 60.0, 0.0, 70.0));
+        assert_eq!((trips[2].direction, trips[2].net_pnl(), trips[2].mae), (DirectionType::SELL, -22.0, 20.0));
+
+        let summary = TradeSummary::of(trips);
+        assert_eq!((summary.trades, summary.wins, summary.losses), (3, 2, 1));
+        assert_eq!((summary.avg_win, summary.avg_loss), (57.0, -22.0));
+        assert!((summary.expectancy - 92.0 / 3.0).abs() < 1e-9);
+        assert!((summary.profit_factor - 114.0 / 22.0).abs() < 1e-9);
+    }
+}
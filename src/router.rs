@@ -0,0 +1,164 @@
+use crate::types::SymbolType;
+use std::collections::HashMap;
+
+/// Decides which worker thread owns a symbol (and therefore all of its strategies).
+///
+/// `route` is called once per symbol when it is first registered; the engine remembers the
+/// answer, so routers may be stateful.
+pub trait Router: Send {
+    fn route(&mut self, symbol: &SymbolType, num_workers: usize) -> usize;
+
+    /// `symbol` lost its last strategy and no longer loads `worker_id`.
+    fn release(&mut self, _symbol: &SymbolType, _worker_id: usize) {}
+
+    /// Reassign every registered symbol at once, called by `init()` before the workers start;
+    /// routers that can do better with the full picture than symbol by symbol return the new
+    /// assignment. None (keep the `route` answers) by default.
+    fn rebalance(&mut self, _symbols: &[SymbolType], _num_workers: usize) -> Option<HashMap<SymbolType, usize>> {
+        None
+    }
+}
+
+/// Default routing: contracts of the same product (same letter prefix) land on the same worker.
+pub struct HashRouter;
+
+impl Router for HashRouter {
+    fn route(&mut self, symbol: &SymbolType, num_workers: usize) -> usize {
+        (symbol.hash_future_symbol() as usize) % num_workers
+    }
+}
+
+/// Explicit symbol → worker assignments, e.g. to keep every leg of a spread on one worker.
+/// Symbols without an entry go to `fallback`.
+pub struct StaticRouter {
+    map: HashMap<SymbolType, usize>,
+    fallback: Box<dyn Router>,
+}
+
+impl StaticRouter {
+    pub fn new(fallback: Box<dyn Router>) -> Self {
+        Self {
+            map: HashMap::new(),
+            fallback,
+        }
+    }
+
+    pub fn assign(mut self, symbol: SymbolType, worker_id: usize) -> Self {
+        self.map.insert(symbol, worker_id);
+        self
+    }
+}
+
+impl Router for StaticRouter {
+    fn route(&mut self, symbol: &SymbolType, num_workers: usize) -> usize {
+        match self.map.get(symbol) {
+            Some(&worker_id) => worker_id % num_workers,
+            None => self.fallback.route(symbol, num_workers),
+        }
+    }
+
+    fn release(&mut self, symbol: &SymbolType, worker_id: usize) {
+        if !self.map.contains_key(symbol) {
+            self.fallback.release(symbol, worker_id);
+        }
+    }
+}
+
+/// Puts each new symbol on the worker that currently owns the fewest symbols.
+#[derive(Default)]
+pub struct LoadAwareRouter {
+    loads: Vec<usize>,
+}
+
+impl LoadAwareRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Router for LoadAwareRouter {
+    fn route(&mut self, _symbol: &SymbolType, num_workers: usize) -> usize {
+        self.loads.resize(num_workers, 0);
+        let (worker_id, _) = self.loads.iter().enumerate().min_by_key(|&(_, &load)| load).unwrap_or((0, &0));
+        self.loads[worker_id] += 1;
+        worker_id
+    }
+
+    fn release(&mut self, _symbol: &SymbolType, worker_id: usize) {
+        if let Some(load) = self.loads.get_mut(worker_id) {
+            *load = load.saturating_sub(1);
+        }
+    }
+}
+
+/// Evens out worker load by tick rate: each symbol goes to the worker with the lowest
+/// expected ticks per second. `rebalance` places the busiest symbols first, so a few hot
+/// contracts end up on different workers. Rates come from config (`config::load_tick_rates`)
+/// or a warm-up measurement (`CtaEngine::measure_tick_rates`); symbols without one count as
+/// `default_rate`.
+pub struct TickRateRouter {
+    rates: HashMap<SymbolType, f64>,
+    default_rate: f64,
+    loads: Vec<f64>,
+}
+
+impl TickRateRouter {
+    pub fn new(rates: HashMap<SymbolType, f64>, default_rate: f64) -> Self {
+        Self {
+            rates,
+            default_rate,
+            loads: Vec::new(),
+        }
+    }
+
+    pub fn rate(&self, symbol: &SymbolType) -> f64 {
+        self.rates.get(symbol).copied().unwrap_or(self.default_rate)
+    }
+
+    /// Expected ticks per second on each worker.
+    pub fn loads(&self) -> &[f64] {
+        &self.loads
+    }
+}
+
+impl Router for TickRateRouter {
+    fn route(&mut self, symbol: &SymbolType, num_workers: usize) -> usize {
+        self.loads.resize(num_workers, 0.0);
+        let (worker_id, _) = self.loads.iter().enumerate().min_by(|(_, a), (_, b)| a.total_cmp(b)).unwrap_or((0, &0.0));
+        self.loads[worker_id] += self.rate(symbol);
+        worker_id
+    }
+
+    fn release(&mut self, symbol: &SymbolType, worker_id: usize) {
+        let rate = self.rate(symbol);
+        if let Some(load) = self.loads.get_mut(worker_id) {
+            *load = (*load - rate).max(0.0);
+        }
+    }
+
+    fn rebalance(&mut self, symbols: &[SymbolType], num_workers: usize) -> Option<HashMap<SymbolType, usize>> {
+        let mut symbols = symbols.to_vec();
+        symbols.sort_by(|a, b| self.rate(b).total_cmp(&self.rate(a)).then_with(|| a.as_str().cmp(b.as_str())));
+        self.loads = vec![0.0; num_workers];
+        Some(symbols.into_iter().map(|symbol| (symbol, self.route(&symbol, num_workers))).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreads_hot_symbols_across_workers() {
+        let rates = [("rb2505", 40.0), ("rb2510", 30.0), ("MA505", 10.0), ("MA509", 5.0)]
+            .into_iter()
+            .map(|(symbol, rate)| (SymbolType::from(symbol), rate))
+            .collect();
+        let mut router = TickRateRouter::new(rates, 1.0);
+        let symbols: Vec<_> = ["MA509", "rb2510", "MA505", "rb2505"].into_iter().map(SymbolType::from).collect();
+        let assignment = router.rebalance(&symbols, 2).unwrap();
+        // hashing by product would put both rb contracts on one worker
+        assert_ne!(assignment[&SymbolType::from("rb2505")], assignment[&SymbolType::from("rb2510")]);
+        assert_eq!(router.loads(), &[45.0, 40.0]);
+    }
+}
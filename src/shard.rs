@@ -0,0 +1,81 @@
+//! Multi-process sharding: `count` engine processes split the symbol universe between them,
+//! each trading and subscribing to only the symbols its `index` owns. Ownership is decided by
+//! rendezvous (highest random weight) hashing of the symbol, a form of consistent hashing:
+//! every process computes the same owner without talking to the others, and going from `n`
+//! to `n + 1` shards moves only the symbols the new shard takes, about `1 / (n + 1)` of them.
+use crate::types::SymbolType;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Shard {
+    pub fn new(index: u32, count: u32) -> Result<Self, String> {
+        if count == 0 || index >= count {
+            return Err(format!("shard {} of {} does not exist", index, count));
+        }
+        Ok(Self { index, count })
+    }
+
+    /// Parse `index/count`, e.g. `0/4`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (index, count) = s.split_once('/').ok_or_else(|| format!("expected <index>/<count>, got {:?}", s))?;
+        let number = |n: &str| n.trim().parse::<u32>().map_err(|e| format!("{:?}: {}", n, e));
+        Self::new(number(index)?, number(count)?)
+    }
+
+    /// The shard trading `symbol`.
+    pub fn owner(&self, symbol: &SymbolType) -> u32 {
+        (0..self.count).max_by_key(|&shard| weight(symbol, shard)).unwrap_or(0)
+    }
+
+    pub fn owns(&self, symbol: &SymbolType) -> bool {
+        self.owner(symbol) == self.index
+    }
+}
+
+/// FNV-1a of the symbol and the shard index: stable across processes, builds and platforms,
+/// unlike the std hashers.
+fn weight(symbol: &SymbolType, shard: u32) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in symbol.as_str().as_bytes().iter().chain(&shard.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    // FNV mixes its last bytes poorly; finish with a multiply-xorshift
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_symbols_evenly_and_moves_few_on_growth() {
+        let symbols: Vec<SymbolType> = (0..2000)
+            .map(|i| SymbolType::from(format!("{}{}", ["rb", "hc", "MA", "IF", "au", "cu"][i % 6], 2000 + i).as_str()))
+            .collect();
+        let three: Vec<u32> = symbols.iter().map(|s| Shard::new(0, 3).unwrap().owner(s)).collect();
+        for shard in 0..3 {
+            let owned = three.iter().filter(|&&owner| owner == shard).count();
+            assert!((550..=780).contains(&owned), "shard {} owns {}", shard, owned);
+            let this = Shard::new(shard, 3).unwrap();
+            assert_eq!(symbols.iter().filter(|s| this.owns(s)).count(), owned);
+        }
+
+        // a fourth shard only takes symbols over, about a quarter of them
+        let four: Vec<u32> = symbols.iter().map(|s| Shard::new(0, 4).unwrap().owner(s)).collect();
+        let moved = three.iter().zip(&four).filter(|(a, b)| a != b).count();
+        assert!(three.iter().zip(&four).all(|(&a, &b)| a == b || b == 3));
+        assert!((400..=600).contains(&moved), "{} moved", moved);
+
+        assert_eq!(Shard::parse("1/4"), Ok(Shard { index: 1, count: 4 }));
+        assert!(Shard::parse("4/4").is_err() && Shard::parse("1").is_err());
+    }
+}
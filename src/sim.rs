@@ -0,0 +1,560 @@
+//! Simulated matching for paper trading: orders from the engine are matched against the top
+//! of book of the tick stream and fills come back as `ExecutionReport`s, just as from the
+//! real gateway.
+//!
+//! Marketable orders take the opposite touch up to its size; the rest of a limit order joins
+//! the queue at its price behind the volume already shown there, and fills once that much
+//! volume has traded at or through its price. What a tick offers, its traded volume and its
+//! touch sizes, is shared by the resting orders, oldest first. What an FAK, FOK or market
+//! order cannot fill at once is cancelled, and refused orders are rejected, each with a
+//! `StatusReport`, so the engine stops waiting on them.
+//!
+//! How much a marketable order takes and at what price are pluggable through an
+//! `ExecutionModel`: a `FillModel` caps the lots taken from the touch and a `SlippageModel`
+//! moves the fill price against the order. The default takes up to the whole touch size at
+//! the touch price. Commissions are charged with the tracker's `FeeModel` when given one
+//! through `with_fees`.
+//!
+//! The daily price limits are enforced as the exchange does: a limit order priced beyond
+//! them is refused, and nothing fills past them however far slippage would push it. A book
+//! locked at a limit quotes its empty side as 0, so orders on the crowded side queue there
+//! and only fill once volume trades through the queue ahead of them.
+use crate::conduct::ConductLimits;
+use crate::execution::{ExecutionReport, Report, STATUS_CANCELLED, STATUS_REJECTED, StatusReport};
+use crate::fees::FeeModel;
+use crate::timeutil;
+use crate::types::{DirectionType, Order, OrderCancel, OrderType, PriceLimits, SymbolType, TickData};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+
+/// Price a marketable order fills at, given the touch it trades against.
+pub trait SlippageModel: Send + Sync {
+    fn fill_price(&self, order: &Order, touch: f64) -> f64;
+}
+
+/// Lots a marketable order takes from a touch showing `size`.
+pub trait FillModel: Send + Sync {
+    fn fill_lots(&self, order: &Order, size: i64) -> i64;
+}
+
+/// Fills at the touch.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoSlippage;
+
+impl SlippageModel for NoSlippage {
+    fn fill_price(&self, _order: &Order, touch: f64) -> f64 {
+        touch
+    }
+}
+
+/// Fills a fixed number of price ticks beyond the touch.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTicks {
+    pub ticks: f64,
+    /// the contract's `min_move`
+    pub min_move: f64,
+}
+
+impl SlippageModel for FixedTicks {
+    fn fill_price(&self, order: &Order, touch: f64) -> f64 {
+        adverse(order, touch, self.ticks * self.min_move)
+    }
+}
+
+/// Fills a fraction of the touch price beyond it, e.g. 0.0002 for 2 bp.
+#[derive(Debug, Clone, Copy)]
+pub struct Proportional {
+    pub rate: f64,
+}
+
+impl SlippageModel for Proportional {
+    fn fill_price(&self, order: &Order, touch: f64) -> f64 {
+        adverse(order, touch, touch * self.rate)
+    }
+}
+
+/// `touch` moved by `by` against the order, but never past a limit price.
+fn adverse(order: &Order, touch: f64, by: f64) -> f64 {
+    match (order.direction, order.order_type) {
+        (DirectionType::BUY, OrderType::MARKET) => touch + by,
+        (DirectionType::BUY, _) => (touch + by).min(order.price),
+        (DirectionType::SELL, OrderType::MARKET) => touch - by,
+        (DirectionType::SELL, _) => (touch - by).max(order.price),
+    }
+}
+
+/// Takes the whole order at the touch, whatever size it shows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unlimited;
+
+impl FillModel for Unlimited {
+    fn fill_lots(&self, order: &Order, _size: i64) -> i64 {
+        order.lots as i64
+    }
+}
+
+/// Takes at most `participation` of the size shown at the touch (`av1` / `bv1`).
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeLimited {
+    pub participation: f64,
+}
+
+impl Default for VolumeLimited {
+    fn default() -> Self {
+        Self { participation: 1.0 }
+    }
+}
+
+impl FillModel for VolumeLimited {
+    fn fill_lots(&self, order: &Order, size: i64) -> i64 {
+        let available = (size.max(0) as f64 * self.participation).floor() as i64;
+        (order.lots as i64).min(available)
+    }
+}
+
+/// The fill and slippage models a `SimBroker` matches with, and the exchange conduct limits a
+/// backtest holds the strategy to.
+#[derive(Clone)]
+pub struct ExecutionModel {
+    pub fill: Arc<dyn FillModel>,
+    pub slippage: Arc<dyn SlippageModel>,
+    /// `None` leaves order flow unchecked and free of message fees
+    pub conduct: Option<ConductLimits>,
+}
+
+impl Default for ExecutionModel {
+    fn default() -> Self {
+        Self {
+            fill: Arc::new(VolumeLimited::default()),
+            slippage: Arc::new(NoSlippage),
+            conduct: None,
+        }
+    }
+}
+
+/// A message from the engine on the order socket, told apart by frame size.
+#[derive(Debug, Clone, Copy)]
+pub enum Request {
+    Order(Order),
+    Cancel(OrderCancel),
+}
+
+impl Request {
+    pub fn decode(frame: &[u8]) -> Option<Self> {
+        // SAFETY: the length matches and both structs are plain-old-data
+        if frame.len() == mem::size_of::<Order>() {
+            Some(Request::Order(unsafe { std::ptr::read_unaligned(frame.as_ptr() as *const Order) }))
+        } else if frame.len() == mem::size_of::<OrderCancel>() {
+            Some(Request::Cancel(unsafe { std::ptr::read_unaligned(frame.as_ptr() as *const OrderCancel) }))
+        } else {
+            None
+        }
+    }
+}
+
+struct Resting {
+    /// `lots` is what is still open
+    order: Order,
+    /// estimated volume ahead of the order at its price
+    queue_ahead: i64,
+}
+
+pub struct SimBroker {
+    books: HashMap<SymbolType, TickData>,
+    resting: Vec<Resting>,
+    seq: u64,
+    model: ExecutionModel,
+    /// commission model and multiplier of each symbol fees are charged on
+    fees: HashMap<SymbolType, (FeeModel, f64)>,
+    /// commissions charged per strategy
+    commissions: HashMap<String, f64>,
+    /// orders refused: priced beyond the daily limits, or not resting and sent before the
+    /// symbol's first tick
+    refused: u64,
+}
+
+impl Default for SimBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Size shown at `price` on the order's own side, if it is one of the five levels.
+fn level_size(tick: &TickData, direction: DirectionType, price: f64) -> Option<i64> {
+    let levels = match direction {
+        DirectionType::BUY => [
+            (tick.bp1, tick.bv1),
+            (tick.bp2, tick.bv2),
+            (tick.bp3, tick.bv3),
+            (tick.bp4, tick.bv4),
+            (tick.bp5, tick.bv5),
+        ],
+        DirectionType::SELL => [
+            (tick.ap1, tick.av1),
+            (tick.ap2, tick.av2),
+            (tick.ap3, tick.av3),
+            (tick.ap4, tick.av4),
+            (tick.ap5, tick.av5),
+        ],
+    };
+    levels.iter().find(|(p, _)| *p == price).map(|&(_, size)| size as i64)
+}
+
+/// Opposite touch price and size an incoming order of `direction` would trade against.
+fn touch(tick: &TickData, direction: DirectionType) -> (f64, i64) {
+    match direction {
+        DirectionType::BUY => (tick.ap1, tick.av1 as i64),
+        DirectionType::SELL => (tick.bp1, tick.bv1 as i64),
+    }
+}
+
+impl SimBroker {
+    /// Sequence numbers start from the wall clock so they keep growing across restarts, as
+    /// the engine skips reports it has seen a higher sequence for.
+    pub fn new() -> Self {
+        Self {
+            books: HashMap::new(),
+            resting: Vec::new(),
+            seq: timeutil::now_stamp() as u64 * 1000,
+            model: ExecutionModel::default(),
+            fees: HashMap::new(),
+            commissions: HashMap::new(),
+            refused: 0,
+        }
+    }
+
+    pub fn with_model(mut self, model: ExecutionModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Charge fills on `symbol` commission as `fees` computes it for a contract of `multiplier`,
+    /// the way the engine's tracker books them.
+    pub fn with_fees(mut self, symbol: SymbolType, fees: FeeModel, multiplier: f64) -> Self {
+        self.fees.insert(symbol, (fees, multiplier));
+        self
+    }
+
+    /// Commission charged on the fills of `strategy` so far.
+    pub fn commission(&self, strategy: &str) -> f64 {
+        self.commissions.get(strategy).copied().unwrap_or(0.0)
+    }
+
+    fn report(&mut self, order: &Order, price: f64, lots: u32, stamp: i64) -> Report {
+        self.seq += 1;
+        let fill = Order {
+            timestamp: stamp,
+            price,
+            lots,
+            ..*order
+        };
+        if let Some((fees, multiplier)) = self.fees.get(&order.symbol) {
+            *self.commissions.entry(order.stg_name.as_str().to_string()).or_default() += fees.fee(&fill, *multiplier);
+        }
+        Report::Fill(ExecutionReport { seq: self.seq, fill })
+    }
+
+    /// `order`, with `lots` of it, ended unfilled.
+    fn status(&mut self, order: &Order, lots: u32, status: u8) -> StatusReport {
+        self.seq += 1;
+        StatusReport {
+            seq: self.seq,
+            order: Order { lots, ..*order },
+            status,
+        }
+    }
+
+    pub fn resting_count(&self) -> usize {
+        self.resting.len()
+    }
+
+    pub fn refused_count(&self) -> u64 {
+        self.refused
+    }
+
+    /// Match a new order against the last tick of its symbol.
+    pub fn on_order(&mut self, order: &Order) -> Vec<Report> {
+        let mut reports = Vec::new();
+        let Some(tick) = self.books.get(&order.symbol).copied() else {
+            // nothing to match against yet; limit orders wait for the first tick
+            if order.order_type == OrderType::LIMIT {
+                self.resting.push(Resting {
+                    order: *order,
+                    queue_ahead: 0,
+                });
+            } else {
+                self.refused += 1;
+                reports.push(Report::Status(self.status(order, order.lots, STATUS_REJECTED)));
+            }
+            return reports;
+        };
+
+        let limits = PriceLimits::of(&tick);
+        if order.order_type != OrderType::MARKET && !limits.contains(order.price) {
+            self.refused += 1;
+            reports.push(Report::Status(self.status(order, order.lots, STATUS_REJECTED)));
+            return reports;
+        }
+        let mut open = *order;
+        if order.is_marketable(&tick) {
+            let (price, size) = touch(&tick, order.direction);
+            let lots = self.model.fill.fill_lots(order, size).clamp(0, order.lots as i64) as u32;
+            let killed = order.order_type == OrderType::FOK && lots < order.lots;
+            if lots > 0 && !killed {
+                let price = limits.clamp(self.model.slippage.fill_price(order, price));
+                reports.push(self.report(order, price, lots, tick.stamp));
+                open.lots -= lots;
+            }
+        }
+        if open.lots > 0 {
+            if order.order_type == OrderType::LIMIT {
+                let queue_ahead = level_size(&tick, order.direction, order.price).unwrap_or(0);
+                self.resting.push(Resting { order: open, queue_ahead });
+            } else {
+                reports.push(Report::Status(self.status(order, open.lots, STATUS_CANCELLED)));
+            }
+        }
+        reports
+    }
+
+    /// Withdraw a resting order; the report of its cancelled rest, or `None` if it already
+    /// filled or never rested.
+    pub fn on_cancel(&mut self, cancel: &OrderCancel) -> Option<StatusReport> {
+        let index = self
+            .resting
+            .iter()
+            .position(|r| r.order.client_id == cancel.client_id && r.order.stg_name.as_str() == cancel.stg_name.as_str())?;
+        let rest = self.resting.remove(index).order;
+        Some(self.status(&rest, rest.lots, STATUS_CANCELLED))
+    }
+
+    /// Update the book and fill resting orders the new tick reaches.
+    pub fn on_tick(&mut self, tick: &TickData) -> Vec<Report> {
+        let traded = match self.books.insert(tick.symbol, *tick) {
+            Some(prev) => (tick.volume - prev.volume).max(0),
+            None => 0,
+        };
+
+        // shared by the resting orders, oldest first: the volume traded fills those queued at
+        // prices it reached, the touch those the book moved through
+        let mut volume_left = traded;
+        let mut touch_left = [touch(tick, DirectionType::BUY).1, touch(tick, DirectionType::SELL).1];
+        let mut fills = Vec::new();
+        for resting in self.resting.iter_mut().filter(|r| r.order.symbol == tick.symbol) {
+            let order = resting.order;
+            let lots = if order.is_marketable(tick) {
+                // the book moved through the order: it trades at its own price
+                let left = &mut touch_left[(order.direction == DirectionType::SELL) as usize];
+                let lots = self.model.fill.fill_lots(&order, *left).clamp(0, order.lots as i64);
+                *left -= lots;
+                lots
+            } else {
+                let reached = match order.direction {
+                    DirectionType::BUY => tick.last > 0.0 && tick.last <= order.price,
+                    DirectionType::SELL => tick.last >= order.price,
+                };
+                if !reached {
+                    continue;
+                }
+                // volume traded at the price first works off the queue ahead
+                let past_queue = volume_left - resting.queue_ahead;
+                resting.queue_ahead = (resting.queue_ahead - volume_left).max(0);
+                let lots = past_queue.clamp(0, order.lots as i64);
+                volume_left -= lots;
+                lots
+            } as u32;
+            if lots > 0 {
+                resting.order.lots -= lots;
+                fills.push((order, lots));
+            }
+        }
+        self.resting.retain(|r| r.order.lots > 0);
+
+        fills
+            .into_iter()
+            .map(|(order, lots)| self.report(&order, order.price, lots, tick.stamp))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::FeeRates;
+    use crate::types::{LimitLock, NameType, OffsetFlagType};
+
+    fn tick(stamp: i64, last: f64, volume: i64) -> TickData {
+        TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp,
+            last,
+            volume,
+            bp1: 100.0,
+            bv1: 5,
+            ap1: 101.0,
+            av1: 3,
+            ..TickData::default()
+        }
+    }
+
+    fn buy(price: f64, lots: u32, order_type: OrderType) -> Order {
+        Order {
+            stg_name: NameType::from("s"),
+            symbol: SymbolType::from("rb2505"),
+            timestamp: 0,
+            price,
+            lots,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type,
+            client_id: 1,
+            order_id: 0,
+        }
+    }
+
+    /// The fills among `reports`.
+    fn filled(reports: Vec<Report>) -> Vec<ExecutionReport> {
+        reports
+            .into_iter()
+            .filter_map(|r| match r {
+                Report::Fill(fill) => Some(fill),
+                Report::Status(_) => None,
+            })
+            .collect()
+    }
+
+    /// Lots and status of each status report among `reports`.
+    fn statuses(reports: &[Report]) -> Vec<(u32, u8)> {
+        reports
+            .iter()
+            .filter_map(|r| match r {
+                Report::Status(status) => Some((status.order.lots, status.status)),
+                Report::Fill(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crossing_partial_and_queued_fills() {
+        let per_lot = FeeModel::ByVolume(FeeRates {
+            open: 2.0,
+            ..FeeRates::default()
+        });
+        let mut sim = SimBroker::new().with_fees(SymbolType::from("rb2505"), per_lot, 10.0);
+        sim.on_tick(&tick(1, 100.5, 1000));
+
+        // takes the 3 lots offered, the other 2 rest at 101 ahead of nobody
+        let reports = sim.on_order(&buy(101.0, 5, OrderType::LIMIT));
+        assert!(statuses(&reports).is_empty());
+        let fills = filled(reports);
+        assert_eq!((fills.len(), fills[0].fill.lots, fills[0].fill.price), (1, 3, 101.0));
+        assert_eq!(sim.resting_count(), 1);
+        // the FOK is killed whole, the FAK's rest cancelled
+        assert_eq!(statuses(&sim.on_order(&buy(101.0, 5, OrderType::FOK))), [(5, STATUS_CANCELLED)]);
+        let reports = sim.on_order(&buy(101.0, 5, OrderType::FAK));
+        assert_eq!(statuses(&reports), [(2, STATUS_CANCELLED)]);
+        assert!(matches!(reports[0], Report::Fill(_)) && reports[0].seq() < reports[1].seq());
+
+        // joins the bid behind 5 lots; 4 trade at 100, then 3 more
+        let cancel = OrderCancel {
+            stg_name: NameType::from("s"),
+            symbol: SymbolType::from("rb2505"),
+            timestamp: 0,
+            client_id: 1,
+        };
+        let rest = sim.on_cancel(&cancel).unwrap();
+        assert_eq!((rest.order.lots, rest.is_rejection()), (2, false));
+        assert!(sim.on_cancel(&cancel).is_none());
+        sim.on_order(&buy(100.0, 2, OrderType::LIMIT));
+        assert!(sim.on_tick(&tick(2, 100.0, 1004)).is_empty());
+        let fills = filled(sim.on_tick(&tick(3, 100.0, 1007)));
+        assert_eq!((fills[0].fill.lots, fills[0].fill.price), (2, 100.0));
+        assert!(fills[0].seq > 0);
+        assert_eq!(sim.resting_count(), 0);
+        assert_eq!(sim.commission("s"), 16.0);
+    }
+
+    #[test]
+    fn resting_orders_share_the_volume_a_tick_trades() {
+        let mut sim = SimBroker::new();
+        sim.on_tick(&tick(1, 100.5, 1000));
+        // both queue behind the 5 lots bid at 100
+        sim.on_order(&buy(100.0, 2, OrderType::LIMIT));
+        sim.on_order(&Order {
+            client_id: 2,
+            ..buy(100.0, 2, OrderType::LIMIT)
+        });
+        // 7 lots trade: 5 work off the queue, the older order takes the other 2
+        let fills = filled(sim.on_tick(&tick(2, 100.0, 1007)));
+        assert_eq!(fills.iter().map(|f| (f.fill.client_id, f.fill.lots)).collect::<Vec<_>>(), [(1, 2)]);
+        let fills = filled(sim.on_tick(&tick(3, 100.0, 1008)));
+        assert_eq!(fills.iter().map(|f| (f.fill.client_id, f.fill.lots)).collect::<Vec<_>>(), [(2, 1)]);
+    }
+
+    #[test]
+    fn fill_and_slippage_models_shape_taker_fills() {
+        let model = ExecutionModel {
+            fill: Arc::new(VolumeLimited { participation: 0.5 }),
+            slippage: Arc::new(FixedTicks { ticks: 2.0, min_move: 1.0 }),
+            ..Default::default()
+        };
+        let mut sim = SimBroker::new().with_model(model);
+        sim.on_tick(&tick(1, 100.5, 1000));
+
+        // half of the 3 lots offered, two ticks through the ask
+        let fills = filled(sim.on_order(&buy(110.0, 5, OrderType::FAK)));
+        assert_eq!((fills[0].fill.lots, fills[0].fill.price), (1, 103.0));
+        // but never beyond the limit price
+        let fills = filled(sim.on_order(&buy(102.0, 1, OrderType::FAK)));
+        assert_eq!(fills[0].fill.price, 102.0);
+
+        let naive = ExecutionModel {
+            fill: Arc::new(Unlimited),
+            slippage: Arc::new(Proportional { rate: 0.01 }),
+            ..Default::default()
+        };
+        let mut sim = SimBroker::new().with_model(naive);
+        sim.on_tick(&tick(1, 100.5, 1000));
+        let fills = filled(sim.on_order(&buy(0.0, 10, OrderType::MARKET)));
+        assert_eq!(fills[0].fill.lots, 10);
+        assert!((fills[0].fill.price - 102.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn price_limits_refuse_cap_and_queue_orders() {
+        let limited = |tick: TickData| TickData {
+            limit_down: 95.0,
+            limit_up: 101.5,
+            ..tick
+        };
+        let slippy = ExecutionModel {
+            fill: Arc::new(Unlimited),
+            slippage: Arc::new(FixedTicks { ticks: 2.0, min_move: 1.0 }),
+            ..Default::default()
+        };
+        let mut sim = SimBroker::new().with_model(slippy);
+        sim.on_tick(&limited(tick(1, 100.5, 1000)));
+        assert_eq!(statuses(&sim.on_order(&buy(102.0, 1, OrderType::LIMIT))), [(1, STATUS_REJECTED)]);
+        assert_eq!((sim.refused_count(), sim.resting_count()), (1, 0));
+        // two ticks through the ask would be 103, past the limit
+        let fills = filled(sim.on_order(&buy(0.0, 1, OrderType::MARKET)));
+        assert_eq!(fills[0].fill.price, 101.5);
+
+        // locked limit-up: nothing offered, a buy at the limit joins the bid queue
+        let locked = TickData {
+            bp1: 101.5,
+            bv1: 4,
+            ap1: 0.0,
+            av1: 0,
+            ..limited(tick(2, 101.5, 1000))
+        };
+        assert_eq!(locked.limit_lock(), Some(LimitLock::Up));
+        let mut sim = SimBroker::new();
+        sim.on_tick(&locked);
+        assert!(sim.on_order(&buy(101.5, 2, OrderType::LIMIT)).is_empty());
+        assert!(sim.on_tick(&TickData { volume: 1004, ..locked }).is_empty());
+        let fills = filled(sim.on_tick(&TickData { volume: 1006, ..locked }));
+        assert_eq!((fills[0].fill.lots, fills[0].fill.price), (2, 101.5));
+    }
+}
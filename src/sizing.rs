@@ -0,0 +1,183 @@
+//! Position sizing. Strategies decide direction and price; a `Sizer` decides how many lots.
+use crate::config::ContractInfo;
+use crate::operator::rolling::{self, NanPolicy};
+use crate::types::TickData;
+
+/// What a sizer knows about the account the strategy trades in. The engine refreshes it
+/// before every tick through `Strategy::on_account`.
+#[derive(Debug, Clone, Copy)]
+pub struct SizingContext {
+    /// cash + margin + floating PnL of the strategy's tracker
+    pub equity: f64,
+    /// value of a one-point move per lot in the account's currency, see `ContractInfo::point_value`
+    pub multiplier: f64,
+}
+
+impl Default for SizingContext {
+    fn default() -> Self {
+        Self {
+            equity: 0.0,
+            multiplier: 1.0,
+        }
+    }
+}
+
+impl SizingContext {
+    pub fn new(equity: f64, info: &ContractInfo) -> Self {
+        Self {
+            equity,
+            multiplier: info.point_value(),
+        }
+    }
+
+    /// Whole lots whose notional at `price` fits in `cash`.
+    fn lots_for_notional(&self, cash: f64, price: f64) -> u32 {
+        let per_lot = price * self.multiplier;
+        if !(cash > 0.0 && per_lot > 0.0) {
+            return 0;
+        }
+        (cash / per_lot).floor().min(u32::MAX as f64) as u32
+    }
+}
+
+pub trait Sizer: Send {
+    /// Fed every tick of the strategy's symbol, before `lots` may be asked.
+    fn on_tick(&mut self, _tick: &TickData) {}
+
+    /// Lots for a new position entered at `price`; 0 means the trade should be skipped.
+    fn lots(&mut self, price: f64, ctx: &SizingContext) -> u32;
+}
+
+/// Always the same size.
+pub struct FixedLots(pub u32);
+
+impl Sizer for FixedLots {
+    fn lots(&mut self, _price: f64, _ctx: &SizingContext) -> u32 {
+        self.0
+    }
+}
+
+/// Notional exposure of `fraction` × equity.
+pub struct FixedFraction {
+    pub fraction: f64,
+}
+
+impl Sizer for FixedFraction {
+    fn lots(&mut self, price: f64, ctx: &SizingContext) -> u32 {
+        ctx.lots_for_notional(self.fraction * ctx.equity, price)
+    }
+}
+
+/// Volatility targeting: risk `risk_fraction` of equity on a stop `atr_multiple` ATRs away.
+///
+/// The range is measured tick to tick (|Δlast|), so `window` is a number of ticks.
+/// Returns 0 until the window has filled.
+pub struct AtrTarget {
+    risk_fraction: f64,
+    atr_multiple: f64,
+    atr: rolling::Mean,
+    prev_last: f64,
+    atr_value: f64,
+}
+
+impl AtrTarget {
+    pub fn new(window: usize, risk_fraction: f64, atr_multiple: f64) -> Self {
+        Self {
+            risk_fraction,
+            atr_multiple,
+            atr: rolling::Mean::new(window).with_nan_policy(NanPolicy::Propagate),
+            prev_last: f64::NAN,
+            atr_value: f64::NAN,
+        }
+    }
+}
+
+impl Sizer for AtrTarget {
+    fn on_tick(&mut self, tick: &TickData) {
+        self.atr_value = self.atr.update((tick.last - self.prev_last).abs());
+        self.prev_last = tick.last;
+    }
+
+    fn lots(&mut self, _price: f64, ctx: &SizingContext) -> u32 {
+        // losing `stop` price units costs `stop * multiplier` per lot, same as a notional at that price
+        let stop = self.atr_value * self.atr_multiple;
+        ctx.lots_for_notional(self.risk_fraction * ctx.equity, stop)
+    }
+}
+
+/// Kelly criterion for a strategy that wins `win_rate` of the time with an average
+/// win/loss ratio of `payoff`, scaled by `scale` (0.5 = half Kelly) and applied to notional.
+pub struct Kelly {
+    pub win_rate: f64,
+    pub payoff: f64,
+    pub scale: f64,
+}
+
+impl Kelly {
+    /// Optimal fraction of equity; negative edges are clamped to 0.
+    pub fn fraction(&self) -> f64 {
+        let f = self.win_rate - (1.0 - self.win_rate) / self.payoff;
+        (f * self.scale).max(0.0)
+    }
+}
+
+impl Sizer for Kelly {
+    fn lots(&mut self, price: f64, ctx: &SizingContext) -> u32 {
+        ctx.lots_for_notional(self.fraction() * ctx.equity, price)
+    }
+}
+
+/// Parse a sizer from a colon-separated spec, as passed to `strategies::create`:
+/// `fixed:<lots>`, `fraction:<f>`, `atr:<window>:<risk_fraction>:<atr_multiple>`,
+/// `kelly:<win_rate>:<payoff>:<scale>`.
+pub fn from_spec(spec: &str) -> Result<Box<dyn Sizer>, String> {
+    let fields: Vec<&str> = spec.split(':').collect();
+    let num = |s: &str| s.parse::<f64>().map_err(|_| format!("invalid number {:?} in sizer {:?}", s, spec));
+    match fields.as_slice() {
+        ["fixed", lots] => Ok(Box::new(FixedLots(lots.parse().map_err(|_| format!("invalid lots {:?}", lots))?))),
+        ["fraction", f] => Ok(Box::new(FixedFraction { fraction: num(f)? })),
+        ["atr", window, risk, multiple] => {
+            let window: usize = window.parse().map_err(|_| format!("invalid window {:?}", window))?;
+            if !(1..=rolling::MAX_WINDOW).contains(&window) {
+                return Err(format!("atr window must be from 1 to {}, got {}", rolling::MAX_WINDOW, window));
+            }
+            Ok(Box::new(AtrTarget::new(window, num(risk)?, num(multiple)?)))
+        }
+        ["kelly", win_rate, payoff, scale] => Ok(Box::new(Kelly {
+            win_rate: num(win_rate)?,
+            payoff: num(payoff)?,
+            scale: num(scale)?,
+        })),
+        _ => Err(format!("unknown sizer {:?}", spec)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizers() {
+        let ctx = SizingContext {
+            equity: 1e6,
+            multiplier: 10.0,
+        };
+        assert_eq!(from_spec("fixed:3").unwrap().lots(3500.0, &ctx), 3);
+        // 20% of 1e6 over 35_000 per lot
+        assert_eq!(from_spec("fraction:0.2").unwrap().lots(3500.0, &ctx), 5);
+        // f* = 0.6 - 0.4 / 2 = 0.4, half Kelly -> 0.2
+        assert_eq!(from_spec("kelly:0.6:2:0.5").unwrap().lots(3500.0, &ctx), 5);
+        assert_eq!(from_spec("kelly:0.3:1:1").unwrap().lots(3500.0, &ctx), 0);
+        assert!(from_spec("atr:0:0.01:2").is_err());
+
+        let mut atr = AtrTarget::new(2, 0.01, 2.0);
+        let tick = |last| TickData { last, ..TickData::default() };
+        atr.on_tick(&tick(3500.0));
+        assert_eq!(atr.lots(3500.0, &ctx), 0, "not warm yet");
+        atr.on_tick(&tick(3504.0));
+        atr.on_tick(&tick(3498.0));
+        // atr = 5, stop = 10 -> 100 per lot against 10_000 of risk
+        assert_eq!(atr.lots(3500.0, &ctx), 100);
+        assert!(from_spec("atr:2:0.01").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Clock skew between tick stamps and local receive time. A publisher whose clock drifts
+//! still delivers ticks on time, so the watchdog sees nothing wrong, but every time-window
+//! computation downstream (TWAP slices, session boundaries, order timeouts) quietly shifts.
+use crate::types::SymbolType;
+use crate::watchdog::DataAlert;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkewConfig {
+    /// alert once a symbol's smoothed skew exceeds this, either way
+    pub max_skew_ms: i64,
+    /// EWMA weight of each new sample
+    pub alpha: f64,
+    /// samples per symbol before alerts start
+    pub warmup: u32,
+}
+
+impl Default for SkewConfig {
+    fn default() -> Self {
+        Self {
+            max_skew_ms: 500,
+            alpha: 0.05,
+            warmup: 20,
+        }
+    }
+}
+
+/// Receive time minus tick stamp of one symbol: network latency plus clock offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SkewStats {
+    pub last_ms: i64,
+    /// exponentially weighted mean
+    pub mean_ms: f64,
+    /// smallest seen; the best estimate of the clock offset alone
+    pub min_ms: i64,
+    pub samples: u32,
+}
+
+#[derive(Default)]
+struct SymbolSkew {
+    stats: SkewStats,
+    drifting: bool,
+}
+
+pub struct SkewMonitor {
+    cfg: SkewConfig,
+    symbols: HashMap<SymbolType, SymbolSkew>,
+}
+
+impl SkewMonitor {
+    pub fn new(cfg: SkewConfig) -> Self {
+        Self {
+            cfg,
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn stats(&self, symbol: &SymbolType) -> Option<SkewStats> {
+        self.symbols.get(symbol).map(|s| s.stats)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&SymbolType, &SkewStats)> {
+        self.symbols.iter().map(|(symbol, s)| (symbol, &s.stats))
+    }
+
+    /// Record a tick stamped `stamp` received at `now`. Alerts when the smoothed skew crosses
+    /// `max_skew_ms`, and again once it is back under half of that.
+    pub fn on_tick(&mut self, symbol: SymbolType, stamp: i64, now: i64) -> Option<DataAlert> {
+        let entry = self.symbols.entry(symbol).or_default();
+        let skew = now - stamp;
+        let stats = &mut entry.stats;
+        if stats.samples == 0 {
+            stats.mean_ms = skew as f64;
+            stats.min_ms = skew;
+        } else {
+            stats.mean_ms += self.cfg.alpha * (skew as f64 - stats.mean_ms);
+            stats.min_ms = stats.min_ms.min(skew);
+        }
+        stats.last_ms = skew;
+        stats.samples = stats.samples.saturating_add(1);
+        if stats.samples < self.cfg.warmup {
+            return None;
+        }
+
+        let skew_ms = stats.mean_ms.round() as i64;
+        let limit = self.cfg.max_skew_ms;
+        if !entry.drifting && skew_ms.abs() > limit {
+            entry.drifting = true;
+            return Some(DataAlert::ClockSkew { symbol, skew_ms });
+        }
+        if entry.drifting && skew_ms.abs() <= limit / 2 {
+            entry.drifting = false;
+            return Some(DataAlert::SkewRecovered { symbol, skew_ms });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts_when_the_publisher_clock_drifts() {
+        let symbol = SymbolType::from("rb2505");
+        let mut skew = SkewMonitor::new(SkewConfig {
+            max_skew_ms: 100,
+            alpha: 0.5,
+            warmup: 3,
+        });
+        let mut now = 1_700_000_000_000;
+        for _ in 0..5 {
+            now += 500;
+            assert_eq!(skew.on_tick(symbol, now - 20, now), None);
+        }
+        // the publisher's clock falls behind by a second
+        now += 500;
+        assert_eq!(skew.on_tick(symbol, now - 1020, now), Some(DataAlert::ClockSkew { symbol, skew_ms: 520 }));
+        assert_eq!(skew.on_tick(symbol, now - 1020, now), None, "alerted once");
+        let recovered = (0..10).find_map(|_| skew.on_tick(symbol, now - 20, now));
+        assert!(matches!(recovered, Some(DataAlert::SkewRecovered { .. })));
+        assert_eq!(skew.stats(&symbol).map(|s| s.min_ms), Some(20));
+    }
+}
@@ -0,0 +1,326 @@
+//! A/B allocation for gradual rollouts: versions A and B of a strategy run on the same ticks,
+//! and of the size the versions ask for, a configurable fraction is traded for A and the rest
+//! for B. Each arm is its own strategy (named `<name>/A`, `<name>/B`) with its own tracker, so
+//! their PnL, fees and positions stay apart. An arm holds what its orders filled; a rejected
+//! or cancelled order leaves it free to send those lots again.
+use crate::calendar::Session;
+use crate::clock::SharedClock;
+use crate::conduct::ConductWarning;
+use crate::sizing::SizingContext;
+use crate::stops::StopLevels;
+use crate::strategy::{EngineContext, Orders, Strategy};
+use crate::tracking::Benchmark;
+use crate::types::{DirectionType, LimitLock, NameType, OffsetFlagType, Order, SymbolType, TickData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arm {
+    A,
+    B,
+}
+
+/// One arm of an A/B split. The wrapped version sizes as if it traded the whole allocation;
+/// this arm sends its share of the lots, keeping its position in proportion to the version's
+/// own as it scales in and out.
+pub struct SplitArm {
+    inner: Box<dyn Strategy>,
+    name: NameType,
+    arm: Arm,
+    /// share of the size traded for arm A, in [0, 1]
+    fraction_a: f64,
+    /// lots the wrapped version believes it holds, long and short
+    wanted: [u32; 2],
+    /// lots this arm's fills hold, long and short
+    held: [u32; 2],
+    /// lots its orders in flight add to each side, or take from it when negative
+    working: [i64; 2],
+}
+
+/// Wrap versions `a` and `b` for an A/B split giving `fraction_a` of the size to `a`.
+/// Register both, e.g. through `CtaEngine::add_ab_split`, on the same symbol.
+pub fn ab_split(a: Box<dyn Strategy>, b: Box<dyn Strategy>, fraction_a: f64) -> (SplitArm, SplitArm) {
+    (SplitArm::new(a, Arm::A, fraction_a), SplitArm::new(b, Arm::B, fraction_a))
+}
+
+/// The position side an order adds to or takes from: 0 long, 1 short.
+fn side(order: &Order) -> usize {
+    match (order.offset, order.direction) {
+        (OffsetFlagType::OPEN, DirectionType::BUY) | (OffsetFlagType::CLOSE, DirectionType::SELL) => 0,
+        (OffsetFlagType::OPEN, DirectionType::SELL) | (OffsetFlagType::CLOSE, DirectionType::BUY) => 1,
+    }
+}
+
+/// The lots `order` adds to its side, negative for a close.
+fn change(order: &Order) -> i64 {
+    match order.offset {
+        OffsetFlagType::OPEN => order.lots as i64,
+        OffsetFlagType::CLOSE => -(order.lots as i64),
+    }
+}
+
+impl SplitArm {
+    pub fn new(inner: Box<dyn Strategy>, arm: Arm, fraction_a: f64) -> Self {
+        let suffix = match arm {
+            Arm::A => "/A",
+            Arm::B => "/B",
+        };
+        let name = format!("{}{}", inner.name().as_str(), suffix);
+        Self {
+            inner,
+            name: NameType::from(name.as_str()),
+            arm,
+            fraction_a: fraction_a.clamp(0.0, 1.0),
+            wanted: [0; 2],
+            held: [0; 2],
+            working: [0; 2],
+        }
+    }
+
+    pub fn arm(&self) -> Arm {
+        self.arm
+    }
+
+    /// This arm's share of the capital.
+    pub fn fraction(&self) -> f64 {
+        match self.arm {
+            Arm::A => self.fraction_a,
+            Arm::B => 1.0 - self.fraction_a,
+        }
+    }
+
+    /// This arm's lots of a `lots` position; the two arms' shares always add up to `lots`.
+    fn share(&self, lots: u32) -> u32 {
+        let a = (lots as f64 * self.fraction_a).round() as u32;
+        match self.arm {
+            Arm::A => a,
+            Arm::B => lots - a,
+        }
+    }
+
+    /// Lots on `side` once the orders in flight are filled.
+    fn expected(&self, side: usize) -> u32 {
+        (self.held[side] as i64 + self.working[side]).max(0) as u32
+    }
+
+    /// Rescale an order of the wrapped version to this arm's share, or drop it if the share
+    /// does not change.
+    fn scale(&mut self, mut order: Order) -> Option<Order> {
+        let side = side(&order);
+        let lots = match order.offset {
+            OffsetFlagType::OPEN => {
+                self.wanted[side] += order.lots;
+                self.share(self.wanted[side]).saturating_sub(self.expected(side))
+            }
+            OffsetFlagType::CLOSE => {
+                self.wanted[side] -= order.lots.min(self.wanted[side]);
+                self.expected(side).saturating_sub(self.share(self.wanted[side]))
+            }
+        };
+        if lots == 0 {
+            return None;
+        }
+        order.lots = lots;
+        order.stg_name = self.name;
+        self.working[side] += change(&order);
+        Some(order)
+    }
+}
+
+impl Strategy for SplitArm {
+    fn name(&self) -> NameType {
+        self.name
+    }
+
+    fn update(&mut self, tick: &TickData) -> Orders {
+        let orders = self.inner.update(tick);
+        orders.into_iter().filter_map(|order| self.scale(order)).collect()
+    }
+
+    fn target_position(&mut self, tick: &TickData) -> Option<i32> {
+        let target = self.inner.target_position(tick)?;
+        Some(target.signum() * self.share(target.unsigned_abs()) as i32)
+    }
+
+    fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+        self.inner.on_param_update(name, value)
+    }
+
+    fn on_account(&mut self, ctx: &SizingContext) {
+        // the version sizes for the whole allocation; this arm trades its share of that
+        let fraction = self.fraction();
+        let equity = if fraction > 0.0 { ctx.equity / fraction } else { 0.0 };
+        self.inner.on_account(&SizingContext { equity, ..*ctx });
+    }
+
+    fn on_conduct_warning(&mut self, warning: &ConductWarning) {
+        self.inner.on_conduct_warning(warning)
+    }
+
+    fn stop_levels(&self, entry: &Order) -> StopLevels {
+        self.inner.stop_levels(entry)
+    }
+
+    fn benchmark(&self) -> Option<Benchmark> {
+        self.inner.benchmark()
+    }
+
+    fn references(&self) -> Vec<SymbolType> {
+        self.inner.references()
+    }
+
+    fn on_reference_tick(&mut self, tick: &TickData) {
+        self.inner.on_reference_tick(tick)
+    }
+
+    fn order_timeout_ms(&self) -> Option<i64> {
+        self.inner.order_timeout_ms()
+    }
+
+    fn take_cancels(&mut self) -> Vec<u64> {
+        self.inner.take_cancels()
+    }
+
+    fn on_limit_lock(&mut self, lock: Option<LimitLock>) {
+        self.inner.on_limit_lock(lock)
+    }
+
+    fn on_order_cancelled(&mut self, order: &Order) {
+        // `order` carries the lots that were still open
+        self.working[side(order)] -= change(order);
+        self.inner.on_order_cancelled(order)
+    }
+
+    fn on_order_rejected(&mut self, order: &Order, reason: &str) {
+        self.working[side(order)] -= change(order);
+        self.inner.on_order_rejected(order, reason)
+    }
+
+    fn on_fill(&mut self, fill: &Order, remaining: u32) {
+        let side = side(fill);
+        self.held[side] = (self.held[side] as i64 + change(fill)).max(0) as u32;
+        self.working[side] -= change(fill);
+        self.inner.on_fill(fill, remaining)
+    }
+
+    fn on_stop_triggered(&mut self, order: &Order) {
+        // a stop closes the whole side, for the arm and for the version
+        let side = side(order);
+        self.held[side] = 0;
+        self.working[side] = 0;
+        self.wanted[side] = 0;
+        self.inner.on_stop_triggered(order)
+    }
+
+    fn on_clock(&mut self, clock: SharedClock) {
+        self.inner.on_clock(clock)
+    }
+
+    fn on_resume(&mut self, long_lots: u32, short_lots: u32) {
+        self.held = [long_lots, short_lots];
+        self.working = [0; 2];
+        let fraction = self.fraction();
+        let wanted = |lots: u32| if fraction > 0.0 { (lots as f64 / fraction).round() as u32 } else { 0 };
+        self.wanted = [wanted(long_lots), wanted(short_lots)];
+        self.inner.on_resume(self.wanted[0], self.wanted[1])
+    }
+
+    fn on_start(&mut self, ctx: &EngineContext) {
+        self.inner.on_start(ctx)
+    }
+
+    fn on_stop(&mut self) {
+        self.inner.on_stop()
+    }
+
+    fn on_day_open(&mut self) {
+        self.inner.on_day_open()
+    }
+
+    fn on_day_close(&mut self) {
+        self.inner.on_day_close()
+    }
+
+    fn on_session_start(&mut self, session: &Session) {
+        self.inner.on_session_start(session)
+    }
+
+    fn on_session_end(&mut self, session: &Session) {
+        self.inner.on_session_end(session)
+    }
+
+    fn on_toggle(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        self.inner.on_toggle(name, enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::smallvec;
+    use crate::types::OrderType;
+
+    /// Opens `lots` on the first tick, adds on the second and closes all on the third.
+    struct Scripted {
+        step: usize,
+        lots: u32,
+    }
+
+    impl Strategy for Scripted {
+        fn name(&self) -> NameType {
+            NameType::from("scripted")
+        }
+
+        fn update(&mut self, tick: &TickData) -> Orders {
+            self.step += 1;
+            let (lots, offset, direction) = match self.step {
+                1 | 2 => (self.lots, OffsetFlagType::OPEN, DirectionType::BUY),
+                3 => (2 * self.lots, OffsetFlagType::CLOSE, DirectionType::SELL),
+                _ => return Orders::new(),
+            };
+            smallvec![Order {
+                stg_name: self.name(),
+                symbol: tick.symbol,
+                timestamp: tick.stamp,
+                price: tick.last,
+                lots,
+                direction,
+                offset,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            }]
+        }
+    }
+
+    #[test]
+    fn splits_size_between_arms() {
+        let (mut a, mut b) = ab_split(Box::new(Scripted { step: 0, lots: 3 }), Box::new(Scripted { step: 0, lots: 3 }), 0.3);
+        assert_eq!(a.name().as_str(), "scripted/A");
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            last: 3500.0,
+            ..Default::default()
+        };
+        let lots = |arm: &mut SplitArm| (0..3).map(|_| arm.update(&tick).first().map(|o| o.lots)).collect::<Vec<_>>();
+        // 3 lots → A 1, B 2; 6 lots → A 2, B 4; both arms close out completely
+        assert_eq!(lots(&mut a), vec![Some(1), Some(1), Some(2)]);
+        assert_eq!(lots(&mut b), vec![Some(2), Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn sends_rejected_lots_again() {
+        let (mut a, _) = ab_split(Box::new(Scripted { step: 0, lots: 4 }), Box::new(Scripted { step: 0, lots: 4 }), 0.5);
+        let tick = TickData {
+            symbol: SymbolType::from("rb2505"),
+            last: 3500.0,
+            ..Default::default()
+        };
+        let first = a.update(&tick)[0];
+        assert_eq!(first.lots, 2);
+        a.on_order_rejected(&first, "send failed");
+        // the version now wants 8 long; the arm holds none of its 4
+        assert_eq!(a.update(&tick)[0].lots, 4);
+        a.on_fill(&Order { lots: 4, ..first }, 0);
+        assert_eq!(a.held, [4, 0]);
+        assert_eq!(a.working, [0, 0]);
+    }
+}
@@ -0,0 +1,268 @@
+//! End-of-day statement export, for reconciling the engine's books against the broker's
+//! settlement statement (结算单). Each day's orders, fills and closing positions are written
+//! to `<dir>/<yyyymmdd>/` as three CSV files laid out like the statement's sections, with
+//! its column names and a leading `策略` column; summing a contract's rows over strategies
+//! gives the account's line on the broker's statement:
+//!
+//! - `orders.csv`: 委托记录, every order sent, from the audit log
+//! - `trades.csv`: 成交记录, every fill with its commission (手续费) and 平仓盈亏
+//! - `positions.csv`: 持仓汇总, what each strategy carries into the next session, marked at
+//!   the last price as 今结算
+//!
+//! The files are UTF-8 with a byte order mark, so spreadsheet software reads the headers.
+//! Closes are written 平 whether the broker books them as 平今 or 平昨.
+use crate::query::{FillDetail, StrategySnapshot};
+use crate::symbols;
+use crate::timeutil;
+use crate::types::{DirectionType, OffsetFlagType, Order};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BOM: &str = "\u{feff}";
+const ORDERS_HEADER: &str = "策略,委托日期,委托时间,交易所,合约,买/卖,开/平,报单类型,委托价,委托手数,本地编号,报单编号";
+const TRADES_HEADER: &str = "策略,成交日期,成交时间,交易所,品种,合约,买/卖,投/保,成交价,手数,成交额,开/平,手续费,平仓盈亏,本地编号,报单编号";
+const POSITIONS_HEADER: &str = "策略,交易所,合约,买持,买均价,卖持,卖均价,今结算,持仓盈亏,投/保";
+
+/// One strategy's fill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub strategy: String,
+    pub symbol: String,
+    pub multiplier: f64,
+    pub fill: FillDetail,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    /// trading day, `yyyymmdd`
+    pub date: u32,
+    pub orders: Vec<Order>,
+    pub trades: Vec<Trade>,
+    /// every strategy, flat ones included, for the positions section
+    pub positions: Vec<StrategySnapshot>,
+    /// symbol → exchange, e.g. `rb2505` → `SHFE`; blank where unknown
+    pub exchanges: BTreeMap<String, String>,
+}
+
+impl Statement {
+    /// The statement for `date` from snapshots taken with `SnapshotFilter::fills_since` and the
+    /// orders sent over the same span.
+    pub fn new(date: u32, snapshots: Vec<StrategySnapshot>, mut orders: Vec<Order>) -> Self {
+        let mut trades: Vec<Trade> = snapshots
+            .iter()
+            .flat_map(|s| {
+                s.booked_fills.iter().map(|&fill| Trade {
+                    strategy: s.strategy.clone(),
+                    symbol: s.symbol.clone(),
+                    multiplier: s.multiplier,
+                    fill,
+                })
+            })
+            .collect();
+        trades.sort_by_key(|t| (t.fill.fill.timestamp, t.fill.fill.order_id));
+        orders.sort_by_key(|o| (o.timestamp, o.order_id));
+        let mut positions = snapshots;
+        positions.sort_by(|a, b| (&a.strategy, &a.symbol).cmp(&(&b.strategy, &b.symbol)));
+        Self {
+            date,
+            orders,
+            trades,
+            positions,
+            exchanges: BTreeMap::new(),
+        }
+    }
+
+    /// Name `symbol`'s exchange in the files.
+    pub fn with_exchange(mut self, symbol: &str, exchange: &str) -> Self {
+        self.exchanges.insert(symbol.to_string(), exchange.to_string());
+        self
+    }
+
+    fn exchange(&self, symbol: &str) -> &str {
+        self.exchanges.get(symbol).map_or("", String::as_str)
+    }
+
+    pub fn orders_csv(&self) -> String {
+        let mut csv = format!("{}{}\n", BOM, ORDERS_HEADER);
+        for o in &self.orders {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{:?},{},{},{},{}",
+                o.stg_name.as_str(),
+                timeutil::local_date(o.timestamp),
+                clock_time(o.timestamp),
+                self.exchange(o.symbol.as_str()),
+                o.symbol.as_str(),
+                side(o.direction),
+                offset(o.offset),
+                o.order_type,
+                o.price,
+                o.lots,
+                o.client_id,
+                o.order_id
+            );
+        }
+        csv
+    }
+
+    pub fn trades_csv(&self) -> String {
+        let mut csv = format!("{}{}\n", BOM, TRADES_HEADER);
+        for t in &self.trades {
+            let f = &t.fill.fill;
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},投机,{},{},{},{},{:.2},{:.2},{},{}",
+                t.strategy,
+                timeutil::local_date(f.timestamp),
+                clock_time(f.timestamp),
+                self.exchange(&t.symbol),
+                symbols::root(&t.symbol),
+                t.symbol,
+                side(f.direction),
+                f.price,
+                f.lots,
+                f.price * f.lots as f64 * t.multiplier,
+                offset(f.offset),
+                t.fill.fee,
+                t.fill.realized_pnl,
+                f.client_id,
+                f.order_id
+            );
+        }
+        csv
+    }
+
+    pub fn positions_csv(&self) -> String {
+        let mut csv = format!("{}{}\n", BOM, POSITIONS_HEADER);
+        for s in self.positions.iter().filter(|s| s.long.is_some() || s.short.is_some()) {
+            let (long_lots, long_avg) = s.long.map_or((0, 0.0), |p| (p.lots, p.avg_price));
+            let (short_lots, short_avg) = s.short.map_or((0, 0.0), |p| (p.lots, p.avg_price));
+            let floating = ((s.last_price - long_avg) * long_lots as f64 + (short_avg - s.last_price) * short_lots as f64) * s.multiplier;
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{:.2},投机",
+                s.strategy,
+                self.exchange(&s.symbol),
+                s.symbol,
+                long_lots,
+                long_avg,
+                short_lots,
+                short_avg,
+                s.last_price,
+                floating
+            );
+        }
+        csv
+    }
+}
+
+fn side(direction: DirectionType) -> &'static str {
+    match direction {
+        DirectionType::BUY => "买",
+        DirectionType::SELL => "卖",
+    }
+}
+
+fn offset(offset: OffsetFlagType) -> &'static str {
+    match offset {
+        OffsetFlagType::OPEN => "开",
+        OffsetFlagType::CLOSE => "平",
+    }
+}
+
+/// `HH:MM:SS` local time of `stamp`.
+fn clock_time(stamp: i64) -> String {
+    let secs = timeutil::local_secs_of_day(stamp);
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Write the statement's three files to `<dir>/<date>/`; returns that directory.
+pub fn save<P: AsRef<Path>>(dir: P, statement: &Statement) -> io::Result<PathBuf> {
+    let dir = dir.as_ref().join(statement.date.to_string());
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("orders.csv"), statement.orders_csv())?;
+    fs::write(dir.join("trades.csv"), statement.trades_csv())?;
+    fs::write(dir.join("positions.csv"), statement.positions_csv())?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handoff::{OpenOrder, PositionState};
+    use crate::types::{NameType, OrderType, SymbolType};
+
+    #[test]
+    fn lays_out_the_statement_sections() {
+        let at = timeutil::local_stamp(20250313, 9 * 3600 + 30 * 60 + 5);
+        let fill = |direction, offset, price, fee, realized_pnl| FillDetail {
+            fill: OpenOrder {
+                client_id: 1,
+                order_id: 42,
+                direction,
+                offset,
+                order_type: OrderType::LIMIT,
+                price,
+                lots: 2,
+                timestamp: at,
+            },
+            fee,
+            realized_pnl,
+        };
+        let snapshot = StrategySnapshot {
+            strategy: "aberration_20".into(),
+            symbol: "rb2505".into(),
+            worker: 0,
+            equity: 1e6,
+            available_cash: 1e6,
+            last_price: 3510.0,
+            multiplier: 10.0,
+            realized_pnl: 0.0,
+            total_fee: 0.0,
+            long: Some(PositionState { lots: 2, avg_price: 3500.0 }),
+            short: None,
+            open_orders: 0,
+            fills: 2,
+            rejected_orders: 0,
+            halted: false,
+            paused: false,
+            account: None,
+            equity_curve: Vec::new(),
+            curve_samples: 0,
+            performance: Default::default(),
+            recent_fills: Vec::new(),
+            booked_fills: vec![
+                fill(DirectionType::BUY, OffsetFlagType::OPEN, 3500.0, 3.5, 0.0),
+                fill(DirectionType::SELL, OffsetFlagType::CLOSE, 3520.0, 3.52, 400.0),
+            ],
+        };
+        let order = fill(DirectionType::BUY, OffsetFlagType::OPEN, 3500.0, 0.0, 0.0)
+            .fill
+            .to_order(NameType::from("aberration_20"), SymbolType::from("rb2505"));
+        let statement = Statement::new(20250313, vec![snapshot], vec![order]).with_exchange("rb2505", "SHFE");
+
+        let orders = statement.orders_csv();
+        assert!(orders.starts_with(BOM));
+        assert_eq!(
+            orders.lines().nth(1),
+            Some("aberration_20,20250313,09:30:05,SHFE,rb2505,买,开,LIMIT,3500,2,1,42")
+        );
+        let trades = statement.trades_csv();
+        let trades: Vec<&str> = trades.lines().skip(1).collect();
+        assert_eq!(
+            trades[0],
+            "aberration_20,20250313,09:30:05,SHFE,rb,rb2505,买,投机,3500,2,70000,开,3.50,0.00,1,42"
+        );
+        assert_eq!(
+            trades[1],
+            "aberration_20,20250313,09:30:05,SHFE,rb,rb2505,卖,投机,3520,2,70400,平,3.52,400.00,1,42"
+        );
+        assert_eq!(
+            statement.positions_csv().lines().nth(1),
+            Some("aberration_20,SHFE,rb2505,2,3500,0,0,3510,200.00,投机")
+        );
+    }
+}
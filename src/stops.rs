@@ -0,0 +1,147 @@
+//! Engine-side stop-loss / take-profit. Strategies register levels when they open a position
+//! (`Strategy::stop_levels`) and the engine closes it when the last price crosses one, whether
+//! or not the strategy produces a signal on that tick.
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, TickData};
+
+/// Absolute trigger prices for one position; `None` disables that side.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StopLevels {
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+}
+
+impl StopLevels {
+    /// Levels `loss` and `profit` price units away from an entry; non-positive distances are off.
+    pub fn around(entry: &Order, loss: f64, profit: f64) -> Self {
+        let sign = match entry.direction {
+            DirectionType::BUY => 1.0,
+            DirectionType::SELL => -1.0,
+        };
+        Self {
+            stop_loss: (loss > 0.0).then_some(entry.price - sign * loss),
+            take_profit: (profit > 0.0).then_some(entry.price + sign * profit),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stop_loss.is_none() && self.take_profit.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Leg {
+    lots: u32,
+    levels: StopLevels,
+}
+
+/// Open lots and stop levels of one strategy on one symbol, long and short tracked separately
+/// like `PerformanceTracker` does.
+#[derive(Debug, Default)]
+pub struct StopManager {
+    long: Leg,
+    short: Leg,
+}
+
+impl StopManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a sent order. Opening orders replace the side's levels with `levels`.
+    pub fn on_order(&mut self, order: &Order, levels: StopLevels) {
+        match (order.offset, order.direction) {
+            (OffsetFlagType::OPEN, DirectionType::BUY) => {
+                self.long.lots += order.lots;
+                self.long.levels = levels;
+            }
+            (OffsetFlagType::OPEN, DirectionType::SELL) => {
+                self.short.lots += order.lots;
+                self.short.levels = levels;
+            }
+            (OffsetFlagType::CLOSE, DirectionType::SELL) => Self::reduce(&mut self.long, order.lots),
+            (OffsetFlagType::CLOSE, DirectionType::BUY) => Self::reduce(&mut self.short, order.lots),
+        }
+    }
+
+    /// Clear the levels of the side `close` is closing, so a sent stop order does not fire
+    /// again while its fill is outstanding.
+    pub fn disarm(&mut self, close: &Order) {
+        match close.direction {
+            DirectionType::SELL => self.long.levels = StopLevels::default(),
+            DirectionType::BUY => self.short.levels = StopLevels::default(),
+        }
+    }
+
+    fn reduce(leg: &mut Leg, lots: u32) {
+        leg.lots -= lots.min(leg.lots);
+        if leg.lots == 0 {
+            leg.levels = StopLevels::default();
+        }
+    }
+
+    /// The closing order for a position whose level `tick.last` crossed, if any. The position
+    /// is only released once the fill is passed back through `on_order`; `disarm` it meanwhile.
+    pub fn check(&self, stg_name: NameType, tick: &TickData) -> Option<Order> {
+        let close = |lots, price, direction| Order {
+            stg_name,
+            symbol: tick.symbol,
+            timestamp: tick.stamp,
+            price,
+            lots,
+            direction,
+            offset: OffsetFlagType::CLOSE,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+
+        let long = self.long.levels;
+        if self.long.lots > 0 && (long.stop_loss.is_some_and(|p| tick.last <= p) || long.take_profit.is_some_and(|p| tick.last >= p)) {
+            return Some(close(self.long.lots, tick.bp1, DirectionType::SELL));
+        }
+        let short = self.short.levels;
+        if self.short.lots > 0 && (short.stop_loss.is_some_and(|p| tick.last >= p) || short.take_profit.is_some_and(|p| tick.last <= p)) {
+            return Some(close(self.short.lots, tick.ap1, DirectionType::BUY));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SymbolType;
+
+    #[test]
+    fn long_stop_loss_closes_full_position() {
+        let entry = Order {
+            stg_name: NameType::from("s"),
+            symbol: SymbolType::from("rb2505"),
+            timestamp: 0,
+            price: 100.0,
+            lots: 2,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        };
+        let mut stops = StopManager::new();
+        stops.on_order(&entry, StopLevels::around(&entry, 5.0, 0.0));
+
+        let tick = |last| TickData {
+            last,
+            bp1: last - 1.0,
+            ..TickData::default()
+        };
+        assert!(stops.check(entry.stg_name, &tick(96.0)).is_none());
+        let close = stops.check(entry.stg_name, &tick(95.0)).unwrap();
+        assert_eq!(
+            (close.lots, close.direction, close.offset, close.price),
+            (2, DirectionType::SELL, OffsetFlagType::CLOSE, 94.0)
+        );
+
+        stops.on_order(&close, StopLevels::default());
+        assert!(stops.check(entry.stg_name, &tick(90.0)).is_none());
+    }
+}
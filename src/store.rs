@@ -0,0 +1,237 @@
+//! Durable order and fill history in an embedded SQLite database. Every order the engine
+//! sends and every fill it books is a row tagged with the engine session that produced it,
+//! so history survives crashes and restarts and can be queried with any SQLite client:
+//!
+//! ```sql
+//! SELECT strategy, count(*), sum(lots) FROM fills WHERE session = ? GROUP BY strategy;
+//! ```
+//!
+//! The engine writes from its own thread (see `CtaEngine::enable_order_store`), so a slow
+//! disk never stalls a worker.
+use crate::types::Order;
+use rusqlite::{Connection, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS orders (
+    id INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    strategy TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    client_id INTEGER NOT NULL,
+    order_id INTEGER NOT NULL DEFAULT 0,
+    stamp INTEGER NOT NULL,
+    direction TEXT NOT NULL,
+    offset TEXT NOT NULL,
+    order_type TEXT NOT NULL,
+    price REAL NOT NULL,
+    lots INTEGER NOT NULL,
+    recorded_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fills (
+    id INTEGER PRIMARY KEY,
+    session INTEGER NOT NULL,
+    strategy TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    client_id INTEGER NOT NULL,
+    order_id INTEGER NOT NULL DEFAULT 0,
+    stamp INTEGER NOT NULL,
+    direction TEXT NOT NULL,
+    offset TEXT NOT NULL,
+    order_type TEXT NOT NULL,
+    price REAL NOT NULL,
+    lots INTEGER NOT NULL,
+    recorded_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS orders_by_strategy ON orders (strategy, stamp);
+CREATE INDEX IF NOT EXISTS fills_by_strategy ON fills (strategy, stamp);
+";
+
+/// What the engine hands the store's thread.
+#[derive(Debug, Clone, Copy)]
+pub enum StoreRecord {
+    /// an order as sent
+    Order(Order),
+    /// an order as filled: the fill's price, lots and stamp
+    Fill(Order),
+}
+
+/// A row of the `orders` or `fills` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredOrder {
+    pub session: i64,
+    pub strategy: String,
+    pub symbol: String,
+    pub client_id: u64,
+    /// 0 in rows written before engine order ids were recorded
+    pub order_id: u64,
+    pub stamp: i64,
+    pub direction: String,
+    pub offset: String,
+    pub order_type: String,
+    pub price: f64,
+    pub lots: u32,
+}
+
+pub struct OrderStore {
+    conn: Connection,
+    session: i64,
+}
+
+impl OrderStore {
+    /// Open or create the database at `path`; rows written through this store are tagged
+    /// with `session`, e.g. the engine's start stamp.
+    pub fn open<P: AsRef<Path>>(path: P, session: i64) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        // readers (reports, ad-hoc queries) do not block the writer
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        // stores created before engine order ids were recorded
+        for table in ["orders", "fills"] {
+            let sql = format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = 'order_id'", table);
+            if !conn.prepare(&sql)?.exists([])? {
+                conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN order_id INTEGER NOT NULL DEFAULT 0", table))?;
+            }
+        }
+        Ok(Self { conn, session })
+    }
+
+    pub fn session(&self) -> i64 {
+        self.session
+    }
+
+    /// Write `record`, stamped `recorded_at`.
+    pub fn record(&self, record: &StoreRecord, recorded_at: i64) -> rusqlite::Result<()> {
+        let (table, o) = match record {
+            StoreRecord::Order(order) => ("orders", order),
+            StoreRecord::Fill(fill) => ("fills", fill),
+        };
+        let sql = format!(
+            "INSERT INTO {} (session, strategy, symbol, client_id, order_id, stamp, direction, offset, order_type, price, lots, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            table
+        );
+        self.conn.prepare_cached(&sql)?.execute(params![
+            self.session,
+            o.stg_name.as_str(),
+            o.symbol.as_str(),
+            o.client_id as i64,
+            o.order_id as i64,
+            o.timestamp,
+            format!("{:?}", o.direction),
+            format!("{:?}", o.offset),
+            format!("{:?}", o.order_type),
+            o.price,
+            o.lots,
+            recorded_at,
+        ])?;
+        Ok(())
+    }
+
+    /// Orders of `strategy` (or all strategies) sent at or after `since`, across sessions,
+    /// oldest first.
+    pub fn orders(&self, strategy: Option<&str>, since: i64) -> rusqlite::Result<Vec<StoredOrder>> {
+        self.select("orders", strategy, since)
+    }
+
+    /// Fills of `strategy` (or all strategies) at or after `since`, across sessions, oldest first.
+    pub fn fills(&self, strategy: Option<&str>, since: i64) -> rusqlite::Result<Vec<StoredOrder>> {
+        self.select("fills", strategy, since)
+    }
+
+    fn select(&self, table: &str, strategy: Option<&str>, since: i64) -> rusqlite::Result<Vec<StoredOrder>> {
+        let sql = format!(
+            "SELECT session, strategy, symbol, client_id, order_id, stamp, direction, offset, order_type, price, lots FROM {}
+             WHERE (?1 IS NULL OR strategy = ?1) AND stamp >= ?2 ORDER BY stamp, id",
+            table
+        );
+        let mut statement = self.conn.prepare_cached(&sql)?;
+        let rows = statement.query_map(params![strategy, since], |row| {
+            Ok(StoredOrder {
+                session: row.get(0)?,
+                strategy: row.get(1)?,
+                symbol: row.get(2)?,
+                client_id: row.get::<_, i64>(3)? as u64,
+                order_id: row.get::<_, i64>(4)? as u64,
+                stamp: row.get(5)?,
+                direction: row.get(6)?,
+                offset: row.get(7)?,
+                order_type: row.get(8)?,
+                price: row.get(9)?,
+                lots: row.get(10)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DirectionType, NameType, OffsetFlagType, OrderType, SymbolType};
+
+    #[test]
+    fn keeps_history_across_sessions() {
+        let path = std::env::temp_dir().join(format!("orders-{}.db", std::process::id()));
+        let order = Order {
+            stg_name: NameType::from("aberration_20"),
+            symbol: SymbolType::from("rb2505"),
+            timestamp: 1000,
+            price: 3500.0,
+            lots: 2,
+            direction: DirectionType::BUY,
+            offset: OffsetFlagType::OPEN,
+            order_type: OrderType::LIMIT,
+            client_id: 1,
+            order_id: 42,
+        };
+        {
+            let store = OrderStore::open(&path, 1).unwrap();
+            store.record(&StoreRecord::Order(order), 1000).unwrap();
+            store.record(&StoreRecord::Fill(Order { price: 3499.0, ..order }), 1001).unwrap();
+        }
+        let store = OrderStore::open(&path, 2).unwrap();
+        let close = Order {
+            timestamp: 2000,
+            direction: DirectionType::SELL,
+            offset: OffsetFlagType::CLOSE,
+            client_id: 2,
+            ..order
+        };
+        store.record(&StoreRecord::Order(close), 2000).unwrap();
+
+        let orders = store.orders(Some("aberration_20"), 0).unwrap();
+        assert_eq!(orders.iter().map(|o| (o.session, o.client_id)).collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+        assert_eq!((orders[1].direction.as_str(), orders[1].offset.as_str()), ("SELL", "CLOSE"));
+        let fills = store.fills(None, 0).unwrap();
+        assert_eq!((fills.len(), fills[0].price, fills[0].lots, fills[0].order_id), (1, 3499.0, 2, 42));
+        assert!(store.orders(Some("other"), 0).unwrap().is_empty());
+        assert_eq!(store.orders(None, 1500).unwrap().len(), 1);
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn adds_order_ids_to_older_stores() {
+        let path = std::env::temp_dir().join(format!("orders-old-{}.db", std::process::id()));
+        {
+            let conn = Connection::open(&path).unwrap();
+            let old = SCHEMA.replace("    order_id INTEGER NOT NULL DEFAULT 0,\n", "");
+            conn.execute_batch(&old).unwrap();
+            conn.execute(
+                "INSERT INTO fills (session, strategy, symbol, client_id, stamp, direction, offset, order_type, price, lots, recorded_at)
+                 VALUES (1, 'a', 'rb2505', 1, 1000, 'BUY', 'OPEN', 'LIMIT', 3500.0, 1, 1000)",
+                [],
+            )
+            .unwrap();
+        }
+        let store = OrderStore::open(&path, 2).unwrap();
+        assert_eq!(store.fills(None, 0).unwrap()[0].order_id, 0);
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
@@ -1,12 +1,21 @@
 use crate::operator::rolling;
-use crate::strategy::Strategy;
-use crate::types::{DirectionType, NameType, OffsetFlagType, Order, TickData};
+use crate::sizing::{FixedLots, Sizer, SizingContext};
+use crate::stops::StopLevels;
+use crate::strategy::{Orders, Strategy, Toggles};
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, TickData};
 
 pub struct Aberration {
     ma: rolling::Mean,
     stdev: rolling::StDev,
     name: NameType,
+    /// signed lots: > 0 long, < 0 short
     position: i32,
+    sizer: Box<dyn Sizer>,
+    account: SizingContext,
+    toggles: Toggles,
+    /// price distances from entry for engine-side stops; 0 disables
+    stop_loss: f64,
+    take_profit: f64,
 }
 
 impl Aberration {
@@ -17,8 +26,19 @@ impl Aberration {
             stdev: rolling::StDev::new(ma_len),
             name: NameType::from(full_str.as_str()),
             position: 0,
+            sizer: Box::new(FixedLots(1)),
+            account: SizingContext::default(),
+            toggles: Toggles::new(&[("allow_longs", true), ("allow_shorts", true)]),
+            stop_loss: 0.0,
+            take_profit: 0.0,
         }
     }
+
+    /// Size entries with `sizer` instead of one lot.
+    pub fn with_sizer(mut self, sizer: Box<dyn Sizer>) -> Self {
+        self.sizer = sizer;
+        self
+    }
 }
 
 impl Strategy for Aberration {
@@ -28,22 +48,28 @@ impl Strategy for Aberration {
         self.name
     }
 
-    fn update(&mut self, tick: &TickData) -> Option<Order> {
+    fn update(&mut self, tick: &TickData) -> Orders {
         let ma = self.ma.update(tick.last);
         let stdev = self.stdev.update(tick.last);
+        self.sizer.on_tick(tick);
+        let held = self.position.unsigned_abs();
+        let mut orders = Orders::new();
 
         // 先平仓, 再开仓
         if self.position > 0 {
             if tick.last < ma {
                 self.position = 0;
-                return Some(Order {
+                orders.push(Order {
                     stg_name: self.name(),
                     symbol: tick.symbol,
                     timestamp: tick.stamp,
                     price: tick.bp1, // 买一价成交
-                    lots: 1,
+                    lots: held,
                     direction: DirectionType::SELL,
                     offset: OffsetFlagType::CLOSE,
+                    order_type: OrderType::LIMIT,
+                    client_id: 0,
+                    order_id: 0,
                 });
             }
         }
@@ -51,47 +77,180 @@ impl Strategy for Aberration {
         if self.position < 0 {
             if tick.last > ma {
                 self.position = 0;
-                return Some(Order {
+                orders.push(Order {
                     stg_name: self.name(),
                     symbol: tick.symbol,
                     timestamp: tick.stamp,
                     price: tick.ap1, // 卖一价成交
-                    lots: 1,
+                    lots: held,
                     direction: DirectionType::BUY,
                     offset: OffsetFlagType::CLOSE,
+                    order_type: OrderType::LIMIT,
+                    client_id: 0,
+                    order_id: 0,
                 });
             }
         }
 
         if self.position == 0 {
-            if tick.last > ma + 2.0 * stdev {
-                self.position = 1;
-                return Some(Order {
+            let long_entry = tick.last > ma + 2.0 * stdev && self.toggles.get("allow_longs");
+            let short_entry = tick.last < ma - 2.0 * stdev && self.toggles.get("allow_shorts");
+            let entry = long_entry || short_entry;
+            let lots = if entry {
+                self.sizer.lots(tick.last, &self.account).min(i32::MAX as u32)
+            } else {
+                0
+            };
+            if lots == 0 {
+                return orders;
+            }
+
+            if long_entry {
+                self.position = lots as i32;
+                orders.push(Order {
                     stg_name: self.name(),
                     symbol: tick.symbol,
                     timestamp: tick.stamp,
                     price: tick.ap1, // 卖一价成交
-                    lots: 1,
+                    lots,
                     direction: DirectionType::BUY,
                     offset: OffsetFlagType::OPEN,
+                    order_type: OrderType::LIMIT,
+                    client_id: 0,
+                    order_id: 0,
                 });
             }
 
-            if tick.last < ma - 2.0 * stdev {
-                self.position = -1;
-                return Some(Order {
+            if short_entry {
+                self.position = -(lots as i32);
+                orders.push(Order {
                     stg_name: self.name(),
                     symbol: tick.symbol,
                     timestamp: tick.stamp,
                     price: tick.bp1, // 买一价成交
-                    lots: 1,
+                    lots,
                     direction: DirectionType::SELL,
                     offset: OffsetFlagType::OPEN,
+                    order_type: OrderType::LIMIT,
+                    client_id: 0,
+                    order_id: 0,
                 });
             }
         }
 
-        return None;
-        // do some strategy to generate order
+        // a close above leaves the position flat, so the entry may reverse it on the same tick
+        orders
+    }
+
+    fn on_account(&mut self, ctx: &SizingContext) {
+        self.account = *ctx;
+    }
+
+    fn stop_levels(&self, entry: &Order) -> StopLevels {
+        StopLevels::around(entry, self.stop_loss, self.take_profit)
+    }
+
+    fn on_stop_triggered(&mut self, _order: &Order) {
+        self.position = 0;
+    }
+
+    fn on_resume(&mut self, long_lots: u32, short_lots: u32) {
+        self.position = long_lots as i32 - short_lots as i32;
+    }
+
+    fn on_toggle(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        self.toggles.set(name, enabled)
+    }
+
+    fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "ma_len" => {
+                let len = rolling::window_len(name, value, 2)?;
+                // keep the most recent prices instead of warming up from scratch
+                self.ma.resize(len);
+                self.stdev.resize(len);
+                Ok(())
+            }
+            // takes effect from the next entry
+            "stop_loss" if value >= 0.0 => {
+                self.stop_loss = value;
+                Ok(())
+            }
+            "take_profit" if value >= 0.0 => {
+                self.take_profit = value;
+                Ok(())
+            }
+            "stop_loss" | "take_profit" => Err(format!("{} must be >= 0, got {}", name, value)),
+            _ => Err(format!("unknown parameter {:?}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{StrategyHarness, check, random_walk, ticks};
+
+    fn walk(rng: &mut crate::testing::Rng) -> Vec<TickData> {
+        let prices = random_walk(rng, 500, 3500.0, 1.0, 3);
+        ticks("rb2505", &prices, 0, 500, 1.0)
+    }
+
+    #[test]
+    fn only_flips_between_flat_and_one_side() {
+        check(50, |rng| {
+            let ma_len = 5 + rng.below(30) as usize;
+            let mut harness = StrategyHarness::new(Aberration::new(ma_len));
+            let ticks = walk(rng);
+            harness.run(&ticks);
+            for t in harness.transitions() {
+                // entries only from flat, exits only to flat, never straight through
+                assert!(t.before == 0 || t.after == 0, "{:?}", t);
+                assert_eq!((t.before - t.after).abs(), 1);
+                let tick = ticks.iter().find(|tick| tick.stamp == t.stamp).unwrap();
+                let expected = match t.order.direction {
+                    DirectionType::BUY => tick.ap1,
+                    DirectionType::SELL => tick.bp1,
+                };
+                assert_eq!((t.order.symbol, t.order.timestamp, t.order.price), (tick.symbol, tick.stamp, expected));
+            }
+        });
+    }
+
+    #[test]
+    fn replays_the_same_orders_and_respects_toggles() {
+        check(20, |rng| {
+            let ticks = walk(rng);
+            let run = |allow_shorts: bool| {
+                let mut aberration = Aberration::new(10);
+                aberration.on_toggle("allow_shorts", allow_shorts).unwrap();
+                let mut harness = StrategyHarness::new(aberration);
+                harness.run(&ticks);
+                harness.transitions().iter().map(|t| t.after).collect::<Vec<_>>()
+            };
+            assert_eq!(run(true), run(true));
+            assert!(run(false).iter().all(|&net| net >= 0));
+        });
+    }
+
+    #[test]
+    fn reverses_on_a_single_tick() {
+        let mut prices = vec![100.0; 9];
+        prices.extend([120.0, 60.0]);
+        let mut harness = StrategyHarness::new(Aberration::new(10));
+        harness.run(&ticks("rb2505", &prices, 0, 500, 1.0));
+        // the crash closes the long and opens the short in the same update
+        harness.assert_positions(&[1, 0, -1]);
+        let stamps: Vec<i64> = harness.transitions().iter().map(|t| t.stamp).collect();
+        assert_eq!(stamps, [4500, 5000, 5000]);
+    }
+
+    #[test]
+    fn refuses_window_lengths_it_cannot_allocate() {
+        let mut aberration = Aberration::new(10);
+        for bad in [1.0, 2.5, f64::INFINITY, f64::NAN, 1e12] {
+            assert!(aberration.on_param_update("ma_len", bad).is_err(), "{}", bad);
+        }
+        assert!(aberration.on_param_update("ma_len", 20.0).is_ok());
     }
 }
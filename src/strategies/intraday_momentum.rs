@@ -0,0 +1,163 @@
+//! Intraday momentum: follow the day's move once it has gone `threshold_bp` basis points from
+//! the day's first price, at most once a day, and be flat again by the end of every session.
+//! Entries are kept out of the first and last minutes of each session with a `TimeFilter`,
+//! and a position still open when the filter closes is flattened there.
+use crate::calendar::Session;
+use crate::strategy::{Orders, Strategy};
+use crate::time_filter::TimeFilter;
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, TickData};
+
+pub struct IntradayMomentum {
+    name: NameType,
+    threshold_bp: f64,
+    filter: TimeFilter,
+    /// the day's first price
+    open: f64,
+    /// set once the day's one trade has been entered
+    traded_today: bool,
+    /// signed lots: > 0 long, < 0 short
+    position: i32,
+    lots: u32,
+}
+
+impl IntradayMomentum {
+    /// Keeps out of the first and last 5 minutes of every session.
+    pub fn new(threshold_bp: u32) -> Self {
+        Self {
+            name: NameType::from(format!("IntradayMomentum{}", threshold_bp).as_str()),
+            threshold_bp: threshold_bp as f64,
+            filter: TimeFilter::new().skip_session_edges(300, 300),
+            open: f64::NAN,
+            traded_today: false,
+            position: 0,
+            lots: 1,
+        }
+    }
+
+    /// Trade only where `filter` allows instead of the default session edges.
+    pub fn with_filter(mut self, filter: TimeFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    fn order(&self, tick: &TickData, direction: DirectionType, offset: OffsetFlagType, lots: u32) -> Order {
+        Order {
+            stg_name: self.name,
+            symbol: tick.symbol,
+            timestamp: tick.stamp,
+            price: match direction {
+                DirectionType::BUY => tick.ap1,
+                DirectionType::SELL => tick.bp1,
+            },
+            lots,
+            direction,
+            offset,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        }
+    }
+
+    fn close(&mut self, tick: &TickData) -> Order {
+        let direction = if self.position > 0 { DirectionType::SELL } else { DirectionType::BUY };
+        let order = self.order(tick, direction, OffsetFlagType::CLOSE, self.position.unsigned_abs());
+        self.position = 0;
+        order
+    }
+}
+
+impl Strategy for IntradayMomentum {
+    fn name(&self) -> NameType {
+        self.name
+    }
+
+    fn update(&mut self, tick: &TickData) -> Orders {
+        if self.open.is_nan() {
+            self.open = tick.last;
+        }
+        let mut orders = Orders::new();
+        let allowed = self.filter.allows(tick.stamp);
+        let move_bp = (tick.last / self.open - 1.0) * 10_000.0;
+        // out by the session's end, or once the move is given back through the open
+        if self.position != 0 && (!allowed || move_bp * self.position as f64 <= 0.0) {
+            orders.push(self.close(tick));
+            return orders;
+        }
+        if self.position == 0 && allowed && !self.traded_today && move_bp.abs() >= self.threshold_bp {
+            let direction = if move_bp > 0.0 { DirectionType::BUY } else { DirectionType::SELL };
+            self.position = if move_bp > 0.0 { self.lots as i32 } else { -(self.lots as i32) };
+            self.traded_today = true;
+            orders.push(self.order(tick, direction, OffsetFlagType::OPEN, self.lots));
+        }
+        orders
+    }
+
+    fn on_day_open(&mut self) {
+        self.open = f64::NAN;
+        self.traded_today = false;
+    }
+
+    fn on_session_start(&mut self, session: &Session) {
+        self.filter.on_session_start(session);
+    }
+
+    fn on_session_end(&mut self, session: &Session) {
+        self.filter.on_session_end(session);
+    }
+
+    fn on_stop_triggered(&mut self, _order: &Order) {
+        self.position = 0;
+    }
+
+    fn on_resume(&mut self, long_lots: u32, short_lots: u32) {
+        self.position = long_lots as i32 - short_lots as i32;
+    }
+
+    fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "threshold_bp" if value > 0.0 => {
+                self.threshold_bp = value;
+                Ok(())
+            }
+            "lots" if value >= 1.0 => {
+                self.lots = value as u32;
+                Ok(())
+            }
+            // minutes kept clear at either end of a session
+            "skip_minutes" if value >= 0.0 => {
+                let secs = (value * 60.0) as u32;
+                self.filter.set_session_edges(secs, secs);
+                Ok(())
+            }
+            "threshold_bp" | "lots" | "skip_minutes" => Err(format!("invalid {} {}", name, value)),
+            _ => Err(format!("unknown parameter {:?}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{StrategyHarness, ticks};
+    use crate::timeutil;
+
+    #[test]
+    fn enters_after_the_open_and_is_flat_before_the_close() {
+        let morning = Session {
+            start: 9 * 3600,
+            end: 10 * 3600 + 15 * 60,
+        };
+        // a tick a minute from 09:00; the day runs up 1% in the first 3 minutes and holds there
+        let prices: Vec<f64> = (0..75).map(|i| if i < 3 { 3500.0 + i as f64 * 20.0 } else { 3540.0 }).collect();
+        let ticks = ticks("rb2505", &prices, timeutil::local_stamp(20250313, 9 * 3600), 60_000, 1.0);
+        let mut harness = StrategyHarness::new(IntradayMomentum::new(50));
+        harness.strategy_mut().on_day_open();
+        harness.strategy_mut().on_session_start(&morning);
+        harness.run(&ticks);
+
+        // in at 09:05 once the opening minutes pass, out at 10:10 as the closing ones begin
+        harness.assert_positions(&[1, 0]);
+        let stamps: Vec<i64> = harness.transitions().iter().map(|t| t.stamp).collect();
+        assert_eq!(stamps, [ticks[5].stamp, ticks[70].stamp]);
+    }
+}
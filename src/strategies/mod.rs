@@ -1,3 +1,83 @@
 pub mod aberration;
+pub mod intraday_momentum;
+pub mod pair_spread;
 
-pub use aberration::Aberration;
\ No newline at end of file
+pub use aberration::Aberration;
+pub use intraday_momentum::IntradayMomentum;
+pub use pair_spread::{Leg, PairConfig, PairSpread};
+
+use crate::operator::rolling;
+use crate::sizing;
+use crate::strategy::Strategy;
+use crate::time_filter::TimeFilter;
+use crate::types::SymbolType;
+
+/// Build a strategy by kind name from textual arguments, as sent over the control socket.
+pub fn create(kind: &str, args: &[String]) -> Result<Box<dyn Strategy>, String> {
+    match kind {
+        "Aberration" => {
+            let (ma_len, sizer) = match args {
+                [ma_len] => (ma_len, None),
+                [ma_len, sizer] => (ma_len, Some(sizing::from_spec(sizer)?)),
+                _ => return Err("usage: Aberration <ma_len> [sizer]".into()),
+            };
+            let ma_len: usize = ma_len.parse().map_err(|_| format!("invalid ma_len {:?}", ma_len))?;
+            // as `on_param_update` checks it: a shorter window has no deviation to band by
+            if !(2..=rolling::MAX_WINDOW).contains(&ma_len) {
+                return Err(format!("ma_len must be from 2 to {}, got {}", rolling::MAX_WINDOW, ma_len));
+            }
+            let strategy = Aberration::new(ma_len);
+            Ok(Box::new(match sizer {
+                Some(sizer) => strategy.with_sizer(sizer),
+                None => strategy,
+            }))
+        }
+        "IntradayMomentum" => {
+            let (threshold_bp, skip_minutes) = match args {
+                [threshold_bp] => (threshold_bp, None),
+                [threshold_bp, skip_minutes] => (threshold_bp, Some(skip_minutes)),
+                _ => return Err("usage: IntradayMomentum <threshold_bp> [skip_minutes]".into()),
+            };
+            let threshold_bp = threshold_bp.parse().map_err(|_| format!("invalid threshold_bp {:?}", threshold_bp))?;
+            let strategy = IntradayMomentum::new(threshold_bp);
+            Ok(Box::new(match skip_minutes {
+                Some(minutes) => {
+                    let minutes: u32 = minutes.parse().map_err(|_| format!("invalid skip_minutes {:?}", minutes))?;
+                    strategy.with_filter(TimeFilter::new().skip_session_edges(minutes * 60, minutes * 60))
+                }
+                None => strategy,
+            }))
+        }
+        "PairSpread" => {
+            let [leg, y, x, hedge_window] = args else {
+                return Err("usage: PairSpread <y|x> <y_symbol> <x_symbol> <hedge_window>".into());
+            };
+            let leg = match leg.as_str() {
+                "y" => Leg::Y,
+                "x" => Leg::X,
+                _ => return Err(format!("leg must be y or x, got {:?}", leg)),
+            };
+            let hedge_window = hedge_window.parse().map_err(|_| format!("invalid hedge_window {:?}", hedge_window))?;
+            if hedge_window < 2 {
+                return Err(format!("hedge_window must be >= 2, got {}", hedge_window));
+            }
+            let config = PairConfig::new(SymbolType::from(y.as_str()), SymbolType::from(x.as_str()), hedge_window);
+            Ok(Box::new(PairSpread::new(config, leg)))
+        }
+        _ => Err(format!("unknown strategy kind {:?}", kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_windows_too_short_to_trade() {
+        let args = |ma_len: &str| vec![ma_len.to_string()];
+        assert!(create("Aberration", &args("10")).is_ok());
+        for bad in ["0", "1", "1000000"] {
+            assert!(create("Aberration", &args(bad)).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Spread trading between two related contracts, e.g. rb against hc. The hedge ratio is the
+//! rolling least-squares beta of y's price on x's, and the spread is the newest prices'
+//! residual off that fit, `(y - ȳ) - beta * (x - x̄)`: measured from the window's means, a
+//! small change in beta moves it by little instead of by beta times the price. The windows
+//! advance once both legs have printed since the last sample, so every sample pairs two
+//! fresh prices. The spread is
+//! traded when its rolling z-score stretches past `entry_z` while the legs are still
+//! correlated, and unwound once it comes back inside `exit_z`.
+//!
+//! The engine keeps positions per symbol, so the pair runs as two strategies, one per leg
+//! (see `PairSpread::legs`), each trading its own symbol and reading the other's ticks as a
+//! reference. Both legs see the same ticks in the same order and reach the same signal; the
+//! hedge size is fixed when the spread is entered.
+use crate::operator::rolling;
+use crate::strategy::{Orders, Strategy};
+use crate::types::{DirectionType, NameType, OffsetFlagType, Order, OrderType, SymbolType, TickData};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairConfig {
+    /// the leg the spread is long of when it is bought
+    pub y: SymbolType,
+    /// the hedge leg
+    pub x: SymbolType,
+    /// samples the hedge ratio and the correlation are estimated over
+    pub hedge_window: usize,
+    /// samples the spread's z-score is taken over
+    pub z_window: usize,
+    pub entry_z: f64,
+    pub exit_z: f64,
+    /// no new entries while the legs' correlation is below this
+    pub min_corr: f64,
+    /// lots of y per spread; x trades `beta` times as many, rounded
+    pub lots: u32,
+}
+
+impl PairConfig {
+    pub fn new(y: SymbolType, x: SymbolType, hedge_window: usize) -> Self {
+        Self {
+            y,
+            x,
+            hedge_window,
+            z_window: hedge_window,
+            entry_z: 2.0,
+            exit_z: 0.5,
+            min_corr: 0.7,
+            lots: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leg {
+    Y,
+    X,
+}
+
+/// One leg of a pair; build both with `PairSpread::legs` and register each on its symbol.
+pub struct PairSpread {
+    config: PairConfig,
+    name: NameType,
+    leg: Leg,
+    beta: rolling::Beta,
+    corr: rolling::Corr,
+    mean_y: rolling::Mean,
+    mean_x: rolling::Mean,
+    zscore: rolling::ZScore,
+    /// latest prices of y and x
+    prices: (f64, f64),
+    /// whether y and x printed since the last sample
+    fresh: (bool, bool),
+    /// +1 long the spread, -1 short, 0 flat
+    spread: i32,
+    /// lots of x per spread, fixed at entry
+    hedge_lots: u32,
+    /// signed lots of this leg's symbol held
+    held: i64,
+}
+
+impl PairSpread {
+    pub fn new(config: PairConfig, leg: Leg) -> Self {
+        let suffix = match leg {
+            Leg::Y => "Y",
+            Leg::X => "X",
+        };
+        let name = format!("Pair{}_{}/{}", config.y.as_str(), config.x.as_str(), suffix);
+        Self {
+            config,
+            name: NameType::from(name.as_str()),
+            leg,
+            beta: rolling::Beta::new(config.hedge_window),
+            corr: rolling::Corr::new(config.hedge_window),
+            mean_y: rolling::Mean::new(config.hedge_window).with_nan_policy(rolling::NanPolicy::Propagate),
+            mean_x: rolling::Mean::new(config.hedge_window).with_nan_policy(rolling::NanPolicy::Propagate),
+            zscore: rolling::ZScore::new(config.z_window),
+            prices: (f64::NAN, f64::NAN),
+            fresh: (false, false),
+            spread: 0,
+            hedge_lots: 0,
+            held: 0,
+        }
+    }
+
+    /// The y and x legs of the pair.
+    pub fn legs(config: PairConfig) -> (Self, Self) {
+        (Self::new(config, Leg::Y), Self::new(config, Leg::X))
+    }
+
+    pub fn symbol(&self) -> SymbolType {
+        match self.leg {
+            Leg::Y => self.config.y,
+            Leg::X => self.config.x,
+        }
+    }
+
+    /// Feed a price of either leg and move the spread position the signal calls for.
+    fn on_price(&mut self, symbol: SymbolType, price: f64) {
+        if symbol == self.config.y {
+            self.prices.0 = price;
+            self.fresh.0 = true;
+        } else if symbol == self.config.x {
+            self.prices.1 = price;
+            self.fresh.1 = true;
+        } else {
+            return;
+        }
+        // one sample per pair of fresh prices: a price paired with the other leg's stale one
+        // shows the whole move since as spread
+        if self.fresh != (true, true) {
+            return;
+        }
+        self.fresh = (false, false);
+        let (y, x) = self.prices;
+        if !(y.is_finite() && x.is_finite()) {
+            return;
+        }
+        let beta = self.beta.update(x, y);
+        let corr = self.corr.update(x, y);
+        let (mean_y, mean_x) = (self.mean_y.update(y), self.mean_x.update(x));
+        let z = self.zscore.update((y - mean_y) - beta * (x - mean_x));
+        if z.is_nan() {
+            return;
+        }
+        let c = &self.config;
+        self.spread = match self.spread {
+            0 if corr >= c.min_corr && beta > 0.0 && z.abs() >= c.entry_z => {
+                self.hedge_lots = ((beta * c.lots as f64).round() as u32).max(1);
+                if z > 0.0 { -1 } else { 1 }
+            }
+            1 if z >= -c.exit_z => 0,
+            -1 if z <= c.exit_z => 0,
+            held => held,
+        };
+    }
+
+    /// Signed lots this leg should hold for the current spread position.
+    fn target(&self) -> i64 {
+        match self.leg {
+            Leg::Y => self.spread as i64 * self.config.lots as i64,
+            Leg::X => -(self.spread as i64) * self.hedge_lots as i64,
+        }
+    }
+
+    fn order(&self, tick: &TickData, direction: DirectionType, offset: OffsetFlagType, lots: u64) -> Order {
+        Order {
+            stg_name: self.name,
+            symbol: tick.symbol,
+            timestamp: tick.stamp,
+            price: match direction {
+                DirectionType::BUY => tick.ap1,
+                DirectionType::SELL => tick.bp1,
+            },
+            lots: lots as u32,
+            direction,
+            offset,
+            order_type: OrderType::LIMIT,
+            client_id: 0,
+            order_id: 0,
+        }
+    }
+}
+
+impl Strategy for PairSpread {
+    fn name(&self) -> NameType {
+        self.name
+    }
+
+    fn update(&mut self, tick: &TickData) -> Orders {
+        self.on_price(tick.symbol, tick.last);
+        let (held, target) = (self.held, self.target());
+        let mut orders = Orders::new();
+        if held == target {
+            return orders;
+        }
+        // close what is held on the wrong side or beyond the target, then open the rest
+        if held > 0 && target < held {
+            orders.push(self.order(tick, DirectionType::SELL, OffsetFlagType::CLOSE, (held - target.max(0)) as u64));
+        } else if held < 0 && target > held {
+            orders.push(self.order(tick, DirectionType::BUY, OffsetFlagType::CLOSE, (target.min(0) - held) as u64));
+        }
+        if target > 0 && target > held.max(0) {
+            orders.push(self.order(tick, DirectionType::BUY, OffsetFlagType::OPEN, (target - held.max(0)) as u64));
+        } else if target < 0 && target < held.min(0) {
+            orders.push(self.order(tick, DirectionType::SELL, OffsetFlagType::OPEN, (held.min(0) - target) as u64));
+        }
+        self.held = target;
+        orders
+    }
+
+    fn references(&self) -> Vec<SymbolType> {
+        let other = match self.leg {
+            Leg::Y => self.config.x,
+            Leg::X => self.config.y,
+        };
+        vec![other]
+    }
+
+    fn on_reference_tick(&mut self, tick: &TickData) {
+        self.on_price(tick.symbol, tick.last);
+    }
+
+    fn on_stop_triggered(&mut self, _order: &Order) {
+        self.held = 0;
+    }
+
+    fn on_resume(&mut self, long_lots: u32, short_lots: u32) {
+        self.held = long_lots as i64 - short_lots as i64;
+    }
+
+    fn on_param_update(&mut self, name: &str, value: f64) -> Result<(), String> {
+        match name {
+            "hedge_window" => {
+                let len = rolling::window_len(name, value, 2)?;
+                self.beta.resize(len);
+                self.corr.resize(len);
+                self.mean_y.resize(len);
+                self.mean_x.resize(len);
+                self.config.hedge_window = len;
+                Ok(())
+            }
+            "z_window" => {
+                let len = rolling::window_len(name, value, 2)?;
+                self.zscore.resize(len);
+                self.config.z_window = len;
+                Ok(())
+            }
+            "entry_z" if value > self.config.exit_z => {
+                self.config.entry_z = value;
+                Ok(())
+            }
+            "exit_z" if value >= 0.0 && value < self.config.entry_z => {
+                self.config.exit_z = value;
+                Ok(())
+            }
+            "entry_z" | "exit_z" => Err(format!("{} must keep 0 <= exit_z < entry_z, got {}", name, value)),
+            _ => Err(format!("unknown parameter {:?}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{StrategyHarness, ticks};
+
+    #[test]
+    fn legs_enter_and_unwind_the_spread_together() {
+        let (y, x) = (SymbolType::from("rb2505"), SymbolType::from("hc2505"));
+        let config = PairConfig {
+            z_window: 20,
+            entry_z: 2.0,
+            exit_z: 0.5,
+            ..PairConfig::new(y, x, 40)
+        };
+        // hc swings, rb follows at twice its moves give or take 0.5; then rb jumps alone and falls back
+        let hc: Vec<f64> = (0..100).map(|i| 3400.0 + (20.0 * (i as f64 * 0.15).sin()).round()).collect();
+        let mut rb: Vec<f64> = hc
+            .iter()
+            .enumerate()
+            .map(|(i, p)| 2.0 * p - 3300.0 + (i * 7 % 5) as f64 * 0.25 - 0.5)
+            .collect();
+        rb[70] += 30.0;
+        let (hc_ticks, rb_ticks) = (ticks("hc2505", &hc, 0, 500, 1.0), ticks("rb2505", &rb, 250, 500, 1.0));
+
+        let (y_leg, x_leg) = PairSpread::legs(config);
+        assert_eq!(y_leg.references(), vec![x]);
+        let (mut y_leg, mut x_leg) = (StrategyHarness::new(y_leg), StrategyHarness::new(x_leg));
+        for (h, r) in hc_ticks.iter().zip(&rb_ticks) {
+            y_leg.feed_reference(h);
+            x_leg.feed(h);
+            y_leg.feed(r);
+            x_leg.feed_reference(r);
+        }
+        // rb rich: short the spread, selling rb against two hc per lot, then unwind
+        y_leg.assert_positions(&[-1, 0]);
+        x_leg.assert_positions(&[2, 0]);
+        assert_eq!(y_leg.transitions()[0].stamp, rb_ticks[70].stamp);
+    }
+
+    #[test]
+    fn refuses_window_lengths_it_cannot_allocate() {
+        let (mut y_leg, _) = PairSpread::legs(PairConfig::new(SymbolType::from("rb2505"), SymbolType::from("hc2505"), 40));
+        for name in ["hedge_window", "z_window"] {
+            for bad in [1.0, 2.5, f64::INFINITY, f64::NAN, 1e12] {
+                assert!(y_leg.on_param_update(name, bad).is_err(), "{} {}", name, bad);
+            }
+            assert!(y_leg.on_param_update(name, 30.0).is_ok());
+        }
+    }
+}
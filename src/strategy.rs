@@ -1,10 +1,185 @@
-use crate::types::{NameType, Order, TickData};
+use crate::bars::BarStore;
+use crate::calendar::Session;
+use crate::clock::SharedClock;
+use crate::conduct::ConductWarning;
+use crate::sizing::SizingContext;
+use crate::stops::StopLevels;
+use crate::tracking::Benchmark;
+use crate::types::{LimitLock, NameType, Order, SymbolType, TickData};
+use smallvec::SmallVec;
+pub use smallvec::smallvec;
 
-/// The Strategy trait. Every strategy must implement `name()` and `update(&TickData)` → `Order`.
+/// The orders one `update` sends, in the order they go out: reversing a position is a close
+/// followed by an open. Two fit without allocating.
+pub type Orders = SmallVec<[Order; 2]>;
+
+/// What a strategy is told when the engine starts it, in `Strategy::on_start`.
+pub struct EngineContext<'a> {
+    /// the symbol the strategy trades
+    pub symbol: SymbolType,
+    /// the worker running it
+    pub worker: usize,
+    /// the worker clock's time at start
+    pub now: i64,
+    /// recorded bars to warm up from, when the engine was given a bar directory
+    pub history: Option<&'a BarStore>,
+}
+
+/// The Strategy trait. Every strategy must implement `name()` and either `update(&TickData)`
+/// → `Orders` or, to trade by target position, `target_position(&TickData)`.
 pub trait Strategy: Send {
     /// Return the strategy’s name (as a NameType).
     fn name(&self) -> NameType;
 
-    /// Given a TickData, produce a new Order.
-    fn update(&mut self, tick: &TickData) -> Option<Order>;
+    /// Given a TickData, produce the orders to send, if any. The engine sends them in turn
+    /// and drops the rest once one is refused, so an open never goes out without the close
+    /// before it. Not called while `target_position` gives a target.
+    fn update(&mut self, _tick: &TickData) -> Orders {
+        Orders::new()
+    }
+
+    /// Given a TickData, the signed lots to hold (> 0 long, < 0 short); the engine's
+    /// `PositionManager` sends the orders to get there from the actual position. `None`, the
+    /// default, leaves the orders to `update`.
+    fn target_position(&mut self, _tick: &TickData) -> Option<i32> {
+        None
+    }
+
+    /// Apply a parameter pushed at runtime (e.g. via the control socket).
+    /// Window-length changes should `resize` indicators so their warm state is kept.
+    fn on_param_update(&mut self, name: &str, _value: f64) -> Result<(), String> {
+        Err(format!("unknown parameter {:?}", name))
+    }
+
+    /// Latest account state, pushed by the engine before every `update`; strategies that
+    /// size through a `Sizer` keep it to pass to `Sizer::lots`.
+    fn on_account(&mut self, _ctx: &SizingContext) {}
+
+    /// The strategy is nearing (or, if `warning.blocking`, has hit) an exchange conduct limit
+    /// such as the daily cancel count. Blocked orders are dropped by the engine.
+    fn on_conduct_warning(&mut self, _warning: &ConductWarning) {}
+
+    /// Stop-loss / take-profit for the position `entry` just opened; the engine closes it when
+    /// the last price crosses either level. None by default.
+    fn stop_levels(&self, _entry: &Order) -> StopLevels {
+        StopLevels::default()
+    }
+
+    /// The series this strategy's live returns are tracked against, with its backtest profile;
+    /// drift beyond the profile's thresholds is alerted on. None by default.
+    fn benchmark(&self) -> Option<Benchmark> {
+        None
+    }
+
+    /// Non-traded symbols (spot indices, FX fixings, option underlyings) this strategy reads
+    /// as inputs. The engine subscribes to them and passes their ticks to
+    /// `on_reference_tick`; it keeps no position or risk state for them. None by default.
+    fn references(&self) -> Vec<SymbolType> {
+        Vec::new()
+    }
+
+    /// A tick of one of `references`, delivered before later ticks of the traded symbol.
+    fn on_reference_tick(&mut self, _tick: &TickData) {}
+
+    /// The traded symbol's book locked at a daily price limit, or came off it when `lock` is
+    /// `None`. While locked, orders on the crowded side only queue at the limit and may never
+    /// fill; the engine refuses orders priced beyond it.
+    fn on_limit_lock(&mut self, _lock: Option<LimitLock>) {}
+
+    /// Resting orders older than this many milliseconds (by the engine clock) are cancelled
+    /// by the engine. Never by default.
+    fn order_timeout_ms(&self) -> Option<i64> {
+        None
+    }
+
+    /// Client ids of resting orders to cancel, polled after every `update`.
+    fn take_cancels(&mut self) -> Vec<u64> {
+        Vec::new()
+    }
+
+    /// A cancel for `order` was sent, whether on request or on timeout; `order.lots` is what
+    /// was still unfilled.
+    fn on_order_cancelled(&mut self, _order: &Order) {}
+
+    /// The engine's risk checks refused `order`, e.g. at the strategy's position limits, and
+    /// it was never sent; `reason` says why.
+    fn on_order_rejected(&mut self, _order: &Order, _reason: &str) {}
+
+    /// One of the strategy's orders traded `fill.lots` at `fill.price`; `remaining` lots of
+    /// it are still open, 0 once it is complete. An order may fill in several parts.
+    fn on_fill(&mut self, _fill: &Order, _remaining: u32) {}
+
+    /// The engine closed a position on a stop level with `order`; update internal position state.
+    fn on_stop_triggered(&mut self, _order: &Order) {}
+
+    /// The clock of the worker running this strategy, handed over before its first tick: the
+    /// wall clock live, tick time in a backtest. Read it for time-based logic instead of the
+    /// system time, so the logic replays deterministically.
+    fn on_clock(&mut self, _clock: SharedClock) {}
+
+    /// The engine restored a position from a previous run's handoff file before the first
+    /// tick; strategies that track their own position should adopt it.
+    fn on_resume(&mut self, _long_lots: u32, _short_lots: u32) {}
+
+    /// The engine is about to deliver the strategy's first tick; called on its worker thread,
+    /// after `on_clock` and `on_resume`. Load history from `ctx.history` here to warm up
+    /// indicators instead of waiting for live ticks.
+    fn on_start(&mut self, _ctx: &EngineContext) {}
+
+    /// The strategy is being removed or the engine is shutting down; no more ticks follow.
+    /// Flush any state kept outside the engine's handoff.
+    fn on_stop(&mut self) {}
+
+    /// The first tick of a new trading day has arrived, before it is passed to `update`. A
+    /// night session counts for the next trading day, so this comes with the evening's first
+    /// tick; reset daily counters here.
+    fn on_day_open(&mut self) {}
+
+    /// The trading day is over: its last day session ended (see `calendar`), or, without a
+    /// calendar, the next day's first tick arrived, in which case it precedes `on_day_open`.
+    fn on_day_close(&mut self) {}
+
+    /// The strategy's symbol entered a trading session (see `calendar`), by tick time.
+    fn on_session_start(&mut self, _session: &Session) {}
+
+    /// The strategy's symbol left a trading session; the first tick past its end triggers this.
+    fn on_session_end(&mut self, _session: &Session) {}
+
+    /// Flip a named toggle (see `Toggles`) at runtime, e.g. via the control socket.
+    fn on_toggle(&mut self, name: &str, _enabled: bool) -> Result<(), String> {
+        Err(format!("unknown toggle {:?}", name))
+    }
+}
+
+/// Named on/off switches a strategy declares up front, e.g. `allow_shorts`.
+/// Strategies embed one and forward `on_toggle` to `set`.
+#[derive(Debug, Clone, Default)]
+pub struct Toggles {
+    flags: Vec<(&'static str, bool)>,
+}
+
+impl Toggles {
+    /// Declare toggles with their initial state.
+    pub fn new(flags: &[(&'static str, bool)]) -> Self {
+        Self { flags: flags.to_vec() }
+    }
+
+    /// State of a declared toggle; undeclared names read as off.
+    pub fn get(&self, name: &str) -> bool {
+        self.flags.iter().any(|&(n, enabled)| n == name && enabled)
+    }
+
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), String> {
+        match self.flags.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, flag)) => {
+                *flag = enabled;
+                Ok(())
+            }
+            None => Err(format!("unknown toggle {:?}", name)),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, bool)> + '_ {
+        self.flags.iter().copied()
+    }
 }
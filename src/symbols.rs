@@ -0,0 +1,124 @@
+//! Resolving contract symbols to their fees-table entries. Concrete contracts such as `rb2505`
+//! trade under their product's entry (`SHFE.rb`), found automatically from the product root,
+//! the symbol's leading letters, matched against the entries' product parts. Prefix rules
+//! from the config settle anything else, the longest matching prefix winning.
+//!
+//! A dominant-contract alias, the root followed by `dominant_suffix` (`rb888`), stands for the
+//! product's current dominant contract as set in `dominant`, so a config can name the alias
+//! and trade whichever contract is dominant.
+use crate::types::SymbolType;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SymbolMap {
+    /// symbol prefix → fees table entry, e.g. `ru = "SHFE.ru"`
+    pub rules: BTreeMap<String, String>,
+    /// product root → its dominant contract, e.g. `rb = "rb2505"`
+    pub dominant: BTreeMap<String, String>,
+    pub dominant_suffix: String,
+    /// product root → fees table entry, from the entries' names
+    #[serde(skip)]
+    roots: HashMap<String, String>,
+}
+
+impl Default for SymbolMap {
+    fn default() -> Self {
+        Self {
+            rules: BTreeMap::new(),
+            dominant: BTreeMap::new(),
+            dominant_suffix: "888".into(),
+            roots: HashMap::new(),
+        }
+    }
+}
+
+/// The product root of `symbol`: its leading letters, `rb` of `rb2505`.
+pub fn root(symbol: &str) -> &str {
+    let end = symbol.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(symbol.len());
+    &symbol[..end]
+}
+
+impl SymbolMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, prefix: &str, contract: &str) -> Self {
+        self.rules.insert(prefix.into(), contract.into());
+        self
+    }
+
+    /// Learn the product roots of fees table entries named `<exchange>.<root>`.
+    pub fn index_contracts<'a>(&mut self, contracts: impl IntoIterator<Item = &'a String>) {
+        self.roots.clear();
+        for contract in contracts {
+            if let Some((_, product)) = contract.split_once('.') {
+                self.roots.insert(product.to_string(), contract.clone());
+            }
+        }
+    }
+
+    /// Make `symbol` the dominant contract of its product.
+    pub fn set_dominant(&mut self, symbol: &str) {
+        self.dominant.insert(root(symbol).to_string(), symbol.to_string());
+    }
+
+    /// Whether `symbol` is a dominant alias such as `rb888`.
+    pub fn is_alias(&self, symbol: &str) -> bool {
+        !self.dominant_suffix.is_empty() && symbol[root(symbol).len()..] == self.dominant_suffix
+    }
+
+    /// The contract `symbol` trades: the dominant contract for an alias, `symbol` otherwise.
+    pub fn resolve(&self, symbol: &str) -> Result<SymbolType, String> {
+        if self.is_alias(symbol) {
+            return match self.dominant.get(root(symbol)) {
+                Some(contract) => Ok(SymbolType::from(contract.as_str())),
+                None => Err(format!("no dominant contract set for {:?}", symbol)),
+            };
+        }
+        Ok(SymbolType::from(symbol))
+    }
+
+    /// The fees table entry of `symbol`, by rule first, then by product root.
+    pub fn contract(&self, symbol: &str) -> Option<&str> {
+        let rule = self
+            .rules
+            .iter()
+            .filter(|(prefix, _)| symbol.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len());
+        match rule {
+            Some((_, contract)) => Some(contract),
+            None => self.roots.get(root(symbol)).map(String::as_str),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_symbols_aliases_and_rules() {
+        let entries: Vec<String> = ["SHFE.rb", "SHFE.hc", "CZCE.MA", "CFFEX.IF"].iter().map(|s| s.to_string()).collect();
+        let mut map = SymbolMap::new().with_rule("IF", "CFFEX.IF").with_rule("IFX", "CFFEX.IF-custom");
+        map.index_contracts(&entries);
+
+        assert_eq!(map.contract("rb2505"), Some("SHFE.rb"));
+        assert_eq!(map.contract("MA505"), Some("CZCE.MA"));
+        // roots are case-sensitive, as the exchanges' products are
+        assert_eq!(map.contract("ma505"), None);
+        assert_eq!(map.contract("IFX2503"), Some("CFFEX.IF-custom"));
+
+        assert!(map.resolve("rb888").is_err());
+        map.set_dominant("rb2510");
+        assert_eq!(map.resolve("rb888"), Ok(SymbolType::from("rb2510")));
+        assert_eq!(map.resolve("rb2505"), Ok(SymbolType::from("rb2505")));
+        assert_eq!(map.contract("rb888"), Some("SHFE.rb"));
+
+        let map: SymbolMap = toml::from_str("dominant = { MA = 'MA509' }\n[rules]\nsc = 'INE.sc'").unwrap();
+        assert_eq!(map.resolve("MA888"), Ok(SymbolType::from("MA509")));
+        assert_eq!(map.contract("sc2506"), Some("INE.sc"));
+    }
+}
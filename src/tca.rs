@@ -0,0 +1,394 @@
+//! Post-trade transaction cost analysis: the fills an order store recorded, matched to the
+//! orders in the audit log that produced them, costed against the price the strategy decided
+//! on, the arrival mid and the interval VWAP. What an order left unfilled is charged as
+//! opportunity cost, from the decision price to the last price at the end of the interval,
+//! and both together make up the implementation shortfall.
+use crate::audit::OrderRecord;
+use crate::recorder::{self, TickRecorder};
+use crate::store::StoredOrder;
+use crate::timeutil;
+use crate::types::{DirectionType, SymbolType, TickData};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+/// Execution cost of one fill, in price units per lot. Positive means the fill was worse
+/// than the benchmark (paid more when buying, received less when selling).
+#[derive(Debug, Clone, Copy)]
+pub struct FillCost {
+    /// the order as submitted
+    pub record: OrderRecord,
+    pub price: f64,
+    pub lots: u32,
+    pub stamp: i64,
+    /// vs the last price the strategy decided on
+    pub vs_decision: f64,
+    /// vs the bid/ask mid when the order was submitted
+    pub vs_arrival_mid: f64,
+    /// vs the volume-weighted price of the interval after submission; `None` without trades in it
+    pub vs_vwap: Option<f64>,
+}
+
+/// What an order left unfilled.
+#[derive(Debug, Clone, Copy)]
+pub struct Unfilled {
+    pub record: OrderRecord,
+    pub lots: u32,
+    /// per lot, the move from the decision price to the last price at the end of the interval
+    /// after submission, signed like a cost; `None` without ticks up to then
+    pub opportunity_cost: Option<f64>,
+}
+
+/// Lot-weighted average costs over a group of orders.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CostSummary {
+    pub fills: usize,
+    pub lots: u64,
+    pub vs_decision: f64,
+    pub vs_arrival_mid: f64,
+    /// averaged only over fills that had a VWAP benchmark
+    pub vs_vwap: f64,
+    vwap_lots: u64,
+    pub unfilled_lots: u64,
+    /// per unfilled lot that had an end price
+    pub opportunity_cost: f64,
+    opportunity_lots: u64,
+}
+
+impl CostSummary {
+    fn add(&mut self, cost: &FillCost) {
+        let lots = cost.lots as u64;
+        let (old, new) = (self.lots as f64, (self.lots + lots) as f64);
+        self.vs_decision = (self.vs_decision * old + cost.vs_decision * lots as f64) / new;
+        self.vs_arrival_mid = (self.vs_arrival_mid * old + cost.vs_arrival_mid * lots as f64) / new;
+        if let Some(vs_vwap) = cost.vs_vwap {
+            let (old, new) = (self.vwap_lots as f64, (self.vwap_lots + lots) as f64);
+            self.vs_vwap = (self.vs_vwap * old + vs_vwap * lots as f64) / new;
+            self.vwap_lots += lots;
+        }
+        self.fills += 1;
+        self.lots += lots;
+    }
+
+    fn add_unfilled(&mut self, unfilled: &Unfilled) {
+        let lots = unfilled.lots as u64;
+        if let Some(cost) = unfilled.opportunity_cost {
+            let (old, new) = (self.opportunity_lots as f64, (self.opportunity_lots + lots) as f64);
+            self.opportunity_cost = (self.opportunity_cost * old + cost * lots as f64) / new;
+            self.opportunity_lots += lots;
+        }
+        self.unfilled_lots += lots;
+    }
+
+    /// Execution cost vs decision plus opportunity cost, per lot ordered.
+    pub fn shortfall(&self) -> f64 {
+        let ordered = self.lots + self.opportunity_lots;
+        if ordered == 0 {
+            return 0.0;
+        }
+        (self.vs_decision * self.lots as f64 + self.opportunity_cost * self.opportunity_lots as f64) / ordered as f64
+    }
+}
+
+pub struct TcaReport {
+    pub fills: Vec<FillCost>,
+    pub unfilled: Vec<Unfilled>,
+    /// fills of orders missing from the audit log, left out
+    pub unmatched: usize,
+    pub by_strategy: BTreeMap<String, CostSummary>,
+    pub by_symbol: BTreeMap<String, CostSummary>,
+}
+
+impl TcaReport {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (title, groups) in [("strategy", &self.by_strategy), ("symbol", &self.by_symbol)] {
+            let _ = writeln!(
+                out,
+                "{:<24} {:>6} {:>6} {:>12} {:>12} {:>12} {:>8} {:>12} {:>12}",
+                title, "fills", "lots", "vs_decision", "vs_mid", "vs_vwap", "unfilled", "opportunity", "shortfall"
+            );
+            for (key, s) in groups {
+                let _ = writeln!(
+                    out,
+                    "{:<24} {:>6} {:>6} {:>12.4} {:>12.4} {:>12.4} {:>8} {:>12.4} {:>12.4}",
+                    key,
+                    s.fills,
+                    s.lots,
+                    s.vs_decision,
+                    s.vs_arrival_mid,
+                    s.vs_vwap,
+                    s.unfilled_lots,
+                    s.opportunity_cost,
+                    s.shortfall()
+                );
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Signed so that positive is a cost for either side.
+fn cost(direction: DirectionType, price: f64, benchmark: f64) -> f64 {
+    match direction {
+        DirectionType::BUY => price - benchmark,
+        DirectionType::SELL => benchmark - price,
+    }
+}
+
+/// VWAP of trades in `(from, to]`, from cumulative volume deltas of consecutive ticks.
+fn interval_vwap(ticks: &[TickData], from: i64, to: i64) -> Option<f64> {
+    let (mut notional, mut volume) = (0.0, 0i64);
+    for pair in ticks.windows(2) {
+        let (prev, cur) = (&pair[0], &pair[1]);
+        if cur.stamp <= from || cur.stamp > to {
+            continue;
+        }
+        // a negative delta means the cumulative counter reset (new trading day)
+        let traded = cur.volume - prev.volume;
+        if traded > 0 {
+            notional += cur.last * traded as f64;
+            volume += traded;
+        }
+    }
+    (volume > 0).then(|| notional / volume as f64)
+}
+
+/// Last price at or before `at`.
+fn last_price(ticks: &[TickData], at: i64) -> Option<f64> {
+    let n = ticks.partition_point(|t| t.stamp <= at);
+    n.checked_sub(1).map(|i| ticks[i].last)
+}
+
+/// Cost every fill in `fills` against the order in `records` it filled, found by engine
+/// order id, or by strategy and client id for rows written before order ids were recorded.
+/// `ticks` must be sorted by stamp per symbol; `vwap_window` is the benchmark interval after
+/// submission, in stamp units.
+pub fn analyze(records: &[OrderRecord], fills: &[StoredOrder], ticks: &HashMap<SymbolType, Vec<TickData>>, vwap_window: i64) -> TcaReport {
+    let mut report = TcaReport {
+        fills: Vec::with_capacity(fills.len()),
+        unfilled: Vec::new(),
+        unmatched: 0,
+        by_strategy: BTreeMap::new(),
+        by_symbol: BTreeMap::new(),
+    };
+    let by_order_id: HashMap<u64, usize> = records
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.order.order_id != 0)
+        .map(|(i, r)| (r.order.order_id, i))
+        .collect();
+    let by_client_id: HashMap<(&str, u64), usize> = records
+        .iter()
+        .enumerate()
+        .map(|(i, r)| ((r.order.stg_name.as_str(), r.order.client_id), i))
+        .collect();
+    let mut filled = vec![0u32; records.len()];
+
+    for fill in fills {
+        let index = match fill.order_id {
+            0 => by_client_id.get(&(fill.strategy.as_str(), fill.client_id)),
+            id => by_order_id.get(&id),
+        };
+        let Some(&index) = index else {
+            report.unmatched += 1;
+            continue;
+        };
+        let record = &records[index];
+        let order = &record.order;
+        filled[index] += fill.lots;
+        let vwap = ticks
+            .get(&order.symbol)
+            .and_then(|t| interval_vwap(t, order.timestamp, order.timestamp + vwap_window));
+        let cost = FillCost {
+            record: *record,
+            price: fill.price,
+            lots: fill.lots,
+            stamp: fill.stamp,
+            vs_decision: cost(order.direction, fill.price, record.book.last),
+            vs_arrival_mid: cost(order.direction, fill.price, record.book.mid()),
+            vs_vwap: vwap.map(|v| cost(order.direction, fill.price, v)),
+        };
+        report.by_strategy.entry(order.stg_name.as_str().to_string()).or_default().add(&cost);
+        report.by_symbol.entry(order.symbol.as_str().to_string()).or_default().add(&cost);
+        report.fills.push(cost);
+    }
+
+    for (record, filled) in records.iter().zip(filled) {
+        let order = &record.order;
+        let lots = order.lots.saturating_sub(filled);
+        if lots == 0 {
+            continue;
+        }
+        let end = ticks.get(&order.symbol).and_then(|t| last_price(t, order.timestamp + vwap_window));
+        let unfilled = Unfilled {
+            record: *record,
+            lots,
+            opportunity_cost: end.map(|end| cost(order.direction, end, record.book.last)),
+        };
+        report
+            .by_strategy
+            .entry(order.stg_name.as_str().to_string())
+            .or_default()
+            .add_unfilled(&unfilled);
+        report
+            .by_symbol
+            .entry(order.symbol.as_str().to_string())
+            .or_default()
+            .add_unfilled(&unfilled);
+        report.unfilled.push(unfilled);
+    }
+    report
+}
+
+/// Load the recorded ticks covering every (symbol, day) that appears in `records`.
+/// Missing files are skipped; those fills simply get no VWAP benchmark.
+pub fn load_ticks<P: AsRef<Path>>(tick_dir: P, records: &[OrderRecord]) -> io::Result<HashMap<SymbolType, Vec<TickData>>> {
+    let mut wanted: Vec<(SymbolType, u32)> = records
+        .iter()
+        .map(|r| (r.order.symbol, timeutil::local_date(r.order.timestamp)))
+        .collect();
+    wanted.sort_by_key(|(symbol, date)| (symbol.0, *date));
+    wanted.dedup();
+
+    let mut ticks: HashMap<SymbolType, Vec<TickData>> = HashMap::new();
+    for (symbol, date) in wanted {
+        let path = TickRecorder::tick_path(tick_dir.as_ref(), date, &symbol);
+        match recorder::read_ticks(&path) {
+            Ok(day) => ticks.entry(symbol).or_default().extend(day),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    for day in ticks.values_mut() {
+        day.sort_by_key(|t| t.stamp);
+    }
+    Ok(ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BookSnapshot, NameType, OffsetFlagType, Order, OrderType};
+
+    fn record(strategy: &str, symbol: &str, stamp: i64, direction: DirectionType, price: f64, lots: u32, book: (f64, f64, f64)) -> OrderRecord {
+        let (last, bid, ask) = book;
+        OrderRecord {
+            order: Order {
+                stg_name: NameType::from(strategy),
+                symbol: SymbolType::from(symbol),
+                timestamp: stamp,
+                price,
+                lots,
+                direction,
+                offset: OffsetFlagType::OPEN,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            },
+            book: BookSnapshot {
+                stamp,
+                last,
+                bid,
+                ask,
+                bid_size: 1,
+                ask_size: 1,
+            },
+        }
+    }
+
+    fn tick(stamp: i64, last: f64, volume: i64) -> TickData {
+        TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp,
+            last,
+            volume,
+            ..Default::default()
+        }
+    }
+
+    fn fill(record: &OrderRecord, price: f64, lots: u32) -> StoredOrder {
+        let order = &record.order;
+        StoredOrder {
+            session: 1,
+            strategy: order.stg_name.as_str().to_string(),
+            symbol: order.symbol.as_str().to_string(),
+            client_id: order.client_id,
+            order_id: order.order_id,
+            stamp: order.timestamp + 100,
+            direction: format!("{:?}", order.direction),
+            offset: format!("{:?}", order.offset),
+            order_type: format!("{:?}", order.order_type),
+            price,
+            lots,
+        }
+    }
+
+    #[test]
+    fn costs_fills_against_decision_arrival_and_vwap() {
+        let mut records = [
+            // filled 1 over the 100 decided on and the 100 mid
+            record("Aberration10", "rb2505", 1000, DirectionType::BUY, 101.0, 2, (100.0, 99.5, 100.5)),
+            // filled 2 under the last and 1.5 under the 99.5 mid
+            record("Aberration10", "rb2505", 3000, DirectionType::SELL, 98.0, 1, (100.0, 99.0, 100.0)),
+            // no ticks recorded: no VWAP benchmark
+            record("Other", "MA505", 1000, DirectionType::BUY, 2500.0, 1, (2500.0, 2499.0, 2501.0)),
+        ];
+        for (i, record) in records.iter_mut().enumerate() {
+            record.order.order_id = i as u64 + 1;
+        }
+        let fills = [
+            fill(&records[0], 101.0, 2),
+            fill(&records[1], 98.0, 1),
+            fill(&records[2], 2500.0, 1),
+            // an order the audit log does not have
+            StoredOrder {
+                order_id: 99,
+                ..fill(&records[0], 101.0, 1)
+            },
+        ];
+        let ticks = HashMap::from([(
+            SymbolType::from("rb2505"),
+            vec![tick(1000, 100.0, 10), tick(1500, 100.5, 13), tick(2000, 102.0, 14), tick(4000, 99.0, 20)],
+        )]);
+        let report = analyze(&records, &fills, &ticks, 1000);
+
+        let costs: Vec<_> = report.fills.iter().map(|f| (f.vs_decision, f.vs_arrival_mid, f.vs_vwap)).collect();
+        // 3 lots at 100.5 and 1 at 102 in the second after the buy, 6 at 99 after the sell
+        assert_eq!(costs, [(1.0, 1.0, Some(0.125)), (2.0, 1.5, Some(1.0)), (0.0, 0.0, None)]);
+        assert_eq!((report.unmatched, report.unfilled.len()), (1, 0));
+
+        let summary = report.by_strategy["Aberration10"];
+        assert_eq!((summary.fills, summary.lots), (2, 3));
+        assert!((summary.vs_decision - 4.0 / 3.0).abs() < 1e-12);
+        assert!((summary.vs_arrival_mid - 3.5 / 3.0).abs() < 1e-12);
+        assert!((summary.vs_vwap - 1.25 / 3.0).abs() < 1e-12);
+        assert_eq!(summary.shortfall(), summary.vs_decision);
+        let other = report.by_symbol["MA505"];
+        assert_eq!((other.fills, other.vs_vwap), (1, 0.0));
+    }
+
+    #[test]
+    fn costs_the_fill_price_and_charges_what_went_unfilled() {
+        // bought 3 of 4 lots, decided at 100; logs from before order ids match by client id
+        let mut record = record("Aberration10", "rb2505", 1000, DirectionType::BUY, 102.0, 4, (100.0, 99.5, 100.5));
+        record.order.client_id = 7;
+        let fills = [fill(&record, 100.5, 2), fill(&record, 101.0, 1)];
+        let ticks = HashMap::from([(
+            SymbolType::from("rb2505"),
+            vec![tick(1000, 100.0, 10), tick(1500, 101.0, 13), tick(2500, 104.0, 14)],
+        )]);
+        let report = analyze(&[record], &fills, &ticks, 1000);
+
+        assert_eq!(report.fills.iter().map(|f| f.vs_decision).collect::<Vec<_>>(), [0.5, 1.0]);
+        // the price had moved to 101 by the end of the second after the order
+        assert_eq!(report.unfilled.len(), 1);
+        assert_eq!((report.unfilled[0].lots, report.unfilled[0].opportunity_cost), (1, Some(1.0)));
+        let summary = report.by_symbol["rb2505"];
+        assert_eq!((summary.lots, summary.unfilled_lots), (3, 1));
+        // (0.5 * 2 + 1.0 + 1.0) / 4 lots
+        assert!((summary.shortfall() - 0.75).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,292 @@
+//! Helpers for unit-testing strategies without an engine: build scripted or random tick
+//! sequences, feed them to a `Strategy` through a `StrategyHarness`, and assert on the orders
+//! it sends and the positions they lead to.
+//!
+//! The harness assumes every order fills in full at once, so the position it tracks is the
+//! one the strategy believes it has; a strategy closing more than it holds fails the test.
+//! `check` runs a property over many seeded random cases and names the seed that failed.
+//! The strategy reads time from an `EventClock` that follows the ticks fed to it.
+use crate::clock::{Clock, EventClock};
+use crate::position::PositionManager;
+use crate::sizing::SizingContext;
+use crate::strategy::{Orders, Strategy};
+use crate::types::{DirectionType, OffsetFlagType, Order, SymbolType, TickData};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// Ticks at `prices`, `step_ms` apart from `start_stamp`, each with a one-`tick_size` spread
+/// around the last price and growing volume.
+pub fn ticks(symbol: &str, prices: &[f64], start_stamp: i64, step_ms: i64, tick_size: f64) -> Vec<TickData> {
+    let symbol = SymbolType::from(symbol);
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, &last)| TickData {
+            symbol,
+            stamp: start_stamp + i as i64 * step_ms,
+            last,
+            bp1: last - tick_size / 2.0,
+            ap1: last + tick_size / 2.0,
+            bv1: 10,
+            av1: 10,
+            volume: i as i64 + 1,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// `n` prices of a random walk from `start` moving up to `max_ticks` ticks of `tick_size` per
+/// step, kept above zero.
+pub fn random_walk(rng: &mut Rng, n: usize, start: f64, tick_size: f64, max_ticks: u32) -> Vec<f64> {
+    let mut price = start;
+    (0..n)
+        .map(|_| {
+            let moves = rng.below(2 * max_ticks as u64 + 1) as f64 - max_ticks as f64;
+            price = (price + moves * tick_size).max(tick_size);
+            price
+        })
+        .collect()
+}
+
+/// Small deterministic generator (xorshift64*) so a failing case can be replayed by seed.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // zero is a fixed point of xorshift
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0..n`; `n` must be positive.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Run `property` on `cases` generators seeded 0, 1, …; a failure is re-raised with its seed.
+pub fn check(cases: u64, property: impl Fn(&mut Rng)) {
+    for seed in 0..cases {
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| property(&mut Rng::new(seed))));
+        if let Err(cause) = outcome {
+            let message = cause
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| cause.downcast_ref::<&str>().copied())
+                .unwrap_or("panic");
+            panic!("property failed for seed {}: {}", seed, message);
+        }
+    }
+}
+
+/// One order the strategy sent and the net position it left (long minus short lots).
+#[derive(Debug, Clone, Copy)]
+pub struct Transition {
+    pub stamp: i64,
+    pub order: Order,
+    pub before: i64,
+    pub after: i64,
+}
+
+/// Drives one strategy tick by tick, filling every order it sends.
+pub struct StrategyHarness<S: Strategy> {
+    strategy: S,
+    clock: Arc<EventClock>,
+    account: SizingContext,
+    long: u32,
+    short: u32,
+    /// turns targets into orders for strategies trading by target position
+    positions: PositionManager,
+    transitions: Vec<Transition>,
+}
+
+impl<S: Strategy> StrategyHarness<S> {
+    pub fn new(mut strategy: S) -> Self {
+        let clock = Arc::new(EventClock::default());
+        strategy.on_clock(clock.clone());
+        Self {
+            strategy,
+            clock,
+            account: SizingContext {
+                equity: 1_000_000.0,
+                multiplier: 1.0,
+            },
+            long: 0,
+            short: 0,
+            positions: PositionManager::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Account state pushed before every tick, as the engine does.
+    pub fn with_account(mut self, account: SizingContext) -> Self {
+        self.account = account;
+        self
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    pub fn strategy_mut(&mut self) -> &mut S {
+        &mut self.strategy
+    }
+
+    /// The strategy's clock, as of the last tick fed.
+    pub fn now(&self) -> i64 {
+        self.clock.now()
+    }
+
+    /// Feed one tick and fill the orders it produces, in turn; in target-position mode, the
+    /// orders that reach the target.
+    pub fn feed(&mut self, tick: &TickData) -> Orders {
+        self.clock.on_event(tick.stamp);
+        self.strategy.on_account(&self.account);
+        let orders = match self.strategy.target_position(tick) {
+            Some(target) => self.positions.rebalance(self.strategy.name(), tick, target, (self.long, self.short), []),
+            None => self.strategy.update(tick),
+        };
+        for &order in &orders {
+            self.fill(order, tick.stamp);
+        }
+        orders
+    }
+
+    fn fill(&mut self, order: Order, stamp: i64) {
+        assert!(order.lots > 0, "{:?} sent an order for 0 lots at {}", self.strategy.name(), stamp);
+        let before = self.net();
+        let (held, name) = match (order.offset, order.direction) {
+            (OffsetFlagType::OPEN, DirectionType::BUY) => (None, "long"),
+            (OffsetFlagType::OPEN, DirectionType::SELL) => (None, "short"),
+            (OffsetFlagType::CLOSE, DirectionType::SELL) => (Some(&mut self.long), "long"),
+            (OffsetFlagType::CLOSE, DirectionType::BUY) => (Some(&mut self.short), "short"),
+        };
+        match held {
+            Some(held) => {
+                assert!(
+                    order.lots <= *held,
+                    "{:?} closed {} {} lots holding {} at {}",
+                    self.strategy.name(),
+                    order.lots,
+                    name,
+                    held,
+                    stamp
+                );
+                *held -= order.lots;
+            }
+            None if order.direction == DirectionType::BUY => self.long += order.lots,
+            None => self.short += order.lots,
+        }
+        self.strategy.on_fill(&order, 0);
+        self.transitions.push(Transition {
+            stamp,
+            order,
+            before,
+            after: self.net(),
+        });
+    }
+
+    /// Feed a tick of one of the strategy's reference symbols.
+    pub fn feed_reference(&mut self, tick: &TickData) {
+        self.clock.on_event(tick.stamp);
+        self.strategy.on_reference_tick(tick);
+    }
+
+    /// Feed every tick in turn and return the orders they produced.
+    pub fn run<'a>(&mut self, ticks: impl IntoIterator<Item = &'a TickData>) -> Vec<Order> {
+        ticks.into_iter().flat_map(|tick| self.feed(tick)).collect()
+    }
+
+    /// Long and short lots held.
+    pub fn position(&self) -> (u32, u32) {
+        (self.long, self.short)
+    }
+
+    /// Long minus short lots.
+    pub fn net(&self) -> i64 {
+        self.long as i64 - self.short as i64
+    }
+
+    pub fn transitions(&self) -> &[Transition] {
+        &self.transitions
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &Order> {
+        self.transitions.iter().map(|t| &t.order)
+    }
+
+    /// Assert the net position after each order so far, in order.
+    pub fn assert_positions(&self, expected: &[i64]) {
+        let actual: Vec<i64> = self.transitions.iter().map(|t| t.after).collect();
+        assert_eq!(actual, expected, "net positions after each order of {:?}", self.strategy.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::smallvec;
+    use crate::types::{NameType, OrderType};
+
+    /// Buys one lot below 100 and sells it back above 102.
+    struct Threshold {
+        holding: bool,
+    }
+
+    impl Strategy for Threshold {
+        fn name(&self) -> NameType {
+            NameType::from("threshold")
+        }
+
+        fn update(&mut self, tick: &TickData) -> Orders {
+            let (direction, offset) = match (self.holding, tick.last) {
+                (false, last) if last < 100.0 => (DirectionType::BUY, OffsetFlagType::OPEN),
+                (true, last) if last > 102.0 => (DirectionType::SELL, OffsetFlagType::CLOSE),
+                _ => return Orders::new(),
+            };
+            self.holding = !self.holding;
+            smallvec![Order {
+                stg_name: self.name(),
+                symbol: tick.symbol,
+                timestamp: tick.stamp,
+                price: tick.last,
+                lots: 1,
+                direction,
+                offset,
+                order_type: OrderType::LIMIT,
+                client_id: 0,
+                order_id: 0,
+            }]
+        }
+    }
+
+    #[test]
+    fn tracks_positions_through_scripted_ticks() {
+        let mut harness = StrategyHarness::new(Threshold { holding: false });
+        let orders = harness.run(&ticks("rb2505", &[101.0, 99.0, 98.0, 103.0, 99.5], 0, 500, 1.0));
+        assert_eq!(orders.len(), 3);
+        harness.assert_positions(&[1, 0, 1]);
+        assert_eq!(harness.transitions()[1].stamp, 1500);
+        assert_eq!(harness.now(), 2000);
+
+        check(20, |rng| {
+            let prices = random_walk(rng, 200, 100.0, 1.0, 2);
+            assert!(prices.iter().all(|&p| p >= 1.0));
+            let mut harness = StrategyHarness::new(Threshold { holding: false });
+            harness.run(&ticks("rb2505", &prices, 0, 500, 1.0));
+            assert!((0..=1).contains(&harness.net()));
+        });
+    }
+}
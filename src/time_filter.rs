@@ -0,0 +1,113 @@
+//! Time-of-day filtering for strategies: trade only inside chosen windows of the local (CST)
+//! clock, and keep clear of the first and last minutes of each trading session, when spreads
+//! are wide and prices jump on the auction and on the close.
+//!
+//! A strategy embeds a `TimeFilter`, forwards `on_session_start`/`on_session_end` to it and
+//! asks `allows` before entering. Session edges need the engine to know the sessions (see
+//! `CtaEngine::enable_calendar`); until a session has been reported, only the windows apply.
+use crate::calendar::Session;
+use crate::timeutil;
+
+const SECS_PER_DAY: u32 = 86400;
+
+#[derive(Debug, Clone, Default)]
+pub struct TimeFilter {
+    /// local windows `[start, end)` in seconds of day; none means all day
+    windows: Vec<Session>,
+    /// seconds kept clear after a session opens and before it closes
+    skip_open: u32,
+    skip_close: u32,
+    /// the session the symbol is in, once the engine has reported one
+    session: Option<Session>,
+    sessions_reported: bool,
+}
+
+impl TimeFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also allow `[start, end)`, in seconds of local day; `end < start` wraps past midnight.
+    pub fn with_window(mut self, start: u32, end: u32) -> Self {
+        self.windows.push(Session { start, end });
+        self
+    }
+
+    /// Keep out of the first `after_open` and the last `before_close` seconds of every session.
+    pub fn skip_session_edges(mut self, after_open: u32, before_close: u32) -> Self {
+        self.set_session_edges(after_open, before_close);
+        self
+    }
+
+    pub fn set_session_edges(&mut self, after_open: u32, before_close: u32) {
+        self.skip_open = after_open;
+        self.skip_close = before_close;
+    }
+
+    pub fn on_session_start(&mut self, session: &Session) {
+        self.session = Some(*session);
+        self.sessions_reported = true;
+    }
+
+    pub fn on_session_end(&mut self, _session: &Session) {
+        self.session = None;
+        self.sessions_reported = true;
+    }
+
+    /// Whether a tick at `stamp` falls where trading is allowed.
+    pub fn allows(&self, stamp: i64) -> bool {
+        let secs = timeutil::local_secs_of_day(stamp);
+        if !self.windows.is_empty() && !self.windows.iter().any(|w| contains(w, secs)) {
+            return false;
+        }
+        match self.session {
+            Some(s) => since(s.start, secs) >= self.skip_open && since(secs, s.end) > self.skip_close,
+            None => !self.sessions_reported,
+        }
+    }
+}
+
+fn contains(window: &Session, secs: u32) -> bool {
+    since(window.start, secs) < since(window.start, window.end)
+}
+
+/// Seconds from `from` forward to `to`, wrapping past midnight.
+fn since(from: u32, to: u32) -> u32 {
+    (to + SECS_PER_DAY - from) % SECS_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_to_windows_and_off_session_edges() {
+        let at = |hh: u32, mm: u32| timeutil::local_stamp(20250313, hh * 3600 + mm * 60);
+        let night = Session {
+            start: 21 * 3600,
+            end: 23 * 3600,
+        };
+        let mut filter = TimeFilter::new().skip_session_edges(300, 300);
+        // nothing reported yet: no calendar, so only windows apply
+        assert!(filter.allows(at(21, 0)));
+
+        filter.on_session_start(&night);
+        assert!(!filter.allows(at(21, 4)));
+        assert!(filter.allows(at(21, 5)));
+        assert!(filter.allows(at(22, 54)));
+        assert!(!filter.allows(at(22, 55)));
+        filter.on_session_end(&night);
+        assert!(!filter.allows(at(23, 30)));
+
+        // a window wrapping midnight, on top of a session wrapping it too
+        let mut filter = TimeFilter::new().with_window(22 * 3600, 3600);
+        assert!(!filter.allows(at(21, 30)));
+        assert!(filter.allows(at(23, 30)) && filter.allows(at(0, 30)));
+        assert!(!filter.allows(at(1, 0)));
+        filter.on_session_start(&Session {
+            start: 21 * 3600,
+            end: 2 * 3600 + 1800,
+        });
+        assert!(filter.allows(at(0, 59)));
+    }
+}
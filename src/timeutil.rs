@@ -0,0 +1,99 @@
+/// `TickData.stamp` and `Order.timestamp` are milliseconds since the Unix epoch (UTC).
+pub const STAMP_PER_SEC: i64 = 1000;
+
+/// China Standard Time, the clock every domestic futures exchange quotes in.
+pub const CST_OFFSET_SECS: i64 = 8 * 3600;
+
+const SECS_PER_DAY: i64 = 86400;
+
+/// Wall-clock time as a stamp.
+pub fn now_stamp() -> i64 {
+    let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    since_epoch.as_millis() as i64
+}
+
+/// Wall-clock time in nanoseconds since the epoch, for latencies finer than a stamp.
+pub fn now_nanos() -> i64 {
+    let since_epoch = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    since_epoch.as_nanos() as i64
+}
+
+/// Seconds since local (CST) midnight.
+pub fn local_secs_of_day(stamp: i64) -> u32 {
+    (stamp.div_euclid(STAMP_PER_SEC) + CST_OFFSET_SECS).rem_euclid(SECS_PER_DAY) as u32
+}
+
+/// Local (CST) calendar date of a stamp, as `yyyymmdd`.
+pub fn local_date(stamp: i64) -> u32 {
+    day_to_date(local_day(stamp))
+}
+
+/// Local (CST) days since 1970-01-01, for date arithmetic.
+pub fn local_day(stamp: i64) -> i64 {
+    (stamp.div_euclid(STAMP_PER_SEC) + CST_OFFSET_SECS).div_euclid(SECS_PER_DAY)
+}
+
+/// A `local_day` as `yyyymmdd`.
+pub fn day_to_date(day: i64) -> u32 {
+    let (y, m, d) = civil_from_days(day);
+    y as u32 * 10000 + m * 100 + d
+}
+
+/// `yyyymmdd` → `local_day`, the inverse of `day_to_date`.
+pub fn date_to_day(date: u32) -> i64 {
+    days_from_civil((date / 10000) as i32, date / 100 % 100, date % 100)
+}
+
+/// Stamp of `secs` past local (CST) midnight on `date` (`yyyymmdd`).
+pub fn local_stamp(date: u32, secs: u32) -> i64 {
+    (date_to_day(date) * SECS_PER_DAY + secs as i64 - CST_OFFSET_SECS) * STAMP_PER_SEC
+}
+
+/// Day of week of a `local_day`, 0 = Sunday.
+pub fn weekday(day: i64) -> u32 {
+    // 1970-01-01 was a Thursday
+    (day + 4).rem_euclid(7) as u32
+}
+
+/// Days since 1970-01-01 → (year, month, day), Howard Hinnant's algorithm.
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = (yoe + era * 400 + if m <= 2 { 1 } else { 0 }) as i32;
+    (y, m, d)
+}
+
+/// (year, month, day) → days since 1970-01-01, Howard Hinnant's algorithm.
+fn days_from_civil(y: i32, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y } as i64;
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_and_days_follow_cst_midnight() {
+        // 2025-05-06 23:59:59 CST is 15:59:59 UTC; a second later it is the 7th in China
+        let before = local_stamp(20250506, 86399);
+        assert_eq!(before, 1_746_547_199_000);
+        assert_eq!((local_date(before), local_date(before + 1000)), (20250506, 20250507));
+        assert_eq!(local_day(before + 1000) - local_day(before), 1);
+        assert_eq!(local_secs_of_day(before + 1000), 0);
+        assert_eq!(day_to_date(date_to_day(20240229)), 20240229);
+        // a Tuesday
+        assert_eq!(weekday(local_day(before)), 2);
+    }
+}
@@ -0,0 +1,196 @@
+//! Benchmark tracking: a strategy's live returns against a benchmark series, compared with the
+//! tracking error and information ratio it showed in its backtest.
+//!
+//! Equity and benchmark price are sampled once per period (by tick time); the active return
+//! of a period is the strategy's return minus the benchmark's. Tracking error is the rolling
+//! standard deviation of active returns, information ratio their mean over that deviation.
+use crate::operator::rolling::{Mean, StDev};
+use crate::types::SymbolType;
+use std::fmt;
+
+/// Tracking error and information ratio, per period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackingStats {
+    pub tracking_error: f64,
+    pub information_ratio: f64,
+}
+
+impl fmt::Display for TrackingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "tracking error {:.6}, information ratio {:.3}",
+            self.tracking_error, self.information_ratio
+        )
+    }
+}
+
+/// The series a strategy is measured against, declared through `Strategy::benchmark`.
+#[derive(Debug, Clone, Copy)]
+pub struct Benchmark {
+    /// a continuous contract (e.g. `rb.main`) or index published on the tick feed
+    pub symbol: SymbolType,
+    /// returns are sampled once per this many milliseconds of tick time
+    pub period_ms: i64,
+    /// rolling window, in periods
+    pub window: usize,
+    /// what the backtest showed, in the same period
+    pub profile: TrackingStats,
+    /// alert when live tracking error exceeds the profile's by this factor, e.g. 1.5
+    pub max_tracking_error_ratio: f64,
+    /// alert when live information ratio falls this far below the profile's
+    pub max_information_ratio_drop: f64,
+}
+
+impl Benchmark {
+    /// Daily sampling over `window` days with the default thresholds.
+    pub fn daily(symbol: SymbolType, window: usize, profile: TrackingStats) -> Self {
+        Self {
+            symbol,
+            period_ms: 86400 * crate::timeutil::STAMP_PER_SEC,
+            window,
+            profile,
+            max_tracking_error_ratio: 1.5,
+            max_information_ratio_drop: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackingAlert {
+    /// live behaviour left the backtest profile
+    Drifting(TrackingStats),
+    /// back within the profile after a `Drifting` alert
+    Recovered(TrackingStats),
+}
+
+pub struct TrackingMonitor {
+    benchmark: Benchmark,
+    /// latest benchmark price
+    price: Option<f64>,
+    /// (period, equity, benchmark price) of the last sample
+    last: Option<(i64, f64, f64)>,
+    mean: Mean,
+    stdev: StDev,
+    /// rolling mean and standard deviation of active returns as of the last sample
+    active_mean: f64,
+    tracking_error: f64,
+    samples: usize,
+    drifting: bool,
+}
+
+impl TrackingMonitor {
+    pub fn new(benchmark: Benchmark) -> Self {
+        Self {
+            benchmark,
+            price: None,
+            last: None,
+            mean: Mean::new(benchmark.window),
+            stdev: StDev::new(benchmark.window),
+            active_mean: f64::NAN,
+            tracking_error: f64::NAN,
+            samples: 0,
+            drifting: false,
+        }
+    }
+
+    pub fn benchmark(&self) -> &Benchmark {
+        &self.benchmark
+    }
+
+    pub fn on_benchmark(&mut self, price: f64) {
+        if price.is_finite() && price > 0.0 {
+            self.price = Some(price);
+        }
+    }
+
+    /// Live stats, once a full window of periods has been sampled.
+    pub fn stats(&self) -> Option<TrackingStats> {
+        if !self.tracking_error.is_finite() {
+            return None;
+        }
+        let information_ratio = if self.tracking_error > 0.0 {
+            self.active_mean / self.tracking_error
+        } else {
+            0.0
+        };
+        Some(TrackingStats {
+            tracking_error: self.tracking_error,
+            information_ratio,
+        })
+    }
+
+    /// Sample the strategy's `equity` at tick time `stamp`; the first sample of each period
+    /// closes the previous one. Returns an alert when the strategy starts or stops drifting.
+    pub fn on_equity(&mut self, stamp: i64, equity: f64) -> Option<TrackingAlert> {
+        let price = self.price?;
+        let period = stamp.div_euclid(self.benchmark.period_ms);
+        match self.last {
+            Some((last_period, _, _)) if last_period == period => return None,
+            Some((_, last_equity, last_price)) if last_equity > 0.0 => {
+                let active = (equity / last_equity - 1.0) - (price / last_price - 1.0);
+                self.active_mean = self.mean.update(active);
+                // NAN while warming up; rounding can also push a flat window's variance below zero
+                let stdev = self.stdev.update(active);
+                self.samples += 1;
+                self.tracking_error = if self.samples >= self.benchmark.window { stdev.max(0.0) } else { stdev };
+            }
+            _ => {}
+        }
+        self.last = Some((period, equity, price));
+
+        let stats = self.stats()?;
+        let profile = self.benchmark.profile;
+        let drifting = stats.tracking_error > profile.tracking_error * self.benchmark.max_tracking_error_ratio
+            || stats.information_ratio < profile.information_ratio - self.benchmark.max_information_ratio_drop;
+        if drifting == self.drifting {
+            return None;
+        }
+        self.drifting = drifting;
+        Some(if drifting {
+            TrackingAlert::Drifting(stats)
+        } else {
+            TrackingAlert::Recovered(stats)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86400 * crate::timeutil::STAMP_PER_SEC;
+
+    #[test]
+    fn alerts_on_drift_and_recovery() {
+        let profile = TrackingStats {
+            tracking_error: 0.01,
+            information_ratio: 0.0,
+        };
+        let mut monitor = TrackingMonitor::new(Benchmark::daily(SymbolType::from("rb.main"), 3, profile));
+        assert_eq!(monitor.on_equity(0, 100.0), None, "no benchmark price yet");
+
+        // the strategy follows the benchmark exactly: no tracking error
+        let mut equity = 100.0;
+        for day in 0..4 {
+            let price = 100.0 + day as f64;
+            monitor.on_benchmark(price);
+            equity = price;
+            assert_eq!(monitor.on_equity(day * DAY, equity), None);
+        }
+        assert_eq!(monitor.stats().unwrap().tracking_error, 0.0);
+
+        // then swings 5% around it
+        let mut alerts = Vec::new();
+        for (day, swing) in (4..7).zip([1.05, 0.95, 1.05]) {
+            equity *= swing;
+            alerts.extend(monitor.on_equity(day * DAY, equity));
+        }
+        assert!(matches!(alerts[..], [TrackingAlert::Drifting(stats)] if stats.tracking_error > 0.015));
+
+        for day in 7..10 {
+            alerts.extend(monitor.on_equity(day * DAY, equity));
+        }
+        assert!(matches!(alerts[1..], [TrackingAlert::Recovered(_)]));
+    }
+}
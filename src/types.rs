@@ -1,3 +1,4 @@
+use serde::{Deserialize, Deserializer, Serialize};
 use std::array;
 use std::fmt;
 
@@ -47,6 +48,14 @@ impl From<&str> for SymbolType {
     }
 }
 
+/// From a plain string, for ticks published as JSON or MessagePack.
+impl<'de> Deserialize<'de> for SymbolType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(SymbolType::from(s.as_str()))
+    }
+}
+
 impl NameType {
     /// Interpret the bytes as a (possibly NUL-terminated) UTF-8 string.
     pub fn as_str(&self) -> &str {
@@ -73,7 +82,8 @@ impl From<&str> for NameType {
 
 // TickData: exactly matches the C struct layout
 #[repr(C)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(default)]
 pub struct TickData {
     pub symbol: SymbolType, // char symbol[16]
     pub stamp: i64,         // int64_t stamp
@@ -115,9 +125,100 @@ pub struct TickData {
     pub adj: f64,           // double adj
 }
 
+impl Default for TickData {
+    /// All-zero tick with a neutral adjustment factor.
+    fn default() -> Self {
+        // every field is plain-old-data, so all-zero bytes are a valid TickData
+        let mut tick: TickData = unsafe { std::mem::zeroed() };
+        tick.adj = 1.0;
+        tick
+    }
+}
+
+impl TickData {
+    /// The daily price limit the book is locked at: the price sits on it and the side that
+    /// would trade against it is empty, so orders on the crowded side only queue.
+    pub fn limit_lock(&self) -> Option<LimitLock> {
+        if self.limit_up > 0.0 && self.bp1 >= self.limit_up && self.ap1 == 0.0 {
+            Some(LimitLock::Up)
+        } else if self.limit_down > 0.0 && self.ap1 > 0.0 && self.ap1 <= self.limit_down && self.bp1 == 0.0 {
+            Some(LimitLock::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// A day's price limits (涨跌停板) from the feed; a limit the feed leaves at 0 is not checked.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct PriceLimits {
+    pub down: f64,
+    pub up: f64,
+}
+
+impl PriceLimits {
+    pub fn of(tick: &TickData) -> Self {
+        Self {
+            down: tick.limit_down,
+            up: tick.limit_up,
+        }
+    }
+
+    /// Whether the exchange accepts an order at `price`.
+    pub fn contains(&self, price: f64) -> bool {
+        (self.up <= 0.0 || price <= self.up) && (self.down <= 0.0 || price >= self.down)
+    }
+
+    /// `price` moved inside the limits; nothing trades beyond them.
+    pub fn clamp(&self, price: f64) -> f64 {
+        let price = if self.up > 0.0 { price.min(self.up) } else { price };
+        if self.down > 0.0 { price.max(self.down) } else { price }
+    }
+}
+
+/// Which daily price limit a book is locked at, see `TickData::limit_lock`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LimitLock {
+    Up,
+    Down,
+}
+
+/// Top of book as a strategy saw it, captured alongside each order for slippage analysis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BookSnapshot {
+    pub stamp: i64,
+    /// last trade price, i.e. the price the strategy's decision was based on
+    pub last: f64,
+    pub bid: f64,
+    pub ask: f64,
+    pub bid_size: i32,
+    pub ask_size: i32,
+}
+
+impl BookSnapshot {
+    pub fn from_tick(tick: &TickData) -> Self {
+        Self {
+            stamp: tick.stamp,
+            last: tick.last,
+            bid: tick.bp1,
+            ask: tick.ap1,
+            bid_size: tick.bv1,
+            ask_size: tick.av1,
+        }
+    }
+
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.ask + self.bid) / 2.0
+    }
+}
+
 // C “enum class DirectionType : uint8_t { NONE, BUY, SELL };”
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DirectionType {
     BUY = 0,
     SELL = 1,
@@ -125,12 +226,27 @@ pub enum DirectionType {
 
 // C “enum class OffsetFlagType : uint8_t { NONE, OPEN, CLOSE };”
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OffsetFlagType {
     OPEN = 0,
     CLOSE = 1,
 }
 
+// C “enum class OrderType : uint8_t { LIMIT, MARKET, FAK, FOK };”
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderType {
+    /// rests in the book at `price` until filled or cancelled
+    #[default]
+    LIMIT = 0,
+    /// trades at the best available price; `price` is ignored
+    MARKET = 1,
+    /// fill-and-kill: trades what it can at `price` or better, the rest is cancelled
+    FAK = 2,
+    /// fill-or-kill: trades all lots at `price` or better, or nothing
+    FOK = 3,
+}
+
 // Order: matches the C struct exactly, assuming NameType is char[32]
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -138,8 +254,50 @@ pub struct Order {
     pub stg_name: NameType,       // NameType stg_name;
     pub symbol: SymbolType,       // SymbolType symbol;
     pub timestamp: i64,           // int64_t timestamp;
-    pub price: f64,               // order price
+    pub price: f64,               // limit price, ignored for MARKET
     pub lots: u32,                // uint32_t lots;
     pub direction: DirectionType, // DirectionType direction;
     pub offset: OffsetFlagType,   // OffsetFlagType offset;
+    pub order_type: OrderType,    // OrderType order_type;
+    pub client_id: u64,           // uint64_t client_id; chosen by the strategy, echoed in reports
+    pub order_id: u64,            // uint64_t order_id; assigned by the engine (see `ids`), echoed in reports
+}
+
+// OrderCancel: withdraws a resting order; the gateway tells it from an Order by frame size
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct OrderCancel {
+    pub stg_name: NameType, // NameType stg_name;
+    pub symbol: SymbolType, // SymbolType symbol;
+    pub timestamp: i64,     // int64_t timestamp;
+    pub client_id: u64,     // uint64_t client_id; of the order to cancel
+}
+
+const _: () = assert!(std::mem::size_of::<Order>() != std::mem::size_of::<OrderCancel>());
+
+impl Order {
+    pub fn with_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    pub fn with_client_id(mut self, client_id: u64) -> Self {
+        self.client_id = client_id;
+        self
+    }
+
+    /// Whether the order would trade on arrival against `tick`'s top of book.
+    pub fn is_marketable(&self, tick: &TickData) -> bool {
+        let touch = match self.direction {
+            DirectionType::BUY => tick.ap1,
+            DirectionType::SELL => tick.bp1,
+        };
+        // an empty side (e.g. at a price limit) is quoted as 0
+        touch > 0.0
+            && match (self.order_type, self.direction) {
+                (OrderType::MARKET, _) => true,
+                (_, DirectionType::BUY) => self.price >= touch,
+                (_, DirectionType::SELL) => self.price <= touch,
+            }
+    }
 }
@@ -0,0 +1,144 @@
+//! Dry-start validation: everything `CtaEngine::validate` checks before a session, collected
+//! into one report instead of failing on the first problem.
+use std::fmt;
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    /// the engine would run, but probably not as intended
+    Warn,
+    /// the engine would not run, or not trade
+    Fail,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub severity: Severity,
+    /// what was checked, e.g. `strategy` or `endpoint`
+    pub area: &'static str,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub checks: Vec<Check>,
+}
+
+impl ValidationReport {
+    pub fn ok(&mut self, area: &'static str, detail: impl Into<String>) {
+        self.push(Severity::Ok, area, detail);
+    }
+
+    pub fn warn(&mut self, area: &'static str, detail: impl Into<String>) {
+        self.push(Severity::Warn, area, detail);
+    }
+
+    pub fn fail(&mut self, area: &'static str, detail: impl Into<String>) {
+        self.push(Severity::Fail, area, detail);
+    }
+
+    fn push(&mut self, severity: Severity, area: &'static str, detail: impl Into<String>) {
+        self.checks.push(Check {
+            severity,
+            area,
+            detail: detail.into(),
+        });
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.checks.iter().filter(|c| c.severity == severity).count()
+    }
+
+    /// No check failed; warnings are allowed.
+    pub fn passed(&self) -> bool {
+        self.count(Severity::Fail) == 0
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            let tag = match check.severity {
+                Severity::Ok => "ok",
+                Severity::Warn => "WARN",
+                Severity::Fail => "FAIL",
+            };
+            writeln!(f, "[{:>4}] {:<10} {}", tag, check.area, check.detail)?;
+        }
+        write!(
+            f,
+            "{} checks: {} ok, {} warnings, {} failures",
+            self.checks.len(),
+            self.count(Severity::Ok),
+            self.count(Severity::Warn),
+            self.count(Severity::Fail)
+        )
+    }
+}
+
+/// Whether a peer is listening at ZMQ endpoint `uri`: `tcp://host:port` and `ipc://path`
+/// (including Linux abstract `ipc://@name`) are probed with a plain connect, which ZMQ
+/// peers accept and drop. Other transports cannot be probed and return `Ok(false)`.
+pub fn probe_endpoint(uri: &str, timeout: Duration) -> io::Result<bool> {
+    if let Some(addr) = uri.strip_prefix("tcp://") {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::other("address did not resolve"))?;
+        TcpStream::connect_timeout(&addr, timeout)?;
+        return Ok(true);
+    }
+    if let Some(path) = uri.strip_prefix("ipc://") {
+        match path.strip_prefix('@') {
+            Some(name) => connect_abstract(name)?,
+            None => drop(UnixStream::connect(path)?),
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &str) -> io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+    UnixStream::connect_addr(&SocketAddr::from_abstract_name(name)?)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn connect_abstract(_name: &str) -> io::Result<()> {
+    Err(io::Error::other("abstract sockets are Linux-only"))
+}
+
+/// Whether `addr` (e.g. the metrics address) can be bound right now.
+pub fn probe_bind(addr: &str) -> io::Result<()> {
+    TcpListener::bind(addr).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_endpoints_and_summarizes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("tcp://{}", listener.local_addr().unwrap());
+        assert!(probe_endpoint(&uri, Duration::from_secs(1)).unwrap());
+        drop(listener);
+        assert!(probe_endpoint(&uri, Duration::from_secs(1)).is_err());
+        assert!(!probe_endpoint("inproc://hq", Duration::from_secs(1)).unwrap());
+
+        let mut report = ValidationReport::default();
+        report.ok("endpoint", "tick feed reachable");
+        report.warn("contract", "MA505 trades with default fees");
+        assert!(report.passed());
+        report.fail("contract", "rb2505 has no contract info");
+        assert!(!report.passed());
+        assert!(report.to_string().ends_with("3 checks: 1 ok, 1 warnings, 1 failures"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Walk-forward validation: pick parameters with `optimizer::search` on an in-sample window
+//! of trading days, trade them on the out-of-sample days right after, then roll both windows
+//! forward and repeat. Only out-of-sample days count towards the aggregate, so it estimates
+//! how the re-optimizing process would have done rather than how well one parameter set fits
+//! the whole history.
+//!
+//! Each window is backtested from flat with the configured starting cash; whatever position
+//! is open at its end is marked to market, not carried into the next window.
+use crate::backtest::{self, BacktestStats};
+use crate::optimizer::{self, Params, SearchConfig};
+use crate::strategy::Strategy;
+use crate::timeutil;
+use crate::types::TickData;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WalkForwardConfig {
+    /// trading days optimized over
+    pub in_sample_days: usize,
+    /// trading days traded with the chosen parameters
+    pub out_of_sample_days: usize,
+    /// trading days to roll forward by; usually `out_of_sample_days`, so the out-of-sample
+    /// windows tile the history without overlap
+    pub step_days: usize,
+}
+
+/// One in-sample / out-of-sample pair.
+#[derive(Debug, Clone)]
+pub struct Window {
+    /// first and last trading day, `yyyymmdd`
+    pub in_sample: (u32, u32),
+    pub out_of_sample: (u32, u32),
+    pub params: Params,
+    pub in_sample_stats: BacktestStats,
+    pub out_of_sample_stats: BacktestStats,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkForward {
+    pub windows: Vec<Window>,
+    /// out-of-sample windows chained together, returns compounded day by day
+    pub aggregate: BacktestStats,
+    /// closing equity of each out-of-sample day on the chained curve
+    pub daily_equity: Vec<(u32, f64)>,
+}
+
+/// Walk `candidates` forward over `ticks`, which must be in stamp order. Fails if there are
+/// not enough days for one window or a window has no candidate that runs.
+pub fn run<F>(factory: F, candidates: &[Params], ticks: &[TickData], search: &SearchConfig, config: &WalkForwardConfig) -> Result<WalkForward, String>
+where
+    F: Fn() -> Result<Box<dyn Strategy>, String> + Sync,
+{
+    if config.in_sample_days == 0 || config.out_of_sample_days == 0 || config.step_days == 0 {
+        return Err("walk-forward windows must span at least one day".into());
+    }
+    let days = day_starts(ticks);
+    let span = config.in_sample_days + config.out_of_sample_days;
+    if days.len() < span {
+        return Err(format!("{} trading days of ticks, one window needs {}", days.len(), span));
+    }
+    // tick range of days `from..to`
+    let slice = |from: usize, to: usize| {
+        let end = days.get(to).map_or(ticks.len(), |&(_, start)| start);
+        &ticks[days[from].1..end]
+    };
+
+    let mut windows = Vec::new();
+    let mut chained = Vec::new();
+    let mut equity = search.init_cash;
+    let mut start = 0;
+    while start + span <= days.len() {
+        let split = start + config.in_sample_days;
+        let end = start + span;
+        let trials = optimizer::search(&factory, candidates.to_vec(), slice(start, split), search);
+        let best = trials
+            .into_iter()
+            .next()
+            .filter(|t| t.error.is_none())
+            .ok_or_else(|| format!("no candidate ran on the in-sample days from {}", days[start].0))?;
+
+        let mut strategy = optimizer::build(&factory, &best.params)?;
+        let out = backtest::run(strategy.as_mut(), slice(split, end), search.init_cash, search.info, &search.execution);
+        // rescale the window's curve onto the chained one
+        let scale = equity / search.init_cash;
+        chained.extend(out.daily_equity.iter().map(|&(date, e)| (date, e * scale)));
+        equity *= out.stats.final_equity / search.init_cash;
+
+        windows.push(Window {
+            in_sample: (days[start].0, days[split - 1].0),
+            out_of_sample: (days[split].0, days[end - 1].0),
+            params: best.params,
+            in_sample_stats: best.stats,
+            out_of_sample_stats: out.stats,
+        });
+        start += config.step_days;
+    }
+
+    let curve: Vec<f64> = std::iter::once(search.init_cash).chain(chained.iter().map(|&(_, e)| e)).collect();
+    let aggregate = BacktestStats {
+        final_equity: equity,
+        total_return: equity / search.init_cash - 1.0,
+        sharpe: backtest::sharpe(search.init_cash, &chained),
+        max_drawdown: backtest::max_drawdown(&curve),
+        realized_pnl: windows.iter().map(|w| w.out_of_sample_stats.realized_pnl).sum(),
+        fees: windows.iter().map(|w| w.out_of_sample_stats.fees).sum(),
+        fills: windows.iter().map(|w| w.out_of_sample_stats.fills).sum(),
+        margin_calls: windows.iter().map(|w| w.out_of_sample_stats.margin_calls).sum(),
+    };
+    Ok(WalkForward {
+        windows,
+        aggregate,
+        daily_equity: chained,
+    })
+}
+
+/// Each local trading day in `ticks` with the index of its first tick.
+fn day_starts(ticks: &[TickData]) -> Vec<(u32, usize)> {
+    let mut days: Vec<(u32, usize)> = Vec::new();
+    for (i, tick) in ticks.iter().enumerate() {
+        let date = timeutil::local_date(tick.stamp);
+        if days.last().is_none_or(|&(last, _)| last != date) {
+            days.push((date, i));
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ContractInfo;
+    use crate::optimizer::{Objective, ParamGrid, kind_factory};
+    use crate::testing::{self, Rng};
+
+    #[test]
+    fn rolls_out_of_sample_windows_over_the_history() {
+        // 12 days of half-hourly ticks
+        let prices = testing::random_walk(&mut Rng::new(3), 12 * 48, 3500.0, 1.0, 4);
+        let ticks = testing::ticks("rb2505", &prices, 0, 30 * 60 * 1000, 1.0);
+        let search = SearchConfig {
+            init_cash: 1_000_000.0,
+            info: ContractInfo {
+                multiplier: 10.0,
+                ..Default::default()
+            },
+            objective: Objective::TotalReturn,
+            execution: Default::default(),
+            threads: 2,
+        };
+        let config = WalkForwardConfig {
+            in_sample_days: 4,
+            out_of_sample_days: 2,
+            step_days: 2,
+        };
+        let candidates = ParamGrid::new().axis("ma_len", vec![10.0, 20.0]).points();
+        let result = run(kind_factory("Aberration", &["20"]), &candidates, &ticks, &search, &config).unwrap();
+
+        assert_eq!(result.windows.len(), 4);
+        for pair in result.windows.windows(2) {
+            assert!(pair[0].out_of_sample.1 < pair[1].out_of_sample.0, "out-of-sample days overlap");
+        }
+        assert!(result.windows.iter().all(|w| w.in_sample.1 < w.out_of_sample.0));
+        assert_eq!(result.daily_equity.len(), 8);
+        let fills: usize = result.windows.iter().map(|w| w.out_of_sample_stats.fills).sum();
+        assert_eq!(result.aggregate.fills, fills);
+        assert!((result.aggregate.final_equity - result.daily_equity.last().unwrap().1).abs() < 1e-6);
+
+        let too_long = WalkForwardConfig {
+            in_sample_days: 12,
+            ..config
+        };
+        assert!(run(kind_factory("Aberration", &["20"]), &candidates, &ticks, &search, &too_long).is_err());
+    }
+}
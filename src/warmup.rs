@@ -0,0 +1,168 @@
+//! Warm-up before live trading. A strategy with a 200-tick window would otherwise trade blind
+//! for its first 200 live ticks; with warm-up enabled, each strategy is run through its
+//! symbol's latest history when its worker starts it, right after `on_start`. Orders it sends
+//! meanwhile are dropped, and it is then told the position it actually holds through
+//! `on_resume`, so state built on orders that never went out is reset.
+//!
+//! History comes from a `WarmupSource`: the recorder's tick files, stored bars replayed as
+//! ticks, or, with the `download` feature, a REST endpoint serving ticks as JSON.
+use crate::bars::{Bar, BarPeriod, BarStore};
+use crate::recorder::{self, TickRecorder};
+use crate::timeutil;
+use crate::types::{SymbolType, TickData};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+pub trait WarmupSource: Send + Sync {
+    /// Up to `count` of `symbol`'s latest ticks stamped before `before`, oldest first.
+    fn ticks(&self, symbol: &SymbolType, before: i64, count: usize) -> io::Result<Vec<TickData>>;
+}
+
+/// The last `count` of `ticks` stamped before `before`.
+fn latest(mut ticks: Vec<TickData>, before: i64, count: usize) -> Vec<TickData> {
+    ticks.retain(|t| t.stamp < before);
+    let skip = ticks.len().saturating_sub(count);
+    ticks.drain(..skip);
+    ticks
+}
+
+/// Ticks archived by `TickRecorder` under `root`, read back day by day from the latest.
+pub struct RecordedTicks {
+    root: PathBuf,
+}
+
+impl RecordedTicks {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl WarmupSource for RecordedTicks {
+    fn ticks(&self, symbol: &SymbolType, before: i64, count: usize) -> io::Result<Vec<TickData>> {
+        let until = timeutil::local_date(before);
+        let mut dates: Vec<u32> = fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .filter(|&date| date <= until)
+            .collect();
+        dates.sort_unstable_by(|a, b| b.cmp(a));
+        let mut ticks = Vec::new();
+        for date in dates {
+            let path = TickRecorder::tick_path(&self.root, date, symbol);
+            if !path.exists() {
+                continue;
+            }
+            let mut day = latest(recorder::read_ticks(&path)?, before, count);
+            day.append(&mut ticks);
+            ticks = day;
+            if ticks.len() >= count {
+                break;
+            }
+        }
+        Ok(latest(ticks, before, count))
+    }
+}
+
+/// Bars from a `BarStore`, one tick per bar at its close.
+pub struct BarTicks {
+    store: BarStore,
+    period: BarPeriod,
+}
+
+impl BarTicks {
+    pub fn new(store: BarStore, period: BarPeriod) -> Self {
+        Self { store, period }
+    }
+}
+
+fn bar_tick(symbol: SymbolType, bar: &Bar) -> TickData {
+    TickData {
+        symbol,
+        stamp: bar.stamp,
+        open: bar.open,
+        high: bar.high,
+        low: bar.low,
+        last: bar.close,
+        close: bar.close,
+        oi: bar.oi,
+        volume: bar.volume as i64,
+        bp1: bar.close,
+        ap1: bar.close,
+        ..Default::default()
+    }
+}
+
+impl WarmupSource for BarTicks {
+    fn ticks(&self, symbol: &SymbolType, before: i64, count: usize) -> io::Result<Vec<TickData>> {
+        let bars = match self.store.load(symbol.as_str(), self.period) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            bars => bars?,
+        };
+        Ok(latest(bars.iter().map(|bar| bar_tick(*symbol, bar)).collect(), before, count))
+    }
+}
+
+/// `GET <base_url>?symbol=<symbol>&before=<stamp>&count=<count>`, answered with a JSON array
+/// of ticks in `TickData`'s field names.
+#[cfg(feature = "download")]
+pub struct RestTicks {
+    base_url: String,
+}
+
+#[cfg(feature = "download")]
+impl RestTicks {
+    pub fn new(base_url: &str) -> Self {
+        Self { base_url: base_url.into() }
+    }
+}
+
+#[cfg(feature = "download")]
+impl WarmupSource for RestTicks {
+    fn ticks(&self, symbol: &SymbolType, before: i64, count: usize) -> io::Result<Vec<TickData>> {
+        let url = format!("{}?symbol={}&before={}&count={}", self.base_url, symbol.as_str(), before, count);
+        let body = ureq::get(&url)
+            .timeout(std::time::Duration::from_secs(30))
+            .call()
+            .map_err(io::Error::other)?
+            .into_string()?;
+        let ticks: Vec<TickData> = serde_json::from_str(&body).map_err(io::Error::other)?;
+        Ok(latest(ticks, before, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_the_latest_recorded_ticks_across_days() {
+        let root = std::env::temp_dir().join(format!("fustg_warmup_{}", std::process::id()));
+        let symbol = SymbolType::from("rb2505");
+        let at = |date: u32, i: i64| TickData {
+            symbol,
+            stamp: timeutil::local_stamp(date, 10 * 3600) + i * 500,
+            last: 3500.0 + i as f64,
+            ..Default::default()
+        };
+        {
+            let mut recorder = TickRecorder::new(&root);
+            for date in [20250312, 20250313, 20250314] {
+                for i in 0..4 {
+                    recorder.record(&at(date, i)).unwrap();
+                }
+            }
+        }
+
+        let source = RecordedTicks::new(&root);
+        // up to the second tick of the 14th: two of that day, the rest from the 13th
+        let ticks = source.ticks(&symbol, at(20250314, 2).stamp, 6).unwrap();
+        let stamps: Vec<i64> = ticks.iter().map(|t| t.stamp).collect();
+        let expected: Vec<i64> = (0..4)
+            .map(|i| at(20250313, i).stamp)
+            .chain((0..2).map(|i| at(20250314, i).stamp))
+            .collect();
+        assert_eq!(stamps, expected);
+        assert!(source.ticks(&SymbolType::from("MA505"), at(20250315, 0).stamp, 10).unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
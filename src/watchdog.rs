@@ -0,0 +1,191 @@
+//! Market data watchdog: notices subscribed symbols that go silent during trading hours and
+//! ticks that arrive too late to trade on.
+use crate::timeutil::{self, STAMP_PER_SEC};
+use crate::types::{SymbolType, TickData};
+use std::collections::{HashMap, HashSet};
+
+/// Day and night sessions of the domestic futures exchanges, as CST `[start, end)` seconds of day.
+/// Night sessions past midnight are split at 24:00.
+pub const CHINA_FUTURES_SESSIONS: &[(u32, u32)] = &[
+    (9 * 3600, 10 * 3600 + 15 * 60),
+    (10 * 3600 + 30 * 60, 11 * 3600 + 30 * 60),
+    (13 * 3600 + 30 * 60, 15 * 3600),
+    (21 * 3600, 24 * 3600),
+    (0, 2 * 3600 + 30 * 60),
+];
+
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// alert when a symbol has had no tick for this long inside a session
+    pub silence_secs: i64,
+    /// a tick whose stamp lags the wall clock by more than this is stale
+    pub stale_secs: i64,
+    /// drop stale ticks instead of dispatching them
+    pub drop_stale: bool,
+    /// CST seconds-of-day ranges in which silence is alerted on
+    pub sessions: Vec<(u32, u32)>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            silence_secs: 30,
+            stale_secs: 5,
+            drop_stale: false,
+            sessions: CHINA_FUTURES_SESSIONS.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataAlert {
+    /// no tick for `silent_ms` while in session
+    Silent { symbol: SymbolType, silent_ms: i64 },
+    /// ticks are flowing again after a `Silent` alert
+    Resumed { symbol: SymbolType, silent_ms: i64 },
+    /// the tick's stamp was `lag_ms` behind the wall clock on arrival
+    Stale { symbol: SymbolType, lag_ms: i64 },
+    /// the symbol's smoothed receive-time minus stamp skew passed `SkewConfig::max_skew_ms`
+    ClockSkew { symbol: SymbolType, skew_ms: i64 },
+    /// the skew is back within half of the threshold
+    SkewRecovered { symbol: SymbolType, skew_ms: i64 },
+    /// the feed handler's sequence skipped `missed` ticks of the symbol
+    SequenceGap { symbol: SymbolType, missed: u64 },
+}
+
+/// Receives every alert, on the engine's receive thread.
+pub type DataAlertCallback = Box<dyn FnMut(&DataAlert) + Send>;
+
+pub struct Watchdog {
+    cfg: WatchdogConfig,
+    /// wall-clock stamp of the last tick (or of the subscription) per watched symbol
+    last_seen: HashMap<SymbolType, i64>,
+    silent: HashSet<SymbolType>,
+}
+
+impl Watchdog {
+    pub fn new(cfg: WatchdogConfig) -> Self {
+        Self {
+            cfg,
+            last_seen: HashMap::new(),
+            silent: HashSet::new(),
+        }
+    }
+
+    pub fn config(&self) -> &WatchdogConfig {
+        &self.cfg
+    }
+
+    /// Start watching `symbol`; its silence is measured from `now`.
+    pub fn watch(&mut self, symbol: SymbolType, now: i64) {
+        self.last_seen.entry(symbol).or_insert(now);
+    }
+
+    pub fn unwatch(&mut self, symbol: &SymbolType) {
+        self.last_seen.remove(symbol);
+        self.silent.remove(symbol);
+    }
+
+    pub fn silent_count(&self) -> usize {
+        self.silent.len()
+    }
+
+    pub fn in_session(&self, now: i64) -> bool {
+        let secs = timeutil::local_secs_of_day(now);
+        self.cfg.sessions.iter().any(|&(start, end)| (start..end).contains(&secs))
+    }
+
+    /// Record a tick received at wall-clock `now`. Reports a `Resumed` symbol and a `Stale` tick.
+    pub fn on_tick(&mut self, tick: &TickData, now: i64) -> (Option<DataAlert>, Option<DataAlert>) {
+        let resumed = self.last_seen.insert(tick.symbol, now).and_then(|prev| {
+            self.silent.remove(&tick.symbol).then_some(DataAlert::Resumed {
+                symbol: tick.symbol,
+                silent_ms: now - prev,
+            })
+        });
+        let lag_ms = now - tick.stamp;
+        let stale = (lag_ms > self.cfg.stale_secs * STAMP_PER_SEC).then_some(DataAlert::Stale { symbol: tick.symbol, lag_ms });
+        (resumed, stale)
+    }
+
+    /// Symbols that just went silent; each is reported once until it resumes.
+    pub fn check(&mut self, now: i64) -> Vec<DataAlert> {
+        if !self.in_session(now) {
+            // silence between sessions is expected; count from the next session's open
+            self.last_seen.values_mut().for_each(|seen| *seen = now);
+            return Vec::new();
+        }
+        let threshold = self.cfg.silence_secs * STAMP_PER_SEC;
+        let mut alerts = Vec::new();
+        for (&symbol, &seen) in &self.last_seen {
+            let silent_ms = now - seen;
+            if silent_ms > threshold && self.silent.insert(symbol) {
+                alerts.push(DataAlert::Silent { symbol, silent_ms });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEN_AM_CST: i64 = 2 * 3600 * STAMP_PER_SEC;
+
+    fn tick(stamp: i64) -> TickData {
+        TickData {
+            symbol: SymbolType::from("rb2505"),
+            stamp,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn alerts_on_silence_once_and_on_resumption() {
+        let rb = SymbolType::from("rb2505");
+        let mut watchdog = Watchdog::new(WatchdogConfig::default());
+        watchdog.watch(rb, TEN_AM_CST);
+        assert_eq!(watchdog.on_tick(&tick(TEN_AM_CST), TEN_AM_CST), (None, None));
+        assert!(watchdog.check(TEN_AM_CST + 30_000).is_empty(), "silent for exactly the threshold");
+        let later = TEN_AM_CST + 45_000;
+        assert_eq!(
+            watchdog.check(later),
+            [DataAlert::Silent {
+                symbol: rb,
+                silent_ms: 45_000
+            }]
+        );
+        assert!(watchdog.check(later + 1000).is_empty(), "reported once");
+        assert_eq!(watchdog.silent_count(), 1);
+
+        let (resumed, stale) = watchdog.on_tick(&tick(later + 2000), later + 2000);
+        assert_eq!(
+            resumed,
+            Some(DataAlert::Resumed {
+                symbol: rb,
+                silent_ms: 47_000
+            })
+        );
+        assert_eq!((stale, watchdog.silent_count()), (None, 0));
+
+        // lunch break: silence is not alerted on, and counted afresh from the next check
+        let lunch = TEN_AM_CST + 2 * 3600 * STAMP_PER_SEC;
+        assert!(watchdog.check(lunch).is_empty());
+    }
+
+    #[test]
+    fn flags_ticks_lagging_the_wall_clock() {
+        let mut watchdog = Watchdog::new(WatchdogConfig::default());
+        let (_, stale) = watchdog.on_tick(&tick(TEN_AM_CST), TEN_AM_CST + 5000);
+        assert_eq!(stale, None);
+        let (_, stale) = watchdog.on_tick(&tick(TEN_AM_CST), TEN_AM_CST + 6000);
+        assert_eq!(
+            stale,
+            Some(DataAlert::Stale {
+                symbol: SymbolType::from("rb2505"),
+                lag_ms: 6000
+            })
+        );
+    }
+}